@@ -23,7 +23,7 @@ fn main() -> Result<(), std::io::Error> {
     );
     tmpdir_path.push("LineBreak.txt");
     Command::new("curl")
-        .args(&["-o", tmpdir_path.to_str().unwrap(), LINE_BREAK_TABLE_URL])
+        .args(["-o", tmpdir_path.to_str().unwrap(), LINE_BREAK_TABLE_URL])
         .output()?;
 
     let file = File::open(&tmpdir_path)?;
@@ -0,0 +1,1114 @@
+/*
+ * bb
+ *
+ * Copyright 2019 Manos Pitsidianakis
+ *
+ * This file is part of bb.
+ *
+ * bb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * bb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with bb. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+/*! Command line arguments. */
+
+use crate::ui::components::{
+    valid_env_key, Alignments, BarFill, BarGlyphs, ContainerMode, CwdCommand, HookCommand,
+    MemFormat, Notation, PercentFormat, Pid, PidFilter, PidNsMode, Pin, SecondarySort, Separators,
+    SortOrder, SortScroll, TreeGlyphs, ViewPreset, BAR_GLYPH_SETS, CONTAINER_MODES, MEM_FORMATS,
+    NOTATIONS, PID_NS_MODES, SEPARATOR_MODES, TREE_GLYPH_SETS,
+};
+use crate::ui::theme::{Theme, Thresholds, PRESETS};
+use crate::ui::{AmbiguousWidth, NonPrintable, Truncation};
+
+pub const USAGE: &str = "usage: bb [--smooth-bars] [--bar-glyphs SET] [--vertical-bars] [--compact]
+          [--meter-height ROWS] [--meter-epsilon FRACTION] [--theme NAME|FILE]
+          [--bidi] [--fade-new SAMPLES] [--pid PID]... [--no-children]
+          [--pin PID|NAME]...
+          [--proc-root PATH] [--hover] [--nfc] [--case-sensitive] [--smooth ALPHA]
+          [--highlight-mem FRACTION] [--highlight-d-state] [--max-cmd-len COLUMNS]
+          [--truncate MODE] [--control-chars MODE] [--ambiguous-width WIDTH]
+          [--sort-scroll MODE] [--fixed-tick] [--max-fps N] [--cgroup] [--elapsed]
+          [--threads] [--cpu-time] [--caps] [--io] [--summary] [--sparkline]
+          [--sparkline-baseline] [--zebra] [--color-categories] [--titles]
+          [--scrollbar] [--no-header] [--confirm-quit] [--confirm-kill] [--fuzzy]
+          [--hook COMMAND] [--open-cwd COMMAND] [--no-altscreen] [--min-cpu PERCENT]
+          [--tombstones SAMPLES] [--select-tombstones] [--precise-below PERCENT]
+          [--precise-decimals N] [--align COLUMN=SIDE,...] [--stream-to PATH]
+          [--highlight-mem-growth MIB] [--heatmap] [--then-sort KEY[:asc|:desc]]
+          [--mem-format FORMAT] [--view NAME,SETTING=VALUE,...]...
+          [--idle-unfocused TICKS] [--print-caps] [--env VAR] [--auto-expand]
+          [--sample-time FORMAT] [--two-line] [--sort KEY[:asc|:desc]] [--swap-meter]
+          [--scroll-freeze MS] [--sockets] [--highlight-sockets] [--newest-left]
+          [--load-warning MULTIPLE] [--affinity] [--read-only]
+          [--cpu-thresholds LOW,HIGH] [--mem-thresholds LOW,HIGH]
+          [--load-thresholds LOW,HIGH] [--strip WIDTH] [--container MODE]
+          [--separators MODE] [--print-theme] [--gpu] [--self-usage]
+          [--wrap-selection] [--notation NAME] [--follow-new] [--tree-glyphs SET]
+          [--pid-warning PERCENT] [--pid-ns MODE] [--key-hints] [--dim-below PERCENT]
+          [--batch [-n ITERATIONS] [--color=WHEN]]
+
+    --smooth-bars   draw the last cell of cpu bars with partial block glyphs
+    --bar-glyphs SET
+                    characters to draw bars with: ascii, block, or two characters for filled
+                    and empty cells, eg. =-
+    --vertical-bars draw cpu bars as columns that fill upwards
+    --heatmap       draw a cell per cpu, from green to red with its usage, instead of bars
+    --compact       start without cpu bar labels and with narrower padding, toggle with Alt-c
+    --meter-height ROWS
+                    make the cpu and RAM meters ROWS rows tall, filling upwards, default 1
+    --swap-meter    draw the swap in use as a meter of its own below the RAM one, left out
+                    without swap
+    --load-warning MULTIPLE
+                    warn across the top while the 1 minute load average is above MULTIPLE
+                    times the number of cpus, eg. 2, until it drops below 90% of that
+    --pid-warning PERCENT
+                    warn across the top while at least PERCENT of the PIDs pid_max allows
+                    are in use by processes and threads, eg. 90
+    --container MODE
+                    auto (default) notes in the header when bb runs in a container, off
+                    doesn't look, limits also scopes the RAM meter and --load-warning to
+                    the memory and cpu limits of bb's cgroup
+    --self-usage    show bb's own cpu usage and memory in the header, left of the clock
+    --meter-epsilon FRACTION
+                    only redraw the meters once one moved by more than FRACTION, in
+                    [0, 1), eg. 0.01 for a percent
+    --theme NAME|FILE
+                    color theme, one of: default, auto, light, deuteranopia, protanopia.
+                    auto is light if COLORFGBG says the background is light. Or a theme
+                    file, as --print-theme writes them
+    --cpu-thresholds LOW,HIGH
+                    color cpu percentages green below LOW, yellow below HIGH and red from
+                    there up, eg. 50,80
+    --mem-thresholds LOW,HIGH
+                    color memory by percentage of the total memory the same way
+    --load-thresholds LOW,HIGH
+                    color the load averages the same way, eg. 2,4
+    --pid PID       only show PID and its children, can be given multiple times
+    --no-children   don't show the children of the PIDs given with --pid
+    --pin PID|NAME  list a process, or the processes with a name, above the others whatever
+                    the sort order. Can be given multiple times, P pins more by PID
+    --proc-root PATH
+                    read procfs from PATH instead of /proc, eg. another namespace's
+    --hover         highlight the process under the mouse pointer, click to select it
+    --nfc           normalize command lines to Unicode NFC before display and filtering
+    --case-sensitive
+                    match filter and search terms exactly instead of ignoring case
+    --fuzzy         start with fuzzy matching of filter and search terms, z toggles it
+    --smooth ALPHA  smooth cpu usage with a moving average, ALPHA in (0, 1] is the weight
+                    of each new sample
+    --highlight-mem FRACTION
+                    highlight processes using more than FRACTION, in (0, 1], of the total
+                    memory, eg. 0.1 for 10%
+    --highlight-mem-growth MIB
+                    highlight processes whose resident memory grew by more than MIB MiB
+                    per second over the last few samples, eg. 0.5
+    --mem-format FORMAT
+                    how the memory column shows resident memory: bytes, percent of the
+                    total, combined, eg. 4.2G/15.6G (27%), or compact, eg. 4.2G 27%
+    --notation NAME how the I/O columns write byte counts: binary (default), eg. 1.15 GiB,
+                    engineering, eg. 1.23 GB up to EB, or scientific, eg. 1.23e9 bytes
+    --min-cpu PERCENT
+                    hide processes using less than PERCENT cpu, eg. 0.5, except the one
+                    under the cursor, c changes it
+    --dim-below PERCENT
+                    dim processes using less than PERCENT cpu, eg. 2, from the start instead
+                    of below 0.5% once i is pressed, which still toggles it
+    --highlight-d-state
+                    highlight processes in uninterruptible sleep and count them on the
+                    status line
+    --fade-new SAMPLES
+                    highlight new processes in green, fading to the normal color over
+                    SAMPLES samples
+    --precise-below PERCENT
+                    show cpu percentages below PERCENT with decimals and the others
+                    rounded to whole numbers, eg. 1 for 0.3% and 12%
+    --precise-decimals N
+                    decimal places of the percentages below --precise-below, 1 or 2,
+                    default 1
+    --sort KEY[:asc|:desc]
+                    start sorted by KEY, one of the keys of --then-sort or mem, eg. mem or
+                    cpu:asc. cpu, memory, time and threads are descending by default
+    --then-sort KEY[:asc|:desc]
+                    order processes that tie on the sort column by KEY, ascending unless
+                    :desc is given, eg. pid or memory:desc. Keys: pid, cpu, memory,
+                    user, command, time, threads, start (with --elapsed)
+    --view NAME,SETTING=VALUE,...
+                    a view that Alt-v cycles to, with sort=KEY[:asc|:desc] as in --then-sort,
+                    columns=COLUMN+... of threads, time, elapsed and caps, and filter=TERM,
+                    eg. mem,sort=memory:desc,columns=threads. Can be given more than once
+    --align COLUMN=SIDE,...
+                    align COLUMN to the left or right SIDE of its width, eg. --align
+                    user=right. Numbers are on the right and text on the left by default.
+                    Columns: pid, ppid, user, rss, cpu, state, threads, time, elapsed,
+                    io, delta, cgroup, affinity, caps, env, net, gpu
+    --tombstones SAMPLES
+                    keep processes that exited in the list for SAMPLES samples, greyed out
+    --select-tombstones
+                    let the cursor onto the rows of exited processes instead of skipping them
+    --wrap-selection
+                    move the cursor from the last row to the first with Down and back with
+                    Up, instead of stopping at them
+    --auto-expand   in the tree view, expand collapsed processes when the cursor moves onto
+                    them
+    --tree-glyphs SET
+                    characters to draw the guides of the tree view with: unicode, ascii, or
+                    four characters for the vertical line, branch, last branch and
+                    horizontal line, eg. |++-
+    --two-line      draw each process on two lines, with its whole command line on the second
+    --separators MODE
+                    draw a dim line on a row of its own between process rows: rows between
+                    all of them, tree only above top-level processes in the tree view
+    --scroll-freeze MS
+                    keep the rows in place while scrolling, sorting them again once the list
+                    wasn't scrolled for MS milliseconds. Their values still update
+    --follow-new    while the list is sorted by start, move the cursor onto processes as they
+                    start, unless it was moved in the last few seconds
+    --pid-ns MODE   read the PID namespace of each process and only link processes to parents
+                    in the same one in the tree view. color colors the PIDs of other
+                    namespaces, group lists each namespace's processes together
+    --key-hints     show the keys of the most common actions on the last row of the list,
+                    which ? shows or hides
+    --max-cmd-len COLUMNS
+                    shorten command lines to at most COLUMNS columns
+    --truncate MODE what to leave out of command lines that don't fit, marked with …:
+                    end (default) or middle, which keeps the executable path's start
+                    and the last arguments
+    --control-chars MODE
+                    how to draw control characters and others without a width: replace
+                    (default) with ·, escape, eg. as \\u{1b}, or skip. Also applies to
+                    tabs and newlines in command lines printed by --batch
+    --ambiguous-width WIDTH
+                    columns taken by characters like α and …: narrow or wide. By default
+                    wide in Chinese, Japanese and Korean locales, narrow otherwise
+    --bidi          draw Arabic and Hebrew text right to left
+    --sort-scroll MODE
+                    what a sort change does to the selection: top jumps to the first row,
+                    keep stays on the selected process, auto (default) picks top for cpu
+                    and memory sorts and keep for the others
+    --fixed-tick    only redraw when sampling, instead of right after every key press
+    --max-fps N     redraw at most N times per second on input, default 30, 0 for no limit
+    --cgroup        show a column with the cgroup of each process, or its container id
+    --affinity      show a column with the cpus each process may run on, eg. 0-3,8
+    --elapsed       show a column with how long each process has been running
+    --threads       show a column with the number of threads of each process
+    --cpu-time      show a column with the cpu time each process has used in total, like
+                    top's TIME+
+    --caps          show a column with the effective capabilities of each process, naming
+                    notable ones such as SYS_ADMIN and NET_RAW
+    --env VAR       show a column with the value of the environment variable VAR in each
+                    process, - where the environment can't be read
+    --sockets       show a column with whether each process holds a listening or established
+                    TCP socket, - where its descriptors can't be read
+    --highlight-sockets
+                    highlight processes holding a listening or established TCP socket
+    --gpu           show columns with the memory each process has on NVIDIA GPUs and how
+                    busy it keeps them. Needs a build with the nvml feature and the driver
+    --io            show columns with the bytes per second each process reads from and
+                    writes to storage, - where that can't be read
+    --sparkline     show a column with the cpu usage of each process over the last few
+                    seconds
+    --sparkline-baseline
+                    with --sparkline, draw idle samples as a dim baseline instead of blanks
+    --newest-left   with --sparkline, draw the newest sample on the left, like a seismograph
+    --zebra         give every other process row a faint background
+    --color-categories
+                    color command lines by what started them: kernel threads, daemons, or
+                    processes with a terminal
+    --titles        draw a title bar above the meters and the process list, with the number
+                    of processes listed
+    --summary       count the processes in each state above the process list, like the
+                    Tasks line of top
+    --scrollbar     draw a scrollbar on the right edge of process lists that don't fit
+    --no-header     start without the column headers of the process list, H shows them
+    --sample-time FORMAT
+                    show when the process list was sampled at the end of its column headers,
+                    formatted like strftime(3), eg. %H:%M:%S. It stops while frozen
+    --confirm-quit  only quit when q is pressed twice in a row, within two seconds
+    --confirm-kill  ask again before the kill menu sends a signal, except for ones that only
+                    pause or resume a process, like STOP and CONT
+    --read-only     disable the shortcuts that signal processes and the hook command, for
+                    shared or demo machines
+    --hook COMMAND  what x runs on the process under the cursor, with {pid} in its arguments
+                    replaced by the PID, eg. --hook 'strace -p {pid}'
+    --open-cwd COMMAND
+                    what w opens the working directory of the process under the cursor with,
+                    with {cwd} in its arguments replaced by it, eg. --open-cwd 'ranger {cwd}'.
+                    Without it, w shows the directory on the status line
+    --no-altscreen  draw in the terminal's main screen instead of the alternate one, and clear
+                    it on exit. The default for terminals without one, eg. TERM=vt100
+    --stream-to PATH
+                    also write each frame to the named pipe PATH, dropping frames while its
+                    reader is behind, eg. mkfifo /tmp/bb; cat /tmp/bb in another terminal
+    --idle-unfocused TICKS
+                    while the terminal doesn't have focus, don't draw and only sample on one
+                    in TICKS ticks. Needs a terminal that reports focus changes
+    --print-caps    print what bb detected about the terminal: TERM, COLORTERM, size, colors,
+                    alternate screen and mouse support, and exit
+    --print-theme   print the theme, with the thresholds of the options, as a theme file
+                    for --theme and exit
+    --batch         print the process list as plain text and exit. The default when stdout
+                    isn't a terminal
+    -n ITERATIONS   number of snapshots to print in batch mode, default 1
+    --strip WIDTH   print the cpu usage, memory and load averages on one line of at most
+                    WIDTH columns and exit, for status bars, eg. tmux's
+    --color=WHEN    color batch output: auto, always or never, default auto
+    -h, --help      print this message and exit";
+
+/* Fast enough for key repeats to look smooth, without redrawing on every one of them */
+const DEFAULT_MAX_FPS: u32 = 30;
+
+/* Larger meters wouldn't leave room for the process list on most terminals */
+const MAX_METER_HEIGHT: usize = 8;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ColorChoice {
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    /// Whether to color output written to stdout.
+    pub fn enabled(self) -> bool {
+        match self {
+            ColorChoice::Auto => termion::is_tty(&std::io::stdout()),
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Args {
+    pub bar_fill: BarFill,
+    pub bar_glyphs: Option<BarGlyphs>,
+    pub vertical_bars: bool,
+    pub heatmap: bool,
+    pub swap_meter: bool,
+    pub load_warning: Option<f64>,
+    pub pid_warning: Option<f64>,
+    pub container: ContainerMode,
+    pub self_usage: bool,
+    pub compact: bool,
+    pub meter_height: usize,
+    pub meter_epsilon: Option<f64>,
+    pub theme: Theme,
+    pub pid_filter: PidFilter,
+    pub pins: Vec<Pin>,
+    /* where procfs is mounted, checked when bb starts */
+    pub proc_root: std::path::PathBuf,
+    pub hover: bool,
+    pub nfc: bool,
+    pub bidi: bool,
+    pub case_sensitive: bool,
+    pub fuzzy: bool,
+    pub smoothing: Option<f64>,
+    pub mem_threshold: Option<f64>,
+    /* in bytes per second */
+    pub mem_growth: Option<f64>,
+    pub mem_format: MemFormat,
+    pub notation: Notation,
+    /* in hundredths of a percent */
+    pub min_cpu: Option<usize>,
+    pub dim_below: Option<usize>,
+    /* in hundredths of a percent */
+    pub precise_below: Option<usize>,
+    pub precise_decimals: usize,
+    pub alignments: Alignments,
+    pub sort: Option<SortOrder>,
+    pub secondary_sort: Option<SecondarySort>,
+    pub views: Vec<ViewPreset>,
+    pub highlight_d_state: bool,
+    pub fade_new: Option<usize>,
+    pub tombstones: Option<usize>,
+    pub select_tombstones: bool,
+    pub wrap_selection: bool,
+    pub auto_expand: bool,
+    pub tree_glyphs: TreeGlyphs,
+    pub two_line: bool,
+    pub separators: Option<Separators>,
+    pub scroll_freeze: Option<std::time::Duration>,
+    pub follow_new: bool,
+    pub pid_ns: Option<PidNsMode>,
+    pub key_hints: bool,
+    pub max_cmd_len: Option<usize>,
+    pub truncation: Truncation,
+    pub non_printable: NonPrintable,
+    /* `None` follows the locale */
+    pub ambiguous_width: Option<AmbiguousWidth>,
+    pub sort_scroll: SortScroll,
+    pub fixed_tick: bool,
+    /* `None` doesn't limit the redraws */
+    pub max_fps: Option<u32>,
+    pub cgroup: bool,
+    pub affinity: bool,
+    pub elapsed: bool,
+    pub threads: bool,
+    pub cpu_time: bool,
+    pub caps: bool,
+    pub env_var: Option<String>,
+    pub sockets: bool,
+    pub highlight_sockets: bool,
+    pub gpu: bool,
+    pub io: bool,
+    pub sparkline: bool,
+    pub sparkline_baseline: bool,
+    pub newest_left: bool,
+    pub zebra: bool,
+    pub color_categories: bool,
+    pub titles: bool,
+    pub summary: bool,
+    pub scrollbar: bool,
+    pub no_header: bool,
+    pub sample_time: Option<String>,
+    pub confirm_quit: bool,
+    pub confirm_kill: bool,
+    pub read_only: bool,
+    pub hook: Option<HookCommand>,
+    pub open_cwd: Option<CwdCommand>,
+    pub no_altscreen: bool,
+    pub stream_to: Option<std::path::PathBuf>,
+    pub idle_unfocused: Option<usize>,
+    pub print_caps: bool,
+    /// Print the theme as a theme file and exit.
+    pub print_theme: bool,
+    /// Width of the line `--strip` prints.
+    pub strip: Option<usize>,
+    pub batch: bool,
+    pub iterations: usize,
+    pub color: ColorChoice,
+    pub help: bool,
+}
+
+impl Default for Args {
+    fn default() -> Self {
+        Args {
+            bar_fill: BarFill::default(),
+            bar_glyphs: None,
+            vertical_bars: false,
+            heatmap: false,
+            swap_meter: false,
+            load_warning: None,
+            pid_warning: None,
+            container: ContainerMode::default(),
+            self_usage: false,
+            compact: false,
+            meter_height: 1,
+            meter_epsilon: None,
+            theme: Theme::default(),
+            pid_filter: PidFilter {
+                pids: vec![],
+                descendants: true,
+            },
+            pins: vec![],
+            proc_root: std::path::PathBuf::from("/proc"),
+            hover: false,
+            nfc: false,
+            bidi: false,
+            case_sensitive: false,
+            fuzzy: false,
+            smoothing: None,
+            mem_threshold: None,
+            mem_growth: None,
+            mem_format: MemFormat::default(),
+            notation: Notation::default(),
+            min_cpu: None,
+            dim_below: None,
+            precise_below: None,
+            precise_decimals: 1,
+            alignments: Alignments::default(),
+            sort: None,
+            secondary_sort: None,
+            views: vec![],
+            highlight_d_state: false,
+            fade_new: None,
+            tombstones: None,
+            select_tombstones: false,
+            wrap_selection: false,
+            auto_expand: false,
+            tree_glyphs: TreeGlyphs::default(),
+            two_line: false,
+            separators: None,
+            scroll_freeze: None,
+            follow_new: false,
+            pid_ns: None,
+            key_hints: false,
+            max_cmd_len: None,
+            truncation: Truncation::default(),
+            non_printable: NonPrintable::default(),
+            ambiguous_width: None,
+            sort_scroll: SortScroll::default(),
+            fixed_tick: false,
+            max_fps: Some(DEFAULT_MAX_FPS),
+            cgroup: false,
+            affinity: false,
+            elapsed: false,
+            threads: false,
+            cpu_time: false,
+            caps: false,
+            env_var: None,
+            sockets: false,
+            highlight_sockets: false,
+            gpu: false,
+            io: false,
+            sparkline: false,
+            sparkline_baseline: false,
+            newest_left: false,
+            zebra: false,
+            color_categories: false,
+            titles: false,
+            summary: false,
+            scrollbar: false,
+            no_header: false,
+            sample_time: None,
+            confirm_quit: false,
+            confirm_kill: false,
+            read_only: false,
+            hook: None,
+            open_cwd: None,
+            no_altscreen: false,
+            stream_to: None,
+            idle_unfocused: None,
+            print_caps: false,
+            print_theme: false,
+            strip: None,
+            batch: false,
+            iterations: 1,
+            color: ColorChoice::Auto,
+            help: false,
+        }
+    }
+}
+
+impl Args {
+    /// Parse the arguments following the program name.
+    pub fn parse<I: Iterator<Item = String>>(mut args: I) -> Result<Args, String> {
+        let mut ret = Args::default();
+        /* applied once all arguments are read, with the colors of whichever --theme is given */
+        let mut thresholds: [Option<[f64; 2]>; 3] = [None; 3];
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--smooth-bars" => ret.bar_fill = BarFill::Eighths,
+                "--bar-glyphs" => {
+                    let value = args
+                        .next()
+                        .ok_or_else(|| "`--bar-glyphs` requires a value".to_string())?;
+                    ret.bar_glyphs = Some(BarGlyphs::parse(&value).ok_or_else(|| {
+                        format!(
+                            "invalid bar glyphs `{}`, expected one of: {}, or two characters",
+                            value,
+                            BAR_GLYPH_SETS.join(", ")
+                        )
+                    })?);
+                }
+                "--meter-epsilon" => {
+                    let value = args
+                        .next()
+                        .ok_or_else(|| "`--meter-epsilon` requires a value".to_string())?;
+                    ret.meter_epsilon = Some(
+                        value
+                            .parse::<f64>()
+                            .ok()
+                            .filter(|fraction| *fraction >= 0.0 && *fraction < 1.0)
+                            .ok_or_else(|| {
+                                format!(
+                                    "invalid meter epsilon `{}`, expected a number in [0, 1)",
+                                    value
+                                )
+                            })?,
+                    );
+                }
+                "--vertical-bars" => ret.vertical_bars = true,
+                "--heatmap" => ret.heatmap = true,
+                "--swap-meter" => ret.swap_meter = true,
+                "--load-warning" => {
+                    let value = args
+                        .next()
+                        .ok_or_else(|| "`--load-warning` requires a value".to_string())?;
+                    ret.load_warning = Some(
+                        value
+                            .parse::<f64>()
+                            .ok()
+                            .filter(|multiple| *multiple > 0.0 && multiple.is_finite())
+                            .ok_or_else(|| {
+                                format!(
+                                    "invalid load warning `{}`, expected a positive number",
+                                    value
+                                )
+                            })?,
+                    );
+                }
+                "--pid-warning" => {
+                    let value = args
+                        .next()
+                        .ok_or_else(|| "`--pid-warning` requires a value".to_string())?;
+                    ret.pid_warning = Some(
+                        value
+                            .parse::<f64>()
+                            .ok()
+                            .filter(|percent| *percent > 0.0 && *percent <= 100.0)
+                            .map(|percent| percent / 100.0)
+                            .ok_or_else(|| {
+                                format!(
+                                    "invalid pid warning `{}`, expected a percentage in (0, 100]",
+                                    value
+                                )
+                            })?,
+                    );
+                }
+                "--self-usage" => ret.self_usage = true,
+                "--container" => {
+                    let name = args
+                        .next()
+                        .ok_or_else(|| "`--container` requires a value".to_string())?;
+                    ret.container = ContainerMode::parse(&name).ok_or_else(|| {
+                        format!(
+                            "unknown container mode `{}`, expected one of: {}",
+                            name,
+                            CONTAINER_MODES.join(", ")
+                        )
+                    })?;
+                }
+                "--compact" => ret.compact = true,
+                "--meter-height" => {
+                    let value = args
+                        .next()
+                        .ok_or_else(|| "`--meter-height` requires a value".to_string())?;
+                    ret.meter_height = value
+                        .parse::<usize>()
+                        .ok()
+                        .filter(|n| (1..=MAX_METER_HEIGHT).contains(n))
+                        .ok_or_else(|| {
+                            format!(
+                                "invalid meter height `{}`, expected 1 to {} rows",
+                                value, MAX_METER_HEIGHT
+                            )
+                        })?;
+                }
+                "--theme" => {
+                    let name = args
+                        .next()
+                        .ok_or_else(|| "`--theme` requires a value".to_string())?;
+                    ret.theme = match Theme::preset(&name) {
+                        Some(theme) => theme,
+                        None if std::path::Path::new(&name).is_file() => {
+                            std::fs::read_to_string(&name)
+                                .map_err(|err| err.to_string())
+                                .and_then(|toml| Theme::from_toml(&toml))
+                                .map_err(|err| format!("theme file `{}`: {}", name, err))?
+                        }
+                        None => {
+                            return Err(format!(
+                                "unknown theme `{}`, expected one of: {}, or a theme file",
+                                name,
+                                PRESETS.join(", ")
+                            ))
+                        }
+                    };
+                }
+                "--cpu-thresholds" | "--mem-thresholds" | "--load-thresholds" => {
+                    let value = args
+                        .next()
+                        .ok_or_else(|| format!("`{}` requires a value", arg))?;
+                    let i = ["--cpu-thresholds", "--mem-thresholds", "--load-thresholds"]
+                        .iter()
+                        .position(|name| *name == arg)
+                        .unwrap();
+                    thresholds[i] = Some(Thresholds::parse_limits(&value)?);
+                }
+                "--pid" => {
+                    let value = args
+                        .next()
+                        .ok_or_else(|| "`--pid` requires a value".to_string())?;
+                    let pid = value
+                        .parse::<Pid>()
+                        .map_err(|_| format!("invalid PID `{}`", value))?;
+                    ret.pid_filter.pids.push(pid);
+                }
+                "--no-children" => ret.pid_filter.descendants = false,
+                "--pin" => {
+                    let spec = args
+                        .next()
+                        .ok_or_else(|| "`--pin` requires a value".to_string())?;
+                    ret.pins.push(Pin::parse(&spec)?);
+                }
+                "--proc-root" => {
+                    let path = args
+                        .next()
+                        .ok_or_else(|| "`--proc-root` requires a value".to_string())?;
+                    ret.proc_root = path.into();
+                }
+                "--hover" => ret.hover = true,
+                "--nfc" => ret.nfc = true,
+                "--bidi" => ret.bidi = true,
+                "--case-sensitive" => ret.case_sensitive = true,
+                "--fuzzy" => ret.fuzzy = true,
+                "--smooth" => {
+                    let value = args
+                        .next()
+                        .ok_or_else(|| "`--smooth` requires a value".to_string())?;
+                    ret.smoothing = Some(
+                        value
+                            .parse::<f64>()
+                            .ok()
+                            .filter(|alpha| *alpha > 0.0 && *alpha <= 1.0)
+                            .ok_or_else(|| {
+                                format!(
+                                    "invalid smoothing `{}`, expected a number in (0, 1]",
+                                    value
+                                )
+                            })?,
+                    );
+                }
+                "--highlight-mem" => {
+                    let value = args
+                        .next()
+                        .ok_or_else(|| "`--highlight-mem` requires a value".to_string())?;
+                    ret.mem_threshold = Some(
+                        value
+                            .parse::<f64>()
+                            .ok()
+                            .filter(|fraction| *fraction > 0.0 && *fraction <= 1.0)
+                            .ok_or_else(|| {
+                                format!(
+                                    "invalid memory fraction `{}`, expected a number in (0, 1]",
+                                    value
+                                )
+                            })?,
+                    );
+                }
+                "--highlight-mem-growth" => {
+                    let value = args
+                        .next()
+                        .ok_or_else(|| "`--highlight-mem-growth` requires a value".to_string())?;
+                    let mib = value
+                        .parse::<f64>()
+                        .ok()
+                        .filter(|mib| *mib > 0.0 && mib.is_finite())
+                        .ok_or_else(|| {
+                            format!(
+                                "invalid growth rate `{}`, expected a positive number of MiB",
+                                value
+                            )
+                        })?;
+                    ret.mem_growth = Some(mib * 1024.0 * 1024.0);
+                }
+                "--mem-format" => {
+                    let name = args
+                        .next()
+                        .ok_or_else(|| "`--mem-format` requires a value".to_string())?;
+                    ret.mem_format = MemFormat::parse(&name).ok_or_else(|| {
+                        format!(
+                            "unknown memory format `{}`, expected one of: {}",
+                            name,
+                            MEM_FORMATS.join(", ")
+                        )
+                    })?;
+                }
+                "--notation" => {
+                    let name = args
+                        .next()
+                        .ok_or_else(|| "`--notation` requires a value".to_string())?;
+                    ret.notation = Notation::parse(&name).ok_or_else(|| {
+                        format!(
+                            "unknown notation `{}`, expected one of: {}",
+                            name,
+                            NOTATIONS.join(", ")
+                        )
+                    })?;
+                }
+                "--min-cpu" => {
+                    let value = args
+                        .next()
+                        .ok_or_else(|| "`--min-cpu` requires a value".to_string())?;
+                    ret.min_cpu = Some(
+                        value
+                            .parse::<f64>()
+                            .ok()
+                            .filter(|percent| percent.is_finite() && *percent >= 0.0)
+                            .map(|percent| (percent * 100.0).round() as usize)
+                            .ok_or_else(|| {
+                                format!(
+                                    "invalid cpu percentage `{}`, expected a number of at least 0",
+                                    value
+                                )
+                            })?,
+                    );
+                }
+                "--dim-below" => {
+                    let value = args
+                        .next()
+                        .ok_or_else(|| "`--dim-below` requires a value".to_string())?;
+                    ret.dim_below = Some(
+                        value
+                            .parse::<f64>()
+                            .ok()
+                            .filter(|percent| percent.is_finite() && *percent >= 0.0)
+                            .map(|percent| (percent * 100.0).round() as usize)
+                            .ok_or_else(|| {
+                                format!(
+                                    "invalid cpu percentage `{}`, expected a number of at least 0",
+                                    value
+                                )
+                            })?,
+                    );
+                }
+                "--precise-below" => {
+                    let value = args
+                        .next()
+                        .ok_or_else(|| "`--precise-below` requires a value".to_string())?;
+                    ret.precise_below = Some(
+                        value
+                            .parse::<f64>()
+                            .ok()
+                            .filter(|percent| percent.is_finite() && *percent > 0.0)
+                            .map(|percent| (percent * 100.0).round() as usize)
+                            .ok_or_else(|| {
+                                format!(
+                                    "invalid cpu percentage `{}`, expected a number above 0",
+                                    value
+                                )
+                            })?,
+                    );
+                }
+                "--precise-decimals" => {
+                    let value = args
+                        .next()
+                        .ok_or_else(|| "`--precise-decimals` requires a value".to_string())?;
+                    ret.precise_decimals = value
+                        .parse::<usize>()
+                        .ok()
+                        .filter(|n| *n == 1 || *n == 2)
+                        .ok_or_else(|| {
+                            format!("invalid decimal places `{}`, expected 1 or 2", value)
+                        })?;
+                }
+                "--sort" => {
+                    let spec = args
+                        .next()
+                        .ok_or_else(|| "`--sort` requires a value".to_string())?;
+                    ret.sort = Some(SortOrder::parse(&spec)?);
+                }
+                spec if spec.starts_with("--sort=") => {
+                    ret.sort = Some(SortOrder::parse(&spec["--sort=".len()..])?);
+                }
+                "--then-sort" => {
+                    let spec = args
+                        .next()
+                        .ok_or_else(|| "`--then-sort` requires a value".to_string())?;
+                    ret.secondary_sort = Some(SecondarySort::parse(&spec)?);
+                }
+                "--view" => {
+                    let spec = args
+                        .next()
+                        .ok_or_else(|| "`--view` requires a value".to_string())?;
+                    ret.views.push(ViewPreset::parse(&spec)?);
+                }
+                "--align" => {
+                    let spec = args
+                        .next()
+                        .ok_or_else(|| "`--align` requires a value".to_string())?;
+                    ret.alignments = Alignments::parse(&spec)?;
+                }
+                "--highlight-d-state" => ret.highlight_d_state = true,
+                "--fade-new" => {
+                    let value = args
+                        .next()
+                        .ok_or_else(|| "`--fade-new` requires a value".to_string())?;
+                    ret.fade_new = Some(
+                        value
+                            .parse::<usize>()
+                            .ok()
+                            .filter(|n| *n > 0)
+                            .ok_or_else(|| {
+                                format!("invalid sample count `{}`, expected at least 1", value)
+                            })?,
+                    );
+                }
+                "--tombstones" => {
+                    let value = args
+                        .next()
+                        .ok_or_else(|| "`--tombstones` requires a value".to_string())?;
+                    ret.tombstones = Some(
+                        value
+                            .parse::<usize>()
+                            .ok()
+                            .filter(|n| *n > 0)
+                            .ok_or_else(|| {
+                                format!("invalid sample count `{}`, expected at least 1", value)
+                            })?,
+                    );
+                }
+                "--select-tombstones" => ret.select_tombstones = true,
+                "--wrap-selection" => ret.wrap_selection = true,
+                "--auto-expand" => ret.auto_expand = true,
+                "--tree-glyphs" => {
+                    let value = args
+                        .next()
+                        .ok_or_else(|| "`--tree-glyphs` requires a value".to_string())?;
+                    ret.tree_glyphs = TreeGlyphs::parse(&value).ok_or_else(|| {
+                        format!(
+                            "invalid tree glyphs `{}`, expected one of: {}, or four characters",
+                            value,
+                            TREE_GLYPH_SETS.join(", ")
+                        )
+                    })?;
+                }
+                "--two-line" => ret.two_line = true,
+                "--separators" => {
+                    let name = args
+                        .next()
+                        .ok_or_else(|| "`--separators` requires a value".to_string())?;
+                    ret.separators = Some(Separators::parse(&name).ok_or_else(|| {
+                        format!(
+                            "unknown separators `{}`, expected one of: {}",
+                            name,
+                            SEPARATOR_MODES.join(", ")
+                        )
+                    })?);
+                }
+                "--pid-ns" => {
+                    let name = args
+                        .next()
+                        .ok_or_else(|| "`--pid-ns` requires a value".to_string())?;
+                    ret.pid_ns = Some(PidNsMode::parse(&name).ok_or_else(|| {
+                        format!(
+                            "unknown PID namespace mode `{}`, expected one of: {}",
+                            name,
+                            PID_NS_MODES.join(", ")
+                        )
+                    })?);
+                }
+                "--scroll-freeze" => {
+                    let value = args
+                        .next()
+                        .ok_or_else(|| "`--scroll-freeze` requires a value".to_string())?;
+                    ret.scroll_freeze = Some(std::time::Duration::from_millis(
+                        value
+                            .parse::<u64>()
+                            .ok()
+                            .filter(|n| *n > 0)
+                            .ok_or_else(|| format!("invalid number of milliseconds `{}`", value))?,
+                    ));
+                }
+                "--follow-new" => ret.follow_new = true,
+                "--key-hints" => ret.key_hints = true,
+                "--max-cmd-len" => {
+                    let value = args
+                        .next()
+                        .ok_or_else(|| "`--max-cmd-len` requires a value".to_string())?;
+                    ret.max_cmd_len = Some(
+                        value
+                            .parse::<usize>()
+                            .ok()
+                            .filter(|n| *n > 0)
+                            .ok_or_else(|| format!("invalid command line length `{}`", value))?,
+                    );
+                }
+                "--truncate" => {
+                    let value = args
+                        .next()
+                        .ok_or_else(|| "`--truncate` requires a value".to_string())?;
+                    ret.truncation = match value.as_str() {
+                        "end" => Truncation::End,
+                        "middle" => Truncation::Middle,
+                        _ => {
+                            return Err(format!(
+                                "invalid truncation mode `{}`, expected one of: end, middle",
+                                value
+                            ))
+                        }
+                    };
+                }
+                "--control-chars" => {
+                    let value = args
+                        .next()
+                        .ok_or_else(|| "`--control-chars` requires a value".to_string())?;
+                    ret.non_printable = match value.as_str() {
+                        "replace" => NonPrintable::Replace,
+                        "escape" => NonPrintable::Escape,
+                        "skip" => NonPrintable::Skip,
+                        _ => {
+                            return Err(format!(
+                                "invalid control character mode `{}`, expected one of: replace, escape, skip",
+                                value
+                            ))
+                        }
+                    };
+                }
+                "--ambiguous-width" => {
+                    let value = args
+                        .next()
+                        .ok_or_else(|| "`--ambiguous-width` requires a value".to_string())?;
+                    ret.ambiguous_width = Some(AmbiguousWidth::parse(&value).ok_or_else(|| {
+                        format!(
+                            "invalid ambiguous width `{}`, expected one of: narrow, wide",
+                            value
+                        )
+                    })?);
+                }
+                "--sort-scroll" => {
+                    let value = args
+                        .next()
+                        .ok_or_else(|| "`--sort-scroll` requires a value".to_string())?;
+                    ret.sort_scroll = match value.as_str() {
+                        "auto" => SortScroll::Auto,
+                        "top" => SortScroll::Top,
+                        "keep" => SortScroll::Keep,
+                        _ => {
+                            return Err(format!(
+                                "invalid sort scroll mode `{}`, expected one of: auto, top, keep",
+                                value
+                            ))
+                        }
+                    };
+                }
+                "--fixed-tick" => ret.fixed_tick = true,
+                "--max-fps" => {
+                    let value = args
+                        .next()
+                        .ok_or_else(|| "`--max-fps` requires a value".to_string())?;
+                    ret.max_fps = match value.parse::<u32>() {
+                        Ok(0) => None,
+                        Ok(n) => Some(n),
+                        Err(_) => {
+                            return Err(format!(
+                                "invalid redraw rate `{}`, expected frames per second",
+                                value
+                            ))
+                        }
+                    };
+                }
+                "--cgroup" => ret.cgroup = true,
+                "--affinity" => ret.affinity = true,
+                "--elapsed" => ret.elapsed = true,
+                "--threads" => ret.threads = true,
+                "--cpu-time" => ret.cpu_time = true,
+                "--caps" => ret.caps = true,
+                "--env" => {
+                    let key = args
+                        .next()
+                        .ok_or_else(|| "`--env` requires a value".to_string())?;
+                    if !valid_env_key(&key) {
+                        return Err(format!("invalid environment variable name `{}`", key));
+                    }
+                    ret.env_var = Some(key);
+                }
+                "--sockets" => ret.sockets = true,
+                "--highlight-sockets" => ret.highlight_sockets = true,
+                "--gpu" => ret.gpu = true,
+                "--io" => ret.io = true,
+                "--sparkline" => ret.sparkline = true,
+                "--sparkline-baseline" => ret.sparkline_baseline = true,
+                "--newest-left" => ret.newest_left = true,
+                "--zebra" => ret.zebra = true,
+                "--color-categories" => ret.color_categories = true,
+                "--titles" => ret.titles = true,
+                "--summary" => ret.summary = true,
+                "--scrollbar" => ret.scrollbar = true,
+                "--no-header" => ret.no_header = true,
+                "--sample-time" => {
+                    let format = args
+                        .next()
+                        .ok_or_else(|| "`--sample-time` requires a value".to_string())?;
+                    ret.sample_time = Some(format);
+                }
+                "--confirm-quit" => ret.confirm_quit = true,
+                "--confirm-kill" => ret.confirm_kill = true,
+                "--read-only" => ret.read_only = true,
+                "--hook" => {
+                    let template = args
+                        .next()
+                        .ok_or_else(|| "`--hook` requires a value".to_string())?;
+                    ret.hook = Some(HookCommand::parse(&template)?);
+                }
+                "--open-cwd" => {
+                    let template = args
+                        .next()
+                        .ok_or_else(|| "`--open-cwd` requires a value".to_string())?;
+                    ret.open_cwd = Some(CwdCommand::parse(&template)?);
+                }
+                "--no-altscreen" => ret.no_altscreen = true,
+                "--stream-to" => {
+                    let path = args
+                        .next()
+                        .ok_or_else(|| "`--stream-to` requires a value".to_string())?;
+                    ret.stream_to = Some(path.into());
+                }
+                "--idle-unfocused" => {
+                    let value = args
+                        .next()
+                        .ok_or_else(|| "`--idle-unfocused` requires a value".to_string())?;
+                    ret.idle_unfocused = Some(
+                        value
+                            .parse::<usize>()
+                            .ok()
+                            .filter(|n| *n > 0)
+                            .ok_or_else(|| format!("invalid number of ticks `{}`", value))?,
+                    );
+                }
+                "--print-caps" => ret.print_caps = true,
+                "--print-theme" => ret.print_theme = true,
+                "--strip" => {
+                    let value = args
+                        .next()
+                        .ok_or_else(|| "`--strip` requires a value".to_string())?;
+                    ret.strip = Some(
+                        value
+                            .parse::<usize>()
+                            .ok()
+                            .filter(|width| *width > 0)
+                            .ok_or_else(|| format!("invalid strip width `{}`", value))?,
+                    );
+                }
+                "--batch" => ret.batch = true,
+                "-n" => {
+                    let value = args
+                        .next()
+                        .ok_or_else(|| "`-n` requires a value".to_string())?;
+                    ret.iterations = value
+                        .parse::<usize>()
+                        .ok()
+                        .filter(|n| *n > 0)
+                        .ok_or_else(|| format!("invalid number of iterations `{}`", value))?;
+                }
+                "--color=auto" => ret.color = ColorChoice::Auto,
+                "--color=always" => ret.color = ColorChoice::Always,
+                "--color=never" => ret.color = ColorChoice::Never,
+                "-h" | "--help" => ret.help = true,
+                other => return Err(format!("unrecognized argument `{}`", other)),
+            }
+        }
+        let [cpu, mem, load] = thresholds;
+        let theme = ret.theme;
+        /* over the thresholds of a theme file, if it has some */
+        let colored = |limits: Option<[f64; 2]>, file: Option<Thresholds>| {
+            limits
+                .map(|limits| theme.thresholds(limits))
+                .transpose()
+                .map(|thresholds| thresholds.or(file))
+        };
+        ret.theme.cpu_thresholds = colored(cpu, theme.cpu_thresholds)?;
+        ret.theme.mem_thresholds = colored(mem, theme.mem_thresholds)?;
+        ret.theme.load_thresholds = colored(load, theme.load_thresholds)?;
+        Ok(ret)
+    }
+
+    /// How cpu percentages are rounded, if `--precise-below` is given.
+    pub fn percent_format(&self) -> Option<PercentFormat> {
+        self.precise_below.map(|precise_below| PercentFormat {
+            precise_below,
+            decimals: self.precise_decimals,
+        })
+    }
+}
@@ -0,0 +1,102 @@
+/*
+ * bb
+ *
+ * Copyright 2019 Manos Pitsidianakis
+ *
+ * This file is part of bb.
+ *
+ * bb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * bb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with bb. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+/*! bb's user interface, as a library.
+
+The `bb` binary is a thin `main` around the `ui` module, which holds the process viewer's components
+as well as the terminal drawing primitives they're built on. Only the primitives are part of the
+library's API and are re-exported here, so that other TUIs can draw with them without bb's event
+loop; the rest of `ui` is internal to bb and may change at any time:
+
+```
+use bb::{write_string_to_grid, Attr, Cell, CellBuffer, Color};
+
+let mut grid = CellBuffer::new(20, 2, Cell::with_char(' '));
+let end = write_string_to_grid(
+    "hello, 世界",
+    &mut grid,
+    Color::Red,
+    Color::Default,
+    Attr::Bold,
+    ((0, 0), (19, 1)),
+    false,
+);
+/* wide characters take two columns */
+assert_eq!(end, (11, 0));
+assert_eq!(grid[(0, 0)].ch(), 'h');
+assert_eq!(grid[(0, 0)].fg(), Color::Red);
+assert_eq!(grid[(0, 0)].attrs(), Attr::Bold);
+assert_eq!(grid[(11, 0)].ch(), ' ');
+```
+
+A `CellBuffer` is drawn to a terminal by writing its cells in order; empty cells, such as the right
+halves of wide characters, are skipped. A `Screen` does that for the cells that changed since the
+last frame:
+
+```
+use bb::{write_string_to_grid, Attr, Color, Screen};
+
+let mut screen = Screen::new(20, 2);
+let mut out = vec![];
+screen.flush(&mut out).unwrap();
+write_string_to_grid(
+    "hi",
+    screen.buffer_mut(),
+    Color::Default,
+    Color::Default,
+    Attr::Default,
+    ((5, 1), (19, 1)),
+    false,
+);
+out.clear();
+screen.flush(&mut out).unwrap();
+assert_eq!(out, b"\x1B[2;6Hhi");
+```
+
+A `Style` bundles the colors and attributes of `write_string_to_grid`, for `write_styled`:
+
+```
+use bb::{write_styled, Attr, Cell, CellBuffer, Color, Style};
+
+let mut grid = CellBuffer::new(10, 1, Cell::with_char(' '));
+let style = Style {
+    fg: Color::Green,
+    attrs: Attr::Underline,
+    ..Style::default()
+};
+write_styled("ok", &mut grid, style, ((0, 0), (9, 0)), false);
+assert_eq!(grid[(1, 0)].fg(), Color::Green);
+assert_eq!(grid[(1, 0)].attrs(), Attr::Underline);
+```
+*/
+
+extern crate crossbeam;
+extern crate nix;
+extern crate termion;
+
+#[macro_use]
+#[doc(hidden)]
+pub mod ui;
+
+pub use ui::{
+    change_colors, clear_area, create_box, write_string_to_grid, write_styled, Area, Attr, Cell,
+    CellAccessor, CellBuffer, Color, NonPrintable, Pos, Screen, Style,
+};
@@ -18,27 +18,31 @@
  */
 
 //!
-//!  This crate contains the frontend stuff of the application. The application entry way on
-//!  `src/bin.rs` creates an event loop and passes input to the `ui` module.
-//!
-//! The mail handling stuff is done in the `bb` crate which includes all backend needs. The
-//! split is done to theoretically be able to create different frontends with the same innards.
+//!  The application entry way: parses the arguments, creates an event loop and passes input to
+//!  the `ui` module of the `bb` library.
 //!
 
+#[macro_use]
+extern crate bb;
 extern crate crossbeam;
 extern crate nix;
 extern crate signal_hook;
 extern crate termion;
 
-use crossbeam::channel::{bounded, tick};
+use crossbeam::channel::{after, bounded, never, tick};
 use crossbeam::select;
 use libc::c_int;
 use std::io::Error;
 use std::time::Duration;
 
-mod ui;
+mod args;
+use args::{Args, USAGE};
+use bb::ui;
 use ui::*;
 
+/* How long after a first press of q a second one quits, with --confirm-quit */
+const QUIT_TIMEOUT: Duration = Duration::from_secs(2);
+
 fn notify(signals: &[c_int]) -> Result<crossbeam::channel::Receiver<c_int>, Error> {
     let (s, r) = bounded(100);
     let signals = signal_hook::iterator::Signals::new(signals)?;
@@ -51,6 +55,144 @@ fn notify(signals: &[c_int]) -> Result<crossbeam::channel::Receiver<c_int>, Erro
 }
 
 fn main() -> Result<(), Error> {
+    let args = match Args::parse(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(err) => {
+            eprintln!("bb: {}\n{}", err, USAGE);
+            std::process::exit(1);
+        }
+    };
+    if args.help {
+        println!("{}", USAGE);
+        return Ok(());
+    }
+    if args.print_caps {
+        print!("{}", Capabilities::detect(args.no_altscreen).report());
+        return Ok(());
+    }
+    if args.print_theme {
+        print!("{}", args.theme.to_toml());
+        return Ok(());
+    }
+    if let Err(err) = ui::log::init() {
+        eprintln!("bb: {}", err);
+        std::process::exit(1);
+    }
+    log_info!("bb {} started", env!("CARGO_PKG_VERSION"));
+    let proc_source = match ProcSource::new(&args.proc_root) {
+        Ok(proc_source) => proc_source,
+        Err(err) => {
+            eprintln!("bb: {}", err);
+            std::process::exit(1);
+        }
+    };
+    if let Some(width) = args.strip {
+        return ui::components::print_strip(width, &proc_source);
+    }
+    ui::set_ambiguous_width(
+        args.ambiguous_width
+            .unwrap_or_else(ui::AmbiguousWidth::from_env),
+    );
+    let percent_format = args.percent_format();
+    let list_options = ui::components::ProcessListOptions {
+        pid_filter: args.pid_filter,
+        pins: args.pins,
+        hover: args.hover,
+        normalize: args.nfc,
+        case_sensitive: args.case_sensitive,
+        fuzzy: args.fuzzy,
+        max_cmd_len: args.max_cmd_len,
+        truncation: args.truncation,
+        smoothing: args.smoothing,
+        sort_scroll: args.sort_scroll,
+        cgroups: args.cgroup,
+        affinity: args.affinity,
+        io: args.io,
+        elapsed: args.elapsed,
+        threads: args.threads,
+        cpu_time: args.cpu_time,
+        caps: args.caps,
+        env_var: args.env_var,
+        sockets: args.sockets,
+        highlight_sockets: if args.highlight_sockets {
+            Some(args.theme.sockets)
+        } else {
+            None
+        },
+        gpu: args.gpu,
+        sparkline: args.sparkline,
+        sparkline_baseline: args.sparkline_baseline,
+        graph_direction: if args.newest_left {
+            ui::components::GraphDirection::NewestLeft
+        } else {
+            ui::components::GraphDirection::NewestRight
+        },
+        fade_new: args.fade_new,
+        tombstones: args.tombstones,
+        select_tombstones: args.select_tombstones,
+        wrap_selection: args.wrap_selection,
+        auto_expand: args.auto_expand,
+        tree_glyphs: args.tree_glyphs,
+        two_line: args.two_line,
+        separators: args.separators,
+        scroll_freeze: args.scroll_freeze,
+        follow_new: args.follow_new,
+        pid_ns: args.pid_ns,
+        key_hints: args.key_hints,
+        mem_threshold: args.mem_threshold,
+        cpu_thresholds: args.theme.cpu_thresholds,
+        mem_thresholds: args.theme.mem_thresholds,
+        mem_growth: args.mem_growth,
+        min_cpu: args.min_cpu,
+        dim_below: args.dim_below,
+        percent_format,
+        mem_format: args.mem_format,
+        notation: args.notation,
+        alignments: args.alignments,
+        sort: args.sort,
+        secondary_sort: args.secondary_sort,
+        views: args.views,
+        uninterruptible: if args.highlight_d_state {
+            Some(args.theme.uninterruptible)
+        } else {
+            None
+        },
+        selection: Some(args.theme.selection()),
+        stripe: if args.zebra {
+            Some(args.theme.row_stripe)
+        } else {
+            None
+        },
+        scrollbar: args.scrollbar,
+        no_header: args.no_header,
+        sample_time: args.sample_time,
+        confirm_kill: args.confirm_kill,
+        read_only: args.read_only,
+        hook: args.hook,
+        open_cwd: args.open_cwd,
+        summary: args.summary,
+        categories: if args.color_categories {
+            Some(args.theme)
+        } else {
+            None
+        },
+        proc_source: proc_source.clone(),
+    };
+    /* Piped, bb prints the list like with --batch instead of drawing on a terminal it doesn't have */
+    let stdout_is_tty = termion::is_tty(&std::io::stdout());
+    let start_mode = StartMode::select(stdout_is_tty, termion::terminal_size().ok());
+    if !args.batch && start_mode == StartMode::Batch && stdout_is_tty {
+        eprintln!("bb: can't read the size of the terminal, printing the process list instead");
+    }
+    if args.batch || start_mode == StartMode::Batch {
+        return ui::components::batch(
+            args.iterations,
+            args.color.enabled(),
+            args.non_printable,
+            &list_options,
+        );
+    }
+
     let signals = &[
         signal_hook::SIGALRM,
         signal_hook::SIGTERM,
@@ -58,72 +200,150 @@ fn main() -> Result<(), Error> {
         signal_hook::SIGQUIT,
         /* Catch SIGWINCH to handle terminal resizing */
         signal_hook::SIGWINCH,
+        /* Restore the terminal before being stopped by job control, and redraw once continued */
+        libc::SIGTSTP,
+        signal_hook::SIGCONT,
     ];
 
-    let ticker = tick(Duration::from_millis(1600));
+    let interval = Duration::from_millis(1600);
+    let ticker = tick(interval);
+    let mut refresh_policy = RefreshPolicy::new(
+        interval,
+        !args.fixed_tick,
+        args.max_fps.map(|fps| Duration::from_secs(1) / fps),
+        std::time::Instant::now(),
+    );
 
+    let mut focus_policy = FocusPolicy::new(args.idle_unfocused);
+
+    /* Ctrl-C is a key in raw mode, but SIGINT can still come from elsewhere, eg. kill(1). The flag
+     * is set before the signal watcher hears of it, so the loop sees it once woken up. */
+    let shutdown = Shutdown::new();
+    shutdown.register(signal_hook::SIGINT)?;
     let signal_recvr = notify(signals)?;
+    let mut quit_guard = QuitGuard::new(if args.confirm_quit {
+        Some(QUIT_TIMEOUT)
+    } else {
+        None
+    });
 
     /* Create the application State */
-    let mut state = State::new();
+    let mut state = State::new(ScreenMode::select(args.no_altscreen));
 
     let receiver = state.receiver();
+    let kernel = ui::components::KernelMetrics::new(ui::components::KernelMetricsOptions {
+        bar_fill: args.bar_fill,
+        glyphs: args.bar_glyphs,
+        theme: args.theme,
+        smoothing: args.smoothing,
+        vertical: args.vertical_bars,
+        heatmap: args.heatmap,
+        compact: args.compact,
+        meter_height: args.meter_height,
+        proc_source,
+        meter_epsilon: args.meter_epsilon,
+        swap_meter: args.swap_meter,
+        load_warning: args.load_warning,
+        pid_warning: args.pid_warning,
+        container: args.container,
+        self_usage: args.self_usage,
+        percent_format,
+    });
+    let kernel_height = kernel.height();
     let window = Box::new(Window::new(
-        Box::new(ui::components::KernelMetrics::new()),
-        Box::new(ui::components::ProcessList::new()),
+        Box::new(kernel),
+        Box::new(ui::components::ProcessList::new(list_options)),
+        kernel_height,
+        args.titles,
     ));
 
     state.register_component(window);
+    /* the meters, and the header and a row of the process list, each below its title */
+    if let Some(path) = args.stream_to {
+        state.stream_frames_to(path);
+    }
+    state.set_min_size(80, kernel_height + 2 + if args.titles { 2 } else { 0 });
+    state.set_non_printable(args.non_printable);
+    state.set_bidi(args.bidi);
+    if args.hover {
+        state.enable_mouse_motion();
+    }
+    if args.idle_unfocused.is_some() {
+        state.enable_focus_tracking();
+    }
     state.render();
     state.redraw(true);
 
     /* Keep track of the input mode. See ui::UIMode for details */
-    'main: loop {
+    run(state, &shutdown, |state| {
         /* Poll on all channels. Currently we have the input channel for stdin, watching events and the signal watcher. */
+        let frame = refresh_policy
+            .deadline()
+            .map(|deadline| after(deadline.saturating_duration_since(std::time::Instant::now())))
+            .unwrap_or_else(never);
         select! {
+            recv(frame) -> _ => {
+                let refresh = refresh_policy.wake(Wake::Frame, std::time::Instant::now());
+                state.refresh(focus_policy.refresh(refresh));
+            },
             recv(ticker) -> _ => {
-                state.redraw(true);
+                let refresh = refresh_policy.wake(Wake::Tick, std::time::Instant::now());
+                state.refresh(focus_policy.refresh(refresh));
             },
             recv(signal_recvr) -> sig => {
                 eprintln!("got signal {:?}", sig);
                 match sig.unwrap() {
-                    signal_hook::SIGWINCH => {
+                    libc::SIGTSTP => suspend(state),
+                    /* SIGCONT also follows a `suspend`, but the process could have been stopped
+                     * by SIGSTOP, which can't be caught, and the screen overwritten meanwhile */
+                    signal_hook::SIGWINCH | signal_hook::SIGCONT => {
                         state.update_size();
                         state.render();
                         state.redraw(true);
-                    },
+                    }
                     _ => {}
                 }
             },
             recv(receiver) -> msg => {
                 match msg.unwrap() {
+                    ThreadEvent::Mouse(ev) => {
+                        state.rcv_event(UIEvent::Mouse(ev));
+                        state.refresh(refresh_policy.wake(Wake::Input, std::time::Instant::now()));
+                    },
+                    ThreadEvent::Focus(focused) => {
+                        if focus_policy.focus(focused) {
+                            state.suppress_redraws(focus_policy.suppressed());
+                        }
+                    },
                     ThreadEvent::Input(Key::Ctrl('z')) => {
-                        state.switch_to_main_screen();
-                        //_thread_handler.join().expect("Couldn't join on the associated thread");
-                        let self_pid = nix::unistd::Pid::this();
-                        nix::sys::signal::kill(self_pid, nix::sys::signal::Signal::SIGSTOP).unwrap();
-                        state.switch_to_alternate_screen();
-                        state.restore_input();
-                        // BUG: thread sends input event after one received key
-                        state.update_size();
-                        state.render();
-                        state.redraw(true);
+                        suspend(state);
+                    },
+                    ThreadEvent::Input(Key::Ctrl('c')) => {
+                        shutdown.request();
                     },
                     ThreadEvent::Input(k) => {
                         match k {
-                            Key::Char('q') | Key::Char('Q') => {
-                                drop(state);
-                                break 'main;
+                            Key::Char('q') | Key::Char('Q') if !state.takes_text_input() => {
+                                if quit_guard.press(std::time::Instant::now()) {
+                                    return false;
+                                }
+                                state.rcv_event(UIEvent::StatusMessage(format!(
+                                    "press q again within {} seconds to quit",
+                                    QUIT_TIMEOUT.as_secs()
+                                )));
+                                state.refresh(refresh_policy.wake(Wake::Input, std::time::Instant::now()));
                             },
                             key  => {
+                                quit_guard.reset();
                                 state.rcv_event(UIEvent::Input(key));
-                                state.redraw(false);
+                                state.refresh(refresh_policy.wake(Wake::Input, std::time::Instant::now()));
                             },
                         }
                     },
                 }
             },
         }
-    }
+        true
+    });
     Ok(())
 }
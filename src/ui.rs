@@ -23,6 +23,9 @@
  * This library exports the public types and methods of its modules
  */
 
+#[macro_use]
+pub mod log;
+
 #[allow(dead_code)]
 mod text_processing;
 pub use crate::ui::text_processing::*;
@@ -39,6 +42,9 @@ pub use crate::ui::terminal::*;
 pub mod state;
 pub use crate::ui::state::*;
 
+pub mod theme;
+pub use crate::ui::theme::Theme;
+
 #[allow(dead_code)]
 pub mod components;
 pub use crate::ui::components::*;
@@ -104,7 +110,7 @@ pub mod username {
         unsafe {
             string = ::std::ffi::CStr::from_ptr(pwent.pw_name)
                 .to_str()
-                .unwrap_or_else(|_| "")
+                .unwrap_or("")
                 .to_string();
         }
 
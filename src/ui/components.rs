@@ -31,6 +31,8 @@ pub use utilities::*;
 
 mod kernel;
 pub use kernel::*;
+mod render;
+pub use render::*;
 pub mod processes;
 pub use processes::*;
 
@@ -66,6 +68,15 @@ pub trait Component: Display + Debug + Send {
     fn get_shortcuts(&self) -> ShortcutMaps {
         Default::default()
     }
+    /// What containers that draw titles show above the component, eg. `Processes (123)`.
+    fn title(&self) -> Option<String> {
+        None
+    }
+    /// Whether the component is taking typed text, eg. a filter, so that keys the main loop acts
+    /// on, like `q` for quitting, are passed to it instead.
+    fn takes_text_input(&self) -> bool {
+        false
+    }
 }
 
 fn bin_to_ch(b: u32) -> char {
@@ -85,7 +96,7 @@ fn bin_to_ch(b: u32) -> char {
         0b1101 => '┬',
         0b1110 => '┤',
         0b1111 => '┼',
-        x => unreachable!(format!("unreachable bin_to_ch(x), x = {:b}", x)),
+        x => unreachable!("unreachable bin_to_ch(x), x = {:b}", x),
     }
 }
 
@@ -354,3 +365,35 @@ pub fn create_box(grid: &mut CellBuffer, area: Area) {
     //set_and_join_box(grid, set_y(upper_left, get_y(bottom_right)), VERT_BOUNDARY);
     //set_and_join_box(grid, bottom_right, VERT_BOUNDARY);
 }
+
+/// Draw a horizontal line along the top row of `area`, with `title` inset into it after the first
+/// two cells, eg. `─ Processes (123) ─────`. Titles that don't fit are shortened with `…`.
+pub fn draw_title(grid: &mut CellBuffer, area: Area, title: &str) {
+    if !is_valid_area!(area) {
+        return;
+    }
+    let upper_left = upper_left!(area);
+    let bottom_right = bottom_right!(area);
+    let y = get_y(upper_left);
+    for x in get_x(upper_left)..=get_x(bottom_right) {
+        if let Some(cell) = grid.get_mut(x, y) {
+            *cell = Cell::default();
+            cell.set_ch(HORZ_BOUNDARY);
+            cell.set_fg(Color::Byte(240));
+        }
+    }
+    /* a cell of the line and a space on either side of the title */
+    let budget = (width!(area) + 1).saturating_sub(4);
+    if title.is_empty() || budget == 0 {
+        return;
+    }
+    write_string_to_grid(
+        &format!(" {} ", truncate(title, budget, Truncation::End)),
+        grid,
+        Color::Default,
+        Color::Default,
+        Attr::Bold,
+        ((get_x(upper_left) + 1, y), (get_x(bottom_right), y)),
+        false,
+    );
+}
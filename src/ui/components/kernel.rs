@@ -20,22 +20,208 @@
  */
 
 use super::*;
-use std::fs::File;
-use std::io::prelude::*;
 use std::str::FromStr;
 
+mod container;
+mod header;
+use container::{detect_container, CgroupLimits};
+pub use container::{ContainerMode, CONTAINER_MODES};
+pub use header::{SystemHeader, SystemInfo};
+mod load_banner;
+mod pid_warning;
+mod self_usage;
+mod strip;
+mod swap;
+use load_banner::{draw_banner, overload_warning, LoadBanner};
+use pid_warning::PidUsage;
+use self_usage::SelfUsage;
+pub use strip::print_strip;
+use swap::{draw_swap_meter, SwapInfo};
+
 /* if cpu_no > MAX_CPU_ROWS, the cpu bars wrap in columns */
 static MAX_CPU_ROWS: usize = 5;
+
+/* Left partial blocks, from one eighth to seven eighths */
+const PARTIAL_BLOCKS: [char; 7] = ['▏', '▎', '▍', '▌', '▋', '▊', '▉'];
+/* Lower partial blocks, from one eighth to seven eighths */
+const LOWER_BLOCKS: [char; 7] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇'];
+
+/// How the last cell of a bar is filled.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub enum BarFill {
+    /// Only draw whole cells.
+    #[default]
+    Whole,
+    /// Draw the remainder with a partial block glyph, in eighths of a cell.
+    Eighths,
+}
+
+/// Rows of the top of `KernelMetrics` taken by the hostname and the spacing below it.
+const HEADER_ROWS: usize = 2;
+
+/* Colors of an idle and of a fully used cpu in the heatmap, the ones in between are blended */
+const HEAT_COLD: Color = Color::Byte(34); // Green3
+const HEAT_HOT: Color = Color::Byte(196); // Red1
+
+/* What a row of a meter that fills upwards shows */
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum MeterRow {
+    Full,
+    /* the boundary row, with the lower block glyph of its fill */
+    Partial(char),
+    Empty,
+}
+
+/// Characters bars are drawn with instead of the built-in block glyphs, for fonts that don't
+/// render those well.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct BarGlyphs {
+    /// Filled cells.
+    pub fill: char,
+    /// Empty cells.
+    pub empty: char,
+    /// Whether `BarFill::Eighths` may still draw partial block glyphs.
+    pub eighths: bool,
+}
+
+/// Names accepted by `BarGlyphs::parse`, besides two characters.
+pub const BAR_GLYPH_SETS: &[&str] = &["ascii", "block"];
+
+impl BarGlyphs {
+    pub const ASCII: BarGlyphs = BarGlyphs {
+        fill: '#',
+        empty: '.',
+        eighths: false,
+    };
+    pub const BLOCK: BarGlyphs = BarGlyphs {
+        fill: '█',
+        empty: ' ',
+        eighths: true,
+    };
+
+    /// One of `BAR_GLYPH_SETS`, or the fill and empty characters, eg. `=-`.
+    pub fn parse(s: &str) -> Option<BarGlyphs> {
+        match s {
+            "ascii" => return Some(BarGlyphs::ASCII),
+            "block" => return Some(BarGlyphs::BLOCK),
+            _ => {}
+        }
+        let mut chars = s.chars();
+        match (chars.next(), chars.next(), chars.next()) {
+            (Some(fill), Some(empty), None) => Some(BarGlyphs {
+                fill,
+                empty,
+                eighths: !fill.is_ascii() && !empty.is_ascii(),
+            }),
+            _ => None,
+        }
+    }
+
+    /// Bars are laid out assuming every glyph takes up one cell. Returns `ASCII` if some glyph of
+    /// `self` doesn't.
+    pub fn or_ascii(self) -> BarGlyphs {
+        let narrow = |ch: char| wcwidth(u32::from(ch)) == Some(1);
+        let partial_narrow = !self.eighths
+            || PARTIAL_BLOCKS
+                .iter()
+                .chain(LOWER_BLOCKS.iter())
+                .all(|&ch| narrow(ch));
+        if narrow(self.fill) && narrow(self.empty) && partial_narrow {
+            self
+        } else {
+            BarGlyphs::ASCII
+        }
+    }
+}
+
+/* Glyph and colors of a whole cell of a bar. Without `glyphs`, horizontal and vertical bars have
+ * built-in glyphs of their own. */
+fn bar_cell(glyphs: Option<BarGlyphs>, filled: bool, vertical: bool) -> (char, Color, Color) {
+    match (glyphs, filled) {
+        (Some(glyphs), true) => (glyphs.fill, Color::Byte(240), Color::Byte(235)),
+        (Some(glyphs), false) => (glyphs.empty, Color::Byte(238), Color::Byte(235)),
+        (None, true) if vertical => ('█', Color::Byte(240), Color::Byte(235)),
+        (None, true) => ('▁', Color::Byte(235), Color::Byte(240)),
+        (None, false) => ('▁', Color::Byte(236), Color::Byte(235)),
+    }
+}
+
+/// Settings of a `KernelMetrics`, see `KernelMetrics::new`.
+#[derive(Debug, Default)]
+pub struct KernelMetricsOptions {
+    pub bar_fill: BarFill,
+    /// Characters to draw the bars with instead of the built-in ones.
+    pub glyphs: Option<BarGlyphs>,
+    pub theme: Theme,
+    /// Weight of new samples in the smoothed cpu bars, if enabled.
+    pub smoothing: Option<f64>,
+    /// Draw the cpu meters as columns instead of rows.
+    pub vertical: bool,
+    /// Draw a cell per cpu colored by its usage instead of the cpu meters.
+    pub heatmap: bool,
+    /// Leave out the cpu meter labels and pad columns with a single space.
+    pub compact: bool,
+    /// Rows each horizontal meter spans, at least 1.
+    pub meter_height: usize,
+    /// Where procfs is mounted.
+    pub proc_source: ProcSource,
+    /// Only redraw the meters once one of them moved by more than this fraction since it was last
+    /// drawn.
+    pub meter_epsilon: Option<f64>,
+    /// Round the cpu time percentages next to the meters instead of showing one decimal place.
+    pub percent_format: Option<PercentFormat>,
+    /// Draw a meter of the swap in use below the RAM meter, if the system has swap.
+    pub swap_meter: bool,
+    /// Show a warning across the top while the 1 minute load average is above this many times
+    /// the number of cpus.
+    pub load_warning: Option<f64>,
+    /// Show a warning across the top while at least this fraction of `pid_max` PIDs are in use.
+    pub pid_warning: Option<f64>,
+    /// Whether to look for a container bb runs in, and scope the meters to its limits.
+    pub container: ContainerMode,
+    /// Show bb's own cpu usage and memory in the header.
+    pub self_usage: bool,
+}
+
 /* Kernel metrics components */
 #[derive(Debug)]
 pub struct KernelMetrics {
-    hostname: String,
-    kernel: String,
-    os_type: String,
-    uptime: String,
+    header: SystemHeader,
     cpu_stat: Vec<Stat>,
     boot_time: usize,
     dirty: bool,
+    bar_fill: BarFill,
+    glyphs: Option<BarGlyphs>,
+    theme: Theme,
+    /* weight of new samples in the smoothed cpu bars, if enabled */
+    smoothing: Option<f64>,
+    cpu_averages: Vec<Ewma>,
+    /* draw the cpu meters as columns instead of rows */
+    vertical: bool,
+    /* draw a cell per cpu instead of the cpu meters, see `draw_cpu_heatmap` */
+    heatmap: bool,
+    /* leave out the cpu meter labels and pad columns with a single space */
+    compact: bool,
+    /* rows each horizontal meter spans */
+    meter_height: usize,
+    /* draw the meters on the next draw even if it's not a tick */
+    force_redraw: bool,
+    proc_source: ProcSource,
+    meter_epsilon: Option<f64>,
+    percent_format: Option<PercentFormat>,
+    /* draw the swap meter below the RAM one, only if there was swap when bb started */
+    swap_meter: bool,
+    /* `None` unless the overload warning is enabled */
+    load_banner: Option<LoadBanner>,
+    /* fraction of `pid_max` in use over which to warn, `None` unless enabled */
+    pid_warning: Option<f64>,
+    /* the limits of bb's cgroup with `ContainerMode::Limits`, if it has any */
+    limits: Option<CgroupLimits>,
+    /* bb's own usage, if it's shown */
+    self_usage: Option<SelfUsage>,
+    /* the cpu fills and the RAM fraction in use the meters were last drawn with, if
+     * `meter_epsilon` is set */
+    drawn_meters: Vec<f64>,
 }
 
 impl fmt::Display for KernelMetrics {
@@ -45,90 +231,241 @@ impl fmt::Display for KernelMetrics {
 }
 
 impl KernelMetrics {
-    pub fn new() -> Self {
-        let mut file = File::open("/proc/sys/kernel/hostname").unwrap();
-        let mut hostname = String::new();
-        file.read_to_string(&mut hostname).unwrap();
-        let mut kernel = String::new();
-        file = File::open("/proc/sys/kernel/version").unwrap();
-        file.read_to_string(&mut kernel).unwrap();
-        let mut os_type = String::new();
-        file = File::open("/proc/sys/kernel/ostype").unwrap();
-        file.read_to_string(&mut os_type).unwrap();
-        let mut boot_time = 0;
-        let cpu_stat = get_stat(&mut boot_time);
+    pub fn new(options: KernelMetricsOptions) -> Self {
+        let KernelMetricsOptions {
+            bar_fill,
+            glyphs,
+            theme,
+            smoothing,
+            vertical,
+            heatmap,
+            compact,
+            meter_height,
+            proc_source,
+            meter_epsilon,
+            percent_format,
+            swap_meter,
+            load_warning,
+            pid_warning,
+            container,
+            self_usage,
+        } = options;
+        let glyphs = glyphs.map(|glyphs| {
+            let checked = glyphs.or_ascii();
+            if checked != glyphs {
+                log_warn!(
+                    "bar glyphs {:?} aren't one cell wide, using ASCII instead",
+                    glyphs
+                );
+            }
+            checked
+        });
+        let swap_meter = swap_meter && SwapInfo::read(&proc_source).total > 0;
+        let (runtime, limits) =
+            detect_container(container, std::path::Path::new("/"), &proc_source);
+        let self_usage = if self_usage {
+            Some(SelfUsage::new(proc_source.clone()))
+        } else {
+            None
+        };
         KernelMetrics {
-            hostname,
-            kernel,
-            os_type,
-            uptime: String::with_capacity(60),
-            cpu_stat,
-            boot_time,
+            header: SystemHeader::new(SystemInfo {
+                container: runtime,
+                limits: limits.as_ref().and_then(CgroupLimits::describe),
+                ..SystemInfo::read(&proc_source)
+            }),
+            /* the first draw takes the baseline */
+            cpu_stat: vec![],
+            boot_time: 0,
             dirty: true,
+            bar_fill: match glyphs {
+                Some(glyphs) if !glyphs.eighths => BarFill::Whole,
+                _ => bar_fill,
+            },
+            glyphs,
+            theme,
+            smoothing,
+            cpu_averages: vec![],
+            vertical,
+            heatmap,
+            compact,
+            meter_height: meter_height.max(1),
+            force_redraw: false,
+            proc_source,
+            meter_epsilon,
+            percent_format,
+            swap_meter,
+            load_banner: load_warning.map(LoadBanner::new),
+            pid_warning,
+            limits,
+            self_usage,
+            drawn_meters: vec![],
         }
     }
 
-    /* Returns width of entire widget */
-    fn draw_cpu_bars(&mut self, grid: &mut CellBuffer, area: Area) -> usize {
-        let upper_left = upper_left!(area);
-        let total_cols = width!(area);
-        /* no of bars is no of CPUs along with the total CPU usage  */
-        let cpu_no = self.cpu_stat.len();
-
-        /* Calculate how much horizontal space the labels (ie CPU0, CPU1, CPU2) take in order to
-         * distribute the remainder for each column, specifically the bars */
-        let mut cpu_label_space = 0;
-        let mut cpu_bar_columns = 0;
-        let mut i = 0;
-        while i < cpu_no {
-            /* Reserve space for CPU labels */
-            if i < 10 {
-                /* label will be " CPU0  " */
-                cpu_label_space += 7;
-            } else if i < 100 {
-                /* label will be " CPU32  " */
-                cpu_label_space += 8;
-            } else {
-                /* label will be " CPU128  " */
-                cpu_label_space += 9;
+    /// Rows needed to draw every meter: the header, `MAX_CPU_ROWS` cpu meters, the RAM meter and
+    /// the swap meter, if it's shown.
+    pub fn height(&self) -> usize {
+        HEADER_ROWS + (MAX_CPU_ROWS + 1 + self.swap_meter as usize) * self.meter_height
+    }
+
+    /* Read the cpu times and return the fraction of time each cpu (the total first) was busy since
+     * the previous call, smoothed if enabled. The first call only takes the baseline, so there are
+     * no fractions yet. */
+    fn cpu_fills(&mut self) -> Vec<Option<f64>> {
+        let mut boot_time: usize = 0;
+        let cpu_stat = get_stat(&self.proc_source, &mut boot_time);
+        let now = std::time::Instant::now();
+        if let Some(alpha) = self.smoothing {
+            self.cpu_averages.resize(cpu_stat.len(), Ewma::new(alpha));
+        }
+        let mut fills = Vec::with_capacity(cpu_stat.len());
+        for (i, new) in cpu_stat.iter().enumerate() {
+            /* Calculate percentages for the cpu usage bar */
+            let old = self.cpu_stat.get(i);
+            let mut fill = rate(
+                old.map(Stat::busy_time),
+                new.busy_time(),
+                old.map_or(0, |old| new.total_time().saturating_sub(old.total_time())),
+            );
+            if let (Some(sample), Some(average)) = (fill, self.cpu_averages.get_mut(i)) {
+                fill = Some(average.update(sample, now));
             }
-            /* each column holds MAX_CPU_ROWS */
-            i += MAX_CPU_ROWS;
-            cpu_bar_columns += 1;
+            fills.push(fill);
         }
-        /* max width of each cpu bar */
-        let bar_width = if cpu_no < MAX_CPU_ROWS {
-            total_cols
+        self.cpu_stat = cpu_stat;
+        self.boot_time = boot_time;
+        fills
+    }
+
+    /* Vertical variant of `draw_cpu_bars`: a column per cpu, labelled below. Returns width of
+     * entire widget */
+    fn draw_cpu_vbars(
+        &mut self,
+        grid: &mut CellBuffer,
+        area: Area,
+        fills: Vec<Option<f64>>,
+    ) -> usize {
+        let upper_left = upper_left!(area);
+        let bottom_right = bottom_right!(area);
+        /* every column is as wide as the longest label, and one cell apart from the next */
+        let column_width = if self.compact {
+            1
         } else {
-            (total_cols - cpu_label_space) / (cpu_bar_columns)
+            (fills.len() - 1).to_string().len()
         };
-
-        let mut boot_time: usize = 0;
-
-        let mut x_offset = 0;
-        for (i, cpu_stat) in get_stat(&mut boot_time).into_iter().enumerate() {
-            let label_len = if i < 10 {
-                8
-            } else if i < 100 {
-                9
+        let mut x = get_x(upper_left);
+        for (i, fill) in fills.into_iter().enumerate() {
+            if x + column_width > get_x(bottom_right) {
+                break;
+            }
+            let label = if i == 0 {
+                "Σ".to_string()
             } else {
-                10
+                i.to_string()
             };
-            let bottom_right = pos_inc(
-                upper_left,
-                (x_offset + bar_width + label_len, i % MAX_CPU_ROWS + 2),
+            draw_vbar(
+                grid,
+                (
+                    (x, get_y(upper_left) + 2),
+                    (x + column_width - 1, get_y(bottom_right)),
+                ),
+                fill.unwrap_or(0.0),
+                if self.compact { None } else { Some(&label) },
+                self.bar_fill,
+                self.glyphs,
             );
-            let (mut x, y) = if i > 0 {
+            x += column_width + 1;
+        }
+        width!(area)
+    }
+
+    /* Heatmap variant of `draw_cpu_bars`: the total usage on the first row, and below it a cell per
+     * cpu colored by its usage, wrapped to the width of `area`. Returns width of entire widget */
+    fn draw_cpu_heatmap(
+        &mut self,
+        grid: &mut CellBuffer,
+        area: Area,
+        fills: Vec<Option<f64>>,
+    ) -> usize {
+        let upper_left = upper_left!(area);
+        let bottom_right = bottom_right!(area);
+        let (x, y) = (get_x(upper_left), get_y(upper_left) + HEADER_ROWS);
+        clear_area(grid, ((x, y), (get_x(bottom_right), y)));
+        let (x_, _) = write_string_to_grid(
+            "Σ",
+            grid,
+            Color::Default,
+            Color::Default,
+            Attr::Default,
+            ((x, y), bottom_right),
+            false,
+        );
+        let (x_, _) = write_string_to_grid(
+            "CPU",
+            grid,
+            Color::Default,
+            Color::Default,
+            Attr::Bold,
+            ((x_, y), bottom_right),
+            false,
+        );
+        write_string_to_grid(
+            &format!(" {:.1}%", fills[0].unwrap_or(0.0) * 100.0),
+            grid,
+            Color::Default,
+            Color::Default,
+            Attr::Default,
+            ((x_, y), bottom_right),
+            false,
+        );
+        let cores = &fills[1..];
+        let rows = (get_y(bottom_right) + 1).saturating_sub(y + 1);
+        for (i, (column, row)) in heatmap_layout(cores.len(), width!(area)).enumerate() {
+            if row >= rows {
+                break;
+            }
+            let cell = &mut grid[(x + column, y + 1 + row)];
+            cell.set_ch(' ');
+            cell.set_bg(heat_color(cores[i].unwrap_or(0.0)));
+        }
+        width!(area)
+    }
+
+    /* Returns width of entire widget */
+    fn draw_cpu_bars(
+        &mut self,
+        grid: &mut CellBuffer,
+        area: Area,
+        fills: Vec<Option<f64>>,
+    ) -> usize {
+        if self.heatmap {
+            return self.draw_cpu_heatmap(grid, area, fills);
+        }
+        if self.vertical {
+            return self.draw_cpu_vbars(grid, area, fills);
+        }
+        let upper_left = upper_left!(area);
+        /* no of bars is no of CPUs along with the total CPU usage  */
+        let (bar_width, widget_width) = cpu_bars_layout(fills.len(), width!(area), self.compact);
+        let height = self.meter_height;
+
+        let mut x_offset = 0;
+        for (i, fill) in fills.into_iter().enumerate() {
+            let label_len = cpu_label_len(i, self.compact);
+            /* labels go on the bottom row of their meter, where it starts filling */
+            let label_y = HEADER_ROWS + (i % MAX_CPU_ROWS) * height + height - 1;
+            let bottom_right = pos_inc(upper_left, (x_offset + bar_width + label_len, label_y));
+            let (mut x, y) = if self.compact {
+                pos_inc(upper_left, (x_offset, label_y))
+            } else if i > 0 {
                 write_string_to_grid(
                     &format!("CPU{}", i),
                     grid,
                     Color::Default,
                     Color::Default,
                     Attr::Bold,
-                    (
-                        pos_inc(upper_left, (x_offset, 2 + (i % MAX_CPU_ROWS))),
-                        bottom_right,
-                    ),
+                    (pos_inc(upper_left, (x_offset, label_y)), bottom_right),
                     false,
                 )
             } else {
@@ -139,10 +476,7 @@ impl KernelMetrics {
                     Color::Default,
                     Color::Default,
                     Attr::Default,
-                    (
-                        pos_inc(upper_left, (x_offset, 2 + i % MAX_CPU_ROWS)),
-                        bottom_right,
-                    ),
+                    (pos_inc(upper_left, (x_offset, label_y)), bottom_right),
                     false,
                 );
                 write_string_to_grid(
@@ -155,31 +489,55 @@ impl KernelMetrics {
                     false,
                 )
             };
-            x += 2;
+            if !self.compact {
+                x += 2;
+            }
 
-            /* Calculate percentages for the cpu usage bar */
-            let busy_length = (cpu_stat.user_time + cpu_stat.system_time)
-                .saturating_sub(self.cpu_stat[i].user_time + self.cpu_stat[i].system_time);
-            let iowait_length = cpu_stat
-                .iowait_time
-                .saturating_sub(self.cpu_stat[i].iowait_time);
-            let bar_length: usize = (((busy_length + iowait_length) as f64
-                / (cpu_stat
-                    .total_time()
-                    .saturating_sub(self.cpu_stat[i].total_time())) as f64)
-                * bar_width as f64) as usize;
+            if height > 1 {
+                draw_vbar(
+                    grid,
+                    ((x, y + 1 - height), (x + bar_width, y)),
+                    fill.unwrap_or(0.0),
+                    None,
+                    self.bar_fill,
+                    self.glyphs,
+                );
+                if (i + 1) % MAX_CPU_ROWS == 0 {
+                    x_offset += bar_width + label_len;
+                }
+                continue;
+            }
+
+            let fill_length = fill.unwrap_or(0.0) * bar_width as f64;
+            let (bar_length, partial_block) = match self.bar_fill {
+                BarFill::Whole => (fill_length as usize, None),
+                BarFill::Eighths => bar_cells(fill_length),
+            };
             if bar_length >= width!(area) {
                 return x_offset;
             }
 
             /* Sometimes you draw the bar */
             let mut _x_offset = 0;
+            let (ch, fg, bg) = bar_cell(self.glyphs, true, false);
             while _x_offset < bar_length {
                 write_string_to_grid(
-                    "▁",
+                    &ch.to_string(),
+                    grid,
+                    fg,
+                    bg,
+                    Attr::Default,
+                    ((x + _x_offset, y), bottom_right),
+                    false,
+                );
+                _x_offset += 1;
+            }
+            if let Some(ch) = partial_block {
+                write_string_to_grid(
+                    &ch.to_string(),
                     grid,
-                    Color::Byte(235),
                     Color::Byte(240),
+                    Color::Byte(235),
                     Attr::Default,
                     ((x + _x_offset, y), bottom_right),
                     false,
@@ -187,12 +545,13 @@ impl KernelMetrics {
                 _x_offset += 1;
             }
             /* and sometimes the bar draws you */
+            let (ch, fg, bg) = bar_cell(self.glyphs, false, false);
             while _x_offset <= bar_width {
                 write_string_to_grid(
-                    "▁",
+                    &ch.to_string(),
                     grid,
-                    Color::Byte(236),
-                    Color::Byte(235),
+                    fg,
+                    bg,
                     Attr::Default,
                     ((x + _x_offset, y), bottom_right),
                     false,
@@ -200,37 +559,32 @@ impl KernelMetrics {
 
                 _x_offset += 1;
             }
-            self.cpu_stat[i] = cpu_stat;
             if (i + 1) % MAX_CPU_ROWS == 0 {
                 x_offset += bar_width + label_len;
             }
         }
 
-        self.boot_time = boot_time;
-        if (self.cpu_stat.len()) % MAX_CPU_ROWS == 0 {
-            x_offset
-        } else {
-            x_offset
-                + bar_width
-                + if self.cpu_stat.len() < 10 {
-                    8
-                } else if self.cpu_stat.len() < 100 {
-                    9
-                } else {
-                    10
-                }
-        }
+        widget_width
     }
 
-    fn draw_ram_bar(&mut self, grid: &mut CellBuffer, area: Area, bars_max: usize) {
+    /* Draw the RAM meter from the upper left corner of `area`, `bars_max` + 1 cells wide */
+    fn draw_ram_bar(
+        &mut self,
+        grid: &mut CellBuffer,
+        area: Area,
+        bars_max: usize,
+        (available, total): (usize, usize),
+    ) {
         let upper_left = upper_left!(area);
         let bottom_right = bottom_right!(area);
         if bars_max == 0 {
-            /* In first draw, we have no cpu data since there is no previous measurement to use
-             * in the calculation so bars_max will be 0 */
+            /* There's no room for the bar */
+            return;
+        }
+        if self.meter_height > 1 {
+            self.draw_tall_ram_bar(grid, area, bars_max, available, total);
             return;
         }
-        let (available, total) = get_mem_info();
 
         /*  available_length == the length the spaces takes up in this case:
          *  |********       | 50%
@@ -248,7 +602,6 @@ impl KernelMetrics {
         /* Put the "RAM XGB/YGB" in the middle of the RAM bar */
         let mem_display_padding = bars_max.saturating_sub(mem_display.len()) / 2;
 
-        let y_offset = 2 + MAX_CPU_ROWS;
         let mut x = 0;
         /* Calculate spillover of mem_display string to available part of the bar in order to
          * paint it differently
@@ -283,18 +636,22 @@ impl KernelMetrics {
                     Color::White,
                     Color::Byte(240),
                     Attr::Default,
-                    (pos_inc(upper_left, (x + 2, y_offset)), bottom_right),
+                    (pos_inc(upper_left, (x, 0)), bottom_right),
                     false,
                 );
                 x += cutoff;
             } else {
+                let (ch, fg, bg) = match self.glyphs {
+                    Some(_) => bar_cell(self.glyphs, x < mem_bar_length, false),
+                    None => bar_cell(None, true, true),
+                };
                 write_string_to_grid(
-                    "█",
+                    &ch.to_string(),
                     grid,
-                    Color::Byte(240),
-                    Color::Byte(235),
+                    fg,
+                    bg,
                     Attr::Default,
-                    (pos_inc(upper_left, (x + 2, y_offset)), bottom_right),
+                    (pos_inc(upper_left, (x, 0)), bottom_right),
                     false,
                 );
                 x += 1;
@@ -307,11 +664,50 @@ impl KernelMetrics {
                 Color::White,
                 Color::Byte(235),
                 Attr::Default,
-                (pos_inc(upper_left, (x + 2, y_offset)), bottom_right),
+                (pos_inc(upper_left, (x, 0)), bottom_right),
                 false,
             );
         }
     }
+
+    /* `draw_ram_bar` for meters of more than one row: the meter fills upwards and the "RAM
+     * XGB/YGB" goes in the middle of its bottom row, over the colors of the meter. */
+    fn draw_tall_ram_bar(
+        &mut self,
+        grid: &mut CellBuffer,
+        area: Area,
+        bars_max: usize,
+        available: usize,
+        total: usize,
+    ) {
+        let upper_left = upper_left!(area);
+        let bottom_right = bottom_right!(area);
+        let top = get_y(upper_left);
+        let bottom = top + self.meter_height - 1;
+        let left = get_x(upper_left);
+        let right = std::cmp::min(left + bars_max, get_x(bottom_right));
+        if bottom > get_y(bottom_right) || left > right {
+            return;
+        }
+        let used = total.saturating_sub(available) as f64 / std::cmp::max(total, 1) as f64;
+        draw_vbar(
+            grid,
+            ((left, top), (right, bottom)),
+            used,
+            None,
+            self.bar_fill,
+            self.glyphs,
+        );
+        let mem_display = format!(
+            "RAM {}/{}",
+            Bytes((total - available) * 1024).as_convenient_string(),
+            Bytes(total * 1024).as_convenient_string()
+        );
+        let padding = (right - left + 1).saturating_sub(mem_display.len()) / 2;
+        for (x, ch) in (left + padding..=right).zip(mem_display.chars()) {
+            grid[(x, bottom)].set_ch(ch).set_fg(Color::White);
+        }
+    }
 }
 
 impl Component for KernelMetrics {
@@ -329,75 +725,82 @@ impl Component for KernelMetrics {
         let bottom_right = bottom_right!(area);
         let total_cols = width!(area);
 
-        dirty_areas.push_back(area);
         if self.dirty {
             clear_area(grid, area);
-            let (x, y) = write_string_to_grid(
-                &self.hostname,
-                grid,
-                Color::Default,
-                Color::Default,
-                Attr::Bold,
-                area,
-                false,
-            );
-            let (x, y) = write_string_to_grid(
-                &self.os_type,
-                grid,
-                Color::Default,
-                Color::Default,
-                Attr::Default,
-                ((x + 2, y), bottom_right),
-                false,
-            );
-            write_string_to_grid(
-                &self.kernel,
-                grid,
-                Color::Default,
-                Color::Default,
-                Attr::Default,
-                ((x + 2, y), bottom_right),
-                false,
-            );
+            self.drawn_meters.clear();
+            self.header.draw_static(grid, area);
             self.dirty = false;
         }
 
-        /* Draw uptime */
-        let mut file = File::open("/proc/uptime").unwrap();
-        self.uptime.clear();
-        file.read_to_string(&mut self.uptime).unwrap();
-        let seconds: usize =
-            f64::from_str(self.uptime.split(" ").next().unwrap()).unwrap() as usize;
-        let days = seconds / (60 * 60 * 24);
-        let hours = seconds / 3600 - days * 24;
-        let mins = seconds / 60 - hours * 60 - days * 24 * 60;
-        let seconds = seconds % 60;
-        let uptime = if days > 0 {
-            format!(
-                "uptime: {} days, {:02}:{:02}:{:02}",
-                days, hours, mins, seconds
-            )
-        } else {
-            format!("uptime: {:02}:{:02}:{:02}", hours, mins, seconds)
-        };
-
-        write_string_to_grid(
-            &uptime,
-            grid,
-            Color::Default,
-            Color::Default,
-            Attr::Default,
-            (
-                (get_x(bottom_right) - uptime.len(), get_y(upper_left)),
-                bottom_right,
-            ),
-            false,
-        );
+        /* Draw the time and uptime */
+        let uptime = self.proc_source.read_to_string("uptime").unwrap();
+        let seconds: usize = f64::from_str(uptime.split(" ").next().unwrap()).unwrap() as usize;
+        if let (true, Some(ref mut self_usage)) = (tick, self.self_usage.as_mut()) {
+            self_usage.update(std::time::Instant::now());
+        }
+        let self_usage = self.self_usage.as_ref().and_then(SelfUsage::text);
+        self.header
+            .draw_clock(grid, area, seconds, self_usage.as_deref());
 
-        if !tick {
+        if self.force_redraw {
+            self.force_redraw = false;
+            self.drawn_meters.clear();
+        } else if !tick {
+            dirty_areas.push_back(area);
             return;
         }
-        let old_cpu_stat = self.cpu_stat[0];
+        let old_cpu_stat = self.cpu_stat.first().copied();
+        let fills = self.cpu_fills();
+        let cpus = self
+            .limits
+            .as_ref()
+            .and_then(CgroupLimits::cpu_count)
+            .unwrap_or_else(|| self.cpu_stat.len().saturating_sub(1));
+        if self.load_banner.is_some() || self.pid_warning.is_some() {
+            /* on the row between the header and the meters, an overload before running out of
+             * PIDs */
+            let mut text = None;
+            if let Some(ref mut banner) = self.load_banner {
+                let load = get_loadavg(&self.proc_source)[0].parse().unwrap_or(0.0);
+                if banner.update(load, cpus) {
+                    text = Some(overload_warning(load, cpus));
+                }
+            }
+            if let (None, Some(threshold)) = (&text, self.pid_warning) {
+                text = PidUsage::read(&self.proc_source)
+                    .filter(|usage| usage.is_high(threshold))
+                    .map(|usage| usage.warning());
+            }
+            let row = pos_inc(upper_left, (0, 1));
+            draw_banner(grid, (row, bottom_right), text.as_deref());
+            dirty_areas.push_back((row, (get_x(bottom_right), get_y(row))));
+        }
+        let mem_info = self
+            .limits
+            .as_ref()
+            .and_then(CgroupLimits::mem_info)
+            .unwrap_or_else(|| get_mem_info(&self.proc_source).unwrap());
+        let swap_info = if self.swap_meter {
+            Some(SwapInfo::read(&self.proc_source))
+        } else {
+            None
+        };
+        if let Some(epsilon) = self.meter_epsilon {
+            let (available, total) = mem_info;
+            let meters = fills
+                .iter()
+                .map(|fill| fill.unwrap_or(0.0))
+                .chain(Some(1.0 - available as f64 / total as f64))
+                .chain(swap_info.map(|swap| swap.fraction().unwrap_or(0.0)))
+                .collect::<Vec<f64>>();
+            if !meters_changed(&self.drawn_meters, &meters, epsilon) {
+                /* only the uptime changed */
+                dirty_areas.push_back((upper_left, (get_x(bottom_right), get_y(upper_left))));
+                return;
+            }
+            self.drawn_meters = meters;
+        }
+        dirty_areas.push_back(area);
 
         /* Draw CPU usage bars */
 
@@ -407,19 +810,50 @@ impl Component for KernelMetrics {
             grid,
             (
                 pos_inc(upper_left, (2, 0)),
-                pos_inc(upper_left, (bars_max + 1, MAX_CPU_ROWS + 1)),
+                pos_inc(
+                    upper_left,
+                    (
+                        bars_max + 1,
+                        HEADER_ROWS + MAX_CPU_ROWS * self.meter_height - 1,
+                    ),
+                ),
             ),
+            fills,
         );
         /* Draw RAM usage bar */
 
-        self.draw_ram_bar(grid, area, cpu_widget_width.saturating_sub(2));
+        self.draw_ram_bar(
+            grid,
+            (
+                pos_inc(
+                    upper_left,
+                    (2, HEADER_ROWS + MAX_CPU_ROWS * self.meter_height),
+                ),
+                bottom_right,
+            ),
+            cpu_widget_width.saturating_sub(2),
+            mem_info,
+        );
+        if let Some(swap) = swap_info {
+            /* as wide as the RAM bar, on the rows below it */
+            let top = get_y(upper_left) + HEADER_ROWS + (MAX_CPU_ROWS + 1) * self.meter_height;
+            let bottom = top + self.meter_height - 1;
+            let left = get_x(upper_left) + 2;
+            let right = std::cmp::min(
+                left + cpu_widget_width.saturating_sub(2),
+                get_x(bottom_right),
+            );
+            if bottom <= get_y(bottom_right) && cpu_widget_width > 2 {
+                draw_swap_meter(grid, ((left, top), (right, bottom)), swap, self.glyphs);
+            }
+        }
         /* Various values table */
         /* max width of cpu bar area */
         let bars_max = (0.6 * total_cols as f32) as usize;
 
         /* CPU Times */
         let mut cpu_column_width = "CPU".len();
-        let upper_left = pos_inc(upper_left, (bars_max + 5, 2));
+        let upper_left = pos_inc(upper_left, (bars_max + if self.compact { 3 } else { 5 }, 2));
         clear_area(grid, (upper_left, bottom_right));
         if get_x(upper_left) >= get_x(bottom_right) {
             return;
@@ -434,9 +868,14 @@ impl Component for KernelMetrics {
             false,
         );
 
-        for (i, (tag, s, fg_color, bg_color)) in get_cpu_times(&old_cpu_stat, &self.cpu_stat[0])
-            .into_iter()
-            .enumerate()
+        for (i, (tag, s, fg_color, bg_color)) in get_cpu_times(
+            old_cpu_stat.as_ref(),
+            &self.cpu_stat[0],
+            &self.theme,
+            self.percent_format,
+        )
+        .into_iter()
+        .enumerate()
         {
             let (x, y) = write_string_to_grid(
                 tag,
@@ -464,7 +903,10 @@ impl Component for KernelMetrics {
         }
 
         /* Load average */
-        let upper_left = pos_inc(upper_left, (cpu_column_width + 3, 0));
+        let upper_left = pos_inc(
+            upper_left,
+            (cpu_column_width + if self.compact { 1 } else { 3 }, 0),
+        );
         if get_x(upper_left) >= get_x(bottom_right) {
             return;
         }
@@ -477,8 +919,13 @@ impl Component for KernelMetrics {
             (upper_left, bottom_right),
             false,
         );
-        let loadavgs = get_loadavg();
-        for (i, avg) in loadavgs.into_iter().enumerate() {
+        let loadavgs = get_loadavg(&self.proc_source);
+        for (i, avg) in loadavgs.iter().enumerate() {
+            let fg = self
+                .theme
+                .load_thresholds
+                .zip(avg.parse::<f64>().ok())
+                .map_or(Color::Default, |(thresholds, load)| thresholds.color(load));
             write_string_to_grid(
                 &format!(
                     "{}  {}",
@@ -490,7 +937,7 @@ impl Component for KernelMetrics {
                     avg
                 ),
                 grid,
-                Color::Default,
+                fg,
                 Color::Default,
                 Attr::Default,
                 (pos_inc(upper_left, (0, i + 1)), bottom_right),
@@ -504,10 +951,16 @@ impl Component for KernelMetrics {
     }
 
     fn process_event(&mut self, event: &mut UIEvent) {
+        let map = &self.get_shortcuts()[""];
         match event {
             UIEvent::Resize => {
                 self.dirty = true;
             }
+            UIEvent::Input(k) if *k == map["toggle compact meters"] => {
+                self.compact = !self.compact;
+                self.dirty = true;
+                self.force_redraw = true;
+            }
             _ => {}
         }
     }
@@ -519,40 +972,165 @@ impl Component for KernelMetrics {
     fn set_dirty(&mut self) {
         self.dirty = true;
     }
+
+    fn get_shortcuts(&self) -> ShortcutMaps {
+        let mut map: ShortcutMap = Default::default();
+        map.insert("toggle compact meters", Key::Alt('c'));
+        let mut ret: ShortcutMaps = Default::default();
+        ret.insert("".to_string(), map);
+        ret
+    }
+
+    fn title(&self) -> Option<String> {
+        Some("System".to_string())
+    }
 }
 
-fn get_mem_info() -> (usize, usize) {
-    let mut file = File::open("/proc/meminfo").unwrap();
-    let mut res = String::with_capacity(2048);
-    file.read_to_string(&mut res).unwrap();
-    let mut lines_iter = res.lines();
-    let mem_total = usize::from_str(
-        lines_iter
-            .next()
-            .unwrap()
-            .split_whitespace()
-            .skip(1)
-            .next()
-            .unwrap(),
-    )
-    .unwrap();
-    let mem_available = usize::from_str(
-        lines_iter
-            .next()
-            .unwrap()
-            .split_whitespace()
-            .skip(1)
-            .next()
-            .unwrap(),
+/* Horizontal space taken by the label in front of the bar of cpu `i`, plus the padding up to the
+ * next column of bars */
+fn cpu_label_len(i: usize, compact: bool) -> usize {
+    if compact {
+        /* no label, the bar and a space */
+        2
+    } else if i < 10 {
+        /* label will be " CPU0  " */
+        8
+    } else if i < 100 {
+        /* label will be " CPU32  " */
+        9
+    } else {
+        /* label will be " CPU128  " */
+        10
+    }
+}
+
+/* The color of a heatmap cell of a cpu busy for the fraction `fill` of the time */
+fn heat_color(fill: f64) -> Color {
+    HEAT_COLD.blend(HEAT_HOT, fill)
+}
+
+/* The column and row of the heatmap cell of each of `cores` cpus, filling rows of `width` cells */
+fn heatmap_layout(cores: usize, width: usize) -> impl Iterator<Item = (usize, usize)> {
+    let width = width.max(1);
+    (0..cores).map(move |i| (i % width, i / width))
+}
+
+/* Width of each bar and of the whole widget when `cpu_no` bars (the total included) are laid out
+ * in `total_cols`, MAX_CPU_ROWS to a column */
+fn cpu_bars_layout(cpu_no: usize, total_cols: usize, compact: bool) -> (usize, usize) {
+    /* Calculate how much horizontal space the labels (ie CPU0, CPU1, CPU2) take in order to
+     * distribute the remainder for each column, specifically the bars */
+    let mut cpu_label_space = 0;
+    let mut cpu_bar_columns = 0;
+    let mut i = 0;
+    while i < cpu_no {
+        cpu_label_space += cpu_label_len(i, compact) - 1;
+        /* each column holds MAX_CPU_ROWS */
+        i += MAX_CPU_ROWS;
+        cpu_bar_columns += 1;
+    }
+    /* max width of each cpu bar */
+    let bar_width = if cpu_no < MAX_CPU_ROWS {
+        total_cols
+    } else {
+        total_cols.saturating_sub(cpu_label_space) / cpu_bar_columns
+    };
+
+    let mut widget_width = (1..=cpu_no / MAX_CPU_ROWS)
+        .map(|column| bar_width + cpu_label_len(column * MAX_CPU_ROWS - 1, compact))
+        .sum();
+    if !cpu_no.is_multiple_of(MAX_CPU_ROWS) {
+        widget_width += bar_width + cpu_label_len(cpu_no, compact);
+    }
+    (bar_width, widget_width)
+}
+
+/* Nearest partial block glyph for a cell that is `remainder` full, where `remainder` is in [0, 1) */
+fn eighth_block(remainder: f64) -> Option<char> {
+    match (remainder * 8.0).round() as usize {
+        0 | 8 => None,
+        n => Some(PARTIAL_BLOCKS[n - 1]),
+    }
+}
+
+/* Split a bar `length` in cells into the number of whole cells and the glyph of the partially
+ * filled last cell, if any */
+fn bar_cells(length: f64) -> (usize, Option<char>) {
+    let eighths = (length.max(0.0) * 8.0).round() as usize;
+    (eighths / 8, eighth_block((eighths % 8) as f64 / 8.0))
+}
+
+/* Split a vertical bar `length` in cells into the number of whole cells and the glyph of the
+ * partially filled top cell, if any */
+fn vbar_cells(length: f64) -> (usize, Option<char>) {
+    let (cells, partial) = bar_cells(length);
+    (
+        cells,
+        partial.map(|ch| LOWER_BLOCKS[PARTIAL_BLOCKS.iter().position(|&p| p == ch).unwrap()]),
     )
-    .unwrap();
-    (mem_available, mem_total)
 }
 
-fn get_loadavg() -> [String; 3] {
-    let mut file = File::open("/proc/loadavg").unwrap();
-    let mut res = String::with_capacity(2048);
-    file.read_to_string(&mut res).unwrap();
+/* What each of the `height` rows of a meter that is `fill` full shows, from the bottom row up. The
+ * fill is spread over the rows and, with `BarFill::Eighths`, the row it ends in is partially
+ * filled. */
+fn meter_rows(fill: f64, height: usize, bar_fill: BarFill) -> Vec<MeterRow> {
+    let fill_length = fill.clamp(0.0, 1.0) * height as f64;
+    let (full, partial) = match bar_fill {
+        BarFill::Whole => (fill_length as usize, None),
+        BarFill::Eighths => vbar_cells(fill_length),
+    };
+    (0..height)
+        .map(|row| match partial {
+            _ if row < full => MeterRow::Full,
+            Some(ch) if row == full => MeterRow::Partial(ch),
+            _ => MeterRow::Empty,
+        })
+        .collect()
+}
+
+/* Draw a meter that is `fill` full, `fill` being in [0, 1], growing from the bottom of `area`
+ * upwards. If there's a `label`, it goes on the last row of `area`, below the meter. */
+fn draw_vbar(
+    grid: &mut CellBuffer,
+    area: Area,
+    fill: f64,
+    label: Option<&str>,
+    bar_fill: BarFill,
+    glyphs: Option<BarGlyphs>,
+) {
+    let upper_left = upper_left!(area);
+    let mut bottom_right = bottom_right!(area);
+    if let Some(label) = label {
+        write_string_to_grid(
+            label,
+            grid,
+            Color::Default,
+            Color::Default,
+            Attr::Bold,
+            (set_y(upper_left, get_y(bottom_right)), bottom_right),
+            false,
+        );
+        if get_y(bottom_right) == get_y(upper_left) {
+            return;
+        }
+        bottom_right = pos_dec(bottom_right, (0, 1));
+    }
+    let height = get_y(bottom_right) - get_y(upper_left) + 1;
+    for (row, meter_row) in meter_rows(fill, height, bar_fill).into_iter().enumerate() {
+        let (ch, fg, bg) = match meter_row {
+            MeterRow::Partial(ch) => (ch, Color::Byte(240), Color::Byte(235)),
+            MeterRow::Full => bar_cell(glyphs, true, true),
+            MeterRow::Empty => bar_cell(glyphs, false, true),
+        };
+        let y = get_y(bottom_right) - row;
+        for x in get_x(upper_left)..=get_x(bottom_right) {
+            grid[(x, y)].set_ch(ch).set_fg(fg).set_bg(bg);
+        }
+    }
+}
+
+fn get_loadavg(source: &ProcSource) -> [String; 3] {
+    let res = source.read_to_string("loadavg").unwrap();
     let mut mut_value_iter = res.split_whitespace();
     let avg_1 = mut_value_iter.next().unwrap().to_string();
     let avg_5 = mut_value_iter.next().unwrap().to_string();
@@ -560,41 +1138,63 @@ fn get_loadavg() -> [String; 3] {
     [avg_1, avg_5, avg_15]
 }
 
+/* Whether any of the `meters` moved by more than `epsilon` since they were `drawn`. Meters that
+ * weren't drawn yet always count as changed. */
+fn meters_changed(drawn: &[f64], meters: &[f64], epsilon: f64) -> bool {
+    drawn.len() != meters.len()
+        || drawn
+            .iter()
+            .zip(meters)
+            .any(|(drawn, meter)| (drawn - meter).abs() > epsilon)
+}
+
+/* Percentages of each kind of cpu time since `old_cpu_stat`, shown as `--` without one */
 fn get_cpu_times(
-    old_cpu_stat: &Stat,
+    old_cpu_stat: Option<&Stat>,
     cpu_stat: &Stat,
+    theme: &Theme,
+    percent_format: Option<PercentFormat>,
 ) -> Vec<(&'static str, String, Color, Color)> {
     let mut ret = Vec::new();
+    let elapsed = old_cpu_stat.map_or(0, |old_cpu_stat| {
+        cpu_stat
+            .total_time()
+            .saturating_sub(old_cpu_stat.total_time())
+    });
 
     macro_rules! val {
         ($tag:literal, $field:tt) => {
-            let percent = (cpu_stat.$field.saturating_sub(old_cpu_stat.$field)) as f64
-                / (cpu_stat
-                    .total_time()
-                    .saturating_sub(old_cpu_stat.total_time())) as f64;
-            let s = format!("{:.1}%", percent * 100.0);
-            ret.push((
-                $tag,
-                s,
-                if percent < 0.15 {
-                    Color::Default
-                } else if percent < 0.50 {
-                    Color::Default
-                } else {
-                    Color::White
-                },
-                if percent < 0.15 {
-                    Color::Default
-                } else if percent < 0.50 {
-                    Color::Byte(70)
-                } else if $tag != "idle%  " {
-                    Color::Red
-                } else {
-                    Color::Default
+            ret.push(
+                match rate(
+                    old_cpu_stat.map(|old_cpu_stat| old_cpu_stat.$field),
+                    cpu_stat.$field,
+                    elapsed,
+                ) {
+                    Some(percent) => (
+                        $tag,
+                        match percent_format {
+                            Some(percent_format) => {
+                                percent_format.format((percent * 10000.0).round() as usize)
+                            }
+                            None => format!("{:.1}%", percent * 100.0),
+                        },
+                        if percent < 0.50 {
+                            Color::Default
+                        } else {
+                            Color::White
+                        },
+                        if percent >= 0.50 && $tag == "idle%  " {
+                            /* Being mostly idle is not worth highlighting */
+                            theme.meter_low
+                        } else {
+                            theme.meter(percent)
+                        },
+                    ),
+                    None => ($tag, "--".to_string(), Color::Default, Color::Default),
                 },
-            ));
+            );
         };
-    };
+    }
 
     /* user % */
     val!("user%  ", user_time);
@@ -609,3 +1209,348 @@ fn get_cpu_times(
 
     ret
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_heatmap() {
+        assert_eq!(heat_color(0.0), HEAT_COLD);
+        assert_eq!(heat_color(1.0), HEAT_HOT);
+        assert_eq!(heat_color(2.0), HEAT_HOT);
+        let warm = heat_color(0.5);
+        assert!(warm != HEAT_COLD && warm != HEAT_HOT, "{:?}", warm);
+        assert_eq!(heat_color(0.5), HEAT_COLD.blend(HEAT_HOT, 0.5));
+
+        assert_eq!(
+            heatmap_layout(10, 4).collect::<Vec<_>>(),
+            [
+                (0, 0),
+                (1, 0),
+                (2, 0),
+                (3, 0),
+                (0, 1),
+                (1, 1),
+                (2, 1),
+                (3, 1),
+                (0, 2),
+                (1, 2)
+            ]
+        );
+        assert_eq!(heatmap_layout(3, 8).last(), Some((2, 0)));
+        assert_eq!(heatmap_layout(2, 0).collect::<Vec<_>>(), [(0, 0), (0, 1)]);
+        assert_eq!(heatmap_layout(0, 8).count(), 0);
+    }
+
+    #[test]
+    fn test_eighth_block() {
+        assert_eq!(eighth_block(0.0), None);
+        assert_eq!(eighth_block(0.05), None);
+        assert_eq!(eighth_block(0.125), Some('▏'));
+        assert_eq!(eighth_block(0.3), Some('▎'));
+        assert_eq!(eighth_block(0.5), Some('▌'));
+        assert_eq!(eighth_block(0.875), Some('▉'));
+        assert_eq!(eighth_block(0.99), None);
+    }
+
+    #[test]
+    fn test_bar_cells() {
+        assert_eq!(bar_cells(0.0), (0, None));
+        assert_eq!(bar_cells(f64::NAN), (0, None));
+        assert_eq!(bar_cells(0.5), (0, Some('▌')));
+        assert_eq!(bar_cells(3.0), (3, None));
+        assert_eq!(bar_cells(3.25), (3, Some('▎')));
+        assert_eq!(bar_cells(3.99), (4, None));
+        assert_eq!(bar_cells(10.7), (10, Some('▊')));
+    }
+
+    #[test]
+    fn test_get_cpu_times() {
+        let stat = |user_time, idle_time| Stat {
+            user_time,
+            nice_time: 0,
+            system_time: 0,
+            idle_time,
+            iowait_time: 0,
+            irq: 0,
+            soft_irq: 0,
+            steal: 0,
+            guest: 0,
+            guest_nice: 0,
+        };
+        let theme = Theme::default();
+        let values = |times: Vec<(&str, String, Color, Color)>| {
+            times
+                .into_iter()
+                .map(|(_, s, _, _)| s)
+                .collect::<Vec<String>>()
+        };
+        /* the first sample is only a baseline */
+        assert_eq!(
+            values(get_cpu_times(None, &stat(10, 90), &theme, None)),
+            vec!["--"; 5]
+        );
+        assert_eq!(
+            values(get_cpu_times(
+                Some(&stat(10, 90)),
+                &stat(10, 90),
+                &theme,
+                None
+            )),
+            vec!["--"; 5]
+        );
+        assert_eq!(
+            values(get_cpu_times(
+                Some(&stat(10, 90)),
+                &stat(35, 165),
+                &theme,
+                None
+            )),
+            vec!["25.0%", "0.0%", "0.0%", "75.0%", "0.0%"]
+        );
+        let percent_format = PercentFormat {
+            precise_below: 100,
+            decimals: 2,
+        };
+        assert_eq!(
+            values(get_cpu_times(
+                Some(&stat(10, 90)),
+                &stat(35, 165),
+                &theme,
+                Some(percent_format)
+            )),
+            vec!["25%", "0.00%", "0.00%", "75%", "0.00%"]
+        );
+    }
+
+    #[test]
+    fn test_cpu_bars_layout() {
+        /* the total and 3 cpus fit in one column */
+        assert_eq!(cpu_bars_layout(4, 60, false), (60, 68));
+        assert_eq!(cpu_bars_layout(4, 60, true), (60, 62));
+        /* the total and 16 cpus, in four columns */
+        assert_eq!(cpu_bars_layout(17, 100, false), (17, 102));
+        assert_eq!(cpu_bars_layout(17, 100, true), (24, 104));
+        for cpu_no in 1..200 {
+            for total_cols in &[20, 80, 200] {
+                let (normal_bar, normal) = cpu_bars_layout(cpu_no, *total_cols, false);
+                let (compact_bar, compact) = cpu_bars_layout(cpu_no, *total_cols, true);
+                assert!(compact_bar >= normal_bar);
+                /* the labels of every column are gone, so with same bar width the widget is
+                 * narrower */
+                let columns = cpu_no.div_ceil(MAX_CPU_ROWS);
+                assert!(
+                    compact - columns * compact_bar < normal - columns * normal_bar,
+                    "{} {}",
+                    cpu_no,
+                    total_cols
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_vbar_cells() {
+        /* a four row meter */
+        assert_eq!(vbar_cells(0.0 * 4.0), (0, None));
+        assert_eq!(vbar_cells(0.1 * 4.0), (0, Some('▃')));
+        assert_eq!(vbar_cells(0.25 * 4.0), (1, None));
+        assert_eq!(vbar_cells(0.4 * 4.0), (1, Some('▅')));
+        assert_eq!(vbar_cells(0.53 * 4.0), (2, Some('▁')));
+        assert_eq!(vbar_cells(0.97 * 4.0), (3, Some('▇')));
+        assert_eq!(vbar_cells(1.0 * 4.0), (4, None));
+    }
+
+    #[test]
+    fn test_draw_vbar() {
+        let mut grid = CellBuffer::new(3, 5, Cell::default());
+        /* the meter has four rows, 0.6 of it is 2.4 rows */
+        draw_vbar(
+            &mut grid,
+            ((0, 0), (1, 4)),
+            0.6,
+            Some("12"),
+            BarFill::Eighths,
+            None,
+        );
+        let column = |grid: &CellBuffer, x| (0..5).map(|y| grid[(x, y)].ch()).collect::<String>();
+        assert_eq!(column(&grid, 0), "▁▃██1");
+        assert_eq!(column(&grid, 1), "▁▃██2");
+        assert_eq!(column(&grid, 2), "     ");
+
+        draw_vbar(&mut grid, ((2, 0), (2, 4)), 2.0, None, BarFill::Whole, None);
+        assert_eq!(column(&grid, 2), "█████");
+    }
+
+    #[test]
+    fn test_meter_rows() {
+        use MeterRow::*;
+        assert_eq!(meter_rows(0.0, 3, BarFill::Eighths), [Empty, Empty, Empty]);
+        assert_eq!(meter_rows(1.0, 3, BarFill::Eighths), [Full, Full, Full]);
+        /* 0.5 of three rows is one and a half rows */
+        assert_eq!(
+            meter_rows(0.5, 3, BarFill::Eighths),
+            [Full, Partial('▄'), Empty]
+        );
+        assert_eq!(meter_rows(0.5, 3, BarFill::Whole), [Full, Empty, Empty]);
+        /* 0.6 of four rows is 2.4 rows, the boundary row is three eighths full */
+        assert_eq!(
+            meter_rows(0.6, 4, BarFill::Eighths),
+            [Full, Full, Partial('▃'), Empty]
+        );
+        assert_eq!(meter_rows(0.25, 2, BarFill::Eighths), [Partial('▄'), Empty]);
+        /* fills past the ends are clamped */
+        assert_eq!(meter_rows(1.5, 2, BarFill::Eighths), [Full, Full]);
+        assert_eq!(meter_rows(-0.5, 2, BarFill::Whole), [Empty, Empty]);
+        assert_eq!(meter_rows(0.9, 1, BarFill::Eighths), [Partial('▇')]);
+        assert!(meter_rows(0.5, 0, BarFill::Eighths).is_empty());
+    }
+
+    #[test]
+    fn test_meter_epsilon() {
+        assert!(meters_changed(&[], &[0.0], 0.1));
+        assert!(!meters_changed(&[0.5, 0.2], &[0.55, 0.2], 0.1));
+        assert!(meters_changed(&[0.5, 0.2], &[0.5, 0.35], 0.1));
+        assert!(meters_changed(&[0.5, 0.2], &[0.5], 0.1));
+
+        let dir =
+            std::env::temp_dir().join(format!("bb-test-meter-epsilon-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("sys/kernel")).unwrap();
+        for (file, contents) in &[
+            ("sys/kernel/hostname", "host"),
+            ("sys/kernel/osrelease", "5.4.0"),
+            ("sys/kernel/ostype", "Linux"),
+            ("uptime", "100.00 200.00"),
+            ("loadavg", "0.00 0.00 0.00 1/1 1"),
+            /* three quarters in use, so that the RAM bar has room for its label */
+            ("meminfo", "MemTotal: 16384 kB\nMemAvailable: 4096 kB\n"),
+        ] {
+            std::fs::write(dir.join(file), contents).unwrap();
+        }
+        let set_cpu = |busy: usize, idle: usize| {
+            std::fs::write(
+                dir.join("stat"),
+                format!(
+                    "cpu  {} 0 0 {} 0 0 0 0 0 0\ncpu0 {} 0 0 {} 0 0 0 0 0 0\nbtime 1\n",
+                    busy, idle, busy, idle
+                ),
+            )
+            .unwrap();
+        };
+        /* the header counts the cores when it's created */
+        set_cpu(0, 0);
+        let mut kernel = KernelMetrics::new(KernelMetricsOptions {
+            meter_height: 1,
+            proc_source: ProcSource::new(&dir).unwrap(),
+            meter_epsilon: Some(0.01),
+            ..Default::default()
+        });
+        let mut grid = CellBuffer::new(80, 8, Cell::default());
+        let area = ((0, 0), (79, 7));
+        let mut draw = |kernel: &mut KernelMetrics, tick| {
+            let mut dirty_areas = VecDeque::new();
+            kernel.draw(&mut grid, area, &mut dirty_areas, tick);
+            dirty_areas
+        };
+        let header = ((0, 0), (79, 0));
+
+        set_cpu(0, 0);
+        assert_eq!(draw(&mut kernel, true), [area]);
+        /* 0.1% busy since the baseline, the meters still show 0% */
+        set_cpu(1, 999);
+        assert_eq!(draw(&mut kernel, true), [header]);
+        /* 50% busy */
+        set_cpu(501, 1499);
+        assert_eq!(draw(&mut kernel, true), [area]);
+        set_cpu(1004, 1996);
+        assert_eq!(draw(&mut kernel, true), [header]);
+        /* all of it is drawn again after a resize */
+        kernel.set_dirty();
+        set_cpu(1506, 2494);
+        assert_eq!(draw(&mut kernel, true), [area]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_render_ram_bar() {
+        let mut kernel = KernelMetrics::new(KernelMetricsOptions {
+            meter_height: 1,
+            ..Default::default()
+        });
+        /* all of 4 GiB in use, with the label in the middle of a meter wide enough for it */
+        let grid = render_with((30, 1), |grid, area| {
+            kernel.draw_ram_bar(grid, area, 29, (0, 4 * 1024 * 1024))
+        });
+        assert_eq!(grid_rows(&grid), ["████RAM 4.00 GiB/4.00 GiB█████"]);
+        assert_eq!(
+            (grid[(0, 0)].fg(), grid[(0, 0)].bg()),
+            (Color::Byte(240), Color::Byte(235))
+        );
+        assert_eq!(
+            (grid[(4, 0)].fg(), grid[(4, 0)].bg()),
+            (Color::White, Color::Byte(240))
+        );
+        assert!(frame(&grid).starts_with("\x1B[1;1H\x1B[48;5;235m\x1B[38;5;240m█"));
+    }
+
+    #[test]
+    fn test_meter_height() {
+        let kernel = |meter_height| {
+            KernelMetrics::new(KernelMetricsOptions {
+                meter_height,
+                ..Default::default()
+            })
+        };
+        /* the header, five cpu rows and the RAM row */
+        assert_eq!(kernel(1).height(), 8);
+        assert_eq!(kernel(3).height(), 2 + 6 * 3);
+        assert_eq!(kernel(0).height(), 8);
+    }
+
+    #[test]
+    fn test_bar_glyphs() {
+        assert_eq!(BarGlyphs::parse("ascii"), Some(BarGlyphs::ASCII));
+        assert_eq!(BarGlyphs::parse("block"), Some(BarGlyphs::BLOCK));
+        assert_eq!(
+            BarGlyphs::parse("=-"),
+            Some(BarGlyphs {
+                fill: '=',
+                empty: '-',
+                eighths: false
+            })
+        );
+        assert_eq!(BarGlyphs::parse("="), None);
+        assert_eq!(BarGlyphs::parse("=-+"), None);
+        for name in BAR_GLYPH_SETS {
+            let glyphs = BarGlyphs::parse(name).unwrap();
+            assert_eq!(glyphs.or_ascii(), glyphs, "{}", name);
+        }
+        /* fullwidth and CJK characters take up two cells */
+        assert_eq!(
+            BarGlyphs::parse("＃.").unwrap().or_ascii(),
+            BarGlyphs::ASCII
+        );
+        assert_eq!(
+            BarGlyphs::parse("中 ").unwrap().or_ascii(),
+            BarGlyphs::ASCII
+        );
+
+        /* a custom set is drawn instead of the block glyphs, one cell per glyph */
+        let mut grid = CellBuffer::new(2, 4, Cell::default());
+        let glyphs = BarGlyphs::parse("=-").unwrap();
+        draw_vbar(
+            &mut grid,
+            ((0, 0), (1, 3)),
+            0.5,
+            None,
+            BarFill::Whole,
+            Some(glyphs),
+        );
+        for x in 0..2 {
+            let column = (0..4).map(|y| grid[(x, y)].ch()).collect::<String>();
+            assert_eq!(column, "--==");
+            assert!(column.chars().all(|ch| wcwidth(u32::from(ch)) == Some(1)));
+        }
+    }
+}
@@ -0,0 +1,345 @@
+/*
+ * bb
+ *
+ * Copyright 2019 Manos Pitsidianakis
+ *
+ * This file is part of bb.
+ *
+ * bb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * bb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with bb. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+/*! Whether bb runs in a container, which `--container` controls, and the limits of its cgroup.
+
+procfs isn't namespaced for most of what the meters show, so in a container they're those of the
+whole host. The header says when bb looks like it's in one: Docker leaves a `/.dockerenv` file,
+Podman a `/run/.containerenv`, and otherwise the cgroup of bb itself may be named after a pod or a
+container id. With a cgroup namespace the cgroup is `/` whatever the runtime, so the files come
+first.
+
+With `--container limits`, the RAM meter is scoped to the `memory.max` of bb's cgroup v2 and the
+load warning to its `cpu.max`, `QUOTA PERIOD` in microseconds, eg. `150000 100000` for one and a
+half cpus. Either is `max` without a limit.
+*/
+
+use super::*;
+use std::path::{Path, PathBuf};
+
+/// Names accepted by `ContainerMode::parse`.
+pub const CONTAINER_MODES: &[&str] = &["auto", "off", "limits"];
+
+/* Runtimes by what the cgroup of a process in one of their containers is named, see
+ * `detect_runtime` */
+const CGROUP_RUNTIMES: &[(&str, &str)] = &[
+    ("docker", "docker"),
+    ("libpod", "podman"),
+    ("crio", "cri-o"),
+    ("containerd", "containerd"),
+];
+
+/// What `--container` does.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub enum ContainerMode {
+    /// Say in the header that bb runs in a container, if it looks like it does.
+    #[default]
+    Auto,
+    /// Don't look.
+    Off,
+    /// Also scope the RAM meter and the load warning to the limits of bb's cgroup.
+    Limits,
+}
+
+impl ContainerMode {
+    pub fn parse(name: &str) -> Option<ContainerMode> {
+        match name {
+            "auto" => Some(ContainerMode::Auto),
+            "off" => Some(ContainerMode::Off),
+            "limits" => Some(ContainerMode::Limits),
+            _ => None,
+        }
+    }
+}
+
+/// The container runtime a process runs under, given the filesystem `root` it sees and the
+/// contents of its `/proc/[pid]/cgroup`, or `None` if it doesn't look like it's in a container.
+pub fn detect_runtime(root: &Path, cgroup: &str) -> Option<&'static str> {
+    if root.join(".dockerenv").exists() {
+        return Some("docker");
+    }
+    if root.join("run/.containerenv").exists() {
+        return Some("podman");
+    }
+    let path = cgroup_path(cgroup);
+    if path.contains("kubepods") {
+        return Some("kubernetes");
+    }
+    if path
+        .split('/')
+        .any(|component| component.starts_with("lxc"))
+    {
+        return Some("lxc");
+    }
+    container_id(path)?;
+    Some(
+        CGROUP_RUNTIMES
+            .iter()
+            .find(|(hint, _)| path.contains(hint))
+            .map_or("container", |(_, runtime)| runtime),
+    )
+}
+
+/// A `memory.max` in bytes, `None` without a limit.
+pub fn parse_memory_max(contents: &str) -> Option<usize> {
+    contents.trim().parse().ok()
+}
+
+/// A `cpu.max` in cpus, `None` without a limit.
+pub fn parse_cpu_max(contents: &str) -> Option<f64> {
+    let mut fields = contents.split_whitespace();
+    let quota = fields.next()?.parse::<f64>().ok()?;
+    let period = fields
+        .next()
+        .map_or(Some(100_000.0), |period| period.parse().ok())?;
+    if quota > 0.0 && period > 0.0 {
+        Some(quota / period)
+    } else {
+        None
+    }
+}
+
+/// The limits of a cgroup v2.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CgroupLimits {
+    /* the directory of the cgroup, which `memory.current` is read from */
+    dir: PathBuf,
+    pub cpus: Option<f64>,
+    /// Memory in bytes.
+    pub memory: Option<usize>,
+}
+
+impl CgroupLimits {
+    /// The limits of the cgroup at `path` in the hierarchy mounted at `cgroup_root`, eg.
+    /// `/sys/fs/cgroup`. Files that can't be read, eg. in cgroup v1, are no limit.
+    pub fn read(cgroup_root: &Path, path: &str) -> CgroupLimits {
+        let dir = cgroup_root.join(path.trim_start_matches('/'));
+        let read = |file| std::fs::read_to_string(dir.join(file)).ok();
+        CgroupLimits {
+            cpus: read("cpu.max").as_deref().and_then(parse_cpu_max),
+            memory: read("memory.max").as_deref().and_then(parse_memory_max),
+            dir,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cpus.is_none() && self.memory.is_none()
+    }
+
+    /// The limits, eg. `1.5 cpus and 512.00 MiB`, or `None` without any.
+    pub fn describe(&self) -> Option<String> {
+        let cpus = self.cpus.map(|cpus| {
+            format!(
+                "{} cpu{}",
+                (cpus * 100.0).round() / 100.0,
+                if cpus == 1.0 { "" } else { "s" }
+            )
+        });
+        let memory = self
+            .memory
+            .map(|memory| Bytes(memory).as_convenient_string());
+        match (cpus, memory) {
+            (Some(cpus), Some(memory)) => Some(format!("{} and {}", cpus, memory)),
+            (cpus, memory) => cpus.or(memory),
+        }
+    }
+
+    /// The cpus the load warning compares the load to, the limit rounded up.
+    pub fn cpu_count(&self) -> Option<usize> {
+        self.cpus.map(|cpus| cpus.ceil() as usize)
+    }
+
+    /// Available and total memory in KiB like `get_mem_info`, of the memory limit and what the
+    /// cgroup uses of it. `None` without a limit.
+    pub fn mem_info(&self) -> Option<(usize, usize)> {
+        let limit = self.memory?;
+        let current = std::fs::read_to_string(self.dir.join("memory.current"))
+            .ok()
+            .and_then(|current| current.trim().parse::<usize>().ok())?;
+        Some((limit.saturating_sub(current) / 1024, limit / 1024))
+    }
+}
+
+/// What `mode` finds out about the container bb runs in, given the filesystem `root` it sees,
+/// usually `/`: the runtime, and the limits of its cgroup with `ContainerMode::Limits`, if it has
+/// any.
+pub fn detect_container(
+    mode: ContainerMode,
+    root: &Path,
+    source: &ProcSource,
+) -> (Option<&'static str>, Option<CgroupLimits>) {
+    if mode == ContainerMode::Off {
+        return (None, None);
+    }
+    let cgroup = source.read_to_string("self/cgroup").unwrap_or_default();
+    let runtime = detect_runtime(root, &cgroup);
+    let limits = if mode == ContainerMode::Limits {
+        Some(CgroupLimits::read(
+            &root.join("sys/fs/cgroup"),
+            cgroup_path(&cgroup),
+        ))
+        .filter(|limits| !limits.is_empty())
+    } else {
+        None
+    };
+    (runtime, limits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ID: &str = "3f4e9a1c2b7d8e6f5a4b3c2d1e0f9a8b7c6d5e4f3a2b1c0d9e8f7a6b5c4d3e2f";
+
+    #[test]
+    fn test_detect_runtime() {
+        let dir = std::env::temp_dir().join(format!("bb-test-container-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("run")).unwrap();
+        /* on the host, or in a container with a cgroup namespace and no markers */
+        assert_eq!(detect_runtime(&dir, "0::/\n"), None);
+        assert_eq!(
+            detect_runtime(&dir, "0::/user.slice/user-1000.slice/session-2.scope\n"),
+            None
+        );
+        assert_eq!(
+            detect_runtime(&dir, "0::/system.slice/docker.service\n"),
+            None
+        );
+
+        /* without a cgroup namespace, by the name of the cgroup */
+        assert_eq!(
+            detect_runtime(&dir, &format!("0::/system.slice/docker-{}.scope\n", ID)),
+            Some("docker")
+        );
+        assert_eq!(
+            detect_runtime(&dir, &format!("11:memory:/docker/{}\n0::/\n", ID)),
+            Some("docker")
+        );
+        assert_eq!(
+            detect_runtime(&dir, &format!("0::/machine.slice/libpod-{}.scope\n", ID)),
+            Some("podman")
+        );
+        assert_eq!(
+            detect_runtime(
+                &dir,
+                &format!(
+                    "0::/kubepods.slice/kubepods-besteffort.slice/kubepods-besteffort-pod1b2c.slice/cri-containerd-{}.scope\n",
+                    ID
+                )
+            ),
+            Some("kubernetes")
+        );
+        assert_eq!(
+            detect_runtime(&dir, "0::/lxc.payload.web/init.scope\n"),
+            Some("lxc")
+        );
+        assert_eq!(
+            detect_runtime(&dir, &format!("0::/garden/{}\n", ID)),
+            Some("container")
+        );
+
+        /* the files the runtimes leave win over the cgroup */
+        std::fs::write(dir.join("run/.containerenv"), "").unwrap();
+        assert_eq!(detect_runtime(&dir, "0::/\n"), Some("podman"));
+        std::fs::write(dir.join(".dockerenv"), "").unwrap();
+        assert_eq!(detect_runtime(&dir, "0::/\n"), Some("docker"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_parse_limits() {
+        assert_eq!(parse_memory_max("536870912\n"), Some(512 * 1024 * 1024));
+        assert_eq!(parse_memory_max("max\n"), None);
+        assert_eq!(parse_memory_max(""), None);
+
+        assert_eq!(parse_cpu_max("150000 100000\n"), Some(1.5));
+        assert_eq!(parse_cpu_max("50000 100000\n"), Some(0.5));
+        assert_eq!(parse_cpu_max("max 100000\n"), None);
+        /* the period is 100ms unless given */
+        assert_eq!(parse_cpu_max("200000\n"), Some(2.0));
+        assert_eq!(parse_cpu_max("100000 0\n"), None);
+        assert_eq!(parse_cpu_max("quota period\n"), None);
+        assert_eq!(parse_cpu_max(""), None);
+
+        let limits = CgroupLimits {
+            cpus: Some(1.5),
+            memory: Some(512 * 1024 * 1024),
+            ..Default::default()
+        };
+        assert_eq!(
+            limits.describe().as_deref(),
+            Some("1.5 cpus and 512.00 MiB")
+        );
+        assert_eq!(limits.cpu_count(), Some(2));
+        let limits = CgroupLimits {
+            cpus: Some(1.0),
+            memory: None,
+            ..limits
+        };
+        assert_eq!(limits.describe().as_deref(), Some("1 cpu"));
+        assert_eq!(CgroupLimits::default().describe(), None);
+    }
+
+    #[test]
+    fn test_detect_container() {
+        let dir =
+            std::env::temp_dir().join(format!("bb-test-container-limits-{}", std::process::id()));
+        let cgroup = dir.join("sys/fs/cgroup/system.slice/app.service");
+        std::fs::create_dir_all(dir.join("proc/self")).unwrap();
+        std::fs::create_dir_all(&cgroup).unwrap();
+        std::fs::write(dir.join(".dockerenv"), "").unwrap();
+        std::fs::write(
+            dir.join("proc/self/cgroup"),
+            "0::/system.slice/app.service\n",
+        )
+        .unwrap();
+        std::fs::write(cgroup.join("cpu.max"), "max 100000\n").unwrap();
+        std::fs::write(cgroup.join("memory.max"), "1073741824\n").unwrap();
+        std::fs::write(cgroup.join("memory.current"), "268435456\n").unwrap();
+        let source = ProcSource::fixture(dir.join("proc"));
+
+        assert_eq!(
+            detect_container(ContainerMode::Off, &dir, &source),
+            (None, None)
+        );
+        assert_eq!(
+            detect_container(ContainerMode::Auto, &dir, &source),
+            (Some("docker"), None)
+        );
+        let (runtime, limits) = detect_container(ContainerMode::Limits, &dir, &source);
+        let limits = limits.unwrap();
+        assert_eq!(runtime, Some("docker"));
+        assert_eq!(
+            (limits.cpus, limits.memory),
+            (None, Some(1024 * 1024 * 1024))
+        );
+        /* a quarter of the limit in use */
+        assert_eq!(limits.mem_info(), Some((768 * 1024, 1024 * 1024)));
+
+        /* no limits, as in cgroup v1 where the files aren't there */
+        std::fs::write(cgroup.join("memory.max"), "max\n").unwrap();
+        assert_eq!(
+            detect_container(ContainerMode::Limits, &dir, &source),
+            (Some("docker"), None)
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,247 @@
+/*
+ * bb
+ *
+ * Copyright 2019 Manos Pitsidianakis
+ *
+ * This file is part of bb.
+ *
+ * bb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * bb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with bb. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+/*! The top row of `KernelMetrics`: the hostname, kernel, RAM and core count on the left, the time
+and uptime on the right.
+*/
+
+use super::*;
+
+/// What the header says about the system. None of it changes while bb runs, so it's read once.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SystemInfo {
+    pub hostname: String,
+    /// Kernel name, like `uname -s`.
+    pub os_type: String,
+    /// Kernel release, like `uname -r`.
+    pub release: String,
+    /// Total memory in KiB.
+    pub mem_total: usize,
+    pub cores: usize,
+    /// The container runtime bb runs under, see `detect_container`.
+    pub container: Option<&'static str>,
+    /// The limits the meters are scoped to, as `CgroupLimits::describe` puts them.
+    pub limits: Option<String>,
+}
+
+impl SystemInfo {
+    pub fn read(source: &ProcSource) -> SystemInfo {
+        /* an alternate `--proc-root` doesn't have to provide these */
+        let read = |file| {
+            source
+                .read_to_string(file)
+                .map(|s| s.trim().to_string())
+                .unwrap_or_else(|_| "-".into())
+        };
+        let mut boot_time = 0;
+        SystemInfo {
+            hostname: read("sys/kernel/hostname"),
+            os_type: read("sys/kernel/ostype"),
+            release: read("sys/kernel/osrelease"),
+            mem_total: get_mem_info(source).unwrap().1,
+            /* the first line is the total of all cpus */
+            cores: get_stat(source, &mut boot_time).len().saturating_sub(1),
+            container: None,
+            limits: None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct SystemHeader {
+    info: SystemInfo,
+    /* everything after the hostname, formatted once */
+    details: String,
+}
+
+impl SystemHeader {
+    pub fn new(info: SystemInfo) -> SystemHeader {
+        let mut details = format!(
+            "{} {}  {} RAM  {} {}",
+            info.os_type,
+            info.release,
+            Bytes(info.mem_total * 1024).as_convenient_string(),
+            info.cores,
+            if info.cores == 1 { "core" } else { "cores" }
+        );
+        match (info.container, &info.limits) {
+            (Some(runtime), Some(limits)) => {
+                details.push_str(&format!("  {} container limited to {}", runtime, limits))
+            }
+            (Some(runtime), None) => details.push_str(&format!("  {} container", runtime)),
+            (None, Some(limits)) => details.push_str(&format!("  cgroup limited to {}", limits)),
+            (None, None) => {}
+        }
+        SystemHeader { info, details }
+    }
+
+    /// The left side of the header, eg. `host  Linux 5.4.0  15.50 GiB RAM  8 cores`.
+    pub fn text(&self) -> String {
+        format!("{}  {}", self.info.hostname, self.details)
+    }
+
+    /// Draw the left side in the first row of `area`, with the hostname in bold.
+    pub fn draw_static(&self, grid: &mut CellBuffer, area: Area) {
+        let (x, y) = write_string_to_grid(
+            &self.info.hostname,
+            grid,
+            Color::Default,
+            Color::Default,
+            Attr::Bold,
+            area,
+            false,
+        );
+        write_string_to_grid(
+            &self.details,
+            grid,
+            Color::Default,
+            Color::Default,
+            Attr::Default,
+            ((x + 2, y), bottom_right!(area)),
+            false,
+        );
+    }
+
+    /// Draw the current time and `uptime_secs` right-aligned in the first row of `area`, after
+    /// `self_usage`, the text of `SelfUsage`, if it's shown.
+    pub fn draw_clock(
+        &self,
+        grid: &mut CellBuffer,
+        area: Area,
+        uptime_secs: usize,
+        self_usage: Option<&str>,
+    ) {
+        let mut text = format!("{}  {}", clock(), format_uptime(uptime_secs));
+        if let Some(self_usage) = self_usage {
+            text = format!("{}  {}", self_usage, text);
+        }
+        let bottom_right = bottom_right!(area);
+        write_string_to_grid(
+            &text,
+            grid,
+            Color::Default,
+            Color::Default,
+            Attr::Default,
+            (
+                (
+                    get_x(bottom_right).saturating_sub(text.len()),
+                    get_y(upper_left!(area)),
+                ),
+                bottom_right,
+            ),
+            false,
+        );
+    }
+}
+
+/* eg. `uptime: 01:02:03` or `uptime: 4 days, 01:02:03` */
+fn format_uptime(seconds: usize) -> String {
+    let days = seconds / (60 * 60 * 24);
+    let hms = format!(
+        "{:02}:{:02}:{:02}",
+        seconds / 3600 % 24,
+        seconds / 60 % 60,
+        seconds % 60
+    );
+    if days > 0 {
+        format!("uptime: {} days, {}", days, hms)
+    } else {
+        format!("uptime: {}", hms)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_header_text() {
+        let header = SystemHeader::new(SystemInfo {
+            hostname: "host".to_string(),
+            os_type: "Linux".to_string(),
+            release: "5.4.0-42-generic".to_string(),
+            mem_total: 16 * 1024 * 1024,
+            cores: 8,
+            container: None,
+            limits: None,
+        });
+        assert_eq!(
+            header.text(),
+            "host  Linux 5.4.0-42-generic  16.00 GiB RAM  8 cores"
+        );
+        let header = SystemHeader::new(SystemInfo {
+            mem_total: 512 * 1024,
+            cores: 1,
+            ..header.info
+        });
+        assert!(header.text().ends_with("  512.00 MiB RAM  1 core"));
+        let header = SystemHeader::new(SystemInfo {
+            container: Some("docker"),
+            ..header.info
+        });
+        assert!(header.text().ends_with("  1 core  docker container"));
+        let header = SystemHeader::new(SystemInfo {
+            limits: Some("2 cpus".to_string()),
+            ..header.info
+        });
+        assert!(header
+            .text()
+            .ends_with("  1 core  docker container limited to 2 cpus"));
+        let header = SystemHeader::new(SystemInfo {
+            container: None,
+            ..header.info
+        });
+        assert!(header
+            .text()
+            .ends_with("  1 core  cgroup limited to 2 cpus"));
+
+        assert_eq!(format_uptime(3723), "uptime: 01:02:03");
+        assert_eq!(format_uptime(4 * 86400 + 3723), "uptime: 4 days, 01:02:03");
+    }
+
+    #[test]
+    fn test_read_without_sys_kernel() {
+        let dir = std::env::temp_dir().join(format!("bb-test-header-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("stat"),
+            "cpu  10 0 5 100 1 0 0 0 0 0\ncpu0 10 0 5 100 1 0 0 0 0 0\nbtime 1600000000\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("meminfo"),
+            "MemTotal: 16384 kB\nMemFree: 4096 kB\n",
+        )
+        .unwrap();
+
+        let info = SystemInfo::read(&ProcSource::new(&dir).unwrap());
+        assert_eq!(
+            (
+                info.hostname.as_str(),
+                info.os_type.as_str(),
+                info.release.as_str()
+            ),
+            ("-", "-", "-")
+        );
+        assert_eq!((info.mem_total, info.cores), (16384, 1));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
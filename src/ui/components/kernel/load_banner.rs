@@ -0,0 +1,142 @@
+/*
+ * bb
+ *
+ * Copyright 2019 Manos Pitsidianakis
+ *
+ * This file is part of bb.
+ *
+ * bb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * bb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with bb. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+/*! The banner of `--load-warning`: a red row across the top of the meters while the 1 minute load
+average is above a multiple of the number of cpus, eg. 2 for twice as many runnable tasks as there
+are cpus to run them.
+
+Once shown, the banner only goes away when the load drops below `HIDE_FRACTION` of the threshold,
+so that a load going up and down around it doesn't make it flicker.
+*/
+
+use super::*;
+
+/* Fraction of the threshold the load has to drop below for a shown banner to hide */
+const HIDE_FRACTION: f64 = 0.9;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct LoadBanner {
+    /* load per cpu over which the banner is shown */
+    threshold: f64,
+    shown: bool,
+}
+
+impl LoadBanner {
+    pub fn new(threshold: f64) -> LoadBanner {
+        LoadBanner {
+            threshold,
+            shown: false,
+        }
+    }
+
+    /// Take in the 1 minute `load` average of a system with `cpus` cpus, and return whether the
+    /// banner is shown.
+    pub fn update(&mut self, load: f64, cpus: usize) -> bool {
+        let limit = self.threshold * std::cmp::max(cpus, 1) as f64;
+        if load > limit {
+            self.shown = true;
+        } else if load < limit * HIDE_FRACTION {
+            self.shown = false;
+        }
+        self.shown
+    }
+}
+
+/// The warning for `load` on `cpus` cpus.
+pub fn overload_warning(load: f64, cpus: usize) -> String {
+    format!(
+        "OVERLOADED: load average {:.2} on {} cpu{}",
+        load,
+        cpus,
+        if cpus == 1 { "" } else { "s" }
+    )
+}
+
+/// Draw `text` centered across the top row of `area` in reverse red, or clear the row if there is
+/// none.
+pub fn draw_banner(grid: &mut CellBuffer, area: Area, text: Option<&str>) {
+    if !is_valid_area!(area) {
+        return;
+    }
+    let (upper_left, bottom_right) = area;
+    let row = (upper_left, (get_x(bottom_right), get_y(upper_left)));
+    clear_area(grid, row);
+    let text = match text {
+        Some(text) => text,
+        None => return,
+    };
+    let (left, right) = (get_x(upper_left), get_x(bottom_right));
+    let padding = (right - left + 1).saturating_sub(text.len()) / 2;
+    let mut chars = text.chars();
+    for x in left..=right {
+        let ch = if x - left < padding {
+            ' '
+        } else {
+            chars.next().unwrap_or(' ')
+        };
+        grid[(x, get_y(upper_left))]
+            .set_ch(ch)
+            .set_fg(Color::Red)
+            .set_attrs(Attr::Reverse);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_banner_hysteresis() {
+        /* a limit of 4 on two cpus, hidden again below 3.6 */
+        let mut banner = LoadBanner::new(2.0);
+        let shown = [1.0, 4.0, 4.1, 3.9, 3.7, 3.6, 3.59, 3.9, 4.01]
+            .iter()
+            .map(|&load| banner.update(load, 2))
+            .collect::<Vec<bool>>();
+        assert_eq!(
+            shown,
+            [false, false, true, true, true, true, false, false, true]
+        );
+        /* without cpus to count, the threshold is the load itself */
+        assert!(LoadBanner::new(1.5).update(1.6, 0));
+    }
+
+    #[test]
+    fn test_draw_load_banner() {
+        let grid = render_with((44, 2), |grid, area| {
+            draw_banner(grid, area, Some(&overload_warning(8.5, 4)))
+        });
+        assert_eq!(
+            grid_rows(&grid)[0],
+            "  OVERLOADED: load average 8.50 on 4 cpus   "
+        );
+        assert_eq!(
+            (grid[(0, 0)].fg(), grid[(0, 0)].attrs()),
+            (Color::Red, Attr::Reverse)
+        );
+        assert_eq!(grid_rows(&grid)[1], " ".repeat(44));
+
+        let mut grid = grid;
+        draw_banner(&mut grid, ((0, 0), (43, 1)), None);
+        assert_eq!(grid_rows(&grid)[0], " ".repeat(44));
+        assert_eq!(grid[(0, 0)].attrs(), Attr::Default);
+    }
+}
@@ -0,0 +1,118 @@
+/*
+ * bb
+ *
+ * Copyright 2019 Manos Pitsidianakis
+ *
+ * This file is part of bb.
+ *
+ * bb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * bb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with bb. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+/*! The banner of `--pid-warning`: a red row across the top of the meters while most of the PIDs
+`/proc/sys/kernel/pid_max` allows are in use, since no process or thread can start once they all
+are.
+
+Every thread takes a PID, so the PIDs in use are the tasks the kernel counts in the fourth field
+of `/proc/loadavg`, eg. `1/300`, not the processes of the list. The highest PID in use says
+little, since PIDs wrap around to the lowest free one once they reach `pid_max`.
+*/
+
+use super::*;
+
+/// The PIDs in use and how many there can be.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct PidUsage {
+    pub tasks: usize,
+    pub pid_max: usize,
+}
+
+impl PidUsage {
+    /// `None` if either file can't be read or parsed.
+    pub fn read(source: &ProcSource) -> Option<PidUsage> {
+        let pid_max = source.read_to_string("sys/kernel/pid_max").ok()?;
+        let loadavg = source.read_to_string("loadavg").ok()?;
+        let (_, tasks) = loadavg.split_whitespace().nth(3)?.split_once('/')?;
+        Some(PidUsage {
+            tasks: tasks.parse().ok()?,
+            pid_max: pid_max.trim().parse().ok()?,
+        })
+    }
+
+    /// The fraction of the PIDs in use, in [0, 1].
+    pub fn utilization(&self) -> f64 {
+        if self.pid_max == 0 {
+            return 0.0;
+        }
+        (self.tasks as f64 / self.pid_max as f64).min(1.0)
+    }
+
+    /// Whether at least `threshold` of the PIDs are in use.
+    pub fn is_high(&self, threshold: f64) -> bool {
+        self.utilization() >= threshold
+    }
+
+    pub fn warning(&self) -> String {
+        format!(
+            "RUNNING OUT OF PIDS: {} of {} in use ({:.0}%)",
+            self.tasks,
+            self.pid_max,
+            self.utilization() * 100.0
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pid_usage() {
+        let usage = |tasks, pid_max| PidUsage { tasks, pid_max };
+        assert_eq!(usage(0, 32768).utilization(), 0.0);
+        assert_eq!(usage(8192, 32768).utilization(), 0.25);
+        assert_eq!(usage(32768, 32768).utilization(), 1.0);
+        /* tasks counted while pid_max was lowered */
+        assert_eq!(usage(40000, 32768).utilization(), 1.0);
+        assert_eq!(usage(10, 0).utilization(), 0.0);
+
+        /* the threshold itself warns */
+        assert!(!usage(26213, 32768).is_high(0.8));
+        assert!(usage(26215, 32768).is_high(0.8));
+        assert!(usage(32768, 32768).is_high(1.0));
+        assert_eq!(
+            usage(29491, 32768).warning(),
+            "RUNNING OUT OF PIDS: 29491 of 32768 in use (90%)"
+        );
+    }
+
+    #[test]
+    fn test_read_pid_usage() {
+        let dir = std::env::temp_dir().join(format!("bb-test-pids-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("sys/kernel")).unwrap();
+        let source = ProcSource::fixture(&dir);
+        std::fs::write(dir.join("loadavg"), "1.50 0.75 0.25 2/29491 4242\n").unwrap();
+        assert_eq!(PidUsage::read(&source), None);
+        std::fs::write(dir.join("sys/kernel/pid_max"), "32768\n").unwrap();
+        assert_eq!(
+            PidUsage::read(&source),
+            Some(PidUsage {
+                tasks: 29491,
+                pid_max: 32768,
+            })
+        );
+        std::fs::write(dir.join("loadavg"), "1.50 0.75 0.25\n").unwrap();
+        assert_eq!(PidUsage::read(&source), None);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
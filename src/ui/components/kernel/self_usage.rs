@@ -0,0 +1,178 @@
+/*
+ * bb
+ *
+ * Copyright 2019 Manos Pitsidianakis
+ *
+ * This file is part of bb.
+ *
+ * bb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * bb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with bb. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+/*! bb's own cpu usage and memory, for `--self-usage`: from `/proc/self/stat` and
+`/proc/self/statm`, drawn in the header left of the clock, eg. `bb  0.4%   5.2M`, so that what
+the columns that read a file per process cost shows.
+
+The cpu usage is a percentage of one cpu, like the CPU% column, over the time between two samples.
+*/
+
+use super::*;
+use std::time::Instant;
+
+/// What bb had used at some point.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SelfSample {
+    /// User and system time, in clock ticks.
+    pub ticks: usize,
+    /// Resident memory, in bytes.
+    pub rss: usize,
+}
+
+/// Read the times of `source`'s `self/stat` and the resident pages of its `self/statm`, of
+/// `page_size` bytes.
+pub fn read_self_sample(source: &ProcSource, page_size: usize) -> Option<SelfSample> {
+    let stat = source.read_to_string("self/stat").ok()?;
+    /* the command name can have spaces and parentheses in it, the fields after it can't */
+    let fields = stat[stat.rfind(')')? + 1..]
+        .split_whitespace()
+        .collect::<Vec<&str>>();
+    /* utime and stime, the 14th and 15th fields from the start */
+    let utime = fields.get(11)?.parse::<usize>().ok()?;
+    let stime = fields.get(12)?.parse::<usize>().ok()?;
+    let statm = source.read_to_string("self/statm").ok()?;
+    let resident = statm.split_whitespace().nth(1)?.parse::<usize>().ok()?;
+    Some(SelfSample {
+        ticks: utime + stime,
+        rss: resident * page_size,
+    })
+}
+
+/// The text drawn in the header, of the same width whatever the values so that it covers the
+/// previous one, or the cpu usage as `--` before it's known.
+pub fn format_self_usage(cpu_percent: Option<f64>, rss: usize) -> String {
+    format!(
+        "bb {:>5} {:>6}",
+        cpu_percent.map_or_else(|| "--".to_string(), |cpu| format!("{:.1}%", cpu)),
+        Bytes(rss).as_short_string()
+    )
+}
+
+#[derive(Debug)]
+pub struct SelfUsage {
+    source: ProcSource,
+    page_size: usize,
+    /* the last sample and when it was taken */
+    last: Option<(SelfSample, Instant)>,
+    cpu_percent: Option<f64>,
+}
+
+impl SelfUsage {
+    pub fn new(source: ProcSource) -> SelfUsage {
+        SelfUsage {
+            source,
+            page_size: match unsafe { libc::sysconf(libc::_SC_PAGESIZE) } {
+                size if size > 0 => size as usize,
+                _ => 4096,
+            },
+            last: None,
+            cpu_percent: None,
+        }
+    }
+
+    /// Take a sample at `now`, and compute the cpu usage since the previous one.
+    pub fn update(&mut self, now: Instant) {
+        let sample = match read_self_sample(&self.source, self.page_size) {
+            Some(sample) => sample,
+            None => return,
+        };
+        if let Some((last, then)) = self.last {
+            let seconds = now.duration_since(then).as_secs_f64();
+            if seconds > 0.0 {
+                let ticks = sample.ticks.saturating_sub(last.ticks) as f64;
+                self.cpu_percent = Some(ticks * 100.0 / clock_ticks() as f64 / seconds);
+            }
+        }
+        self.last = Some((sample, now));
+    }
+
+    pub fn text(&self) -> Option<String> {
+        self.last
+            .map(|(sample, _)| format_self_usage(self.cpu_percent, sample.rss))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mock_self(dir: &std::path::Path, utime: usize, stime: usize, resident: usize) {
+        std::fs::create_dir_all(dir.join("self")).unwrap();
+        std::fs::write(
+            dir.join("self/stat"),
+            format!(
+                "4242 (bb (monitor)) S 1 4242 4242 34816 4242 4194304 900 0 0 0 {} {} 0 0 20 0 \
+                 3 0 12345 20000000 {} 18446744073709551615\n",
+                utime, stime, resident
+            ),
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("self/statm"),
+            format!("5000 {} 300 100 0 900 0\n", resident),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_read_self_sample() {
+        let dir = std::env::temp_dir().join(format!("bb-test-self-usage-{}", std::process::id()));
+        mock_self(&dir, 30, 12, 1280);
+        let source = ProcSource::fixture(&dir);
+        assert_eq!(
+            read_self_sample(&source, 4096),
+            Some(SelfSample {
+                ticks: 42,
+                rss: 1280 * 4096,
+            })
+        );
+
+        let mut usage = SelfUsage::new(source.clone());
+        usage.page_size = 4096;
+        assert_eq!(usage.text(), None);
+        let start = Instant::now();
+        usage.update(start);
+        assert_eq!(usage.text().unwrap(), "bb    --   5.0M");
+        /* a tenth of a cpu over two seconds */
+        mock_self(&dir, 30 + clock_ticks() / 10, 12 + clock_ticks() / 10, 2560);
+        usage.update(start + std::time::Duration::from_secs(2));
+        assert_eq!(usage.text().unwrap(), "bb 10.0%  10.0M");
+
+        std::fs::write(dir.join("self/stat"), "4242 (bb) S 1\n").unwrap();
+        assert_eq!(read_self_sample(&source, 4096), None);
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(read_self_sample(&source, 4096), None);
+    }
+
+    #[test]
+    fn test_format_self_usage() {
+        assert_eq!(
+            format_self_usage(Some(0.4), 5 * 1024 * 1024),
+            "bb  0.4%   5.0M"
+        );
+        assert_eq!(
+            format_self_usage(Some(123.45), 1536 * 1024 * 1024),
+            "bb 123.5%   1.5G"
+        );
+        assert_eq!(format_self_usage(None, 800), "bb    --   800B");
+    }
+}
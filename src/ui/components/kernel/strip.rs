@@ -0,0 +1,209 @@
+/*
+ * bb
+ *
+ * Copyright 2019 Manos Pitsidianakis
+ *
+ * This file is part of bb.
+ *
+ * bb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * bb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with bb. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+/*! The meter strip of `--strip`: the cpu usage, memory and load averages on one line of at most a
+given width, printed once for status bars like tmux's to show, eg.
+`CPU 12.3%  MEM 4.2G/15.6G (27%)  LOAD 0.52 0.48 0.40`.
+
+What doesn't fit is left out, least telling first: the total memory, then the 5 and 15 minute
+load averages, then the labels. A strip that still doesn't fit is cut short with `…`.
+*/
+
+use super::*;
+use std::io::Write;
+use std::time::Duration;
+
+/* Time between the two cpu samples usage is computed over, like the samples of the process list */
+const STRIP_INTERVAL: Duration = Duration::from_millis(1600);
+
+/// What the strip shows.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct StripMetrics {
+    /// Busy fraction of all cpus in [0, 1], `None` without two samples to compare.
+    pub cpu: Option<f64>,
+    /// Memory in use and in total, in bytes.
+    pub mem_used: usize,
+    pub mem_total: usize,
+    /// The 1, 5 and 15 minute load averages.
+    pub load: [f64; 3],
+}
+
+impl StripMetrics {
+    /// Sample `source` twice, `STRIP_INTERVAL` apart.
+    pub fn read(source: &ProcSource) -> StripMetrics {
+        let mut boot_time = 0;
+        let old = get_stat(source, &mut boot_time);
+        std::thread::sleep(STRIP_INTERVAL);
+        let new = get_stat(source, &mut boot_time);
+        let cpu = match (old.first(), new.first()) {
+            (Some(old), Some(new)) => rate(
+                Some(old.busy_time()),
+                new.busy_time(),
+                new.total_time().saturating_sub(old.total_time()),
+            ),
+            _ => None,
+        };
+        let (available, total) = get_mem_info(source).unwrap();
+        let load = get_loadavg(source);
+        let load = |i: usize| load[i].parse::<f64>().unwrap_or(0.0);
+        StripMetrics {
+            cpu,
+            mem_used: total.saturating_sub(available) * 1024,
+            mem_total: total * 1024,
+            load: [load(0), load(1), load(2)],
+        }
+    }
+}
+
+/// The strip of `metrics` in at most `width` columns.
+pub fn format_strip(metrics: &StripMetrics, width: usize) -> String {
+    let cpu = metrics
+        .cpu
+        .map_or_else(|| "--".to_string(), |cpu| format!("{:.1}%", cpu * 100.0));
+    let cpu_whole = metrics
+        .cpu
+        .map_or_else(|| "--".to_string(), |cpu| format!("{:.0}%", cpu * 100.0));
+    let mem_percent = if metrics.mem_total == 0 {
+        "--".to_string()
+    } else {
+        format!(
+            "{:.0}%",
+            metrics.mem_used as f64 * 100.0 / metrics.mem_total as f64
+        )
+    };
+    let used = Bytes(metrics.mem_used).as_short_string();
+    let [load_1, load_5, load_15] = metrics.load;
+    let layouts = [
+        format!(
+            "CPU {}  MEM {}  LOAD {:.2} {:.2} {:.2}",
+            cpu,
+            format_mem_combined(metrics.mem_used, metrics.mem_total),
+            load_1,
+            load_5,
+            load_15
+        ),
+        format!(
+            "CPU {}  MEM {} {}  LOAD {:.2} {:.2} {:.2}",
+            cpu, used, mem_percent, load_1, load_5, load_15
+        ),
+        format!("CPU {}  MEM {}  LOAD {:.2}", cpu, mem_percent, load_1),
+        format!("CPU {} MEM {} LOAD {:.2}", cpu_whole, mem_percent, load_1),
+        format!("{} {} {:.2}", cpu_whole, mem_percent, load_1),
+    ];
+    if let Some(layout) = layouts.iter().find(|layout| layout.len() <= width) {
+        return layout.clone();
+    }
+    if width == 0 {
+        return String::new();
+    }
+    let mut ret = layouts[layouts.len() - 1][..width - 1].to_string();
+    ret.push('…');
+    ret
+}
+
+/// Print the strip of the system `source` is the procfs of, in at most `width` columns.
+pub fn print_strip(width: usize, source: &ProcSource) -> std::io::Result<()> {
+    let strip = format_strip(&StripMetrics::read(source), width);
+    let stdout = std::io::stdout();
+    let mut out = stdout.lock();
+    writeln!(out, "{}", strip)?;
+    out.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const METRICS: StripMetrics = StripMetrics {
+        cpu: Some(0.123),
+        mem_used: 4 * 1024 * 1024 * 1024,
+        mem_total: 16 * 1024 * 1024 * 1024,
+        load: [0.52, 0.48, 0.4],
+    };
+
+    #[test]
+    fn test_format_strip() {
+        let strip = |width| format_strip(&METRICS, width);
+        assert_eq!(
+            strip(80),
+            "CPU 12.3%  MEM 4.0G/16.0G (25%)  LOAD 0.52 0.48 0.40"
+        );
+        assert_eq!(
+            strip(52),
+            "CPU 12.3%  MEM 4.0G/16.0G (25%)  LOAD 0.52 0.48 0.40"
+        );
+        /* every layout is the longest one that fits */
+        assert_eq!(strip(51), "CPU 12.3%  MEM 4.0G 25%  LOAD 0.52 0.48 0.40");
+        assert_eq!(strip(44), "CPU 12.3%  MEM 4.0G 25%  LOAD 0.52 0.48 0.40");
+        assert_eq!(strip(43), "CPU 12.3%  MEM 25%  LOAD 0.52");
+        assert_eq!(strip(29), "CPU 12.3%  MEM 25%  LOAD 0.52");
+        assert_eq!(strip(28), "CPU 12% MEM 25% LOAD 0.52");
+        assert_eq!(strip(24), "12% 25% 0.52");
+        assert_eq!(strip(12), "12% 25% 0.52");
+        assert_eq!(strip(8), "12% 25%…");
+        assert_eq!(strip(1), "…");
+        assert_eq!(strip(0), "");
+        for width in 0..60 {
+            assert!(strip(width).chars().count() <= width, "{}", width);
+        }
+
+        /* before a second sample, and without a memory total */
+        let metrics = StripMetrics {
+            cpu: None,
+            mem_total: 0,
+            ..METRICS
+        };
+        assert_eq!(format_strip(&metrics, 28), "CPU --  MEM --  LOAD 0.52");
+    }
+
+    #[test]
+    fn test_read_strip() {
+        let dir = std::env::temp_dir().join(format!("bb-test-strip-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("stat"),
+            "cpu  10 0 5 100 1 0 0 0 0 0\ncpu0 10 0 5 100 1 0 0 0 0 0\nbtime 1600000000\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("meminfo"),
+            "MemTotal: 16384 kB\nMemAvailable: 4096 kB\n",
+        )
+        .unwrap();
+        std::fs::write(dir.join("loadavg"), "1.50 0.75 0.25 2/300 4242\n").unwrap();
+        let metrics = StripMetrics::read(&ProcSource::new(&dir).unwrap());
+        /* the stat didn't change between the samples, so there's no time to compute usage over */
+        assert_eq!(
+            metrics,
+            StripMetrics {
+                cpu: None,
+                mem_used: 12288 * 1024,
+                mem_total: 16384 * 1024,
+                load: [1.5, 0.75, 0.25],
+            }
+        );
+        assert_eq!(
+            format_strip(&metrics, 80),
+            "CPU --  MEM 12.0M/16.0M (75%)  LOAD 1.50 0.75 0.25"
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
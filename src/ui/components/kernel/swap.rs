@@ -0,0 +1,174 @@
+/*
+ * bb
+ *
+ * Copyright 2019 Manos Pitsidianakis
+ *
+ * This file is part of bb.
+ *
+ * bb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * bb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with bb. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+/*! The swap meter of `--swap-meter`: a meter of its own below the RAM one with the swap in use,
+left out on systems without swap.
+*/
+
+use super::*;
+
+/* Backgrounds of the part of the swap meter in use and of the rest */
+const SWAP_USED_BG: Color = Color::Byte(97); // MediumPurple3
+const SWAP_FREE_BG: Color = Color::Byte(235); // Grey15
+
+/// The swap of the system, in KiB.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct SwapInfo {
+    pub total: usize,
+    pub free: usize,
+}
+
+impl SwapInfo {
+    /// Reads `SwapTotal` and `SwapFree` from `meminfo`. Both are 0 if they can't be read.
+    pub fn read(source: &ProcSource) -> SwapInfo {
+        source
+            .read_to_string("meminfo")
+            .map(|meminfo| SwapInfo::parse(&meminfo))
+            .unwrap_or_default()
+    }
+
+    fn parse(meminfo: &str) -> SwapInfo {
+        let field = |name: &str| {
+            meminfo
+                .lines()
+                .find_map(|line| {
+                    line.strip_prefix(name)?
+                        .strip_prefix(':')?
+                        .split_whitespace()
+                        .next()?
+                        .parse()
+                        .ok()
+                })
+                .unwrap_or(0)
+        };
+        SwapInfo {
+            total: field("SwapTotal"),
+            free: field("SwapFree"),
+        }
+    }
+
+    pub fn used(&self) -> usize {
+        self.total.saturating_sub(self.free)
+    }
+
+    /// The fraction of the swap in use, or `None` without swap.
+    pub fn fraction(&self) -> Option<f64> {
+        if self.total == 0 {
+            None
+        } else {
+            Some(self.used() as f64 / self.total as f64)
+        }
+    }
+}
+
+/// Draw `swap` as a meter filling `area` from the left, with `SWP used/total` in the middle of
+/// its bottom row. Without swap `area` is left blank and `false` is returned.
+pub fn draw_swap_meter(
+    grid: &mut CellBuffer,
+    area: Area,
+    swap: SwapInfo,
+    glyphs: Option<BarGlyphs>,
+) -> bool {
+    if !is_valid_area!(area) {
+        return false;
+    }
+    clear_area(grid, area);
+    let fraction = match swap.fraction() {
+        Some(fraction) => fraction,
+        None => return false,
+    };
+    let (upper_left, bottom_right) = area;
+    let (left, right) = (get_x(upper_left), get_x(bottom_right));
+    let width = right - left + 1;
+    let used = std::cmp::min(width, (fraction * width as f64).round() as usize);
+    for y in get_y(upper_left)..=get_y(bottom_right) {
+        for x in left..=right {
+            let filled = x - left < used;
+            let ch = match glyphs {
+                Some(glyphs) if filled => glyphs.fill,
+                Some(glyphs) => glyphs.empty,
+                None => ' ',
+            };
+            grid[(x, y)]
+                .set_ch(ch)
+                .set_fg(Color::Byte(240))
+                .set_bg(if filled { SWAP_USED_BG } else { SWAP_FREE_BG });
+        }
+    }
+    let label = format!(
+        "SWP {}/{}",
+        Bytes(swap.used() * 1024).as_convenient_string(),
+        Bytes(swap.total * 1024).as_convenient_string()
+    );
+    let padding = width.saturating_sub(label.len()) / 2;
+    let bottom = get_y(bottom_right);
+    for (x, ch) in (left + padding..=right).zip(label.chars()) {
+        grid[(x, bottom)].set_ch(ch).set_fg(Color::White);
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_swap_used() {
+        let swap = SwapInfo::parse(
+            "MemTotal:       16384 kB\nSwapCached:         0 kB\n\
+             SwapTotal:       8192 kB\nSwapFree:        6144 kB\n",
+        );
+        assert_eq!(
+            swap,
+            SwapInfo {
+                total: 8192,
+                free: 6144
+            }
+        );
+        assert_eq!((swap.used(), swap.fraction()), (2048, Some(0.25)));
+        /* a free count above the total doesn't wrap around */
+        assert_eq!(SwapInfo { total: 1, free: 2 }.used(), 0);
+        assert_eq!(SwapInfo::parse("MemTotal: 16384 kB\n"), SwapInfo::default());
+    }
+
+    #[test]
+    fn test_swap_meter_hidden() {
+        let mut grid = CellBuffer::new(30, 2, Cell::default());
+        let area = ((0, 0), (29, 0));
+        let text = |grid: &CellBuffer| (0..30).map(|x| grid[(x, 0)].ch()).collect::<String>();
+
+        let swap = SwapInfo {
+            total: 1024 * 1024,
+            free: 512 * 1024,
+        };
+        assert!(draw_swap_meter(&mut grid, area, swap, None));
+        assert_eq!(text(&grid), "   SWP 512.00 MiB/1.00 GiB    ");
+        assert_eq!(grid[(14, 0)].bg(), SWAP_USED_BG);
+        assert_eq!(grid[(15, 0)].bg(), SWAP_FREE_BG);
+
+        /* without swap what was drawn is cleared, and nothing takes its place */
+        let none = SwapInfo { total: 0, free: 0 };
+        assert_eq!(none.fraction(), None);
+        assert!(!draw_swap_meter(&mut grid, area, none, None));
+        assert_eq!(text(&grid), " ".repeat(30));
+        assert_eq!(grid[(0, 0)].bg(), Color::Default);
+    }
+}
@@ -20,22 +20,210 @@
  */
 
 use super::*;
+use crate::ui::text_processing::grapheme_clusters::Graphemes;
+use crate::ui::theme::Thresholds;
+use std::borrow::Cow;
 use std::collections::{HashMap, HashSet};
 use std::fs::File;
 use std::io::prelude::*;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use std::time::Duration;
+
+mod affinity;
+mod age;
+mod baseline;
+mod caps;
+mod category;
+mod cgroup;
+mod column_picker;
+mod columns;
+mod cwd;
+mod environ;
+mod follow_new;
+mod gpu;
+mod group;
+mod growth;
+mod history;
+mod hook;
+mod inspector;
+mod io;
+mod key_hints;
+#[cfg(feature = "nvml")]
+mod nvml;
+mod pid_ns;
+mod pins;
+mod sampler;
+mod scroll_freeze;
+mod scroll_indicators;
+mod separators;
+mod smaps;
+mod sockets;
+mod summary;
+mod tombstone;
+mod tree_glyphs;
+mod views;
+use affinity::affinity_label;
+use age::Ages;
+use baseline::{delta_strings, sum_deltas, Baseline, Delta};
+use caps::{caps_label, parse_cap_mask};
+pub use category::{classify, Category};
+pub use cgroup::{cgroup_path, container_id};
+use column_picker::{ColumnConfig, OPTIONAL_COLUMNS, READ_WHEN_SHOWN};
+pub use columns::{Alignment, Alignments, Column, COLUMN_NAMES};
+use cwd::read_cwd;
+pub use cwd::CwdCommand;
+pub use environ::valid_key as valid_env_key;
+use environ::EnvColumn;
+use follow_new::FollowNew;
+use gpu::{GpuUsage, Gpus};
+use group::{group_branches, grouped_indices, regroup, GroupedRow};
+use growth::{MemGrowth, MEM_GROWTH_FG};
+pub use history::GraphDirection;
+use history::{record_cpu_history, CpuHistory, BASELINE, HISTORY_LEN};
+pub use hook::HookCommand;
+use inspector::inspector_lines;
+use io::{IoCounters, IoRates};
+use key_hints::KeyHints;
+use pid_ns::{group_by_ns, ns_color, read_pid_ns, same_ns};
+pub use pid_ns::{PidNsMode, PID_NS_MODES};
+pub use pins::Pin;
+use pins::{Pins, PIN_MARKER};
+use sampler::{Sampler, Snapshot};
+use scroll_freeze::ScrollFreeze;
+use scroll_indicators::draw_scroll_indicators;
+use separators::draw_separator;
+pub use separators::{Separators, SEPARATOR_MODES};
+use smaps::{read_memory_map, MemoryMap};
+use sockets::{NetState, Sockets};
+use summary::TaskSummary;
+use tombstone::{Tombstones, TOMBSTONE_FG};
+pub use tree_glyphs::{TreeGlyphs, TREE_GLYPH_SETS};
+use views::Views;
+pub use views::{ViewColumns, ViewPreset, VIEW_COLUMNS};
+
+/* Matches the ticker interval in main.rs */
+const SAMPLE_INTERVAL: Duration = Duration::from_millis(1600);
+
+/* Rows formatted above and below the current page. Column widths fit the formatted rows, so this
+ * keeps them from changing on every page */
+const OVERSCAN: usize = 16;
+
+/* Background of the state of zombie processes */
+const ZOMBIE_BG: Color = Color::Byte(178); // Gold3
+
+/* Foreground of the rows of processes that use more than the memory threshold */
+const MEM_HOG_FG: Color = Color::Red;
+
+/* The cpu usage below which i dims processes, in hundredths of a percent, unless `--dim-below`
+ * gives another */
+const DEFAULT_IDLE_THRESHOLD: usize = 50;
+
+/* Minimum cpu usages c cycles through, in hundredths of a percent, and the mark of the process
+ * that's only listed because it's under the cursor */
+const MIN_CPU_STEPS: &[usize] = &[10, 100, 500, 1000, 2500];
+const MIN_CPU_KEPT_MARKER: &str = "* ";
+
+/* Columns the command line is indented by on the second line of rows, with `--two-line` */
+const SECOND_LINE_INDENT: usize = 4;
+
+/* Background of the row under the mouse pointer, fainter than the cursor's in the default theme */
+const HOVER_BG: Color = Color::Byte(234);
+
+/* Foreground of the shortcuts `--read-only` disables in the help overlay */
+const DISABLED_FG: Color = Color::Byte(243);
+
+/* Drawn in the middle of the rows when there are none, eg. because the filter matches nothing */
+const EMPTY_STATE: &str = "No matching processes";
+
+/// Files of `/proc/[pid]` that are only read when a column needs them, since each is another file
+/// to open for every process.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct ExtraReads {
+    /// `cgroup`, for the cgroup column.
+    pub cgroups: bool,
+    /// `io`, for the I/O rate columns.
+    pub io: bool,
+    /// The cpu affinity, for the affinity column.
+    pub affinity: bool,
+    /// `ns/pid`, for `--pid-ns`.
+    pub pid_ns: bool,
+}
+
+/* The cpu percentage and I/O rates of a process over an interval */
+type Rates = (Option<usize>, Option<IoRates>);
+
+/* `cpu_stat`, `processes_times` and `processes_io` hold the values of the snapshot preceding the
+ * current one, and `rates` the cpu percentages and I/O rates of the last snapshot that came over a
+ * `usable_interval`. The per-process values are keyed by PID and paired with the start time of the
+ * process, see `previous`. */
 #[derive(Debug)]
 pub struct ProcessData {
     cpu_stat: Stat,
-    processes_times: HashMap<Pid, usize>,
+    processes_times: HashMap<Pid, (usize, usize)>,
+    processes_io: HashMap<Pid, (usize, IoCounters)>,
+    rates: HashMap<Pid, (usize, Rates)>,
     parents: HashMap<Pid, Vec<Pid>>,
     processes_index: HashMap<Pid, usize>,
     tree_index: HashMap<Pid, usize>,
     tree: Vec<(usize, Pid)>,
+    /* looking up a user reads the password database, so names are kept across snapshots */
+    usernames: HashMap<u32, String>,
+    /* fill in `ProcessDisplay::elapsed`, for the elapsed time column */
+    elapsed: bool,
+    /* fill in `ProcessDisplay::io`, for the I/O rate columns */
+    io: bool,
+    /* fill in `ProcessDisplay::threads`, for the thread count column */
+    threads: bool,
+    /* fill in `ProcessDisplay::cpu_time`, for the cpu time column */
+    cpu_time: bool,
+    /* fill in `ProcessDisplay::caps`, for the capabilities column */
+    caps: bool,
+    /* fill in `ProcessDisplay::delta` against this, for the delta columns */
+    baseline: Option<Baseline>,
+    /* only link processes to parents in the same PID namespace in the tree, see `pid_ns` */
+    pid_ns: bool,
 }
 
-const SIGNAL_LIST: &[(i32, &'static str)] = &[
+impl ProcessData {
+    fn new(cpu_stat: Stat) -> ProcessData {
+        ProcessData {
+            cpu_stat,
+            processes_times: Default::default(),
+            processes_io: Default::default(),
+            rates: Default::default(),
+            processes_index: Default::default(),
+            tree_index: Default::default(),
+            parents: Default::default(),
+            tree: Default::default(),
+            usernames: Default::default(),
+            elapsed: false,
+            io: false,
+            threads: false,
+            cpu_time: false,
+            caps: false,
+            baseline: None,
+            pid_ns: false,
+        }
+    }
+
+    /* Use `prev` as the reference for cpu usage calculations of the next snapshot */
+    fn set_previous(&mut self, prev: &Snapshot) {
+        self.cpu_stat = prev.cpu_stat;
+        self.processes_times = prev
+            .processes
+            .iter()
+            .map(|p| (p.pid, (p.start_time, p.rtime)))
+            .collect();
+        self.processes_io = prev
+            .processes
+            .iter()
+            .filter_map(|p| p.io.map(|io| (p.pid, (p.start_time, io))))
+            .collect();
+    }
+}
+
+const SIGNAL_LIST: &[(i32, &str)] = &[
     (1, "1 HUP"),
     (2, "2 INT"),
     (3, "3 QUIT"),
@@ -69,6 +257,236 @@ const SIGNAL_LIST: &[(i32, &'static str)] = &[
     (31, "31 SYS"),
 ];
 
+/* What the kill menu sends its signal to */
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum SignalTarget {
+    Process(Pid),
+    /* every process in the group with this id */
+    Group(Pid),
+}
+
+impl SignalTarget {
+    /* The pid argument of kill(2), which addresses process groups by their negated id */
+    fn raw(self) -> Pid {
+        match self {
+            SignalTarget::Process(pid) => pid,
+            SignalTarget::Group(pgrp) => -pgrp,
+        }
+    }
+}
+
+impl fmt::Display for SignalTarget {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SignalTarget::Process(pid) => write!(f, "PID {}", pid),
+            SignalTarget::Group(pgrp) => write!(f, "process group {}", pgrp),
+        }
+    }
+}
+
+/* Send `signal` to `target` through `kill`, which is `nix::sys::signal::kill` outside of tests.
+ *
+ * kill(2) only fails for a process group if none of its members could be signalled, so on EPERM
+ * the known `members` are probed with the null signal to report the ones we lack permission for.
+ * Groups 0 and 1 are refused, since kill(2) gives them special meanings.
+ */
+fn send_signal<F>(
+    target: SignalTarget,
+    signal: nix::sys::signal::Signal,
+    members: &[Pid],
+    mut kill: F,
+) -> Result<(), String>
+where
+    F: FnMut(nix::unistd::Pid, Option<nix::sys::signal::Signal>) -> nix::Result<()>,
+{
+    if let SignalTarget::Group(pgrp) = target {
+        /* kill(2) reads -1 as every process we are allowed to signal, and 0 as our own group */
+        if pgrp <= 1 {
+            return Err(format!("refusing to signal {}", target));
+        }
+    }
+    let eperm = nix::Error::Sys(nix::errno::Errno::EPERM);
+    match kill(nix::unistd::Pid::from_raw(target.raw()), Some(signal)) {
+        Ok(()) => Ok(()),
+        Err(err) if err == eperm && matches!(target, SignalTarget::Group(_)) => {
+            let denied = members
+                .iter()
+                .filter(|pid| kill(nix::unistd::Pid::from_raw(**pid), None) == Err(eperm))
+                .map(|pid| pid.to_string())
+                .collect::<Vec<String>>();
+            if denied.is_empty() {
+                Err(format!("cannot signal {}: {}", target, err))
+            } else {
+                Err(format!(
+                    "cannot signal {}: not permitted for PID {}",
+                    target,
+                    denied.join(", ")
+                ))
+            }
+        }
+        Err(err) => Err(format!("cannot signal {}: {}", target, err)),
+    }
+}
+
+/* The shortcuts that affect processes, which `--read-only` takes out of the ones keys are
+ * dispatched to. The hook command is one of them, since it's run on a PID and can signal it, eg.
+ * `--hook 'kill -STOP {pid}'`. */
+const PROCESS_ACTIONS: [&str; 4] = [
+    "kill process",
+    "signal process group",
+    "kill processes by name",
+    "run hook command",
+];
+
+/* "NAME [n]" for signal `n`, as shown in the kill menus */
+fn signal_name(n: u16) -> String {
+    if n == 0 {
+        "__".to_string()
+    } else if n < 32 {
+        format!("{} [{}]", SIGNAL_LIST[n as usize - 1].1, n)
+    } else {
+        format!("invalid [{}]", n)
+    }
+}
+
+/* Whether signal `n` can't be taken back: every signal but those that stop and continue a
+ * process, and those whose default action is to do nothing */
+fn is_destructive(n: u16) -> bool {
+    let harmless = [
+        nix::sys::signal::Signal::SIGSTOP,
+        nix::sys::signal::Signal::SIGCONT,
+        nix::sys::signal::Signal::SIGTSTP,
+        nix::sys::signal::Signal::SIGTTIN,
+        nix::sys::signal::Signal::SIGTTOU,
+        nix::sys::signal::Signal::SIGCHLD,
+        nix::sys::signal::Signal::SIGURG,
+        nix::sys::signal::Signal::SIGWINCH,
+    ];
+    !harmless.iter().any(|&signal| signal as i32 == n as i32)
+}
+
+/* Whether the kill menu asks before sending signal `n`. Pausing and resuming a process is never
+ * asked about, even with `confirm_kill`. */
+fn needs_confirmation(confirm_kill: bool, n: u16) -> bool {
+    confirm_kill && is_destructive(n)
+}
+
+/* Foreground and background of row `i`. The cursor takes priority over the located PID, which takes
+ * priority over the hovered row, and all of them over the stripes of the odd rows. */
+fn row_colors_at(
+    i: usize,
+    cursor: usize,
+    located: bool,
+    hovered: Option<usize>,
+    stripe: Option<Color>,
+    selection: (Color, Color),
+) -> (Color, Color) {
+    if i == cursor {
+        selection
+    } else if located {
+        (Color::Red, Color::Yellow)
+    } else if hovered == Some(i) {
+        (Color::Default, HOVER_BG)
+    } else {
+        match stripe {
+            Some(stripe) if i % 2 == 1 => (Color::Default, stripe),
+            _ => (Color::Default, Color::Default),
+        }
+    }
+}
+
+/* Set the foreground of the cells of `area` that have `from` as their foreground to `to` */
+fn recolor_fg(grid: &mut CellBuffer, area: Area, from: Color, to: Color) {
+    for y in get_y(upper_left!(area))..=get_y(bottom_right!(area)) {
+        for x in get_x(upper_left!(area))..=get_x(bottom_right!(area)) {
+            if let Some(cell) = grid.get_mut(x, y).filter(|cell| cell.fg() == from) {
+                cell.set_fg(to);
+            }
+        }
+    }
+}
+
+/* First row and length of the scrollbar thumb on a `track` rows long, for a list of `total` rows
+ * scrolled down by `offset` rows, `visible` of which fit. `None` if the whole list fits, or there's
+ * no track. The thumb is at least one row long, and only touches the ends of the track at the ends
+ * of the list. */
+fn scrollbar_thumb(
+    track: usize,
+    offset: usize,
+    visible: usize,
+    total: usize,
+) -> Option<(usize, usize)> {
+    if track == 0 || total <= visible {
+        return None;
+    }
+    let len = (track * visible / total).clamp(1, track);
+    let max_offset = total - visible;
+    let free = track - len;
+    let start = if offset >= max_offset {
+        free
+    } else {
+        let start = (free * offset + max_offset / 2) / max_offset;
+        if offset > 0 && free >= 2 {
+            start.clamp(1, free - 1)
+        } else {
+            start
+        }
+    };
+    Some((start, len))
+}
+
+/* The shell command equivalent to sending signal `n` to `targets`, eg. `kill -TERM 1234 1235`. A
+ * signal of 0 stands for none chosen yet, and is left to kill's default of SIGTERM. */
+fn kill_command(n: u16, targets: &[SignalTarget]) -> String {
+    let mut ret = "kill".to_string();
+    if n > 0 && n < 32 {
+        let name = SIGNAL_LIST[n as usize - 1].1.split(' ').nth(1).unwrap();
+        ret.push_str(&format!(" -{}", name));
+    } else if n > 0 {
+        ret.push_str(&format!(" -{}", n));
+    }
+    /* so that negated group ids aren't parsed as signals */
+    if targets.iter().any(|t| matches!(t, SignalTarget::Group(_))) {
+        ret.push_str(" --");
+    }
+    for t in targets {
+        ret.push_str(&format!(" {}", t.raw()));
+    }
+    ret
+}
+
+/* The name of a process as `ps -o comm` would show it: the file name of its executable */
+fn process_name(cmd_line: &str) -> &str {
+    let exe = cmd_line.split(' ').next().unwrap_or_default();
+    exe.rsplit('/').next().unwrap_or(exe)
+}
+
+/* PIDs of `processes` whose name contains `pattern`, or is `pattern` if `exact`, in ascending
+ * order. `exclude` is left out, so that bb doesn't signal itself. */
+fn processes_named(
+    processes: &[ProcessDisplay],
+    pattern: &str,
+    exact: bool,
+    exclude: Pid,
+) -> Vec<Pid> {
+    let mut ret = processes
+        .iter()
+        /* requested PIDs that don't exist */
+        .filter(|p| p.state != State::Dead && p.i != exclude && p.members == 0)
+        .filter(|p| {
+            let name = process_name(&p.cmd_line.0);
+            if exact {
+                name == pattern
+            } else {
+                name.contains(pattern)
+            }
+        })
+        .map(|p| p.i)
+        .collect::<Vec<Pid>>();
+    ret.sort_unstable();
+    ret
+}
+
 /* Hold maximum width for each column */
 #[derive(Debug)]
 pub struct ColumnWidthMaxima {
@@ -78,6 +496,40 @@ pub struct ColumnWidthMaxima {
     cpu_percent: usize,
     state: usize,
     username: usize,
+    /* whether to show the cpu history column, which is `HISTORY_LEN` wide */
+    sparkline: bool,
+    /* `None` hides the column, which is the case unless thread counts are shown */
+    threads: Option<usize>,
+    /* `None` hides the column, which is the case unless cpu times are shown */
+    cpu_time: Option<usize>,
+    /* `None` hides the column, which is the case unless elapsed times are computed */
+    elapsed: Option<usize>,
+    /* widths of the read and write rate columns, `None` unless I/O is read */
+    io: Option<(usize, usize)>,
+    /* widths of the cpu time and memory delta columns, `None` unless a baseline is pinned */
+    delta: Option<(usize, usize)>,
+    /* `None` hides the column, which is the case unless cgroups are read */
+    cgroup: Option<usize>,
+    /* `None` hides the column, which is the case unless affinities are read */
+    affinity: Option<usize>,
+    /* `None` hides the column, which is the case unless capabilities are shown */
+    caps: Option<usize>,
+    /* the environment variable and the width of its column, `None` unless it's shown, see
+     * `fit_env` */
+    env: Option<(String, usize)>,
+    /* whether to show the socket column, which is `sockets::COLUMN_WIDTH` wide */
+    net: bool,
+    /* whether to show the GPU columns, which are `gpu::MEM_WIDTH` and `gpu::UTIL_WIDTH` wide */
+    gpu: bool,
+    /* how the cpu percentages are rounded, all of their digits are shown if `None` */
+    percent_format: Option<PercentFormat>,
+    /* how the I/O rates are written, see `fit_notation` */
+    notation: Notation,
+    alignments: Alignments,
+    /* the order of the optional columns and which are hidden, see `column_picker` */
+    columns: ColumnConfig,
+    /* which way the cpu history column goes, for its header */
+    graph_direction: GraphDirection,
 }
 
 impl ColumnWidthMaxima {
@@ -89,14 +541,579 @@ impl ColumnWidthMaxima {
             cpu_percent: " CPU%".len(),
             state: 1,
             username: "USER".len(),
+            sparkline: false,
+            threads: None,
+            cpu_time: None,
+            elapsed: None,
+            io: None,
+            delta: None,
+            cgroup: None,
+            affinity: None,
+            caps: None,
+            env: None,
+            net: false,
+            gpu: false,
+            percent_format: None,
+            notation: Notation::default(),
+            alignments: Alignments::default(),
+            columns: ColumnConfig::default(),
+            graph_direction: GraphDirection::NewestRight,
+        }
+    }
+
+    /* Widen the PID columns to fit `max_pid`, so that they don't change width while scrolling
+     * through the list when `pid_max` has been raised past 5 digits */
+    fn fit_pids(&mut self, max_pid: Pid) {
+        let digits = max_pid.to_string().len();
+        self.pid = std::cmp::max(self.pid, digits);
+        self.ppid = std::cmp::max(self.ppid, digits);
+    }
+
+    /* Whether the values of an optional `column` are read, and it's drawn unless it's hidden */
+    fn is_read(&self, column: Column) -> bool {
+        match column {
+            Column::Threads => self.threads.is_some(),
+            Column::CpuTime => self.cpu_time.is_some(),
+            Column::Elapsed => self.elapsed.is_some(),
+            Column::Io => self.io.is_some(),
+            Column::Delta => self.delta.is_some(),
+            Column::Cgroup => self.cgroup.is_some(),
+            Column::Affinity => self.affinity.is_some(),
+            Column::Caps => self.caps.is_some(),
+            Column::Env => self.env.is_some(),
+            Column::Net => self.net,
+            Column::Gpu => self.gpu,
+            _ => true,
+        }
+    }
+
+    /* The header of the CSV export: the columns `row` draws, the optional ones in the order of the
+     * column picker, and the command line, without the cpu history. `pid_ns` adds the PID
+     * namespaces, which the list only tells apart by color or order. */
+    fn csv_header(&self, pid_ns: bool) -> Vec<String> {
+        let labels = |labels: &[&str]| labels.iter().map(|l| l.to_string()).collect::<Vec<_>>();
+        let mut ret = labels(&["PID", "PPID", "USER", "VM_RSS", "CPU%", "STATE"]);
+        if pid_ns {
+            ret.push("PID_NS".to_string());
+        }
+        let optional = OPTIONAL_COLUMNS
+            .iter()
+            .filter(|&&column| self.is_read(column))
+            .map(|&column| {
+                let cells = match column {
+                    Column::Threads => labels(&["THREADS"]),
+                    Column::CpuTime => labels(&["TIME+"]),
+                    Column::Elapsed => labels(&["ELAPSED"]),
+                    Column::Io => labels(&["READ_BYTES/s", "WRITE_BYTES/s"]),
+                    Column::Delta => labels(&["+CPU", "+MEM"]),
+                    Column::Cgroup => labels(&["CGROUP"]),
+                    Column::Affinity => labels(&["AFFINITY"]),
+                    Column::Caps => labels(&["CAPS"]),
+                    Column::Env => vec![self.env.as_ref().map_or(String::new(), |e| e.0.clone())],
+                    Column::Net => labels(&["NET"]),
+                    Column::Gpu => labels(&["GPU_MEM", "GPU%"]),
+                    _ => vec![],
+                };
+                (column, cells)
+            })
+            .collect();
+        ret.extend(self.columns.order(optional).into_iter().flatten());
+        ret.push("CMD_LINE".to_string());
+        ret
+    }
+
+    /* The fields of `p` under `csv_header`. Counts, times and rates are written as plain numbers,
+     * in seconds and bytes, the rest as the list shows them. */
+    fn csv_row(&self, p: &ProcessDisplay, pid_ns: bool) -> Vec<String> {
+        let number = |n: Option<String>| n.unwrap_or_default();
+        let mut ret = vec![
+            p.pid.0.clone(),
+            p.ppid.0.clone(),
+            p.username.0.clone(),
+            p.vm_rss.0.clone(),
+            number(
+                p.cpu_percent_raw
+                    .map(|cpu_percent| (cpu_percent as f64 / 100.0).to_string()),
+            ),
+            p.state.to_string(),
+        ];
+        if pid_ns {
+            ret.push(number(p.pid_ns.map(|ns| ns.to_string())));
+        }
+        let optional = OPTIONAL_COLUMNS
+            .iter()
+            .filter(|&&column| self.is_read(column))
+            .map(|&column| {
+                let cells =
+                    match column {
+                        Column::Threads => vec![number(p.threads.map(|n| n.to_string()))],
+                        Column::CpuTime => vec![number(p.cpu_time.map(|ticks| {
+                            (ticks as f64 / clock_ticks().max(1) as f64).to_string()
+                        }))],
+                        Column::Elapsed => vec![number(p.elapsed.map(|secs| secs.to_string()))],
+                        Column::Io => match p.io.flatten() {
+                            Some(rates) => vec![rates.read.to_string(), rates.write.to_string()],
+                            None => vec![String::new(), String::new()],
+                        },
+                        Column::Delta => match p.delta {
+                            Some(delta) => {
+                                let (cpu, mem) = delta_strings(delta, clock_ticks());
+                                vec![cpu, mem]
+                            }
+                            None => vec![String::new(), String::new()],
+                        },
+                        Column::Cgroup => vec![p
+                            .cgroup
+                            .as_ref()
+                            .map_or(String::new(), |cgroup| cgroup.0.clone())],
+                        Column::Affinity => vec![p.affinity.clone().unwrap_or_default()],
+                        Column::Caps => vec![number(p.caps.map(caps_label))],
+                        Column::Env => vec![p.env.clone().unwrap_or_default()],
+                        Column::Net => vec![p.net.map_or("", NetState::label).to_string()],
+                        Column::Gpu => match p.gpu {
+                            Some(usage) => vec![
+                                usage.memory.to_string(),
+                                number(usage.utilization.map(|percent| percent.to_string())),
+                            ],
+                            None => vec![String::new(), String::new()],
+                        },
+                        _ => vec![],
+                    };
+                (column, cells)
+            })
+            .collect();
+        ret.extend(self.columns.order(optional).into_iter().flatten());
+        ret.push(p.cmd_line.0.clone());
+        ret
+    }
+
+    /* Show the column of the environment variable `key`, wide enough for its name and the values
+     * of the processes measured by `from_processes` */
+    fn fit_env(&mut self, key: &str) {
+        let width = self.env.take().map_or(0, |(_, width)| width);
+        self.env = Some((key.to_string(), std::cmp::max(width, key.len())));
+    }
+
+    /* Write the I/O rates in `notation`, measuring their columns again for the `processes` measured
+     * by `from_processes` */
+    fn fit_notation<'a>(
+        &mut self,
+        notation: Notation,
+        processes: impl IntoIterator<Item = &'a ProcessDisplay>,
+    ) {
+        self.notation = notation;
+        if self.io.is_none() || notation == Notation::default() {
+            return;
+        }
+        self.io = None;
+        for p in processes {
+            self.fit_io(p);
+        }
+    }
+
+    fn fit_io(&mut self, p: &ProcessDisplay) {
+        if let Some(rates) = p.io {
+            let (read, write) = self.io.unwrap_or(("READ/s".len(), "WRITE/s".len()));
+            let (read_rate, write_rate) = io_rate_strings(rates, self.notation);
+            self.io = Some((
+                std::cmp::max(read, read_rate.len()),
+                std::cmp::max(write, write_rate.len()),
+            ));
+        }
+    }
+
+    /* Keep tabs on biggest element in each column */
+    fn from_processes<'a>(
+        processes: impl IntoIterator<Item = &'a ProcessDisplay>,
+    ) -> ColumnWidthMaxima {
+        let mut ret = ColumnWidthMaxima::new();
+        for p in processes {
+            ret.pid = std::cmp::max(ret.pid, p.pid.len());
+            ret.ppid = std::cmp::max(ret.ppid, p.ppid.len());
+            ret.vm_rss = std::cmp::max(ret.vm_rss, p.vm_rss.len());
+            ret.username = std::cmp::max(ret.username, p.username.0.grapheme_width());
+            ret.sparkline |= p.sparkline.is_some();
+            if let Some(threads) = p.threads {
+                ret.threads = Some(std::cmp::max(
+                    ret.threads.unwrap_or("THREADS".len()),
+                    threads.to_string().len(),
+                ));
+            }
+            if let Some(ticks) = p.cpu_time {
+                ret.cpu_time = Some(std::cmp::max(
+                    ret.cpu_time.unwrap_or("TIME+".len()),
+                    format_cpu_time(ticks, clock_ticks()).len(),
+                ));
+            }
+            if let Some(elapsed) = p.elapsed {
+                ret.elapsed = Some(std::cmp::max(
+                    ret.elapsed.unwrap_or("ELAPSED".len()),
+                    format_elapsed(elapsed).len(),
+                ));
+            }
+            ret.fit_io(p);
+            if let Some(delta) = p.delta {
+                let (cpu, mem) = ret.delta.unwrap_or(("+CPU".len(), "+MEM".len()));
+                let (cpu_delta, mem_delta) = delta_strings(delta, clock_ticks());
+                ret.delta = Some((
+                    std::cmp::max(cpu, cpu_delta.len()),
+                    std::cmp::max(mem, mem_delta.len()),
+                ));
+            }
+            if let Some(ref cgroup) = p.cgroup {
+                ret.cgroup = Some(std::cmp::max(
+                    ret.cgroup.unwrap_or("CGROUP".len()),
+                    cgroup.0.grapheme_width(),
+                ));
+            }
+            if let Some(ref affinity) = p.affinity {
+                ret.affinity = Some(std::cmp::max(
+                    ret.affinity.unwrap_or("AFFINITY".len()),
+                    affinity.len(),
+                ));
+            }
+            if let Some(mask) = p.caps {
+                ret.caps = Some(std::cmp::max(
+                    ret.caps.unwrap_or("CAPS".len()),
+                    caps_label(mask).len(),
+                ));
+            }
+            if let Some(ref value) = p.env {
+                let width = ret.env.as_ref().map_or(0, |(_, width)| *width);
+                ret.env = Some((String::new(), std::cmp::max(width, value.grapheme_width())));
+            }
+        }
+        ret
+    }
+
+    fn header(&self, sort: Sort, cmd_line: &str) -> String {
+        let a = &self.alignments;
+        let marker = |asc: bool, desc: bool| {
+            if asc {
+                "↑"
+            } else if desc {
+                "↓"
+            } else {
+                " "
+            }
+        };
+        format!(
+            "{pid}  {ppid}  {username} {vm_rss} {cpu_percent} {state}  {sparkline}{optional}{cmd_line}{cmd_linesort}",
+            pid = a.pad(Column::Pid, "PID", self.pid),
+            ppid = a.pad(Column::Ppid, "PPID", self.ppid),
+            username = header_cell(a.get(Column::User), "USER", marker(matches!(sort, Sort::UserAsc), matches!(sort, Sort::UserDesc)), self.username),
+            vm_rss = header_cell(a.get(Column::VmRss), "VM_RSS", marker(matches!(sort, Sort::VmRssAsc), matches!(sort, Sort::VmRssDesc)), self.vm_rss),
+            cpu_percent = header_cell(a.get(Column::Cpu), "CPU%", marker(matches!(sort, Sort::CpuAsc), matches!(sort, Sort::CpuDesc)), self.cpu_percent + 1),
+            state = a.pad(Column::State, " ", self.state),
+            sparkline = if self.sparkline {
+                format!("{:<width$}  ", self.graph_direction.label(), width = HISTORY_LEN)
+            } else {
+                String::new()
+            },
+            optional = self.columns.arrange(vec![
+                (Column::Threads, match self.threads {
+                    Some(width) => format!("{} ", header_cell(a.get(Column::Threads), "THREADS", marker(matches!(sort, Sort::ThreadsAsc), matches!(sort, Sort::ThreadsDesc)), width)),
+                    None => String::new(),
+                }),
+                (Column::CpuTime, match self.cpu_time {
+                    Some(width) => format!("{} ", header_cell(a.get(Column::CpuTime), "TIME+", marker(matches!(sort, Sort::CpuTimeAsc), matches!(sort, Sort::CpuTimeDesc)), width)),
+                    None => String::new(),
+                }),
+                (Column::Elapsed, match self.elapsed {
+                    /* the elapsed times go down as the start times go up */
+                    Some(width) => format!("{} ", header_cell(a.get(Column::Elapsed), "ELAPSED", marker(matches!(sort, Sort::StartDesc), matches!(sort, Sort::StartAsc)), width)),
+                    None => String::new(),
+                }),
+                (Column::Io, match self.io {
+                    Some((read, write)) => format!(
+                        "{} {} ",
+                        header_cell(a.get(Column::Io), "READ/s", marker(matches!(sort, Sort::ReadAsc | Sort::IoAsc), matches!(sort, Sort::ReadDesc | Sort::IoDesc)), read),
+                        header_cell(a.get(Column::Io), "WRITE/s", marker(matches!(sort, Sort::WriteAsc | Sort::IoAsc), matches!(sort, Sort::WriteDesc | Sort::IoDesc)), write),
+                    ),
+                    None => String::new(),
+                }),
+                (Column::Delta, match self.delta {
+                    Some((cpu, mem)) => format!("{}  {}  ", a.pad(Column::Delta, "+CPU", cpu), a.pad(Column::Delta, "+MEM", mem)),
+                    None => String::new(),
+                }),
+                (Column::Cgroup, match self.cgroup {
+                    Some(width) => format!("{}  ", a.pad(Column::Cgroup, "CGROUP", width)),
+                    None => String::new(),
+                }),
+                (Column::Affinity, match self.affinity {
+                    Some(width) => format!("{}  ", a.pad(Column::Affinity, "AFFINITY", width)),
+                    None => String::new(),
+                }),
+                (Column::Caps, match self.caps {
+                    Some(width) => format!("{}  ", a.pad(Column::Caps, "CAPS", width)),
+                    None => String::new(),
+                }),
+                (Column::Env, match self.env {
+                    Some((ref key, width)) => format!("{}  ", a.pad(Column::Env, key, width)),
+                    None => String::new(),
+                }),
+                (Column::Net, if self.net {
+                    format!("{}  ", a.pad(Column::Net, "NET", sockets::COLUMN_WIDTH))
+                } else {
+                    String::new()
+                }),
+                (Column::Gpu, if self.gpu {
+                    format!("{}  {}  ", a.pad(Column::Gpu, "GPU_MEM", gpu::MEM_WIDTH), a.pad(Column::Gpu, "GPU%", gpu::UTIL_WIDTH))
+                } else {
+                    String::new()
+                }),
+            ]),
+            cmd_line = cmd_line,
+            cmd_linesort = if let Sort::CmdLineAsc = sort { "↑" } else if let Sort::CmdLineDesc = sort { "↓" } else { "" },
+        )
+    }
+
+    /* Columns of a process row before the cpu history column, if it's shown */
+    fn sparkline_offset(&self) -> Option<usize> {
+        if !self.sparkline {
+            return None;
         }
+        Some(
+            self.pid
+                + self.ppid
+                + self.username
+                + self.vm_rss
+                + self.cpu_percent
+                + 1
+                + self.state
+                + 6 * "  ".len(),
+        )
+    }
+
+    /* Offset and width of the memory column in a process row */
+    fn vm_rss_span(&self) -> (usize, usize) {
+        (
+            self.pid + self.ppid + self.username + 3 * "  ".len(),
+            self.vm_rss,
+        )
+    }
+
+    /* Offset and width of the cpu usage column in a process row */
+    fn cpu_span(&self) -> (usize, usize) {
+        let (offset, width) = self.vm_rss_span();
+        (offset + width + "  ".len(), self.cpu_percent + 1)
+    }
+
+    /* Every column of a process row except for the command line */
+    fn row(&self, p: &ProcessDisplay) -> String {
+        let a = &self.alignments;
+        let dash = "-".to_string();
+        format!(
+            "{pid}  {ppid}  {username}  {vm_rss}  {cpu_percent}  {state}  {sparkline}{optional}",
+            pid = a.pad(Column::Pid, &p.pid.0, self.pid),
+            ppid = a.pad(Column::Ppid, &p.ppid.0, self.ppid),
+            username = a.pad(Column::User, &p.username.0, self.username),
+            vm_rss = a.pad(Column::VmRss, &p.vm_rss.0, self.vm_rss),
+            cpu_percent = a.pad(
+                Column::Cpu,
+                &match p.cpu_percent {
+                    Some(cpu_percent) => match self.percent_format {
+                        Some(percent_format) => percent_format.format(cpu_percent),
+                        None => format!("{}%", cpu_percent as f64 / 100.0),
+                    },
+                    /* no usage until the process has been seen in two snapshots */
+                    None => "--".to_string(),
+                },
+                self.cpu_percent + 1,
+            ),
+            state = a.pad(Column::State, &p.state.to_string(), self.state),
+            sparkline = match (self.sparkline, &p.sparkline) {
+                (true, Some(sparkline)) => format!("{}  ", sparkline),
+                (true, None) => format!("{:width$}  ", "", width = HISTORY_LEN),
+                (false, _) => String::new(),
+            },
+            optional = self.columns.arrange(vec![
+                (
+                    Column::Threads,
+                    match self.threads {
+                        Some(width) => format!(
+                            "{}  ",
+                            a.pad(
+                                Column::Threads,
+                                &p.threads
+                                    .map_or(dash.clone(), |threads| threads.to_string()),
+                                width
+                            )
+                        ),
+                        None => String::new(),
+                    }
+                ),
+                (
+                    Column::CpuTime,
+                    match self.cpu_time {
+                        Some(width) => format!(
+                            "{}  ",
+                            a.pad(
+                                Column::CpuTime,
+                                &p.cpu_time.map_or(dash.clone(), |ticks| format_cpu_time(
+                                    ticks,
+                                    clock_ticks()
+                                )),
+                                width
+                            )
+                        ),
+                        None => String::new(),
+                    }
+                ),
+                (
+                    Column::Elapsed,
+                    match self.elapsed {
+                        Some(width) => format!(
+                            "{}  ",
+                            a.pad(
+                                Column::Elapsed,
+                                &p.elapsed.map_or(dash.clone(), format_elapsed),
+                                width
+                            )
+                        ),
+                        None => String::new(),
+                    }
+                ),
+                (
+                    Column::Io,
+                    match self.io {
+                        Some((read, write)) => {
+                            let (read_rate, write_rate) =
+                                p.io.map_or((dash.clone(), dash.clone()), |rates| {
+                                    io_rate_strings(rates, self.notation)
+                                });
+                            format!(
+                                "{}  {}  ",
+                                a.pad(Column::Io, &read_rate, read),
+                                a.pad(Column::Io, &write_rate, write)
+                            )
+                        }
+                        None => String::new(),
+                    }
+                ),
+                (
+                    Column::Delta,
+                    match self.delta {
+                        Some((cpu, mem)) => {
+                            let (cpu_delta, mem_delta) =
+                                p.delta.map_or((dash.clone(), dash.clone()), |delta| {
+                                    delta_strings(delta, clock_ticks())
+                                });
+                            format!(
+                                "{}  {}  ",
+                                a.pad(Column::Delta, &cpu_delta, cpu),
+                                a.pad(Column::Delta, &mem_delta, mem)
+                            )
+                        }
+                        None => String::new(),
+                    }
+                ),
+                (
+                    Column::Cgroup,
+                    match self.cgroup {
+                        Some(width) => format!(
+                            "{}  ",
+                            a.pad(
+                                Column::Cgroup,
+                                p.cgroup.as_ref().map_or("", |cgroup| &cgroup.0),
+                                width
+                            )
+                        ),
+                        None => String::new(),
+                    }
+                ),
+                (
+                    Column::Affinity,
+                    match self.affinity {
+                        Some(width) => format!(
+                            "{}  ",
+                            a.pad(Column::Affinity, p.affinity.as_deref().unwrap_or(""), width)
+                        ),
+                        None => String::new(),
+                    }
+                ),
+                (
+                    Column::Caps,
+                    match self.caps {
+                        Some(width) => format!(
+                            "{}  ",
+                            a.pad(
+                                Column::Caps,
+                                &p.caps.map_or(dash.clone(), caps_label),
+                                width
+                            )
+                        ),
+                        None => String::new(),
+                    }
+                ),
+                (
+                    Column::Env,
+                    match self.env {
+                        Some((_, width)) => format!(
+                            "{}  ",
+                            a.pad(Column::Env, p.env.as_deref().unwrap_or(""), width)
+                        ),
+                        None => String::new(),
+                    }
+                ),
+                (
+                    Column::Net,
+                    if self.net {
+                        format!(
+                            "{}  ",
+                            a.pad(
+                                Column::Net,
+                                p.net.map_or("", NetState::label),
+                                sockets::COLUMN_WIDTH
+                            )
+                        )
+                    } else {
+                        String::new()
+                    }
+                ),
+                (
+                    Column::Gpu,
+                    if self.gpu {
+                        let (memory, utilization) = GpuUsage::labels(p.gpu);
+                        format!(
+                            "{}  {}  ",
+                            a.pad(Column::Gpu, &memory, gpu::MEM_WIDTH),
+                            a.pad(Column::Gpu, &utilization, gpu::UTIL_WIDTH)
+                        )
+                    } else {
+                        String::new()
+                    }
+                ),
+            ]),
+        )
+    }
+}
+
+/* A column header followed by its sort `marker`, the arrow of the column the list is sorted by or a
+ * space. Left-aligned headers keep the arrow next to their label. */
+fn header_cell(alignment: Alignment, label: &str, marker: &str, width: usize) -> String {
+    match alignment {
+        Alignment::Left => alignment.pad(&format!("{}{}", label, marker), width + 1),
+        Alignment::Right => format!("{}{}", alignment.pad(label, width), marker),
+    }
+}
+
+/* The read and write rate columns of a process in `notation`, "-" if `/proc/[pid]/io` couldn't be
+ * read or there's no earlier snapshot to compare with */
+fn io_rate_strings(rates: Option<IoRates>, notation: Notation) -> (String, String) {
+    match rates {
+        Some(rates) => (
+            Bytes(rates.read as usize).in_notation(notation),
+            Bytes(rates.write as usize).in_notation(notation),
+        ),
+        None => ("-".to_string(), "-".to_string()),
     }
 }
 
 macro_rules! define_column_string {
     ($($typename: tt),+) => {
         $(
-          #[derive(Debug)]
+          #[derive(Debug, Clone)]
           pub struct $typename(pub String);
 
           impl $typename {
@@ -104,6 +1121,11 @@ macro_rules! define_column_string {
               pub fn len(&self) -> usize {
                   self.0.len()
               }
+
+              #[allow(dead_code)]
+              pub fn is_empty(&self) -> bool {
+                  self.0.is_empty()
+              }
           }
 
           impl std::fmt::Display for $typename {
@@ -123,12 +1145,13 @@ define_column_string!(
     PpidString,
     VmRssString,
     CmdLineString,
-    UserString
+    UserString,
+    CgroupString
 );
 
-pub type Pid = i32;
+pub type Pid = libc::pid_t;
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq)]
 enum Sort {
     UserAsc,
     UserDesc,
@@ -138,25 +1161,513 @@ enum Sort {
     CpuDesc,
     CmdLineAsc,
     CmdLineDesc,
+    /* rates of the I/O columns, `IoAsc` and `IoDesc` by their sum */
+    ReadAsc,
+    ReadDesc,
+    WriteAsc,
+    WriteDesc,
+    IoAsc,
+    IoDesc,
+    ThreadsAsc,
+    ThreadsDesc,
+    CpuTimeAsc,
+    CpuTimeDesc,
+    /* by the start times the elapsed times are from, `StartAsc` from the oldest process */
+    StartAsc,
+    StartDesc,
+    /* only a secondary sort, there's no PID column header to sort by */
+    PidAsc,
+    PidDesc,
+}
+
+/// What processes that tie on the sort order are ordered by, see `SecondarySort::parse`.
+#[derive(Debug, Copy, Clone)]
+pub struct SecondarySort(Sort);
+
+/// Keys of `--then-sort`.
+pub const SECONDARY_SORT_KEYS: &[&str] = &[
+    "pid", "cpu", "memory", "user", "command", "time", "threads", "start",
+];
+
+impl SecondarySort {
+    /// Parses the value of `--then-sort`: one of `SECONDARY_SORT_KEYS`, optionally followed by
+    /// `:asc` or `:desc`, eg. `memory:desc`. Ascending by default.
+    pub fn parse(spec: &str) -> Result<SecondarySort, String> {
+        let (asc, desc, descending) = parse_sort_spec(spec)?;
+        Ok(SecondarySort(if descending == Some(true) {
+            desc
+        } else {
+            asc
+        }))
+    }
+}
+
+/// The order the list starts sorted in, see `SortOrder::parse`.
+#[derive(Debug, Copy, Clone)]
+pub struct SortOrder(Sort);
+
+impl SortOrder {
+    /// Parses the value of `--sort`: a key of `--then-sort`, optionally followed by `:asc` or
+    /// `:desc`, eg. `cpu:asc`. Without one the usage keys, `cpu`, `memory`, `time` and `threads`,
+    /// are descending like the function keys sort them, and the others ascending.
+    pub fn parse(spec: &str) -> Result<SortOrder, String> {
+        let (asc, desc, descending) = parse_sort_spec(spec)?;
+        let usage = matches!(
+            asc,
+            Sort::CpuAsc | Sort::VmRssAsc | Sort::CpuTimeAsc | Sort::ThreadsAsc
+        );
+        Ok(SortOrder(if descending.unwrap_or(usage) {
+            desc
+        } else {
+            asc
+        }))
+    }
+}
+
+/* The ascending and descending sorts by the key of `spec`, and whether its direction is
+ * descending, if it's given. `mem` is short for `memory`. */
+fn parse_sort_spec(spec: &str) -> Result<(Sort, Sort, Option<bool>), String> {
+    let (key, direction) = match spec.split_once(':') {
+        Some((key, direction)) => (key, Some(direction)),
+        None => (spec, None),
+    };
+    let descending = match direction {
+        None => None,
+        Some("asc") => Some(false),
+        Some("desc") => Some(true),
+        Some(direction) => {
+            return Err(format!(
+                "unknown direction `{}`, expected asc or desc",
+                direction
+            ))
+        }
+    };
+    let (asc, desc) = match key {
+        "pid" => (Sort::PidAsc, Sort::PidDesc),
+        "cpu" => (Sort::CpuAsc, Sort::CpuDesc),
+        "memory" | "mem" => (Sort::VmRssAsc, Sort::VmRssDesc),
+        "user" => (Sort::UserAsc, Sort::UserDesc),
+        "command" => (Sort::CmdLineAsc, Sort::CmdLineDesc),
+        "time" => (Sort::CpuTimeAsc, Sort::CpuTimeDesc),
+        "threads" => (Sort::ThreadsAsc, Sort::ThreadsDesc),
+        "start" => (Sort::StartAsc, Sort::StartDesc),
+        _ => {
+            return Err(format!(
+                "unknown sort key `{}`, expected one of: {}",
+                key,
+                SECONDARY_SORT_KEYS.join(", ")
+            ))
+        }
+    };
+    Ok((asc, desc, descending))
 }
 
 /* Wrapper type for display strings */
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct ProcessDisplay {
     pub i: Pid,
     pub p: Pid,
+    pub pgrp: Pid,
     pub pid: PidString,
     pub ppid: PpidString,
     pub vm_rss: VmRssString,
     vm_rss_value: usize,
-    pub cpu_percent: usize,
+    /* smoothed if `--smooth` is given, see `smooth_cpu_percent`. `None` in the first snapshot the
+     * process is in, since there's nothing to measure its usage against */
+    pub cpu_percent: Option<usize>,
+    /* usage since the previous snapshot, which is what gets exported */
+    pub cpu_percent_raw: Option<usize>,
     pub state: State,
     pub cmd_line: CmdLineString,
     pub username: UserString,
+    /* recent cpu usage, `None` unless the cpu history column is shown, see `record_cpu_history` */
+    pub sparkline: Option<String>,
+    /* `None` unless the thread count column is shown, see `Process::threads` */
+    pub threads: Option<usize>,
+    /* clock ticks the process spent on a cpu, in user and kernel mode, `None` unless the cpu time
+     * column is shown */
+    pub cpu_time: Option<usize>,
+    /* seconds since the process started, `None` unless the elapsed time column is shown */
+    pub elapsed: Option<u64>,
+    /* `None` unless I/O is read. The inner `None` is for processes whose rates aren't known, see
+     * `io_rate_strings` */
+    pub io: Option<Option<IoRates>>,
+    /* change since the baseline, `None` unless one is pinned, see `Baseline::delta` */
+    pub delta: Option<Delta>,
+    /* `None` unless cgroups are read, see `Process::cgroup` */
+    pub cgroup: Option<CgroupString>,
+    /* the cpus the process may run on, `None` unless affinities are read, see
+     * `Process::affinity` */
+    pub affinity: Option<String>,
+    /* effective capabilities, `None` unless the capabilities column is shown, see
+     * `Process::cap_eff` */
+    pub caps: Option<u64>,
+    /* value of the environment variable of the column, `None` unless it's shown and until the row
+     * is formatted, see `format_window` */
+    pub env: Option<String>,
+    /* the TCP sockets of the process, `None` unless they're looked up and until the row is
+     * formatted, see `format_window` */
+    pub net: Option<NetState>,
+    /* what the process uses of the GPUs, `None` unless it uses one and they're looked up, see
+     * `Gpus::record` */
+    pub gpu: Option<GpuUsage>,
+    /* the inode of the PID namespace, `None` unless it's read, see `Process::pid_ns` */
+    pub pid_ns: Option<u64>,
+    pub category: Category,
     pub rtime: usize,
+    /* number of processes a row of the grouped view stands for, 0 for the rows of processes, see
+     * `regroup` */
+    members: usize,
+    /* whether `pid`, `ppid` and `vm_rss` are filled in, see `format` */
+    formatted: bool,
 }
 
-/* process list components */
+impl ProcessDisplay {
+    /* Placeholder for a requested PID that doesn't exist */
+    fn gone(pid: Pid) -> ProcessDisplay {
+        ProcessDisplay {
+            i: pid,
+            p: 0,
+            pgrp: 0,
+            pid: PidString(pid.to_string()),
+            ppid: PpidString("-".to_string()),
+            vm_rss: VmRssString("-".to_string()),
+            vm_rss_value: 0,
+            cpu_percent: None,
+            cpu_percent_raw: None,
+            state: State::Dead,
+            cmd_line: CmdLineString("<gone>".to_string()),
+            username: UserString("-".to_string()),
+            sparkline: None,
+            threads: None,
+            cpu_time: None,
+            elapsed: None,
+            io: None,
+            delta: None,
+            cgroup: None,
+            affinity: None,
+            caps: None,
+            env: None,
+            net: None,
+            gpu: None,
+            pid_ns: None,
+            category: Category::Other,
+            rtime: 0,
+            members: 0,
+            formatted: true,
+        }
+    }
+
+    /* `get` leaves the display strings empty, since only the rows around the current page are
+     * drawn. Fill them in, with the memory in `mem_format` of the `mem_total` bytes. */
+    fn format(&mut self, mem_format: MemFormat, mem_total: usize) {
+        if self.formatted {
+            return;
+        }
+        self.pid = PidString(self.i.to_string());
+        self.ppid = PpidString(self.p.to_string());
+        self.vm_rss = VmRssString(mem_format.format(self.vm_rss_value, mem_total));
+        self.formatted = true;
+    }
+}
+
+/// Settings of a `ProcessList`, see `ProcessList::new`.
+#[derive(Debug, Default)]
+pub struct ProcessListOptions {
+    pub pid_filter: PidFilter,
+    /// Processes listed above the others in the flat view, whatever the sort order. More can be
+    /// pinned by PID with P.
+    pub pins: Vec<Pin>,
+    /// Highlight the row under the mouse pointer and select rows by clicking.
+    pub hover: bool,
+    /// Convert command lines and filter/search terms to NFC.
+    pub normalize: bool,
+    /// Match filter/search terms exactly instead of ignoring case.
+    pub case_sensitive: bool,
+    /// Start with fuzzy matching of filter/search terms. It can be toggled with z.
+    pub fuzzy: bool,
+    /// Shorten command lines to this many columns.
+    pub max_cmd_len: Option<usize>,
+    /// How command lines that don't fit are shortened. `Truncation::End` without `max_cmd_len`
+    /// cuts them at the edge of the list without an ellipsis.
+    pub truncation: Truncation,
+    /// Weight of new samples in the smoothed cpu percentages, if enabled.
+    pub smoothing: Option<f64>,
+    pub sort_scroll: SortScroll,
+    /// Read the cgroup of every process, for the cgroup column.
+    pub cgroups: bool,
+    /// Read the I/O counters of every process, for the I/O rate columns.
+    pub io: bool,
+    /// Read the cpu affinity of every process, for the affinity column.
+    pub affinity: bool,
+    /// Show the elapsed time column.
+    pub elapsed: bool,
+    /// Show the thread count column.
+    pub threads: bool,
+    /// Show the cumulative cpu time column.
+    pub cpu_time: bool,
+    /// Show the effective capabilities column.
+    pub caps: bool,
+    /// Show a column with the value of this environment variable. Environments are only read for
+    /// the rows around the current page.
+    pub env_var: Option<String>,
+    /// Show a column with whether each process holds a listening or established TCP socket.
+    pub sockets: bool,
+    /// Foreground of the rows of processes holding listening or established TCP sockets, if
+    /// highlighted.
+    pub highlight_sockets: Option<Color>,
+    /// Show the GPU memory and utilization columns, if NVML finds a GPU.
+    pub gpu: bool,
+    /// Show a sparkline of the recent cpu usage of each process.
+    pub sparkline: bool,
+    /// Draw idle samples of the sparklines as a dim baseline instead of leaving them blank.
+    pub sparkline_baseline: bool,
+    /// Which side of the sparklines the newest sample is on.
+    pub graph_direction: GraphDirection,
+    /// Highlight new processes, fading to the normal foreground over this many samples.
+    pub fade_new: Option<usize>,
+    /// Highlight processes using more than this fraction of the total memory.
+    pub mem_threshold: Option<f64>,
+    /// Color the cpu usage column by percentage.
+    pub cpu_thresholds: Option<Thresholds>,
+    /// Color the memory column by percentage of the total memory.
+    pub mem_thresholds: Option<Thresholds>,
+    /// Highlight processes whose resident memory grew faster than this many bytes per second over
+    /// the last few samples.
+    pub mem_growth: Option<f64>,
+    /// Keep the rows of processes that exited for this many samples, greyed out.
+    pub tombstones: Option<usize>,
+    /// Let the cursor onto the rows of exited processes instead of skipping them.
+    pub select_tombstones: bool,
+    /// Move the cursor from the last row to the first with Down, and back with Up, instead of
+    /// stopping at them.
+    pub wrap_selection: bool,
+    /// In the tree view, expand collapsed processes when the cursor moves onto them.
+    pub auto_expand: bool,
+    /// Draw each process on two lines, the metrics on the first and the whole command line on
+    /// the second.
+    pub two_line: bool,
+    /// Draw a line on a row of its own below each process row, or below the top-level ones of
+    /// the tree view.
+    pub separators: Option<Separators>,
+    /// The characters the guides of the tree view are drawn with.
+    pub tree_glyphs: TreeGlyphs,
+    /// Hold the order of the list while it's scrolled, until it's left alone for this long.
+    pub scroll_freeze: Option<Duration>,
+    /// Move the cursor onto new processes as they start while the list is sorted by start time,
+    /// unless it's being moved by hand.
+    pub follow_new: bool,
+    /// Read the PID namespace of every process, to only link processes to parents in the same
+    /// one in the tree view and tell other namespaces apart, see `pid_ns`.
+    pub pid_ns: Option<PidNsMode>,
+    /// Show the footer of key hints from the start, see `key_hints`.
+    pub key_hints: bool,
+    /// Round cpu percentages, see `PercentFormat`, instead of showing all of their digits.
+    pub percent_format: Option<PercentFormat>,
+    /// How the memory column shows the resident memory.
+    pub mem_format: MemFormat,
+    /// How the I/O columns write byte counts.
+    pub notation: Notation,
+    /// Which side of its column each value is aligned to.
+    pub alignments: Alignments,
+    /// The sort order the list starts in, instead of descending cpu usage.
+    pub sort: Option<SortOrder>,
+    /// What processes that tie on the sort order are ordered by.
+    pub secondary_sort: Option<SecondarySort>,
+    /// Views cycled through with Alt-v, after the one the list starts in.
+    pub views: Vec<ViewPreset>,
+    /// Hide processes using less cpu than this, in hundredths of a percent like
+    /// `ProcessDisplay::cpu_percent`. The process under the cursor stays. It can be changed with c.
+    pub min_cpu: Option<usize>,
+    /// Dim processes using less cpu than this from the start, in hundredths of a percent, instead
+    /// of `DEFAULT_IDLE_THRESHOLD` once i is pressed.
+    pub dim_below: Option<usize>,
+    /// Background of every other row, if the rows are striped.
+    pub stripe: Option<Color>,
+    /// Foreground and background of the row under the cursor, the `Theme::selection` of the
+    /// default theme if `None`.
+    pub selection: Option<(Color, Color)>,
+    /// Draw a scrollbar along the right edge of lists that don't fit.
+    pub scrollbar: bool,
+    /// Start without the column headers, to save a row. They can be toggled with H.
+    pub no_header: bool,
+    /// Show when the processes were sampled at the end of the column headers, as formatted by
+    /// `format_local_time` with this pattern.
+    pub sample_time: Option<String>,
+    /// Ask before sending a signal from the kill menu, unless it only pauses or resumes.
+    pub confirm_kill: bool,
+    /// Disable the shortcuts that affect processes, for shared or demo machines. They're greyed
+    /// out in the help overlay.
+    pub read_only: bool,
+    /// What x runs on the process under the cursor.
+    pub hook: Option<HookCommand>,
+    /// What w opens the working directory of the process under the cursor with, instead of
+    /// showing it on the status line.
+    pub open_cwd: Option<CwdCommand>,
+    /// Show how many processes there are in each state above the list.
+    pub summary: bool,
+    /// Foreground of the rows of processes in uninterruptible sleep, if highlighted.
+    pub uninterruptible: Option<Color>,
+    /// Color command lines by the `Category` of their process with the colors of this theme.
+    pub categories: Option<Theme>,
+    /// Where procfs is mounted.
+    pub proc_source: ProcSource,
+}
+
+/* A filter or search term, matched against command lines */
+#[derive(Debug)]
+struct Filter<'a> {
+    /* folded with `fold_case` unless `case_sensitive` */
+    term: Cow<'a, str>,
+    case_sensitive: bool,
+    /* match the characters of the term in order instead of as a substring, see `fuzzy_match` */
+    fuzzy: bool,
+}
+
+impl<'a> Filter<'a> {
+    fn new(term: &'a str, case_sensitive: bool, fuzzy: bool) -> Filter<'a> {
+        Filter {
+            term: if case_sensitive {
+                Cow::Borrowed(term)
+            } else {
+                fold_case(term)
+            },
+            case_sensitive,
+            fuzzy,
+        }
+    }
+
+    fn matches(&self, cmd_line: &str) -> bool {
+        if self.fuzzy {
+            self.fuzzy_match(cmd_line).is_some()
+        } else if self.case_sensitive {
+            cmd_line.contains(self.term.as_ref())
+        } else {
+            contains_folded(cmd_line, &self.term)
+        }
+    }
+
+    /* `None` unless the filter is fuzzy and matches `cmd_line` */
+    fn fuzzy_match(&self, cmd_line: &str) -> Option<FuzzyMatch> {
+        if self.fuzzy {
+            fuzzy_match(cmd_line, &self.term, self.case_sensitive)
+        } else {
+            None
+        }
+    }
+
+    /* How well `cmd_line` matches, to list the best fuzzy matches first. Every substring match
+     * scores the same. */
+    fn score(&self, cmd_line: &str) -> i64 {
+        self.fuzzy_match(cmd_line).map_or(0, |m| m.score)
+    }
+}
+
+/// Restrict the process list to a set of PIDs.
+#[derive(Debug, Default)]
+pub struct PidFilter {
+    /// Show everything if empty.
+    pub pids: Vec<Pid>,
+    /// Also show the descendants of `pids`.
+    pub descendants: bool,
+}
+
+/// Where the cursor goes when the sort order changes.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub enum SortScroll {
+    /// `Top` for cpu and memory sorts, `Keep` for the others.
+    #[default]
+    Auto,
+    /// Jump to the first row.
+    Top,
+    /// Stay on the selected process.
+    Keep,
+}
+
+impl SortScroll {
+    fn jumps_to_top(self, sort: Sort) -> bool {
+        match self {
+            SortScroll::Auto => matches!(
+                sort,
+                Sort::CpuAsc
+                    | Sort::CpuDesc
+                    | Sort::VmRssAsc
+                    | Sort::VmRssDesc
+                    | Sort::ReadAsc
+                    | Sort::ReadDesc
+                    | Sort::WriteAsc
+                    | Sort::WriteDesc
+                    | Sort::IoAsc
+                    | Sort::IoDesc
+                    | Sort::ThreadsAsc
+                    | Sort::ThreadsDesc
+                    | Sort::CpuTimeAsc
+                    | Sort::CpuTimeDesc
+            ),
+            SortScroll::Top => true,
+            SortScroll::Keep => false,
+        }
+    }
+}
+
+/* Cursor position after the list was re-sorted by `sort` while `selected` was under the cursor.
+ * `rows` are the indices into `processes` in the new display order. */
+fn cursor_after_sort(
+    scroll: SortScroll,
+    sort: Sort,
+    selected: Pid,
+    rows: &[usize],
+    processes: &[ProcessDisplay],
+) -> Option<usize> {
+    if scroll.jumps_to_top(sort) {
+        Some(0)
+    } else {
+        rows.iter().position(|&i| processes[i].i == selected)
+    }
+}
+
+/* Screen area of the process rows drawn last, used to map mouse positions to list indices */
+#[derive(Debug, Clone, Copy)]
+struct RowsArea {
+    area: Area,
+    /* index of the process drawn on the first row */
+    first: usize,
+    /* screen rows each process takes */
+    row_height: usize,
+}
+
+impl RowsArea {
+    fn new(
+        upper_left: Pos,
+        bottom_right: Pos,
+        first: usize,
+        (rows, row_height): (usize, usize),
+    ) -> Option<RowsArea> {
+        if rows == 0 {
+            return None;
+        }
+        Some(RowsArea {
+            area: (
+                upper_left,
+                set_y(bottom_right, get_y(upper_left) + rows * row_height - 1),
+            ),
+            first,
+            row_height,
+        })
+    }
+
+    /* Index of the process drawn at `pos`, if any */
+    fn row_at(&self, (x, y): Pos) -> Option<usize> {
+        let ((x_start, y_start), (x_end, y_end)) = self.area;
+        if x < x_start || x > x_end || y < y_start || y > y_end {
+            None
+        } else {
+            Some(self.first + (y - y_start) / self.row_height)
+        }
+    }
+}
+
+/* process list components */
 #[derive(Debug)]
 pub struct ProcessList {
     page_movement: Option<PageMovement>,
@@ -171,11 +1682,153 @@ pub struct ProcessList {
     /* stop updating data */
     freeze: bool,
     draw_tree: bool,
+    /* merge processes with the same command into one row, see `group` */
+    grouped: bool,
+    /* names of the groups whose members are shown */
+    expanded_groups: HashSet<String>,
+    /* nodes of the tree view whose descendants are hidden, toggled with Enter */
+    collapsed: HashSet<Pid>,
+    /* expand the node the cursor moves onto, see `expand_selected` */
+    auto_expand: bool,
+    /* the process under the cursor as of the last `expand_selected` */
+    expanded_at: Option<Pid>,
+    /* draw the command line on a second line under each row, see `row_height` */
+    two_line: bool,
+    /* the separator rows below each process row, see `row_stride` */
+    separators: Option<Separators>,
+    /* the guides of the tree view, one cell wide each */
+    tree_glyphs: TreeGlyphs,
+    /* the order of the rows while the list is scrolled, with `--scroll-freeze` */
+    scroll_freeze: Option<ScrollFreeze>,
+    /* the new processes the cursor moves onto, with `--follow-new` */
+    follow_new: Option<FollowNew>,
+    /* how processes of other PID namespaces are told apart, with `--pid-ns` */
+    pid_ns: Option<PidNsMode>,
+    /* bb's PID namespace, which isn't colored or grouped */
+    own_pid_ns: Option<u64>,
+    /* the footer of key hints, while it's shown */
+    key_hints: Option<KeyHints>,
+    /* the process the inspector is open on, from the flat view */
+    inspected: Option<Pid>,
+    /* the memory map of the inspected process, read again with every sample */
+    memory_map: Option<Result<MemoryMap, String>>,
+    /* the order of the optional columns and which are shown, edited with the column picker */
+    columns: ColumnConfig,
+    /* the row of the column picker under its cursor, while it's open */
+    column_picker: Option<usize>,
     draw_help: bool,
     processes_times: HashMap<Pid, usize>,
     processes: Vec<ProcessDisplay>,
     sort: Sort,
     mode: ProcessListMode,
+    /* one-off message shown in the status line until the next key press */
+    status_message: Option<String>,
+    /* dim rows of processes whose cpu usage is below `idle_threshold` */
+    dim_idle: bool,
+    /* draw the column headers above the rows, toggled with H */
+    show_header: bool,
+    /* strftime(3) pattern of the time of the snapshot, drawn at the end of the column headers */
+    sample_time: Option<String>,
+    /* in hundredths of a percent, see `dim_idle` */
+    idle_threshold: usize,
+    /* highlight rows of processes using more than this fraction of the total memory */
+    mem_threshold: Option<f64>,
+    /* colors of the cpu usage and memory columns by percentage, if any */
+    cpu_thresholds: Option<Thresholds>,
+    mem_thresholds: Option<Thresholds>,
+    /* hide rows of processes whose cpu usage is below this, except the one of `min_cpu_kept`: the
+     * process under the cursor as of the last update, which is marked instead */
+    min_cpu: Option<usize>,
+    min_cpu_kept: Option<Pid>,
+    /* how the cpu percentages are rounded, if they are */
+    percent_format: Option<PercentFormat>,
+    mem_format: MemFormat,
+    /* how the I/O columns write byte counts */
+    notation: Notation,
+    /* the environment variable column, if shown */
+    env_column: Option<EnvColumn>,
+    /* the sockets of the processes, looked up if they're shown or highlighted */
+    sockets: Option<Sockets>,
+    /* whether the socket column is shown */
+    net_column: bool,
+    /* highlight rows of processes holding sockets with this color */
+    highlight_sockets: Option<Color>,
+    /* the GPUs, if their columns are shown and NVML found some */
+    gpus: Option<Gpus>,
+    alignments: Alignments,
+    /* breaks the ties of `sort`, in every view */
+    secondary_sort: Option<SecondarySort>,
+    /* the views cycled through with Alt-v, which can be pressed while typing a filter */
+    views: Views,
+    /* processes that exited in the last few samples, merged into the rows of the flat view */
+    tombstones: Option<Tombstones>,
+    /* the cursor can be on the rows of `tombstones` */
+    select_tombstones: bool,
+    /* Up and Down wrap around the ends of the visible rows instead of stopping at them */
+    wrap_selection: bool,
+    /* highlight rows of processes in uninterruptible sleep with this color, and count them in the
+     * status line */
+    uninterruptible: Option<Color>,
+    /* the kill menu signals the process group of the process under the cursor */
+    kill_group: bool,
+    /* kill by name matches whole process names instead of substrings */
+    kill_name_exact: bool,
+    /* ask before sending destructive signals from the kill menu, see `needs_confirmation` */
+    confirm_kill: bool,
+    /* the `PROCESS_ACTIONS` are disabled */
+    read_only: bool,
+    /* run by x on the process under the cursor, see `UIEvent::Exec` */
+    hook: Option<HookCommand>,
+    /* run by w on the working directory of the process under the cursor, which is shown on the
+     * status line if `None` */
+    open_cwd: Option<CwdCommand>,
+    /* where the working directories are read from, the rest is read by `sampler` */
+    proc_source: ProcSource,
+    /* `None` unless the tally of process states is shown above the list */
+    summary: Option<TaskSummary>,
+    sampler: Sampler,
+    snapshot: Snapshot,
+    pid_filter: PidFilter,
+    /* hoisted above the other rows of the flat view */
+    pins: Pins,
+    /* highlight the row under the mouse pointer and select rows by clicking */
+    hover_enabled: bool,
+    /* mouse pointer position, if it's over a process row */
+    hover: Option<Pos>,
+    rows_area: Option<RowsArea>,
+    /* convert command lines and filter/search terms to NFC */
+    normalize: bool,
+    /* match filter/search terms exactly instead of ignoring case */
+    case_sensitive: bool,
+    /* match filter/search terms as subsequences instead of substrings, with the best matches
+     * listed first */
+    fuzzy: bool,
+    /* longest command line to show, in columns, and how longer ones are shortened */
+    max_cmd_len: Option<usize>,
+    truncation: Truncation,
+    /* weight of new samples in the smoothed cpu percentages, if enabled */
+    smoothing: Option<f64>,
+    cpu_averages: HashMap<Pid, Ewma>,
+    /* `None` unless the cpu history column is shown */
+    cpu_histories: Option<HashMap<Pid, CpuHistory>>,
+    sparkline_baseline: bool,
+    graph_direction: GraphDirection,
+    /* `None` unless new processes are highlighted */
+    ages: Option<Ages>,
+    /* `None` unless processes whose memory grows fast are highlighted */
+    mem_growth: Option<MemGrowth>,
+    /* background of the odd rows, if striped */
+    stripe: Option<Color>,
+    /* foreground and background of the row under the cursor */
+    selection: (Color, Color),
+    /* colors of the command lines of each category, if they're colored by it */
+    categories: Option<Theme>,
+    scrollbar: bool,
+    sort_scroll: SortScroll,
+    /* PID under the cursor when the sort order last changed, until the rows are re-sorted */
+    resorted: Option<Pid>,
+    /* row of the grouped view under the cursor before the groups were recomputed */
+    regrouped: Option<GroupedRow>,
 }
 
 #[derive(Debug, PartialEq)]
@@ -185,6 +1838,12 @@ enum ProcessListMode {
     Locate(Pid),
     Search(String),
     Kill(u16),
+    /* asking whether to send the signal chosen in the kill menu, with `--confirm-kill` */
+    KillConfirm(u16),
+    /* typing the name of the processes to signal */
+    KillName(String),
+    /* confirming the signal to send to the matched PIDs */
+    KillMatches(Vec<Pid>, u16),
 }
 
 impl ProcessListMode {
@@ -193,37 +1852,29 @@ impl ProcessListMode {
     }
 
     fn is_follow(&self) -> bool {
-        match self {
-            Follow(_) => true,
-            _ => false,
-        }
+        matches!(self, Follow(_))
     }
 
     fn is_locate(&self) -> bool {
-        match self {
-            Locate(_) => true,
-            _ => false,
-        }
+        matches!(self, Locate(_))
     }
 
     fn is_search(&self) -> bool {
-        match self {
-            Search(_) => true,
-            _ => false,
-        }
+        matches!(self, Search(_))
     }
 
     fn is_kill(&self) -> bool {
-        match self {
-            Kill(_) => true,
-            _ => false,
-        }
+        matches!(self, Kill(_) | KillConfirm(_))
+    }
+
+    fn is_kill_name(&self) -> bool {
+        matches!(self, KillName(_) | KillMatches(_, _))
     }
 }
 
 use ProcessListMode::*;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub enum State {
     /* Z  Zombie */
     Zombie,
@@ -241,6 +1892,19 @@ pub enum State {
     Dead,
 }
 
+impl State {
+    /// Whether the process has exited but its parent hasn't reaped it yet.
+    pub fn is_zombie(self) -> bool {
+        self == State::Zombie
+    }
+
+    /// Whether the process is in uninterruptible sleep, usually waiting for I/O. Processes that
+    /// stay in it may be stuck on a hung disk or network filesystem.
+    pub fn is_uninterruptible(self) -> bool {
+        self == State::Waiting
+    }
+}
+
 impl From<char> for State {
     fn from(val: char) -> State {
         match val {
@@ -276,14 +1940,34 @@ impl std::fmt::Display for State {
     }
 }
 
+#[derive(Debug)]
 pub struct Process {
-    pub pid: i32,
-    pub ppid: i32,
+    pub pid: Pid,
+    pub ppid: Pid,
+    pub pgrp: Pid,
     pub vm_rss: usize,
     pub state: State,
     pub uid: u32,
+    /* from the `Threads:` line of the status, `None` if it has none */
+    pub threads: Option<usize>,
     pub cmd_line: String,
     pub rtime: usize,
+    /* clock ticks after boot when the process started */
+    pub start_time: usize,
+    /* what the cgroup column shows, see `cgroup::cgroup_name`. Only read if the column is enabled,
+     * since it's another file to open for every process. */
+    pub cgroup: Option<String>,
+    /* only read if the I/O columns are enabled, and `None` if `/proc/[pid]/io` can't be read */
+    pub io: Option<IoCounters>,
+    /* from the `CapEff:` line of the status, `None` if it has none */
+    pub cap_eff: Option<u64>,
+    /* what the affinity column shows, see `affinity::affinity_label`. Only read if the column is
+     * enabled. */
+    pub affinity: Option<String>,
+    /* the inode of the PID namespace, see `pid_ns`. Only read with `--pid-ns`, and `None` if
+     * `/proc/[pid]/ns/pid` can't be read. */
+    pub pid_ns: Option<u64>,
+    pub category: Category,
 }
 
 impl fmt::Display for ProcessList {
@@ -293,19 +1977,112 @@ impl fmt::Display for ProcessList {
 }
 
 impl ProcessList {
-    pub fn new() -> Self {
-        let data = ProcessData {
-            cpu_stat: get_stat(&mut 0).remove(0),
-            processes_times: Default::default(),
-            processes_index: Default::default(),
-            tree_index: Default::default(),
-            parents: Default::default(),
-            tree: Default::default(),
+    pub fn new(options: ProcessListOptions) -> Self {
+        let ProcessListOptions {
+            pid_filter,
+            pins,
+            hover: hover_enabled,
+            normalize,
+            case_sensitive,
+            fuzzy,
+            max_cmd_len,
+            truncation,
+            smoothing,
+            sort_scroll,
+            cgroups,
+            io,
+            affinity,
+            elapsed,
+            threads,
+            cpu_time,
+            caps,
+            env_var,
+            sockets,
+            highlight_sockets,
+            gpu,
+            sparkline,
+            sparkline_baseline,
+            graph_direction,
+            fade_new,
+            mem_threshold,
+            cpu_thresholds,
+            mem_thresholds,
+            mem_growth,
+            tombstones,
+            select_tombstones,
+            wrap_selection,
+            auto_expand,
+            two_line,
+            separators,
+            tree_glyphs,
+            scroll_freeze,
+            follow_new,
+            pid_ns,
+            key_hints,
+            percent_format,
+            mem_format,
+            notation,
+            alignments,
+            sort,
+            secondary_sort,
+            views,
+            min_cpu,
+            dim_below,
+            stripe,
+            selection,
+            scrollbar,
+            no_header,
+            sample_time,
+            confirm_kill,
+            read_only,
+            hook,
+            open_cwd,
+            summary,
+            uninterruptible,
+            categories,
+            proc_source,
+        } = options;
+        let sort = sort.map_or(Sort::CpuDesc, |SortOrder(sort)| sort);
+        let checked = tree_glyphs.or_ascii();
+        if checked != tree_glyphs {
+            log_warn!(
+                "tree glyphs {:?} aren't one cell wide, using ASCII instead",
+                tree_glyphs
+            );
+        }
+        let reads = ExtraReads {
+            cgroups,
+            io,
+            affinity,
+            pid_ns: pid_ns.is_some(),
+        };
+        /* Take the first snapshot synchronously so that there's something to draw right away */
+        let snapshot = Snapshot::take(&proc_source, reads).unwrap_or_else(|err| {
+            log_warn!("cannot read {}: {}", proc_source.root().display(), err);
+            Snapshot::default()
+        });
+        let mut data = ProcessData::new(snapshot.cpu_stat);
+        data.elapsed = elapsed;
+        data.io = io;
+        data.threads = threads;
+        data.cpu_time = cpu_time;
+        data.caps = caps;
+        data.pid_ns = pid_ns.is_some();
+        let initial_view = ViewPreset {
+            name: "default".to_string(),
+            sort: Some(sort),
+            columns: Some(ViewColumns {
+                threads,
+                cpu_time,
+                elapsed,
+                caps,
+            }),
+            filter: None,
         };
         ProcessList {
             cursor: 0,
             page_movement: None,
-            cpu_stat: get_stat(&mut 0).remove(0),
+            cpu_stat: get_stat(&proc_source, &mut 0).remove(0),
             data,
             processes: Vec::with_capacity(1024),
             processes_times: Default::default(),
@@ -314,14 +2091,201 @@ impl ProcessList {
             maxima: ColumnWidthMaxima::new(),
             freeze: false,
             draw_tree: false,
+            grouped: false,
+            expanded_groups: Default::default(),
+            collapsed: Default::default(),
+            auto_expand,
+            expanded_at: None,
+            two_line,
+            separators,
+            tree_glyphs: checked,
+            scroll_freeze: scroll_freeze.map(ScrollFreeze::new),
+            follow_new: if follow_new {
+                Some(FollowNew::new())
+            } else {
+                None
+            },
+            pid_ns,
+            own_pid_ns: proc_source
+                .path("self/ns/pid")
+                .ok()
+                .and_then(|path| read_pid_ns(&path)),
+            key_hints: if key_hints {
+                Some(KeyHints::new())
+            } else {
+                None
+            },
+            inspected: None,
+            memory_map: None,
+            columns: {
+                let mut columns = ColumnConfig::default();
+                columns.set_shown(Column::Threads, threads);
+                columns.set_shown(Column::CpuTime, cpu_time);
+                columns.set_shown(Column::Elapsed, elapsed);
+                columns.set_shown(Column::Caps, caps);
+                columns
+            },
+            column_picker: None,
             draw_help: false,
             mode: Normal,
             dirty: true,
-            sort: Sort::CpuDesc,
+            sort,
             force_redraw: false,
+            status_message: None,
+            dim_idle: dim_below.is_some(),
+            show_header: !no_header,
+            sample_time,
+            idle_threshold: dim_below.unwrap_or(DEFAULT_IDLE_THRESHOLD),
+            mem_threshold,
+            cpu_thresholds,
+            mem_thresholds,
+            min_cpu,
+            min_cpu_kept: None,
+            percent_format,
+            mem_format,
+            notation,
+            env_column: env_var.map(|key| EnvColumn::new(key, proc_source.clone())),
+            sockets: if sockets || highlight_sockets.is_some() {
+                Some(Sockets::new(proc_source.clone()))
+            } else {
+                None
+            },
+            net_column: sockets,
+            highlight_sockets,
+            gpus: if gpu { Gpus::open() } else { None },
+            alignments,
+            secondary_sort,
+            views: Views::new(initial_view, views),
+            tombstones: tombstones.map(Tombstones::new),
+            select_tombstones,
+            wrap_selection,
+            uninterruptible,
+            kill_group: false,
+            kill_name_exact: false,
+            confirm_kill,
+            read_only,
+            hook,
+            summary: if summary {
+                Some(Default::default())
+            } else {
+                None
+            },
+            open_cwd,
+            proc_source: proc_source.clone(),
+            sampler: Sampler::new(SAMPLE_INTERVAL, proc_source, reads),
+            snapshot,
+            pid_filter,
+            pins: Pins::new(pins),
+            hover_enabled,
+            hover: None,
+            rows_area: None,
+            normalize,
+            case_sensitive,
+            fuzzy,
+            max_cmd_len,
+            truncation,
+            smoothing,
+            cpu_averages: Default::default(),
+            cpu_histories: if sparkline {
+                Some(Default::default())
+            } else {
+                None
+            },
+            sparkline_baseline,
+            graph_direction,
+            ages: fade_new.map(Ages::new),
+            mem_growth: mem_growth.map(MemGrowth::new),
+            stripe,
+            selection: selection.unwrap_or_else(|| Theme::default().selection()),
+            categories,
+            scrollbar,
+            sort_scroll,
+            resorted: None,
+            regrouped: None,
+        }
+    }
+
+    /* Replace the current snapshot with the newest one from the sampling thread, if any. Returns
+     * whether the snapshot changed. */
+    fn update_snapshot(&mut self) -> bool {
+        if let Some(snapshot) = self.sampler.latest() {
+            let prev = std::mem::replace(&mut self.snapshot, snapshot);
+            self.data.set_previous(&prev);
+            if let Some(ref mut env_column) = self.env_column {
+                env_column.clear();
+            }
+            if let Some(ref mut sockets) = self.sockets {
+                sockets.clear();
+            }
+            self.read_memory_map();
+            true
+        } else {
+            false
+        }
+    }
+
+    /* Read the memory map of the inspected process, if any */
+    fn read_memory_map(&mut self) {
+        self.memory_map = self
+            .inspected
+            .map(|pid| read_memory_map(&self.proc_source, pid));
+    }
+
+    /* Screen rows each process takes, two with `--two-line` */
+    fn row_height(&self) -> usize {
+        if self.two_line {
+            2
+        } else {
+            1
+        }
+    }
+
+    /* Screen rows from the top of a process row to the top of the next, one more than
+     * `row_height` with `--separators` */
+    fn row_stride(&self) -> usize {
+        self.row_height() + self.separators.is_some() as usize
+    }
+
+    /* Screen area of the `row`th process of a page whose first process is drawn at the upper left
+     * corner of `rows`, without the separator below it */
+    fn process_row_area(&self, rows: Area, row: usize) -> Area {
+        let (top, bottom_right) = page_row_area(rows, row, self.row_stride());
+        (top, set_y(bottom_right, get_y(top) + self.row_height() - 1))
+    }
+
+    /* Draw the separator below the process row drawn at `upper_left`, if `self.separators` draws
+     * one above the next row, which is `top_level` in the tree view */
+    fn draw_row_separator(
+        &self,
+        grid: &mut CellBuffer,
+        (upper_left, bottom_right): Area,
+        top_level: bool,
+    ) {
+        let y = get_y(upper_left) + self.row_height();
+        match self.separators {
+            Some(separators) if separators.draws_above(top_level) && y <= get_y(bottom_right) => {
+                draw_separator(grid, (set_y(upper_left, y), set_y(bottom_right, y)))
+            }
+            _ => {}
         }
     }
 
+    fn hovered_row(&self) -> Option<usize> {
+        self.rows_area.and_then(|r| r.row_at(self.hover?))
+    }
+
+    /* Colors of the process with `pid`, drawn on row `i` of the list */
+    fn row_colors(&self, i: usize, pid: Pid, hovered: Option<usize>) -> (Color, Color) {
+        row_colors_at(
+            i,
+            self.cursor,
+            self.mode == Locate(pid),
+            hovered,
+            self.stripe,
+            self.selection,
+        )
+    }
+
     fn follow(&self) -> Option<Pid> {
         match self.mode {
             ProcessListMode::Follow(pid) => Some(pid),
@@ -329,142 +2293,684 @@ impl ProcessList {
         }
     }
 
-    fn get_pid_under_cursor(&self, cursor: usize) -> Pid {
+    /* Set the sort order, columns and filter of `view` together */
+    fn apply_view(&mut self, view: ViewPreset) {
+        if let Some(sort) = view.sort {
+            self.sort = sort;
+        }
+        if let Some(columns) = view.columns {
+            self.set_view_columns(columns);
+        }
+        /* like typing the filter, which is of the flat list */
+        if view.filter.is_some() {
+            self.draw_tree = false;
+        }
+        self.filter_term = view.filter;
+        self.cursor = 0;
+        self.status_message = Some(format!("view: {}", view.name));
+        self.force_redraw = true;
+        self.dirty = true;
+    }
+
+    /* Show the columns of a view, reading their values only while they're shown */
+    fn set_view_columns(&mut self, columns: ViewColumns) {
+        self.data.threads = columns.threads;
+        self.data.cpu_time = columns.cpu_time;
+        self.data.elapsed = columns.elapsed;
+        self.data.caps = columns.caps;
+        self.columns.set_shown(Column::Threads, columns.threads);
+        self.columns.set_shown(Column::CpuTime, columns.cpu_time);
+        self.columns.set_shown(Column::Elapsed, columns.elapsed);
+        self.columns.set_shown(Column::Caps, columns.caps);
+    }
+
+    /* The columns of a view that the column picker shows */
+    fn view_columns(&self) -> ViewColumns {
+        ViewColumns {
+            threads: self.columns.is_shown(Column::Threads),
+            cpu_time: self.columns.is_shown(Column::CpuTime),
+            elapsed: self.columns.is_shown(Column::Elapsed),
+            caps: self.columns.is_shown(Column::Caps),
+        }
+    }
+
+    fn filter(&self) -> Option<Filter<'_>> {
+        self.filter_term
+            .as_deref()
+            .map(|term| Filter::new(term, self.case_sensitive, self.fuzzy))
+    }
+
+    /* The nodes of the tree view that are shown, the descendants of collapsed ones left out */
+    fn tree_rows(&self) -> Vec<(usize, Pid)> {
+        collapse_tree(&self.data.tree, &self.collapsed)
+    }
+
+    /* Expand the node of the tree view under the cursor if the cursor moved onto it since the last
+     * call. A node collapsed with Enter stays collapsed until the cursor comes back to it. */
+    fn expand_selected(&mut self) {
+        let pid = self
+            .visible_rows()
+            .get(self.cursor)
+            .map(|&i| self.processes[i].i);
+        if pid != self.expanded_at {
+            self.expanded_at = pid;
+            if let Some(pid) = pid {
+                self.collapsed.remove(&pid);
+            }
+        }
+    }
+
+    /* Indices into `self.processes` of the rows, in display order */
+    fn visible_rows(&self) -> Vec<usize> {
         if self.draw_tree {
-            self.data.tree[cursor].1
+            return self
+                .tree_rows()
+                .iter()
+                .map(|(_, pid)| self.data.processes_index[pid])
+                .collect();
+        }
+        let mut rows = if self.grouped {
+            grouped_indices(
+                &self.processes,
+                self.sort,
+                self.secondary_sort,
+                self.filter().as_ref(),
+                &self.expanded_groups,
+            )
         } else {
-            let mut processes = self.processes.iter().collect::<Vec<&ProcessDisplay>>();
-            processes.sort_unstable_by(|a, b| match self.sort {
-                Sort::CpuAsc => a.cpu_percent.cmp(&b.cpu_percent),
-                Sort::CpuDesc => b.cpu_percent.cmp(&a.cpu_percent),
-                Sort::VmRssAsc => a.vm_rss_value.cmp(&b.vm_rss_value),
-                Sort::VmRssDesc => b.vm_rss_value.cmp(&a.vm_rss_value),
-                Sort::UserAsc => a.username.0.cmp(&b.username.0),
-                Sort::UserDesc => b.username.0.cmp(&a.username.0),
-                Sort::CmdLineAsc => a.cmd_line.0.cmp(&b.cmd_line.0),
-                Sort::CmdLineDesc => b.cmd_line.0.cmp(&a.cmd_line.0),
+            visible_indices(
+                &self.processes,
+                self.sort,
+                self.secondary_sort,
+                self.filter().as_ref(),
+            )
+        };
+        if let Some(min_cpu) = self.min_cpu {
+            retain_min_cpu(&mut rows, &self.processes, min_cpu, self.min_cpu_kept);
+        }
+        if let (Some(PidNsMode::Group), false) = (self.pid_ns, self.grouped) {
+            group_by_ns(&mut rows, self.own_pid_ns, |&i| self.processes[i].pid_ns);
+        }
+        /* group rows have no PID of their own to hold them in place by */
+        if let (Some(freeze), false) = (&self.scroll_freeze, self.grouped) {
+            freeze.reorder(std::time::Instant::now(), &mut rows, |&i| {
+                self.processes[i].i
             });
-            if self.filter_term.is_some() {
-                processes.retain(|process| {
-                    process
-                        .cmd_line
-                        .0
-                        .contains(self.filter_term.as_ref().unwrap())
-                });
+        }
+        if !self.grouped {
+            self.pins.hoist(&mut rows, &self.processes);
+        }
+        rows
+    }
+
+    /* Whether `p` is the row of a process that exited */
+    fn is_tombstone(&self, p: &ProcessDisplay) -> bool {
+        self.tombstones
+            .as_ref()
+            .is_some_and(|tombstones| tombstones.contains(p.i))
+    }
+
+    /* The row nearest to `cursor` in `rows` that the cursor can be on, looking `forward` first.
+     * Tombstones are skipped unless they can be selected. */
+    fn selectable_row(&self, rows: &[usize], cursor: usize, forward: bool) -> usize {
+        if self.select_tombstones || self.tombstones.is_none() {
+            return cursor;
+        }
+        nearest_row(rows.len(), cursor, forward, |row| {
+            !self.is_tombstone(&self.processes[rows[row]])
+        })
+    }
+
+    /* Whether `p` is only listed because it was under the cursor when it went below `min_cpu` */
+    fn is_kept_below_min_cpu(&self, p: &ProcessDisplay) -> bool {
+        !self.draw_tree
+            && Some(p.i) == self.min_cpu_kept
+            && self.min_cpu.is_some_and(|min_cpu| is_idle(p, min_cpu))
+    }
+
+    fn get_pid_under_cursor(&self, cursor: usize) -> Pid {
+        self.processes[self.visible_rows()[cursor]].i
+    }
+
+    /* The row under the cursor, if it's the row of a group */
+    fn group_under_cursor(&self) -> Option<&ProcessDisplay> {
+        self.visible_rows()
+            .get(self.cursor)
+            .map(|&i| &self.processes[i])
+            .filter(|p| p.members > 0)
+    }
+
+    /* Where the kill menu sends its signal: the process under the cursor or its process group */
+    fn signal_target(&self) -> SignalTarget {
+        let pid = self.get_pid_under_cursor(self.cursor);
+        match self.data.processes_index.get(&pid) {
+            Some(&i) if self.kill_group => SignalTarget::Group(self.processes[i].pgrp),
+            _ => SignalTarget::Process(pid),
+        }
+    }
+
+    /* Write the list as it is currently displayed (sort, filter and tree order) to a CSV file in
+     * the current directory. */
+    fn export_csv(&mut self) -> std::io::Result<(PathBuf, usize)> {
+        let mem_total = self.snapshot.mem_total * 1024;
+        for p in self.processes.iter_mut() {
+            p.format(self.mem_format, mem_total);
+        }
+        let processes = self
+            .visible_rows()
+            .into_iter()
+            .map(|i| &self.processes[i])
+            .collect::<Vec<&ProcessDisplay>>();
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let path = PathBuf::from(format!("bb-{}.csv", timestamp));
+        let mut file = File::create(&path)?;
+        write_csv(&mut file, &self.maxima, &processes)?;
+        Ok((path, processes.len()))
+    }
+
+    /* The shortcuts keys are dispatched to, and the ones `--read-only` took out of them */
+    fn dispatch_map(&self) -> (ShortcutMap, ShortcutMap) {
+        let mut map = self.get_shortcuts().remove("").unwrap_or_default();
+        let mut disabled = ShortcutMap::default();
+        if self.read_only {
+            for action in PROCESS_ACTIONS.iter() {
+                if let Some((action, key)) = map.remove_entry(action) {
+                    disabled.insert(action, key);
+                }
             }
-            processes[cursor].i
         }
+        (map, disabled)
     }
 
     fn draw_help_box(&self, grid: &mut CellBuffer) {
         let (cols, rows) = grid.size();
-        let margin_left = (cols / 2).saturating_sub(20);
-        let margin_top = (rows / 2).saturating_sub(12);
-        let box_area = (
-            (margin_left, margin_top),
-            (margin_left + 36, margin_top + 12),
-        );
-        clear_area(grid, box_area);
-        create_box(grid, box_area);
         let shortcuts_map = &self.get_shortcuts()[""];
+        let (_, disabled) = self.dispatch_map();
         let max_key = shortcuts_map.keys().map(|k| k.len()).max().unwrap();
         let mut shortcuts = shortcuts_map
             .iter()
             .map(|(k, v)| (*k, v))
             .collect::<Vec<(&str, &Key)>>();
         shortcuts.sort_by_key(|s| s.0);
-        let mut y = 0;
-        for (k, v) in shortcuts.iter() {
+        let entries = shortcuts
+            .iter()
+            .map(|(k, v)| format!("{k:>max_key$} {v}", k = k, v = v, max_key = max_key))
+            .collect::<Vec<String>>();
+        let entry_width = entries.iter().map(|e| e.chars().count()).max().unwrap_or(0);
+        /* The shortcuts go in as many columns as it takes for them to fit in the grid's height,
+         * and those that don't fit in its width either are left out */
+        let max_height = rows.saturating_sub(4);
+        let max_columns = std::cmp::max(1, (cols.saturating_sub(4) + 3) / (entry_width + 3));
+        if max_height == 0 {
+            return;
+        }
+        let columns = std::cmp::min(max_columns, entries.len().div_ceil(max_height));
+        let columns = std::cmp::max(columns, 1);
+        let height = std::cmp::min(max_height, entries.len().div_ceil(columns));
+        let width = std::cmp::min(cols.saturating_sub(1), columns * (entry_width + 3) - 3 + 4);
+        let margin_left = cols.saturating_sub(width + 1) / 2;
+        let margin_top = rows.saturating_sub(height + 4) / 2;
+        let box_area = (
+            (margin_left, margin_top),
+            (margin_left + width, margin_top + height + 3),
+        );
+        clear_area(grid, box_area);
+        create_box(grid, box_area);
+        for (i, ((k, _), entry)) in shortcuts
+            .iter()
+            .zip(entries.iter())
+            .enumerate()
+            .take(columns * height)
+        {
+            let (column, y) = (i / height, i % height);
             write_string_to_grid(
-                &format!("{k:>max_key$} {v}", k = k, v = v, max_key = max_key),
+                entry,
                 grid,
-                Color::Default,
+                if disabled.contains_key(k) {
+                    DISABLED_FG
+                } else {
+                    Color::Default
+                },
                 Color::Default,
                 Attr::Default,
                 (
-                    pos_inc(upper_left!(box_area), (2, 4 + y)),
+                    pos_inc(
+                        upper_left!(box_area),
+                        (2 + column * (entry_width + 3), 2 + y),
+                    ),
+                    pos_dec(bottom_right!(box_area), (1, 1)),
+                ),
+                false,
+            );
+        }
+    }
+
+    /* Confirmation of a kill by name */
+    fn draw_kill_matches_box(&self, grid: &mut CellBuffer, pids: &[Pid], signal: u16) {
+        let (cols, rows) = grid.size();
+        let width = std::cmp::min(50, cols.saturating_sub(2));
+        if width < 10 {
+            return;
+        }
+        /* PIDs wrapped to the width of the box, at most 4 rows of them */
+        let mut pid_lines = vec![String::new()];
+        for (i, pid) in pids.iter().enumerate() {
+            let pid = pid.to_string();
+            let line = pid_lines.last_mut().unwrap();
+            if line.len() + pid.len() + 2 > width - 2 {
+                if pid_lines.len() == 4 {
+                    pid_lines[3].push_str(&format!("… (+{})", pids.len() - i));
+                    break;
+                }
+                pid_lines.push(pid);
+            } else {
+                if !line.is_empty() {
+                    line.push_str(", ");
+                }
+                line.push_str(&pid);
+            }
+        }
+        let lines = [
+            vec![
+                format!("send {}", signal_name(signal)),
+                format!("to {} processes:", pids.len()),
+            ],
+            pid_lines,
+            vec!["Enter sends, 'y' prints, Esc cancels".to_string()],
+        ]
+        .concat();
+        let margin_left = (cols - width) / 2;
+        let margin_top = (rows / 2).saturating_sub(lines.len() / 2 + 1);
+        let box_area = (
+            (margin_left, margin_top),
+            (margin_left + width, margin_top + lines.len() + 1),
+        );
+        clear_area(grid, box_area);
+        create_box(grid, box_area);
+        for (y, line) in lines.iter().enumerate() {
+            write_styled(
+                line,
+                grid,
+                Style::default(),
+                (
+                    pos_inc(upper_left!(box_area), (1, 1 + y)),
+                    bottom_right!(box_area),
+                ),
+                false,
+            );
+        }
+    }
+
+    fn draw_inspector_box(&self, grid: &mut CellBuffer, pid: Pid) {
+        let (cols, rows) = grid.size();
+        let width = std::cmp::min(72, cols.saturating_sub(2));
+        if width < 20 || rows < 8 {
+            return;
+        }
+        let mut lines = match self.processes.iter().find(|p| p.i == pid && p.members == 0) {
+            Some(p) => inspector_lines(p, self.memory_map.as_ref(), width - 2),
+            None => vec![format!("PID {} exited", pid)],
+        };
+        lines.truncate(rows - 6);
+        lines.push(String::new());
+        lines.push("Enter or Esc closes".to_string());
+        let margin_left = (cols - width) / 2;
+        let margin_top = (rows / 2).saturating_sub(lines.len() / 2 + 1);
+        let box_area = (
+            (margin_left, margin_top),
+            (margin_left + width, margin_top + lines.len() + 1),
+        );
+        clear_area(grid, box_area);
+        create_box(grid, box_area);
+        for (y, line) in lines.iter().enumerate() {
+            write_styled(
+                line,
+                grid,
+                Style::default(),
+                (
+                    pos_inc(upper_left!(box_area), (1, 1 + y)),
                     bottom_right!(box_area),
                 ),
                 false,
             );
-            y += 1;
         }
+    }
+
+    /* The column picker, with the row under its cursor in reverse and the columns whose values
+     * aren't read dimmed */
+    /* The columns the column picker lists, see `ColumnConfig::rows` */
+    fn picker_rows(&self) -> Vec<usize> {
+        self.columns
+            .rows(|column| READ_WHEN_SHOWN.contains(&column) || self.maxima.is_read(column))
+    }
+
+    fn draw_column_picker_box(&self, grid: &mut CellBuffer, selected: usize) {
+        let (cols, rows) = grid.size();
+        let picker_rows = self.picker_rows();
+        let mut lines = self.columns.picker_lines(&picker_rows);
+        lines.push(String::new());
+        lines.push("Space shows or hides".to_string());
+        lines.push("K and J move up and down".to_string());
+        lines.push("C or Esc closes".to_string());
+        let longest = lines.iter().map(String::len).max().unwrap_or(0);
+        let width = std::cmp::min(longest + 3, cols.saturating_sub(2));
+        if width < 16 || rows < lines.len() + 2 {
+            return;
+        }
+        let margin_left = (cols - width) / 2;
+        let margin_top = (rows / 2).saturating_sub(lines.len() / 2 + 1);
         let box_area = (
-            (margin_left, margin_top + 13),
-            (margin_left + 36, margin_top + 16),
+            (margin_left, margin_top),
+            (margin_left + width, margin_top + lines.len() + 1),
         );
         clear_area(grid, box_area);
         create_box(grid, box_area);
+        for (y, line) in lines.iter().enumerate() {
+            let style = Style {
+                attrs: if y == selected && y < picker_rows.len() {
+                    Attr::Reverse
+                } else {
+                    Attr::Default
+                },
+                ..Style::default()
+            };
+            write_styled(
+                line,
+                grid,
+                style,
+                (
+                    pos_inc(upper_left!(box_area), (2, 1 + y)),
+                    bottom_right!(box_area),
+                ),
+                false,
+            );
+        }
     }
 
-    fn draw_tree_list(
-        &mut self,
+    /* Draw a single process row starting at the upper left corner of `area`. `branches` holds the
+     * tree view guides, if any. */
+    fn draw_process_row(
+        &self,
         grid: &mut CellBuffer,
         area: Area,
-        mut pages: usize,
-        height: usize,
+        p: &ProcessDisplay,
+        branches: &str,
+        (fg_color, bg_color): (Color, Color),
+        attrs: Attr,
     ) {
         let (upper_left, bottom_right) = area;
-
-        let mut y_offset = 0;
-
-        let mut branches = vec![];
-        let mut child_counters = vec![0];
-
-        self.cursor = std::cmp::min(self.height, self.cursor);
-        let mut lines = Vec::with_capacity(2048);
-        let mut iter = self.data.tree.iter().peekable();
-
-        let mut stop_search = false;
-        while let Some((ind, pid)) = iter.next() {
-            let p = &self.processes[self.data.processes_index[pid]];
-            if let Search(ref search) = self.mode {
-                if !stop_search && search.len() > 1 && p.cmd_line.0.contains(search) {
-                    let i = lines.len(); /* get index from lines to avoid making another counter */
-                    pages = i / height;
-                    self.cursor = i;
-                    stop_search = true;
-                }
+        let y = get_y(upper_left);
+        /* the cursor and selection colors take precedence */
+        let fg_color = match (
+            self.uninterruptible,
+            self.mem_threshold,
+            self.highlight_sockets,
+        ) {
+            (Some(color), _, _) if fg_color == Color::Default && p.state.is_uninterruptible() => {
+                color
             }
-            let has_sibling: bool = child_counters
-                .get(*ind)
-                .map(|n| {
-                    *n != self
-                        .data
-                        .parents
-                        .get(&p.p)
-                        .and_then(|v| Some(v.len() - 1))
-                        .unwrap_or(0)
-                })
-                .unwrap_or(false);
-
-            let is_first: bool = child_counters.last().map(|n| *n == 0).unwrap_or(false);
-
-            if let Some(counter) = child_counters.get_mut(*ind) {
-                *counter += 1;
+            (_, Some(threshold), _)
+                if fg_color == Color::Default
+                    && is_memory_hog(p, self.snapshot.mem_total, threshold) =>
+            {
+                MEM_HOG_FG
             }
-
-            let mut s = String::with_capacity(16);
-
-            for i in 0..*ind {
-                if branches.len() > i && branches[i] {
-                    s.push('│');
-                } else {
-                    s.push(' ');
-                }
-                if i > 0 {}
-                s.push(' ');
+            _ if fg_color == Color::Default
+                && self
+                    .mem_growth
+                    .as_ref()
+                    .is_some_and(|mem_growth| mem_growth.is_growing(p.i)) =>
+            {
+                MEM_GROWTH_FG
+            }
+            (_, _, Some(color))
+                if fg_color == Color::Default && p.net.is_some_and(NetState::is_open) =>
+            {
+                color
+            }
+            _ if fg_color == Color::Default && p.members == 0 => self
+                .ages
+                .as_ref()
+                .and_then(|ages| ages.color(p.i))
+                .unwrap_or(fg_color),
+            _ => fg_color,
+        };
+        let style = Style {
+            fg: fg_color,
+            bg: bg_color,
+            attrs,
+        };
+        let (x, y_) = write_styled(&self.maxima.row(p), grid, style, area, false);
+        /* write_string_to_grid() moves to the next line when it reaches the edge of the area */
+        if y_ != y {
+            return;
+        }
+        /* like the row highlights, the thresholds don't recolor the cursor or a highlighted row */
+        if fg_color == Color::Default {
+            let cpu = self
+                .cpu_thresholds
+                .zip(p.cpu_percent)
+                .map(|(thresholds, cpu_percent)| {
+                    (
+                        self.maxima.cpu_span(),
+                        thresholds.color(cpu_percent as f64 / 100.0),
+                    )
+                });
+            let mem = self
+                .mem_thresholds
+                .filter(|_| self.snapshot.mem_total > 0)
+                .map(|thresholds| {
+                    let percent =
+                        p.vm_rss_value as f64 * 100.0 / (self.snapshot.mem_total * 1024) as f64;
+                    (self.maxima.vm_rss_span(), thresholds.color(percent))
+                });
+            let ns = Some(self.own_pid_ns)
+                .filter(|_| self.pid_ns == Some(PidNsMode::Color))
+                .and_then(|own| ns_color(p.pid_ns, own))
+                .map(|color| ((0, self.maxima.pid), color));
+            for ((offset, width), color) in cpu.into_iter().chain(mem).chain(ns) {
+                let start = get_x(upper_left) + offset;
+                for x in start..std::cmp::min(start + width, x) {
+                    grid[(x, y)].set_fg(color);
+                }
+            }
+        }
+        if let (true, Some(offset)) = (self.sparkline_baseline, self.maxima.sparkline_offset()) {
+            let start = get_x(upper_left) + offset;
+            for x in start..std::cmp::min(start + HISTORY_LEN, x) {
+                if grid[(x, y)].ch() == BASELINE {
+                    grid[(x, y)].set_attrs(Attr::Dim);
+                }
+            }
+        }
+        if p.state == State::Running {
+            grid[(x - 3, y)].set_fg(if self.freeze {
+                Color::Byte(12)
+            } else {
+                Color::Byte(10)
+            });
+        } else if p.state.is_zombie() {
+            grid[(x - 3, y)]
+                .set_fg(Color::Black)
+                .set_bg(ZOMBIE_BG)
+                .set_attrs(Attr::Bold);
+        }
+        let (x, y_) = write_styled(branches, grid, style, ((x, y), bottom_right), false);
+        if y_ != y {
+            return;
+        }
+        /* the cursor and the highlights above take precedence over the category colors */
+        let category_color = match self.categories {
+            Some(theme) if fg_color == Color::Default => {
+                Some(theme.category(p.category)).filter(|&color| color != Color::Default)
+            }
+            _ => None,
+        };
+        let bin_fg = if let Some(color) = category_color {
+            color
+        } else if self.freeze {
+            Color::Byte(32)
+        } else {
+            Color::Byte(34)
+        };
+        let (x, y) = if self.two_line {
+            /* the executable's name on the first line, the whole command line on the second */
+            let bin = match executable_path_color(&p.cmd_line.0) {
+                Ok((_, bin, _)) | Err((bin, _)) => bin,
+            };
+            let (x, y_) = write_styled(
+                bin,
+                grid,
+                Style {
+                    fg: bin_fg,
+                    ..style
+                },
+                ((x, y), bottom_right),
+                false,
+            );
+            if y_ == y {
+                change_colors(
+                    grid,
+                    ((x, y), set_y(bottom_right, y)),
+                    Some(fg_color),
+                    Some(bg_color),
+                );
+            }
+            let y = y + 1;
+            if y > get_y(bottom_right) {
+                return;
+            }
+            change_colors(
+                grid,
+                (set_y(upper_left, y), set_y(bottom_right, y)),
+                Some(fg_color),
+                Some(bg_color),
+            );
+            (get_x(upper_left) + SECOND_LINE_INDENT, y)
+        } else {
+            (x, y)
+        };
+        let available = (get_x(bottom_right) + 1).saturating_sub(x);
+        let width = self
+            .max_cmd_len
+            .map_or(available, |max| std::cmp::min(max, available));
+        let cmd_line = match self.truncation {
+            /* without a maximum the command line is cut at the edge of the list, like every other
+             * row that doesn't fit */
+            Truncation::End if self.max_cmd_len.is_none() => Cow::Borrowed(p.cmd_line.0.as_str()),
+            truncation => truncate(&p.cmd_line.0, width, truncation),
+        };
+        let (path, bin, rest) = match executable_path_color(&cmd_line) {
+            Ok((path, bin, rest)) => (path, bin, rest),
+            Err((bin, rest)) => ("", bin, rest),
+        };
+        let cmd_x = x;
+        let (x, y_) = write_styled(
+            path,
+            grid,
+            Style {
+                fg: Color::Byte(243),
+                ..style
+            },
+            ((x, y), bottom_right),
+            false,
+        );
+        if y_ != y {
+            return;
+        }
+        let (x, y_) = write_styled(
+            bin,
+            grid,
+            Style {
+                fg: bin_fg,
+                ..style
+            },
+            ((x, y), bottom_right),
+            false,
+        );
+        if y_ != y {
+            return;
+        }
+        let (x, y_) = write_styled(
+            rest,
+            grid,
+            Style {
+                fg: category_color.unwrap_or(fg_color),
+                ..style
+            },
+            ((x, y), bottom_right),
+            false,
+        );
+        if let Some(filter) = self.filter() {
+            highlight_fuzzy_match(grid, &filter, &cmd_line, (cmd_x, y), get_x(bottom_right));
+        }
+        if y_ != y {
+            return;
+        }
+        change_colors(
+            grid,
+            ((x, y), set_y(bottom_right, y)),
+            Some(fg_color),
+            Some(bg_color),
+        );
+    }
+
+    fn draw_tree_list(&mut self, grid: &mut CellBuffer, area: Area, pages: usize, height: usize) {
+        let (upper_left, bottom_right) = area;
+
+        let mut y_offset = 0;
+
+        let mut branches = vec![];
+        let mut child_counters = vec![0];
+
+        let mut lines = Vec::with_capacity(2048);
+        let tree = self.tree_rows();
+        let root_count = tree.iter().filter(|(ind, _)| *ind == 0).count();
+        let mut iter = tree.iter().peekable();
+
+        while let Some((ind, pid)) = iter.next() {
+            let p = &self.processes[self.data.processes_index[pid]];
+            /* Roots are siblings of each other, even if their parents differ */
+            let siblings = if *ind == 0 {
+                root_count
+            } else {
+                self.data.parents.get(&p.p).map(Vec::len).unwrap_or(1)
+            };
+            let has_sibling: bool = child_counters
+                .get(*ind)
+                .map(|n| *n != siblings.saturating_sub(1))
+                .unwrap_or(false);
+
+            if let Some(counter) = child_counters.get_mut(*ind) {
+                *counter += 1;
             }
 
-            if *ind > 0 || (has_sibling || is_first) {
-                if has_sibling && is_first {
-                    s.push('├');
-                } else if has_sibling {
-                    s.push('├');
+            let mut s = String::with_capacity(16);
+            let glyphs = self.tree_glyphs;
+
+            for i in 0..*ind {
+                if branches.len() > i && branches[i] {
+                    s.push(glyphs.vertical);
                 } else {
-                    s.push('└');
+                    s.push(' ');
                 }
-                s.push('─');
+
+                s.push(' ');
+            }
+
+            if has_sibling {
+                s.push(glyphs.branch);
+            } else {
+                s.push(glyphs.last);
+            }
+            s.push(glyphs.horizontal);
+            /* collapsed nodes with hidden children */
+            if self.collapsed.contains(pid) {
+                s.push('+');
+            } else {
                 s.push('>');
             }
 
@@ -488,190 +2994,41 @@ impl ProcessList {
             }
         }
 
-        for ((_, pid), s) in self
-            .data
-            .tree
+        let first = pages * height;
+        let first_row = first_row_offset(self.show_header);
+        let row_stride = self.row_stride();
+        let shown = std::cmp::min(height, lines.len().saturating_sub(first));
+        self.rows_area = RowsArea::new(
+            pos_inc(upper_left, (0, first_row)),
+            bottom_right,
+            first,
+            (shown, row_stride),
+        );
+        let hovered = self.hovered_row();
+        for (n, ((_, pid), s)) in tree
             .iter()
             .zip(lines.iter())
+            .enumerate()
             .skip(pages * height)
             .take(height)
         {
-            let (fg_color, bg_color) = if pages * height + y_offset == self.cursor {
-                (Color::White, Color::Byte(235))
-            } else if let Locate(highlighted_pid) = self.mode {
-                if highlighted_pid == *pid {
-                    (Color::Red, Color::Yellow)
-                } else {
-                    (Color::Default, Color::Default)
-                }
-            } else {
-                (Color::Default, Color::Default)
-            };
+            let (fg_color, bg_color) = self.row_colors(pages * height + y_offset, *pid, hovered);
 
             let p = &self.processes[self.data.processes_index[pid]];
-            match executable_path_color(&p.cmd_line) {
-                Ok((path, bin, rest)) => {
-                    let (x, _) = write_string_to_grid(
-                            &format!(
-                    "{pid:>max_pid$}  {ppid:>max_ppid$}  {username:>max_username$}  {vm_rss:>max_vm_rss$}  {cpu_percent:>max_cpu_percent$}%  {state:>max_state$}  {branches}",
-                            pid = p.pid,
-                            ppid = p.ppid,
-                            username = p.username,
-                            vm_rss = p.vm_rss,
-                            cpu_percent = (p.cpu_percent as f32 / 1000.0),
-                            state = p.state,
-                            max_pid = self.maxima.pid,
-                            max_ppid = self.maxima.ppid,
-                            max_username = self.maxima.username,
-                            max_vm_rss = self.maxima.vm_rss,
-                            max_cpu_percent = self.maxima.cpu_percent,
-                            max_state = self.maxima.state,
-                            branches = s,
-                                ),
-                            grid,
-                            fg_color,
-                            bg_color,
-                            Attr::Default,
-                            (pos_inc(upper_left, (0, y_offset + 2)), bottom_right),
-                            false,
-                        );
-                    if p.state == State::Running {
-                        grid[pos_inc(
-                            upper_left,
-                            (
-                                2 * 5
-                                    + self.maxima.pid
-                                    + self.maxima.ppid
-                                    + self.maxima.username
-                                    + self.maxima.vm_rss
-                                    + self.maxima.cpu_percent,
-                                y_offset + 2,
-                            ),
-                        )]
-                        .set_fg(if self.freeze {
-                            Color::Byte(12)
-                        } else {
-                            Color::Byte(10)
-                        });
-                    }
-                    let (x, _) = write_string_to_grid(
-                        path,
-                        grid,
-                        Color::Byte(243),
-                        bg_color,
-                        Attr::Default,
-                        (pos_inc(upper_left, (x - 1, y_offset + 2)), bottom_right),
-                        false,
-                    );
-                    let (x, _) = write_string_to_grid(
-                        bin,
-                        grid,
-                        if self.freeze {
-                            Color::Byte(32)
-                        } else {
-                            Color::Byte(34)
-                        },
-                        bg_color,
-                        Attr::Default,
-                        (pos_inc(upper_left, (x - 1, y_offset + 2)), bottom_right),
-                        false,
-                    );
-                    write_string_to_grid(
-                        rest,
-                        grid,
-                        fg_color,
-                        bg_color,
-                        Attr::Default,
-                        (pos_inc(upper_left, (x - 1, y_offset + 2)), bottom_right),
-                        false,
-                    );
-                    change_colors(
-                        grid,
-                        (
-                            pos_inc(upper_left, (x - 1 + rest.len(), y_offset + 2)),
-                            set_y(bottom_right, get_y(upper_left) + y_offset + 2),
-                        ),
-                        Some(fg_color),
-                        Some(bg_color),
-                    );
-                }
-                Err((bin, rest)) => {
-                    let (x, _) = write_string_to_grid(
-                            &format!(
-                            "{pid:>max_pid$}  {ppid:>max_ppid$}  {username:>max_username$}  {vm_rss:>max_vm_rss$}  {cpu_percent:>max_cpu_percent$}%  {state:>max_state$}  {branches}",
-                            pid = p.pid,
-                            ppid = p.ppid,
-                            username = p.username,
-                            vm_rss = p.vm_rss,
-                            cpu_percent = (p.cpu_percent as f32 / 100.0),
-                            state = p.state,
-                            max_pid = self.maxima.pid,
-                            max_ppid = self.maxima.ppid,
-                            max_username = self.maxima.username,
-                            max_vm_rss = self.maxima.vm_rss,
-                            max_cpu_percent = self.maxima.cpu_percent,
-                            max_state = self.maxima.state,
-                            branches = s,
-                            ),
-                            grid,
-                            fg_color,
-                            bg_color,
-                            Attr::Default,
-                            (pos_inc(upper_left, (0, y_offset + 2)), bottom_right),
-                            false,
-                        );
-                    if p.state == State::Running {
-                        grid[pos_inc(
-                            upper_left,
-                            (
-                                2 * 5
-                                    + 1
-                                    + self.maxima.pid
-                                    + self.maxima.ppid
-                                    + self.maxima.username
-                                    + self.maxima.vm_rss
-                                    + self.maxima.cpu_percent,
-                                y_offset + 2,
-                            ),
-                        )]
-                        .set_fg(if self.freeze {
-                            Color::Byte(12)
-                        } else {
-                            Color::Byte(10)
-                        });
-                    }
-                    let (x, _) = write_string_to_grid(
-                        bin,
-                        grid,
-                        if self.freeze {
-                            Color::Byte(32)
-                        } else {
-                            Color::Byte(34)
-                        },
-                        bg_color,
-                        Attr::Default,
-                        (pos_inc(upper_left, (x - 1, y_offset + 2)), bottom_right),
-                        false,
-                    );
-                    write_string_to_grid(
-                        rest,
-                        grid,
-                        fg_color,
-                        bg_color,
-                        Attr::Default,
-                        (pos_inc(upper_left, (x - 1, y_offset + 2)), bottom_right),
-                        false,
-                    );
-                    change_colors(
-                        grid,
-                        (
-                            pos_inc(upper_left, (x - 1 + rest.len(), y_offset + 2)),
-                            set_y(bottom_right, get_y(upper_left) + y_offset + 2),
-                        ),
-                        Some(fg_color),
-                        Some(bg_color),
-                    );
-                }
+            let attrs =
+                if self.dim_idle && fg_color == Color::Default && is_idle(p, self.idle_threshold) {
+                    Attr::Dim
+                } else {
+                    Attr::Default
+                };
+            let row_area = (
+                pos_inc(upper_left, (0, y_offset * row_stride + first_row)),
+                bottom_right,
+            );
+            self.draw_process_row(grid, row_area, p, s, (fg_color, bg_color), attrs);
+            /* between rows, not below the last one of the page */
+            if n + 1 < first + shown {
+                self.draw_row_separator(grid, row_area, tree[n + 1].0 == 0);
             }
             y_offset += 1;
         }
@@ -696,22 +3053,62 @@ impl Component for ProcessList {
             tick = true;
         }
 
+        /* The key hints take the last row, and the list the ones above it */
+        let area = if self.key_hints.is_some() && height!(area) > 2 {
+            let (map, _) = self.dispatch_map();
+            let footer = (
+                set_y(upper_left!(area), get_y(bottom_right!(area))),
+                bottom_right!(area),
+            );
+            if let Some(ref mut key_hints) = self.key_hints {
+                if key_hints.draw(grid, footer, &map, std::time::Instant::now(), tick) {
+                    dirty_areas.push_back(footer);
+                }
+            }
+            (upper_left!(area), pos_dec(bottom_right!(area), (0, 1)))
+        } else {
+            area
+        };
+
         let mut upper_left = pos_inc(upper_left!(area), (1, 0));
         let bottom_right = pos_dec(bottom_right!(area), (1, 1));
 
-        /* Reserve first row for column headers */
-        let height = height!(area) - 2 - if self.mode.is_locate() { 2 } else { 0 };
+        /* Reserve the first rows for the column headers, if shown */
+        let first_row = first_row_offset(self.show_header);
+        let row_stride = self.row_stride();
+        /* in processes, not screen rows. The last row needs no separator below it. */
+        let height = std::cmp::max(
+            1,
+            (height!(area) + row_stride
+                - self.row_height()
+                - first_row
+                - if self.mode.is_locate() { 2 } else { 0 })
+                / row_stride,
+        );
         let width = width!(area);
         let old_pages = (self.cursor) / height;
 
         let old_cursor = self.cursor;
         if let Some(mvm) = self.page_movement.take() {
+            if let Some(ref mut follow_new) = self.follow_new {
+                follow_new.moved(std::time::Instant::now());
+            }
+            if self.scroll_freeze.is_some() {
+                let rows = self.visible_rows();
+                let processes = &self.processes;
+                if let Some(ref mut freeze) = self.scroll_freeze {
+                    freeze.scrolled(
+                        std::time::Instant::now(),
+                        rows.iter().map(|&i| processes[i].i),
+                    );
+                }
+            }
             match mvm {
                 PageMovement::Up => {
-                    self.cursor = self.cursor.saturating_sub(1);
+                    self.cursor = step_cursor(self.cursor, self.height, false, self.wrap_selection);
                 }
                 PageMovement::Down => {
-                    self.cursor = std::cmp::min(self.height.saturating_sub(1), self.cursor + 1);
+                    self.cursor = step_cursor(self.cursor, self.height, true, self.wrap_selection);
                 }
                 PageMovement::Home => {
                     self.cursor = 0;
@@ -727,6 +3124,27 @@ impl Component for ProcessList {
                     self.cursor = self.height.saturating_sub(1);
                 }
             }
+            if self.tombstones.is_some() && !self.select_tombstones {
+                let forward = !matches!(
+                    mvm,
+                    PageMovement::Up | PageMovement::PageUp | PageMovement::End
+                );
+                let rows = self.visible_rows();
+                self.cursor = self.selectable_row(&rows, self.cursor, forward);
+                /* only tombstones were left between the cursor and the end it moved to */
+                if self.wrap_selection && self.cursor == old_cursor {
+                    match mvm {
+                        PageMovement::Up => {
+                            self.cursor =
+                                self.selectable_row(&rows, rows.len().saturating_sub(1), false);
+                        }
+                        PageMovement::Down => {
+                            self.cursor = self.selectable_row(&rows, 0, true);
+                        }
+                        _ => {}
+                    }
+                }
+            }
         }
 
         let mut pages = (self.cursor) / height;
@@ -737,8 +3155,77 @@ impl Component for ProcessList {
         let update_maxima = tick && !self.freeze;
 
         if update_maxima {
+            if self.grouped && self.regrouped.is_none() {
+                self.regrouped = self
+                    .visible_rows()
+                    .get(self.cursor)
+                    .map(|&i| GroupedRow::of(&self.processes[i]));
+            }
+            if self.min_cpu.is_some() && !self.draw_tree {
+                self.min_cpu_kept = self
+                    .visible_rows()
+                    .get(self.cursor)
+                    .map(|&i| self.processes[i].i);
+            }
+            let new_sample = self.update_snapshot();
+            if let Some(ref mut summary) = self.summary {
+                summary.update(self.snapshot.processes.iter().map(|p| p.state));
+            }
             let follow = self.follow();
-            self.processes = get(&mut self.data, follow, self.sort);
+            let previous = std::mem::take(&mut self.processes);
+            self.processes = get(
+                &mut self.data,
+                &self.snapshot,
+                follow,
+                self.sort,
+                self.secondary_sort,
+                &self.pid_filter,
+                self.normalize,
+            );
+            if let Some(alpha) = self.smoothing {
+                smooth_cpu_percent(
+                    &mut self.processes,
+                    &mut self.cpu_averages,
+                    alpha,
+                    new_sample,
+                    std::time::Instant::now(),
+                );
+            }
+            if let Some(ref mut histories) = self.cpu_histories {
+                record_cpu_history(
+                    &mut self.processes,
+                    histories,
+                    new_sample,
+                    self.sparkline_baseline,
+                    self.graph_direction,
+                );
+            }
+            if let Some(ref mut ages) = self.ages {
+                ages.record(&self.processes, new_sample);
+            }
+            if let Some(ref mut mem_growth) = self.mem_growth {
+                mem_growth.record(&self.processes, new_sample, std::time::Instant::now());
+            }
+            if let Some(ref mut gpus) = self.gpus {
+                gpus.record(&mut self.processes, new_sample);
+            }
+            if let Some(ref mut follow_new) = self.follow_new {
+                follow_new.record(&self.processes, new_sample);
+            }
+            if new_sample {
+                /* forget the collapsed nodes of processes that exited */
+                let index = &self.data.processes_index;
+                self.collapsed.retain(|pid| index.contains_key(pid));
+            }
+            if let Some(ref mut tombstones) = self.tombstones {
+                tombstones.record(previous, &self.processes, new_sample);
+                if !self.grouped && !self.draw_tree {
+                    tombstones.merge_into(&mut self.processes);
+                }
+            }
+            if self.grouped {
+                regroup(&mut self.processes, true);
+            }
         };
 
         if tick || self.freeze {
@@ -748,19 +3235,101 @@ impl Component for ProcessList {
 
             dirty_areas.push_back(area);
 
-            if !self.freeze && update_maxima {
-                /* Keep tabs on biggest element in each column */
-                self.maxima = ColumnWidthMaxima::new();
+            /* The tally goes on the blank row above the column headers */
+            if let Some(ref summary) = self.summary {
+                summary.draw(grid, (upper_left, set_y(bottom_right, get_y(upper_left))));
+            }
 
-                for p in &self.processes {
-                    self.maxima.pid = std::cmp::max(self.maxima.pid, p.pid.len());
-                    self.maxima.ppid = std::cmp::max(self.maxima.ppid, p.ppid.len());
-                    self.maxima.vm_rss = std::cmp::max(self.maxima.vm_rss, p.vm_rss.len());
-                    self.maxima.username = std::cmp::max(self.maxima.username, p.username.len());
+            if self.auto_expand && self.draw_tree {
+                self.expand_selected();
+            }
+            let rows = self.visible_rows();
+            self.height = rows.len();
+            /* keep the cursor on the process it was on if only the cursor keeps it listed */
+            if let Some(i) = rows
+                .iter()
+                .position(|&i| self.is_kept_below_min_cpu(&self.processes[i]))
+                .filter(|_| update_maxima)
+            {
+                self.cursor = i;
+                pages = i / height;
+            }
+            if let Some(pid) = self.resorted.take() {
+                if let Some(i) =
+                    cursor_after_sort(self.sort_scroll, self.sort, pid, &rows, &self.processes)
+                {
+                    self.cursor = i;
+                    pages = i / height;
+                }
+            }
+            let newest = self
+                .follow_new
+                .as_mut()
+                .and_then(|follow_new| follow_new.take_newest(std::time::Instant::now()))
+                .filter(|_| matches!(self.sort, Sort::StartAsc | Sort::StartDesc));
+            if let Some(i) =
+                newest.and_then(|pid| rows.iter().position(|&i| self.processes[i].i == pid))
+            {
+                self.cursor = i;
+                pages = i / height;
+            }
+            if let Some(row) = self.regrouped.take() {
+                if let Some(i) = row.find(&self.processes, &rows) {
+                    self.cursor = i;
+                    pages = i / height;
+                }
+            }
+            if let Search(ref search) = self.mode {
+                if search.len() > 1 {
+                    let search = Filter::new(search, self.case_sensitive, self.fuzzy);
+                    /* the first match, or the best one if matching is fuzzy */
+                    if let Some((i, _)) = rows
+                        .iter()
+                        .enumerate()
+                        .filter(|&(_, &i)| search.matches(&self.processes[i].cmd_line.0))
+                        .min_by_key(|&(_, &i)| -search.score(&self.processes[i].cmd_line.0))
+                    {
+                        self.cursor = i;
+                        pages = i / height;
+                    }
                 }
+            }
+            self.cursor = std::cmp::min(self.height.saturating_sub(1), self.cursor);
+            self.cursor = self.selectable_row(&rows, self.cursor, true);
 
-                self.cursor = std::cmp::min(self.height.saturating_sub(1), self.cursor);
+            /* Only the rows around the current page are formatted, and the column widths fit
+             * them */
+            let window = format_window(
+                &mut self.processes,
+                &rows,
+                pages * height,
+                height,
+                (self.mem_format, self.snapshot.mem_total * 1024),
+                self.env_column.as_mut(),
+                self.sockets.as_mut(),
+            );
+            let processes = &self.processes;
+            self.maxima = ColumnWidthMaxima::from_processes(
+                rows[window.clone()].iter().map(|&i| &processes[i]),
+            );
+            if let Some(ref env_column) = self.env_column {
+                self.maxima.fit_env(env_column.key());
             }
+            self.maxima.net = self.net_column;
+            self.maxima.gpu = self.gpus.is_some();
+            self.maxima.percent_format = self.percent_format;
+            self.maxima
+                .fit_notation(self.notation, rows[window].iter().map(|&i| &processes[i]));
+            self.maxima.alignments = self.alignments;
+            self.maxima.columns = self.columns;
+            self.maxima.graph_direction = self.graph_direction;
+            self.maxima.fit_pids(
+                self.processes
+                    .iter()
+                    .map(|p| std::cmp::max(p.i, p.p))
+                    .max()
+                    .unwrap_or(0),
+            );
             if let Follow(ref pid) = self.mode {
                 let info = format!("Following PID == {pid} || PPID == {pid}", pid = pid);
                 write_string_to_grid(
@@ -801,58 +3370,84 @@ impl Component for ProcessList {
 
             let cmd_header = if let Search(ref p) = self.mode {
                 Some(format!("CMD_LINE (search: {})", p))
+            } else if let KillName(ref p) = self.mode {
+                Some(format!(
+                    "CMD_LINE (kill by {}name: {})",
+                    if self.kill_name_exact { "exact " } else { "" },
+                    p
+                ))
             } else {
                 self.filter_term
                     .as_ref()
                     .map(|filter_term| format!("CMD_LINE (filter: {})", filter_term))
             };
 
-            /* Write column headers */
-            let (x, y) = write_string_to_grid(
-                &format!(
-                    "{pid:>max_pid$}  {ppid:>max_ppid$}  {username:>max_username$}{usernamesort} {vm_rss:>max_vm_rss$}{vmrsssort} {cpu_percent:>max_cpu_percent$}{cpusort} {state:>max_state$}  {cmd_line}{cmd_linesort}",
-                    pid = "PID",
-                    ppid ="PPID",
-                    username = "USER",
-                    vm_rss = "VM_RSS",
-                    cpu_percent = "  CPU%",
-                    state = " ",
-                    cmd_line = if let Some(ref cmd_header) = cmd_header { cmd_header } else { "CMD_LINE"} ,
-                    max_pid = self.maxima.pid,
-                    max_ppid = self.maxima.ppid,
-                    max_username = self.maxima.username,
-                    max_vm_rss = self.maxima.vm_rss,
-                    max_cpu_percent = self.maxima.cpu_percent,
-                    max_state = self.maxima.state,
-                    usernamesort = if let Sort::UserAsc = self.sort { "↑" } else if let Sort::UserDesc = self.sort { "↓" } else { " " },
-                    vmrsssort = if let Sort::VmRssAsc = self.sort { "↑" } else if let Sort::VmRssDesc = self.sort { "↓" } else { " " },
-                    cpusort = if let Sort::CpuAsc = self.sort { "↑" } else if let Sort::CpuDesc = self.sort { "↓" } else { " " },
-                    cmd_linesort = if let Sort::CmdLineAsc = self.sort { "↑" } else if let Sort::CmdLineDesc = self.sort { "↓" } else { "" },
-                ),
-                grid,
-                Color::Black,
-                Color::White,
-                Attr::Default,
-                (pos_inc(upper_left, (0, 1)), bottom_right),
-                false,
-            );
-            change_colors(
-                grid,
-                ((x, y), set_y(bottom_right, y)),
-                Some(Color::Black),
-                Some(Color::White),
-            );
+            /* Write column headers. They are drawn above the rows of every page, so scrolling
+             * never moves them. */
+            if self.show_header {
+                let header = self.maxima.header(
+                    self.sort,
+                    if let Some(ref cmd_header) = cmd_header {
+                        cmd_header
+                    } else {
+                        "CMD_LINE"
+                    },
+                );
+                let (x, y) = write_string_to_grid(
+                    &header,
+                    grid,
+                    Color::Black,
+                    Color::White,
+                    Attr::Default,
+                    (pos_inc(upper_left, (0, 1)), bottom_right),
+                    false,
+                );
+                change_colors(
+                    grid,
+                    ((x, y), set_y(bottom_right, y)),
+                    Some(Color::Black),
+                    Some(Color::White),
+                );
+                /* the list doesn't scroll sideways, so the table always starts at its first
+                 * column */
+                let header_row = pos_inc(upper_left, (0, 1));
+                draw_scroll_indicators(
+                    grid,
+                    (header_row, set_y(bottom_right, get_y(header_row))),
+                    0,
+                    header.grapheme_width(),
+                );
+                /* the time the rows are from, which stops while the list is frozen */
+                if let Some(ref pattern) = self.sample_time {
+                    let time = format_local_time(self.snapshot.time, pattern);
+                    let time_x = (get_x(bottom_right) + 1).saturating_sub(time.grapheme_width());
+                    if y == get_y(upper_left) + 1 && time_x > x {
+                        write_string_to_grid(
+                            &time,
+                            grid,
+                            Color::Black,
+                            Color::White,
+                            Attr::Default,
+                            ((time_x, y), set_y(bottom_right, y)),
+                            false,
+                        );
+                    }
+                }
+            }
 
             /* Write current selected status if any. eg. if list is frozen, show 'FROZEN'. */
             {
                 let (mut x, y) = set_y(upper_left!(area), get_y(bottom_right) + 1);
+                let badge = |bg| Style {
+                    fg: Color::White,
+                    bg,
+                    attrs: Attr::Bold,
+                };
                 if self.freeze {
-                    let (_x, _) = write_string_to_grid(
+                    let (_x, _) = write_styled(
                         "  FROZEN  ",
                         grid,
-                        Color::White,
-                        Color::Byte(26), // DodgerBlue3
-                        Attr::Bold,
+                        badge(Color::Byte(26)), // DodgerBlue3
                         ((x, y), pos_inc(bottom_right, (0, 1))),
                         false,
                     );
@@ -860,45 +3455,37 @@ impl Component for ProcessList {
                 }
 
                 if self.mode.is_search() {
-                    let (_x, _) = write_string_to_grid(
+                    let (_x, _) = write_styled(
                         "  SEARCH  ",
                         grid,
-                        Color::White,
-                        Color::Byte(88), // DarkRed
-                        Attr::Bold,
+                        badge(Color::Byte(88)), // DarkRed
                         ((x, y), pos_inc(bottom_right, (0, 1))),
                         false,
                     );
                     x = _x;
                 } else if self.mode.is_follow() {
-                    let (_x, _) = write_string_to_grid(
+                    let (_x, _) = write_styled(
                         "  FOLLOW  ",
                         grid,
-                        Color::White,
-                        Color::Byte(172), // Orange3
-                        Attr::Bold,
+                        badge(Color::Byte(172)), // Orange3
                         ((x, y), pos_inc(bottom_right, (0, 1))),
                         false,
                     );
                     x = _x;
                 } else if self.mode.is_locate() {
-                    let (_x, _) = write_string_to_grid(
+                    let (_x, _) = write_styled(
                         "  LOCATE  ",
                         grid,
-                        Color::White,
-                        Color::Green,
-                        Attr::Bold,
+                        badge(Color::Green),
                         ((x, y), pos_inc(bottom_right, (0, 1))),
                         false,
                     );
                     x = _x;
-                } else if self.mode.is_kill() {
-                    let (_x, _) = write_string_to_grid(
+                } else if self.mode.is_kill() || self.mode.is_kill_name() {
+                    let (_x, _) = write_styled(
                         "  KILL  ",
                         grid,
-                        Color::White,
-                        Color::Byte(8), // Grey
-                        Attr::Bold,
+                        badge(Color::Byte(8)), // Grey
                         ((x, y), pos_inc(bottom_right, (0, 1))),
                         false,
                     );
@@ -906,238 +3493,161 @@ impl Component for ProcessList {
                 }
 
                 if self.filter_term.is_some() {
-                    write_string_to_grid(
+                    let (_x, _) = write_styled(
                         "  FILTER  ",
                         grid,
-                        Color::White,
-                        Color::Byte(13), // Fuschia
-                        Attr::Bold,
+                        badge(Color::Byte(13)), // Fuschia
                         ((x, y), pos_inc(bottom_right, (0, 1))),
                         false,
                     );
+                    x = _x;
                 }
-            }
-            let mut y_offset = 0;
 
-            if self.draw_tree {
-                self.draw_tree_list(grid, (upper_left, bottom_right), pages, height);
-                self.height = self.data.tree.len();
-                self.cursor = std::cmp::min(self.height, self.cursor);
-            } else {
-                let mut processes = self.processes.iter().collect::<Vec<&ProcessDisplay>>();
-                processes.sort_unstable_by(|a, b| match self.sort {
-                    Sort::CpuAsc => a.cpu_percent.cmp(&b.cpu_percent),
-                    Sort::CpuDesc => b.cpu_percent.cmp(&a.cpu_percent),
-                    Sort::VmRssAsc => a.vm_rss_value.cmp(&b.vm_rss_value),
-                    Sort::VmRssDesc => b.vm_rss_value.cmp(&a.vm_rss_value),
-                    Sort::UserAsc => a.username.0.cmp(&b.username.0),
-                    Sort::UserDesc => b.username.0.cmp(&a.username.0),
-                    Sort::CmdLineAsc => a.cmd_line.0.cmp(&b.cmd_line.0),
-                    Sort::CmdLineDesc => b.cmd_line.0.cmp(&a.cmd_line.0),
-                });
-                if self.filter_term.is_some() {
-                    processes.retain(|process| {
-                        process
-                            .cmd_line
-                            .0
-                            .contains(self.filter_term.as_ref().unwrap())
-                    });
+                if !self.pid_filter.pids.is_empty() {
+                    let (_x, _) = write_styled(
+                        "  WATCH  ",
+                        grid,
+                        badge(Color::Byte(30)), // DarkCyan
+                        ((x, y), pos_inc(bottom_right, (0, 1))),
+                        false,
+                    );
+                    x = _x;
                 }
-                self.height = processes.len();
-                self.cursor = if let Search(ref search) = self.mode {
-                    let mut ret = self.cursor;
-                    if search.len() > 1 {
-                        for (i, p) in processes.iter().enumerate() {
-                            if p.cmd_line.0.contains(search) {
-                                pages = i / height;
-                                ret = i;
-                                break;
-                            }
-                        }
-                    }
-                    ret
-                } else {
-                    std::cmp::min(self.height, self.cursor)
-                };
 
-                for p in processes.iter().skip(pages * height).take(height) {
-                    let (fg_color, bg_color) = if pages * height + y_offset == self.cursor {
-                        (Color::White, Color::Byte(235))
-                    } else if let Locate(highlighted_pid) = self.mode {
-                        if highlighted_pid == p.i {
-                            (Color::Red, Color::Yellow)
+                if let Some(ref baseline) = self.data.baseline {
+                    let gone = baseline.gone(&self.snapshot).len();
+                    let (_x, _) = write_styled(
+                        &if gone > 0 {
+                            format!("  BASELINE  {} GONE  ", gone)
                         } else {
-                            (Color::Default, Color::Default)
-                        }
-                    } else {
-                        (Color::Default, Color::Default)
-                    };
+                            "  BASELINE  ".to_string()
+                        },
+                        grid,
+                        badge(Color::Byte(94)), // Orange4
+                        ((x, y), pos_inc(bottom_right, (0, 1))),
+                        false,
+                    );
+                    x = _x;
+                }
 
-                    match executable_path_color(&p.cmd_line) {
-                        Ok((path, bin, rest)) => {
-                            let (x, _) = write_string_to_grid(
-                            &format!(
-                    "{pid:>max_pid$}  {ppid:>max_ppid$}  {username:>max_username$}  {vm_rss:>max_vm_rss$}  {cpu_percent:>max_cpu_percent$}%  {state:>max_state$}  ",
-                            pid = p.pid,
-                            ppid = p.ppid,
-                            username = p.username,
-                            vm_rss = p.vm_rss,
-                            cpu_percent = (p.cpu_percent as f64 / 100.0),
-                            state = p.state,
-                            max_pid = self.maxima.pid,
-                            max_ppid = self.maxima.ppid,
-                            max_username = self.maxima.username,
-                            max_vm_rss = self.maxima.vm_rss,
-                            max_cpu_percent = self.maxima.cpu_percent,
-                            max_state = self.maxima.state,
-                                ),
+                if let Some(color) = self.uninterruptible {
+                    let count = count_uninterruptible(&self.processes);
+                    if count > 0 {
+                        let (_x, _) = write_styled(
+                            &format!("  D-STATE {}  ", count),
                             grid,
-                            fg_color,
-                            bg_color,
-                            Attr::Default,
-                            (pos_inc(upper_left, (0, y_offset + 2)), bottom_right),
+                            badge(color),
+                            ((x, y), pos_inc(bottom_right, (0, 1))),
                             false,
                         );
-                            if p.state == State::Running {
-                                grid[pos_inc(
-                                    upper_left,
-                                    (
-                                        2 * 5
-                                            + self.maxima.pid
-                                            + self.maxima.ppid
-                                            + self.maxima.username
-                                            + self.maxima.vm_rss
-                                            + self.maxima.cpu_percent,
-                                        y_offset + 2,
-                                    ),
-                                )]
-                                .set_fg(if self.freeze {
-                                    Color::Byte(12)
-                                } else {
-                                    Color::Byte(10)
-                                });
-                            }
-                            let (x, _) = write_string_to_grid(
-                                path,
-                                grid,
-                                Color::Byte(243),
-                                bg_color,
-                                Attr::Default,
-                                (pos_inc(upper_left, (x - 1, y_offset + 2)), bottom_right),
-                                false,
-                            );
-                            let (x, _) = write_string_to_grid(
-                                bin,
-                                grid,
-                                if self.freeze {
-                                    Color::Byte(32)
-                                } else {
-                                    Color::Byte(34)
-                                },
-                                bg_color,
-                                Attr::Default,
-                                (pos_inc(upper_left, (x - 1, y_offset + 2)), bottom_right),
-                                false,
-                            );
-                            write_string_to_grid(
-                                rest,
-                                grid,
-                                fg_color,
-                                bg_color,
-                                Attr::Default,
-                                (pos_inc(upper_left, (x - 1, y_offset + 2)), bottom_right),
-                                false,
-                            );
-                            change_colors(
-                                grid,
-                                (
-                                    pos_inc(upper_left, (x - 1 + rest.len(), y_offset + 2)),
-                                    set_y(bottom_right, get_y(upper_left) + y_offset + 2),
-                                ),
-                                Some(fg_color),
-                                Some(bg_color),
-                            );
-                        }
-                        Err((bin, rest)) => {
-                            let (x, _) = write_string_to_grid(
-                            &format!(
-                            "{pid:>max_pid$}  {ppid:>max_ppid$}  {username:>max_username$}  {vm_rss:>max_vm_rss$}  {cpu_percent:>max_cpu_percent$}%  {state:>max_state$}  ",
-                            pid = p.pid,
-                            ppid = p.ppid,
-                            username = p.username,
-                            vm_rss = p.vm_rss,
-                            cpu_percent = (p.cpu_percent as f64 / 100.0),
-                            state = p.state,
-                            max_pid = self.maxima.pid,
-                            max_ppid = self.maxima.ppid,
-                            max_username = self.maxima.username,
-                            max_vm_rss = self.maxima.vm_rss,
-                            max_cpu_percent = self.maxima.cpu_percent,
-                            max_state = self.maxima.state,
-                            ),
-                            grid,
-                            fg_color,
-                            bg_color,
-                            Attr::Default,
-                            (pos_inc(upper_left, (0, y_offset + 2)), bottom_right),
-                            false,
-                        );
-                            if p.state == State::Running {
-                                grid[pos_inc(
-                                    upper_left,
-                                    (
-                                        2 * 5
-                                            + 1
-                                            + self.maxima.pid
-                                            + self.maxima.ppid
-                                            + self.maxima.username
-                                            + self.maxima.vm_rss
-                                            + self.maxima.cpu_percent,
-                                        y_offset + 2,
-                                    ),
-                                )]
-                                .set_fg(if self.freeze {
-                                    Color::Byte(12)
-                                } else {
-                                    Color::Byte(10)
-                                });
-                            }
-                            let (x, _) = write_string_to_grid(
-                                bin,
-                                grid,
-                                if self.freeze {
-                                    Color::Byte(32)
-                                } else {
-                                    Color::Byte(34)
-                                },
-                                bg_color,
-                                Attr::Default,
-                                (pos_inc(upper_left, (x - 1, y_offset + 2)), bottom_right),
-                                false,
-                            );
-                            write_string_to_grid(
-                                rest,
-                                grid,
-                                fg_color,
-                                bg_color,
-                                Attr::Default,
-                                (pos_inc(upper_left, (x - 1, y_offset + 2)), bottom_right),
-                                false,
-                            );
-                            change_colors(
-                                grid,
-                                (
-                                    pos_inc(upper_left, (x - 1 + rest.len(), y_offset + 2)),
-                                    set_y(bottom_right, get_y(upper_left) + y_offset + 2),
-                                ),
-                                Some(fg_color),
-                                Some(bg_color),
-                            );
-                        }
+                        x = _x;
+                    }
+                }
+
+                if let Some(ref msg) = self.status_message {
+                    write_string_to_grid(
+                        &format!("  {}", msg),
+                        grid,
+                        Color::Default,
+                        Color::Default,
+                        Attr::Default,
+                        ((x, y), pos_inc(bottom_right, (0, 1))),
+                        false,
+                    );
+                }
+            }
+            let mut y_offset = 0;
+
+            if self.draw_tree {
+                self.draw_tree_list(grid, (upper_left, bottom_right), pages, height);
+            } else {
+                let first = pages * height;
+                let shown = std::cmp::min(height, self.height.saturating_sub(first));
+                self.rows_area = RowsArea::new(
+                    pos_inc(upper_left, (0, first_row)),
+                    bottom_right,
+                    first,
+                    (shown, row_stride),
+                );
+                let hovered = self.hovered_row();
+                for (n, &i) in rows.iter().enumerate().skip(pages * height).take(height) {
+                    let p = &self.processes[i];
+                    let mut branches = if self.grouped {
+                        group_branches(
+                            &self.processes,
+                            i,
+                            rows.get(n + 1).copied(),
+                            &self.expanded_groups,
+                        )
+                    } else {
+                        String::new()
+                    };
+                    if self.is_kept_below_min_cpu(p) {
+                        branches.insert_str(0, MIN_CPU_KEPT_MARKER);
+                    }
+                    if !self.grouped && self.pins.is_pinned(p) {
+                        branches.insert_str(0, PIN_MARKER);
+                    }
+                    let (fg_color, bg_color) =
+                        self.row_colors(pages * height + y_offset, p.i, hovered);
+
+                    let tombstone = self.is_tombstone(p);
+                    let attrs = if tombstone
+                        || (self.dim_idle
+                            && fg_color == Color::Default
+                            && is_idle(p, self.idle_threshold))
+                    {
+                        Attr::Dim
+                    } else {
+                        Attr::Default
+                    };
+                    let fg_color = match fg_color {
+                        Color::Default if tombstone => TOMBSTONE_FG,
+                        fg_color => fg_color,
+                    };
+                    let row_area = (
+                        pos_inc(upper_left, (0, y_offset * row_stride + first_row)),
+                        bottom_right,
+                    );
+                    self.draw_process_row(
+                        grid,
+                        row_area,
+                        p,
+                        &branches,
+                        (fg_color, bg_color),
+                        attrs,
+                    );
+                    if n + 1 < first + shown {
+                        self.draw_row_separator(grid, row_area, true);
                     }
                     y_offset += 1;
                 }
             }
+            if self.height == 0 {
+                draw_empty_state(grid, (pos_inc(upper_left, (0, first_row)), bottom_right));
+            }
+            if self.scrollbar {
+                /* in the right margin, next to the rows */
+                let list_rows =
+                    (height * row_stride).saturating_sub(row_stride - self.row_height());
+                let x = get_x(bottom_right!(area));
+                let top = get_y(upper_left) + first_row;
+                if let Some((start, len)) =
+                    scrollbar_thumb(list_rows, pages * height, height, self.height)
+                {
+                    for row in 0..list_rows {
+                        let (ch, fg) = if (start..start + len).contains(&row) {
+                            ('█', Color::Byte(244))
+                        } else {
+                            ('│', Color::Byte(238))
+                        };
+                        if let Some(cell) = grid.get_mut(x, top + row) {
+                            cell.set_ch(ch);
+                            cell.set_fg(fg);
+                        }
+                    }
+                }
+            }
         } else if old_cursor != self.cursor {
             if let Follow(ref pid) = self.mode {
                 let info = format!("Following PID == {pid} || PPID == {pid}", pid = pid);
@@ -1192,32 +3702,17 @@ impl Component for ProcessList {
                 upper_left = pos_inc(upper_left, (0, 2));
             }
 
-            let new_area = (
-                pos_inc(upper_left, (0, self.cursor + 2 - pages * height)),
-                set_y(
-                    bottom_right,
-                    get_y(upper_left) + self.cursor + 2 - pages * height,
-                ),
-            );
+            let rows = (pos_inc(upper_left, (0, first_row)), bottom_right);
+            let new_area = self.process_row_area(rows, self.cursor - pages * height);
             let old_pid = self.get_pid_under_cursor(old_cursor);
-            change_colors(grid, new_area, None, Some(Color::Byte(235)));
-            let bg_color = if let Locate(highlighted_pid) = self.mode {
-                if highlighted_pid == old_pid {
-                    Color::Yellow
-                } else {
-                    Color::Default
-                }
-            } else {
-                Color::Default
-            };
-            let old_area = (
-                pos_inc(upper_left, (0, old_cursor + 2 - old_pages * height)),
-                set_y(
-                    bottom_right,
-                    get_y(upper_left) + old_cursor + 2 - old_pages * height,
-                ),
-            );
+            let (selected_fg, selected_bg) = self.selection;
+            change_colors(grid, new_area, None, Some(selected_bg));
+            /* only the text drawn in the row's own color, not eg. the executable names */
+            recolor_fg(grid, new_area, Color::Default, selected_fg);
+            let (_, bg_color) = self.row_colors(old_cursor, old_pid, self.hovered_row());
+            let old_area = self.process_row_area(rows, old_cursor - old_pages * height);
             change_colors(grid, old_area, None, Some(bg_color));
+            recolor_fg(grid, old_area, selected_fg, Color::Default);
             dirty_areas.push_back(old_area);
             dirty_areas.push_back(new_area);
         } else if let Follow(ref pid) = self.mode {
@@ -1270,8 +3765,7 @@ impl Component for ProcessList {
             create_box(grid, box_area);
             let mut x = 1;
             for s in SIGNAL_LIST.chunks(11) {
-                let mut y = 0;
-                for sig in s {
+                for (y, sig) in s.iter().enumerate() {
                     write_string_to_grid(
                         sig.1,
                         grid,
@@ -1281,23 +3775,16 @@ impl Component for ProcessList {
                         (pos_inc(upper_left!(box_area), (x, 1 + y)), bottom_right),
                         false,
                     );
-                    y += 1;
                 }
                 x += 11;
             }
             let box_area = (
                 (margin_left, margin_top + 13),
-                (margin_left + 36, margin_top + 16),
+                (margin_left + 36, margin_top + 18),
             );
             clear_area(grid, box_area);
             create_box(grid, box_area);
-            let signal_fmt = if *n == 0 {
-                format!("__")
-            } else if *n < 32 {
-                format!("{} [{}]", SIGNAL_LIST[*n as usize - 1].1, *n)
-            } else {
-                format!("invalid [{}]", *n)
-            };
+            let signal_fmt = signal_name(*n);
             let pid = self.get_pid_under_cursor(self.cursor);
             write_string_to_grid(
                 &format!(
@@ -1333,6 +3820,58 @@ impl Component for ProcessList {
                 ),
                 false,
             );
+            let (target, fg, attr) = match self.signal_target() {
+                SignalTarget::Group(pgrp) => (
+                    format!(
+                        "to PROCESS GROUP {} ({} processes)",
+                        pgrp,
+                        self.processes.iter().filter(|p| p.pgrp == pgrp).count()
+                    ),
+                    Color::Red,
+                    Attr::Bold,
+                ),
+                SignalTarget::Process(pid) => (
+                    format!("to PID {} only", pid),
+                    Color::Default,
+                    Attr::Default,
+                ),
+            };
+            write_string_to_grid(
+                &target,
+                grid,
+                fg,
+                Color::Default,
+                attr,
+                (
+                    pos_inc(upper_left!(box_area), (1, 3)),
+                    bottom_right!(box_area),
+                ),
+                false,
+            );
+            write_string_to_grid(
+                "'g' toggles the process group",
+                grid,
+                Color::Default,
+                Color::Default,
+                Attr::Default,
+                (
+                    pos_inc(upper_left!(box_area), (1, 4)),
+                    bottom_right!(box_area),
+                ),
+                false,
+            );
+        }
+
+        if let KillMatches(ref pids, n) = self.mode {
+            self.draw_kill_matches_box(grid, pids, n);
+        }
+
+        if let Some(pid) = self.inspected {
+            self.draw_inspector_box(grid, pid);
+        }
+
+        if let Some(selected) = self.column_picker {
+            self.draw_column_picker_box(grid, selected);
         }
 
         if self.draw_help {
@@ -1343,8 +3882,77 @@ impl Component for ProcessList {
     }
 
     fn process_event(&mut self, event: &mut UIEvent) {
-        let map = &self.get_shortcuts()[""];
+        let (ref map, ref disabled) = self.dispatch_map();
+        if let UIEvent::Input(_) = event {
+            if self.status_message.take().is_some() {
+                self.force_redraw = true;
+            }
+        }
         match event {
+            UIEvent::StatusMessage(msg) => {
+                self.status_message = Some(std::mem::take(msg));
+                self.force_redraw = true;
+                self.dirty = true;
+            }
+            UIEvent::Mouse(ev) if self.hover_enabled && !self.mode.is_kill() && !self.draw_help => {
+                match *ev {
+                    MouseEvent::Move(x, y) => {
+                        let old_row = self.hovered_row();
+                        self.hover = Some((x, y));
+                        if self.hovered_row().is_none() {
+                            /* The pointer left the list */
+                            self.hover = None;
+                        }
+                        if self.hovered_row() != old_row {
+                            self.force_redraw = true;
+                        }
+                    }
+                    MouseEvent::Press(x, y) => {
+                        if let Some(i) = self.rows_area.and_then(|r| r.row_at((x, y))) {
+                            if let Some(ref mut follow_new) = self.follow_new {
+                                follow_new.moved(std::time::Instant::now());
+                            }
+                            self.cursor = i;
+                            self.force_redraw = true;
+                        }
+                    }
+                    MouseEvent::Release(_, _) => {}
+                }
+            }
+            UIEvent::Input(key) if self.column_picker.is_some() => {
+                let rows = self.picker_rows();
+                let selected = std::cmp::min(
+                    self.column_picker.unwrap_or(0),
+                    rows.len().saturating_sub(1),
+                );
+                match key {
+                    Key::Up => self.column_picker = Some(selected.saturating_sub(1)),
+                    Key::Down => {
+                        let last = rows.len().saturating_sub(1);
+                        self.column_picker = Some(std::cmp::min(selected + 1, last));
+                    }
+                    Key::Char(' ') => {
+                        if let Some(&i) = rows.get(selected) {
+                            self.columns.toggle(i);
+                            self.set_view_columns(self.view_columns());
+                        }
+                    }
+                    Key::Char('K') => {
+                        self.column_picker = Some(self.columns.move_up(&rows, selected))
+                    }
+                    Key::Char('J') => {
+                        self.column_picker = Some(self.columns.move_down(&rows, selected))
+                    }
+                    key if *key == map["pick columns"] || *key == map["cancel"] => {
+                        /* cycling back to the view keeps its columns */
+                        self.column_picker = None;
+                        self.views.set_columns(self.view_columns());
+                    }
+                    _ => return,
+                }
+                self.force_redraw = true;
+                self.dirty = true;
+            }
             UIEvent::Input(Key::Up) => {
                 self.page_movement = Some(PageMovement::Up);
                 self.dirty = true;
@@ -1380,12 +3988,97 @@ impl Component for ProcessList {
                     (Sort::CpuAsc, 3) | (_, 3) => CpuDesc,
                     (Sort::CmdLineDesc, 4) => CmdLineAsc,
                     (Sort::CmdLineAsc, 4) | (_, 4) => CmdLineDesc,
+                    /* the I/O sorts only make sense with the I/O columns shown */
+                    (_, 5..=7) if !self.data.io => return,
+                    (Sort::ReadDesc, 5) => ReadAsc,
+                    (Sort::ReadAsc, 5) | (_, 5) => ReadDesc,
+                    (Sort::WriteDesc, 6) => WriteAsc,
+                    (Sort::WriteAsc, 6) | (_, 6) => WriteDesc,
+                    (Sort::IoDesc, 7) => IoAsc,
+                    (Sort::IoAsc, 7) | (_, 7) => IoDesc,
+                    (_, 8) if !self.data.threads => return,
+                    (Sort::ThreadsDesc, 8) => ThreadsAsc,
+                    (Sort::ThreadsAsc, 8) | (_, 8) => ThreadsDesc,
+                    (_, 9) if !self.data.cpu_time => return,
+                    (Sort::CpuTimeDesc, 9) => CpuTimeAsc,
+                    (Sort::CpuTimeAsc, 9) | (_, 9) => CpuTimeDesc,
+                    (_, 10) if !self.data.elapsed => return,
+                    (Sort::StartDesc, 10) => StartAsc,
+                    (Sort::StartAsc, 10) | (_, 10) => StartDesc,
                     _ => return,
                 };
+                if self.cursor < self.height {
+                    self.resorted = Some(self.get_pid_under_cursor(self.cursor));
+                }
+                if let Some(ref mut freeze) = self.scroll_freeze {
+                    freeze.thaw();
+                }
 
                 self.force_redraw = true;
                 self.dirty = true;
             }
+            UIEvent::Input(Key::Char('\t')) if matches!(self.mode, KillName(_)) => {
+                self.kill_name_exact = !self.kill_name_exact;
+                self.force_redraw = true;
+                self.dirty = true;
+            }
+            UIEvent::Input(Key::Char('\n')) if matches!(self.mode, KillName(_)) => {
+                if let KillName(ref pattern) = self.mode {
+                    let pids = processes_named(
+                        &self.processes,
+                        pattern,
+                        self.kill_name_exact,
+                        std::process::id() as Pid,
+                    );
+                    self.mode = if pattern.is_empty() {
+                        /* would match everything */
+                        Normal
+                    } else if pids.is_empty() {
+                        self.status_message = Some(format!("no processes named `{}`", pattern));
+                        Normal
+                    } else {
+                        /* SIGTERM */
+                        KillMatches(pids, 15)
+                    };
+                }
+                self.force_redraw = true;
+                self.dirty = true;
+            }
+            UIEvent::Input(Key::Char(c)) if matches!(self.mode, KillName(_)) => {
+                if let KillName(ref mut p) = self.mode {
+                    if !c.is_ascii_control() {
+                        p.push(*c);
+                        self.force_redraw = true;
+                        self.dirty = true;
+                    }
+                }
+            }
+            /* letters typed into a filter are part of it, disabled or not */
+            UIEvent::Input(k)
+                if self.mode.is_normal()
+                    && self.filter_term.is_none()
+                    && disabled.values().any(|key| key == k) =>
+            {
+                self.status_message = Some(
+                    if disabled.get("run hook command") == Some(k) {
+                        "read-only: no hook command is run"
+                    } else {
+                        "read-only: processes can't be signalled"
+                    }
+                    .to_string(),
+                );
+                self.force_redraw = true;
+                self.dirty = true;
+            }
+            UIEvent::Input(k)
+                if map.get("kill processes by name") == Some(k)
+                    && self.mode.is_normal()
+                    && self.filter_term.is_none() =>
+            {
+                self.mode = KillName(String::new());
+                self.force_redraw = true;
+                self.dirty = true;
+            }
             UIEvent::Input(k)
                 if *k == map["toggle help overlay"]
                     && !self.mode.is_search()
@@ -1395,10 +4088,70 @@ impl Component for ProcessList {
                 self.force_redraw = true;
                 self.dirty = true;
             }
+            UIEvent::Input(k)
+                if *k == map["expand or inspect"] && self.draw_tree && self.mode.is_normal() =>
+            {
+                if let Some(&i) = self.visible_rows().get(self.cursor) {
+                    let pid = self.processes[i].i;
+                    if self.data.parents.get(&pid).is_some_and(|c| !c.is_empty()) {
+                        toggle_collapsed(&mut self.collapsed, pid);
+                    } else if !self.collapsed.remove(&pid) {
+                        self.status_message = Some(format!("PID {} has no children", pid));
+                    }
+                }
+                self.force_redraw = true;
+                self.dirty = true;
+            }
+            UIEvent::Input(k)
+                if *k == map["pick columns"]
+                    && self.mode.is_normal()
+                    && self.filter_term.is_none() =>
+            {
+                self.column_picker = Some(0);
+                self.force_redraw = true;
+                self.dirty = true;
+            }
+            UIEvent::Input(k)
+                if *k == map["expand or inspect"] && !self.grouped && self.mode.is_normal() =>
+            {
+                self.inspected = match self.inspected {
+                    Some(_) => None,
+                    None => self
+                        .visible_rows()
+                        .get(self.cursor)
+                        .map(|&i| self.processes[i].i),
+                };
+                self.read_memory_map();
+                self.force_redraw = true;
+                self.dirty = true;
+            }
+            UIEvent::Input(k)
+                if *k == map["expand or inspect"] && self.grouped && self.mode.is_normal() =>
+            {
+                if let Some(&i) = self.visible_rows().get(self.cursor) {
+                    let p = &self.processes[i];
+                    if p.members > 0 {
+                        let name = p.cmd_line.0.clone();
+                        if !self.expanded_groups.remove(&name) {
+                            self.expanded_groups.insert(name);
+                        }
+                    } else {
+                        /* collapse the group of a member */
+                        let name = process_name(&p.cmd_line.0).to_string();
+                        self.expanded_groups.remove(&name);
+                        self.regrouped = Some(GroupedRow::Group(name));
+                    }
+                }
+                self.force_redraw = true;
+                self.dirty = true;
+            }
             UIEvent::Input(Key::Char(c)) if self.filter_term.is_some() && self.mode == Normal => {
                 if let Some(ref mut filter_term) = self.filter_term {
                     if !c.is_ascii_control() {
                         filter_term.push(*c);
+                        if self.normalize {
+                            *filter_term = normalize_nfc(filter_term).into_owned();
+                        }
                         self.force_redraw = true;
                         self.dirty = true;
                     }
@@ -1442,97 +4195,404 @@ impl Component for ProcessList {
                 self.force_redraw = true;
                 self.dirty = true;
             }
-            UIEvent::Input(k) if *k == map["kill process"] => {
-                self.mode = Kill(0);
-                self.freeze = true;
-                self.dirty = true;
+            UIEvent::Input(k)
+                if *k == map["export to csv"]
+                    && self.mode.is_normal()
+                    && self.filter_term.is_none() =>
+            {
+                self.status_message = Some(match self.export_csv() {
+                    Ok((path, n)) => format!("exported {} processes to {}", n, path.display()),
+                    Err(err) => {
+                        log_error!("could not export to csv: {}", err);
+                        format!("could not export to csv: {}", err)
+                    }
+                });
                 self.force_redraw = true;
+                self.dirty = true;
             }
-            UIEvent::Input(k) if *k == map["cancel"] => {
-                /* layered cancelling */
-                if self.draw_help {
-                    self.draw_help = false;
-                } else if self.mode != Normal {
-                    self.mode = Normal;
-                } else if self.filter_term.is_some() {
-                    self.filter_term = None;
-                } else {
-                    self.freeze = false;
+            UIEvent::Input(k) if *k == map["go to parent"] && self.mode.is_normal() => {
+                match parent_row(&self.processes, &self.visible_rows(), self.cursor) {
+                    Ok(row) => self.cursor = row,
+                    Err(msg) => self.status_message = Some(msg),
                 }
                 self.force_redraw = true;
                 self.dirty = true;
             }
-            UIEvent::Input(k)
-                if *k == map["toggle tree view"]
-                    && !(self.filter_term.is_some() || self.mode.is_search()) =>
-            {
-                self.draw_tree = !self.draw_tree;
+            UIEvent::Input(k) if *k == map["pin baseline"] && self.mode.is_normal() => {
+                self.status_message = Some(if self.data.baseline.take().is_some() {
+                    "unpinned the baseline".to_string()
+                } else {
+                    self.data.baseline = Some(Baseline::pin(&self.snapshot));
+                    "pinned the baseline, +CPU and +MEM show the changes since then".to_string()
+                });
                 self.force_redraw = true;
                 self.dirty = true;
             }
-            UIEvent::Input(Key::Char(f))
-                if !self.mode.is_normal() && !self.mode.is_search() && f.is_numeric() =>
-            {
-                if let Kill(ref mut n) = self.mode {
-                    if let Some(add) = (*n).checked_mul(10) {
-                        *n = add
-                            .checked_add(f.to_digit(10).unwrap() as u16)
-                            .unwrap_or(*n);
-                    }
-                } else if let Locate(ref mut p) = self.mode {
-                    if let Some(add) = (*p).checked_mul(10) {
-                        *p = add
-                            .checked_add(f.to_digit(10).unwrap() as i32)
-                            .unwrap_or(*p);
-                    }
-                    self.dirty = true;
-                }
+            UIEvent::Input(k) if *k == map["dim idle processes"] && self.mode.is_normal() => {
+                self.dim_idle = !self.dim_idle;
+                self.force_redraw = true;
+                self.dirty = true;
             }
-            UIEvent::Input(Key::Char(c)) if self.mode.is_search() => {
-                if let Search(ref mut p) = self.mode {
-                    if !c.is_ascii_control() {
-                        p.push(*c);
-                        self.force_redraw = true;
-                        self.dirty = true;
+            UIEvent::Input(k) if *k == map["cycle minimum cpu"] && self.mode.is_normal() => {
+                self.min_cpu = next_min_cpu(self.min_cpu);
+                self.min_cpu_kept = None;
+                self.status_message = Some(match self.min_cpu {
+                    Some(min_cpu) => {
+                        format!("hiding processes below {}% cpu", min_cpu as f64 / 100.0)
                     }
-                }
+                    None => "showing processes at any cpu usage".to_string(),
+                });
+                self.force_redraw = true;
+                self.dirty = true;
             }
-            UIEvent::Input(Key::Backspace) if self.mode != Normal => {
-                if let Kill(ref mut n) = self.mode {
-                    *n = *n / 10;
-                } else if let Locate(ref mut p) = self.mode {
-                    *p = *p / 10;
-                } else if let Search(ref mut p) = self.mode {
-                    if p.is_empty() {
-                        self.mode = Normal;
+            UIEvent::Input(k) if *k == map["toggle fuzzy matching"] && self.mode.is_normal() => {
+                self.fuzzy = !self.fuzzy;
+                self.status_message = Some(
+                    if self.fuzzy {
+                        "fuzzy matching"
                     } else {
-                        p.pop();
+                        "substring matching"
                     }
-                }
-                self.dirty = true;
+                    .to_string(),
+                );
                 self.force_redraw = true;
+                self.dirty = true;
             }
-            UIEvent::Input(Key::Backspace) if self.filter_term.is_some() => {
-                let filter_term = self.filter_term.as_mut();
-                if filter_term.as_ref().unwrap().is_empty() {
-                    self.filter_term = None;
+            UIEvent::Input(k) if *k == map["cycle views"] && self.mode.is_normal() => {
+                if self.views.is_empty() {
+                    self.status_message = Some("no views, add some with --view".to_string());
+                    self.force_redraw = true;
+                    self.dirty = true;
                 } else {
-                    // TODO pop grapheme
-                    filter_term.unwrap().pop();
+                    let view = self.views.cycle().clone();
+                    self.apply_view(view);
                 }
-                self.dirty = true;
+            }
+            UIEvent::Input(k) if *k == map["toggle column headers"] && self.mode.is_normal() => {
+                self.show_header = !self.show_header;
                 self.force_redraw = true;
+                self.dirty = true;
             }
-            UIEvent::Input(Key::Char('\n')) if self.mode != Normal => {
-                if let Kill(ref n) = self.mode {
-                    use nix::sys::signal::kill;
-
-                    kill(
-                        nix::unistd::Pid::from_raw(self.get_pid_under_cursor(self.cursor)),
-                        nix::sys::signal::Signal::from_c_int(*n as i32).unwrap(),
-                    )
-                    .ok()
-                    .take();
+            UIEvent::Input(k) if *k == map["toggle key hints"] && self.mode.is_normal() => {
+                self.key_hints = match self.key_hints {
+                    Some(_) => None,
+                    None => Some(KeyHints::new()),
+                };
+                self.force_redraw = true;
+                self.dirty = true;
+            }
+            UIEvent::Input(k)
+                if map.get("kill process") == Some(k) && self.group_under_cursor().is_some() =>
+            {
+                self.status_message =
+                    Some("expand the group to signal one of its processes".to_string());
+                self.force_redraw = true;
+                self.dirty = true;
+            }
+            UIEvent::Input(k)
+                if *k == map["pin process to top"]
+                    && self.mode.is_normal()
+                    && !self.draw_tree
+                    && !self.grouped =>
+            {
+                let pid = self
+                    .visible_rows()
+                    .get(self.cursor)
+                    .map(|&i| self.processes[i].i);
+                if let Some(pid) = pid {
+                    self.status_message = Some(format!(
+                        "{} PID {}",
+                        if self.pins.toggle(pid) {
+                            "pinned"
+                        } else {
+                            "unpinned"
+                        },
+                        pid
+                    ));
+                    self.force_redraw = true;
+                    self.dirty = true;
+                }
+            }
+            UIEvent::Input(k)
+                if map.get("run hook command") == Some(k) && self.mode.is_normal() =>
+            {
+                let rows = self.visible_rows();
+                let process = rows.get(self.cursor).map(|&i| &self.processes[i]);
+                self.status_message = match (&self.hook, process) {
+                    (None, _) => Some("no hook command, set one with --hook".to_string()),
+                    (Some(_), Some(p)) if p.members > 0 => Some(
+                        "expand the group to run the hook command on one of its processes"
+                            .to_string(),
+                    ),
+                    (Some(hook), Some(p)) => {
+                        let argv = hook.argv(p.i);
+                        log_info!("running hook command {:?}", argv);
+                        *event = UIEvent::Exec(argv);
+                        self.force_redraw = true;
+                        return;
+                    }
+                    (Some(_), None) => None,
+                };
+                self.force_redraw = true;
+                self.dirty = true;
+            }
+            UIEvent::Input(k) if *k == map["show working directory"] && self.mode.is_normal() => {
+                let rows = self.visible_rows();
+                let process = rows.get(self.cursor).map(|&i| &self.processes[i]);
+                self.status_message = match process {
+                    Some(p) if p.members > 0 => Some(
+                        "expand the group to show the working directory of one of its processes"
+                            .to_string(),
+                    ),
+                    Some(p) => match (read_cwd(&self.proc_source, p.i), &self.open_cwd) {
+                        (Ok(cwd), Some(open_cwd)) => {
+                            let argv = open_cwd.argv(&cwd);
+                            log_info!("opening working directory {:?}", argv);
+                            *event = UIEvent::Exec(argv);
+                            self.force_redraw = true;
+                            return;
+                        }
+                        (Ok(cwd), None) => Some(cwd.display().to_string()),
+                        (Err(msg), _) => Some(msg),
+                    },
+                    None => None,
+                };
+                self.force_redraw = true;
+                self.dirty = true;
+            }
+            UIEvent::Input(k)
+                if *k == map["print kill command"]
+                    && matches!(
+                        self.mode,
+                        Normal | Kill(_) | KillConfirm(_) | KillMatches(_, _)
+                    ) =>
+            {
+                let (n, targets) = match self.mode {
+                    Kill(n) | KillConfirm(n) => (n, vec![self.signal_target()]),
+                    KillMatches(ref pids, n) => (
+                        n,
+                        pids.iter().map(|&pid| SignalTarget::Process(pid)).collect(),
+                    ),
+                    _ => match self.group_under_cursor() {
+                        Some(group) => {
+                            let mut pids = self
+                                .processes
+                                .iter()
+                                .filter(|p| {
+                                    p.members == 0
+                                        && process_name(&p.cmd_line.0) == group.cmd_line.0
+                                })
+                                .map(|p| p.i)
+                                .collect::<Vec<Pid>>();
+                            pids.sort_unstable();
+                            (0, pids.into_iter().map(SignalTarget::Process).collect())
+                        }
+                        None => (
+                            0,
+                            vec![SignalTarget::Process(
+                                self.get_pid_under_cursor(self.cursor),
+                            )],
+                        ),
+                    },
+                };
+                let command = kill_command(n, &targets);
+                log_info!("{}", command);
+                self.status_message = Some(command);
+                self.mode = Normal;
+                self.force_redraw = true;
+                self.dirty = true;
+            }
+            UIEvent::Input(k) if map.get("kill process") == Some(k) => {
+                self.mode = Kill(0);
+                self.kill_group = false;
+                self.freeze = true;
+                self.dirty = true;
+                self.force_redraw = true;
+            }
+            UIEvent::Input(k)
+                if map.get("signal process group") == Some(k) && matches!(self.mode, Kill(_)) =>
+            {
+                self.kill_group = !self.kill_group;
+                self.dirty = true;
+            }
+            UIEvent::Input(k) if *k == map["cancel"] => {
+                /* layered cancelling */
+                if self.draw_help {
+                    self.draw_help = false;
+                } else if self.inspected.is_some() {
+                    self.inspected = None;
+                    self.memory_map = None;
+                } else if self.mode != Normal {
+                    self.mode = Normal;
+                } else if self.filter_term.is_some() {
+                    self.filter_term = None;
+                } else {
+                    self.freeze = false;
+                }
+                self.force_redraw = true;
+                self.dirty = true;
+            }
+            UIEvent::Input(k)
+                if *k == map["toggle tree view"]
+                    && !(self.filter_term.is_some() || self.mode.is_search()) =>
+            {
+                self.draw_tree = !self.draw_tree;
+                if self.grouped {
+                    self.grouped = false;
+                    regroup(&mut self.processes, false);
+                }
+                self.force_redraw = true;
+                self.dirty = true;
+            }
+            UIEvent::Input(k)
+                if *k == map["group by command"]
+                    && self.mode.is_normal()
+                    && self.filter_term.is_none() =>
+            {
+                /* keep the cursor on the selected process, or on its group */
+                self.regrouped = self.visible_rows().get(self.cursor).map(|&i| {
+                    let p = &self.processes[i];
+                    if self.grouped {
+                        GroupedRow::Process(p.i)
+                    } else {
+                        GroupedRow::Group(process_name(&p.cmd_line.0).to_string())
+                    }
+                });
+                self.grouped = !self.grouped;
+                self.draw_tree = false;
+                regroup(&mut self.processes, self.grouped);
+                self.force_redraw = true;
+                self.dirty = true;
+            }
+            UIEvent::Input(Key::Char(f))
+                if !self.mode.is_normal() && !self.mode.is_search() && f.is_numeric() =>
+            {
+                if let Kill(ref mut n) | KillMatches(_, ref mut n) = self.mode {
+                    if let Some(add) = (*n).checked_mul(10) {
+                        *n = add
+                            .checked_add(f.to_digit(10).unwrap() as u16)
+                            .unwrap_or(*n);
+                    }
+                } else if let Locate(ref mut p) = self.mode {
+                    if let Some(add) = (*p).checked_mul(10) {
+                        *p = add
+                            .checked_add(f.to_digit(10).unwrap() as Pid)
+                            .unwrap_or(*p);
+                    }
+                    self.dirty = true;
+                }
+            }
+            UIEvent::Input(Key::Char(c)) if self.mode.is_search() => {
+                if let Search(ref mut p) = self.mode {
+                    if !c.is_ascii_control() {
+                        p.push(*c);
+                        if self.normalize {
+                            *p = normalize_nfc(p).into_owned();
+                        }
+                        self.force_redraw = true;
+                        self.dirty = true;
+                    }
+                }
+            }
+            UIEvent::Input(Key::Backspace) if self.mode != Normal => {
+                if let Kill(ref mut n) | KillMatches(_, ref mut n) = self.mode {
+                    *n /= 10;
+                } else if let Locate(ref mut p) = self.mode {
+                    *p /= 10;
+                } else if let Search(ref mut p) | KillName(ref mut p) = self.mode {
+                    if p.is_empty() {
+                        self.mode = Normal;
+                    } else {
+                        p.pop();
+                    }
+                }
+                self.dirty = true;
+                self.force_redraw = true;
+            }
+            UIEvent::Input(Key::Backspace) if self.filter_term.is_some() => {
+                let filter_term = self.filter_term.as_mut();
+                if filter_term.as_ref().unwrap().is_empty() {
+                    self.filter_term = None;
+                } else {
+                    // TODO pop grapheme
+                    filter_term.unwrap().pop();
+                }
+                self.dirty = true;
+                self.force_redraw = true;
+            }
+            UIEvent::Input(Key::Char('\n')) if self.mode != Normal => {
+                if let Kill(n) = self.mode {
+                    if needs_confirmation(self.confirm_kill, n) {
+                        self.status_message = Some(format!(
+                            "send {} to {}? Enter to confirm, Esc to cancel",
+                            signal_name(n),
+                            self.signal_target()
+                        ));
+                        self.mode = KillConfirm(n);
+                        self.dirty = true;
+                        self.force_redraw = true;
+                        return;
+                    }
+                }
+                if let Kill(ref n) | KillConfirm(ref n) = self.mode {
+                    let target = self.signal_target();
+                    let members = match target {
+                        SignalTarget::Group(pgrp) => self
+                            .processes
+                            .iter()
+                            .filter(|p| p.pgrp == pgrp)
+                            .map(|p| p.i)
+                            .collect::<Vec<Pid>>(),
+                        SignalTarget::Process(_) => vec![],
+                    };
+                    let res = nix::sys::signal::Signal::from_c_int(*n as i32)
+                        .map_err(|_| format!("invalid signal {}", n))
+                        .and_then(|signal| {
+                            send_signal(target, signal, &members, nix::sys::signal::kill)
+                        });
+                    if let Err(msg) = res {
+                        log_warn!("{}", msg);
+                        self.status_message = Some(msg);
+                    }
+                    self.mode = Normal;
+                    self.dirty = true;
+                    self.force_redraw = true;
+                } else if let KillMatches(ref pids, n) = self.mode {
+                    self.status_message = Some(
+                        nix::sys::signal::Signal::from_c_int(n as i32)
+                            .map_err(|_| format!("invalid signal {}", n))
+                            .map(|signal| {
+                                let errors = pids
+                                    .iter()
+                                    .filter_map(|&pid| {
+                                        send_signal(
+                                            SignalTarget::Process(pid),
+                                            signal,
+                                            &[],
+                                            nix::sys::signal::kill,
+                                        )
+                                        .err()
+                                    })
+                                    .collect::<Vec<String>>();
+                                for msg in &errors {
+                                    log_warn!("{}", msg);
+                                }
+                                match errors.first() {
+                                    None => format!(
+                                        "sent {} to {} processes",
+                                        signal_name(n),
+                                        pids.len()
+                                    ),
+                                    Some(msg) => format!(
+                                        "{} of {} processes not signalled: {}",
+                                        errors.len(),
+                                        pids.len(),
+                                        msg
+                                    ),
+                                }
+                            })
+                            .unwrap_or_else(|msg| msg),
+                    );
                     self.mode = Normal;
                     self.dirty = true;
                     self.force_redraw = true;
@@ -1547,6 +4607,13 @@ impl Component for ProcessList {
     }
 
     fn set_dirty(&mut self) {}
+
+    /* Typing a filter, a search or the name of the processes to signal */
+    fn takes_text_input(&self) -> bool {
+        matches!(self.mode, Search(_) | KillName(_))
+            || (self.mode.is_normal() && self.filter_term.is_some())
+    }
+
     fn get_shortcuts(&self) -> ShortcutMaps {
         let mut map: ShortcutMap = Default::default();
         map.insert("follow process group", Key::Char('F'));
@@ -1554,143 +4621,825 @@ impl Component for ProcessList {
         map.insert("freeze updates", Key::Char('f'));
         map.insert("toggle tree view", Key::Char('t'));
         map.insert("kill process", Key::Char('k'));
+        map.insert("signal process group", Key::Char('g'));
+        map.insert("kill processes by name", Key::Char('K'));
+        map.insert("print kill command", Key::Char('y'));
+        map.insert("run hook command", Key::Char('x'));
+        map.insert("show working directory", Key::Char('w'));
+        map.insert("pin baseline", Key::Char('b'));
+        map.insert("pin process to top", Key::Char('P'));
         map.insert("filter", Key::Char(' '));
         map.insert("search process by name", Key::Char('/'));
         map.insert("cancel", Key::Esc);
         map.insert("toggle help overlay", Key::Char('h'));
+        map.insert("export to csv", Key::Char('e'));
+        map.insert("dim idle processes", Key::Char('i'));
+        map.insert("cycle minimum cpu", Key::Char('c'));
+        map.insert("toggle column headers", Key::Char('H'));
+        map.insert("toggle fuzzy matching", Key::Char('z'));
+        map.insert("go to parent", Key::Char('p'));
+        map.insert("group by command", Key::Char('G'));
+        map.insert("cycle views", Key::Alt('v'));
+        map.insert("expand or inspect", Key::Char('\n'));
+        map.insert("pick columns", Key::Char('C'));
+        map.insert("toggle key hints", Key::Char('?'));
         let mut ret: ShortcutMaps = Default::default();
         ret.insert("".to_string(), map);
         ret
     }
+
+    fn title(&self) -> Option<String> {
+        /* the rows left after filtering, as of the last draw */
+        Some(format!("Processes ({})", self.height))
+    }
 }
 
-fn executable_path_color(p: &CmdLineString) -> Result<(&str, &str, &str), (&str, &str)> {
-    let p = &p.0;
-    if !p.starts_with("/") {
-        return if let Some(first_whitespace) = p.as_bytes().iter().position(|c| *c == b' ') {
-            Err(p.split_at(first_whitespace))
-        } else {
-            Err((p, ""))
+/* Replace each process' cpu percentage with its moving average. The averages only take in a sample
+ * when there's a `new_sample`, since the list is also rebuilt from the same snapshot on key
+ * presses. */
+fn smooth_cpu_percent(
+    processes: &mut [ProcessDisplay],
+    averages: &mut HashMap<Pid, Ewma>,
+    alpha: f64,
+    new_sample: bool,
+    now: std::time::Instant,
+) {
+    if new_sample {
+        let pids = processes.iter().map(|p| p.i).collect::<HashSet<Pid>>();
+        averages.retain(|pid, _| pids.contains(pid));
+    }
+    for p in processes {
+        let raw = match p.cpu_percent_raw {
+            Some(raw) => raw,
+            None => {
+                /* a new process, possibly with the PID of one that exited */
+                if new_sample {
+                    averages.remove(&p.i);
+                }
+                continue;
+            }
         };
+        let average = averages.entry(p.i).or_insert_with(|| Ewma::new(alpha));
+        let value = match average.value() {
+            Some(value) if !new_sample => value,
+            _ => average.update(raw as f64, now),
+        };
+        p.cpu_percent = Some(value.round() as usize);
     }
+}
 
-    if let Some(first_whitespace) = p.as_bytes().iter().position(|c| *c == b' ') {
-        if let Some(path_end) = p[0..first_whitespace]
-            .as_bytes()
-            .iter()
-            .rposition(|c| *c == b'/')
-        {
-            let (path, rest) = p.split_at(path_end + 1);
-            if let Some(first_whitespace) = rest.as_bytes().iter().position(|c| *c == b' ') {
-                let (bin, rest) = rest.split_at(first_whitespace);
-                Ok((path, bin, rest))
-            } else {
-                Ok((path, rest, ""))
-            }
-        } else {
-            return Err(p.split_at(first_whitespace));
+/* Row of the parent of the process on row `cursor`, given the `rows` (indices into `processes`) in
+ * display order. The error says why there's no row to go to. */
+fn parent_row(
+    processes: &[ProcessDisplay],
+    rows: &[usize],
+    cursor: usize,
+) -> Result<usize, String> {
+    let p = rows
+        .get(cursor)
+        .map(|&i| &processes[i])
+        .ok_or_else(|| "no process selected".to_string())?;
+    if p.p == 0 {
+        return Err(format!("PID {} has no parent", p.i));
+    }
+    rows.iter()
+        .position(|&i| processes[i].i == p.p)
+        .ok_or_else(|| format!("parent PID {} of PID {} isn't in the list", p.p, p.i))
+}
+
+/* `threshold` is in hundredths of a percent, like `ProcessDisplay::cpu_percent`. Processes whose
+ * usage isn't known yet aren't idle. */
+fn is_idle(p: &ProcessDisplay, threshold: usize) -> bool {
+    p.cpu_percent
+        .is_some_and(|cpu_percent| cpu_percent < threshold)
+}
+
+/* `tree` without the descendants of the `collapsed` nodes. The nodes are in depth-first order with
+ * their depth, like `ProcessData::tree`. */
+fn collapse_tree(tree: &[(usize, Pid)], collapsed: &HashSet<Pid>) -> Vec<(usize, Pid)> {
+    let mut ret = Vec::with_capacity(tree.len());
+    /* the depth of the collapsed node whose descendants are being skipped */
+    let mut hiding_below = None;
+    for &(depth, pid) in tree {
+        match hiding_below {
+            Some(collapsed_depth) if depth > collapsed_depth => continue,
+            _ => hiding_below = None,
         }
-    } else {
-        if let Some(path_end) = p.as_bytes().iter().rposition(|c| *c == b'/') {
-            let (path, bin) = p.split_at(path_end + 1);
-            Ok((path, bin, ""))
-        } else {
-            return Err((p, ""));
+        ret.push((depth, pid));
+        if collapsed.contains(&pid) {
+            hiding_below = Some(depth);
         }
     }
+    ret
 }
 
-fn get(data: &mut ProcessData, follow_pid: Option<Pid>, sort: Sort) -> Vec<ProcessDisplay> {
-    data.tree.clear();
-    data.parents.clear();
-    data.processes_index.clear();
-    data.tree_index.clear();
+/* Collapse the tree node of `pid` if it's expanded and expand it if it's collapsed */
+fn toggle_collapsed(collapsed: &mut HashSet<Pid>, pid: Pid) {
+    if !collapsed.remove(&pid) {
+        collapsed.insert(pid);
+    }
+}
 
-    let ProcessData {
-        ref mut parents,
-        ref mut processes_index,
-        ref mut tree_index,
-        ref mut tree,
-        ref mut processes_times,
-        cpu_stat: ref mut data_cpu_stat,
-    } = data;
-
-    let mut processes = Vec::with_capacity(2048);
-    let mut cpu_stats = get_stat(&mut 0);
-    let cpu_no = cpu_stats.len() - 1;
-    let cpu_stat = cpu_stats.remove(0);
-    let multiplier = (cpu_no as f64) * 10000.0;
-    let divisor = (cpu_stat.total_time() - data_cpu_stat.total_time()) as f64;
-    for entry in std::fs::read_dir("/proc/").unwrap() {
-        let dir = entry.unwrap();
-        if let Some(fname) = dir.file_name().to_str() {
-            if !fname.chars().all(|c| c.is_numeric()) {
-                continue;
-            }
-        } else {
-            continue;
-        }
+/* The row nearest to `cursor` of the `len` rows that is `selectable`, looking `forward` (down) first
+ * and then the other way. `cursor` if no row is. */
+fn nearest_row(
+    len: usize,
+    cursor: usize,
+    forward: bool,
+    selectable: impl Fn(usize) -> bool,
+) -> usize {
+    if cursor < len && selectable(cursor) {
+        return cursor;
+    }
+    let ahead = || (cursor + 1..len).find(|&row| selectable(row));
+    let behind = || (0..cursor.min(len)).rev().find(|&row| selectable(row));
+    if forward {
+        ahead().or_else(behind)
+    } else {
+        behind().or_else(ahead)
+    }
+    .unwrap_or(cursor)
+}
 
-        let process = if let Ok(p) = get_pid_info(dir.path()) {
-            p
-        } else {
-            continue;
-        };
+/* The row the cursor moves to from `cursor` of the `len` rows, one down if `forward` and up
+ * otherwise. Past the ends it goes around to the other one if `wrap`, and stays otherwise. */
+fn step_cursor(cursor: usize, len: usize, forward: bool, wrap: bool) -> usize {
+    let last = len.saturating_sub(1);
+    match (forward, wrap) {
+        (true, true) if cursor >= last => 0,
+        (true, _) => std::cmp::min(last, cursor + 1),
+        (false, true) if cursor == 0 => last,
+        (false, _) => cursor.saturating_sub(1),
+    }
+}
 
-        if process.cmd_line.is_empty() {
-            /* This is a kernel thread, skip for now */
-            continue;
+/* Drop the `rows` (indices into `processes`) of processes using less than `min_cpu`, in hundredths
+ * of a percent, except the one of `kept`. Processes whose usage isn't known yet stay. */
+fn retain_min_cpu(
+    rows: &mut Vec<usize>,
+    processes: &[ProcessDisplay],
+    min_cpu: usize,
+    kept: Option<Pid>,
+) {
+    rows.retain(|&i| !is_idle(&processes[i], min_cpu) || Some(processes[i].i) == kept);
+}
+
+/* The `min_cpu` after `current` when it's cycled with c: the next of `MIN_CPU_STEPS` above it, and
+ * no minimum after the last */
+fn next_min_cpu(current: Option<usize>) -> Option<usize> {
+    match current {
+        None => MIN_CPU_STEPS.first().copied(),
+        Some(current) => MIN_CPU_STEPS.iter().copied().find(|&step| step > current),
+    }
+}
+
+/* Whether `p` uses more than `threshold`, a fraction in (0, 1], of the `mem_total` KiB of memory */
+fn is_memory_hog(p: &ProcessDisplay, mem_total: usize, threshold: f64) -> bool {
+    mem_total > 0 && p.vm_rss_value as f64 > threshold * (mem_total * 1024) as f64
+}
+
+/* Compare by `sort`, and by `secondary` if that's a tie */
+fn sort_cmp(
+    sort: Sort,
+    secondary: Option<SecondarySort>,
+    a: &ProcessDisplay,
+    b: &ProcessDisplay,
+) -> std::cmp::Ordering {
+    key_cmp(sort, a, b).then_with(|| {
+        secondary.map_or(std::cmp::Ordering::Equal, |SecondarySort(key)| {
+            key_cmp(key, a, b)
+        })
+    })
+}
+
+fn key_cmp(sort: Sort, a: &ProcessDisplay, b: &ProcessDisplay) -> std::cmp::Ordering {
+    match sort {
+        Sort::PidAsc => a.i.cmp(&b.i),
+        Sort::PidDesc => b.i.cmp(&a.i),
+        Sort::CpuAsc => a.cpu_percent.cmp(&b.cpu_percent),
+        Sort::CpuDesc => b.cpu_percent.cmp(&a.cpu_percent),
+        Sort::VmRssAsc => a.vm_rss_value.cmp(&b.vm_rss_value),
+        Sort::VmRssDesc => b.vm_rss_value.cmp(&a.vm_rss_value),
+        Sort::UserAsc => a.username.0.cmp(&b.username.0),
+        Sort::UserDesc => b.username.0.cmp(&a.username.0),
+        Sort::CmdLineAsc => a.cmd_line.0.cmp(&b.cmd_line.0),
+        Sort::CmdLineDesc => b.cmd_line.0.cmp(&a.cmd_line.0),
+        Sort::ReadAsc | Sort::ReadDesc => io_cmp(sort, a, b, |rates| rates.read),
+        Sort::WriteAsc | Sort::WriteDesc => io_cmp(sort, a, b, |rates| rates.write),
+        Sort::IoAsc | Sort::IoDesc => {
+            io_cmp(sort, a, b, |rates| rates.read.saturating_add(rates.write))
         }
+        /* processes without a count come last in either direction */
+        Sort::ThreadsAsc | Sort::ThreadsDesc => match (a.threads, b.threads) {
+            (Some(a), Some(b)) if matches!(sort, Sort::ThreadsAsc) => a.cmp(&b),
+            (Some(a), Some(b)) => b.cmp(&a),
+            (a, b) => b.is_some().cmp(&a.is_some()),
+        },
+        Sort::CpuTimeAsc | Sort::CpuTimeDesc => match (a.cpu_time, b.cpu_time) {
+            (Some(a), Some(b)) if matches!(sort, Sort::CpuTimeAsc) => a.cmp(&b),
+            (Some(a), Some(b)) => b.cmp(&a),
+            (a, b) => b.is_some().cmp(&a.is_some()),
+        },
+        /* the process that started first has been running the longest */
+        Sort::StartAsc | Sort::StartDesc => match (a.elapsed, b.elapsed) {
+            (Some(a), Some(b)) if matches!(sort, Sort::StartAsc) => b.cmp(&a),
+            (Some(a), Some(b)) => a.cmp(&b),
+            (a, b) => b.is_some().cmp(&a.is_some()),
+        },
+    }
+}
 
-        let process_display = ProcessDisplay {
-            i: process.pid,
-            p: process.ppid,
-            pid: PidString(process.pid.to_string()),
-            ppid: PpidString(process.ppid.to_string()),
-            vm_rss: VmRssString(Bytes(process.vm_rss * 1024).as_convenient_string()),
-            vm_rss_value: process.vm_rss * 1024,
-            cpu_percent: ((multiplier
-                * (process.rtime
-                    - processes_times
-                        .get(&process.pid)
-                        .map(|v| *v)
-                        .unwrap_or(process.rtime)) as f64)
-                / divisor) as usize,
-            rtime: process.rtime,
-            state: process.state,
-            cmd_line: CmdLineString(process.cmd_line),
-            username: UserString(crate::ui::username(process.uid)),
-        };
+/* Compare I/O rates by `key`. Processes without rates, eg. because their `/proc/[pid]/io` isn't
+ * readable, come last in either direction. */
+fn io_cmp(
+    sort: Sort,
+    a: &ProcessDisplay,
+    b: &ProcessDisplay,
+    key: fn(IoRates) -> u64,
+) -> std::cmp::Ordering {
+    match (a.io.flatten().map(key), b.io.flatten().map(key)) {
+        (Some(a), Some(b)) if matches!(sort, Sort::ReadAsc | Sort::WriteAsc | Sort::IoAsc) => {
+            a.cmp(&b)
+        }
+        (Some(a), Some(b)) => b.cmp(&a),
+        (Some(_), None) => std::cmp::Ordering::Less,
+        (None, Some(_)) => std::cmp::Ordering::Greater,
+        (None, None) => std::cmp::Ordering::Equal,
+    }
+}
 
-        processes_times.insert(process.pid, process_display.rtime);
-        parents.entry(process.ppid).or_default().push(process.pid);
+/* Write `EMPTY_STATE` centered in `area`, where the rows would be */
+fn draw_empty_state(grid: &mut CellBuffer, area: Area) {
+    if !is_valid_area!(area) {
+        return;
+    }
+    let upper_left = upper_left!(area);
+    let x = (width!(area) + 1).saturating_sub(EMPTY_STATE.len()) / 2;
+    write_string_to_grid(
+        EMPTY_STATE,
+        grid,
+        Color::Default,
+        Color::Default,
+        Attr::Bold,
+        (
+            pos_inc(upper_left, (x, height!(area) / 2)),
+            bottom_right!(area),
+        ),
+        false,
+    );
+}
 
-        processes_index.insert(process.pid, processes.len());
-        processes.push(process_display);
+/* Screen area of the `row`th process of a page whose first process is drawn at the upper left
+ * corner of `area`, when each takes `row_height` screen rows */
+fn page_row_area((upper_left, bottom_right): Area, row: usize, row_height: usize) -> Area {
+    let top = pos_inc(upper_left, (0, row * row_height));
+    (top, set_y(bottom_right, get_y(top) + row_height - 1))
+}
+
+/* Offset of the first process row from the top of the list, below a blank row and the column
+ * headers if they're shown */
+fn first_row_offset(show_header: bool) -> usize {
+    if show_header {
+        2
+    } else {
+        1
     }
-    *data_cpu_stat = cpu_stat;
-    let mut keep_list = HashSet::new();
-    if follow_pid.is_some() && processes_index.contains_key(follow_pid.as_ref().unwrap()) {
-        let mut stack = Vec::with_capacity(32);
-        let p = &processes[processes_index[&follow_pid.unwrap()]];
-        keep_list.insert(p.i);
-        if let Some(children) = parents.get(&p.i) {
-            stack.extend(children.iter().cloned());
+}
+
+/* Underline the characters of `cmd_line`, drawn from `pos` on, that a fuzzy `filter` matched.
+ * The cells of each character are counted the way `write_string_to_grid` fills them. */
+fn highlight_fuzzy_match(
+    grid: &mut CellBuffer,
+    filter: &Filter,
+    cmd_line: &str,
+    (mut x, y): Pos,
+    max_x: usize,
+) {
+    let shown = grid.displayed(cmd_line);
+    let positions = match filter.fuzzy_match(&shown) {
+        Some(m) => m.positions,
+        None => return,
+    };
+    let mut positions = positions.into_iter().peekable();
+    for (i, c) in shown.chars().enumerate() {
+        if x > max_x || positions.peek().is_none() {
+            break;
         }
-        while let Some(pid) = stack.pop() {
-            keep_list.insert(pid);
-            if let Some(children) = parents.get(&pid) {
-                stack.extend(children.iter().cloned());
+        if positions.peek() == Some(&i) {
+            positions.next();
+            if let Some(cell) = grid.get_mut(x, y) {
+                cell.set_attrs(Attr::BoldUnderline);
             }
         }
-        stack.push(p.p);
-        while let Some(pid) = stack.pop() {
-            if pid == 0 {
-                continue;
+        x += match c {
+            '\r' => 0,
+            '\t' => 2,
+            c if wcwidth(u32::from(c)) == Some(2) => 2,
+            _ => 1,
+        };
+    }
+}
+
+/* Number of processes in uninterruptible sleep, not counting the rows of the grouped view */
+fn count_uninterruptible(processes: &[ProcessDisplay]) -> usize {
+    processes
+        .iter()
+        .filter(|p| p.members == 0 && p.state.is_uninterruptible())
+        .count()
+}
+
+/* Return `pids` along with all of their descendants */
+fn with_descendants(pids: &[Pid], parents: &HashMap<Pid, Vec<Pid>>) -> HashSet<Pid> {
+    let mut ret = HashSet::new();
+    let mut stack = pids.to_vec();
+    while let Some(pid) = stack.pop() {
+        if ret.insert(pid) {
+            if let Some(children) = parents.get(&pid) {
+                stack.extend(children.iter().cloned());
+            }
+        }
+    }
+    ret
+}
+
+/* Apply the current sort order and filter term to the process list */
+fn visible_processes<'a>(
+    processes: &'a [ProcessDisplay],
+    sort: Sort,
+    secondary: Option<SecondarySort>,
+    filter: Option<&Filter>,
+) -> Vec<&'a ProcessDisplay> {
+    visible_indices(processes, sort, secondary, filter)
+        .into_iter()
+        .map(|i| &processes[i])
+        .collect()
+}
+
+/* Like `visible_processes`, but returns indices into `processes` */
+fn visible_indices(
+    processes: &[ProcessDisplay],
+    sort: Sort,
+    secondary: Option<SecondarySort>,
+    filter: Option<&Filter>,
+) -> Vec<usize> {
+    let mut indices = (0..processes.len()).collect::<Vec<usize>>();
+    indices.sort_unstable_by(|a, b| sort_cmp(sort, secondary, &processes[*a], &processes[*b]));
+    if let Some(filter) = filter {
+        indices.retain(|i| filter.matches(&processes[*i].cmd_line.0));
+        if filter.fuzzy {
+            /* stable, so matches that score the same stay in the sort order */
+            indices.sort_by_cached_key(|&i| -filter.score(&processes[i].cmd_line.0));
+        }
+    }
+    indices
+}
+
+/* Format the `rows` (indices into `processes`) of the page starting at `first`, along with
+ * `OVERSCAN` rows on each side, with the memory as in `ProcessDisplay::format`, the environment
+ * variable of `env_column` if it's shown and the state of the `sockets` if they're looked up.
+ * Returns the range of `rows` that was formatted. */
+fn format_window(
+    processes: &mut [ProcessDisplay],
+    rows: &[usize],
+    first: usize,
+    height: usize,
+    (mem_format, mem_total): (MemFormat, usize),
+    mut env_column: Option<&mut EnvColumn>,
+    mut sockets: Option<&mut Sockets>,
+) -> std::ops::Range<usize> {
+    let window = std::cmp::min(first.saturating_sub(OVERSCAN), rows.len())
+        ..std::cmp::min(first + height + OVERSCAN, rows.len());
+    for &i in &rows[window.clone()] {
+        let p = &mut processes[i];
+        p.format(mem_format, mem_total);
+        if let Some(ref mut env_column) = env_column {
+            /* a row of the grouped view has no environment of its own */
+            if p.members == 0 && p.env.is_none() {
+                p.env = Some(env_column.value(p.i).to_string());
+            }
+        }
+        if let Some(ref mut sockets) = sockets {
+            if p.members == 0 && p.net.is_none() {
+                p.net = Some(sockets.state(p.i));
+            }
+        }
+    }
+    window
+}
+
+/// Print `iterations` snapshots of the process list to stdout as plain text, similar to
+/// `top -b`. Snapshots are taken every `SAMPLE_INTERVAL`, and separated by a blank line and a
+/// timestamp header if there is more than one.
+///
+/// Of the `options`, only the ones for what's read, which processes are listed and how they're
+/// sorted apply. Control characters in command lines are printed as `non_printable` says, so that
+/// each process stays on one line and can't send escape sequences to the terminal.
+pub fn batch(
+    iterations: usize,
+    color: bool,
+    non_printable: NonPrintable,
+    options: &ProcessListOptions,
+) -> std::io::Result<()> {
+    let source = &options.proc_source;
+    let reads = ExtraReads {
+        cgroups: options.cgroups,
+        io: options.io,
+        affinity: options.affinity,
+        pid_ns: options.pid_ns.is_some(),
+    };
+    let mut prev = Snapshot::take(source, reads)?;
+    let mut data = ProcessData::new(prev.cpu_stat);
+    data.elapsed = options.elapsed;
+    data.io = options.io;
+    data.threads = options.threads;
+    data.cpu_time = options.cpu_time;
+    data.caps = options.caps;
+    data.pid_ns = options.pid_ns.is_some();
+    let own_ns = source
+        .path("self/ns/pid")
+        .ok()
+        .and_then(|path| read_pid_ns(&path));
+    let mut env_column = options
+        .env_var
+        .clone()
+        .map(|key| EnvColumn::new(key, source.clone()));
+    let mut sockets = if options.sockets {
+        Some(Sockets::new(source.clone()))
+    } else {
+        None
+    };
+    let mut gpus = if options.gpu { Gpus::open() } else { None };
+    if options.gpu && gpus.is_none() {
+        eprintln!(
+            "bb: no GPU columns: NVML can't be loaded or finds no GPU, or bb was built without the \
+             nvml feature"
+        );
+    }
+    let sort = options.sort.map_or(Sort::CpuDesc, |SortOrder(sort)| sort);
+    let stdout = std::io::stdout();
+    let mut out = stdout.lock();
+    for i in 0..iterations {
+        std::thread::sleep(SAMPLE_INTERVAL);
+        let snapshot = Snapshot::take(source, reads)?;
+        data.set_previous(&prev);
+        let mut processes = get(
+            &mut data,
+            &snapshot,
+            None,
+            sort,
+            options.secondary_sort,
+            &options.pid_filter,
+            options.normalize,
+        );
+        if let Some(ref mut env_column) = env_column {
+            env_column.clear();
+        }
+        if let Some(ref mut sockets) = sockets {
+            sockets.clear();
+        }
+        for p in processes.iter_mut() {
+            p.format(options.mem_format, snapshot.mem_total * 1024);
+            if let Some(ref mut env_column) = env_column {
+                p.env = Some(env_column.value(p.i).to_string());
+            }
+            if let Some(ref mut sockets) = sockets {
+                p.net = Some(sockets.state(p.i));
+            }
+            escape_control_chars(p, non_printable);
+        }
+        if let Some(ref mut gpus) = gpus {
+            gpus.record(&mut processes, true);
+        }
+        if i > 0 {
+            writeln!(out)?;
+        }
+        if iterations > 1 {
+            writeln!(out, "{}", timestamp())?;
+        }
+        let mut rows = visible_processes(&processes, sort, options.secondary_sort, None);
+        if options.pid_ns == Some(PidNsMode::Group) {
+            group_by_ns(&mut rows, own_ns, |p| p.pid_ns);
+        }
+        let gpu = gpus.is_some();
+        out.write_all(format_batch(&processes, &rows, sort, color, options, gpu).as_bytes())?;
+        out.flush()?;
+        prev = snapshot;
+    }
+    Ok(())
+}
+
+/* Replace the control characters of `p`'s command line and environment variable, for `batch`,
+ * which prints them as they are */
+fn escape_control_chars(p: &mut ProcessDisplay, non_printable: NonPrintable) {
+    if let Cow::Owned(cmd_line) = non_printable.apply_to_line(&p.cmd_line.0) {
+        p.cmd_line.0 = cmd_line;
+    }
+    if let Some(Cow::Owned(env)) = p.env.as_deref().map(|env| non_printable.apply_to_line(env)) {
+        p.env = Some(env);
+    }
+}
+
+/* Format the column headers and `rows` like the process list does, with the columns and number
+ * formats of `options`, and the GPU columns if `gpu` is set, since `options.gpu` only asks for them
+ * and GPUs may not be found. `processes` is used to compute column widths. */
+fn format_batch(
+    processes: &[ProcessDisplay],
+    rows: &[&ProcessDisplay],
+    sort: Sort,
+    color: bool,
+    options: &ProcessListOptions,
+    gpu: bool,
+) -> String {
+    use std::fmt::Write;
+    use termion::{color::Fg, color::Reset, style};
+
+    let mut maxima = ColumnWidthMaxima::from_processes(processes);
+    if let Some(ref key) = options.env_var {
+        maxima.fit_env(key);
+    }
+    maxima.net = options.sockets;
+    maxima.gpu = gpu;
+    maxima.percent_format = options.percent_format;
+    maxima.fit_notation(options.notation, processes);
+    maxima.alignments = options.alignments;
+    let mut ret = String::new();
+    let header = maxima.header(sort, "CMD_LINE");
+    if color {
+        writeln!(ret, "{}{}{}", style::Bold, header, style::Reset).unwrap();
+    } else {
+        writeln!(ret, "{}", header.trim_end()).unwrap();
+    }
+    for p in rows {
+        ret.push_str(&maxima.row(p));
+        let (path, bin, rest) = match executable_path_color(&p.cmd_line.0) {
+            Ok((path, bin, rest)) => (path, bin, rest),
+            Err((bin, rest)) => ("", bin, rest),
+        };
+        if color {
+            write!(
+                ret,
+                "{}{}{}{}{}{}",
+                Fg(Color::Byte(243).as_termion()),
+                path,
+                Fg(Color::Byte(34).as_termion()),
+                bin,
+                Fg(Reset),
+                rest
+            )
+            .unwrap();
+        } else {
+            ret.push_str(path);
+            ret.push_str(bin);
+            ret.push_str(rest);
+        }
+        ret.push('\n');
+    }
+    ret
+}
+
+/* Quote a CSV field if needed, according to RFC 4180 */
+fn csv_field(s: &str) -> std::borrow::Cow<'_, str> {
+    if s.contains(&[',', '"', '\r', '\n'][..]) {
+        format!("\"{}\"", s.replace('"', "\"\"")).into()
+    } else {
+        s.into()
+    }
+}
+
+/* Write `processes` with the columns of the list that `columns` measured */
+fn write_csv<W: Write>(
+    w: &mut W,
+    columns: &ColumnWidthMaxima,
+    processes: &[&ProcessDisplay],
+) -> std::io::Result<()> {
+    let pid_ns = processes.iter().any(|p| p.pid_ns.is_some());
+    let line = |fields: Vec<String>| {
+        fields
+            .iter()
+            .map(|field| csv_field(field))
+            .collect::<Vec<_>>()
+            .join(",")
+    };
+    write!(w, "{}\r\n", line(columns.csv_header(pid_ns)))?;
+    for p in processes {
+        write!(w, "{}\r\n", line(columns.csv_row(p, pid_ns)))?;
+    }
+    Ok(())
+}
+
+fn executable_path_color(p: &str) -> Result<(&str, &str, &str), (&str, &str)> {
+    if !p.starts_with("/") {
+        return if let Some(first_whitespace) = p.as_bytes().iter().position(|c| *c == b' ') {
+            Err(p.split_at(first_whitespace))
+        } else {
+            Err((p, ""))
+        };
+    }
+
+    if let Some(first_whitespace) = p.as_bytes().iter().position(|c| *c == b' ') {
+        if let Some(path_end) = p.as_bytes()[0..first_whitespace]
+            .iter()
+            .rposition(|c| *c == b'/')
+        {
+            let (path, rest) = p.split_at(path_end + 1);
+            if let Some(first_whitespace) = rest.as_bytes().iter().position(|c| *c == b' ') {
+                let (bin, rest) = rest.split_at(first_whitespace);
+                Ok((path, bin, rest))
+            } else {
+                Ok((path, rest, ""))
+            }
+        } else {
+            Err(p.split_at(first_whitespace))
+        }
+    } else {
+        if let Some(path_end) = p.as_bytes().iter().rposition(|c| *c == b'/') {
+            let (path, bin) = p.split_at(path_end + 1);
+            Ok((path, bin, ""))
+        } else {
+            Err((p, ""))
+        }
+    }
+}
+
+/* The value `process` had in the previous snapshot. If its PID was in the previous snapshot but
+ * with another start time, the process exited and the kernel reused its PID for a new one, which
+ * has no previous value. */
+fn previous<T: Copy>(values: &HashMap<Pid, (usize, T)>, process: &Process) -> Option<T> {
+    values
+        .get(&process.pid)
+        .filter(|(start_time, _)| *start_time == process.start_time)
+        .map(|&(_, value)| value)
+}
+
+fn get(
+    data: &mut ProcessData,
+    snapshot: &Snapshot,
+    follow_pid: Option<Pid>,
+    sort: Sort,
+    secondary: Option<SecondarySort>,
+    pid_filter: &PidFilter,
+    normalize: bool,
+) -> Vec<ProcessDisplay> {
+    data.tree.clear();
+    data.parents.clear();
+    data.processes_index.clear();
+    data.tree_index.clear();
+
+    let ProcessData {
+        ref mut parents,
+        ref mut processes_index,
+        ref mut tree_index,
+        ref mut tree,
+        ref mut usernames,
+        ref processes_times,
+        ref processes_io,
+        ref mut rates,
+        cpu_stat: ref data_cpu_stat,
+        elapsed: show_elapsed,
+        io: show_io,
+        threads: show_threads,
+        cpu_time: show_cpu_time,
+        caps: show_caps,
+        ref baseline,
+        pid_ns: scope_ns,
+    } = *data;
+
+    let mut processes = Vec::with_capacity(snapshot.processes.len());
+    let multiplier = (snapshot.cpu_no as f64) * 10000.0;
+    let elapsed = snapshot
+        .cpu_stat
+        .total_time()
+        .saturating_sub(data_cpu_stat.total_time());
+    let clock_ticks = clock_ticks();
+    /* `elapsed` is in clock ticks of every cpu */
+    let seconds = elapsed as f64 / (snapshot.cpu_no.max(1) * clock_ticks) as f64;
+    /* over an interval that isn't usable, the rates of the last one that was are kept */
+    let usable = usable_interval(seconds);
+    let mut new_rates = HashMap::with_capacity(if usable { snapshot.processes.len() } else { 0 });
+    /* the PID namespaces of the parents, which can come after their children */
+    let namespaces = snapshot
+        .processes
+        .iter()
+        .filter(|_| scope_ns)
+        .filter_map(|p| p.pid_ns.map(|ns| (p.pid, ns)))
+        .collect::<HashMap<Pid, u64>>();
+    let mut ns_roots = HashSet::new();
+    for process in &snapshot.processes {
+        if process.cmd_line.is_empty() {
+            /* This is a kernel thread, skip for now */
+            continue;
+        }
+
+        let (cpu_percent, io_rates) = if usable {
+            let cpu_percent = rate(previous(processes_times, process), process.rtime, elapsed)
+                .map(|rate| (multiplier * rate) as usize);
+            let io_rates = process
+                .io
+                .and_then(|io| io::io_rates(previous(processes_io, process), io, seconds));
+            new_rates.insert(process.pid, (process.start_time, (cpu_percent, io_rates)));
+            (cpu_percent, io_rates)
+        } else {
+            previous(rates, process).unwrap_or((None, None))
+        };
+        let process_display = ProcessDisplay {
+            i: process.pid,
+            p: process.ppid,
+            pgrp: process.pgrp,
+            pid: PidString(String::new()),
+            ppid: PpidString(String::new()),
+            vm_rss: VmRssString(String::new()),
+            vm_rss_value: process.vm_rss * 1024,
+            cpu_percent,
+            cpu_percent_raw: cpu_percent,
+            rtime: process.rtime,
+            state: process.state,
+            cmd_line: CmdLineString(if normalize {
+                normalize_nfc(&process.cmd_line).into_owned()
+            } else {
+                process.cmd_line.clone()
+            }),
+            username: UserString(
+                usernames
+                    .entry(process.uid)
+                    .or_insert_with(|| crate::ui::username(process.uid))
+                    .clone(),
+            ),
+            sparkline: None,
+            threads: if show_threads { process.threads } else { None },
+            cpu_time: if show_cpu_time {
+                Some(process.rtime)
+            } else {
+                None
+            },
+            elapsed: if show_elapsed {
+                Some(elapsed_since_start(
+                    process.start_time,
+                    snapshot.boot_time,
+                    clock_ticks,
+                    snapshot.time,
+                ))
+            } else {
+                None
+            },
+            io: if show_io { Some(io_rates) } else { None },
+            delta: baseline.as_ref().map(|baseline| baseline.delta(process)),
+            cgroup: process.cgroup.clone().map(CgroupString),
+            affinity: process.affinity.clone(),
+            caps: if show_caps { process.cap_eff } else { None },
+            env: None,
+            net: None,
+            gpu: None,
+            pid_ns: process.pid_ns,
+            category: process.category,
+            members: 0,
+            formatted: false,
+        };
+
+        if scope_ns && !same_ns(process.pid_ns, namespaces.get(&process.ppid).copied()) {
+            /* the first process of a namespace, a root of its own */
+            ns_roots.insert(process.pid);
+        } else {
+            parents.entry(process.ppid).or_default().push(process.pid);
+        }
+
+        processes_index.insert(process.pid, processes.len());
+        processes.push(process_display);
+    }
+    if usable {
+        *rates = new_rates;
+    }
+    if !pid_filter.pids.is_empty() {
+        let keep_list = if pid_filter.descendants {
+            with_descendants(&pid_filter.pids, parents)
+        } else {
+            pid_filter.pids.iter().cloned().collect()
+        };
+        processes.retain(|entry| keep_list.contains(&entry.i));
+        parents.retain(|_, children| {
+            children.retain(|c| keep_list.contains(c));
+            !children.is_empty()
+        });
+        /* Show requested PIDs that don't exist instead of silently dropping them */
+        for &pid in &pid_filter.pids {
+            if !processes.iter().any(|p| p.i == pid) {
+                processes.push(ProcessDisplay::gone(pid));
             }
-            keep_list.insert(pid);
-            if processes[processes_index[&pid]].p != 0 {
-                stack.push(processes[processes_index[&pid]].p);
+        }
+        processes_index.clear();
+        for (i, p) in processes.iter().enumerate() {
+            processes_index.insert(p.i, i);
+        }
+    }
+
+    let mut keep_list = HashSet::new();
+    if let Some(follow_pid) = follow_pid.filter(|pid| processes_index.contains_key(pid)) {
+        let p = &processes[processes_index[&follow_pid]];
+        keep_list = with_descendants(&[p.i], parents);
+        let mut stack = vec![p.p];
+        while let Some(pid) = stack.pop() {
+            if let Some(&i) = processes_index.get(&pid) {
+                keep_list.insert(pid);
+                stack.push(processes[i].p);
             }
         }
         processes_index.clear();
@@ -1701,7 +5450,26 @@ fn get(data: &mut ProcessData, follow_pid: Option<Pid>, sort: Sort) -> Vec<Proce
     }
 
     let mut stack = Vec::with_capacity(processes.len());
-    stack.push((0, 1));
+    if pid_filter.pids.is_empty() {
+        /* below init on the stack, so that the trees of other namespaces come after its tree */
+        let mut roots = ns_roots
+            .iter()
+            .filter_map(|pid| processes_index.get(pid))
+            .map(|&i| &processes[i])
+            .collect::<Vec<&ProcessDisplay>>();
+        roots.sort_unstable_by(|a, b| sort_cmp(sort, secondary, a, b));
+        stack.extend(roots.into_iter().map(|p| (0, p.i)).rev());
+        stack.push((0, 1));
+    } else {
+        /* The filtered processes might not be connected to init, so every process whose parent
+         * isn't shown is a root */
+        let mut roots = processes
+            .iter()
+            .filter(|p| !processes_index.contains_key(&p.p) || ns_roots.contains(&p.i))
+            .collect::<Vec<&ProcessDisplay>>();
+        roots.sort_unstable_by(|a, b| sort_cmp(sort, secondary, a, b));
+        stack.extend(roots.into_iter().map(|p| (0, p.i)).rev());
+    }
     while let Some((ind, pid)) = stack.pop() {
         tree_index.insert(pid, tree.len());
         tree.push((ind, pid));
@@ -1710,18 +5478,12 @@ fn get(data: &mut ProcessData, follow_pid: Option<Pid>, sort: Sort) -> Vec<Proce
                 children.retain(|c| keep_list.contains(c));
             }
             children.sort_unstable_by(|a, b| {
-                let a = &processes[processes_index[a]];
-                let b = &processes[processes_index[b]];
-                match sort {
-                    Sort::CpuAsc => a.cpu_percent.cmp(&b.cpu_percent),
-                    Sort::CpuDesc => b.cpu_percent.cmp(&a.cpu_percent),
-                    Sort::VmRssAsc => a.vm_rss_value.cmp(&b.vm_rss_value),
-                    Sort::VmRssDesc => b.vm_rss_value.cmp(&a.vm_rss_value),
-                    Sort::UserAsc => a.username.0.cmp(&b.username.0),
-                    Sort::UserDesc => b.username.0.cmp(&a.username.0),
-                    Sort::CmdLineAsc => a.cmd_line.0.cmp(&b.cmd_line.0),
-                    Sort::CmdLineDesc => b.cmd_line.0.cmp(&a.cmd_line.0),
-                }
+                sort_cmp(
+                    sort,
+                    secondary,
+                    &processes[processes_index[a]],
+                    &processes[processes_index[b]],
+                )
             });
             stack.extend(children.iter().map(|p| (ind + 1, *p)).rev());
         }
@@ -1729,32 +5491,58 @@ fn get(data: &mut ProcessData, follow_pid: Option<Pid>, sort: Sort) -> Vec<Proce
     processes
 }
 
+/* Replace the contents of `buf` with the file at `path`. Process names and command lines are
+ * arbitrary bytes, so invalid UTF-8 is replaced with U+FFFD instead of failing the read and
+ * dropping the process. */
+fn read_proc_file(path: &Path, buf: &mut String) -> Result<(), std::io::Error> {
+    let mut bytes = Vec::with_capacity(buf.capacity());
+    File::open(path)?.read_to_end(&mut bytes)?;
+    buf.clear();
+    match String::from_utf8(bytes) {
+        Ok(s) => buf.push_str(&s),
+        Err(err) => buf.push_str(&String::from_utf8_lossy(err.as_bytes())),
+    }
+    Ok(())
+}
+
 /* Might return Error if process has disappeared
  * during the function's run */
-fn get_pid_info(mut path: PathBuf) -> Result<Process, std::io::Error> {
+fn get_pid_info(mut path: PathBuf, reads: ExtraReads) -> Result<Process, std::io::Error> {
     /* proc file structure can be found in man 5 proc.*/
     path.push("status");
-    let mut file: File = File::open(&path)?;
     let mut res = String::with_capacity(2048);
-    file.read_to_string(&mut res)?;
+    read_proc_file(&path, &mut res)?;
     let mut lines_iter = res.lines();
     let mut ret = Process {
         pid: 0,
         ppid: 0,
+        pgrp: 0,
         vm_rss: 0,
         uid: 0,
+        threads: None,
         rtime: 0,
+        start_time: 0,
         state: State::Waiting,
         cmd_line: String::new(),
+        cgroup: None,
+        io: None,
+        cap_eff: None,
+        affinity: None,
+        pid_ns: None,
+        category: Category::Other,
     };
     let mut line;
+    let mut name = "";
 
     macro_rules! err {
         ($res:expr) => {
             match $res {
                 Ok(v) => v,
                 Err(_) => {
-                    return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, ""));
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        format!("{} returned malformed input", path.display()),
+                    ));
                 }
             }
         };
@@ -1765,7 +5553,10 @@ fn get_pid_info(mut path: PathBuf) -> Result<Process, std::io::Error> {
             if let Some(v) = $res {
                 v
             } else {
-                return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, ""));
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("{} returned malformed input", path.display()),
+                ));
             }
         };
     }
@@ -1773,6 +5564,10 @@ fn get_pid_info(mut path: PathBuf) -> Result<Process, std::io::Error> {
     let mut b = 0;
     while b < 5 {
         let line_opt = lines_iter.next();
+        if line_opt.is_none() && ret.state.is_zombie() && b == 4 {
+            /* zombies don't use memory, so they don't have a VmRSS line */
+            break;
+        }
         if line_opt.is_none() {
             return Err(std::io::Error::new(
                 std::io::ErrorKind::InvalidData,
@@ -1782,6 +5577,9 @@ fn get_pid_info(mut path: PathBuf) -> Result<Process, std::io::Error> {
         line = none_err!(line_opt);
         let mut mut_value_iter = line.split_whitespace();
         match mut_value_iter.next() {
+            Some("Name:") => {
+                name = line["Name:".len()..].trim();
+            }
             Some("VmRSS:") => {
                 ret.vm_rss = err!(usize::from_str(none_err!(mut_value_iter.next())));
                 b += 1;
@@ -1791,17 +5589,24 @@ fn get_pid_info(mut path: PathBuf) -> Result<Process, std::io::Error> {
                 b += 1;
             }
             Some("Pid:") => {
-                ret.pid = err!(i32::from_str(none_err!(mut_value_iter.next())));
+                ret.pid = err!(Pid::from_str(none_err!(mut_value_iter.next())));
                 b += 1;
             }
             Some("PPid:") => {
-                ret.ppid = err!(i32::from_str(none_err!(mut_value_iter.next())));
+                ret.ppid = err!(Pid::from_str(none_err!(mut_value_iter.next())));
                 b += 1;
             }
             Some("Uid:") => {
                 ret.uid = err!(u32::from_str(none_err!(mut_value_iter.next())));
                 b += 1;
             }
+            /* not counted, since they're after VmRSS: this only reads them for zombies */
+            Some("Threads:") => {
+                ret.threads = Some(err!(usize::from_str(none_err!(mut_value_iter.next()))));
+            }
+            Some("CapEff:") => {
+                ret.cap_eff = Some(none_err!(parse_cap_mask(none_err!(mut_value_iter.next()))));
+            }
             None => {
                 return Err(std::io::Error::new(
                     std::io::ErrorKind::InvalidData,
@@ -1815,25 +5620,3357 @@ fn get_pid_info(mut path: PathBuf) -> Result<Process, std::io::Error> {
         }
     }
 
+    /* Threads: and then CapEff: come after the lines above */
+    for line in lines_iter {
+        if let Some(threads) = line.strip_prefix("Threads:") {
+            ret.threads = Some(err!(usize::from_str(threads.trim())));
+        } else if let Some(mask) = line.strip_prefix("CapEff:") {
+            ret.cap_eff = Some(none_err!(parse_cap_mask(mask)));
+            break;
+        }
+    }
+
+    /* the command line of zombies is empty, like that of kernel threads. Show them like ps does. */
+    let defunct = if ret.state.is_zombie() {
+        Some(format!("[{}] <defunct>", name))
+    } else {
+        None
+    };
     path.pop();
     path.push("cmdline");
-    let mut file: File = File::open(&path)?;
-    res.clear();
-    file.read_to_string(&mut res)?;
+    read_proc_file(&path, &mut res)?;
     if !res.is_empty() {
         /* values are separated by null bytes */
-        ret.cmd_line = format!("{}", res.split('\0').collect::<Vec<&str>>().join(" "));
+        ret.cmd_line = res.split('\0').collect::<Vec<&str>>().join(" ").to_string();
+    } else if let Some(defunct) = defunct {
+        ret.cmd_line = defunct;
     }
     path.pop();
     path.push("stat");
-    let mut file: File = File::open(&path)?;
-    res.clear();
-    file.read_to_string(&mut res)?;
+    read_proc_file(&path, &mut res)?;
     /* values are separated by whitespace and are in a specific order */
     if !res.is_empty() {
+        /* comm is in parentheses and may contain whitespace, the fields after it are state, ppid,
+         * pgrp, session and tty_nr, and starttime is the 20th */
+        let mut vals = none_err!(res.rfind(')').map(|i| &res[i + 1..])).split_whitespace();
+        ret.pgrp = err!(Pid::from_str(none_err!(vals.nth(2))));
+        let session = err!(Pid::from_str(none_err!(vals.next())));
+        let tty_nr = err!(i32::from_str(none_err!(vals.next())));
+        ret.category = classify(ret.pid, ret.ppid, session, tty_nr);
+        ret.start_time = err!(usize::from_str(none_err!(vals.nth(14))));
         let mut vals = res.split_whitespace().skip(13);
         ret.rtime = err!(usize::from_str(none_err!(vals.next()))); /* utime */
         ret.rtime += err!(usize::from_str(none_err!(vals.next()))); /* stime */
     }
+    if reads.io {
+        path.pop();
+        path.push("io");
+        /* only the owner of a process and root can read its io, the others get no rates */
+        ret.io = match read_proc_file(&path, &mut res) {
+            Ok(()) => io::parse_io(&res),
+            Err(_) => None,
+        };
+    }
+    if reads.cgroups {
+        path.pop();
+        path.push("cgroup");
+        read_proc_file(&path, &mut res)?;
+        ret.cgroup = Some(cgroup::cgroup_name(&res));
+    }
+    if reads.affinity {
+        ret.affinity = Some(affinity_label(ret.pid));
+    }
+    if reads.pid_ns {
+        path.pop();
+        path.push("ns");
+        path.push("pid");
+        ret.pid_ns = read_pid_ns(&path);
+    }
     Ok(ret)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /* The memory as `format` shows it by default */
+    const BYTES: (MemFormat, usize) = (MemFormat::Bytes, 0);
+
+    fn process_display(pid: Pid, cmd_line: &str) -> ProcessDisplay {
+        ProcessDisplay {
+            i: pid,
+            p: 1,
+            pgrp: pid,
+            pid: PidString(pid.to_string()),
+            ppid: PpidString("1".to_string()),
+            vm_rss: VmRssString("1.00 MiB".to_string()),
+            vm_rss_value: 1024 * 1024,
+            cpu_percent: Some(150),
+            cpu_percent_raw: Some(150),
+            state: State::Sleeping,
+            cmd_line: CmdLineString(cmd_line.to_string()),
+            username: UserString("user".to_string()),
+            sparkline: None,
+            threads: None,
+            cpu_time: None,
+            elapsed: None,
+            io: None,
+            delta: None,
+            cgroup: None,
+            affinity: None,
+            caps: None,
+            env: None,
+            net: None,
+            gpu: None,
+            pid_ns: None,
+            category: Category::Other,
+            rtime: 0,
+            members: 0,
+            formatted: true,
+        }
+    }
+
+    #[test]
+    fn test_format_batch() {
+        let mut processes = vec![
+            process_display(1, "/sbin/init splash"),
+            process_display(1234, "bash"),
+        ];
+        processes[1].ppid = PpidString("1".to_string());
+        processes[1].state = State::Running;
+        processes[1].cpu_percent = Some(1234);
+        let rows = visible_processes(&processes, Sort::CpuDesc, None, None);
+        assert_eq!(
+            format_batch(
+                &processes,
+                &rows,
+                Sort::CpuDesc,
+                false,
+                &ProcessListOptions::default(),
+                false
+            ),
+            " PID  PPID  USER    VM_RSS    CPU%↓    CMD_LINE
+1234     1  user  1.00 MiB  12.34%  R  bash
+   1     1  user  1.00 MiB    1.5%  S  /sbin/init splash
+"
+        );
+        /* with --precise-below 5 */
+        let percent_format = PercentFormat {
+            precise_below: 500,
+            decimals: 1,
+        };
+        assert_eq!(
+            format_batch(
+                &processes,
+                &rows,
+                Sort::CpuDesc,
+                false,
+                &ProcessListOptions {
+                    percent_format: Some(percent_format),
+                    ..Default::default()
+                },
+                false
+            ),
+            " PID  PPID  USER    VM_RSS    CPU%↓    CMD_LINE
+1234     1  user  1.00 MiB     12%  R  bash
+   1     1  user  1.00 MiB    1.5%  S  /sbin/init splash
+"
+        );
+    }
+
+    #[test]
+    fn test_batch_env() {
+        let mut processes = vec![
+            process_display(1, "/sbin/init"),
+            process_display(2, "cargo test"),
+        ];
+        processes[0].env = Some(String::new());
+        processes[1].env = Some("debug\x1b[2J".to_string());
+        for p in processes.iter_mut() {
+            escape_control_chars(p, NonPrintable::default());
+        }
+        let rows = visible_processes(&processes, Sort::CpuDesc, None, None);
+        assert_eq!(
+            format_batch(
+                &processes,
+                &rows,
+                Sort::CpuDesc,
+                false,
+                &ProcessListOptions {
+                    env_var: Some("RUST_LOG".to_string()),
+                    ..Default::default()
+                },
+                false
+            ),
+            "PID  PPID  USER    VM_RSS    CPU%↓    RUST_LOG   CMD_LINE
+  1     1  user  1.00 MiB    1.5%  S             /sbin/init
+  2     1  user  1.00 MiB    1.5%  S  debug·[2J  cargo test
+"
+        );
+    }
+
+    #[test]
+    fn test_batch_sockets() {
+        let mut processes = vec![
+            process_display(1, "/sbin/init"),
+            process_display(2, "nginx"),
+        ];
+        processes[1].net = Some(NetState::Listening);
+        let rows = visible_processes(&processes, Sort::CpuDesc, None, None);
+        assert_eq!(
+            format_batch(
+                &processes,
+                &rows,
+                Sort::CpuDesc,
+                false,
+                &ProcessListOptions {
+                    sockets: true,
+                    ..Default::default()
+                },
+                false
+            ),
+            "PID  PPID  USER    VM_RSS    CPU%↓    NET     CMD_LINE
+  1     1  user  1.00 MiB    1.5%  S          /sbin/init
+  2     1  user  1.00 MiB    1.5%  S  listen  nginx
+"
+        );
+    }
+
+    #[test]
+    fn test_batch_gpu() {
+        let mut processes = vec![
+            process_display(1, "/sbin/init"),
+            process_display(2, "python train.py"),
+        ];
+        processes[1].gpu = Some(GpuUsage {
+            memory: 1024 * 1024 * 1024,
+            utilization: Some(37),
+        });
+        let rows = visible_processes(&processes, Sort::CpuDesc, None, None);
+        let options = ProcessListOptions {
+            gpu: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            format_batch(&processes, &rows, Sort::CpuDesc, false, &options, true),
+            "PID  PPID  USER    VM_RSS    CPU%↓    GPU_MEM  GPU%  CMD_LINE
+  1     1  user  1.00 MiB    1.5%  S                 /sbin/init
+  2     1  user  1.00 MiB    1.5%  S     1.0G   37%  python train.py
+"
+        );
+        /* without GPUs to read */
+        assert!(
+            !format_batch(&processes, &rows, Sort::CpuDesc, false, &options, false).contains("GPU")
+        );
+    }
+
+    #[test]
+    fn test_batch_control_chars() {
+        let mut processes = vec![process_display(1, "sh -c 'echo \x1b[2J'\nrm -rf x")];
+        escape_control_chars(&mut processes[0], NonPrintable::default());
+        let rows = visible_processes(&processes, Sort::CpuDesc, None, None);
+        assert_eq!(
+            format_batch(
+                &processes,
+                &rows,
+                Sort::CpuDesc,
+                false,
+                &ProcessListOptions::default(),
+                false
+            ),
+            "PID  PPID  USER    VM_RSS    CPU%↓    CMD_LINE
+  1     1  user  1.00 MiB    1.5%  S  sh -c 'echo ·[2J'·rm -rf x
+"
+        );
+        /* with --control-chars escape */
+        escape_control_chars(&mut processes[0], NonPrintable::Escape);
+        assert_eq!(processes[0].cmd_line.0, "sh -c 'echo ·[2J'·rm -rf x");
+        let mut p = process_display(1, "a\x1bb");
+        escape_control_chars(&mut p, NonPrintable::Escape);
+        assert_eq!(p.cmd_line.0, "a\\u{1b}b");
+    }
+
+    #[test]
+    fn test_alignments() {
+        let mut processes = vec![process_display(1, "init"), process_display(1234, "bash")];
+        processes[1].username = UserString("postgres".to_string());
+        let mut maxima = ColumnWidthMaxima::from_processes(&processes);
+        /* the user column is left-aligned and the numbers right-aligned by default */
+        assert!(maxima
+            .header(Sort::UserAsc, "")
+            .starts_with(" PID  PPID  USER↑       VM_RSS "));
+        assert!(maxima
+            .row(&processes[0])
+            .starts_with("   1     1  user      1.00 MiB  "));
+        assert!(maxima
+            .row(&processes[1])
+            .starts_with("1234     1  postgres  1.00 MiB  "));
+
+        maxima.alignments = Alignments::parse("user=right,pid=left").unwrap();
+        assert!(maxima
+            .header(Sort::UserAsc, "")
+            .starts_with("PID   PPID      USER↑   VM_RSS "));
+        assert!(maxima
+            .row(&processes[0])
+            .starts_with("1        1      user  1.00 MiB  "));
+        assert!(maxima
+            .row(&processes[1])
+            .starts_with("1234     1  postgres  1.00 MiB  "));
+    }
+
+    #[test]
+    fn test_huge_pid() {
+        /* the largest `pid_max` on 64-bit Linux is 2^22 */
+        let mut processes = vec![
+            process_display(1, "/sbin/init"),
+            process_display(4194303, "bash"),
+        ];
+        processes[1].ppid = PpidString("1234567".to_string());
+        let rows = visible_processes(&processes, Sort::CpuDesc, None, None);
+        assert_eq!(
+            format_batch(
+                &processes,
+                &rows,
+                Sort::CpuDesc,
+                false,
+                &ProcessListOptions::default(),
+                false
+            ),
+            "    PID     PPID  USER    VM_RSS    CPU%↓    CMD_LINE
+      1        1  user  1.00 MiB    1.5%  S  /sbin/init
+4194303  1234567  user  1.00 MiB    1.5%  S  bash
+"
+        );
+
+        /* the columns fit the largest PID even when it's scrolled out of view */
+        let mut maxima = ColumnWidthMaxima::from_processes(&processes[..1]);
+        assert_eq!((maxima.pid, maxima.ppid), (3, 4));
+        maxima.fit_pids(4194303);
+        assert_eq!((maxima.pid, maxima.ppid), (7, 7));
+
+        let dir = std::env::temp_dir().join(format!("bb-test-huge-pid-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let status = |pid: &str| {
+            format!(
+                "Name:\tbash\nState:\tS (sleeping)\nPid:\t{}\nPPid:\t4194302\n\
+                 Uid:\t0\t0\t0\t0\nVmRSS:\t  8 kB\n",
+                pid
+            )
+        };
+        std::fs::write(dir.join("status"), status("4194303")).unwrap();
+        std::fs::write(
+            dir.join("stat"),
+            "4194303 (bash) S 4194302 4194301 4194301 0 -1 0 0 0 0 0 1 2 0 0 20 0 1 0 99 0 0\n",
+        )
+        .unwrap();
+        std::fs::write(dir.join("cmdline"), "bash\0").unwrap();
+        let p = get_pid_info(dir.clone(), ExtraReads::default()).unwrap();
+        assert_eq!((p.pid, p.ppid, p.pgrp), (4194303, 4194302, 4194301));
+        assert_eq!(p.category, Category::Other);
+        /* the same process with a controlling terminal */
+        std::fs::write(
+            dir.join("stat"),
+            "4194303 (bash) S 4194302 4194301 4194301 34816 -1 0 0 0 0 0 1 2 0 0 20 0 1 0 99 0 0\n",
+        )
+        .unwrap();
+        let p = get_pid_info(dir.clone(), ExtraReads::default()).unwrap();
+        assert_eq!((p.category, p.start_time), (Category::Interactive, 99));
+
+        /* a value that doesn't fit in a pid_t is bad data, not a wrapped around PID */
+        std::fs::write(dir.join("status"), status("4294967297")).unwrap();
+        assert_eq!(
+            get_pid_info(dir.clone(), ExtraReads::default())
+                .unwrap_err()
+                .kind(),
+            std::io::ErrorKind::InvalidData
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /* `n` processes with a cpu time of 0..n, all children of init. Use with `usage_data` so that
+     * cpu usage increases with the PID. */
+    fn mock_snapshot(n: usize) -> Snapshot {
+        let stat = |t| Stat {
+            user_time: t,
+            nice_time: 0,
+            system_time: 0,
+            idle_time: 0,
+            iowait_time: 0,
+            irq: 0,
+            soft_irq: 0,
+            steal: 0,
+            guest: 0,
+            guest_nice: 0,
+        };
+        Snapshot {
+            cpu_stat: stat(n),
+            cpu_no: 4,
+            boot_time: 0,
+            time: 0,
+            mem_total: 0,
+            processes: (0..n)
+                .map(|i| Process {
+                    pid: i as Pid + 1,
+                    ppid: if i == 0 { 0 } else { 1 },
+                    pgrp: 1,
+                    vm_rss: i * 100,
+                    state: State::Sleeping,
+                    uid: 0,
+                    threads: Some(1),
+                    cmd_line: format!("/usr/bin/worker --id {}", i),
+                    rtime: i,
+                    start_time: 0,
+                    cgroup: None,
+                    io: None,
+                    cap_eff: None,
+                    affinity: None,
+                    pid_ns: None,
+                    category: Category::Other,
+                })
+                .collect(),
+        }
+    }
+
+    /* The state after a snapshot of `n` processes that hadn't used any cpu time */
+    fn usage_data(n: usize) -> ProcessData {
+        let mut prev = mock_snapshot(n);
+        prev.cpu_stat.user_time = 0;
+        for p in prev.processes.iter_mut() {
+            p.rtime = 0;
+        }
+        let mut data = ProcessData::new(prev.cpu_stat);
+        data.set_previous(&prev);
+        data
+    }
+
+    #[test]
+    fn test_first_sample_cpu_percent() {
+        /* Nothing to compare the first snapshot with */
+        let snapshot = mock_snapshot(3);
+        let mut data = ProcessData::new(snapshot.cpu_stat);
+        let filter = PidFilter::default();
+        let processes = get(
+            &mut data,
+            &snapshot,
+            None,
+            Sort::CpuDesc,
+            None,
+            &filter,
+            false,
+        );
+        assert!(processes.iter().all(|p| p.cpu_percent.is_none()));
+        let mut p = processes.into_iter().next().unwrap();
+        p.format(MemFormat::Bytes, 0);
+        assert!(ColumnWidthMaxima::new().row(&p).contains("    --  "));
+
+        let mut data = usage_data(3);
+        let mut processes = get(
+            &mut data,
+            &snapshot,
+            None,
+            Sort::CpuDesc,
+            None,
+            &filter,
+            false,
+        );
+        processes.sort_by_key(|p| p.i);
+        assert_eq!(
+            processes
+                .iter()
+                .map(|p| p.cpu_percent)
+                .collect::<Vec<Option<usize>>>(),
+            vec![Some(0), Some(13333), Some(26666)]
+        );
+
+        /* A process that started after the previous snapshot */
+        let mut snapshot = mock_snapshot(4);
+        snapshot.cpu_stat = mock_snapshot(3).cpu_stat;
+        let processes = get(
+            &mut data,
+            &snapshot,
+            None,
+            Sort::CpuDesc,
+            None,
+            &filter,
+            false,
+        );
+        let new = processes.iter().find(|p| p.i == 4).unwrap();
+        assert_eq!(new.cpu_percent, None);
+    }
+
+    #[test]
+    fn test_unusable_interval_keeps_rates() {
+        let filter = PidFilter::default();
+        let cpu_percents = |data: &mut ProcessData, snapshot: &Snapshot| {
+            let mut processes = get(data, snapshot, None, Sort::CpuDesc, None, &filter, false);
+            processes.sort_by_key(|p| p.i);
+            processes
+                .iter()
+                .map(|p| p.cpu_percent)
+                .collect::<Vec<Option<usize>>>()
+        };
+        let mut data = usage_data(3);
+        let snapshot = mock_snapshot(3);
+        let expected = vec![Some(0), Some(13333), Some(26666)];
+        assert_eq!(cpu_percents(&mut data, &snapshot), expected);
+
+        /* the cpu counters didn't advance, or went back after a cpu went offline: the rates of the
+         * previous interval are kept rather than divided by nothing */
+        data.set_previous(&snapshot);
+        let mut stalled = mock_snapshot(3);
+        for p in stalled.processes.iter_mut() {
+            p.rtime += 50;
+        }
+        assert_eq!(cpu_percents(&mut data, &stalled), expected);
+        stalled.cpu_stat.user_time = 1;
+        assert_eq!(cpu_percents(&mut data, &stalled), expected);
+        /* and are still there when the same snapshot is read again */
+        assert_eq!(cpu_percents(&mut data, &stalled), expected);
+
+        /* the next usable interval replaces them, these processes were idle during it */
+        data.set_previous(&snapshot);
+        let mut next = mock_snapshot(6);
+        next.processes.truncate(3);
+        assert_eq!(cpu_percents(&mut data, &next), vec![Some(0); 3]);
+    }
+
+    #[test]
+    fn test_pid_reuse() {
+        let filter = PidFilter::default();
+        let mut data = usage_data(3);
+        data.io = true;
+        let mut snapshot = mock_snapshot(3);
+        for p in snapshot.processes.iter_mut() {
+            p.io = Some(IoCounters {
+                read_bytes: 4096,
+                write_bytes: 0,
+            });
+        }
+        /* PID 3 exited after the previous snapshot and a new process got its PID */
+        snapshot.processes[2].start_time = 5000;
+        let mut processes = get(
+            &mut data,
+            &snapshot,
+            None,
+            Sort::CpuDesc,
+            None,
+            &filter,
+            false,
+        );
+        processes.sort_by_key(|p| p.i);
+        assert_eq!(
+            processes
+                .iter()
+                .map(|p| p.cpu_percent)
+                .collect::<Vec<Option<usize>>>(),
+            vec![Some(0), Some(13333), None]
+        );
+        /* nothing to compare the I/O with either, the previous snapshot had no counters */
+        assert!(processes.iter().all(|p| p.io == Some(None)));
+
+        /* the new process is the baseline of the next snapshot */
+        data.set_previous(&snapshot);
+        let mut next = mock_snapshot(3);
+        next.cpu_stat.user_time *= 2;
+        for p in next.processes.iter_mut() {
+            p.rtime *= 2;
+            p.io = Some(IoCounters {
+                read_bytes: 8192,
+                write_bytes: 0,
+            });
+        }
+        next.processes[2].start_time = 5000;
+        let mut processes = get(&mut data, &next, None, Sort::CpuDesc, None, &filter, false);
+        processes.sort_by_key(|p| p.i);
+        assert_eq!(
+            processes
+                .iter()
+                .map(|p| p.cpu_percent)
+                .collect::<Vec<Option<usize>>>(),
+            vec![Some(0), Some(13333), Some(26666)]
+        );
+        assert!(processes.iter().all(|p| p.io.unwrap().is_some()));
+
+        /* and is reused again */
+        next.processes[2].start_time = 9000;
+        let processes = get(&mut data, &next, None, Sort::CpuDesc, None, &filter, false);
+        let reused = processes.iter().find(|p| p.i == 3).unwrap();
+        assert_eq!((reused.cpu_percent, reused.io), (None, Some(None)));
+    }
+
+    #[test]
+    fn test_format_window() {
+        let snapshot = mock_snapshot(5000);
+        let mut data = usage_data(5000);
+        let mut processes = get(
+            &mut data,
+            &snapshot,
+            None,
+            Sort::CpuDesc,
+            None,
+            &PidFilter::default(),
+            false,
+        );
+        let rows = visible_indices(&processes, Sort::CpuDesc, None, None);
+
+        let window = format_window(&mut processes, &rows, 1000, 40, BYTES, None, None);
+        assert_eq!(window, 1000 - OVERSCAN..1040 + OVERSCAN);
+        let formatted = rows
+            .iter()
+            .enumerate()
+            .filter(|(_, i)| processes[**i].formatted)
+            .map(|(row, _)| row)
+            .collect::<Vec<usize>>();
+        assert_eq!(formatted, window.collect::<Vec<usize>>());
+        assert_eq!(processes[rows[1000]].pid.0, "4000");
+        assert_eq!(processes[rows[1000]].ppid.0, "1");
+        assert_eq!(processes[rows[1000]].vm_rss.0, "390.53 MiB");
+        assert_eq!(processes[rows[900]].pid.0, "");
+
+        /* The window is clamped to the list */
+        assert_eq!(
+            format_window(&mut processes, &rows, 0, 40, BYTES, None, None),
+            0..40 + OVERSCAN
+        );
+        assert_eq!(
+            format_window(&mut processes, &rows, 4980, 40, BYTES, None, None),
+            4980 - OVERSCAN..5000
+        );
+    }
+
+    /* Run with `cargo test --release -- --ignored --nocapture bench_` */
+    #[test]
+    #[ignore]
+    fn bench_format_window() {
+        use std::time::Instant;
+        let snapshot = mock_snapshot(5000);
+        let mut data = usage_data(5000);
+        let filter = PidFilter::default();
+        for (name, all) in &[("window", false), ("all", true)] {
+            let now = Instant::now();
+            for _ in 0..100 {
+                let mut processes = get(
+                    &mut data,
+                    &snapshot,
+                    None,
+                    Sort::CpuDesc,
+                    None,
+                    &filter,
+                    false,
+                );
+                let rows = visible_indices(&processes, Sort::CpuDesc, None, None);
+                let window = if *all {
+                    format_window(&mut processes, &rows, 0, rows.len(), BYTES, None, None)
+                } else {
+                    format_window(&mut processes, &rows, 2000, 50, BYTES, None, None)
+                };
+                ColumnWidthMaxima::from_processes(rows[window].iter().map(|&i| &processes[i]));
+            }
+            eprintln!("{:<6}: {:?}/iter", name, now.elapsed() / 100);
+        }
+    }
+
+    #[test]
+    fn test_smooth_cpu_percent() {
+        let now = std::time::Instant::now();
+        let mut averages = HashMap::default();
+        let mut processes = vec![process_display(1, "init"), process_display(2, "bash")];
+        processes[1].cpu_percent_raw = Some(1000);
+        smooth_cpu_percent(&mut processes, &mut averages, 0.5, true, now);
+        assert_eq!(
+            (processes[0].cpu_percent, processes[1].cpu_percent),
+            (Some(150), Some(1000))
+        );
+
+        /* Rebuilding the list from the same snapshot doesn't add a sample */
+        let mut processes = vec![process_display(1, "init"), process_display(2, "bash")];
+        processes[1].cpu_percent_raw = Some(0);
+        smooth_cpu_percent(&mut processes, &mut averages, 0.5, false, now);
+        assert_eq!(processes[1].cpu_percent, Some(1000));
+        smooth_cpu_percent(&mut processes, &mut averages, 0.5, true, now);
+        assert_eq!(processes[1].cpu_percent, Some(500));
+        assert_eq!(processes[1].cpu_percent_raw, Some(0));
+
+        /* Averages of processes that exited are dropped */
+        processes.truncate(1);
+        smooth_cpu_percent(&mut processes, &mut averages, 0.5, true, now);
+        assert_eq!(averages.len(), 1);
+    }
+
+    #[test]
+    fn test_cpu_history() {
+        let mut histories = HashMap::default();
+        let mut processes = vec![process_display(1, "init"), process_display(2, "bash")];
+        processes[1].cpu_percent_raw = Some(10000);
+        record_cpu_history(
+            &mut processes,
+            &mut histories,
+            true,
+            false,
+            Default::default(),
+        );
+        assert_eq!(processes[0].sparkline.as_deref(), Some("       ▁"));
+        assert_eq!(processes[1].sparkline.as_deref(), Some("       █"));
+
+        /* Rebuilding the list from the same snapshot doesn't add a sample */
+        record_cpu_history(
+            &mut processes,
+            &mut histories,
+            false,
+            false,
+            Default::default(),
+        );
+        processes[1].cpu_percent_raw = Some(0);
+        record_cpu_history(
+            &mut processes,
+            &mut histories,
+            true,
+            false,
+            Default::default(),
+        );
+        assert_eq!(processes[1].sparkline.as_deref(), Some("      █ "));
+
+        /* Histories are bounded */
+        for _ in 0..3 * HISTORY_LEN {
+            record_cpu_history(
+                &mut processes,
+                &mut histories,
+                true,
+                false,
+                Default::default(),
+            );
+        }
+        assert_eq!(processes[1].sparkline.as_deref(), Some("        "));
+
+        /* A PID reused by a new process starts over, and exited processes are dropped */
+        processes[0].cpu_percent_raw = None;
+        processes.truncate(1);
+        record_cpu_history(
+            &mut processes,
+            &mut histories,
+            true,
+            false,
+            Default::default(),
+        );
+        assert!(histories.is_empty());
+        processes[0].cpu_percent_raw = Some(5000);
+        record_cpu_history(
+            &mut processes,
+            &mut histories,
+            true,
+            false,
+            Default::default(),
+        );
+        assert_eq!(processes[0].sparkline.as_deref(), Some("       ▄"));
+        assert_eq!(histories.len(), 1);
+
+        /* The baseline is dimmed by its position in the row */
+        record_cpu_history(
+            &mut processes,
+            &mut histories,
+            false,
+            true,
+            Default::default(),
+        );
+        assert_eq!(processes[0].sparkline.as_deref(), Some("       ▄"));
+        processes[0].cpu_percent_raw = Some(0);
+        record_cpu_history(
+            &mut processes,
+            &mut histories,
+            true,
+            true,
+            Default::default(),
+        );
+        assert_eq!(processes[0].sparkline.as_deref(), Some("      ▄_"));
+        let maxima = ColumnWidthMaxima::from_processes(&processes);
+        let offset = maxima.sparkline_offset().unwrap();
+        assert_eq!(
+            maxima
+                .row(&processes[0])
+                .chars()
+                .skip(offset)
+                .take(HISTORY_LEN)
+                .collect::<String>(),
+            "      ▄_"
+        );
+        assert_eq!(ColumnWidthMaxima::new().sparkline_offset(), None);
+    }
+
+    #[test]
+    fn test_mem_growth() {
+        let start = std::time::Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+        let mib = 1024 * 1024;
+        let mut processes = vec![process_display(1, "leak"), process_display(2, "steady")];
+        /* 1 MiB per second */
+        let mut mem_growth = MemGrowth::new(mib as f64);
+        for secs in 0..4 {
+            processes[0].vm_rss_value = (2 * secs as usize + 1) * mib;
+            mem_growth.record(&processes, true, at(secs));
+        }
+        assert!(mem_growth.is_growing(1));
+        assert!(!mem_growth.is_growing(2));
+        /* redraws between samples don't add to the history */
+        processes[0].vm_rss_value = 0;
+        mem_growth.record(&processes, false, at(4));
+        assert!(mem_growth.is_growing(1));
+        /* the history starts over for a new process with the PID */
+        processes[0].cpu_percent_raw = None;
+        mem_growth.record(&processes, true, at(5));
+        assert!(!mem_growth.is_growing(1));
+        processes[0].cpu_percent_raw = Some(0);
+        processes[0].vm_rss_value = 8 * mib;
+        mem_growth.record(&processes, true, at(6));
+        assert!(mem_growth.is_growing(1));
+        /* and is dropped once it exits */
+        mem_growth.record(&processes[1..], true, at(7));
+        processes[0].vm_rss_value = 9 * mib;
+        mem_growth.record(&processes, true, at(8));
+        assert!(!mem_growth.is_growing(1));
+    }
+
+    #[test]
+    fn test_group_processes() {
+        let mut processes = vec![
+            process_display(1, "/sbin/init splash"),
+            process_display(20, "/usr/bin/python3 server.py"),
+            process_display(30, "bash"),
+            process_display(21, "python3 worker.py"),
+            process_display(22, "/usr/bin/python3 -c pass"),
+        ];
+        processes[1].cpu_percent = Some(1000);
+        processes[3].cpu_percent = Some(250);
+        processes[4].cpu_percent = None;
+        processes[3].username = UserString("www".to_string());
+        processes[4].state = State::Running;
+        regroup(&mut processes, true);
+        assert_eq!(processes.len(), 8);
+
+        fn group<'a>(processes: &'a [ProcessDisplay], name: &str) -> Option<&'a ProcessDisplay> {
+            processes
+                .iter()
+                .find(|p| p.members > 0 && p.cmd_line.0 == name)
+        }
+        let python = group(&processes, "python3").unwrap();
+        assert_eq!(python.members, 3);
+        assert_eq!(python.i, 20);
+        assert_eq!(python.cpu_percent, Some(1250));
+        assert_eq!(python.vm_rss_value, 3 * 1024 * 1024);
+        assert_eq!(python.vm_rss.0, "3.00 MiB");
+        assert_eq!(python.username.0, "*");
+        assert_eq!(python.state, State::Running);
+        assert_eq!(group(&processes, "bash").unwrap().members, 1);
+        assert_eq!(group(&processes, "bash").unwrap().username.0, "user");
+
+        /* Groups are sorted like processes, and expanded ones are followed by their members */
+        let pids = |expanded: &HashSet<String>, filter: Option<&Filter>| {
+            grouped_indices(&processes, Sort::CpuDesc, None, filter, expanded)
+                .into_iter()
+                .map(|i| (processes[i].i, processes[i].members))
+                .collect::<Vec<(Pid, usize)>>()
+        };
+        let mut expanded = HashSet::new();
+        assert_eq!(&pids(&expanded, None)[..1], &[(20, 3)]);
+        assert_eq!(pids(&expanded, None).len(), 3);
+        expanded.insert("python3".to_string());
+        assert_eq!(
+            &pids(&expanded, None)[..4],
+            &[(20, 3), (20, 0), (21, 0), (22, 0)]
+        );
+        let filter = Filter::new("worker", false, false);
+        assert_eq!(pids(&expanded, Some(&filter)), vec![(20, 3), (21, 0)]);
+
+        /* The groups are recomputed from the processes in the list */
+        processes.remove(2);
+        regroup(&mut processes, true);
+        assert_eq!(processes.len(), 6);
+        assert!(group(&processes, "bash").is_none());
+        regroup(&mut processes, false);
+        assert_eq!(processes.len(), 4);
+
+        let row = GroupedRow::Group("python3".to_string());
+        let rows = (0..processes.len()).collect::<Vec<usize>>();
+        assert_eq!(row.find(&processes, &rows), None);
+        assert_eq!(GroupedRow::Process(21).find(&processes, &rows), Some(2));
+    }
+
+    #[test]
+    fn test_send_signal() {
+        use nix::errno::Errno;
+        use nix::sys::signal::Signal;
+
+        let mut calls = vec![];
+        assert_eq!(
+            send_signal(
+                SignalTarget::Process(1234),
+                Signal::SIGTERM,
+                &[],
+                |pid, sig| {
+                    calls.push((pid.as_raw(), sig));
+                    Ok(())
+                }
+            ),
+            Ok(())
+        );
+        assert_eq!(calls, vec![(1234, Some(Signal::SIGTERM))]);
+
+        calls.clear();
+        assert_eq!(
+            send_signal(
+                SignalTarget::Group(1234),
+                Signal::SIGKILL,
+                &[1234, 1235],
+                |pid, sig| {
+                    calls.push((pid.as_raw(), sig));
+                    Ok(())
+                }
+            ),
+            Ok(())
+        );
+        assert_eq!(calls, vec![(-1234, Some(Signal::SIGKILL))]);
+
+        /* the group send failed, 1236 is the only member we may not signal */
+        calls.clear();
+        assert_eq!(
+            send_signal(
+                SignalTarget::Group(1234),
+                Signal::SIGTERM,
+                &[1234, 1235, 1236],
+                |pid, sig| {
+                    calls.push((pid.as_raw(), sig));
+                    if pid.as_raw() == -1234 || pid.as_raw() == 1236 {
+                        Err(nix::Error::Sys(Errno::EPERM))
+                    } else {
+                        Ok(())
+                    }
+                }
+            ),
+            Err("cannot signal process group 1234: not permitted for PID 1236".to_string())
+        );
+        assert_eq!(calls[0], (-1234, Some(Signal::SIGTERM)));
+        assert_eq!(&calls[1..], &[(1234, None), (1235, None), (1236, None)]);
+
+        calls.clear();
+        for pgrp in &[0, 1] {
+            assert!(send_signal(
+                SignalTarget::Group(*pgrp),
+                Signal::SIGTERM,
+                &[],
+                |pid, sig| {
+                    calls.push((pid.as_raw(), sig));
+                    Ok(())
+                }
+            )
+            .is_err());
+        }
+        assert!(calls.is_empty());
+    }
+
+    #[test]
+    fn test_uninterruptible() {
+        assert!(State::from('D').is_uninterruptible());
+        assert!(!State::from('S').is_uninterruptible());
+        assert!(!State::from('R').is_uninterruptible());
+
+        let mut processes = vec![
+            process_display(1, "init"),
+            process_display(2, "dd"),
+            process_display(3, "sync"),
+        ];
+        assert_eq!(count_uninterruptible(&processes), 0);
+        processes[1].state = State::from('D');
+        assert_eq!(count_uninterruptible(&processes), 1);
+        processes[2].state = State::from('D');
+        assert_eq!(count_uninterruptible(&processes), 2);
+        /* a group row doesn't count on top of its members */
+        let mut group = process_display(2, "dd");
+        group.state = State::from('D');
+        group.members = 2;
+        processes.push(group);
+        assert_eq!(count_uninterruptible(&processes), 2);
+        processes[1].state = State::Running;
+        assert_eq!(count_uninterruptible(&processes), 1);
+    }
+
+    #[test]
+    fn test_ages() {
+        let mut ages = Ages::new(2);
+        let mut processes = vec![process_display(1, "init"), process_display(2, "bash")];
+        /* the processes bb starts with aren't new */
+        processes[0].cpu_percent_raw = None;
+        ages.record(&processes, false);
+        assert_eq!((ages.color(1), ages.color(2)), (None, None));
+
+        processes.push(process_display(3, "make"));
+        processes[2].cpu_percent_raw = None;
+        processes[0].cpu_percent_raw = Some(0);
+        /* rebuilding the list from the same snapshot doesn't age anything */
+        ages.record(&processes, false);
+        assert_eq!(ages.color(3), None);
+        ages.record(&processes, true);
+        assert_eq!(ages.color(3), Some(age::NEW_FG));
+        processes[2].cpu_percent_raw = Some(0);
+        ages.record(&processes, false);
+        assert_eq!(ages.color(3), Some(age::NEW_FG));
+        ages.record(&processes, true);
+        let faded = ages.color(3).unwrap();
+        assert_ne!(faded, age::NEW_FG);
+        ages.record(&processes, true);
+        assert_eq!(ages.color(3), None);
+
+        /* exited processes are forgotten, and a PID reused by a new process starts over */
+        processes.remove(1);
+        processes[1].cpu_percent_raw = None;
+        ages.record(&processes, true);
+        assert_eq!(ages.color(2), None);
+        assert_eq!(ages.color(3), Some(age::NEW_FG));
+        processes.push(process_display(2, "bash"));
+        ages.record(&processes, true);
+        assert_eq!(ages.color(2), Some(age::NEW_FG));
+    }
+
+    #[test]
+    fn test_row_colors() {
+        let stripe = Some(Color::Byte(233));
+        let selection = Theme::default().selection();
+        let backgrounds = |cursor, hovered, stripe| {
+            (0..4)
+                .map(|i| row_colors_at(i, cursor, false, hovered, stripe, selection).1)
+                .collect::<Vec<Color>>()
+        };
+        assert_eq!(
+            backgrounds(5, None, stripe),
+            [
+                Color::Default,
+                Color::Byte(233),
+                Color::Default,
+                Color::Byte(233)
+            ]
+        );
+        assert_eq!(backgrounds(5, None, None), [Color::Default; 4]);
+        /* the cursor and the hovered row aren't striped */
+        assert_eq!(
+            backgrounds(1, Some(3), stripe),
+            [Color::Default, Color::Byte(235), Color::Default, HOVER_BG]
+        );
+        assert_eq!(
+            row_colors_at(3, 1, true, None, stripe, selection),
+            (Color::Red, Color::Yellow)
+        );
+        /* selecting a row with a light highlight gives it dark text, and vice versa */
+        for &(bg, fg) in &[
+            (Color::Byte(254), Color::Black),
+            (Color::Byte(17), Color::White),
+        ] {
+            let theme = Theme {
+                selected_bg: bg,
+                ..Theme::default()
+            };
+            assert_eq!(
+                row_colors_at(1, 1, false, None, stripe, theme.selection()),
+                (fg, bg)
+            );
+        }
+    }
+
+    #[test]
+    fn test_scrollbar_thumb() {
+        /* lists that fit have no scrollbar */
+        assert_eq!(scrollbar_thumb(10, 0, 10, 10), None);
+        assert_eq!(scrollbar_thumb(10, 0, 10, 3), None);
+        assert_eq!(scrollbar_thumb(0, 0, 10, 100), None);
+
+        /* a tenth of the list fits, so the thumb is a tenth of the track */
+        assert_eq!(scrollbar_thumb(10, 0, 10, 100), Some((0, 1)));
+        assert_eq!(scrollbar_thumb(10, 45, 10, 100), Some((5, 1)));
+        assert_eq!(scrollbar_thumb(10, 90, 10, 100), Some((9, 1)));
+        /* past the end of the list */
+        assert_eq!(scrollbar_thumb(10, 200, 10, 100), Some((9, 1)));
+        /* only the first and last offsets reach the ends of the track */
+        assert_eq!(scrollbar_thumb(10, 1, 10, 100), Some((1, 1)));
+        assert_eq!(scrollbar_thumb(10, 89, 10, 100), Some((8, 1)));
+
+        assert_eq!(scrollbar_thumb(10, 0, 10, 20), Some((0, 5)));
+        assert_eq!(scrollbar_thumb(10, 5, 10, 20), Some((3, 5)));
+        assert_eq!(scrollbar_thumb(10, 10, 10, 20), Some((5, 5)));
+        assert_eq!(scrollbar_thumb(10, 0, 10, 11), Some((0, 9)));
+        assert_eq!(scrollbar_thumb(10, 1, 10, 11), Some((1, 9)));
+
+        /* the thumb is at least a cell long */
+        assert_eq!(scrollbar_thumb(20, 0, 20, 100_000), Some((0, 1)));
+        assert_eq!(scrollbar_thumb(20, 50_000, 20, 100_000), Some((10, 1)));
+        assert_eq!(scrollbar_thumb(1, 500, 20, 1000), Some((0, 1)));
+    }
+
+    #[test]
+    fn test_kill_command() {
+        assert_eq!(
+            kill_command(15, &[SignalTarget::Process(1234)]),
+            "kill -TERM 1234"
+        );
+        assert_eq!(kill_command(0, &[SignalTarget::Process(1234)]), "kill 1234");
+        assert_eq!(
+            kill_command(9, &[SignalTarget::Group(1234)]),
+            "kill -KILL -- -1234"
+        );
+        assert_eq!(kill_command(40, &[SignalTarget::Process(7)]), "kill -40 7");
+        let pids = [1, 22, 333]
+            .iter()
+            .map(|&pid| SignalTarget::Process(pid))
+            .collect::<Vec<_>>();
+        assert_eq!(kill_command(1, &pids), "kill -HUP 1 22 333");
+    }
+
+    #[test]
+    fn test_needs_confirmation() {
+        for &(signal, destructive) in &[(19, false), (18, false), (9, true), (15, true)] {
+            assert_eq!(
+                is_destructive(signal),
+                destructive,
+                "{}",
+                signal_name(signal)
+            );
+            assert_eq!(needs_confirmation(true, signal), destructive);
+            assert!(!needs_confirmation(false, signal));
+        }
+        /* stopping from a terminal is harmless too, unlike hanging up */
+        assert!(!is_destructive(20));
+        assert!(is_destructive(1));
+        /* real-time signals are asked about, nothing is known about what they do */
+        assert!(is_destructive(40));
+    }
+
+    #[test]
+    fn test_secondary_sort() {
+        let mut processes = vec![
+            process_display(30, "c"),
+            process_display(10, "a"),
+            process_display(20, "b"),
+            process_display(40, "d"),
+        ];
+        processes[0].vm_rss_value = 3;
+        processes[1].vm_rss_value = 1;
+        processes[2].vm_rss_value = 2;
+        /* the only one that doesn't tie on cpu */
+        processes[3].cpu_percent = Some(9000);
+        let pids = |processes: &[ProcessDisplay], secondary: Option<&str>| {
+            let secondary = secondary.map(|spec| SecondarySort::parse(spec).unwrap());
+            visible_indices(processes, Sort::CpuDesc, secondary, None)
+                .into_iter()
+                .map(|i| processes[i].i)
+                .collect::<Vec<Pid>>()
+        };
+        assert_eq!(pids(&processes, Some("pid")), [40, 10, 20, 30]);
+        assert_eq!(pids(&processes, Some("pid:asc")), [40, 10, 20, 30]);
+        assert_eq!(pids(&processes, Some("pid:desc")), [40, 30, 20, 10]);
+        assert_eq!(pids(&processes, Some("memory:desc")), [40, 30, 20, 10]);
+        assert_eq!(pids(&processes, Some("command")), [40, 10, 20, 30]);
+        /* the primary key still comes first */
+        processes[3].cpu_percent = Some(0);
+        assert_eq!(pids(&processes, Some("pid")), [10, 20, 30, 40]);
+
+        assert!(SecondarySort::parse("size").is_err());
+        assert!(SecondarySort::parse("pid:up").is_err());
+        for key in SECONDARY_SORT_KEYS {
+            assert!(SecondarySort::parse(key).is_ok(), "{}", key);
+        }
+    }
+
+    #[test]
+    fn test_sort_order() {
+        let sort = |spec| SortOrder::parse(spec).map(|SortOrder(sort)| sort);
+        /* the usage keys are descending unless told otherwise, like F2 and F3 sort them */
+        assert_eq!(sort("cpu"), Ok(Sort::CpuDesc));
+        assert_eq!(sort("cpu:asc"), Ok(Sort::CpuAsc));
+        assert_eq!(sort("mem"), Ok(Sort::VmRssDesc));
+        assert_eq!(sort("memory:asc"), Ok(Sort::VmRssAsc));
+        assert_eq!(sort("time"), Ok(Sort::CpuTimeDesc));
+        assert_eq!(sort("threads:desc"), Ok(Sort::ThreadsDesc));
+        assert_eq!(sort("user"), Ok(Sort::UserAsc));
+        assert_eq!(sort("command:desc"), Ok(Sort::CmdLineDesc));
+        /* from the oldest process by default */
+        assert_eq!(sort("start"), Ok(Sort::StartAsc));
+        assert_eq!(sort("pid"), Ok(Sort::PidAsc));
+
+        let err = sort("size").unwrap_err();
+        assert!(err.contains("`size`"), "{}", err);
+        for key in SECONDARY_SORT_KEYS {
+            assert!(err.contains(key), "{}", err);
+        }
+        assert!(sort("cpu:up").is_err());
+        assert!(sort("").is_err());
+        assert!(sort(":desc").is_err());
+
+        /* the list starts in it */
+        let dir = mock_proc_root("sort-order", 3);
+        let list = ProcessList::new(ProcessListOptions {
+            proc_source: ProcSource::new(&dir).unwrap(),
+            sort: Some(SortOrder::parse("command:desc").unwrap()),
+            ..Default::default()
+        });
+        assert_eq!(list.sort, Sort::CmdLineDesc);
+        drop(list);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_sort_io() {
+        let mut processes = (1..=5)
+            .map(|pid| process_display(pid, "dd"))
+            .collect::<Vec<ProcessDisplay>>();
+        let rates = |read, write| Some(Some(IoRates { read, write }));
+        processes[0].io = rates(500, 10);
+        /* `/proc/[pid]/io` wasn't readable */
+        processes[1].io = Some(None);
+        processes[2].io = rates(0, 3000);
+        processes[3].io = Some(None);
+        processes[4].io = rates(100, 200);
+
+        let pids = |sort| {
+            visible_indices(&processes, sort, None, None)
+                .into_iter()
+                .map(|i| processes[i].i)
+                .collect::<Vec<Pid>>()
+        };
+        assert_eq!(&pids(Sort::WriteDesc)[..3], &[3, 5, 1]);
+        assert_eq!(&pids(Sort::WriteAsc)[..3], &[1, 5, 3]);
+        assert_eq!(&pids(Sort::ReadDesc)[..3], &[1, 5, 3]);
+        assert_eq!(&pids(Sort::IoDesc)[..3], &[3, 1, 5]);
+        for sort in &[Sort::WriteDesc, Sort::WriteAsc, Sort::IoAsc] {
+            let mut unknown = pids(*sort)[3..].to_vec();
+            unknown.sort();
+            assert_eq!(unknown, vec![2, 4]);
+        }
+
+        let maxima = ColumnWidthMaxima::from_processes(&processes);
+        let header = maxima.header(Sort::WriteDesc, "");
+        assert!(header.contains("READ/s  ") && header.contains("WRITE/s↓ "));
+        let header = maxima.header(Sort::IoAsc, "");
+        assert!(header.contains("READ/s↑ ") && header.contains("WRITE/s↑ "));
+    }
+
+    #[test]
+    fn test_io_notation() {
+        let mut processes = vec![process_display(1, "dd"), process_display(2, "cp")];
+        processes[0].io = Some(Some(IoRates {
+            read: 1 << 60,
+            write: 2_500_000,
+        }));
+        processes[1].io = Some(None);
+        let row = |notation| {
+            let mut maxima = ColumnWidthMaxima::from_processes(&processes);
+            maxima.fit_notation(notation, &processes);
+            (maxima.io, maxima.row(&processes[0]))
+        };
+        let (widths, row_text) = row(Notation::Engineering);
+        assert_eq!(widths, Some(("1.15 EB".len(), "2.50 MB".len())));
+        assert!(row_text.ends_with("1.15 EB  2.50 MB  "), "{:?}", row_text);
+        let (widths, row_text) = row(Notation::Scientific);
+        assert_eq!(widths, Some(("1.15e18 bytes".len(), "2.50e6 bytes".len())));
+        assert!(
+            row_text.ends_with("1.15e18 bytes  2.50e6 bytes  "),
+            "{:?}",
+            row_text
+        );
+        /* the columns of binary notation are the ones `from_processes` measured */
+        let (widths, row_text) = row(Notation::Binary);
+        assert_eq!(widths, ColumnWidthMaxima::from_processes(&processes).io);
+        assert!(row_text.ends_with("2.38 MiB  "), "{:?}", row_text);
+    }
+
+    #[test]
+    fn test_cursor_after_sort() {
+        let mut processes = vec![
+            process_display(1, "init"),
+            process_display(20, "bash"),
+            process_display(300, "vim"),
+        ];
+        processes[0].cpu_percent = Some(10);
+        processes[1].cpu_percent = Some(30);
+        processes[2].cpu_percent = Some(20);
+
+        /* `bash` was selected before sorting */
+        for sort in &[Sort::CpuAsc, Sort::CmdLineAsc] {
+            let rows = visible_indices(&processes, *sort, None, None);
+            assert_eq!(
+                cursor_after_sort(SortScroll::Top, *sort, 20, &rows, &processes),
+                Some(0)
+            );
+        }
+        let rows = visible_indices(&processes, Sort::CpuAsc, None, None);
+        assert_eq!(
+            cursor_after_sort(SortScroll::Keep, Sort::CpuAsc, 20, &rows, &processes),
+            Some(2)
+        );
+        assert_eq!(
+            cursor_after_sort(SortScroll::Auto, Sort::CpuAsc, 20, &rows, &processes),
+            Some(0)
+        );
+        let rows = visible_indices(&processes, Sort::CmdLineDesc, None, None);
+        assert_eq!(
+            cursor_after_sort(SortScroll::Keep, Sort::CmdLineDesc, 20, &rows, &processes),
+            Some(2)
+        );
+        assert_eq!(
+            cursor_after_sort(SortScroll::Auto, Sort::CmdLineDesc, 300, &rows, &processes),
+            Some(0)
+        );
+        assert_eq!(
+            cursor_after_sort(SortScroll::Auto, Sort::CmdLineDesc, 1, &rows, &processes),
+            Some(1)
+        );
+        /* the selected process is filtered out */
+        let rows = visible_indices(
+            &processes,
+            Sort::CmdLineDesc,
+            None,
+            Some(&Filter::new("i", false, false)),
+        );
+        assert_eq!(
+            cursor_after_sort(SortScroll::Keep, Sort::CmdLineDesc, 20, &rows, &processes),
+            None
+        );
+    }
+
+    #[test]
+    fn test_processes_named() {
+        let mut processes = vec![
+            process_display(1, "/sbin/init splash"),
+            process_display(20, "/usr/sbin/nginx -g daemon off;"),
+            process_display(12, "nginx-exporter --port 9113"),
+            process_display(300, "/usr/bin/python3 /usr/local/bin/nginx.py"),
+            process_display(15, "nginx"),
+            process_display(40, "bb"),
+            ProcessDisplay::gone(50),
+        ];
+        processes[6].cmd_line = CmdLineString("nginx".to_string());
+
+        assert_eq!(process_name("/usr/sbin/nginx -g daemon off;"), "nginx");
+        assert_eq!(process_name("nginx"), "nginx");
+        assert_eq!(process_name(""), "");
+        /* only the executable's name is matched, not its directory or arguments */
+        assert_eq!(
+            processes_named(&processes, "nginx", false, 40),
+            vec![12, 15, 20]
+        );
+        assert_eq!(processes_named(&processes, "nginx", true, 40), vec![15, 20]);
+        assert_eq!(
+            processes_named(&processes, "gin", false, 40),
+            vec![12, 15, 20]
+        );
+        assert_eq!(processes_named(&processes, "gin", true, 40), vec![]);
+        assert_eq!(processes_named(&processes, "sbin", false, 40), vec![]);
+        assert_eq!(processes_named(&processes, "Nginx", false, 40), vec![]);
+        /* bb doesn't signal itself */
+        assert_eq!(processes_named(&processes, "bb", true, 40), vec![]);
+        assert_eq!(processes_named(&processes, "bb", true, 0), vec![40]);
+        assert_eq!(processes_named(&processes, "python3", true, 40), vec![300]);
+    }
+
+    #[test]
+    fn test_zombie() {
+        let dir = std::env::temp_dir().join(format!("bb-test-zombie-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let stat = |pid, state| {
+            format!(
+                "{} (my worker) {} 41 41 41 0 -1 4227084 52 0 0 0 7 3 0 0 20 0 1 0 12345 0 0 \
+                 18446744073709551615 0 0 0 0 0 0 0 0 0 0 0 0 17 2 0 0 0 0 0 0 0 0 0 0 0 0 0\n",
+                pid, state
+            )
+        };
+        let write = |status: &str, stat: &str, cmdline: &str| {
+            std::fs::write(dir.join("status"), status).unwrap();
+            std::fs::write(dir.join("stat"), stat).unwrap();
+            std::fs::write(dir.join("cmdline"), cmdline).unwrap();
+        };
+
+        /* no VmRSS and no command line */
+        write(
+            "Name:\tmy worker\nUmask:\t0022\nState:\tZ (zombie)\nTgid:\t42\nPid:\t42\n\
+             PPid:\t41\nUid:\t1000\t1000\t1000\t1000\nThreads:\t1\n",
+            &stat(42, 'Z'),
+            "",
+        );
+        let p = get_pid_info(dir.clone(), ExtraReads::default()).unwrap();
+        assert!(p.state.is_zombie());
+        assert_eq!((p.pid, p.ppid, p.vm_rss), (42, 41, 0));
+        assert_eq!(p.cmd_line, "[my worker] <defunct>");
+        assert_eq!(p.start_time, 12345);
+
+        /* kernel threads still have no command line */
+        write(
+            "Name:\tkworker\nState:\tI (idle)\nPid:\t43\nPPid:\t2\nUid:\t0\t0\t0\t0\n",
+            &stat(43, 'I'),
+            "",
+        );
+        assert!(get_pid_info(dir.clone(), ExtraReads::default()).is_err());
+        write(
+            "Name:\tmy worker\nState:\tS (sleeping)\nPid:\t44\nPPid:\t41\n\
+             Uid:\t1000\t1000\t1000\t1000\nVmRSS:\t  1024 kB\n",
+            &stat(44, 'S'),
+            "worker\0--id\0two\0",
+        );
+        let p = get_pid_info(dir.clone(), ExtraReads::default()).unwrap();
+        assert!(!p.state.is_zombie());
+        assert_eq!((p.vm_rss, p.cmd_line.as_str()), (1024, "worker --id two "));
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(State::from('Z').is_zombie());
+        assert!(!State::from('S').is_zombie());
+    }
+
+    #[test]
+    fn test_invalid_utf8() {
+        let dir = std::env::temp_dir().join(format!("bb-test-utf8-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("status"),
+            b"Name:\tcaf\xe9\nState:\tS (sleeping)\nPid:\t7\nPPid:\t1\nUid:\t0\t0\t0\t0\n\
+              VmRSS:\t  8 kB\n"
+                .as_ref(),
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("stat"),
+            b"7 (caf\xe9) S 1 7 7 0 -1 0 0 0 0 0 1 2 0 0 20 0 1 0 99 0 0\n".as_ref(),
+        )
+        .unwrap();
+        /* a Latin-1 é and a truncated 3-byte sequence */
+        std::fs::write(
+            dir.join("cmdline"),
+            b"/opt/caf\xe9\0--name\0\xe2\x82\0".as_ref(),
+        )
+        .unwrap();
+
+        let mut buf = "stale".to_string();
+        read_proc_file(&dir.join("cmdline"), &mut buf).unwrap();
+        assert_eq!(buf, "/opt/caf\u{fffd}\0--name\0\u{fffd}\0");
+        read_proc_file(&dir.join("stat"), &mut buf).unwrap();
+        assert!(buf.starts_with("7 (caf\u{fffd}) S"));
+
+        let p = get_pid_info(dir.clone(), ExtraReads::default()).unwrap();
+        assert_eq!((p.pid, p.ppid, p.vm_rss), (7, 1, 8));
+        assert_eq!(p.cmd_line, "/opt/caf\u{fffd} --name \u{fffd} ");
+        /* each replacement character takes a column, like any other character here */
+        assert_eq!(p.cmd_line.grapheme_width(), p.cmd_line.chars().count());
+        assert_eq!(truncate(&p.cmd_line, 9, Truncation::End), "/opt/caf…");
+        assert!(read_proc_file(&dir.join("nonexistent"), &mut buf).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_threads() {
+        let dir = std::env::temp_dir().join(format!("bb-test-threads-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("status"),
+            "Name:\tfirefox\nState:\tS (sleeping)\nPid:\t9\nPPid:\t1\nUid:\t0\t0\t0\t0\n\
+             VmRSS:\t  8 kB\nRssAnon:\t  4 kB\nThreads:\t137\nSigQ:\t0/63432\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("stat"),
+            "9 (firefox) S 1 9 9 0 -1 0 0 0 0 0 1 2 0 0 20 0 137 0 99 0 0\n",
+        )
+        .unwrap();
+        std::fs::write(dir.join("cmdline"), "firefox\0").unwrap();
+        let p = get_pid_info(dir.clone(), ExtraReads::default()).unwrap();
+        assert_eq!((p.pid, p.threads), (9, Some(137)));
+
+        /* zombies have no VmRSS line, which is the last one read otherwise */
+        std::fs::write(
+            dir.join("status"),
+            "Name:\tsh\nState:\tZ (zombie)\nPid:\t9\nPPid:\t1\nUid:\t0\t0\t0\t0\n\
+             Threads:\t1\n",
+        )
+        .unwrap();
+        let p = get_pid_info(dir.clone(), ExtraReads::default()).unwrap();
+        assert_eq!((p.state, p.threads), (State::Zombie, Some(1)));
+
+        std::fs::write(
+            dir.join("status"),
+            "Name:\tsh\nState:\tS (sleeping)\nPid:\t9\nPPid:\t1\nUid:\t0\t0\t0\t0\n\
+             VmRSS:\t  8 kB\nThreads:\tmany\n",
+        )
+        .unwrap();
+        assert_eq!(
+            get_pid_info(dir.clone(), ExtraReads::default())
+                .unwrap_err()
+                .kind(),
+            std::io::ErrorKind::InvalidData
+        );
+
+        /* an unreadable status skips the process */
+        std::fs::remove_file(dir.join("status")).unwrap();
+        assert!(get_pid_info(dir.clone(), ExtraReads::default()).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let mut processes = vec![
+            process_display(1, "init"),
+            process_display(2, "firefox"),
+            process_display(3, "sh"),
+        ];
+        processes[0].threads = Some(1);
+        processes[1].threads = Some(137);
+        let pids = |sort| {
+            visible_processes(&processes, sort, None, None)
+                .iter()
+                .map(|p| p.i)
+                .collect::<Vec<Pid>>()
+        };
+        assert_eq!(pids(Sort::ThreadsDesc), [2, 1, 3]);
+        assert_eq!(pids(Sort::ThreadsAsc), [1, 2, 3]);
+        let maxima = ColumnWidthMaxima::from_processes(&processes);
+        assert_eq!(maxima.threads, Some("THREADS".len()));
+        assert!(maxima.header(Sort::ThreadsDesc, "").ends_with("THREADS↓ "));
+        assert!(maxima.row(&processes[1]).ends_with("    137  "));
+        assert!(maxima.row(&processes[2]).ends_with("      -  "));
+    }
+
+    #[test]
+    fn test_caps() {
+        let dir = std::env::temp_dir().join(format!("bb-test-caps-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("status"),
+            "Name:\tping\nState:\tS (sleeping)\nPid:\t9\nPPid:\t1\nUid:\t0\t0\t0\t0\n\
+             VmRSS:\t  8 kB\nThreads:\t1\nCapInh:\t0000000000000000\n\
+             CapPrm:\t0000000000002000\nCapEff:\t0000000000002000\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("stat"),
+            "9 (ping) S 1 9 9 0 -1 0 0 0 0 0 1 2 0 0 20 0 1 0 99 0 0\n",
+        )
+        .unwrap();
+        std::fs::write(dir.join("cmdline"), "ping\0").unwrap();
+        let p = get_pid_info(dir.clone(), ExtraReads::default()).unwrap();
+        assert_eq!((p.threads, p.cap_eff), (Some(1), Some(0x2000)));
+        /* zombies keep their capabilities line */
+        std::fs::write(
+            dir.join("status"),
+            "Name:\tsh\nState:\tZ (zombie)\nPid:\t9\nPPid:\t1\nUid:\t0\t0\t0\t0\n\
+             Threads:\t1\nCapEff:\t000001ffffffffff\n",
+        )
+        .unwrap();
+        let p = get_pid_info(dir.clone(), ExtraReads::default()).unwrap();
+        assert_eq!(p.cap_eff, Some(0x1ff_ffff_ffff));
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let mut processes = vec![
+            process_display(1, "init"),
+            process_display(2, "ping"),
+            process_display(3, "sh"),
+        ];
+        processes[0].caps = Some(0x1ff_ffff_ffff);
+        processes[1].caps = Some(0x2000);
+        processes[2].caps = Some(0);
+        let maxima = ColumnWidthMaxima::from_processes(&processes);
+        assert_eq!(maxima.caps, Some("NET_RAW".len()));
+        assert!(maxima.header(Sort::CpuDesc, "").ends_with("CAPS     "));
+        assert!(maxima.row(&processes[0]).ends_with("  ALL      "));
+        assert!(maxima.row(&processes[1]).ends_with("  NET_RAW  "));
+        assert!(maxima.row(&processes[2]).ends_with("  S           "));
+        /* without the column */
+        processes.iter_mut().for_each(|p| p.caps = None);
+        assert_eq!(ColumnWidthMaxima::from_processes(&processes).caps, None);
+    }
+
+    #[test]
+    fn test_wide_column_values() {
+        let mut processes = vec![process_display(1, "init"), process_display(2, "サーバー")];
+        processes[1].username = UserString("用户".to_string());
+        processes[0].cgroup = Some(CgroupString("init.scope".to_string()));
+        processes[1].cgroup = Some(CgroupString("容器.scope".to_string()));
+        let maxima = ColumnWidthMaxima::from_processes(&processes);
+        assert_eq!((maxima.username, maxima.cgroup), (4, Some(10)));
+        /* the wide values take as many columns as the narrow ones, so the separators that follow
+         * them line up */
+        let rows = processes
+            .iter()
+            .map(|p| maxima.row(p))
+            .collect::<Vec<String>>();
+        assert_eq!(rows[0].grapheme_width(), rows[1].grapheme_width());
+        assert!(rows[1].contains("  用户  "));
+        assert!(rows[1].ends_with("  容器.scope  "));
+        assert_eq!(
+            rows[0]
+                .find("init.scope")
+                .map(|i| rows[0][..i].grapheme_width()),
+            rows[1]
+                .find("容器.scope")
+                .map(|i| rows[1][..i].grapheme_width())
+        );
+    }
+
+    #[test]
+    fn test_env_column() {
+        let dir = std::env::temp_dir().join(format!("bb-test-env-column-{}", std::process::id()));
+        for (pid, environ) in &[(1, "RUST_LOG=bb=debug,info\0"), (2, "HOME=/\0")] {
+            std::fs::create_dir_all(dir.join(pid.to_string())).unwrap();
+            std::fs::write(dir.join(format!("{}/environ", pid)), environ).unwrap();
+        }
+        let mut processes = vec![
+            process_display(1, "bb"),
+            process_display(2, "sh"),
+            process_display(3, "sshd"),
+        ];
+        let rows = vec![0, 1, 2];
+        let mut env_column = EnvColumn::new("RUST_LOG".to_string(), ProcSource::fixture(&dir));
+        format_window(
+            &mut processes,
+            &rows,
+            0,
+            3,
+            BYTES,
+            Some(&mut env_column),
+            None,
+        );
+        assert_eq!(
+            processes
+                .iter()
+                .map(|p| p.env.as_deref())
+                .collect::<Vec<Option<&str>>>(),
+            vec![Some("bb=debug,info"), Some(""), Some("-")]
+        );
+        let mut maxima = ColumnWidthMaxima::from_processes(&processes);
+        maxima.fit_env(env_column.key());
+        assert_eq!(maxima.env, Some(("RUST_LOG".to_string(), 13)));
+        assert!(maxima
+            .header(Sort::CpuDesc, "")
+            .ends_with("RUST_LOG       "));
+        assert!(maxima.row(&processes[0]).ends_with("  bb=debug,info  "));
+        assert!(maxima.row(&processes[2]).ends_with("  -              "));
+        /* the name is wider than the values */
+        processes[0].env = Some("warn".to_string());
+        let mut maxima = ColumnWidthMaxima::from_processes(&processes);
+        maxima.fit_env(env_column.key());
+        assert_eq!(maxima.env, Some(("RUST_LOG".to_string(), "RUST_LOG".len())));
+        /* without the column */
+        processes.iter_mut().for_each(|p| p.env = None);
+        assert_eq!(ColumnWidthMaxima::from_processes(&processes).env, None);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_net_column() {
+        let dir = std::env::temp_dir().join(format!("bb-test-net-column-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("net")).unwrap();
+        std::fs::write(
+            dir.join("net/tcp6"),
+            "  sl  local_address remote_address st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode\n\
+             0: 00000000000000000000000000000000:0016 00000000000000000000000000000000:0000 0A 00000000:00000000 00:00000000 00000000     0        0 2001 1 0000000000000000 100 0 0 10 0\n",
+        )
+        .unwrap();
+        for pid in &[1, 2] {
+            std::fs::create_dir_all(dir.join(format!("{}/fd", pid))).unwrap();
+        }
+        /* the descriptors of 3 can't be read */
+        std::fs::create_dir_all(dir.join("3")).unwrap();
+        std::os::unix::fs::symlink("socket:[2001]", dir.join("1/fd/3")).unwrap();
+        let mut processes = vec![
+            process_display(1, "sshd"),
+            process_display(2, "sh"),
+            process_display(3, "cron"),
+        ];
+        let rows = vec![0, 1, 2];
+        let mut sockets = Sockets::new(ProcSource::fixture(&dir));
+        format_window(&mut processes, &rows, 0, 3, BYTES, None, Some(&mut sockets));
+        assert_eq!(
+            processes.iter().map(|p| p.net).collect::<Vec<_>>(),
+            vec![
+                Some(NetState::Listening),
+                Some(NetState::Idle),
+                Some(NetState::Unreadable)
+            ]
+        );
+        let mut maxima = ColumnWidthMaxima::from_processes(&processes);
+        assert!(!maxima.row(&processes[0]).contains("listen"));
+        maxima.net = true;
+        assert!(maxima.header(Sort::CpuDesc, "").ends_with("NET     "));
+        assert!(maxima.row(&processes[0]).ends_with("  listen  "));
+        assert!(maxima.row(&processes[2]).ends_with("  -       "));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_cpu_time() {
+        let mut processes = vec![
+            process_display(1, "init"),
+            process_display(2, "firefox"),
+            process_display(3, "sh"),
+        ];
+        let ticks = clock_ticks();
+        processes[0].cpu_time = Some(3 * ticks);
+        processes[1].cpu_time = Some(2 * 3600 * ticks);
+        let pids = |sort| {
+            visible_processes(&processes, sort, None, None)
+                .iter()
+                .map(|p| p.i)
+                .collect::<Vec<Pid>>()
+        };
+        assert_eq!(pids(Sort::CpuTimeDesc), [2, 1, 3]);
+        assert_eq!(pids(Sort::CpuTimeAsc), [1, 2, 3]);
+        let maxima = ColumnWidthMaxima::from_processes(&processes);
+        assert_eq!(maxima.cpu_time, Some("120:00.00".len()));
+        assert!(maxima.header(Sort::CpuTimeAsc, "").ends_with("    TIME+↑ "));
+        assert!(maxima.row(&processes[0]).ends_with("  0:03.00  "));
+        assert!(maxima.row(&processes[1]).ends_with("120:00.00  "));
+        assert!(maxima.row(&processes[2]).ends_with("        -  "));
+        assert_eq!(
+            ColumnWidthMaxima::from_processes(&processes[2..]).cpu_time,
+            None
+        );
+    }
+
+    #[test]
+    fn test_io_files() {
+        let dir = std::env::temp_dir().join(format!("bb-test-io-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("status"),
+            "Name:\tcp\nState:\tD (disk sleep)\nPid:\t9\nPPid:\t1\nUid:\t0\t0\t0\t0\n\
+             VmRSS:\t  8 kB\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("stat"),
+            "9 (cp) D 1 9 9 0 -1 0 0 0 0 0 1 2 0 0 20 0 1 0 99 0 0\n",
+        )
+        .unwrap();
+        std::fs::write(dir.join("cmdline"), "cp\0a\0b\0").unwrap();
+        let reads = ExtraReads {
+            io: true,
+            ..ExtraReads::default()
+        };
+
+        /* a missing or unreadable io doesn't drop the process */
+        let p = get_pid_info(dir.clone(), reads).unwrap();
+        assert_eq!((p.pid, p.io), (9, None));
+        std::fs::create_dir(dir.join("io")).unwrap();
+        let p = get_pid_info(dir.clone(), reads).unwrap();
+        assert_eq!((p.pid, p.io), (9, None));
+        std::fs::remove_dir(dir.join("io")).unwrap();
+
+        std::fs::write(dir.join("io"), "read_bytes: 512\nwrite_bytes: 1024\n").unwrap();
+        let p = get_pid_info(dir.clone(), reads).unwrap();
+        assert_eq!(
+            p.io,
+            Some(IoCounters {
+                read_bytes: 512,
+                write_bytes: 1024
+            })
+        );
+        /* not read unless asked for */
+        let p = get_pid_info(dir.clone(), ExtraReads::default()).unwrap();
+        assert_eq!(p.io, None);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        /* processes without rates show - in the columns */
+        let mut processes = vec![process_display(1, "/sbin/init"), process_display(2, "cp")];
+        processes[0].io = Some(None);
+        processes[1].io = Some(Some(IoRates {
+            read: 2048,
+            write: 0,
+        }));
+        let maxima = ColumnWidthMaxima::from_processes(&processes);
+        assert_eq!(maxima.io, Some(("2.00 KiB".len(), "WRITE/s".len())));
+        assert!(maxima
+            .header(Sort::CpuDesc, "")
+            .ends_with("    READ/s  WRITE/s  "));
+        assert!(maxima.row(&processes[0]).ends_with("         -        -  "));
+        assert!(maxima.row(&processes[1]).ends_with("  2.00 KiB        0  "));
+    }
+
+    #[test]
+    fn test_baseline_columns() {
+        let filter = PidFilter::default();
+        let mut data = usage_data(2);
+        let processes = get(
+            &mut data,
+            &mock_snapshot(3),
+            None,
+            Sort::CpuDesc,
+            None,
+            &filter,
+            false,
+        );
+        assert!(processes.iter().all(|p| p.delta.is_none()));
+        assert_eq!(ColumnWidthMaxima::from_processes(&processes).delta, None);
+
+        let mut baseline = mock_snapshot(2);
+        baseline.processes[1].vm_rss = 300;
+        data.baseline = Some(Baseline::pin(&baseline));
+        let mut processes = get(
+            &mut data,
+            &mock_snapshot(3),
+            None,
+            Sort::CmdLineAsc,
+            None,
+            &filter,
+            false,
+        );
+        let deltas = processes.iter().map(|p| p.delta).collect::<Vec<_>>();
+        assert_eq!(
+            deltas,
+            [
+                Some(Delta::Changed {
+                    cpu_ticks: 0,
+                    vm_rss: 0
+                }),
+                Some(Delta::Changed {
+                    cpu_ticks: 0,
+                    vm_rss: -200
+                }),
+                Some(Delta::New)
+            ]
+        );
+        let maxima = ColumnWidthMaxima::from_processes(&processes);
+        assert!(maxima
+            .header(Sort::CpuDesc, "")
+            .ends_with("  +CPU         +MEM  "));
+        processes[1].format(MemFormat::Bytes, 0);
+        processes[2].format(MemFormat::Bytes, 0);
+        assert!(maxima
+            .row(&processes[1])
+            .ends_with("  +0.00s  -200.00 KiB  "));
+        assert!(maxima.row(&processes[2]).ends_with("   new          new  "));
+    }
+
+    /* A procfs with init and `workers` children of it, named worker2 and on */
+    pub(super) fn mock_proc_root(name: &str, workers: usize) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("bb-test-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("stat"),
+            "cpu  10 0 5 100 1 0 0 0 0 0\ncpu0 10 0 5 100 1 0 0 0 0 0\nbtime 1600000000\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("meminfo"),
+            "MemTotal: 16384 kB\nMemFree: 4096 kB\n",
+        )
+        .unwrap();
+        for pid in 1..=workers as Pid + 1 {
+            if pid == 1 {
+                mock_proc_entry(&dir, pid, "init", 0);
+            } else {
+                mock_proc_entry(&dir, pid, &format!("worker{}", pid), 1);
+            }
+        }
+        dir
+    }
+
+    /* Add a sleeping process to the procfs at `dir` */
+    fn mock_proc_entry(dir: &Path, pid: Pid, name: &str, ppid: Pid) {
+        let proc_dir = dir.join(pid.to_string());
+        std::fs::create_dir_all(&proc_dir).unwrap();
+        std::fs::write(
+            proc_dir.join("status"),
+            format!(
+                "Name:\t{}\nState:\tS (sleeping)\nPid:\t{}\nPPid:\t{}\n\
+                 Uid:\t0\t0\t0\t0\nVmRSS:\t  512 kB\n",
+                name, pid, ppid
+            ),
+        )
+        .unwrap();
+        std::fs::write(
+            proc_dir.join("stat"),
+            format!(
+                "{} ({}) S {} {} {} 0 -1 4227084 52 0 0 0 7 3 0 0 20 0 1 0 12345 0 0 \
+                 18446744073709551615 0 0 0 0 0 0 0 0 0 0 0 0 17 2 0 0 0 0 0 0 0 0 0 0 0 0 0\n",
+                pid, name, ppid, pid, pid
+            ),
+        )
+        .unwrap();
+        std::fs::write(proc_dir.join("cmdline"), format!("{}\0", name)).unwrap();
+    }
+
+    /* The rows of a 60x12 grid after drawing `list` on all of it */
+    fn draw_list(list: &mut ProcessList) -> Vec<String> {
+        grid_rows(&render(list, (60, 12)))
+    }
+
+    #[test]
+    fn test_empty_state() {
+        let dir = mock_proc_root("empty-state", 0);
+        let mut list = ProcessList::new(ProcessListOptions {
+            proc_source: ProcSource::new(&dir).unwrap(),
+            ..Default::default()
+        });
+        let has_empty_state = |rows: &[String]| rows.iter().any(|row| row.contains(EMPTY_STATE));
+
+        let rows = draw_list(&mut list);
+        assert!(rows[2].contains("init"), "{:?}", rows);
+        assert!(!has_empty_state(&rows));
+
+        list.filter_term = Some("nothing matches this".to_string());
+        let rows = draw_list(&mut list);
+        assert!(!rows.iter().any(|row| row.contains("init")));
+        /* centered on the rows below the header */
+        assert_eq!(rows[6], format!("{:^60}", EMPTY_STATE));
+
+        list.filter_term = None;
+        assert!(!has_empty_state(&draw_list(&mut list)));
+        drop(list);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_header_toggle() {
+        assert_eq!((first_row_offset(true), first_row_offset(false)), (2, 1));
+
+        let dir = mock_proc_root("header", 20);
+        let mut list = ProcessList::new(ProcessListOptions {
+            proc_source: ProcSource::new(&dir).unwrap(),
+            ..Default::default()
+        });
+        let is_process = |row: &String| row.contains("init") || row.contains("worker");
+        let is_header = |row: &String| row.contains("PID") && row.contains("CMD_LINE");
+
+        let rows = draw_list(&mut list);
+        assert!(is_header(&rows[1]), "{:?}", rows);
+        assert_eq!(rows.iter().filter(|row| is_process(row)).count(), 9);
+        assert!(is_process(&rows[2]) && is_process(&rows[10]));
+        assert_eq!(list.rows_area.as_ref().unwrap().row_at((5, 2)), Some(0));
+        /* scrolling a page and to the end keeps the headers above the rows */
+        list.page_movement = Some(PageMovement::PageDown);
+        let rows = draw_list(&mut list);
+        assert!(is_header(&rows[1]) && is_process(&rows[2]), "{:?}", rows);
+        assert_eq!(list.rows_area.as_ref().unwrap().row_at((5, 2)), Some(9));
+        list.page_movement = Some(PageMovement::End);
+        let rows = draw_list(&mut list);
+        assert!(is_header(&rows[1]), "{:?}", rows);
+        assert_eq!(rows.iter().filter(|row| is_process(row)).count(), 3);
+
+        /* without them the rows start a row higher, and a page has one more */
+        list.process_event(&mut UIEvent::Input(Key::Char('H')));
+        list.page_movement = Some(PageMovement::Home);
+        let rows = draw_list(&mut list);
+        assert!(!rows.iter().any(is_header), "{:?}", rows);
+        assert!(is_process(&rows[1]) && is_process(&rows[10]));
+        assert_eq!(rows.iter().filter(|row| is_process(row)).count(), 10);
+        assert_eq!(list.rows_area.as_ref().unwrap().row_at((5, 1)), Some(0));
+        list.page_movement = Some(PageMovement::PageDown);
+        draw_list(&mut list);
+        assert_eq!(list.rows_area.as_ref().unwrap().row_at((5, 1)), Some(10));
+
+        list.process_event(&mut UIEvent::Input(Key::Char('H')));
+        assert!(is_header(&draw_list(&mut list)[1]));
+        drop(list);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_threshold_colors() {
+        /* every process uses 512 of the 16384 kB, 3.125% */
+        let dir = mock_proc_root("threshold-colors", 2);
+        let theme = Theme::default();
+        let mem_color = |limits: [f64; 2]| {
+            let mut list = ProcessList::new(ProcessListOptions {
+                proc_source: ProcSource::new(&dir).unwrap(),
+                cpu_thresholds: Some(theme.thresholds([50.0, 80.0]).unwrap()),
+                mem_thresholds: Some(theme.thresholds(limits).unwrap()),
+                ..Default::default()
+            });
+            let grid = render(&mut list, (60, 12));
+            let row = &grid_rows(&grid)[3];
+            let (mem, cpu) = (row.find("512").unwrap(), row.find("--").unwrap());
+            /* no usage yet in the first snapshot, so the cpu column isn't colored */
+            assert_eq!(grid[(cpu, 3)].fg(), Color::Default, "{}", row);
+            assert_eq!(grid[(0, 3)].fg(), Color::Default);
+            grid[(mem, 3)].fg()
+        };
+        assert_eq!(mem_color([5.0, 10.0]), Color::Green);
+        assert_eq!(mem_color([3.0, 10.0]), Color::Yellow);
+        assert_eq!(mem_color([1.0, 3.125]), Color::Red);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_read_only() {
+        let dir = mock_proc_root("read-only", 5);
+        let mut list = ProcessList::new(ProcessListOptions {
+            proc_source: ProcSource::new(&dir).unwrap(),
+            read_only: true,
+            ..Default::default()
+        });
+        let (map, disabled) = list.dispatch_map();
+        assert!(PROCESS_ACTIONS
+            .iter()
+            .all(|action| disabled.contains_key(action) && !map.contains_key(action)));
+        assert_eq!(map.len() + disabled.len(), list.get_shortcuts()[""].len());
+        draw_list(&mut list);
+
+        /* the kill menus don't open, and say why */
+        for key in &['k', 'K', 'g'] {
+            list.process_event(&mut UIEvent::Input(Key::Char(*key)));
+            assert_eq!(list.mode, Normal);
+            assert_eq!(
+                list.status_message.as_deref(),
+                Some("read-only: processes can't be signalled")
+            );
+        }
+        assert!(!list.freeze);
+        list.process_event(&mut UIEvent::Input(Key::Char('x')));
+        assert_eq!(
+            list.status_message.as_deref(),
+            Some("read-only: no hook command is run")
+        );
+
+        /* but they can be typed into a filter or a search */
+        list.status_message = None;
+        list.process_event(&mut UIEvent::Input(Key::Char(' ')));
+        for c in "kwogx".chars() {
+            list.process_event(&mut UIEvent::Input(Key::Char(c)));
+        }
+        assert_eq!(list.filter_term.as_deref(), Some("kwogx"));
+        assert!(list.status_message.is_none());
+        list.process_event(&mut UIEvent::Input(Key::Esc));
+        assert_eq!(list.filter_term, None);
+        list.process_event(&mut UIEvent::Input(Key::Char('/')));
+        list.process_event(&mut UIEvent::Input(Key::Char('k')));
+        assert_eq!(list.mode, Search("k".to_string()));
+        list.process_event(&mut UIEvent::Input(Key::Esc));
+        assert_eq!(list.mode, Normal);
+
+        /* moving around and the other shortcuts still work */
+        list.process_event(&mut UIEvent::Input(Key::Down));
+        draw_list(&mut list);
+        assert_eq!(list.cursor, 1);
+        assert!(list.status_message.is_none());
+        list.process_event(&mut UIEvent::Input(Key::Char('/')));
+        assert!(list.mode.is_search());
+        list.process_event(&mut UIEvent::Input(Key::Esc));
+        list.process_event(&mut UIEvent::Input(Key::Char('t')));
+        assert!(list.draw_tree);
+
+        let mut list = ProcessList::new(ProcessListOptions {
+            proc_source: ProcSource::new(&dir).unwrap(),
+            ..Default::default()
+        });
+        assert!(list.dispatch_map().1.is_empty());
+        draw_list(&mut list);
+        list.process_event(&mut UIEvent::Input(Key::Char('k')));
+        assert_eq!(list.mode, Kill(0));
+        drop(list);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_takes_text_input() {
+        let dir = mock_proc_root("text-input", 1);
+        let mut list = ProcessList::new(ProcessListOptions {
+            proc_source: ProcSource::new(&dir).unwrap(),
+            ..Default::default()
+        });
+        draw_list(&mut list);
+        let type_keys = |list: &mut ProcessList, keys: &str| {
+            for c in keys.chars() {
+                list.process_event(&mut UIEvent::Input(Key::Char(c)));
+            }
+        };
+        assert!(!list.takes_text_input());
+
+        /* the name of the processes to signal, eg. one with a q */
+        type_keys(&mut list, "Kqemu");
+        assert_eq!(list.mode, KillName("qemu".to_string()));
+        assert!(list.takes_text_input());
+        list.process_event(&mut UIEvent::Input(Key::Esc));
+        assert!(!list.takes_text_input());
+
+        /* a filter, only while it's typed into */
+        type_keys(&mut list, " q");
+        assert_eq!(list.filter_term.as_deref(), Some("q"));
+        assert!(list.takes_text_input());
+        list.process_event(&mut UIEvent::Input(Key::Esc));
+        assert!(!list.takes_text_input());
+
+        type_keys(&mut list, "/q");
+        assert_eq!(list.mode, Search("q".to_string()));
+        assert!(list.takes_text_input());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_pinned_rows() {
+        let dir = mock_proc_root("pins", 5);
+        let mut list = ProcessList::new(ProcessListOptions {
+            proc_source: ProcSource::new(&dir).unwrap(),
+            sort: Some(SortOrder(Sort::PidAsc)),
+            pins: vec![Pin::parse("5").unwrap(), Pin::parse("worker3").unwrap()],
+            ..Default::default()
+        });
+        let rows = draw_list(&mut list);
+        /* pinned rows come first in the sort order, marked */
+        assert!(rows[2].contains("^ worker3"), "{:?}", rows);
+        assert!(rows[3].contains("^ worker5"), "{:?}", rows);
+        assert!(rows[4].contains(" init"), "{:?}", rows);
+        assert!(!rows[4].contains(PIN_MARKER));
+
+        /* P pins and unpins the process under the cursor by its PID */
+        list.cursor = 2;
+        list.process_event(&mut UIEvent::Input(Key::Char('P')));
+        assert_eq!(list.status_message.as_deref(), Some("pinned PID 1"));
+        let rows = draw_list(&mut list);
+        assert!(rows[2].contains("^ init"), "{:?}", rows);
+        assert!(rows[4].contains("^ worker5"), "{:?}", rows);
+        list.cursor = 0;
+        list.process_event(&mut UIEvent::Input(Key::Char('P')));
+        assert_eq!(list.status_message.as_deref(), Some("unpinned PID 1"));
+        let rows = draw_list(&mut list);
+        assert!(rows[4].contains(" init") && !rows[4].contains(PIN_MARKER));
+
+        /* the tree view keeps its order */
+        list.draw_tree = true;
+        assert!(draw_list(&mut list)[2].contains("init"));
+        drop(list);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_pins_rematch() {
+        let pids = |processes: &[ProcessDisplay], pins: &Pins| {
+            let mut rows = visible_indices(processes, Sort::PidAsc, None, None);
+            pins.hoist(&mut rows, processes);
+            rows.iter().map(|&i| processes[i].i).collect::<Vec<Pid>>()
+        };
+        let pins = Pins::new(vec![Pin::parse("sshd").unwrap(), Pin::parse("7").unwrap()]);
+        let mut processes = vec![
+            process_display(3, "/usr/bin/bash"),
+            process_display(5, "/usr/sbin/sshd -D"),
+            process_display(7, "cron"),
+            process_display(9, "sshd-session"),
+        ];
+        assert_eq!(pids(&processes, &pins), [5, 7, 3, 9]);
+        /* sshd restarted with a new PID, and 7 exited */
+        processes.retain(|p| p.i == 3 || p.i == 9);
+        processes.push(process_display(12, "sshd: /usr/sbin/sshd -D"));
+        processes.push(process_display(11, "/usr/sbin/sshd -D"));
+        assert_eq!(pids(&processes, &pins), [11, 3, 9, 12]);
+        /* nor are groups */
+        processes[2].members = 2;
+        processes[2].cmd_line = CmdLineString("sshd".to_string());
+        assert_eq!(pids(&processes, &pins), [11, 3, 9, 12]);
+
+        assert_eq!(Pin::parse("0"), Err("invalid PID `0`".to_string()));
+        assert!(Pin::parse("").is_err());
+        assert_eq!(Pin::parse("42"), Ok(Pin::Pid(42)));
+    }
+
+    #[test]
+    fn test_cycle_views() {
+        let dir = mock_proc_root("views", 5);
+        let mut list = ProcessList::new(ProcessListOptions {
+            proc_source: ProcSource::new(&dir).unwrap(),
+            threads: true,
+            views: vec![
+                ViewPreset::parse("mem,sort=memory:desc,columns=time+caps,filter=worker").unwrap(),
+                ViewPreset::parse("pids,sort=pid").unwrap(),
+            ],
+            ..Default::default()
+        });
+        let state = |list: &ProcessList| {
+            (
+                list.sort,
+                (list.data.threads, list.data.cpu_time, list.data.caps),
+                list.filter_term.clone(),
+            )
+        };
+        draw_list(&mut list);
+        let initial = state(&list);
+        assert_eq!(initial, (Sort::CpuDesc, (true, false, false), None));
+
+        /* each view sets the sort, columns and filter together */
+        list.draw_tree = true;
+        list.process_event(&mut UIEvent::Input(Key::Alt('v')));
+        assert_eq!(
+            state(&list),
+            (
+                Sort::VmRssDesc,
+                (false, true, true),
+                Some("worker".to_string())
+            )
+        );
+        assert_eq!(list.status_message.as_deref(), Some("view: mem"));
+        assert!(!list.draw_tree);
+        let rows = draw_list(&mut list);
+        assert!(
+            rows[1].contains("TIME+") && !rows[1].contains("THREADS"),
+            "{:?}",
+            rows
+        );
+        assert!(!rows.iter().any(|row| row.contains("init")), "{:?}", rows);
+
+        /* the next one keeps the columns the list started with, and clears the filter */
+        list.process_event(&mut UIEvent::Input(Key::Alt('v')));
+        assert_eq!(state(&list), (Sort::PidAsc, (true, false, false), None));
+        assert!(draw_list(&mut list).iter().any(|row| row.contains("init")));
+
+        /* and it cycles back to the initial view */
+        list.process_event(&mut UIEvent::Input(Key::Alt('v')));
+        assert_eq!(state(&list), initial);
+        assert_eq!(list.status_message.as_deref(), Some("view: default"));
+        drop(list);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_tree_glyphs() {
+        let dir = mock_proc_root("tree-glyphs", 2);
+        mock_proc_entry(&dir, 4, "child", 3);
+        /* the command line column of the process rows */
+        let tree = |tree_glyphs| {
+            let mut list = ProcessList::new(ProcessListOptions {
+                proc_source: ProcSource::new(&dir).unwrap(),
+                tree_glyphs,
+                ..Default::default()
+            });
+            list.draw_tree = true;
+            draw_list(&mut list)[2..6]
+                .iter()
+                .map(|row| {
+                    row.chars()
+                        .skip(41)
+                        .collect::<String>()
+                        .trim_end()
+                        .to_string()
+                })
+                .collect::<Vec<String>>()
+        };
+        assert_eq!(
+            tree(TreeGlyphs::default()),
+            ["└─>init", "  ├─>worker3", "  │ └─>child", "  └─>worker2"]
+        );
+        assert_eq!(
+            tree(TreeGlyphs::parse("!+=~").unwrap()),
+            ["=~>init", "  +~>worker3", "  ! =~>child", "  =~>worker2"]
+        );
+        assert_eq!(
+            tree(TreeGlyphs::ASCII),
+            ["`->init", "  |->worker3", "  | `->child", "  `->worker2"]
+        );
+        /* glyphs wider than a cell fall back to ASCII */
+        assert_eq!(
+            tree(TreeGlyphs::parse("｜++-").unwrap()),
+            tree(TreeGlyphs::ASCII)
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_auto_expand() {
+        let dir = mock_proc_root("auto-expand", 2);
+        mock_proc_entry(&dir, 4, "child", 2);
+        let mut list = ProcessList::new(ProcessListOptions {
+            proc_source: ProcSource::new(&dir).unwrap(),
+            auto_expand: true,
+            ..Default::default()
+        });
+        list.draw_tree = true;
+        let shows_child = |rows: &[String]| rows.iter().any(|row| row.contains("child"));
+        assert!(shows_child(&draw_list(&mut list)));
+
+        /* collapsing the node under the cursor by hand keeps it collapsed, worker2 is below
+         * worker3 */
+        for _ in 0..2 {
+            list.process_event(&mut UIEvent::Input(Key::Down));
+            draw_list(&mut list);
+        }
+        list.process_event(&mut UIEvent::Input(Key::Char('\n')));
+        assert!(list.collapsed.contains(&2));
+        assert!(!shows_child(&draw_list(&mut list)));
+        assert!(!shows_child(&draw_list(&mut list)));
+
+        /* until the cursor leaves and selects it again */
+        list.process_event(&mut UIEvent::Input(Key::Up));
+        assert!(!shows_child(&draw_list(&mut list)));
+        list.process_event(&mut UIEvent::Input(Key::Down));
+        let rows = draw_list(&mut list);
+        assert!(shows_child(&rows), "{:?}", rows);
+        assert!(list.collapsed.is_empty());
+
+        /* without the option, selecting a collapsed node leaves it collapsed */
+        list.auto_expand = false;
+        list.process_event(&mut UIEvent::Input(Key::Char('\n')));
+        list.process_event(&mut UIEvent::Input(Key::Up));
+        draw_list(&mut list);
+        list.process_event(&mut UIEvent::Input(Key::Down));
+        assert!(!shows_child(&draw_list(&mut list)));
+        drop(list);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_sample_time() {
+        let dir = mock_proc_root("sample-time", 1);
+        let mut list = ProcessList::new(ProcessListOptions {
+            proc_source: ProcSource::new(&dir).unwrap(),
+            sample_time: Some("at %M:%S".to_string()),
+            ..Default::default()
+        });
+        /* 2021-03-04 05:06:07 UTC */
+        list.snapshot.time = 1614834367;
+        let rows = draw_list(&mut list);
+        assert!(rows[1].contains("CMD_LINE"), "{:?}", rows);
+        assert!(rows[1]
+            .trim_end()
+            .ends_with(&format_local_time(1614834367, "at %M:%S")));
+        assert!(rows[1].trim_end().ends_with(":07"));
+
+        /* a time that doesn't fit after the headers is left out */
+        list.sample_time = Some("%F %T, %F %T".to_string());
+        let rows = draw_list(&mut list);
+        assert_eq!(
+            rows[1].trim_end().len(),
+            rows[1].find("CMD_LINE").unwrap() + 8
+        );
+        drop(list);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_summary_row() {
+        let dir = mock_proc_root("summary", 2);
+        let mut list = ProcessList::new(ProcessListOptions {
+            summary: true,
+            proc_source: ProcSource::new(&dir).unwrap(),
+            ..Default::default()
+        });
+        let rows = draw_list(&mut list);
+        assert_eq!(rows[0].trim_end(), " tasks: 3, 3 sleeping");
+        assert!(rows[1].contains("CMD_LINE"), "{:?}", rows);
+        assert!(rows[2].contains("init") || rows[2].contains("worker"));
+        drop(list);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_fuzzy_filter() {
+        let processes = vec![
+            process_display(1, "/usr/bin/cron"),
+            process_display(2, "/opt/chrome/chrome --type=renderer"),
+            process_display(3, "/usr/bin/crash"),
+            process_display(4, "bash"),
+        ];
+        let pids = |term: &str, fuzzy: bool| {
+            let filter = Filter::new(term, false, fuzzy);
+            visible_indices(&processes, Sort::CmdLineAsc, None, Some(&filter))
+                .into_iter()
+                .map(|i| processes[i].i)
+                .collect::<Vec<Pid>>()
+        };
+        assert_eq!(pids("cr", false), [3, 1]);
+        /* the best matches come first, and the ones that score the same stay sorted */
+        assert_eq!(pids("cr", true), [3, 1, 2]);
+        assert_eq!(pids("chrm", true), [2]);
+        assert_eq!(pids("chrm", false), []);
+
+        /* the matched characters are underlined, wherever the command line starts */
+        let dir = mock_proc_root("fuzzy", 2);
+        let mut list = ProcessList::new(ProcessListOptions {
+            fuzzy: true,
+            proc_source: ProcSource::new(&dir).unwrap(),
+            ..Default::default()
+        });
+        list.filter_term = Some("wkr2".to_string());
+        let mut grid = CellBuffer::new(60, 12, Cell::default());
+        list.draw(&mut grid, ((0, 0), (59, 11)), &mut VecDeque::new(), true);
+        let row = (0..60).map(|x| grid[(x, 2)].ch()).collect::<String>();
+        let start = row.find("worker2").unwrap();
+        let underlined = (start..start + 7)
+            .filter(|&x| grid[(x, 2)].attrs() == Attr::BoldUnderline)
+            .map(|x| x - start)
+            .collect::<Vec<usize>>();
+        assert_eq!(underlined, [0, 3, 5, 6]);
+        /* z switches back to substring matching */
+        list.process_event(&mut UIEvent::Input(Key::Char('z')));
+        assert!(!draw_list(&mut list)
+            .iter()
+            .any(|row| row.contains("worker")));
+        drop(list);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_parent_row() {
+        let mut processes = vec![
+            process_display(1, "/sbin/init"),
+            process_display(200, "/usr/bin/supervisor"),
+            process_display(300, "[worker] <defunct>"),
+            process_display(400, "orphan"),
+        ];
+        processes[0].p = 0;
+        processes[2].p = 200;
+        processes[2].state = State::Zombie;
+        processes[3].p = 350;
+        let rows = vec![2, 0, 3, 1];
+
+        /* the zombie on row 0 has its parent on row 3 */
+        assert_eq!(parent_row(&processes, &rows, 0), Ok(3));
+        assert_eq!(parent_row(&processes, &rows, 3), Ok(1));
+        assert_eq!(
+            parent_row(&processes, &rows, 1),
+            Err("PID 1 has no parent".to_string())
+        );
+        assert_eq!(
+            parent_row(&processes, &rows, 2),
+            Err("parent PID 350 of PID 400 isn't in the list".to_string())
+        );
+        /* the parent is filtered out */
+        assert_eq!(
+            parent_row(&processes, &[2, 0], 0),
+            Err("parent PID 200 of PID 300 isn't in the list".to_string())
+        );
+        assert!(parent_row(&processes, &[], 0).is_err());
+    }
+
+    #[test]
+    fn test_rows_area_row_at() {
+        /* 3 processes drawn from the 40th one, on rows 5..=7 of an 80 column list */
+        let rows = RowsArea::new((1, 5), (78, 30), 40, (3, 1)).unwrap();
+        assert_eq!(rows.row_at((1, 5)), Some(40));
+        assert_eq!(rows.row_at((40, 6)), Some(41));
+        assert_eq!(rows.row_at((78, 7)), Some(42));
+
+        /* Header, rows below the last process and the side borders don't preview anything */
+        assert_eq!(rows.row_at((10, 4)), None);
+        assert_eq!(rows.row_at((10, 8)), None);
+        assert_eq!(rows.row_at((0, 6)), None);
+        assert_eq!(rows.row_at((79, 6)), None);
+
+        assert!(RowsArea::new((1, 5), (78, 30), 0, (0, 1)).is_none());
+
+        /* with --two-line each process covers two rows, 5..=10 */
+        let rows = RowsArea::new((1, 5), (78, 30), 40, (3, 2)).unwrap();
+        assert_eq!(rows.row_at((1, 5)), Some(40));
+        assert_eq!(rows.row_at((1, 6)), Some(40));
+        assert_eq!(rows.row_at((1, 7)), Some(41));
+        assert_eq!(rows.row_at((78, 10)), Some(42));
+        assert_eq!(rows.row_at((1, 11)), None);
+        assert_eq!(page_row_area(((1, 5), (78, 30)), 2, 2), ((1, 9), (78, 10)));
+        assert_eq!(page_row_area(((1, 5), (78, 30)), 2, 1), ((1, 7), (78, 7)));
+    }
+
+    #[test]
+    fn test_two_line_rows() {
+        let dir = mock_proc_root("two-line", 20);
+        let mut list = ProcessList::new(ProcessListOptions {
+            proc_source: ProcSource::new(&dir).unwrap(),
+            two_line: true,
+            ..Default::default()
+        });
+        let is_metrics = |row: &String| row.contains("root") && row.contains("worker");
+        let rows = draw_list(&mut list);
+        /* the 8 rows below the header fit 4 processes, each with its command line under it */
+        assert_eq!(rows.iter().filter(|row| is_metrics(row)).count(), 4);
+        for y in &[2, 4, 6, 8] {
+            assert!(is_metrics(&rows[*y]), "{:?}", rows);
+            let name = rows[*y].split_whitespace().last().unwrap();
+            assert_eq!(rows[*y + 1].trim_end(), format!("     {}", name));
+        }
+        let area = list.rows_area.unwrap();
+        assert_eq!(
+            (
+                area.row_at((5, 2)),
+                area.row_at((5, 3)),
+                area.row_at((5, 4))
+            ),
+            (Some(0), Some(0), Some(1))
+        );
+        assert_eq!(area.row_at((5, 10)), None);
+
+        /* moving past the last process scrolls a page of whole processes */
+        for _ in 0..4 {
+            list.page_movement = Some(PageMovement::Down);
+            draw_list(&mut list);
+        }
+        assert_eq!(list.cursor, 4);
+        let rows = draw_list(&mut list);
+        assert!(is_metrics(&rows[2]) && !is_metrics(&rows[3]), "{:?}", rows);
+        assert_eq!(list.rows_area.unwrap().row_at((5, 3)), Some(4));
+        list.page_movement = Some(PageMovement::PageDown);
+        draw_list(&mut list);
+        assert_eq!(list.cursor, 8);
+        assert_eq!(list.rows_area.unwrap().row_at((5, 9)), Some(11));
+        drop(list);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_separators() {
+        let dir = mock_proc_root("separators", 20);
+        let mut list = ProcessList::new(ProcessListOptions {
+            proc_source: ProcSource::new(&dir).unwrap(),
+            separators: Some(Separators::Rows),
+            ..Default::default()
+        });
+        let is_separator = |row: &String| row.trim() == "─".repeat(58);
+        let rows = draw_list(&mut list);
+        /* the 9 rows below the header fit 5 processes and the 4 lines between them */
+        for y in 2..11 {
+            assert_eq!(is_separator(&rows[y]), y % 2 == 1, "{}: {:?}", y, rows);
+            assert_eq!(
+                rows[y].contains("worker") || rows[y].contains("init"),
+                y % 2 == 0
+            );
+        }
+        let grid = render(&mut list, (60, 12));
+        assert_eq!(grid[(5, 3)].attrs(), Attr::Dim);
+        let area = list.rows_area.unwrap();
+        assert_eq!(
+            (area.row_at((5, 3)), area.row_at((5, 4))),
+            (Some(0), Some(1))
+        );
+
+        /* the cursor moves over the process rows, not the lines */
+        list.page_movement = Some(PageMovement::Down);
+        let grid = render(&mut list, (60, 12));
+        assert_eq!(grid[(5, 4)].bg(), list.selection.1);
+        assert_eq!(grid[(5, 3)].bg(), Color::Default);
+        assert!(is_separator(&grid_rows(&grid)[3]));
+        for _ in 0..4 {
+            list.page_movement = Some(PageMovement::Down);
+            draw_list(&mut list);
+        }
+        assert_eq!(list.cursor, 5);
+        assert_eq!(list.rows_area.unwrap().row_at((5, 4)), Some(6));
+        drop(list);
+
+        /* in the tree view, only above the other roots */
+        mock_proc_entry(&dir, 30, "kthreadd", 0);
+        let mut list = ProcessList::new(ProcessListOptions {
+            proc_source: ProcSource::new(&dir).unwrap(),
+            pid_filter: PidFilter {
+                pids: vec![1, 30],
+                descendants: false,
+            },
+            separators: Some(Separators::Tree),
+            ..Default::default()
+        });
+        list.draw_tree = true;
+        let rows = draw_list(&mut list);
+        assert!(
+            rows[2].contains("init") || rows[2].contains("kthreadd"),
+            "{:?}",
+            rows
+        );
+        assert!(is_separator(&rows[3]), "{:?}", rows);
+        assert!(
+            rows[4].contains("init") || rows[4].contains("kthreadd"),
+            "{:?}",
+            rows
+        );
+        drop(list);
+        let mut list = ProcessList::new(ProcessListOptions {
+            proc_source: ProcSource::new(&dir).unwrap(),
+            separators: Some(Separators::Tree),
+            ..Default::default()
+        });
+        list.draw_tree = true;
+        let rows = draw_list(&mut list);
+        let separators = (2..11)
+            .filter(|&y| is_separator(&rows[y]))
+            .collect::<Vec<usize>>();
+        let roots = (2..11)
+            .filter(|&y| rows[y].contains("init") || rows[y].contains("kthreadd"))
+            .collect::<Vec<usize>>();
+        assert!(
+            separators.iter().all(|y| roots.contains(&(y + 1))),
+            "{:?}",
+            rows
+        );
+        /* the rows above the workers are left blank */
+        for y in (3..11).step_by(2) {
+            if rows[y + 1].contains("worker") {
+                assert!(rows[y].trim().is_empty(), "{}: {:?}", y, rows);
+            }
+        }
+        assert!(rows[2..11]
+            .iter()
+            .step_by(2)
+            .all(|row| !row.trim().is_empty()));
+        drop(list);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(feature = "nvml")]
+    #[test]
+    fn test_gpu_columns() {
+        let dir = mock_proc_root("gpu", 3);
+        let mut list = ProcessList::new(ProcessListOptions {
+            proc_source: ProcSource::new(&dir).unwrap(),
+            ..Default::default()
+        });
+        /* no GPU, no columns */
+        assert!(!draw_list(&mut list)[1].contains("GPU"));
+
+        let mut usage = HashMap::new();
+        usage.insert(
+            3,
+            GpuUsage {
+                memory: 1024 * 1024 * 1024,
+                utilization: Some(37),
+            },
+        );
+        usage.insert(
+            4,
+            GpuUsage {
+                memory: 2048 * 1024,
+                utilization: None,
+            },
+        );
+        list.gpus = Some(Gpus::with_usage(usage));
+        list.force_redraw = true;
+        let rows = draw_list(&mut list);
+        assert!(rows[1].contains("GPU_MEM  GPU%"), "{:?}", rows[1]);
+        let row = |pid: Pid| {
+            rows.iter()
+                .find(|row| row.split_whitespace().next() == Some(&pid.to_string()))
+                .unwrap_or_else(|| panic!("{}: {:?}", pid, rows))
+        };
+        assert!(row(3).contains("   1.0G   37%"), "{:?}", row(3));
+        assert!(row(4).contains("   2.0M     -"), "{:?}", row(4));
+        /* the processes that don't use a GPU are blank, not `-` */
+        let column = rows[1].find("GPU_MEM").unwrap();
+        assert!(
+            row(1)[column..column + 13].trim().is_empty(),
+            "{:?}",
+            row(1)
+        );
+        drop(list);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_header_scroll_indicators() {
+        let dir = mock_proc_root("scroll-indicators", 2);
+        let mut list = ProcessList::new(ProcessListOptions {
+            proc_source: ProcSource::new(&dir).unwrap(),
+            ..Default::default()
+        });
+        /* every column fits */
+        let rows = draw_list(&mut list);
+        assert!(rows[1].trim_end().ends_with("CMD_LINE"), "{:?}", rows[1]);
+        assert!(!rows[1].contains('›') && !rows[1].contains('‹'));
+
+        /* the header of the command line column doesn't */
+        let grid = render(&mut list, (30, 12));
+        let header = &grid_rows(&grid)[1];
+        assert!(!header.contains("CMD_LINE"), "{:?}", header);
+        assert_eq!(grid[(28, 1)].ch(), '›');
+        assert_eq!(grid[(28, 1)].bg(), Color::White);
+        /* the table starts at its first column */
+        assert_eq!(grid[(1, 1)].ch(), 'P');
+        /* and the rows aren't drawn over */
+        assert!(!grid_rows(&grid)[2..].iter().any(|row| row.contains('›')));
+        drop(list);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_with_descendants() {
+        /*
+         * 1 ─┬─ 10 ─── 100
+         *    └─ 20 ─┬─ 200
+         *           └─ 201 ─── 2010
+         */
+        let mut parents: HashMap<Pid, Vec<Pid>> = HashMap::new();
+        parents.insert(1, vec![10, 20]);
+        parents.insert(10, vec![100]);
+        parents.insert(20, vec![200, 201]);
+        parents.insert(201, vec![2010]);
+
+        let set = |pids: &[Pid]| pids.iter().cloned().collect::<HashSet<Pid>>();
+        assert_eq!(with_descendants(&[100], &parents), set(&[100]));
+        assert_eq!(
+            with_descendants(&[20], &parents),
+            set(&[20, 200, 201, 2010])
+        );
+        assert_eq!(
+            with_descendants(&[10, 201], &parents),
+            set(&[10, 100, 201, 2010])
+        );
+        /* Overlapping and unknown PIDs */
+        assert_eq!(
+            with_descendants(&[1, 20, 999], &parents),
+            set(&[1, 10, 100, 20, 200, 201, 2010, 999])
+        );
+    }
+
+    #[test]
+    fn test_is_idle() {
+        let mut p = process_display(42, "bb");
+        p.cpu_percent = Some(49);
+        assert!(is_idle(&p, 50));
+        p.cpu_percent = Some(50);
+        assert!(!is_idle(&p, 50));
+        assert!(!is_idle(&p, 0));
+        p.cpu_percent = None;
+        assert!(!is_idle(&p, 50));
+    }
+
+    #[test]
+    fn test_tombstones() {
+        let list = |pids: &[Pid]| {
+            pids.iter()
+                .map(|&pid| process_display(pid, "x"))
+                .collect::<Vec<_>>()
+        };
+        let pids = |processes: &[ProcessDisplay]| processes.iter().map(|p| p.i).collect::<Vec<_>>();
+        let mut tombstones = Tombstones::new(2);
+
+        /* 2 exited */
+        tombstones.record(list(&[1, 2, 3]), &list(&[1, 3]), true);
+        let mut processes = list(&[1, 3]);
+        tombstones.merge_into(&mut processes);
+        assert_eq!(pids(&processes), [1, 3, 2]);
+        assert_eq!(
+            visible_indices(&processes, Sort::CpuDesc, None, None).len(),
+            3
+        );
+        assert_eq!(processes[2].state, State::Dead);
+        assert_eq!(processes[2].cpu_percent, None);
+        assert!(tombstones.contains(2) && !tombstones.contains(1));
+
+        /* the list is rebuilt from the same sample, with the tombstone in it */
+        tombstones.record(processes, &list(&[1, 3]), false);
+        let mut processes = list(&[1, 3]);
+        tombstones.merge_into(&mut processes);
+        assert_eq!(pids(&processes), [1, 3, 2]);
+
+        /* 3 exited a sample later, 2 is in its second and last sample */
+        tombstones.record(processes, &list(&[1]), true);
+        let mut processes = list(&[1]);
+        tombstones.merge_into(&mut processes);
+        assert_eq!(pids(&processes), [1, 2, 3]);
+
+        /* 2 is aged out */
+        tombstones.record(processes, &list(&[1]), true);
+        let mut processes = list(&[1]);
+        tombstones.merge_into(&mut processes);
+        assert_eq!(pids(&processes), [1, 3]);
+
+        /* a new process with the PID of a tombstone replaces it */
+        tombstones.record(processes, &list(&[1, 3]), true);
+        assert!(!tombstones.contains(3));
+        let mut processes = list(&[1, 3]);
+        tombstones.merge_into(&mut processes);
+        assert_eq!(pids(&processes), [1, 3]);
+
+        /* rows of groups aren't processes */
+        let mut group = process_display(99, "x");
+        group.members = 2;
+        tombstones.record(vec![group], &list(&[1]), true);
+        assert!(!tombstones.contains(99));
+    }
+
+    #[test]
+    fn test_collapse_tree() {
+        /* 1
+         * ├─2
+         * │ ├─3
+         * │ │ └─4
+         * │ └─5
+         * └─6
+         * 7 */
+        let tree = [(0, 1), (1, 2), (2, 3), (3, 4), (2, 5), (1, 6), (0, 7)];
+        let shown = |collapsed: &HashSet<Pid>| {
+            collapse_tree(&tree, collapsed)
+                .into_iter()
+                .map(|(_, pid)| pid)
+                .collect::<Vec<Pid>>()
+        };
+        let mut collapsed = HashSet::new();
+        assert_eq!(shown(&collapsed), [1, 2, 3, 4, 5, 6, 7]);
+        toggle_collapsed(&mut collapsed, 2);
+        assert_eq!(shown(&collapsed), [1, 2, 6, 7]);
+        /* a collapsed node inside a collapsed subtree stays collapsed when its ancestor is
+         * expanded */
+        toggle_collapsed(&mut collapsed, 3);
+        assert_eq!(shown(&collapsed), [1, 2, 6, 7]);
+        toggle_collapsed(&mut collapsed, 2);
+        assert_eq!(shown(&collapsed), [1, 2, 3, 5, 6, 7]);
+        toggle_collapsed(&mut collapsed, 1);
+        assert_eq!(shown(&collapsed), [1, 7]);
+        toggle_collapsed(&mut collapsed, 1);
+        toggle_collapsed(&mut collapsed, 3);
+        assert!(collapsed.is_empty());
+        assert_eq!(shown(&collapsed), [1, 2, 3, 4, 5, 6, 7]);
+        /* collapsing a leaf hides nothing */
+        toggle_collapsed(&mut collapsed, 7);
+        assert_eq!(shown(&collapsed), [1, 2, 3, 4, 5, 6, 7]);
+    }
+
+    #[test]
+    fn test_inspector_lines() {
+        let mut p = process_display(42, "/usr/bin/python3 -m http.server 8000 ");
+        p.threads = Some(3);
+        assert_eq!(
+            inspector_lines(&p, None, 30),
+            [
+                "    PID  42",
+                " parent  1",
+                "  group  42",
+                "   user  user",
+                "  state  S",
+                "    cpu  1.5%",
+                " memory  1.00 MiB",
+                "threads  3",
+                "command  /usr/bin/python3 -m",
+                "         http.server 8000",
+            ]
+        );
+
+        /* the memory map follows the memory */
+        let lines = |memory_map: Result<MemoryMap, String>| {
+            inspector_lines(&p, Some(&memory_map), 30)[7..9].to_vec()
+        };
+        assert_eq!(
+            lines(Ok(MemoryMap {
+                rss: 1 << 20,
+                pss: 1 << 19,
+                anon: 3 << 18,
+                file: 1 << 18,
+                ..Default::default()
+            })),
+            [
+                "    rss  1.00 MiB: anon 768.00 KiB, file 256.00 KiB, shared 0",
+                "    pss  512.00 KiB, swap 0",
+            ]
+        );
+        assert_eq!(
+            lines(Err(
+                "can't read the memory map of PID 42: permission denied".to_string()
+            )),
+            [
+                "   maps  can't read the memory map of PID 42: permission denied",
+                "threads  3",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_nearest_row() {
+        /* rows 1, 2 and 4 are tombstones */
+        let selectable = |row: usize| ![1, 2, 4].contains(&row);
+        assert_eq!(nearest_row(6, 0, true, selectable), 0);
+        assert_eq!(nearest_row(6, 1, true, selectable), 3);
+        assert_eq!(nearest_row(6, 2, false, selectable), 0);
+        assert_eq!(nearest_row(6, 4, true, selectable), 5);
+        assert_eq!(nearest_row(6, 4, false, selectable), 3);
+        /* the last row is a tombstone, so moving past it goes back up */
+        assert_eq!(nearest_row(5, 4, true, selectable), 3);
+        assert_eq!(nearest_row(3, 1, false, |_| false), 1);
+        assert_eq!(nearest_row(0, 0, true, selectable), 0);
+    }
+
+    #[test]
+    fn test_step_cursor() {
+        /* clamped, the ends stop the cursor */
+        assert_eq!(step_cursor(0, 5, true, false), 1);
+        assert_eq!(step_cursor(4, 5, true, false), 4);
+        assert_eq!(step_cursor(3, 5, false, false), 2);
+        assert_eq!(step_cursor(0, 5, false, false), 0);
+        /* wrapped, they lead to the other end */
+        assert_eq!(step_cursor(3, 5, true, true), 4);
+        assert_eq!(step_cursor(4, 5, true, true), 0);
+        assert_eq!(step_cursor(1, 5, false, true), 0);
+        assert_eq!(step_cursor(0, 5, false, true), 4);
+        /* a cursor past the rows that are left goes back into them */
+        assert_eq!(step_cursor(7, 5, true, true), 0);
+        assert_eq!(step_cursor(7, 5, true, false), 4);
+        assert_eq!(step_cursor(0, 1, true, true), 0);
+        assert_eq!(step_cursor(0, 1, false, true), 0);
+        assert_eq!(step_cursor(0, 0, false, true), 0);
+        assert_eq!(step_cursor(0, 0, true, false), 0);
+    }
+
+    #[test]
+    fn test_follow_new() {
+        let start = std::time::Instant::now();
+        let idle = follow_new::FOLLOW_IDLE;
+        let processes = |pids: &[(Pid, u64)]| {
+            pids.iter()
+                .map(|&(pid, elapsed)| ProcessDisplay {
+                    elapsed: Some(elapsed),
+                    ..process_display(pid, "make")
+                })
+                .collect::<Vec<_>>()
+        };
+        let mut follow = FollowNew::new();
+        follow.record(&processes(&[(1, 100), (2, 50)]), true);
+        assert_eq!(follow.take_newest(start), None);
+
+        /* of the new processes, the one that started last */
+        follow.record(
+            &processes(&[(1, 100), (2, 50), (7, 2), (8, 0), (9, 1)]),
+            true,
+        );
+        assert_eq!(follow.take_newest(start), Some(8));
+        assert_eq!(follow.take_newest(start), None);
+        /* in the same second, the highest PID */
+        follow.record(&processes(&[(1, 100), (12, 0), (11, 0)]), true);
+        /* the same sample again */
+        follow.record(&processes(&[(1, 100), (12, 0), (11, 0)]), false);
+        assert_eq!(follow.take_newest(start), Some(12));
+
+        /* moving the cursor holds it off for a while */
+        follow.moved(start);
+        assert!(!follow.is_idle(start + Duration::from_secs(1)));
+        follow.record(&processes(&[(1, 100), (13, 0)]), true);
+        assert_eq!(follow.take_newest(start + Duration::from_secs(1)), None);
+        follow.record(&processes(&[(1, 100), (13, 1), (14, 0)]), true);
+        assert!(follow.is_idle(start + idle));
+        assert_eq!(follow.take_newest(start + idle), Some(14));
+    }
+
+    #[test]
+    fn test_follow_new_processes() {
+        let dir = mock_proc_root("follow-new", 20);
+        let options = |sort| ProcessListOptions {
+            proc_source: ProcSource::new(&dir).unwrap(),
+            elapsed: true,
+            sort: Some(SortOrder::parse(sort).unwrap()),
+            follow_new: true,
+            ..Default::default()
+        };
+        /* Start a process after the others, the higher the PID the later, and draw the list once
+         * it's sampled */
+        let spawn = |list: &mut ProcessList, pid: Pid| {
+            mock_proc_entry(&dir, pid, "cc1", 1);
+            let stat = dir.join(pid.to_string()).join("stat");
+            let later = std::fs::read_to_string(&stat)
+                .unwrap()
+                .replace(" 0 12345 ", &format!(" 0 {} ", pid * 1000));
+            std::fs::write(&stat, later).unwrap();
+            std::thread::sleep(Duration::from_millis(100));
+            draw_list(list)
+        };
+        let mut list = ProcessList::new(options("start"));
+        list.sampler = Sampler::new(
+            Duration::from_millis(10),
+            ProcSource::new(&dir).unwrap(),
+            ExtraReads::default(),
+        );
+        draw_list(&mut list);
+        assert_eq!(list.cursor, 0);
+
+        /* from the oldest, the new process is past the first page, which scrolls to it */
+        let rows = spawn(&mut list, 40);
+        assert_eq!(list.cursor, 21);
+        assert!(rows[5].trim_start().starts_with("40 "), "{:?}", rows);
+
+        /* not while the cursor is being moved */
+        list.page_movement = Some(PageMovement::Up);
+        draw_list(&mut list);
+        spawn(&mut list, 41);
+        assert_eq!(list.cursor, 20);
+        /* and again once it was left alone */
+        let idle = follow_new::FOLLOW_IDLE + Duration::from_secs(1);
+        if let Some(moved_at) = std::time::Instant::now().checked_sub(idle) {
+            list.follow_new.as_mut().unwrap().moved(moved_at);
+            spawn(&mut list, 42);
+            assert_eq!(list.cursor, 23);
+        }
+        drop(list);
+
+        /* only while the list is sorted by start time */
+        let mut list = ProcessList::new(options("pid"));
+        list.sampler = Sampler::new(
+            Duration::from_millis(10),
+            ProcSource::new(&dir).unwrap(),
+            ExtraReads::default(),
+        );
+        draw_list(&mut list);
+        spawn(&mut list, 43);
+        assert_eq!(list.cursor, 0);
+        drop(list);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_wrap_selection() {
+        let dir = mock_proc_root("wrap-selection", 20);
+        /* 3 of the 21 processes are listed, and the cursor wraps around them */
+        let options = |wrap_selection| ProcessListOptions {
+            proc_source: ProcSource::new(&dir).unwrap(),
+            pid_filter: PidFilter {
+                pids: vec![4, 5, 6],
+                descendants: false,
+            },
+            wrap_selection,
+            ..Default::default()
+        };
+        let mut list = ProcessList::new(options(true));
+        draw_list(&mut list);
+        assert_eq!(list.height, 3);
+        let step = |list: &mut ProcessList, movement| {
+            list.page_movement = Some(movement);
+            draw_list(list);
+            list.cursor
+        };
+        assert_eq!(step(&mut list, PageMovement::Up), 2);
+        assert_eq!(step(&mut list, PageMovement::Down), 0);
+        assert_eq!(step(&mut list, PageMovement::Down), 1);
+        assert_eq!(step(&mut list, PageMovement::Down), 2);
+        assert_eq!(step(&mut list, PageMovement::Down), 0);
+        /* paging still stops at the ends */
+        assert_eq!(step(&mut list, PageMovement::PageUp), 0);
+        assert_eq!(step(&mut list, PageMovement::End), 2);
+        assert_eq!(step(&mut list, PageMovement::PageDown), 2);
+        drop(list);
+
+        /* clamped by default */
+        let mut list = ProcessList::new(options(false));
+        draw_list(&mut list);
+        assert_eq!(step(&mut list, PageMovement::Up), 0);
+        assert_eq!(step(&mut list, PageMovement::Down), 1);
+        assert_eq!(step(&mut list, PageMovement::Down), 2);
+        assert_eq!(step(&mut list, PageMovement::Down), 2);
+        drop(list);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_pid_ns() {
+        /* a container's init under the runtime, 4, with a child of its own, 6 */
+        let dir = mock_proc_root("pid-ns", 3);
+        mock_proc_entry(&dir, 5, "containerd-shim", 1);
+        mock_proc_entry(&dir, 6, "sh", 4);
+        std::fs::write(
+            dir.join("4/stat"),
+            "4 (init) S 5 4 4 0 -1 0 0 0 0 0 7 3 0 0 20 0 1 0 12345 0\n",
+        )
+        .unwrap();
+        std::fs::write(dir.join("4/status"), "Name:\tinit\nState:\tS (sleeping)\nPid:\t4\nPPid:\t5\nUid:\t0\t0\t0\t0\nVmRSS:\t  512 kB\n").unwrap();
+        for (pid, ns) in &[
+            ("self", 100),
+            ("1", 100),
+            ("2", 100),
+            ("4", 200),
+            ("5", 100),
+            ("6", 200),
+        ] {
+            std::fs::create_dir_all(dir.join(pid).join("ns")).unwrap();
+            std::os::unix::fs::symlink(format!("pid:[{}]", ns), dir.join(pid).join("ns/pid"))
+                .unwrap();
+        }
+        let tree = |pid_ns| {
+            let mut list = ProcessList::new(ProcessListOptions {
+                proc_source: ProcSource::new(&dir).unwrap(),
+                sort: Some(SortOrder(Sort::PidAsc)),
+                pid_ns,
+                ..Default::default()
+            });
+            list.draw_tree = true;
+            draw_list(&mut list);
+            let tree = list.tree_rows();
+            list.draw_tree = false;
+            let flat = list
+                .visible_rows()
+                .iter()
+                .map(|&i| list.processes[i].i)
+                .collect::<Vec<Pid>>();
+            (tree, flat)
+        };
+        let (scoped, grouped) = tree(Some(PidNsMode::Group));
+        assert_eq!(
+            scoped,
+            [(0, 1), (1, 2), (1, 3), (1, 5), (0, 4), (1, 6)],
+            "the container is a tree of its own"
+        );
+        /* 3's namespace can't be read, so it's taken to be bb's */
+        assert_eq!(grouped, [1, 2, 3, 5, 4, 6]);
+        let (tree, flat) = tree(None);
+        assert_eq!(tree, [(0, 1), (1, 2), (1, 3), (1, 5), (2, 4), (3, 6)]);
+        assert_eq!(flat, [1, 2, 3, 4, 5, 6]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_column_picker() {
+        let dir = mock_proc_root("column-picker", 2);
+        let mut list = ProcessList::new(ProcessListOptions {
+            proc_source: ProcSource::new(&dir).unwrap(),
+            cpu_time: true,
+            elapsed: true,
+            ..Default::default()
+        });
+        let press = |list: &mut ProcessList, key| {
+            list.process_event(&mut UIEvent::Input(key));
+            grid_rows(&render(list, (100, 24)))
+        };
+        let rows = press(&mut list, Key::Char('C'));
+        assert!(
+            rows[1].find("TIME+") < rows[1].find("ELAPSED"),
+            "{}",
+            rows[1]
+        );
+        assert!(rows.iter().any(|row| row.contains("[ ] threads")));
+        assert!(rows.iter().any(|row| row.contains("[x] time")));
+        /* without --io and the like, their columns can't be drawn */
+        assert!(!rows.iter().any(|row| row.contains("] io")), "{:?}", rows);
+        assert!(!rows.iter().any(|row| row.contains("] gpu")));
+
+        /* the cpu times after the elapsed times, live */
+        press(&mut list, Key::Down);
+        let rows = press(&mut list, Key::Char('J'));
+        assert_eq!(list.column_picker, Some(2));
+        assert!(
+            rows[1].find("ELAPSED") < rows[1].find("TIME+"),
+            "{}",
+            rows[1]
+        );
+        assert_eq!(list.columns.entries()[2], (Column::CpuTime, true));
+
+        /* hiding them stops reading them */
+        let rows = press(&mut list, Key::Char(' '));
+        assert!(!rows[1].contains("TIME+"));
+        assert!(!list.data.cpu_time);
+        assert!(rows.iter().any(|row| row.contains("[ ] time")));
+        /* keys go to the picker while it's open */
+        press(&mut list, Key::Up);
+        let rows = press(&mut list, Key::Char(' '));
+        assert_eq!(list.column_picker, Some(1));
+        assert!(!rows[1].contains("ELAPSED"));
+        assert_eq!(list.cursor, 0);
+
+        /* and the view keeps them once it's closed */
+        let rows = press(&mut list, Key::Esc);
+        assert_eq!(list.column_picker, None);
+        assert!(!rows.iter().any(|row| row.contains("[ ] time")));
+        assert_eq!(list.views.cycle().columns, Some(ViewColumns::default()));
+
+        /* C is typed into a filter instead */
+        press(&mut list, Key::Char(' '));
+        press(&mut list, Key::Char('C'));
+        assert_eq!(list.column_picker, None);
+        assert_eq!(list.filter_term.as_deref(), Some("C"));
+        drop(list);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_help_box() {
+        let dir = mock_proc_root("help-box", 2);
+        let mut list = ProcessList::new(ProcessListOptions {
+            proc_source: ProcSource::new(&dir).unwrap(),
+            ..Default::default()
+        });
+        list.process_event(&mut UIEvent::Input(Key::Char('h')));
+        assert!(list.draw_help);
+        let shortcuts = list.get_shortcuts().remove("").unwrap();
+        /* every shortcut is shown on 80x24, in two columns */
+        let rows = grid_rows(&render(&mut list, (80, 24)));
+        for (action, key) in shortcuts.iter() {
+            let entry = format!("{} {}", action, key);
+            assert!(
+                rows.iter().any(|row| row.contains(&entry)),
+                "{}: {:?}",
+                entry,
+                rows
+            );
+        }
+        let boxed = rows
+            .iter()
+            .enumerate()
+            .filter(|(_, row)| row.ends_with('▒') || row.contains("▒ "))
+            .map(|(y, _)| y)
+            .collect::<Vec<usize>>();
+        assert_eq!(boxed.len(), shortcuts.len().div_ceil(2) + 4, "{:?}", rows);
+        assert!(rows
+            .iter()
+            .any(|row| row.contains(" cancel Esc ") && row.contains(" pick columns C ")));
+
+        /* and the box stays in smaller grids */
+        for size in [(80, 12), (40, 8), (20, 4), (10, 3)].iter() {
+            let rows = grid_rows(&render(&mut list, *size));
+            assert_eq!(rows.len(), size.1);
+        }
+        drop(list);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_key_hints() {
+        let dir = mock_proc_root("key-hints", 2);
+        let options = |read_only| ProcessListOptions {
+            proc_source: ProcSource::new(&dir).unwrap(),
+            read_only,
+            ..Default::default()
+        };
+        let mut list = ProcessList::new(options(false));
+        let rows = draw_list(&mut list);
+        assert!(!rows.iter().any(|row| row.contains("Help")), "{:?}", rows);
+
+        /* on the last row, from the list's shortcuts, as many as fit */
+        list.process_event(&mut UIEvent::Input(Key::Char('?')));
+        let rows = draw_list(&mut list);
+        assert_eq!(
+            rows[11].trim_end(),
+            "h Help  Space Filter  / Search  t Tree  k Kill  f Freeze"
+        );
+        let pages = key_hints::paginate(key_hints::hints(&list.dispatch_map().0), 60);
+        assert_eq!(rows[11].trim_end(), key_hints::page_text(&pages[0]));
+        assert!(pages
+            .iter()
+            .all(|page| key_hints::page_text(page).len() <= 60));
+        assert!(key_hints::page_text(pages.last().unwrap()).ends_with("q Quit"));
+        /* with the list's rows above it */
+        assert!(
+            rows[..11].iter().any(|row| row.contains("init")),
+            "{:?}",
+            rows
+        );
+        assert!(!rows[10].contains("Help"));
+
+        /* and without the keys `--read-only` takes out */
+        let mut read_only = ProcessList::new(ProcessListOptions {
+            key_hints: true,
+            ..options(true)
+        });
+        let rows = draw_list(&mut read_only);
+        assert!(rows[11].starts_with("h Help  Space Filter  / Search  t Tree  f Freeze"));
+
+        list.process_event(&mut UIEvent::Input(Key::Char('?')));
+        let rows = draw_list(&mut list);
+        assert!(!rows.iter().any(|row| row.contains("Help")), "{:?}", rows);
+        drop(list);
+        drop(read_only);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_dim_below() {
+        let dir = mock_proc_root("dim-below", 0);
+        let options = |dim_below| ProcessListOptions {
+            proc_source: ProcSource::new(&dir).unwrap(),
+            dim_below,
+            ..Default::default()
+        };
+        let mut list = ProcessList::new(options(None));
+        assert!(!list.dim_idle);
+        assert_eq!(list.idle_threshold, DEFAULT_IDLE_THRESHOLD);
+        list.process_event(&mut UIEvent::Input(Key::Char('i')));
+        assert!(list.dim_idle);
+
+        /* dimmed from the start, and i still toggles it */
+        let mut list = ProcessList::new(options(Some(200)));
+        assert!(list.dim_idle);
+        assert_eq!(list.idle_threshold, 200);
+        let mut p = process_display(42, "x");
+        p.cpu_percent = Some(150);
+        assert!(is_idle(&p, list.idle_threshold));
+        assert!(!is_idle(&p, DEFAULT_IDLE_THRESHOLD));
+        list.process_event(&mut UIEvent::Input(Key::Char('i')));
+        assert!(!list.dim_idle);
+        drop(list);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_min_cpu() {
+        let processes = [(1, 0), (2, 49), (3, 50), (4, 900)]
+            .iter()
+            .map(|&(pid, cpu_percent)| {
+                let mut p = process_display(pid, "x");
+                p.cpu_percent = Some(cpu_percent);
+                p
+            })
+            .chain(Some(ProcessDisplay {
+                cpu_percent: None,
+                ..process_display(5, "new")
+            }))
+            .collect::<Vec<_>>();
+        let retained = |min_cpu, kept| {
+            let mut rows = (0..processes.len()).collect::<Vec<usize>>();
+            retain_min_cpu(&mut rows, &processes, min_cpu, kept);
+            rows.into_iter()
+                .map(|i| processes[i].i)
+                .collect::<Vec<Pid>>()
+        };
+        assert_eq!(retained(0, None), [1, 2, 3, 4, 5]);
+        assert_eq!(retained(50, None), [3, 4, 5]);
+        assert_eq!(retained(1000, None), [5]);
+        /* the process under the cursor stays even below the minimum */
+        assert_eq!(retained(50, Some(2)), [2, 3, 4, 5]);
+        assert_eq!(retained(50, Some(4)), [3, 4, 5]);
+
+        assert_eq!(next_min_cpu(None), Some(MIN_CPU_STEPS[0]));
+        assert_eq!(next_min_cpu(Some(MIN_CPU_STEPS[0])), Some(MIN_CPU_STEPS[1]));
+        /* from a minimum given with --min-cpu, to the next step above it */
+        assert_eq!(next_min_cpu(Some(150)), Some(500));
+        assert_eq!(next_min_cpu(MIN_CPU_STEPS.last().copied()), None);
+    }
+
+    #[test]
+    fn test_is_memory_hog() {
+        /* 1 MiB of 8 MiB */
+        let mut p = process_display(42, "bb");
+        let mem_total = 8 * 1024;
+        assert!(is_memory_hog(&p, mem_total, 0.1));
+        assert!(!is_memory_hog(&p, mem_total, 0.125));
+        assert!(!is_memory_hog(&p, mem_total, 0.5));
+        p.vm_rss_value = 8 * 1024 * 1024;
+        assert!(is_memory_hog(&p, mem_total, 0.99));
+        assert!(!is_memory_hog(&p, mem_total, 1.0));
+        p.vm_rss_value = 0;
+        assert!(!is_memory_hog(&p, mem_total, 0.01));
+        /* nothing is a hog if the total isn't known */
+        p.vm_rss_value = 1024;
+        assert!(!is_memory_hog(&p, 0, 0.01));
+    }
+
+    #[test]
+    fn test_csv_quoting() {
+        assert_eq!(csv_field("/usr/bin/bb"), "/usr/bin/bb");
+        assert_eq!(csv_field("sh -c a,b"), "\"sh -c a,b\"");
+        assert_eq!(csv_field("echo \"hi\""), "\"echo \"\"hi\"\"\"");
+
+        let p = process_display(42, "sh -c \"echo a,b\"");
+        let mut out = Vec::new();
+        write_csv(&mut out, &ColumnWidthMaxima::new(), &[&p]).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "PID,PPID,USER,VM_RSS,CPU%,STATE,CMD_LINE\r\n\
+             42,1,user,1.00 MiB,1.5,S,\"sh -c \"\"echo a,b\"\"\"\r\n"
+        );
+    }
+
+    #[test]
+    fn test_csv_columns() {
+        let mut p = process_display(42, "make");
+        p.threads = Some(3);
+        p.elapsed = Some(90);
+        p.caps = Some(u64::MAX);
+        p.env = Some("a,b".to_string());
+        p.affinity = Some("0-3".to_string());
+        let mut other = process_display(43, "cc");
+        other.pid_ns = Some(4026531836);
+        let mut columns = ColumnWidthMaxima::from_processes([&p, &other].iter().copied());
+        columns.fit_env("PATH");
+        let csv = |columns: &ColumnWidthMaxima| {
+            let mut out = Vec::new();
+            write_csv(&mut out, columns, &[&p, &other]).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+        /* the columns the list draws, in its order */
+        assert_eq!(
+            csv(&columns),
+            "PID,PPID,USER,VM_RSS,CPU%,STATE,PID_NS,THREADS,ELAPSED,AFFINITY,CAPS,PATH,CMD_LINE\r\n\
+             42,1,user,1.00 MiB,1.5,S,,3,90,0-3,ALL,\"a,b\",make\r\n\
+             43,1,user,1.00 MiB,1.5,S,4026531836,,,,,,cc\r\n"
+        );
+        /* and as the column picker left them */
+        columns.columns.set_shown(Column::Threads, false);
+        let rows = columns.columns.rows(|_| true);
+        let mut row = 7;
+        while row > 0 {
+            row = columns.columns.move_up(&rows, row);
+        }
+        assert!(csv(&columns).starts_with(
+            "PID,PPID,USER,VM_RSS,CPU%,STATE,PID_NS,CAPS,ELAPSED,AFFINITY,PATH,CMD_LINE\r\n"
+        ));
+    }
+}
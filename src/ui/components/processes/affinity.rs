@@ -0,0 +1,107 @@
+/*
+ * bb
+ *
+ * Copyright 2019 Manos Pitsidianakis
+ *
+ * This file is part of bb.
+ *
+ * bb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * bb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with bb. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+/*! The optional column with the cpus each process may run on, as `sched_getaffinity(2)` returns
+them, eg. `0-3,8` for the first four cpus and the ninth.
+
+The affinity is asked of the kernel by PID rather than read from procfs, so it's that of bb's PID
+namespace whatever `--proc` is.
+*/
+
+use super::Pid;
+
+/// Shown for processes whose affinity can't be read, eg. because they exited.
+pub const UNREADABLE: &str = "-";
+
+/// The cpus `pid` may run on, in increasing order, or `None` if they can't be read.
+pub fn read_affinity(pid: Pid) -> Option<Vec<usize>> {
+    let mut set = unsafe { std::mem::zeroed::<libc::cpu_set_t>() };
+    let ret = unsafe { libc::sched_getaffinity(pid, std::mem::size_of_val(&set), &mut set) };
+    if ret != 0 {
+        return None;
+    }
+    Some(
+        (0..libc::CPU_SETSIZE as usize)
+            .filter(|&cpu| unsafe { libc::CPU_ISSET(cpu, &set) })
+            .collect(),
+    )
+}
+
+/// `cpus` as a list of ranges, eg. `0-3,8`, the way the `Cpus_allowed_list` of
+/// `/proc/[pid]/status` lists them.
+pub fn cpu_ranges(cpus: &[usize]) -> String {
+    let mut cpus = cpus.to_vec();
+    cpus.sort_unstable();
+    cpus.dedup();
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    for cpu in cpus {
+        match ranges.last_mut() {
+            Some((_, last)) if *last + 1 == cpu => *last = cpu,
+            _ => ranges.push((cpu, cpu)),
+        }
+    }
+    ranges
+        .into_iter()
+        .map(|(first, last)| {
+            if first == last {
+                first.to_string()
+            } else {
+                format!("{}-{}", first, last)
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
+/// What the column shows for `pid`.
+pub fn affinity_label(pid: Pid) -> String {
+    read_affinity(pid).map_or_else(|| UNREADABLE.to_string(), |cpus| cpu_ranges(&cpus))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cpu_ranges() {
+        assert_eq!(cpu_ranges(&[0, 1, 2, 3, 8]), "0-3,8");
+        assert_eq!(cpu_ranges(&[0]), "0");
+        assert_eq!(cpu_ranges(&[4, 5]), "4-5");
+        assert_eq!(cpu_ranges(&[1, 3, 5, 6, 7, 10, 11]), "1,3,5-7,10-11");
+        /* in any order, and counted once */
+        assert_eq!(cpu_ranges(&[9, 2, 0, 1, 2, 8]), "0-2,8-9");
+        assert_eq!(cpu_ranges(&[]), "");
+    }
+
+    #[test]
+    fn test_read_affinity() {
+        /* bb itself may run on at least one cpu, as its status says */
+        let cpus = read_affinity(std::process::id() as Pid).unwrap();
+        assert!(!cpus.is_empty());
+        let status = std::fs::read_to_string("/proc/self/status").unwrap();
+        let list = status
+            .lines()
+            .find_map(|line| line.strip_prefix("Cpus_allowed_list:"))
+            .unwrap();
+        assert_eq!(cpu_ranges(&cpus), list.trim());
+        assert_eq!(affinity_label(-1), UNREADABLE);
+    }
+}
@@ -0,0 +1,115 @@
+/*
+ * bb
+ *
+ * Copyright 2019 Manos Pitsidianakis
+ *
+ * This file is part of bb.
+ *
+ * bb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * bb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with bb. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+/*! How many samples ago each process appeared, for fading the highlight of new processes.
+
+Ages are only kept for the processes in the latest snapshot, like the cpu histories.
+*/
+
+use super::{Pid, ProcessDisplay};
+use crate::ui::Color;
+use std::collections::{HashMap, HashSet};
+
+/// Foreground of a process in the first sample it's in.
+pub const NEW_FG: Color = Color::Byte(46); // Green1
+                                           /* Stands in for the terminal's default foreground, which can't be blended with since it isn't
+                                            * known. The last step of the fade is to the default foreground itself. */
+const FADED_FG: Color = Color::Byte(252); // Grey82
+
+/// The age of each process in samples, up to the number of samples the highlight fades over.
+#[derive(Debug)]
+pub struct Ages {
+    ages: HashMap<Pid, usize>,
+    fade: usize,
+    /* whether the processes that were there when bb started have been recorded */
+    primed: bool,
+}
+
+impl Ages {
+    pub fn new(fade: usize) -> Ages {
+        Ages {
+            ages: Default::default(),
+            fade,
+            primed: false,
+        }
+    }
+
+    /* Age the processes on a `new_sample` and drop the ones that exited. A process without a cpu
+     * usage is in its first snapshot, even if a process that exited had its PID. The processes
+     * of the first snapshot aren't new, they were just there before bb. */
+    pub fn record(&mut self, processes: &[ProcessDisplay], new_sample: bool) {
+        if self.primed && !new_sample {
+            return;
+        }
+        let processes = processes.iter().filter(|p| p.members == 0);
+        if !self.primed {
+            self.ages = processes.map(|p| (p.i, self.fade)).collect();
+            self.primed = true;
+            return;
+        }
+        let pids = processes.clone().map(|p| p.i).collect::<HashSet<Pid>>();
+        self.ages.retain(|pid, _| pids.contains(pid));
+        for p in processes {
+            match self.ages.get_mut(&p.i) {
+                Some(age) if p.cpu_percent_raw.is_some() => *age = (*age + 1).min(self.fade),
+                _ => {
+                    self.ages.insert(p.i, 0);
+                }
+            }
+        }
+    }
+
+    /// Foreground of the process with `pid`, if it's recent enough to be highlighted.
+    pub fn color(&self, pid: Pid) -> Option<Color> {
+        age_color(*self.ages.get(&pid)?, self.fade)
+    }
+}
+
+/* `NEW_FG` blended towards `FADED_FG` by `age` in `fade` samples, `None` once it has faded */
+fn age_color(age: usize, fade: usize) -> Option<Color> {
+    if age >= fade {
+        return None;
+    }
+    Some(NEW_FG.blend(FADED_FG, age as f64 / fade as f64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_age_color() {
+        let colors = (0..6).map(|age| age_color(age, 4)).collect::<Vec<_>>();
+        assert_eq!(
+            colors,
+            [
+                Some(NEW_FG),
+                Some(Color::Byte(83)),
+                Some(Color::Byte(77)),
+                Some(Color::Byte(151)),
+                None,
+                None
+            ]
+        );
+        assert_eq!(age_color(0, 1), Some(NEW_FG));
+        assert_eq!(age_color(1, 1), None);
+    }
+}
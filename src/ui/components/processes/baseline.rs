@@ -0,0 +1,250 @@
+/*
+ * bb
+ *
+ * Copyright 2019 Manos Pitsidianakis
+ *
+ * This file is part of bb.
+ *
+ * bb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * bb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with bb. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+/*! Changes of each process since a pinned baseline snapshot, for before/after comparisons.
+
+Processes are told apart by their PID and start time, so a PID that was reused since the baseline
+was pinned counts as a process that's gone and a new one.
+*/
+
+use super::*;
+
+/* What a process looked like when the baseline was pinned */
+#[derive(Debug, Copy, Clone)]
+struct Pinned {
+    start_time: usize,
+    rtime: usize,
+    vm_rss: usize,
+}
+
+/// The processes of a snapshot, to compare later snapshots against.
+#[derive(Debug)]
+pub struct Baseline {
+    processes: HashMap<Pid, Pinned>,
+}
+
+/// How a process changed since the baseline.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Delta {
+    /// The process wasn't in the baseline.
+    New,
+    /// Clock ticks of cpu time the process used, and KiB its resident memory grew by.
+    Changed { cpu_ticks: usize, vm_rss: i64 },
+}
+
+impl Baseline {
+    pub fn pin(snapshot: &Snapshot) -> Baseline {
+        Baseline {
+            processes: snapshot
+                .processes
+                .iter()
+                .map(|p| {
+                    (
+                        p.pid,
+                        Pinned {
+                            start_time: p.start_time,
+                            rtime: p.rtime,
+                            vm_rss: p.vm_rss,
+                        },
+                    )
+                })
+                .collect(),
+        }
+    }
+
+    pub fn delta(&self, process: &Process) -> Delta {
+        match self.processes.get(&process.pid) {
+            Some(pinned) if pinned.start_time == process.start_time => Delta::Changed {
+                cpu_ticks: process.rtime.saturating_sub(pinned.rtime),
+                vm_rss: process.vm_rss as i64 - pinned.vm_rss as i64,
+            },
+            _ => Delta::New,
+        }
+    }
+
+    /// PIDs of the processes of the baseline that aren't in `snapshot`, in ascending order.
+    pub fn gone(&self, snapshot: &Snapshot) -> Vec<Pid> {
+        let current = snapshot
+            .processes
+            .iter()
+            .map(|p| (p.pid, p.start_time))
+            .collect::<HashSet<(Pid, usize)>>();
+        let mut ret = self
+            .processes
+            .iter()
+            .filter(|(pid, pinned)| !current.contains(&(**pid, pinned.start_time)))
+            .map(|(pid, _)| *pid)
+            .collect::<Vec<Pid>>();
+        ret.sort_unstable();
+        ret
+    }
+}
+
+/// The sum of the deltas of the members of a group. It's `New` only if all of them are new.
+pub fn sum_deltas(deltas: impl Iterator<Item = Delta>) -> Option<Delta> {
+    deltas.fold(None, |sum, delta| match (sum, delta) {
+        (
+            Some(Delta::Changed { cpu_ticks, vm_rss }),
+            Delta::Changed {
+                cpu_ticks: more_ticks,
+                vm_rss: more_rss,
+            },
+        ) => Some(Delta::Changed {
+            cpu_ticks: cpu_ticks + more_ticks,
+            vm_rss: vm_rss + more_rss,
+        }),
+        (Some(sum @ Delta::Changed { .. }), Delta::New) => Some(sum),
+        (_, delta) => Some(delta),
+    })
+}
+
+/// The cpu time and memory columns of a delta, eg. `+1.50s` and `-2.00 MiB`.
+pub fn delta_strings(delta: Delta, clock_ticks: usize) -> (String, String) {
+    match delta {
+        Delta::New => ("new".to_string(), "new".to_string()),
+        Delta::Changed { cpu_ticks, vm_rss } => (
+            format!("+{:.2}s", cpu_ticks as f64 / clock_ticks.max(1) as f64),
+            match vm_rss {
+                0 => "0".to_string(),
+                kib => format!(
+                    "{}{}",
+                    if kib < 0 { "-" } else { "+" },
+                    Bytes(kib.unsigned_abs() as usize * 1024).as_convenient_string()
+                ),
+            },
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(processes: &[(Pid, usize, usize, usize)]) -> Snapshot {
+        Snapshot {
+            cpu_stat: Stat {
+                user_time: 0,
+                nice_time: 0,
+                system_time: 0,
+                idle_time: 0,
+                iowait_time: 0,
+                irq: 0,
+                soft_irq: 0,
+                steal: 0,
+                guest: 0,
+                guest_nice: 0,
+            },
+            cpu_no: 1,
+            boot_time: 0,
+            time: 0,
+            mem_total: 0,
+            processes: processes
+                .iter()
+                .map(|&(pid, start_time, rtime, vm_rss)| Process {
+                    pid,
+                    ppid: 1,
+                    pgrp: pid,
+                    vm_rss,
+                    state: State::Sleeping,
+                    uid: 0,
+                    threads: None,
+                    cmd_line: "worker".to_string(),
+                    rtime,
+                    start_time,
+                    cgroup: None,
+                    io: None,
+                    cap_eff: None,
+                    affinity: None,
+                    pid_ns: None,
+                    category: Category::Other,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_deltas() {
+        let baseline = Baseline::pin(&snapshot(&[
+            (1, 0, 500, 4096),
+            (10, 100, 20, 1024),
+            (20, 200, 0, 2048),
+            (30, 300, 7, 512),
+        ]));
+        /* 20 exited and 30 was reused by another process */
+        let now = snapshot(&[
+            (1, 0, 650, 4096),
+            (10, 100, 20, 3072),
+            (30, 900, 0, 512),
+            (40, 950, 3, 256),
+        ]);
+        let deltas = now
+            .processes
+            .iter()
+            .map(|p| baseline.delta(p))
+            .collect::<Vec<Delta>>();
+        assert_eq!(
+            deltas,
+            [
+                Delta::Changed {
+                    cpu_ticks: 150,
+                    vm_rss: 0
+                },
+                Delta::Changed {
+                    cpu_ticks: 0,
+                    vm_rss: 2048
+                },
+                Delta::New,
+                Delta::New,
+            ]
+        );
+        assert_eq!(baseline.gone(&now), vec![20, 30]);
+        assert_eq!(baseline.gone(&snapshot(&[])), vec![1, 10, 20, 30]);
+
+        assert_eq!(
+            sum_deltas(deltas.iter().cloned()),
+            Some(Delta::Changed {
+                cpu_ticks: 150,
+                vm_rss: 2048
+            })
+        );
+        assert_eq!(sum_deltas(deltas[2..].iter().cloned()), Some(Delta::New));
+        assert_eq!(sum_deltas(std::iter::empty()), None);
+
+        assert_eq!(
+            delta_strings(deltas[0], 100),
+            ("+1.50s".to_string(), "0".to_string())
+        );
+        assert_eq!(
+            delta_strings(
+                Delta::Changed {
+                    cpu_ticks: 5,
+                    vm_rss: -2048
+                },
+                100
+            ),
+            ("+0.05s".to_string(), "-2.00 MiB".to_string())
+        );
+        assert_eq!(
+            delta_strings(Delta::New, 100),
+            ("new".to_string(), "new".to_string())
+        );
+    }
+}
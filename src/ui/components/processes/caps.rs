@@ -0,0 +1,102 @@
+/*
+ * bb
+ *
+ * Copyright 2019 Manos Pitsidianakis
+ *
+ * This file is part of bb.
+ *
+ * bb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * bb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with bb. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+/*! Effective capabilities of processes, for the capabilities column.
+
+`/proc/[pid]/status` has them as a hex mask on the `CapEff:` line, with bit `n` set for capability
+number `n` of `capabilities(7)`. The column names a few notable ones and counts the rest.
+*/
+
+/// Capabilities the column names, by their bit in the mask.
+pub const NOTABLE: &[(u32, &str)] = &[
+    (21, "SYS_ADMIN"),
+    (13, "NET_RAW"),
+    (12, "NET_ADMIN"),
+    (19, "SYS_PTRACE"),
+    (16, "SYS_MODULE"),
+    (1, "DAC_OVERRIDE"),
+];
+
+/* Every capability up to CAP_CHECKPOINT_RESTORE, what root processes usually have */
+const ALL: u64 = (1 << 41) - 1;
+/* Notable capabilities named in the column before the rest are only counted, to keep it narrow */
+const NAMED: usize = 2;
+
+/// The mask of a `CapEff:` line, eg. `0000000000003000`.
+pub fn parse_cap_mask(s: &str) -> Option<u64> {
+    u64::from_str_radix(s.trim(), 16).ok()
+}
+
+/// Names of the notable capabilities in `mask`, in the order of `NOTABLE`.
+pub fn notable_caps(mask: u64) -> Vec<&'static str> {
+    NOTABLE
+        .iter()
+        .filter(|(bit, _)| mask & (1 << bit) != 0)
+        .map(|&(_, name)| name)
+        .collect()
+}
+
+/// What the capabilities column shows for `mask`: nothing for processes without capabilities, so
+/// that the ones with some stand out, `ALL` for (at least) every capability, or up to two of the
+/// notable ones followed by how many others there are, eg. `NET_RAW,NET_ADMIN+3` or `+1`.
+pub fn caps_label(mask: u64) -> String {
+    if mask == 0 {
+        return String::new();
+    }
+    if mask & ALL == ALL {
+        return "ALL".to_string();
+    }
+    let mut names = notable_caps(mask);
+    names.truncate(NAMED);
+    let others = mask.count_ones() as usize - names.len();
+    let mut ret = names.join(",");
+    if others > 0 {
+        ret.push_str(&format!("+{}", others));
+    }
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_notable_caps() {
+        assert_eq!(parse_cap_mask("0000000000000000\n"), Some(0));
+        assert_eq!(parse_cap_mask("000001ffffffffff"), Some(ALL));
+        assert_eq!(parse_cap_mask("capabilities"), None);
+
+        assert!(notable_caps(0).is_empty());
+        /* CAP_NET_RAW and CAP_NET_BIND_SERVICE, eg. of ping or a web server */
+        let mask = parse_cap_mask("0000000000002400").unwrap();
+        assert_eq!(notable_caps(mask), ["NET_RAW"]);
+        assert_eq!(caps_label(mask), "NET_RAW+1");
+        /* CAP_SYS_ADMIN, CAP_NET_RAW and CAP_NET_ADMIN */
+        let mask = parse_cap_mask("0000000000203000").unwrap();
+        assert_eq!(notable_caps(mask), ["SYS_ADMIN", "NET_RAW", "NET_ADMIN"]);
+        assert_eq!(caps_label(mask), "SYS_ADMIN,NET_RAW+1");
+        /* only CAP_CHOWN */
+        assert_eq!(caps_label(parse_cap_mask("1").unwrap()), "+1");
+        assert_eq!(caps_label(0), "");
+        assert_eq!(caps_label(ALL), "ALL");
+        assert_eq!(caps_label(u64::MAX), "ALL");
+    }
+}
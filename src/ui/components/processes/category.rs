@@ -0,0 +1,75 @@
+/*
+ * bb
+ *
+ * Copyright 2019 Manos Pitsidianakis
+ *
+ * This file is part of bb.
+ *
+ * bb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * bb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with bb. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+/*! Guessing what kind of process a process is, for coloring command lines by category with
+`--color-categories`.
+
+The guess only uses fields of `/proc/[pid]/stat`, so it's cheap enough to make for every process.
+*/
+
+use super::Pid;
+
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub enum Category {
+    /// `kthreadd` and the kernel threads it starts.
+    Kernel,
+    /// Processes without a controlling terminal that detached into a session of their own or were
+    /// started by init.
+    Daemon,
+    /// Processes with a controlling terminal, eg. shells and what they run.
+    Interactive,
+    #[default]
+    Other,
+}
+
+/// Category of the process `pid`, from its parent, its session id and the device number of its
+/// controlling terminal, 0 if it has none.
+pub fn classify(pid: Pid, ppid: Pid, session: Pid, tty_nr: i32) -> Category {
+    /* kthreadd is always PID 2, and every kernel thread is its child */
+    if pid == 2 || ppid == 2 {
+        Category::Kernel
+    } else if tty_nr != 0 {
+        Category::Interactive
+    } else if session == pid || ppid == 1 {
+        Category::Daemon
+    } else {
+        Category::Other
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify() {
+        /* kthreadd and a kworker */
+        assert_eq!(classify(2, 0, 0, 0), Category::Kernel);
+        assert_eq!(classify(57, 2, 0, 0), Category::Kernel);
+        /* a shell on /dev/pts/0 (major 136, minor 0) leading its session, and a command it runs */
+        assert_eq!(classify(4100, 4099, 4100, 34816), Category::Interactive);
+        assert_eq!(classify(4200, 4100, 4100, 34816), Category::Interactive);
+        /* sshd, which called setsid(), a daemon started by init, and one of sshd's workers */
+        assert_eq!(classify(700, 1, 700, 0), Category::Daemon);
+        assert_eq!(classify(800, 1, 650, 0), Category::Daemon);
+        assert_eq!(classify(900, 700, 700, 0), Category::Other);
+    }
+}
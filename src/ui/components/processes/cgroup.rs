@@ -0,0 +1,152 @@
+/*
+ * bb
+ *
+ * Copyright 2019 Manos Pitsidianakis
+ *
+ * This file is part of bb.
+ *
+ * bb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * bb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with bb. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+/*! Parsing of `/proc/[pid]/cgroup`, for the optional cgroup column.
+
+Each line of the file is `hierarchy-ID:controller-list:cgroup-path`. With cgroup v2 there is a
+single `0::/path` line, with v1 there is one line per hierarchy. The column shows the id of the
+container the process runs in if the path looks like it belongs to one, otherwise the last
+component of the path.
+*/
+
+/// Container ids are shortened to this many characters, like `docker ps` does.
+const CONTAINER_ID_LEN: usize = 12;
+
+/// Prefixes container runtimes put in front of the container id in systemd scope names, eg.
+/// `docker-<id>.scope`.
+const SCOPE_PREFIXES: &[&str] = &["docker-", "cri-containerd-", "crio-", "libpod-"];
+
+/// The cgroup path of the process, given the contents of its `/proc/[pid]/cgroup`.
+///
+/// The unified (v2) hierarchy is preferred. In hybrid setups where it is still the root cgroup,
+/// the first v1 hierarchy that isn't is used instead.
+pub fn cgroup_path(contents: &str) -> &str {
+    let mut paths = contents
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.splitn(3, ':');
+            let id = fields.next()?;
+            fields.next()?;
+            Some((id, fields.next()?))
+        })
+        .filter(|(_, path)| *path != "/");
+    let first = paths.next();
+    first
+        .into_iter()
+        .chain(paths)
+        .find(|(id, _)| *id == "0")
+        .or(first)
+        .map(|(_, path)| path)
+        .unwrap_or("/")
+}
+
+/// The id of the container that cgroup `path` belongs to, shortened to `CONTAINER_ID_LEN`.
+///
+/// Docker and containerd name the cgroup of a container after its id, a 64 digit hex string,
+/// either as is or inside a systemd scope name. The innermost component that matches wins.
+pub fn container_id(path: &str) -> Option<&str> {
+    path.rsplit('/').find_map(|component| {
+        let component = component.trim_end_matches(".scope");
+        let id = SCOPE_PREFIXES
+            .iter()
+            .find_map(|prefix| component.strip_prefix(prefix))
+            .unwrap_or(component);
+        if id.len() == 64 && id.bytes().all(|b| b.is_ascii_hexdigit()) {
+            Some(&id[..CONTAINER_ID_LEN])
+        } else {
+            None
+        }
+    })
+}
+
+/// What the cgroup column shows for a process, given the contents of its `/proc/[pid]/cgroup`:
+/// its container id, the leaf name of its cgroup or `-` for the root cgroup.
+pub fn cgroup_name(contents: &str) -> String {
+    let path = cgroup_path(contents);
+    if let Some(id) = container_id(path) {
+        return id.to_string();
+    }
+    match path.trim_end_matches('/').rsplit('/').next() {
+        Some(leaf) if !leaf.is_empty() => leaf.to_string(),
+        _ => "-".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ID: &str = "3f4e9a1c2b7d8e6f5a4b3c2d1e0f9a8b7c6d5e4f3a2b1c0d9e8f7a6b5c4d3e2f";
+
+    #[test]
+    fn test_cgroup_name() {
+        /* cgroup v2 */
+        assert_eq!(cgroup_name("0::/\n"), "-");
+        assert_eq!(cgroup_name(""), "-");
+        assert_eq!(cgroup_name("0::/init.scope\n"), "init.scope");
+        assert_eq!(
+            cgroup_name("0::/user.slice/user-1000.slice/user@1000.service/app.slice/foot.scope\n"),
+            "foot.scope"
+        );
+        assert_eq!(
+            cgroup_name(&format!("0::/system.slice/docker-{}.scope\n", ID)),
+            "3f4e9a1c2b7d"
+        );
+        assert_eq!(
+            cgroup_name(&format!(
+                "0::/kubepods.slice/kubepods-besteffort.slice/kubepods-besteffort-pod1b2c.slice/cri-containerd-{}.scope\n",
+                ID
+            )),
+            "3f4e9a1c2b7d"
+        );
+
+        /* cgroup v1, docker with the cgroupfs driver */
+        let v1 = format!(
+            "12:pids:/docker/{id}\n11:memory:/docker/{id}\n2:cpu,cpuacct:/docker/{id}\n1:name=systemd:/docker/{id}\n",
+            id = ID
+        );
+        assert_eq!(cgroup_path(&v1), format!("/docker/{}", ID));
+        assert_eq!(cgroup_name(&v1), "3f4e9a1c2b7d");
+        assert_eq!(
+            cgroup_name("12:pids:/\n11:memory:/\n1:name=systemd:/\n0::/\n"),
+            "-"
+        );
+
+        /* hybrid: the unified hierarchy wins unless it's the root */
+        assert_eq!(
+            cgroup_name(
+                "1:name=systemd:/user.slice/session-2.scope\n0::/user.slice/session-3.scope\n"
+            ),
+            "session-3.scope"
+        );
+        assert_eq!(
+            cgroup_name(&format!(
+                "3:cpu:/\n2:memory:/docker/{}\n1:name=systemd:/system.slice/cron.service\n0::/\n",
+                ID
+            )),
+            "3f4e9a1c2b7d"
+        );
+
+        /* not quite container ids */
+        assert_eq!(container_id("/docker/3f4e9a1c2b7d"), None);
+        assert_eq!(container_id(&format!("/docker/{}x", ID)), None);
+    }
+}
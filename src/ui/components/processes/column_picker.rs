@@ -0,0 +1,227 @@
+/*
+ * bb
+ *
+ * Copyright 2019 Manos Pitsidianakis
+ *
+ * This file is part of bb.
+ *
+ * bb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * bb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with bb. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+/*! The column picker, opened with `C`: a box listing the optional columns, those after the state,
+in the order they're drawn in, eg.
+
+```text
+[x] threads
+[ ] time
+[x] io
+```
+
+Space shows or hides the column under the cursor and `K` and `J` move it up and down, which the
+list follows right away. The columns before them, from the PID to the state, are always shown and
+stay where they are, since the memory and cpu colors are drawn at their offsets.
+
+Only the columns that can be drawn are listed: those whose values are read, eg. the I/O ones with
+`--io`, and the `READ_WHEN_SHOWN` ones, which checking reads. The others keep their place among
+them.
+
+The layout only lasts until bb exits: bb has no saved preferences to write it back to, so every
+start begins with the default columns and those of the flags.
+*/
+
+use super::Column;
+
+/// The optional columns whose values are read once they're shown, without a flag of their own.
+pub const READ_WHEN_SHOWN: [Column; 4] = [
+    Column::Threads,
+    Column::CpuTime,
+    Column::Elapsed,
+    Column::Caps,
+];
+
+/// The columns the picker lists, in their default order.
+pub const OPTIONAL_COLUMNS: [Column; 11] = [
+    Column::Threads,
+    Column::CpuTime,
+    Column::Elapsed,
+    Column::Io,
+    Column::Delta,
+    Column::Cgroup,
+    Column::Affinity,
+    Column::Caps,
+    Column::Env,
+    Column::Net,
+    Column::Gpu,
+];
+
+/// The order of the `OPTIONAL_COLUMNS` and whether each is shown.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ColumnConfig([(Column, bool); 11]);
+
+impl Default for ColumnConfig {
+    fn default() -> Self {
+        let mut ret = [(Column::Threads, true); 11];
+        for (entry, column) in ret.iter_mut().zip(OPTIONAL_COLUMNS.iter()) {
+            entry.0 = *column;
+        }
+        ColumnConfig(ret)
+    }
+}
+
+impl ColumnConfig {
+    /// The columns in the order they're drawn in, and whether they're shown.
+    pub fn entries(&self) -> &[(Column, bool)] {
+        &self.0
+    }
+
+    /// Whether `column` is shown. The columns that aren't optional always are.
+    pub fn is_shown(&self, column: Column) -> bool {
+        self.0
+            .iter()
+            .find(|(c, _)| *c == column)
+            .is_none_or(|(_, shown)| *shown)
+    }
+
+    pub fn set_shown(&mut self, column: Column, shown: bool) {
+        if let Some(entry) = self.0.iter_mut().find(|(c, _)| *c == column) {
+            entry.1 = shown;
+        }
+    }
+
+    /// Show or hide the column at `index`, and return it.
+    pub fn toggle(&mut self, index: usize) -> Option<Column> {
+        let entry = self.0.get_mut(index)?;
+        entry.1 = !entry.1;
+        Some(entry.0)
+    }
+
+    /// The indices of the columns the picker lists, those `can_draw` is true for, in order. They're
+    /// its rows.
+    pub fn rows(&self, can_draw: impl Fn(Column) -> bool) -> Vec<usize> {
+        (0..self.0.len())
+            .filter(|&i| can_draw(self.0[i].0))
+            .collect()
+    }
+
+    /// Swap the column on `row` of the picker's `rows` with the one on the row above, and return
+    /// the row it went to.
+    pub fn move_up(&mut self, rows: &[usize], row: usize) -> usize {
+        if row == 0 || row >= rows.len() {
+            return row;
+        }
+        self.0.swap(rows[row - 1], rows[row]);
+        row - 1
+    }
+
+    /// Swap the column on `row` of the picker's `rows` with the one on the row below, and return
+    /// the row it went to.
+    pub fn move_down(&mut self, rows: &[usize], row: usize) -> usize {
+        if row + 1 >= rows.len() {
+            return row;
+        }
+        self.0.swap(rows[row], rows[row + 1]);
+        row + 1
+    }
+
+    /// The `cells` of the optional columns, in order and without the hidden ones.
+    pub fn order<T>(&self, mut cells: Vec<(Column, T)>) -> Vec<T> {
+        let position = |column: Column| self.0.iter().position(|(c, _)| *c == column);
+        cells.retain(|(column, _)| self.is_shown(*column));
+        cells.sort_by_key(|(column, _)| position(*column));
+        cells.into_iter().map(|(_, cell)| cell).collect()
+    }
+
+    /// The `cells` of the optional columns of a row of the list, see `order`.
+    pub fn arrange(&self, cells: Vec<(Column, String)>) -> String {
+        self.order(cells).concat()
+    }
+
+    /// The lines of the picker, a checkbox followed by the name of the column of each of `rows`.
+    pub fn picker_lines(&self, rows: &[usize]) -> Vec<String> {
+        rows.iter()
+            .map(|&i| {
+                let (column, shown) = self.0[i];
+                format!("[{}] {}", if shown { 'x' } else { ' ' }, column.name())
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_column_config() {
+        let mut config = ColumnConfig::default();
+        assert_eq!(
+            config.entries().iter().map(|e| e.0).collect::<Vec<_>>(),
+            OPTIONAL_COLUMNS
+        );
+        assert!(config.entries().iter().all(|e| e.1));
+        let all = config.rows(|_| true);
+        assert_eq!(all.len(), 11);
+        assert_eq!(config.picker_lines(&all)[..2], ["[x] threads", "[x] time"]);
+
+        assert_eq!(config.toggle(1), Some(Column::CpuTime));
+        assert!(!config.is_shown(Column::CpuTime));
+        assert_eq!(config.picker_lines(&all)[1], "[ ] time");
+        assert_eq!(config.toggle(1), Some(Column::CpuTime));
+        assert!(config.is_shown(Column::CpuTime));
+        assert_eq!(config.toggle(11), None);
+        /* the leading columns can't be hidden */
+        config.set_shown(Column::Pid, false);
+        assert!(config.is_shown(Column::Pid));
+
+        /* the GPU columns to the front, and back down a row */
+        let mut index = 10;
+        while index > 0 {
+            index = config.move_up(&all, index);
+        }
+        assert_eq!(config.move_up(&all, 0), 0);
+        assert_eq!(config.entries()[0], (Column::Gpu, true));
+        assert_eq!(config.entries()[1], (Column::Threads, true));
+        assert_eq!(config.move_down(&all, 0), 1);
+        assert_eq!(config.entries()[0].0, Column::Threads);
+        assert_eq!(config.entries()[1].0, Column::Gpu);
+        assert_eq!(config.move_down(&all, 10), 10);
+        assert_eq!(config.entries()[10].0, Column::Net);
+
+        config.set_shown(Column::Threads, false);
+        let cells = vec![
+            (Column::Threads, "T ".to_string()),
+            (Column::Io, "I ".to_string()),
+            (Column::Gpu, "G ".to_string()),
+        ];
+        assert_eq!(config.arrange(cells), "G I ");
+    }
+
+    #[test]
+    fn test_column_rows() {
+        let mut config = ColumnConfig::default();
+        /* eg. without --io or --gpu */
+        let rows = config.rows(|column| READ_WHEN_SHOWN.contains(&column));
+        assert_eq!(rows, [0, 1, 2, 7]);
+        assert_eq!(
+            config.picker_lines(&rows),
+            ["[x] threads", "[x] time", "[x] elapsed", "[x] caps"]
+        );
+        /* moving the caps up over the columns that aren't listed */
+        assert_eq!(config.move_up(&rows, 3), 2);
+        assert_eq!(config.entries()[2].0, Column::Caps);
+        assert_eq!(config.entries()[7].0, Column::Elapsed);
+        assert_eq!(config.move_down(&rows, 3), 3);
+        assert_eq!(config.move_up(&rows, 0), 0);
+    }
+}
@@ -0,0 +1,210 @@
+/*
+ * bb
+ *
+ * Copyright 2019 Manos Pitsidianakis
+ *
+ * This file is part of bb.
+ *
+ * bb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * bb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with bb. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+/*! How the columns of the process list are aligned within their widths. Numbers are right-aligned
+and text left-aligned unless `--align` says otherwise.
+*/
+
+use crate::ui::text_processing::grapheme_clusters::Graphemes;
+
+/// Which side of its column a value sits on.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Alignment {
+    Left,
+    Right,
+}
+
+impl Alignment {
+    /// `text` padded with spaces to `width` columns, on the right if `Left` and on the left if
+    /// `Right`. Wide characters take two columns, so that the ones after a value with CJK text in
+    /// it still line up. Longer text is returned as it is.
+    pub fn pad(self, text: &str, width: usize) -> String {
+        let padding = " ".repeat(width.saturating_sub(text.grapheme_width()));
+        match self {
+            Alignment::Left => format!("{}{}", text, padding),
+            Alignment::Right => format!("{}{}", padding, text),
+        }
+    }
+}
+
+/// The columns of a process row, except for the command line which takes the rest of the row.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Column {
+    Pid,
+    Ppid,
+    User,
+    VmRss,
+    Cpu,
+    State,
+    Threads,
+    CpuTime,
+    Elapsed,
+    /// The read and write rate columns.
+    Io,
+    /// The cpu time and memory delta columns.
+    Delta,
+    Cgroup,
+    Affinity,
+    Caps,
+    /// The environment variable column.
+    Env,
+    /// The socket column.
+    Net,
+    /// The GPU memory and utilization columns.
+    Gpu,
+}
+
+/// Names of the columns for `--align`, in the order of `Column`.
+pub const COLUMN_NAMES: &[&str] = &[
+    "pid", "ppid", "user", "rss", "cpu", "state", "threads", "time", "elapsed", "io", "delta",
+    "cgroup", "affinity", "caps", "env", "net", "gpu",
+];
+
+impl Column {
+    const ALL: [Column; 17] = [
+        Column::Pid,
+        Column::Ppid,
+        Column::User,
+        Column::VmRss,
+        Column::Cpu,
+        Column::State,
+        Column::Threads,
+        Column::CpuTime,
+        Column::Elapsed,
+        Column::Io,
+        Column::Delta,
+        Column::Cgroup,
+        Column::Affinity,
+        Column::Caps,
+        Column::Env,
+        Column::Net,
+        Column::Gpu,
+    ];
+
+    pub fn from_name(name: &str) -> Option<Column> {
+        COLUMN_NAMES
+            .iter()
+            .position(|n| *n == name)
+            .map(|i| Column::ALL[i])
+    }
+
+    /// The name of the column in `COLUMN_NAMES`.
+    pub fn name(self) -> &'static str {
+        COLUMN_NAMES[self as usize]
+    }
+
+    fn default_alignment(self) -> Alignment {
+        match self {
+            Column::User
+            | Column::Cgroup
+            | Column::Affinity
+            | Column::Caps
+            | Column::Env
+            | Column::Net => Alignment::Left,
+            _ => Alignment::Right,
+        }
+    }
+}
+
+/// The alignment of each column.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Alignments([Alignment; 17]);
+
+impl Default for Alignments {
+    fn default() -> Self {
+        let mut ret = [Alignment::Right; 17];
+        for (alignment, column) in ret.iter_mut().zip(Column::ALL.iter()) {
+            *alignment = column.default_alignment();
+        }
+        Alignments(ret)
+    }
+}
+
+impl Alignments {
+    /// Parses the value of `--align`: comma-separated `COLUMN=left` or `COLUMN=right` pairs, eg.
+    /// `user=right,pid=left`. The columns that aren't named keep their default alignment.
+    pub fn parse(spec: &str) -> Result<Alignments, String> {
+        let mut ret = Alignments::default();
+        for pair in spec.split(',') {
+            let (name, side) = pair
+                .split_once('=')
+                .ok_or_else(|| format!("expected COLUMN=left or COLUMN=right, got `{}`", pair))?;
+            let column = Column::from_name(name).ok_or_else(|| {
+                format!(
+                    "unknown column `{}`, expected one of: {}",
+                    name,
+                    COLUMN_NAMES.join(", ")
+                )
+            })?;
+            let alignment = match side {
+                "left" => Alignment::Left,
+                "right" => Alignment::Right,
+                _ => {
+                    return Err(format!(
+                        "unknown alignment `{}`, expected left or right",
+                        side
+                    ))
+                }
+            };
+            ret.0[column as usize] = alignment;
+        }
+        Ok(ret)
+    }
+
+    pub fn get(&self, column: Column) -> Alignment {
+        self.0[column as usize]
+    }
+
+    /// `text` padded to `width` according to the alignment of `column`.
+    pub fn pad(&self, column: Column, text: &str, width: usize) -> String {
+        self.get(column).pad(text, width)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_alignments() {
+        assert_eq!(Alignment::Left.pad("ab", 4), "ab  ");
+        assert_eq!(Alignment::Right.pad("ab", 4), "  ab");
+        assert_eq!(Alignment::Right.pad("abcdef", 4), "abcdef");
+        assert_eq!(Alignment::Left.pad("用户", 6), "用户  ");
+        assert_eq!(Alignment::Right.pad("用户", 6), "  用户");
+
+        let defaults = Alignments::default();
+        assert_eq!(defaults.get(Column::Pid), Alignment::Right);
+        assert_eq!(defaults.get(Column::User), Alignment::Left);
+        assert_eq!(defaults.get(Column::Caps), Alignment::Left);
+        let alignments = Alignments::parse("user=right,cpu=left").unwrap();
+        assert_eq!(alignments.get(Column::User), Alignment::Right);
+        assert_eq!(alignments.get(Column::Cpu), Alignment::Left);
+        assert_eq!(alignments.get(Column::VmRss), Alignment::Right);
+        for name in COLUMN_NAMES {
+            assert_eq!(Column::from_name(name).map(Column::name), Some(*name));
+        }
+
+        assert!(Alignments::parse("user").is_err());
+        assert!(Alignments::parse("usr=left").is_err());
+        assert!(Alignments::parse("user=centre").is_err());
+    }
+}
@@ -0,0 +1,145 @@
+/*
+ * bb
+ *
+ * Copyright 2019 Manos Pitsidianakis
+ *
+ * This file is part of bb.
+ *
+ * bb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * bb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with bb. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+/*! The working directory of the process under the cursor, which `w` shows on the status line or
+opens with the `--open-cwd` command, eg. a file manager.
+
+Like `--hook`, the command is run directly and `{cwd}` is only ever replaced within an argument, so
+a directory with spaces in its name stays one argument.
+*/
+
+use super::Pid;
+use crate::ui::components::utilities::ProcSource;
+use std::path::{Path, PathBuf};
+
+/// What's replaced with the directory in the arguments of a `CwdCommand`.
+pub const CWD_PLACEHOLDER: &str = "{cwd}";
+
+/// The working directory of `pid`, which `[pid]/cwd` links to. The link can only be read by the
+/// owner of the process and root, and the error says so.
+pub fn read_cwd(source: &ProcSource, pid: Pid) -> Result<PathBuf, String> {
+    let unreadable = |err: std::io::Error| {
+        format!(
+            "can't read the working directory of PID {}: {}",
+            pid,
+            match err.kind() {
+                std::io::ErrorKind::PermissionDenied => "permission denied".to_string(),
+                std::io::ErrorKind::NotFound => "no such process".to_string(),
+                _ => err.to_string(),
+            }
+        )
+    };
+    let link = source.path(format!("{}/cwd", pid)).map_err(unreadable)?;
+    let target = std::fs::read_link(&link).map_err(unreadable)?;
+    /* procfs links are always absolute, the others are relative to where they are */
+    if target.is_absolute() {
+        Ok(target)
+    } else {
+        Ok(link.parent().unwrap_or(Path::new("/")).join(target))
+    }
+}
+
+/// A program and its arguments, some of which contain `{cwd}`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CwdCommand {
+    template: Vec<String>,
+}
+
+impl CwdCommand {
+    /// Split `template` into arguments at whitespace. At least one needs a `{cwd}`.
+    pub fn parse(template: &str) -> Result<CwdCommand, String> {
+        let template = template
+            .split_whitespace()
+            .map(str::to_string)
+            .collect::<Vec<String>>();
+        if template.is_empty() {
+            return Err("empty --open-cwd command".to_string());
+        }
+        if !template.iter().any(|arg| arg.contains(CWD_PLACEHOLDER)) {
+            return Err(format!(
+                "--open-cwd command `{}` has no {}",
+                template.join(" "),
+                CWD_PLACEHOLDER
+            ));
+        }
+        Ok(CwdCommand { template })
+    }
+
+    /// The program to open `cwd` with followed by its arguments.
+    pub fn argv(&self, cwd: &Path) -> Vec<String> {
+        let cwd = cwd.to_string_lossy();
+        self.template
+            .iter()
+            .map(|arg| arg.replace(CWD_PLACEHOLDER, &cwd))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_cwd() {
+        let dir = std::env::temp_dir().join(format!("bb-test-cwd-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("1")).unwrap();
+        std::fs::create_dir_all(dir.join("2")).unwrap();
+        std::fs::create_dir_all(dir.join("work dir")).unwrap();
+        std::os::unix::fs::symlink(dir.join("work dir"), dir.join("1/cwd")).unwrap();
+        std::os::unix::fs::symlink("../work dir", dir.join("2/cwd")).unwrap();
+        let source = ProcSource::fixture(&dir);
+        assert_eq!(read_cwd(&source, 1), Ok(dir.join("work dir")));
+        /* a relative link is resolved from where it is */
+        assert_eq!(read_cwd(&source, 2), Ok(dir.join("2/../work dir")));
+
+        let open = CwdCommand::parse("xdg-open {cwd}").unwrap();
+        assert_eq!(
+            open.argv(&read_cwd(&source, 1).unwrap()),
+            [
+                "xdg-open".to_string(),
+                dir.join("work dir").display().to_string()
+            ]
+        );
+        assert!(CwdCommand::parse("").is_err());
+        assert_eq!(
+            CwdCommand::parse("nautilus"),
+            Err("--open-cwd command `nautilus` has no {cwd}".to_string())
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_unreadable_cwd() {
+        let dir =
+            std::env::temp_dir().join(format!("bb-test-cwd-unreadable-{}", std::process::id()));
+        /* what can't be read as a link is an error, like the cwd of a process of another user */
+        std::fs::create_dir_all(dir.join("1/cwd")).unwrap();
+        let source = ProcSource::fixture(&dir);
+        assert!(read_cwd(&source, 1)
+            .unwrap_err()
+            .starts_with("can't read the working directory of PID 1: "));
+        assert_eq!(
+            read_cwd(&source, 2),
+            Err("can't read the working directory of PID 2: no such process".to_string())
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
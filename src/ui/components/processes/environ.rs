@@ -0,0 +1,165 @@
+/*
+ * bb
+ *
+ * Copyright 2019 Manos Pitsidianakis
+ *
+ * This file is part of bb.
+ *
+ * bb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * bb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with bb. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+/*! The optional column with the value of an environment variable, from `/proc/[pid]/environ`.
+
+Reading another process's environment is only allowed to its owner or with `CAP_SYS_PTRACE`, and
+it's a file to open per process, so the column is opt-in and only read for the rows around the
+current page, once per sample.
+*/
+
+use super::Pid;
+use crate::ui::components::utilities::ProcSource;
+use std::collections::HashMap;
+
+/// Shown for processes whose environment can't be read.
+pub const UNREADABLE: &str = "-";
+
+/* Values are shortened to this many characters, the last one an ellipsis, so that a long one like
+ * a `PATH` doesn't push the command lines off the screen */
+const MAX_VALUE_LEN: usize = 40;
+
+/// Whether `key` can be looked up: not empty, and without `=` or null bytes.
+pub fn valid_key(key: &str) -> bool {
+    !key.is_empty() && !key.contains(['=', '\0'])
+}
+
+/// The value of `key` in `environ`, the contents of a `/proc/[pid]/environ`: `KEY=value` entries,
+/// each followed by a null byte. If `key` is set more than once, the first entry wins, as with
+/// `getenv`.
+pub fn environ_value<'a>(environ: &'a [u8], key: &str) -> Option<&'a [u8]> {
+    environ
+        .split(|&b| b == 0)
+        .find_map(|entry| entry.strip_prefix(key.as_bytes())?.strip_prefix(b"="))
+}
+
+/// The values of one environment variable, read as the rows that show them are formatted.
+#[derive(Debug)]
+pub struct EnvColumn {
+    key: String,
+    source: ProcSource,
+    /* what was read since the last sample, `None` if the environment couldn't be read */
+    values: HashMap<Pid, Option<String>>,
+}
+
+impl EnvColumn {
+    pub fn new(key: String, source: ProcSource) -> EnvColumn {
+        EnvColumn {
+            key,
+            source,
+            values: HashMap::new(),
+        }
+    }
+
+    /// The name of the variable, which is the header of the column.
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// What the column shows for `pid`: the value of the variable, nothing if it isn't set, or
+    /// `UNREADABLE`. The environment is read the first time after a `clear`.
+    pub fn value(&mut self, pid: Pid) -> &str {
+        let EnvColumn {
+            ref key,
+            ref source,
+            ref mut values,
+        } = *self;
+        values
+            .entry(pid)
+            .or_insert_with(|| {
+                let path = source.path(format!("{}/environ", pid)).ok()?;
+                let environ = std::fs::read(path).ok()?;
+                let value = environ_value(&environ, key).unwrap_or_default();
+                Some(shorten(&String::from_utf8_lossy(value)))
+            })
+            .as_deref()
+            .unwrap_or(UNREADABLE)
+    }
+
+    /// Forget the values read so far, for a new sample.
+    pub fn clear(&mut self) {
+        self.values.clear();
+    }
+}
+
+/* `value`, shortened to `MAX_VALUE_LEN` characters */
+fn shorten(value: &str) -> String {
+    if value.chars().count() <= MAX_VALUE_LEN {
+        return value.to_string();
+    }
+    let mut ret = value.chars().take(MAX_VALUE_LEN - 1).collect::<String>();
+    ret.push('…');
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_environ_value() {
+        let environ = b"HOME=/root\0RUST_LOG=bb=debug,info\0EMPTY=\0RUST_LOG=trace\0LANG=C\0";
+        assert_eq!(
+            environ_value(environ, "RUST_LOG"),
+            Some(&b"bb=debug,info"[..])
+        );
+        assert_eq!(environ_value(environ, "HOME"), Some(&b"/root"[..]));
+        assert_eq!(environ_value(environ, "EMPTY"), Some(&b""[..]));
+        assert_eq!(environ_value(environ, "LANG"), Some(&b"C"[..]));
+        /* prefixes of keys and values don't match */
+        assert_eq!(environ_value(environ, "RUST"), None);
+        assert_eq!(environ_value(environ, "bb"), None);
+        assert_eq!(environ_value(environ, "PATH"), None);
+        assert_eq!(environ_value(b"", "HOME"), None);
+        /* the last entry might not have its null byte if the environment was rewritten */
+        assert_eq!(environ_value(b"A=1\0B=2", "B"), Some(&b"2"[..]));
+
+        assert!(valid_key("RUST_LOG"));
+        assert!(!valid_key(""));
+        assert!(!valid_key("A=B"));
+    }
+
+    #[test]
+    fn test_env_column() {
+        let dir = std::env::temp_dir().join(format!("bb-test-environ-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("1")).unwrap();
+        std::fs::create_dir_all(dir.join("2")).unwrap();
+        std::fs::write(dir.join("1/environ"), b"TERM=xterm\0RUST_LOG=warn\0").unwrap();
+        std::fs::write(dir.join("2/environ"), b"TERM=xterm\0").unwrap();
+        let mut column = EnvColumn::new("RUST_LOG".to_string(), ProcSource::fixture(&dir));
+        assert_eq!(column.value(1), "warn");
+        assert_eq!(column.value(2), "");
+        assert_eq!(column.value(3), UNREADABLE);
+
+        /* read once per sample */
+        std::fs::write(dir.join("1/environ"), b"RUST_LOG=debug\0").unwrap();
+        assert_eq!(column.value(1), "warn");
+        column.clear();
+        assert_eq!(column.value(1), "debug");
+
+        let path = "/usr/local/sbin:/usr/local/bin:/usr/sbin:/usr/bin:/sbin:/bin";
+        std::fs::write(dir.join("2/environ"), format!("RUST_LOG={}\0", path)).unwrap();
+        column.clear();
+        assert_eq!(column.value(2), "/usr/local/sbin:/usr/local/bin:/usr/sbi…");
+        assert_eq!(column.value(2).chars().count(), MAX_VALUE_LEN);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
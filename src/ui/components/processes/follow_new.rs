@@ -0,0 +1,88 @@
+/*
+ * bb
+ *
+ * Copyright 2019 Manos Pitsidianakis
+ *
+ * This file is part of bb.
+ *
+ * bb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * bb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with bb. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+/*! The cursor moved onto the newest process as processes start, with `--follow-new`, to watch a
+build or a test run spawn them in a list sorted by start time.
+
+The list is left alone while it's being scrolled: the cursor only follows new processes once it
+wasn't moved for `FOLLOW_IDLE`.
+*/
+
+use super::{Pid, ProcessDisplay};
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
+
+/// How long after the cursor was last moved by hand new processes are followed again.
+pub const FOLLOW_IDLE: Duration = Duration::from_secs(3);
+
+#[derive(Debug, Default)]
+pub struct FollowNew {
+    /* the processes of the last sample */
+    pids: HashSet<Pid>,
+    /* whether the processes that were there when bb started have been recorded */
+    primed: bool,
+    moved_at: Option<Instant>,
+    /* the newest process of the last sample that wasn't in the one before */
+    newest: Option<Pid>,
+}
+
+impl FollowNew {
+    pub fn new() -> FollowNew {
+        FollowNew::default()
+    }
+
+    /// The cursor was moved by hand at `now`.
+    pub fn moved(&mut self, now: Instant) {
+        self.moved_at = Some(now);
+    }
+
+    /// Whether the cursor wasn't moved by hand for `FOLLOW_IDLE` at `now`.
+    pub fn is_idle(&self, now: Instant) -> bool {
+        self.moved_at
+            .is_none_or(|at| now.saturating_duration_since(at) >= FOLLOW_IDLE)
+    }
+
+    /* Note the processes that appeared on a `new_sample`, the newest of which is followed: the one
+     * that started last, or the one with the highest PID of those that started in the same
+     * second. The processes of the first sample aren't new, they were there before bb. */
+    pub fn record(&mut self, processes: &[ProcessDisplay], new_sample: bool) {
+        if self.primed && !new_sample {
+            return;
+        }
+        let processes = processes.iter().filter(|p| p.members == 0);
+        let pids = processes.clone().map(|p| p.i).collect::<HashSet<Pid>>();
+        if self.primed {
+            self.newest = processes
+                .filter(|p| !self.pids.contains(&p.i))
+                .max_by_key(|p| (std::cmp::Reverse(p.elapsed.unwrap_or(u64::MAX)), p.i))
+                .map(|p| p.i);
+        }
+        self.pids = pids;
+        self.primed = true;
+    }
+
+    /// The newest process to move the cursor onto, if one appeared and the cursor is idle at
+    /// `now`. Each is only returned once.
+    pub fn take_newest(&mut self, now: Instant) -> Option<Pid> {
+        let newest = self.newest.take();
+        newest.filter(|_| self.is_idle(now))
+    }
+}
@@ -0,0 +1,126 @@
+/*
+ * bb
+ *
+ * Copyright 2019 Manos Pitsidianakis
+ *
+ * This file is part of bb.
+ *
+ * bb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * bb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with bb. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+/*! The GPU columns of `--gpu`: the memory each process has on NVIDIA GPUs and how busy it keeps
+their cores, from NVML, the library of NVIDIA's driver.
+
+NVML is only looked for in builds with the `nvml` feature, see `nvml`, which loads it at run time
+so that bb still starts on machines without the driver. Without the feature or without a GPU, there
+is nothing to show and the columns don't appear. Processes that don't use a GPU have empty cells.
+*/
+
+use super::{Pid, ProcessDisplay};
+use crate::ui::components::utilities::Bytes;
+use std::collections::HashMap;
+
+/// Widths of the memory and utilization columns, which fit their headers.
+pub const MEM_WIDTH: usize = "GPU_MEM".len();
+pub const UTIL_WIDTH: usize = "GPU%".len();
+
+/// What a process uses of the GPUs, added up over all of them.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct GpuUsage {
+    /// Memory in bytes.
+    pub memory: u64,
+    /// Percentage of the time the cores were working for the process, `None` until NVML has a
+    /// sample of it.
+    pub utilization: Option<u32>,
+}
+
+impl GpuUsage {
+    /// The cells of the memory and utilization columns.
+    pub fn labels(usage: Option<GpuUsage>) -> (String, String) {
+        match usage {
+            Some(usage) => (
+                Bytes(usage.memory as usize).as_short_string(),
+                usage
+                    .utilization
+                    .map_or_else(|| "-".to_string(), |percent| format!("{}%", percent)),
+            ),
+            None => (String::new(), String::new()),
+        }
+    }
+
+    /// The usage of the processes of a group of the grouped view.
+    pub fn sum(usages: impl Iterator<Item = GpuUsage>) -> Option<GpuUsage> {
+        usages.reduce(|a, b| GpuUsage {
+            memory: a.memory + b.memory,
+            utilization: match (a.utilization, b.utilization) {
+                (None, None) => None,
+                (a, b) => Some(a.unwrap_or(0) + b.unwrap_or(0)),
+            },
+        })
+    }
+}
+
+/// The GPUs of the machine and what the processes last used of them.
+#[derive(Debug)]
+#[cfg_attr(not(feature = "nvml"), allow(dead_code))]
+pub struct Gpus {
+    #[cfg(feature = "nvml")]
+    nvml: Option<super::nvml::Nvml>,
+    usage: HashMap<Pid, GpuUsage>,
+}
+
+#[cfg_attr(not(feature = "nvml"), allow(dead_code))]
+impl Gpus {
+    /// The GPUs NVML finds, or `None` without NVML or a GPU, or in builds without the `nvml`
+    /// feature.
+    pub fn open() -> Option<Gpus> {
+        #[cfg(feature = "nvml")]
+        {
+            super::nvml::Nvml::open().map(|nvml| Gpus {
+                nvml: Some(nvml),
+                usage: HashMap::new(),
+            })
+        }
+        #[cfg(not(feature = "nvml"))]
+        {
+            None
+        }
+    }
+
+    /// GPUs that always report `usage`, for tests.
+    #[cfg(all(test, feature = "nvml"))]
+    pub fn with_usage(usage: HashMap<Pid, GpuUsage>) -> Gpus {
+        Gpus { nvml: None, usage }
+    }
+
+    /// Ask NVML what the processes use on a `new_sample`, and show it on their rows.
+    pub fn record(&mut self, processes: &mut [ProcessDisplay], new_sample: bool) {
+        if new_sample {
+            self.sample();
+        }
+        for p in processes.iter_mut().filter(|p| p.members == 0) {
+            p.gpu = self.usage.get(&p.i).copied();
+        }
+    }
+
+    #[cfg(feature = "nvml")]
+    fn sample(&mut self) {
+        if let Some(ref mut nvml) = self.nvml {
+            self.usage = nvml.usage();
+        }
+    }
+
+    #[cfg(not(feature = "nvml"))]
+    fn sample(&mut self) {}
+}
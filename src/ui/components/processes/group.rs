@@ -0,0 +1,217 @@
+/*
+ * bb
+ *
+ * Copyright 2019 Manos Pitsidianakis
+ *
+ * This file is part of bb.
+ *
+ * bb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * bb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with bb. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+/*! The grouped view, which merges processes running the same command into one row.
+
+A group's row is a `ProcessDisplay` like any other, with the sums of its members' cpu usage, memory
+and I/O rates. The rows are appended to the process list after the processes they aggregate, so that
+indices to the processes stay valid, and are told apart by `ProcessDisplay::members`. Expanded
+groups are followed by their members.
+*/
+
+use super::*;
+use std::collections::BTreeMap;
+
+/* Processes in the list, indexed by the name of their command, see `process_name` */
+fn members_by_name(processes: &[ProcessDisplay]) -> BTreeMap<&str, Vec<usize>> {
+    let mut ret: BTreeMap<&str, Vec<usize>> = BTreeMap::new();
+    for (i, p) in processes.iter().enumerate() {
+        if p.members == 0 {
+            ret.entry(process_name(&p.cmd_line.0)).or_default().push(i);
+        }
+    }
+    ret
+}
+
+/* The row of a group. It takes the PID of its first member, so that the cursor and the PID based
+ * actions have something to refer to, but shows none. */
+fn aggregate(name: &str, members: &[&ProcessDisplay]) -> ProcessDisplay {
+    let first = members.iter().min_by_key(|p| p.i).unwrap();
+    let common = |value: &dyn Fn(&ProcessDisplay) -> &str| {
+        if members.iter().all(|p| value(p) == value(first)) {
+            value(first).to_string()
+        } else {
+            "*".to_string()
+        }
+    };
+    let sum = |value: &dyn Fn(&ProcessDisplay) -> Option<usize>| {
+        members
+            .iter()
+            .filter_map(|p| value(p))
+            .fold(None, |sum, value| Some(sum.unwrap_or(0) + value))
+    };
+    let vm_rss_value = members.iter().map(|p| p.vm_rss_value).sum();
+    ProcessDisplay {
+        i: first.i,
+        p: 0,
+        pgrp: 0,
+        pid: PidString("-".to_string()),
+        ppid: PpidString("-".to_string()),
+        vm_rss: VmRssString(Bytes(vm_rss_value).as_convenient_string()),
+        vm_rss_value,
+        cpu_percent: sum(&|p| p.cpu_percent),
+        cpu_percent_raw: sum(&|p| p.cpu_percent_raw),
+        state: if members.iter().any(|p| p.state == State::Running) {
+            State::Running
+        } else {
+            first.state
+        },
+        cmd_line: CmdLineString(name.to_string()),
+        username: UserString(common(&|p| &p.username.0)),
+        sparkline: None,
+        threads: sum(&|p| p.threads),
+        cpu_time: sum(&|p| p.cpu_time),
+        elapsed: members.iter().filter_map(|p| p.elapsed).max(),
+        io: if members.iter().any(|p| p.io.is_some()) {
+            Some(members.iter().filter_map(|p| p.io.flatten()).fold(
+                None,
+                |sum: Option<IoRates>, rates| {
+                    let sum = sum.unwrap_or(IoRates { read: 0, write: 0 });
+                    Some(IoRates {
+                        read: sum.read.saturating_add(rates.read),
+                        write: sum.write.saturating_add(rates.write),
+                    })
+                },
+            ))
+        } else {
+            None
+        },
+        delta: sum_deltas(members.iter().filter_map(|p| p.delta)),
+        cgroup: None,
+        affinity: None,
+        /* what the members can do between them */
+        caps: members.iter().filter_map(|p| p.caps).reduce(|a, b| a | b),
+        env: None,
+        net: None,
+        gpu: GpuUsage::sum(members.iter().filter_map(|p| p.gpu)),
+        pid_ns: if members.iter().all(|p| p.pid_ns == first.pid_ns) {
+            first.pid_ns
+        } else {
+            None
+        },
+        category: if members.iter().all(|p| p.category == first.category) {
+            first.category
+        } else {
+            Category::Other
+        },
+        rtime: members.iter().map(|p| p.rtime).sum(),
+        members: members.len(),
+        formatted: true,
+    }
+}
+
+/* Replace the group rows at the end of `processes` with rows for its current processes. */
+pub fn regroup(processes: &mut Vec<ProcessDisplay>, grouped: bool) {
+    processes.retain(|p| p.members == 0);
+    if !grouped {
+        return;
+    }
+    let groups = members_by_name(processes)
+        .into_iter()
+        .map(|(name, members)| {
+            aggregate(
+                name,
+                &members.iter().map(|&i| &processes[i]).collect::<Vec<_>>(),
+            )
+        })
+        .collect::<Vec<ProcessDisplay>>();
+    processes.extend(groups);
+}
+
+/* Indices into `processes` of the rows of the grouped view, in display order: the groups with a
+ * member that matches `filter`, each followed by its matching members if it's `expanded`. */
+pub fn grouped_indices(
+    processes: &[ProcessDisplay],
+    sort: Sort,
+    secondary: Option<SecondarySort>,
+    filter: Option<&Filter>,
+    expanded: &HashSet<String>,
+) -> Vec<usize> {
+    let by_name = members_by_name(processes);
+    let mut groups = (0..processes.len())
+        .filter(|&i| processes[i].members > 0)
+        .collect::<Vec<usize>>();
+    groups.sort_unstable_by(|a, b| sort_cmp(sort, secondary, &processes[*a], &processes[*b]));
+    let mut ret = Vec::with_capacity(groups.len());
+    for i in groups {
+        let name = processes[i].cmd_line.0.as_str();
+        let mut members = by_name.get(name).cloned().unwrap_or_default();
+        if let Some(filter) = filter {
+            members.retain(|&m| filter.matches(&processes[m].cmd_line.0));
+            if members.is_empty() {
+                continue;
+            }
+        }
+        ret.push(i);
+        if expanded.contains(name) {
+            members
+                .sort_unstable_by(|a, b| sort_cmp(sort, secondary, &processes[*a], &processes[*b]));
+            ret.extend(members);
+        }
+    }
+    ret
+}
+
+/* What's drawn between the columns and the command line of row `i` of the grouped view: the
+ * member count of groups, and tree view like guides for members. `next` is the row after `i`. */
+pub fn group_branches(
+    processes: &[ProcessDisplay],
+    i: usize,
+    next: Option<usize>,
+    expanded: &HashSet<String>,
+) -> String {
+    let p = &processes[i];
+    if p.members > 0 {
+        let sign = if expanded.contains(&p.cmd_line.0) {
+            '-'
+        } else {
+            '+'
+        };
+        format!("{}{:<4} ", sign, format!("({})", p.members))
+    } else if next.is_some_and(|next| processes[next].members == 0) {
+        " ├─> ".to_string()
+    } else {
+        " └─> ".to_string()
+    }
+}
+
+/// A row of the grouped view, by what stays the same when the groups are recomputed.
+#[derive(Debug, Clone, PartialEq)]
+pub enum GroupedRow {
+    Group(String),
+    Process(Pid),
+}
+
+impl GroupedRow {
+    pub fn of(p: &ProcessDisplay) -> GroupedRow {
+        if p.members > 0 {
+            GroupedRow::Group(p.cmd_line.0.clone())
+        } else {
+            GroupedRow::Process(p.i)
+        }
+    }
+
+    /* Position of `self` in `rows`, indices into `processes` */
+    pub fn find(&self, processes: &[ProcessDisplay], rows: &[usize]) -> Option<usize> {
+        rows.iter()
+            .position(|&i| GroupedRow::of(&processes[i]) == *self)
+    }
+}
@@ -0,0 +1,141 @@
+/*
+ * bb
+ *
+ * Copyright 2019 Manos Pitsidianakis
+ *
+ * This file is part of bb.
+ *
+ * bb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * bb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with bb. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+/*! How fast the resident memory of each process grew over the last few samples, for highlighting
+ones that might be leaking with `--highlight-mem-growth`.
+
+Like the cpu histories, only the last `GROWTH_LEN` samples of the processes in the latest snapshot
+are kept.
+*/
+
+use super::{Pid, ProcessDisplay};
+use crate::ui::Color;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::time::Instant;
+
+/// Foreground of the rows of processes whose memory grows faster than the threshold.
+pub const MEM_GROWTH_FG: Color = Color::Byte(208); // DarkOrange
+
+/// Number of samples the growth rate is computed over.
+pub const GROWTH_LEN: usize = 5;
+
+/// The last `GROWTH_LEN` resident memory samples of a process in bytes, oldest first, each with
+/// the time it was taken.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct RssHistory {
+    samples: VecDeque<(Instant, usize)>,
+}
+
+impl RssHistory {
+    fn push(&mut self, at: Instant, rss: usize) {
+        if self.samples.len() == GROWTH_LEN {
+            self.samples.pop_front();
+        }
+        self.samples.push_back((at, rss));
+    }
+
+    /// Bytes per second the memory grew by from the oldest sample to the newest one, negative if
+    /// it shrank. `None` until there are two samples.
+    pub fn rate(&self) -> Option<f64> {
+        let (first_at, first) = *self.samples.front()?;
+        let (last_at, last) = *self.samples.back()?;
+        let secs = last_at.saturating_duration_since(first_at).as_secs_f64();
+        if secs == 0.0 {
+            return None;
+        }
+        Some((last as f64 - first as f64) / secs)
+    }
+}
+
+/// The memory histories of the processes, and the growth rate above which they're highlighted.
+#[derive(Debug)]
+pub struct MemGrowth {
+    /* in bytes per second */
+    threshold: f64,
+    histories: HashMap<Pid, RssHistory>,
+}
+
+impl MemGrowth {
+    /// `threshold` is in bytes per second.
+    pub fn new(threshold: f64) -> MemGrowth {
+        MemGrowth {
+            threshold,
+            histories: HashMap::new(),
+        }
+    }
+
+    /* Add the memory of each process to its history on a `new_sample`, taken at `now`, and drop
+     * the histories of processes that exited. A process without a cpu usage is in its first
+     * snapshot, even if a process that exited had its PID, so its history starts over. */
+    pub fn record(&mut self, processes: &[ProcessDisplay], new_sample: bool, now: Instant) {
+        if !new_sample {
+            return;
+        }
+        let processes = processes.iter().filter(|p| p.members == 0);
+        let pids = processes.clone().map(|p| p.i).collect::<HashSet<Pid>>();
+        self.histories.retain(|pid, _| pids.contains(pid));
+        for p in processes {
+            if p.cpu_percent_raw.is_none() {
+                self.histories.remove(&p.i);
+            }
+            self.histories
+                .entry(p.i)
+                .or_default()
+                .push(now, p.vm_rss_value);
+        }
+    }
+
+    /// Whether the memory of the process with `pid` grew faster than the threshold.
+    pub fn is_growing(&self, pid: Pid) -> bool {
+        self.histories
+            .get(&pid)
+            .and_then(RssHistory::rate)
+            .is_some_and(|rate| rate > self.threshold)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_growth_rate() {
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+        let mut history = RssHistory::default();
+        assert_eq!(history.rate(), None);
+        history.push(at(0), 1000);
+        assert_eq!(history.rate(), None);
+        history.push(at(2), 5000);
+        assert_eq!(history.rate(), Some(2000.0));
+        history.push(at(4), 3000);
+        assert_eq!(history.rate(), Some(500.0));
+        /* only the last `GROWTH_LEN` samples count */
+        for secs in 5..5 + GROWTH_LEN as u64 {
+            history.push(at(secs), 3000);
+        }
+        assert_eq!(history.samples.len(), GROWTH_LEN);
+        assert_eq!(history.rate(), Some(0.0));
+        history.push(at(20), 0);
+        assert!(history.rate().unwrap() < 0.0);
+    }
+}
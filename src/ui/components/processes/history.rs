@@ -0,0 +1,231 @@
+/*
+ * bb
+ *
+ * Copyright 2019 Manos Pitsidianakis
+ *
+ * This file is part of bb.
+ *
+ * bb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * bb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with bb. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+/*! Recent cpu usage of each process, for the optional sparkline column.
+
+Only the last `HISTORY_LEN` samples of the processes in the latest snapshot are kept, so the memory
+used is bounded by the number of live processes.
+*/
+
+use super::{Pid, ProcessDisplay};
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// Number of samples kept per process, which is also the width of the sparkline column.
+pub const HISTORY_LEN: usize = 8;
+
+/* One cpu fully used, in the units of `ProcessDisplay::cpu_percent` */
+const FULL_CPU: usize = 10000;
+
+const BLOCKS: [char; 9] = [' ', '▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// Drawn for idle samples instead of a blank with a baseline, see `CpuHistory::sparkline`.
+pub const BASELINE: char = '_';
+
+/// Which side of a sparkline the newest sample is on. Older samples are pushed towards the other
+/// side as new ones come in, to the left by default, and to the right with `NewestLeft` like on a
+/// seismograph.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub enum GraphDirection {
+    #[default]
+    NewestRight,
+    NewestLeft,
+}
+
+impl GraphDirection {
+    /// The column of the sample `age` samples older than the newest one in a sparkline of `width`
+    /// columns, if it fits.
+    pub fn column(self, age: usize, width: usize) -> Option<usize> {
+        if age >= width {
+            return None;
+        }
+        Some(match self {
+            GraphDirection::NewestRight => width - 1 - age,
+            GraphDirection::NewestLeft => age,
+        })
+    }
+
+    /// The header of the sparkline column, which reads from the oldest samples to the newest.
+    pub fn label(self) -> &'static str {
+        match self {
+            GraphDirection::NewestRight => "CPU HIST",
+            GraphDirection::NewestLeft => "HIST CPU",
+        }
+    }
+}
+
+/// The last `HISTORY_LEN` cpu usage samples of a process, oldest first.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct CpuHistory {
+    samples: VecDeque<usize>,
+}
+
+impl CpuHistory {
+    fn push(&mut self, sample: usize) {
+        if self.samples.len() == HISTORY_LEN {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+    }
+
+    /// The samples as a sparkline of `width` cells, with the newest on the side of `direction` and
+    /// the cells without a sample on the other.
+    ///
+    /// Bars are scaled to one fully used cpu, or to the highest sample if a multithreaded process
+    /// used more than that. Idle samples are blank, like the cells without a sample, unless
+    /// `baseline` is set, which draws them as `BASELINE`.
+    pub fn sparkline(&self, width: usize, baseline: bool, direction: GraphDirection) -> String {
+        let samples = self.samples.iter().rev().take(width);
+        let max = samples.clone().cloned().fold(FULL_CPU, std::cmp::max);
+        let mut ret = vec![' '; width];
+        for (age, &sample) in samples.enumerate() {
+            if let Some(column) = direction.column(age, width) {
+                ret[column] = match block(sample, max) {
+                    ' ' if baseline => BASELINE,
+                    block => block,
+                };
+            }
+        }
+        ret.into_iter().collect()
+    }
+}
+
+/* The block character of `value` on a scale of 0 to `max`. Any usage gets at least the lowest bar,
+ * so that only idle samples are blank. */
+fn block(value: usize, max: usize) -> char {
+    if max == 0 {
+        return BLOCKS[0];
+    }
+    let steps = BLOCKS.len() - 1;
+    let level = (std::cmp::min(value, max) * steps).div_ceil(max);
+    BLOCKS[level]
+}
+
+/* Add the cpu usage of each process to its history and fill in `ProcessDisplay::sparkline`. As with
+ * `smooth_cpu_percent`, samples are only added on a `new_sample`, which also drops the histories of
+ * processes that exited. */
+pub fn record_cpu_history(
+    processes: &mut [ProcessDisplay],
+    histories: &mut HashMap<Pid, CpuHistory>,
+    new_sample: bool,
+    baseline: bool,
+    direction: GraphDirection,
+) {
+    if new_sample {
+        let pids = processes.iter().map(|p| p.i).collect::<HashSet<Pid>>();
+        histories.retain(|pid, _| pids.contains(pid));
+    }
+    for p in processes {
+        let sample = match p.cpu_percent_raw {
+            Some(sample) => sample,
+            None => {
+                /* a new process, possibly with the PID of one that exited */
+                if new_sample {
+                    histories.remove(&p.i);
+                }
+                p.sparkline = Some(" ".repeat(HISTORY_LEN));
+                continue;
+            }
+        };
+        let history = histories.entry(p.i).or_default();
+        if new_sample || history.samples.is_empty() {
+            history.push(sample);
+        }
+        p.sparkline = Some(history.sparkline(HISTORY_LEN, baseline, direction));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use GraphDirection::*;
+
+    #[test]
+    fn test_block() {
+        assert_eq!(block(0, FULL_CPU), ' ');
+        assert_eq!(block(1, FULL_CPU), '▁');
+        assert_eq!(block(FULL_CPU / 8, FULL_CPU), '▁');
+        assert_eq!(block(FULL_CPU / 8 + 1, FULL_CPU), '▂');
+        assert_eq!(block(FULL_CPU / 2, FULL_CPU), '▄');
+        assert_eq!(block(FULL_CPU, FULL_CPU), '█');
+        assert_eq!(block(3 * FULL_CPU, FULL_CPU), '█');
+        assert_eq!(block(5, 0), ' ');
+
+        let mut history = CpuHistory::default();
+        for sample in &[FULL_CPU, 0, FULL_CPU / 2] {
+            history.push(*sample);
+        }
+        assert_eq!(history.sparkline(5, false, NewestRight), "  █ ▄");
+        assert_eq!(history.sparkline(2, false, NewestRight), " ▄");
+        /* a process using two cpus sets the scale */
+        history.push(2 * FULL_CPU);
+        assert_eq!(history.sparkline(4, false, NewestRight), "▄ ▂█");
+        for _ in 0..2 * HISTORY_LEN {
+            history.push(FULL_CPU);
+        }
+        assert_eq!(history.samples.len(), HISTORY_LEN);
+    }
+
+    #[test]
+    fn test_baseline() {
+        let mut history = CpuHistory::default();
+        for sample in &[0, 0, FULL_CPU / 4, 0, FULL_CPU, 1] {
+            history.push(*sample);
+        }
+        /* cells without a sample stay blank */
+        assert_eq!(history.sparkline(8, true, NewestRight), "  __▂_█▁");
+        assert_eq!(history.sparkline(8, false, NewestRight), "    ▂ █▁");
+        let sparkline = history
+            .sparkline(6, true, NewestRight)
+            .chars()
+            .collect::<Vec<char>>();
+        for (sample, cell) in history.samples.iter().zip(sparkline) {
+            assert_eq!(*sample == 0, cell == BASELINE, "{} {}", sample, cell);
+        }
+    }
+
+    #[test]
+    fn test_graph_direction() {
+        let columns = |direction: GraphDirection| {
+            (0..5)
+                .map(|age| direction.column(age, 4))
+                .collect::<Vec<Option<usize>>>()
+        };
+        assert_eq!(
+            columns(NewestRight),
+            [Some(3), Some(2), Some(1), Some(0), None]
+        );
+        assert_eq!(
+            columns(NewestLeft),
+            [Some(0), Some(1), Some(2), Some(3), None]
+        );
+
+        let mut history = CpuHistory::default();
+        for sample in &[FULL_CPU, 0, FULL_CPU / 2] {
+            history.push(*sample);
+        }
+        assert_eq!(history.sparkline(5, false, NewestRight), "  █ ▄");
+        assert_eq!(history.sparkline(5, true, NewestLeft), "▄_█  ");
+        assert_eq!(history.sparkline(2, false, NewestLeft), "▄ ");
+        /* the header flips with the samples */
+        assert_eq!(NewestRight.label(), "CPU HIST");
+        assert_eq!(NewestLeft.label(), "HIST CPU");
+    }
+}
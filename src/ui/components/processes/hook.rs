@@ -0,0 +1,94 @@
+/*
+ * bb
+ *
+ * Copyright 2019 Manos Pitsidianakis
+ *
+ * This file is part of bb.
+ *
+ * bb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * bb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with bb. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+/*! The command that `--hook` runs on the process under the cursor, eg. `strace -p {pid}`.
+
+The command is run directly, not by a shell, so the PID is only ever a whole argument or part of
+one, and nothing in the template is interpreted other than `{pid}`.
+*/
+
+use super::Pid;
+
+/// What's replaced with the PID in the arguments of a `HookCommand`.
+pub const PID_PLACEHOLDER: &str = "{pid}";
+
+/// A program and its arguments, some of which contain `{pid}`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HookCommand {
+    template: Vec<String>,
+}
+
+impl HookCommand {
+    /// Split `template` into arguments at whitespace. At least one needs a `{pid}`.
+    pub fn parse(template: &str) -> Result<HookCommand, String> {
+        let template = template
+            .split_whitespace()
+            .map(str::to_string)
+            .collect::<Vec<String>>();
+        if template.is_empty() {
+            return Err("empty hook command".to_string());
+        }
+        if !template.iter().any(|arg| arg.contains(PID_PLACEHOLDER)) {
+            return Err(format!(
+                "hook command `{}` has no {}",
+                template.join(" "),
+                PID_PLACEHOLDER
+            ));
+        }
+        Ok(HookCommand { template })
+    }
+
+    /// The program to run on `pid` followed by its arguments.
+    pub fn argv(&self, pid: Pid) -> Vec<String> {
+        let pid = pid.to_string();
+        self.template
+            .iter()
+            .map(|arg| arg.replace(PID_PLACEHOLDER, &pid))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hook_argv() {
+        let hook = HookCommand::parse("strace -f -p {pid}").unwrap();
+        assert_eq!(hook.argv(1234), ["strace", "-f", "-p", "1234"]);
+        assert_eq!(hook.argv(1), ["strace", "-f", "-p", "1"]);
+
+        /* inside arguments, more than once, and with any whitespace between them */
+        let hook = HookCommand::parse("  cat\t/proc/{pid}/status  --label={pid}:{pid}\n").unwrap();
+        assert_eq!(hook.argv(77), ["cat", "/proc/77/status", "--label=77:77"]);
+
+        /* shell syntax is passed through as is, to a program that isn't a shell */
+        let hook = HookCommand::parse("echo {pid};rm $(x) `y` |z").unwrap();
+        assert_eq!(hook.argv(5), ["echo", "5;rm", "$(x)", "`y`", "|z"]);
+
+        assert!(HookCommand::parse("").is_err());
+        assert!(HookCommand::parse("   ").is_err());
+        assert_eq!(
+            HookCommand::parse("htop"),
+            Err("hook command `htop` has no {pid}".to_string())
+        );
+    }
+}
@@ -0,0 +1,94 @@
+/*
+ * bb
+ *
+ * Copyright 2019 Manos Pitsidianakis
+ *
+ * This file is part of bb.
+ *
+ * bb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * bb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with bb. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+/*! The inspector: a box over the process list with the details of one process, opened with Enter in
+the flat view. It follows the process across refreshes until it's closed.
+*/
+
+use super::smaps::{memory_map_fields, MemoryMap};
+use super::*;
+use crate::ui::text_processing::grapheme_clusters::word_break_string;
+
+/* Rows the command line is wrapped to at most, the last one ends with `…` if it doesn't fit */
+const MAX_COMMAND_ROWS: usize = 8;
+
+/// Lines of the inspector of `p`, with the labels padded to the same width and the command line
+/// wrapped to `width` columns, in up to `MAX_COMMAND_ROWS` rows. The summary of its `memory_map`
+/// follows its memory, if it was read.
+pub fn inspector_lines(
+    p: &ProcessDisplay,
+    memory_map: Option<&Result<MemoryMap, String>>,
+    width: usize,
+) -> Vec<String> {
+    let mut fields = vec![
+        ("PID", p.i.to_string()),
+        ("parent", p.p.to_string()),
+        ("group", p.pgrp.to_string()),
+        ("user", p.username.0.clone()),
+        ("state", p.state.to_string()),
+        (
+            "cpu",
+            p.cpu_percent
+                .map_or("--".to_string(), |cpu| format!("{}%", cpu as f64 / 100.0)),
+        ),
+        ("memory", Bytes(p.vm_rss_value).as_convenient_string()),
+    ];
+    if let Some(memory_map) = memory_map {
+        fields.extend(memory_map_fields(memory_map));
+    }
+    if let Some(threads) = p.threads {
+        fields.push(("threads", threads.to_string()));
+    }
+    if let Some(ticks) = p.cpu_time {
+        fields.push(("cpu time", format_cpu_time(ticks, clock_ticks())));
+    }
+    if let Some(secs) = p.elapsed {
+        fields.push(("running", format_elapsed(secs)));
+    }
+    let label_width = fields
+        .iter()
+        .map(|(label, _)| label.len())
+        .chain(Some("command".len()))
+        .max()
+        .unwrap_or(0);
+    let mut ret = fields
+        .into_iter()
+        .map(|(label, value)| format!("{:>w$}  {}", label, value, w = label_width))
+        .collect::<Vec<String>>();
+    let indent = label_width + 2;
+    let rows = word_break_string(p.cmd_line.0.trim_end(), width.saturating_sub(indent).max(1));
+    for (i, row) in rows.iter().take(MAX_COMMAND_ROWS).enumerate() {
+        let label = if i == 0 { "command" } else { "" };
+        let ellipsis = if i + 1 == MAX_COMMAND_ROWS && rows.len() > MAX_COMMAND_ROWS {
+            "…"
+        } else {
+            ""
+        };
+        ret.push(format!(
+            "{:>w$}  {}{}",
+            label,
+            row,
+            ellipsis,
+            w = label_width
+        ));
+    }
+    ret
+}
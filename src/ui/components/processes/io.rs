@@ -0,0 +1,118 @@
+/*
+ * bb
+ *
+ * Copyright 2019 Manos Pitsidianakis
+ *
+ * This file is part of bb.
+ *
+ * bb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * bb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with bb. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+/*! Parsing of `/proc/[pid]/io`, for the optional I/O rate columns.
+
+Of the counters in the file, `read_bytes` and `write_bytes` are the bytes the process caused to be
+fetched from and sent to storage, so reads served from the page cache don't count. The file can
+only be read by the owner of the process and by root.
+*/
+
+/// Bytes a process has read from and written to storage since it started.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct IoCounters {
+    pub read_bytes: u64,
+    pub write_bytes: u64,
+}
+
+/// Bytes per second a process read from and wrote to storage between two snapshots.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct IoRates {
+    pub read: u64,
+    pub write: u64,
+}
+
+/// The counters in the contents of a `/proc/[pid]/io`, if both are there.
+pub fn parse_io(contents: &str) -> Option<IoCounters> {
+    let (mut read_bytes, mut write_bytes) = (None, None);
+    for line in contents.lines() {
+        let mut fields = line.split_whitespace();
+        let counter = match fields.next() {
+            Some("read_bytes:") => &mut read_bytes,
+            Some("write_bytes:") => &mut write_bytes,
+            _ => continue,
+        };
+        *counter = fields.next().and_then(|value| value.parse::<u64>().ok());
+    }
+    Some(IoCounters {
+        read_bytes: read_bytes?,
+        write_bytes: write_bytes?,
+    })
+}
+
+/// Rates of the counters going from `old` to `new` over `seconds`. There are none without `old`,
+/// ie. in the first snapshot a process is in. Counters that went backwards, eg. of a reused PID,
+/// count as no I/O.
+pub fn io_rates(old: Option<IoCounters>, new: IoCounters, seconds: f64) -> Option<IoRates> {
+    let old = old?;
+    if seconds <= 0.0 {
+        return None;
+    }
+    let rate = |old: u64, new: u64| (new.saturating_sub(old) as f64 / seconds) as u64;
+    Some(IoRates {
+        read: rate(old.read_bytes, new.read_bytes),
+        write: rate(old.write_bytes, new.write_bytes),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_io_rates() {
+        let contents = "rchar: 323934931\nwchar: 323929600\nsyscr: 632687\nsyscw: 632675\n\
+                        read_bytes: 4096\nwrite_bytes: 323932160\ncancelled_write_bytes: 0\n";
+        let counters = |read_bytes, write_bytes| IoCounters {
+            read_bytes,
+            write_bytes,
+        };
+        assert_eq!(parse_io(contents), Some(counters(4096, 323932160)));
+        assert_eq!(parse_io("rchar: 1\nwchar: 2\n"), None);
+        assert_eq!(parse_io("read_bytes: 1\nwrite_bytes: x\n"), None);
+        assert_eq!(parse_io(""), None);
+
+        assert_eq!(io_rates(None, counters(100, 100), 1.6), None);
+        assert_eq!(
+            io_rates(Some(counters(0, 1000)), counters(3200, 1000), 1.6),
+            Some(IoRates {
+                read: 2000,
+                write: 0
+            })
+        );
+        assert_eq!(
+            io_rates(Some(counters(0, 0)), counters(1 << 20, 1 << 30), 0.5),
+            Some(IoRates {
+                read: 2 << 20,
+                write: 2 << 30
+            })
+        );
+        /* counters that went backwards, eg. a reused PID, count as no I/O */
+        assert_eq!(
+            io_rates(Some(counters(500, 500)), counters(100, 600), 1.0),
+            Some(IoRates {
+                read: 0,
+                write: 100
+            })
+        );
+        assert_eq!(io_rates(Some(counters(0, 0)), counters(1, 1), 0.0), None);
+    }
+}
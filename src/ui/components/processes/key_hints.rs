@@ -0,0 +1,284 @@
+/*
+ * bb
+ *
+ * Copyright 2019 Manos Pitsidianakis
+ *
+ * This file is part of bb.
+ *
+ * bb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * bb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with bb. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+/*! The footer of key hints, toggled with `?` or shown from the start with `--key-hints`: the keys
+of the most common actions on the last row of the list, eg.
+
+```text
+h Help  Space Filter  / Search  t Tree  k Kill  f Freeze  q Quit
+```
+
+The keys are those of the shortcuts the list dispatches to, so an action `--read-only` takes out,
+like killing, has no hint. The hints that don't fit in the width are put on further pages, which
+the footer turns through every `HINT_PAGE_TIME`.
+*/
+
+use super::*;
+use std::time::Instant;
+
+/// How long each page of hints is shown for, when they don't all fit.
+pub const HINT_PAGE_TIME: Duration = Duration::from_secs(4);
+
+/* The actions with a hint, in the order they're shown in, and their labels */
+const HINTS: &[(&str, &str)] = &[
+    ("toggle help overlay", "Help"),
+    ("filter", "Filter"),
+    ("search process by name", "Search"),
+    ("toggle tree view", "Tree"),
+    ("kill process", "Kill"),
+    ("freeze updates", "Freeze"),
+    ("follow process group", "Follow"),
+    ("pick columns", "Columns"),
+    ("group by command", "Group"),
+    ("cycle views", "Views"),
+    ("toggle key hints", "Hints"),
+];
+
+/* Quitting is handled before the list sees the key, so it isn't in its shortcuts */
+const QUIT_HINT: (&str, &str) = ("q", "Quit");
+
+/* Between two hints */
+const HINT_GAP: &str = "  ";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hint {
+    pub key: String,
+    pub label: &'static str,
+}
+
+impl Hint {
+    fn width(&self) -> usize {
+        self.text().chars().count()
+    }
+
+    fn text(&self) -> String {
+        if self.label.is_empty() {
+            self.key.clone()
+        } else {
+            format!("{} {}", self.key, self.label)
+        }
+    }
+
+    /* Cut the label, and then the key, so that the hint is at most `width` columns wide */
+    fn truncate(mut self, width: usize) -> Hint {
+        let key_width = self.key.chars().count();
+        if key_width >= width {
+            self.key = self.key.chars().take(width).collect();
+            self.label = "";
+        } else {
+            let label_width = width - key_width - 1;
+            if let Some((end, _)) = self.label.char_indices().nth(label_width) {
+                self.label = &self.label[..end];
+            }
+        }
+        self
+    }
+}
+
+/// The hints of the actions `map` has a key for, followed by quitting.
+pub fn hints(map: &ShortcutMap) -> Vec<Hint> {
+    HINTS
+        .iter()
+        .filter_map(|(action, label)| {
+            map.get(action).map(|key| Hint {
+                key: key.to_string(),
+                label,
+            })
+        })
+        .chain(std::iter::once(Hint {
+            key: QUIT_HINT.0.to_string(),
+            label: QUIT_HINT.1,
+        }))
+        .collect()
+}
+
+/// Put `hints` on pages of at most `width` columns, as many on each as fit, in order. A hint
+/// wider than that is cut on a page of its own.
+pub fn paginate(hints: Vec<Hint>, width: usize) -> Vec<Vec<Hint>> {
+    let mut pages: Vec<Vec<Hint>> = vec![];
+    let mut used = 0;
+    for hint in hints {
+        match pages.last_mut() {
+            Some(page) if used + HINT_GAP.len() + hint.width() <= width => {
+                used += HINT_GAP.len() + hint.width();
+                page.push(hint);
+            }
+            _ => {
+                let hint = hint.truncate(width);
+                used = hint.width();
+                pages.push(vec![hint]);
+            }
+        }
+    }
+    pages
+}
+
+/// The text of a page, eg. `h Help  t Tree`.
+pub fn page_text(page: &[Hint]) -> String {
+    page.iter()
+        .map(Hint::text)
+        .collect::<Vec<String>>()
+        .join(HINT_GAP)
+}
+
+/// The footer's page, turned while the hints don't all fit.
+#[derive(Debug, Default)]
+pub struct KeyHints {
+    page: usize,
+    turned_at: Option<Instant>,
+}
+
+impl KeyHints {
+    pub fn new() -> KeyHints {
+        KeyHints::default()
+    }
+
+    /* The page of `pages` to show at `now`, and whether it's another one than before */
+    fn turn(&mut self, pages: usize, now: Instant) -> (usize, bool) {
+        let turned_at = *self.turned_at.get_or_insert(now);
+        let old_page = self.page;
+        if pages <= 1 {
+            self.page = 0;
+        } else if now.saturating_duration_since(turned_at) >= HINT_PAGE_TIME {
+            self.page = (self.page + 1) % pages;
+            self.turned_at = Some(now);
+        } else {
+            self.page %= pages;
+        }
+        (self.page, self.page != old_page)
+    }
+
+    /// Draw the hints of `map` on the first row of `area`, keys in bold, if `force` is set or it's
+    /// time to turn the page. Returns whether anything was drawn.
+    pub fn draw(
+        &mut self,
+        grid: &mut CellBuffer,
+        area: Area,
+        map: &ShortcutMap,
+        now: Instant,
+        force: bool,
+    ) -> bool {
+        let pages = paginate(hints(map), width!(area));
+        let (page, turned) = self.turn(pages.len(), now);
+        if !(force || turned) {
+            return false;
+        }
+        clear_area(grid, area);
+        let bold = Style {
+            attrs: Attr::Bold,
+            ..Style::default()
+        };
+        let mut pos = upper_left!(area);
+        for (i, hint) in pages[page].iter().enumerate() {
+            if i > 0 {
+                pos = write_styled(
+                    HINT_GAP,
+                    grid,
+                    Style::default(),
+                    (pos, bottom_right!(area)),
+                    false,
+                );
+            }
+            pos = write_styled(&hint.key, grid, bold, (pos, bottom_right!(area)), false);
+            if !hint.label.is_empty() {
+                let label = format!(" {}", hint.label);
+                pos = write_styled(
+                    &label,
+                    grid,
+                    Style::default(),
+                    (pos, bottom_right!(area)),
+                    false,
+                );
+            }
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn map(shortcuts: &[(&'static str, Key)]) -> ShortcutMap {
+        shortcuts.iter().cloned().collect()
+    }
+
+    #[test]
+    fn test_hints() {
+        let shortcuts = map(&[
+            ("kill process", Key::Char('k')),
+            ("toggle help overlay", Key::F(1)),
+            ("cycle views", Key::Alt('v')),
+            ("filter", Key::Char(' ')),
+            ("go to parent", Key::Char('p')),
+        ]);
+        /* in the order of `HINTS`, with the map's keys, and only actions that have a hint */
+        assert_eq!(
+            page_text(&hints(&shortcuts)),
+            "F1 Help  Space Filter  k Kill  M-v Views  q Quit"
+        );
+        /* without a kill key, eg. with `--read-only` */
+        let shortcuts = map(&[("toggle help overlay", Key::F(1))]);
+        assert_eq!(page_text(&hints(&shortcuts)), "F1 Help  q Quit");
+    }
+
+    #[test]
+    fn test_paginate() {
+        let shortcuts = map(&[
+            ("toggle help overlay", Key::Char('h')),
+            ("toggle tree view", Key::Char('t')),
+            ("kill process", Key::Char('k')),
+        ]);
+        let page_texts = |width| {
+            paginate(hints(&shortcuts), width)
+                .iter()
+                .map(|page| page_text(page))
+                .collect::<Vec<String>>()
+        };
+        assert_eq!(page_texts(80), ["h Help  t Tree  k Kill  q Quit"]);
+        assert_eq!(page_texts(30), ["h Help  t Tree  k Kill  q Quit"]);
+        assert_eq!(page_texts(29), ["h Help  t Tree  k Kill", "q Quit"]);
+        assert_eq!(page_texts(14), ["h Help  t Tree", "k Kill  q Quit"]);
+        /* hints wider than the footer are cut */
+        assert_eq!(page_texts(4), ["h He", "t Tr", "k Ki", "q Qu"]);
+        assert_eq!(page_texts(1), ["h", "t", "k", "q"]);
+        for width in 1..40 {
+            for page in paginate(hints(&shortcuts), width) {
+                assert!(page_text(&page).len() <= width, "{}: {:?}", width, page);
+            }
+        }
+    }
+
+    #[test]
+    fn test_key_hints_turn() {
+        let start = Instant::now();
+        let mut key_hints = KeyHints::new();
+        assert_eq!(key_hints.turn(3, start), (0, false));
+        assert_eq!(key_hints.turn(3, start + HINT_PAGE_TIME / 2), (0, false));
+        assert_eq!(key_hints.turn(3, start + HINT_PAGE_TIME), (1, true));
+        assert_eq!(key_hints.turn(3, start + HINT_PAGE_TIME * 2), (2, true));
+        assert_eq!(key_hints.turn(3, start + HINT_PAGE_TIME * 3), (0, true));
+        /* once they all fit again, eg. after a resize */
+        assert_eq!(key_hints.turn(3, start + HINT_PAGE_TIME * 4), (1, true));
+        assert_eq!(key_hints.turn(1, start + HINT_PAGE_TIME * 4), (0, true));
+        assert_eq!(key_hints.turn(1, start + HINT_PAGE_TIME * 9), (0, false));
+    }
+}
@@ -0,0 +1,366 @@
+/*
+ * bb
+ *
+ * Copyright 2019 Manos Pitsidianakis
+ *
+ * This file is part of bb.
+ *
+ * bb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * bb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with bb. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+/*! NVML, the NVIDIA Management Library, for the GPU columns. Only built with the `nvml` feature.
+
+The library comes with NVIDIA's driver, so it's loaded at run time with dlopen(3) rather than
+linked, and a machine without the driver just has no GPUs. NVML lists the processes with memory
+on each device, CUDA ones and graphics ones separately, and keeps a few samples of how busy each
+process kept the device, of which it returns the ones taken since a given time.
+*/
+
+use super::gpu::GpuUsage;
+use super::Pid;
+use libc::{c_char, c_int, c_uint, c_ulonglong, c_void};
+use std::collections::HashMap;
+
+type Return = c_int;
+type Device = *mut c_void;
+
+const NVML_SUCCESS: Return = 0;
+const NVML_ERROR_INSUFFICIENT_SIZE: Return = 7;
+/* the `usedGpuMemory` of processes whose memory the driver can't tell */
+const NVML_VALUE_NOT_AVAILABLE: c_ulonglong = c_ulonglong::MAX;
+
+/* Attempts at listing processes that keep starting between asking how many there are and listing
+ * them */
+const ATTEMPTS: usize = 3;
+
+/* `nvmlProcessInfo_t`, of the `_v2` and `_v3` functions */
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Default)]
+struct ProcessInfo {
+    pid: c_uint,
+    used_gpu_memory: c_ulonglong,
+    gpu_instance_id: c_uint,
+    compute_instance_id: c_uint,
+}
+
+/* `nvmlProcessUtilizationSample_t`, with utilizations in percent */
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Default)]
+struct UtilizationSample {
+    pid: c_uint,
+    time_stamp: c_ulonglong,
+    sm_util: c_uint,
+    mem_util: c_uint,
+    enc_util: c_uint,
+    dec_util: c_uint,
+}
+
+type GetProcesses = unsafe extern "C" fn(Device, *mut c_uint, *mut ProcessInfo) -> Return;
+type GetUtilization =
+    unsafe extern "C" fn(Device, *mut UtilizationSample, *mut c_uint, c_ulonglong) -> Return;
+
+/// A process NVML lists on the `device` of that index, with its memory there if the driver can
+/// tell.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct DeviceProcess {
+    pub device: usize,
+    pub pid: Pid,
+    pub memory: Option<u64>,
+}
+
+/// A sample of how busy a process kept the cores of the `device` of that index, in percent.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct DeviceSample {
+    pub device: usize,
+    pub pid: Pid,
+    pub utilization: u32,
+}
+
+/// What each process uses of all the devices. A process listed both as a CUDA and a graphics one
+/// on a device counts once there, and its utilization of a device is its highest sample.
+pub fn usage_by_pid(
+    processes: &[DeviceProcess],
+    samples: &[DeviceSample],
+) -> HashMap<Pid, GpuUsage> {
+    let mut memory: HashMap<(usize, Pid), u64> = HashMap::new();
+    for p in processes {
+        let bytes = memory.entry((p.device, p.pid)).or_insert(0);
+        *bytes = std::cmp::max(*bytes, p.memory.unwrap_or(0));
+    }
+    let mut utilization: HashMap<(usize, Pid), u32> = HashMap::new();
+    for sample in samples {
+        let percent = utilization.entry((sample.device, sample.pid)).or_insert(0);
+        *percent = std::cmp::max(*percent, sample.utilization);
+    }
+    let mut ret: HashMap<Pid, GpuUsage> = HashMap::new();
+    for ((_, pid), bytes) in memory {
+        ret.entry(pid).or_default().memory += bytes;
+    }
+    for ((_, pid), percent) in utilization {
+        let usage = ret.entry(pid).or_default();
+        usage.utilization = Some(usage.utilization.unwrap_or(0) + percent);
+    }
+    ret
+}
+
+#[derive(Debug)]
+pub struct Nvml {
+    library: *mut c_void,
+    shutdown: unsafe extern "C" fn() -> Return,
+    devices: Vec<Device>,
+    /* the CUDA and the graphics processes, whichever the driver has */
+    running_processes: Vec<GetProcesses>,
+    process_utilization: Option<GetUtilization>,
+    /* when the newest utilization sample so far was taken, in microseconds */
+    last_seen: c_ulonglong,
+}
+
+/* NVML is thread-safe, and the handles stay valid until it's shut down */
+unsafe impl Send for Nvml {}
+
+impl Nvml {
+    /// Load and initialize NVML, if the driver is installed and has at least one device.
+    pub fn open() -> Option<Nvml> {
+        unsafe {
+            let library = libc::dlopen(
+                b"libnvidia-ml.so.1\0".as_ptr() as *const c_char,
+                libc::RTLD_NOW | libc::RTLD_LOCAL,
+            );
+            if library.is_null() {
+                return None;
+            }
+            let ret = Nvml::init(library);
+            if ret.is_none() {
+                libc::dlclose(library);
+            }
+            ret
+        }
+    }
+
+    unsafe fn init(library: *mut c_void) -> Option<Nvml> {
+        let init: unsafe extern "C" fn() -> Return = symbol(library, b"nvmlInit_v2\0")?;
+        let shutdown: unsafe extern "C" fn() -> Return = symbol(library, b"nvmlShutdown\0")?;
+        let device_count: unsafe extern "C" fn(*mut c_uint) -> Return =
+            symbol(library, b"nvmlDeviceGetCount_v2\0")?;
+        let device_handle: unsafe extern "C" fn(c_uint, *mut Device) -> Return =
+            symbol(library, b"nvmlDeviceGetHandleByIndex_v2\0")?;
+        if init() != NVML_SUCCESS {
+            return None;
+        }
+        let mut count: c_uint = 0;
+        let mut devices = Vec::new();
+        if device_count(&mut count) == NVML_SUCCESS {
+            for i in 0..count {
+                let mut device = std::ptr::null_mut();
+                if device_handle(i, &mut device) == NVML_SUCCESS {
+                    devices.push(device);
+                }
+            }
+        }
+        if devices.is_empty() {
+            shutdown();
+            return None;
+        }
+        let running_processes = [
+            &[
+                &b"nvmlDeviceGetComputeRunningProcesses_v3\0"[..],
+                b"nvmlDeviceGetComputeRunningProcesses_v2\0",
+            ],
+            &[
+                &b"nvmlDeviceGetGraphicsRunningProcesses_v3\0"[..],
+                b"nvmlDeviceGetGraphicsRunningProcesses_v2\0",
+            ],
+        ]
+        .iter()
+        .filter_map(|names| names.iter().find_map(|name| symbol(library, name)))
+        .collect();
+        Some(Nvml {
+            library,
+            shutdown,
+            devices,
+            running_processes,
+            process_utilization: symbol(library, b"nvmlDeviceGetProcessUtilization\0"),
+            last_seen: 0,
+        })
+    }
+
+    /// What the processes use of the devices now, and of their cores since the last time.
+    pub fn usage(&mut self) -> HashMap<Pid, GpuUsage> {
+        let mut processes = Vec::new();
+        let mut samples = Vec::new();
+        let mut newest = self.last_seen;
+        for (device, &handle) in self.devices.iter().enumerate() {
+            for &get in &self.running_processes {
+                for info in unsafe { running_processes(get, handle) } {
+                    processes.push(DeviceProcess {
+                        device,
+                        pid: info.pid as Pid,
+                        memory: Some(info.used_gpu_memory)
+                            .filter(|&bytes| bytes != NVML_VALUE_NOT_AVAILABLE),
+                    });
+                }
+            }
+            if let Some(get) = self.process_utilization {
+                for sample in unsafe { utilization_samples(get, handle, self.last_seen) } {
+                    newest = std::cmp::max(newest, sample.time_stamp);
+                    samples.push(DeviceSample {
+                        device,
+                        pid: sample.pid as Pid,
+                        utilization: sample.sm_util,
+                    });
+                }
+            }
+        }
+        self.last_seen = newest;
+        usage_by_pid(&processes, &samples)
+    }
+}
+
+impl Drop for Nvml {
+    fn drop(&mut self) {
+        unsafe {
+            (self.shutdown)();
+            libc::dlclose(self.library);
+        }
+    }
+}
+
+/* The function `name`, a nul-terminated symbol, of type `T`, a function pointer */
+unsafe fn symbol<T: Copy>(library: *mut c_void, name: &[u8]) -> Option<T> {
+    let address = libc::dlsym(library, name.as_ptr() as *const c_char);
+    if address.is_null() {
+        None
+    } else {
+        Some(std::mem::transmute_copy(&address))
+    }
+}
+
+/* The processes `get` lists on `device`. NVML says how many there are when there isn't room for
+ * them all. */
+unsafe fn running_processes(get: GetProcesses, device: Device) -> Vec<ProcessInfo> {
+    let mut infos = Vec::new();
+    for _ in 0..ATTEMPTS {
+        let mut count = infos.len() as c_uint;
+        match get(device, &mut count, infos.as_mut_ptr()) {
+            NVML_SUCCESS => {
+                infos.truncate(count as usize);
+                return infos;
+            }
+            NVML_ERROR_INSUFFICIENT_SIZE => {
+                /* with room for a few more that may start in the meantime */
+                infos = vec![ProcessInfo::default(); count as usize + 8];
+            }
+            _ => break,
+        }
+    }
+    Vec::new()
+}
+
+/* The utilization samples of the processes on `device` taken after `since` */
+unsafe fn utilization_samples(
+    get: GetUtilization,
+    device: Device,
+    since: c_ulonglong,
+) -> Vec<UtilizationSample> {
+    let mut samples = Vec::new();
+    for _ in 0..ATTEMPTS {
+        let mut count = samples.len() as c_uint;
+        let buffer = if samples.is_empty() {
+            std::ptr::null_mut()
+        } else {
+            samples.as_mut_ptr()
+        };
+        match get(device, buffer, &mut count, since) {
+            NVML_SUCCESS => {
+                samples.truncate(count as usize);
+                return samples;
+            }
+            NVML_ERROR_INSUFFICIENT_SIZE => {
+                samples = vec![UtilizationSample::default(); count as usize];
+            }
+            /* including `NVML_ERROR_NOT_FOUND`, when there's no sample since then */
+            _ => break,
+        }
+    }
+    Vec::new()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_usage_by_pid() {
+        let process = |device, pid, memory| DeviceProcess {
+            device,
+            pid,
+            memory,
+        };
+        let sample = |device, pid, utilization| DeviceSample {
+            device,
+            pid,
+            utilization,
+        };
+        const MIB: u64 = 1024 * 1024;
+        let usage = usage_by_pid(
+            &[
+                process(0, 100, Some(512 * MIB)),
+                /* a CUDA process that draws too */
+                process(0, 100, Some(512 * MIB)),
+                process(1, 100, Some(256 * MIB)),
+                process(0, 200, None),
+                process(1, 300, Some(64 * MIB)),
+            ],
+            &[
+                sample(0, 100, 30),
+                sample(0, 100, 45),
+                sample(1, 100, 10),
+                sample(0, 200, 5),
+                /* gone from the listings since it was sampled */
+                sample(1, 400, 80),
+            ],
+        );
+        assert_eq!(usage.len(), 4);
+        assert_eq!(
+            usage[&100],
+            GpuUsage {
+                memory: 768 * MIB,
+                utilization: Some(55),
+            }
+        );
+        assert_eq!(
+            usage[&200],
+            GpuUsage {
+                memory: 0,
+                utilization: Some(5),
+            }
+        );
+        assert_eq!(
+            usage[&300],
+            GpuUsage {
+                memory: 64 * MIB,
+                utilization: None,
+            }
+        );
+        assert_eq!(usage[&400].utilization, Some(80));
+        assert!(usage_by_pid(&[], &[]).is_empty());
+    }
+
+    #[test]
+    fn test_layouts() {
+        /* as in nvml.h, for the pointers NVML writes through */
+        assert_eq!(std::mem::size_of::<ProcessInfo>(), 24);
+        assert_eq!(std::mem::size_of::<UtilizationSample>(), 32);
+    }
+}
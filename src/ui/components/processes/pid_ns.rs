@@ -0,0 +1,151 @@
+/*
+ * bb
+ *
+ * Copyright 2019 Manos Pitsidianakis
+ *
+ * This file is part of bb.
+ *
+ * bb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * bb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with bb. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+/*! The PID namespaces of processes, with `--pid-ns`, told apart by the inode `/proc/[pid]/ns/pid`
+links to, eg. `pid:[4026531836]`.
+
+The first process of a container is the child of a process of the host, eg. a runtime shim, which
+puts the container's processes under it in the tree view. With `--pid-ns`, the tree only links a
+process to its parent if both are in the same namespace, so that each container is a tree of its
+own after the others.
+
+The namespace of a process can only be read by its user and root. Processes whose namespace isn't
+known are linked to their parent as usual, and are taken to be in bb's namespace.
+*/
+
+use super::*;
+
+/// Names accepted by `PidNsMode::parse`.
+pub const PID_NS_MODES: &[&str] = &["color", "group"];
+
+/// How the processes of other PID namespaces than bb's are told apart.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum PidNsMode {
+    /// Color their PIDs, one color per namespace.
+    Color,
+    /// Keep the processes of each namespace together in the flat list, bb's first.
+    Group,
+}
+
+impl PidNsMode {
+    pub fn parse(name: &str) -> Option<PidNsMode> {
+        match name {
+            "color" => Some(PidNsMode::Color),
+            "group" => Some(PidNsMode::Group),
+            _ => None,
+        }
+    }
+}
+
+/* Colors of the PIDs of other namespaces, picked by inode */
+const NS_COLORS: [Color; 6] = [
+    Color::Byte(33),
+    Color::Byte(135),
+    Color::Byte(172),
+    Color::Byte(37),
+    Color::Byte(168),
+    Color::Byte(106),
+];
+
+/// The inode of a namespace link target, eg. 4026531836 for `pid:[4026531836]`.
+pub fn parse_ns_link(target: &str) -> Option<u64> {
+    target
+        .strip_prefix("pid:[")?
+        .strip_suffix(']')?
+        .parse()
+        .ok()
+}
+
+/// The inode of the PID namespace of the `ns/pid` link at `path`, `None` if it can't be read.
+pub fn read_pid_ns(path: &Path) -> Option<u64> {
+    parse_ns_link(std::fs::read_link(path).ok()?.to_str()?)
+}
+
+/// Whether a process in namespace `child` is linked to its parent in namespace `parent` in the
+/// tree view.
+pub fn same_ns(child: Option<u64>, parent: Option<u64>) -> bool {
+    match (child, parent) {
+        (Some(child), Some(parent)) => child == parent,
+        _ => true,
+    }
+}
+
+/// The color of the PIDs in namespace `ns` when bb is in `own`, `None` for bb's namespace.
+pub fn ns_color(ns: Option<u64>, own: Option<u64>) -> Option<Color> {
+    let ns = ns.filter(|&ns| Some(ns) != own)?;
+    Some(NS_COLORS[(ns % NS_COLORS.len() as u64) as usize])
+}
+
+/// Reorder `rows` so that the processes of each namespace, as `pid_ns` gives them, are together,
+/// those of `own` first and then the others by inode. Within a namespace, rows keep their order.
+pub fn group_by_ns<T>(rows: &mut [T], own: Option<u64>, pid_ns: impl Fn(&T) -> Option<u64>) {
+    rows.sort_by_key(|row| {
+        let ns = pid_ns(row).filter(|&ns| Some(ns) != own);
+        (ns.is_some(), ns)
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pid_ns() {
+        assert_eq!(PidNsMode::parse("color"), Some(PidNsMode::Color));
+        assert_eq!(PidNsMode::parse("group"), Some(PidNsMode::Group));
+        assert_eq!(PidNsMode::parse("tree"), None);
+        for name in PID_NS_MODES {
+            assert!(PidNsMode::parse(name).is_some(), "{}", name);
+        }
+
+        assert_eq!(parse_ns_link("pid:[4026531836]"), Some(4026531836));
+        assert_eq!(parse_ns_link("net:[4026531840]"), None);
+        assert_eq!(parse_ns_link("pid:[]"), None);
+        assert_eq!(parse_ns_link("pid:4026531836"), None);
+
+        assert!(same_ns(Some(1), Some(1)));
+        assert!(!same_ns(Some(1), Some(2)));
+        assert!(same_ns(None, Some(2)));
+        assert!(same_ns(Some(1), None));
+
+        assert_eq!(ns_color(Some(7), Some(7)), None);
+        assert_eq!(ns_color(None, Some(7)), None);
+        assert_eq!(ns_color(Some(8), Some(7)), Some(NS_COLORS[2]));
+        assert_ne!(ns_color(Some(8), Some(7)), ns_color(Some(9), Some(7)));
+    }
+
+    #[test]
+    fn test_group_by_ns() {
+        let mut rows = [
+            (1, Some(9)),
+            (2, Some(7)),
+            (3, None),
+            (4, Some(8)),
+            (5, Some(9)),
+        ];
+        group_by_ns(&mut rows, Some(7), |&(_, ns)| ns);
+        /* bb's and the unknown ones first, in their order, then by inode */
+        assert_eq!(
+            rows.iter().map(|&(pid, _)| pid).collect::<Vec<i32>>(),
+            [2, 3, 4, 1, 5]
+        );
+    }
+}
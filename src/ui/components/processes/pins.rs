@@ -0,0 +1,108 @@
+/*
+ * bb
+ *
+ * Copyright 2019 Manos Pitsidianakis
+ *
+ * This file is part of bb.
+ *
+ * bb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * bb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with bb. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+/*! Processes pinned to the top of the flat list with `--pin` or P, whatever the sort order.
+
+A pin is either a PID or a process name, which is matched again on every refresh, so a service
+that restarts with a new PID stays pinned.
+*/
+
+use super::{process_name, Pid, ProcessDisplay};
+
+/// The mark at the start of the command line of pinned rows.
+pub const PIN_MARKER: &str = "^ ";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Pin {
+    Pid(Pid),
+    /// Processes with this name, as kill by name matches it exactly.
+    Name(String),
+}
+
+impl Pin {
+    /// A PID if `spec` is a number, a process name otherwise.
+    pub fn parse(spec: &str) -> Result<Pin, String> {
+        if spec.is_empty() {
+            return Err("empty --pin".to_string());
+        }
+        if spec.bytes().all(|b| b.is_ascii_digit()) {
+            return spec
+                .parse()
+                .ok()
+                .filter(|&pid| pid > 0)
+                .map(Pin::Pid)
+                .ok_or_else(|| format!("invalid PID `{}`", spec));
+        }
+        Ok(Pin::Name(spec.to_string()))
+    }
+
+    fn matches(&self, p: &ProcessDisplay) -> bool {
+        match self {
+            Pin::Pid(pid) => p.i == *pid,
+            Pin::Name(name) => process_name(&p.cmd_line.0) == name,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Pins {
+    pins: Vec<Pin>,
+}
+
+impl Pins {
+    pub fn new(pins: Vec<Pin>) -> Pins {
+        Pins { pins }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pins.is_empty()
+    }
+
+    /// Whether `p` is pinned. Rows of groups of the grouped view never are.
+    pub fn is_pinned(&self, p: &ProcessDisplay) -> bool {
+        p.members == 0 && self.pins.iter().any(|pin| pin.matches(p))
+    }
+
+    /// Pin `pid`, or unpin it if it's pinned by its PID. Returns whether it's pinned by its PID
+    /// now.
+    pub fn toggle(&mut self, pid: Pid) -> bool {
+        let pin = Pin::Pid(pid);
+        if let Some(i) = self.pins.iter().position(|p| *p == pin) {
+            self.pins.remove(i);
+            false
+        } else {
+            self.pins.push(pin);
+            true
+        }
+    }
+
+    /// Move the pinned `rows` (indices into `processes`) above the others, keeping the order of
+    /// both.
+    pub fn hoist(&self, rows: &mut Vec<usize>, processes: &[ProcessDisplay]) {
+        if self.is_empty() {
+            return;
+        }
+        let (mut pinned, rest): (Vec<usize>, Vec<usize>) =
+            rows.iter().partition(|&&i| self.is_pinned(&processes[i]));
+        pinned.extend(rest);
+        *rows = pinned;
+    }
+}
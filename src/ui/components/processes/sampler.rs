@@ -0,0 +1,291 @@
+/*
+ * bb
+ *
+ * Copyright 2019 Manos Pitsidianakis
+ *
+ * This file is part of bb.
+ *
+ * bb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * bb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with bb. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+/*! Sampling of `/proc` in a background thread.
+
+Reading `/proc` can block for a long time (eg. on a process in uninterruptible sleep), so it is done
+by a separate thread which sends complete `Snapshot`s to the UI thread. The UI thread only cares
+about the freshest snapshot, so the channel drops stale ones instead of queuing them.
+*/
+
+use super::*;
+use crossbeam::channel::{Receiver, Sender, TrySendError};
+use crossbeam::select;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+/// A complete reading of the system's processes.
+#[derive(Debug, Default)]
+pub struct Snapshot {
+    pub cpu_stat: Stat,
+    pub cpu_no: usize,
+    /* seconds since the epoch, of boot and of when the snapshot was taken */
+    pub boot_time: usize,
+    pub time: u64,
+    /* total memory in KiB */
+    pub mem_total: usize,
+    pub processes: Vec<Process>,
+}
+
+impl Snapshot {
+    /// Read every process in `source`, and the files of `reads` for each. It's an error if `stat`
+    /// can't be read or has no cpu lines, or the root can't be listed; processes that can't be read
+    /// are skipped.
+    pub fn take(source: &ProcSource, reads: ExtraReads) -> std::io::Result<Snapshot> {
+        let mut boot_time = 0;
+        let mut cpu_stats = read_stat(source, &mut boot_time)?;
+        if cpu_stats.is_empty() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "no cpu lines in stat",
+            ));
+        }
+        let time = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let cpu_no = cpu_stats.len() - 1;
+        let cpu_stat = cpu_stats.remove(0);
+        let (_, mem_total) = get_mem_info(source)?;
+        let mut processes = Vec::with_capacity(2048);
+        for entry in std::fs::read_dir(source.root())? {
+            let dir = match entry {
+                Ok(dir) => dir,
+                Err(err) => {
+                    log_warn!("skipping an entry of {}: {}", source.root().display(), err);
+                    continue;
+                }
+            };
+            if let Some(fname) = dir.file_name().to_str() {
+                if !fname.chars().all(|c| c.is_numeric()) {
+                    continue;
+                }
+            } else {
+                continue;
+            }
+
+            match get_pid_info(dir.path(), reads) {
+                Ok(p) => processes.push(p),
+                /* the process exited since the directory was listed, or it's a kernel thread
+                 * and has no memory usage in its status */
+                Err(err)
+                    if err.kind() == std::io::ErrorKind::NotFound
+                        || err.kind() == std::io::ErrorKind::InvalidData =>
+                {
+                    log_debug!("skipping {}: {}", dir.path().display(), err)
+                }
+                Err(err) => log_warn!("skipping {}: {}", dir.path().display(), err),
+            }
+        }
+        Ok(Snapshot {
+            cpu_stat,
+            cpu_no,
+            boot_time,
+            time,
+            mem_total,
+            processes,
+        })
+    }
+}
+
+/// Sending end of a channel that evicts the oldest pending value when it's full.
+#[derive(Debug)]
+pub struct LatestSender<T> {
+    tx: Sender<T>,
+    /* Used to evict stale values */
+    rx: Receiver<T>,
+}
+
+/// Receiving end of a channel created with `latest_channel`.
+#[derive(Debug)]
+pub struct LatestReceiver<T> {
+    rx: Receiver<T>,
+}
+
+/// Create a bounded channel that keeps at most `cap` pending values, dropping the oldest ones.
+pub fn latest_channel<T>(cap: usize) -> (LatestSender<T>, LatestReceiver<T>) {
+    let (tx, rx) = crossbeam::channel::bounded(cap);
+    (LatestSender { tx, rx: rx.clone() }, LatestReceiver { rx })
+}
+
+impl<T> LatestSender<T> {
+    pub fn send(&self, mut value: T) {
+        while let Err(TrySendError::Full(v)) = self.tx.try_send(value) {
+            let _ = self.rx.try_recv();
+            value = v;
+        }
+    }
+}
+
+impl<T> LatestReceiver<T> {
+    /// Drain the channel and return the newest pending value, if any.
+    pub fn latest(&self) -> Option<T> {
+        let mut ret = None;
+        while let Ok(v) = self.rx.try_recv() {
+            ret = Some(v);
+        }
+        ret
+    }
+}
+
+/// Handle to the sampling thread. Dropping it stops and joins the thread.
+#[derive(Debug)]
+pub struct Sampler {
+    rx: LatestReceiver<Snapshot>,
+    quit: Sender<()>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Sampler {
+    pub fn new(interval: Duration, source: ProcSource, reads: ExtraReads) -> Sampler {
+        let (tx, rx) = latest_channel(1);
+        let (quit, quit_rx) = crossbeam::channel::bounded(1);
+        let handle = std::thread::Builder::new()
+            .name("sampler-thread".to_string())
+            .spawn(move || {
+                let ticker = crossbeam::channel::tick(interval);
+                loop {
+                    select! {
+                        recv(quit_rx) -> _ => break,
+                        recv(ticker) -> _ => match Snapshot::take(&source, reads) {
+                            Ok(snapshot) => tx.send(snapshot),
+                            /* the list keeps showing the last snapshot until one can be taken */
+                            Err(err) => log_warn!(
+                                "cannot read {}, trying again: {}",
+                                source.root().display(),
+                                err
+                            ),
+                        },
+                    }
+                }
+            })
+            .unwrap();
+        Sampler {
+            rx,
+            quit,
+            handle: Some(handle),
+        }
+    }
+
+    pub fn latest(&self) -> Option<Snapshot> {
+        self.rx.latest()
+    }
+}
+
+impl Drop for Sampler {
+    fn drop(&mut self) {
+        let _ = self.quit.send(());
+        if let Some(handle) = self.handle.take() {
+            if let Err(panic) = handle.join() {
+                let cause = panic
+                    .downcast_ref::<&str>()
+                    .copied()
+                    .or_else(|| panic.downcast_ref::<String>().map(String::as_str))
+                    .unwrap_or("unknown cause");
+                log_warn!("the sampler thread panicked: {}", cause);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::mock_proc_root;
+    use super::*;
+
+    #[test]
+    fn test_snapshot_of_proc_root() {
+        let dir = mock_proc_root("sampler", 1);
+        /* not a process */
+        std::fs::create_dir_all(dir.join("sys")).unwrap();
+        let source = ProcSource::new(&dir).unwrap();
+
+        let snapshot = Snapshot::take(&source, ExtraReads::default()).unwrap();
+        assert_eq!((snapshot.cpu_no, snapshot.boot_time), (1, 1600000000));
+        assert_eq!(snapshot.mem_total, 16384);
+        let mut processes = snapshot
+            .processes
+            .iter()
+            .map(|p| (p.pid, p.ppid, p.cmd_line.as_str()))
+            .collect::<Vec<_>>();
+        processes.sort_unstable();
+        assert_eq!(processes, [(1, 0, "init "), (2, 1, "worker2 ")]);
+
+        /* a `stat` without cpu lines is an error instead of a panic */
+        std::fs::write(dir.join("stat"), "intr 0\nbtime 1600000000\n").unwrap();
+        assert_eq!(
+            Snapshot::take(&source, ExtraReads::default())
+                .unwrap_err()
+                .kind(),
+            std::io::ErrorKind::InvalidData
+        );
+        /* nor is a truncated `stat`, or one without btime, or a missing `meminfo` */
+        for stat in &["cpu  10 0 5\n", "cpu  10 0 5 100 1 0 0 0 0 0\n"] {
+            std::fs::write(dir.join("stat"), stat).unwrap();
+            assert_eq!(
+                Snapshot::take(&source, ExtraReads::default())
+                    .unwrap_err()
+                    .kind(),
+                std::io::ErrorKind::InvalidData
+            );
+        }
+        std::fs::write(
+            dir.join("stat"),
+            "cpu  10 0 5 100 1 0 0 0 0 0\nbtime 1600000000\n",
+        )
+        .unwrap();
+        std::fs::remove_file(dir.join("meminfo")).unwrap();
+        assert_eq!(
+            Snapshot::take(&source, ExtraReads::default())
+                .unwrap_err()
+                .kind(),
+            std::io::ErrorKind::NotFound
+        );
+        /* and neither is a missing root, eg. when it's unmounted */
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(
+            Snapshot::take(&source, ExtraReads::default())
+                .unwrap_err()
+                .kind(),
+            std::io::ErrorKind::NotFound
+        );
+    }
+
+    #[test]
+    fn test_latest_receiver_keeps_newest() {
+        let (tx, rx) = latest_channel(1);
+        assert_eq!(rx.latest(), None::<usize>);
+        for i in 0..5 {
+            tx.send(i);
+        }
+        assert_eq!(rx.latest(), Some(4));
+        assert_eq!(rx.latest(), None);
+
+        let (tx, rx) = latest_channel(3);
+        for i in 0..10 {
+            tx.send(i);
+        }
+        assert_eq!(rx.latest(), Some(9));
+        assert_eq!(rx.latest(), None);
+        tx.send(10);
+        assert_eq!(rx.latest(), Some(10));
+    }
+}
@@ -0,0 +1,114 @@
+/*
+ * bb
+ *
+ * Copyright 2019 Manos Pitsidianakis
+ *
+ * This file is part of bb.
+ *
+ * bb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * bb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with bb. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+/*! The order of the list held still while it's scrolled, with `--scroll-freeze`, so that rows
+don't move under the cursor. The values in the rows still update, and the list is sorted again
+once it's been left alone for a while.
+*/
+
+use super::Pid;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+#[derive(Debug)]
+pub struct ScrollFreeze {
+    /* how long after the last scroll the list is sorted again */
+    timeout: Duration,
+    scrolled_at: Option<Instant>,
+    /* position of each process in the list when the scrolling started */
+    order: HashMap<Pid, usize>,
+}
+
+impl ScrollFreeze {
+    pub fn new(timeout: Duration) -> ScrollFreeze {
+        ScrollFreeze {
+            timeout,
+            scrolled_at: None,
+            order: HashMap::new(),
+        }
+    }
+
+    /* The list was scrolled at `now`, when it listed `pids` in this order. The order is only
+     * taken if the list isn't frozen already, so that it holds for the whole of the scrolling. */
+    pub fn scrolled(&mut self, now: Instant, pids: impl Iterator<Item = Pid>) {
+        if !self.is_frozen(now) {
+            self.order = pids.enumerate().map(|(i, pid)| (pid, i)).collect();
+        }
+        self.scrolled_at = Some(now);
+    }
+
+    /// Whether the order is held at `now`.
+    pub fn is_frozen(&self, now: Instant) -> bool {
+        self.scrolled_at
+            .is_some_and(|at| now.saturating_duration_since(at) < self.timeout)
+    }
+
+    /// Sort the list again right away, eg. when the sort order is changed.
+    pub fn thaw(&mut self) {
+        self.scrolled_at = None;
+        self.order.clear();
+    }
+
+    /* Put `rows` back in the held order if it's held at `now`. Processes that weren't listed when
+     * it was taken go last, in the order they're in. */
+    pub fn reorder<T>(&self, now: Instant, rows: &mut [T], pid_of: impl Fn(&T) -> Pid) {
+        if self.is_frozen(now) {
+            rows.sort_by_key(|row| self.order.get(&pid_of(row)).copied().unwrap_or(usize::MAX));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_freeze_timer() {
+        let start = Instant::now();
+        let at = |millis| start + Duration::from_millis(millis);
+        let mut freeze = ScrollFreeze::new(Duration::from_millis(1000));
+        assert!(!freeze.is_frozen(start));
+
+        freeze.scrolled(at(0), [3, 1, 2].iter().copied());
+        assert!(freeze.is_frozen(at(0)));
+        assert!(freeze.is_frozen(at(999)));
+        assert!(!freeze.is_frozen(at(1000)));
+        /* scrolling again before the timeout keeps it frozen for longer, in the first order */
+        freeze.scrolled(at(600), [1, 2, 3].iter().copied());
+        assert!(freeze.is_frozen(at(1500)));
+        let mut rows = vec![1, 4, 2, 3];
+        freeze.reorder(at(1500), &mut rows, |&pid| pid);
+        assert_eq!(rows, [3, 1, 2, 4]);
+        /* once it's been left alone the rows keep the order they're given */
+        assert!(!freeze.is_frozen(at(1600)));
+        let mut rows = vec![1, 4, 2, 3];
+        freeze.reorder(at(1600), &mut rows, |&pid| pid);
+        assert_eq!(rows, [1, 4, 2, 3]);
+        /* and the next scroll takes the order anew */
+        freeze.scrolled(at(2000), [2, 1].iter().copied());
+        let mut rows = vec![1, 2];
+        freeze.reorder(at(2000), &mut rows, |&pid| pid);
+        assert_eq!(rows, [2, 1]);
+
+        freeze.thaw();
+        assert!(!freeze.is_frozen(at(2000)));
+    }
+}
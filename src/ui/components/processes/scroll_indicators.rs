@@ -0,0 +1,84 @@
+/*
+ * bb
+ *
+ * Copyright 2019 Manos Pitsidianakis
+ *
+ * This file is part of bb.
+ *
+ * bb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * bb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with bb. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+/*! The `‹` and `›` on the edges of the column headers that tell there are more columns to the left
+or right of the screen.
+
+They are drawn over the first and last cell of the header row, by how far the table is scrolled
+and how wide it is: `‹` once its first columns are scrolled out of view, `›` while its last ones
+don't fit. The command line column ends the table with its header, so `›` means there are columns
+whose header can't be seen, not a command line that was cut short.
+*/
+
+use super::*;
+
+pub const LEFT_INDICATOR: char = '‹';
+pub const RIGHT_INDICATOR: char = '›';
+
+/// Which edges of `width` columns of a table `table_width` columns wide, scrolled by `offset`
+/// columns, have more columns past them, as (left, right).
+pub fn clipped_edges(offset: usize, width: usize, table_width: usize) -> (bool, bool) {
+    (offset > 0, offset + width < table_width)
+}
+
+/// Draw the indicators on the edges of `header`, the area of the header row.
+pub fn draw_scroll_indicators(
+    grid: &mut CellBuffer,
+    (upper_left, bottom_right): Area,
+    offset: usize,
+    table_width: usize,
+) {
+    let y = get_y(upper_left);
+    let (left, right) = (get_x(upper_left), get_x(bottom_right));
+    let (clipped_left, clipped_right) = clipped_edges(offset, right + 1 - left, table_width);
+    for &(clipped, x, ch) in &[
+        (clipped_left, left, LEFT_INDICATOR),
+        (clipped_right, right, RIGHT_INDICATOR),
+    ] {
+        if let (true, Some(cell)) = (clipped, grid.get_mut(x, y)) {
+            cell.set_ch(ch).set_attrs(Attr::Bold);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scroll_indicators() {
+        assert_eq!(clipped_edges(0, 80, 60), (false, false));
+        assert_eq!(clipped_edges(0, 80, 80), (false, false));
+        assert_eq!(clipped_edges(0, 80, 81), (false, true));
+        assert_eq!(clipped_edges(1, 80, 81), (true, false));
+        assert_eq!(clipped_edges(10, 80, 120), (true, true));
+
+        let draw = |offset, table_width| {
+            let mut grid = CellBuffer::new(12, 2, Cell::with_char('-'));
+            draw_scroll_indicators(&mut grid, ((1, 0), (10, 0)), offset, table_width);
+            grid.iter().map(Cell::ch).collect::<String>()
+        };
+        assert_eq!(draw(0, 10), "------------------------");
+        assert_eq!(draw(0, 11), "----------›-------------");
+        assert_eq!(draw(1, 11), "-‹----------------------");
+        assert_eq!(draw(2, 20), "-‹--------›-------------");
+    }
+}
@@ -0,0 +1,71 @@
+/*
+ * bb
+ *
+ * Copyright 2019 Manos Pitsidianakis
+ *
+ * This file is part of bb.
+ *
+ * bb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * bb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with bb. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+/*! The separators of `--separators`: a dim line of `─` on a screen row of its own below each process
+row, like the second line of `--two-line`.
+
+Every process gets the row below it, so that pages hold as many processes whichever are shown and
+rows don't move as trees are expanded. With `Separators::Tree`, the tree view only draws the line
+above top-level processes and leaves the row blank between the others. In the flat list, every
+process is top-level.
+*/
+
+use super::*;
+
+/// Names accepted by `Separators::parse`.
+pub const SEPARATOR_MODES: &[&str] = &["rows", "tree"];
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Separators {
+    /// Between every two rows.
+    Rows,
+    /// Between top-level processes of the tree view.
+    Tree,
+}
+
+impl Separators {
+    pub fn parse(name: &str) -> Option<Separators> {
+        match name {
+            "rows" => Some(Separators::Rows),
+            "tree" => Some(Separators::Tree),
+            _ => None,
+        }
+    }
+
+    /// Whether a line is drawn above a row that is `top_level` in the tree view, or any row of
+    /// the flat list.
+    pub fn draws_above(self, top_level: bool) -> bool {
+        self == Separators::Rows || top_level
+    }
+}
+
+/// Draw a separator across the first row of `area`.
+pub fn draw_separator(grid: &mut CellBuffer, (upper_left, bottom_right): Area) {
+    let y = get_y(upper_left);
+    for x in get_x(upper_left)..=get_x(bottom_right) {
+        if let Some(cell) = grid.get_mut(x, y) {
+            cell.set_ch(HORZ_BOUNDARY)
+                .set_fg(Color::Default)
+                .set_bg(Color::Default)
+                .set_attrs(Attr::Dim);
+        }
+    }
+}
@@ -0,0 +1,217 @@
+/*
+ * bb
+ *
+ * Copyright 2019 Manos Pitsidianakis
+ *
+ * This file is part of bb.
+ *
+ * bb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * bb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with bb. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+/*! The memory map summary of the inspector, from `/proc/[pid]/smaps_rollup`, which the kernel sums
+up for us since Linux 4.14, or else from the mappings of `/proc/[pid]/smaps` added up.
+
+Both list their fields in kB, the same ones for the rollup as for each mapping. Pages backed by
+files, including shared memory, are the resident ones that aren't `Anonymous`.
+*/
+
+use super::Pid;
+use crate::ui::components::utilities::{Bytes, ProcSource};
+
+/// The memory of a process by how it's mapped, in bytes.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct MemoryMap {
+    pub rss: usize,
+    /// The proportional set size: pages shared with `n` processes count for `1/n` of their size.
+    pub pss: usize,
+    pub swap: usize,
+    pub anon: usize,
+    pub file: usize,
+    /// Resident pages also mapped by other processes, anonymous or not.
+    pub shared: usize,
+}
+
+/// Add up the fields of every mapping of `smaps`, the contents of `smaps_rollup` or `smaps`.
+pub fn parse_smaps(smaps: &str) -> MemoryMap {
+    let mut ret = MemoryMap::default();
+    for line in smaps.lines() {
+        let (key, value) = match line.split_once(':') {
+            Some(field) => field,
+            /* the header line of each mapping */
+            None => continue,
+        };
+        let bytes = match value.trim().strip_suffix(" kB").map(str::parse::<usize>) {
+            Some(Ok(kb)) => kb * 1024,
+            _ => continue,
+        };
+        match key {
+            "Rss" => ret.rss += bytes,
+            "Pss" => ret.pss += bytes,
+            "Swap" => ret.swap += bytes,
+            "Anonymous" => ret.anon += bytes,
+            "Shared_Clean" | "Shared_Dirty" => ret.shared += bytes,
+            _ => {}
+        }
+    }
+    ret.file = ret.rss.saturating_sub(ret.anon);
+    ret
+}
+
+/// The memory map of `pid`, from its rollup if the kernel has it. Like the other files of a
+/// process that tell where its memory is, they can only be read by its owner and root, and the
+/// error says so.
+pub fn read_memory_map(source: &ProcSource, pid: Pid) -> Result<MemoryMap, String> {
+    let unreadable = |err: std::io::Error| {
+        format!(
+            "can't read the memory map of PID {}: {}",
+            pid,
+            match err.kind() {
+                std::io::ErrorKind::PermissionDenied => "permission denied".to_string(),
+                std::io::ErrorKind::NotFound => "no such process".to_string(),
+                _ => err.to_string(),
+            }
+        )
+    };
+    match source.read_to_string(format!("{}/smaps_rollup", pid)) {
+        Ok(rollup) => Ok(parse_smaps(&rollup)),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => source
+            .read_to_string(format!("{}/smaps", pid))
+            .map(|smaps| parse_smaps(&smaps))
+            .map_err(unreadable),
+        Err(err) => Err(unreadable(err)),
+    }
+}
+
+/// The lines of the inspector for `memory_map`, as labels and values.
+pub fn memory_map_fields(memory_map: &Result<MemoryMap, String>) -> Vec<(&'static str, String)> {
+    let map = match memory_map {
+        Ok(map) => map,
+        Err(err) => return vec![("maps", err.clone())],
+    };
+    let size = |bytes: usize| Bytes(bytes).as_convenient_string();
+    vec![
+        (
+            "rss",
+            format!(
+                "{}: anon {}, file {}, shared {}",
+                size(map.rss),
+                size(map.anon),
+                size(map.file),
+                size(map.shared)
+            ),
+        ),
+        ("pss", format!("{}, swap {}", size(map.pss), size(map.swap))),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROLLUP: &str =
+        "5601b809b000-7ffe5a408000 ---p 00000000 00:00 0                          [rollup]
+Rss:                1436 kB
+Pss:                 367 kB
+Pss_Dirty:           104 kB
+Pss_Anon:            104 kB
+Pss_File:            263 kB
+Pss_Shmem:             0 kB
+Shared_Clean:       1292 kB
+Shared_Dirty:          4 kB
+Private_Clean:        40 kB
+Private_Dirty:       100 kB
+Referenced:         1436 kB
+Anonymous:           104 kB
+LazyFree:              0 kB
+AnonHugePages:         0 kB
+Swap:                 32 kB
+SwapPss:              16 kB
+Locked:                0 kB
+";
+
+    #[test]
+    fn test_parse_smaps_rollup() {
+        assert_eq!(
+            parse_smaps(ROLLUP),
+            MemoryMap {
+                rss: 1436 * 1024,
+                pss: 367 * 1024,
+                swap: 32 * 1024,
+                anon: 104 * 1024,
+                file: 1332 * 1024,
+                shared: 1296 * 1024,
+            }
+        );
+        /* smaps lists the same fields for each mapping, and they add up */
+        let smaps = "00400000-00452000 r-xp 00000000 08:02 173521      /usr/bin/dbus-daemon
+Rss:                 100 kB
+Pss:                  50 kB
+Shared_Clean:         100 kB
+Anonymous:             0 kB
+Swap:                  0 kB
+VmFlags: rd ex mr mw me dw
+7ffd8c000000-7ffd8c021000 rw-p 00000000 00:00 0             [heap]
+Rss:                  20 kB
+Pss:                  20 kB
+Shared_Clean:          0 kB
+Anonymous:            20 kB
+Swap:                  8 kB
+VmFlags: rd wr mr mw me ac
+";
+        assert_eq!(
+            parse_smaps(smaps),
+            MemoryMap {
+                rss: 120 * 1024,
+                pss: 70 * 1024,
+                swap: 8 * 1024,
+                anon: 20 * 1024,
+                file: 100 * 1024,
+                shared: 100 * 1024,
+            }
+        );
+        assert_eq!(parse_smaps(""), MemoryMap::default());
+    }
+
+    #[test]
+    fn test_read_memory_map() {
+        let dir = std::env::temp_dir().join(format!("bb-test-smaps-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("1")).unwrap();
+        std::fs::create_dir_all(dir.join("2")).unwrap();
+        std::fs::write(dir.join("1/smaps_rollup"), ROLLUP).unwrap();
+        /* without a rollup, the mappings are added up */
+        std::fs::write(
+            dir.join("2/smaps"),
+            "00400000-00452000 r-xp 00000000 08:02 173521 /bin/true\nRss: 8 kB\n",
+        )
+        .unwrap();
+        let source = ProcSource::fixture(&dir);
+        assert_eq!(read_memory_map(&source, 1), Ok(parse_smaps(ROLLUP)));
+        assert_eq!(read_memory_map(&source, 2).unwrap().rss, 8 * 1024);
+        assert_eq!(
+            read_memory_map(&source, 3),
+            Err("can't read the memory map of PID 3: no such process".to_string())
+        );
+        assert_eq!(
+            memory_map_fields(&read_memory_map(&source, 1)),
+            [
+                (
+                    "rss",
+                    "1.40 MiB: anon 104.00 KiB, file 1.30 MiB, shared 1.27 MiB".to_string()
+                ),
+                ("pss", "367.00 KiB, swap 32.00 KiB".to_string()),
+            ]
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,261 @@
+/*
+ * bb
+ *
+ * Copyright 2019 Manos Pitsidianakis
+ *
+ * This file is part of bb.
+ *
+ * bb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * bb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with bb. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+/*! Which processes hold TCP sockets, for the `--sockets` column and `--highlight-sockets`.
+
+The kernel lists the TCP sockets in `/proc/net/tcp` and `/proc/net/tcp6` by inode, and the
+descriptors of a process that are sockets link to `socket:[INODE]` in `/proc/[pid]/fd`. Matching
+the two means reading every descriptor of every process, so it's done at most once per sample, the
+first time a row needs it. Only the sockets of bb's network namespace are listed, and the
+descriptors of other users' processes can only be read with `CAP_SYS_PTRACE`.
+*/
+
+use super::Pid;
+use crate::ui::components::utilities::ProcSource;
+use std::collections::HashMap;
+use std::path::Path;
+
+pub type Inode = u64;
+
+/* States in the `st` field of `/proc/net/tcp`, see `include/net/tcp_states.h` */
+const TCP_ESTABLISHED: u8 = 0x01;
+const TCP_LISTEN: u8 = 0x0A;
+
+/// Width of the column, which fits its longest label.
+pub const COLUMN_WIDTH: usize = "listen".len();
+
+/// What the column shows for a process. A process with sockets in both states is `Listening`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum NetState {
+    /// Its descriptors can't be read.
+    Unreadable,
+    /// It holds no listening or established TCP socket.
+    Idle,
+    Established,
+    Listening,
+}
+
+impl NetState {
+    pub fn label(self) -> &'static str {
+        match self {
+            NetState::Unreadable => "-",
+            NetState::Idle => "",
+            NetState::Established => "estab",
+            NetState::Listening => "listen",
+        }
+    }
+
+    /// Whether the process holds a listening or established socket, which is what's highlighted.
+    pub fn is_open(self) -> bool {
+        matches!(self, NetState::Established | NetState::Listening)
+    }
+}
+
+/// Add the listening and established sockets of `table`, the contents of `/proc/net/tcp` or
+/// `/proc/net/tcp6`, to `states` by inode. The first line is the header.
+pub fn parse_tcp_table(table: &str, states: &mut HashMap<Inode, NetState>) {
+    for line in table.lines().skip(1) {
+        let fields = line.split_whitespace().collect::<Vec<&str>>();
+        if fields.len() < 10 {
+            continue;
+        }
+        let state = match u8::from_str_radix(fields[3], 16) {
+            Ok(TCP_LISTEN) => NetState::Listening,
+            Ok(TCP_ESTABLISHED) => NetState::Established,
+            _ => continue,
+        };
+        /* sockets on their way out have no inode anymore */
+        match fields[9].parse::<Inode>() {
+            Ok(0) | Err(_) => {}
+            Ok(inode) => {
+                states.insert(inode, state);
+            }
+        }
+    }
+}
+
+/// The inode of the socket a descriptor links to, if it's a socket.
+pub fn socket_inode(link: &Path) -> Option<Inode> {
+    link.to_str()?
+        .strip_prefix("socket:[")?
+        .strip_suffix(']')?
+        .parse()
+        .ok()
+}
+
+/// The state of each process of `descriptors`, pairs of a PID and the inode of a socket it holds,
+/// given the `states` of the sockets by inode. Processes whose sockets aren't in `states` are left
+/// out.
+pub fn socket_owners(
+    descriptors: impl IntoIterator<Item = (Pid, Inode)>,
+    states: &HashMap<Inode, NetState>,
+) -> HashMap<Pid, NetState> {
+    let mut ret = HashMap::new();
+    for (pid, inode) in descriptors {
+        if let Some(&state) = states.get(&inode) {
+            let entry = ret.entry(pid).or_insert(state);
+            *entry = std::cmp::max(*entry, state);
+        }
+    }
+    ret
+}
+
+/// The states of the processes, found the first time one is asked for after a `clear`.
+#[derive(Debug)]
+pub struct Sockets {
+    source: ProcSource,
+    states: Option<HashMap<Pid, NetState>>,
+}
+
+impl Sockets {
+    pub fn new(source: ProcSource) -> Sockets {
+        Sockets {
+            source,
+            states: None,
+        }
+    }
+
+    /// The state of `pid`, `Idle` for processes that weren't there when the descriptors were
+    /// read. They're read the first time after a `clear`.
+    pub fn state(&mut self, pid: Pid) -> NetState {
+        let Sockets {
+            ref source,
+            ref mut states,
+        } = *self;
+        states
+            .get_or_insert_with(|| scan(source))
+            .get(&pid)
+            .copied()
+            .unwrap_or(NetState::Idle)
+    }
+
+    /// Forget the states found so far, for a new sample.
+    pub fn clear(&mut self) {
+        self.states = None;
+    }
+}
+
+/* Match the sockets of `source` with the descriptors of all of its processes */
+fn scan(source: &ProcSource) -> HashMap<Pid, NetState> {
+    let mut states = HashMap::new();
+    for table in &["net/tcp", "net/tcp6"] {
+        if let Ok(table) = source.read_to_string(table) {
+            parse_tcp_table(&table, &mut states);
+        }
+    }
+    let mut unreadable = Vec::new();
+    let mut descriptors = Vec::new();
+    for entry in std::fs::read_dir(source.root())
+        .into_iter()
+        .flatten()
+        .flatten()
+    {
+        let pid = match entry
+            .file_name()
+            .to_str()
+            .and_then(|name| name.parse::<Pid>().ok())
+        {
+            Some(pid) => pid,
+            None => continue,
+        };
+        match std::fs::read_dir(entry.path().join("fd")) {
+            Ok(fds) => descriptors.extend(
+                fds.flatten()
+                    .filter_map(|fd| socket_inode(&std::fs::read_link(fd.path()).ok()?))
+                    .map(|inode| (pid, inode)),
+            ),
+            Err(_) => unreadable.push(pid),
+        }
+    }
+    let mut ret = socket_owners(descriptors, &states);
+    ret.extend(
+        unreadable
+            .into_iter()
+            .map(|pid| (pid, NetState::Unreadable)),
+    );
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TCP: &str = "  sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode
+   0: 0100007F:0277 00000000:0000 0A 00000000:00000000 00:00000000 00000000     0        0 1001 1 0000000000000000 100 0 0 10 0
+   1: 0100007F:A1B2 0100007F:0277 01 00000000:00000000 00:00000000 00000000  1000        0 1002 1 0000000000000000 20 4 30 10 -1
+   2: 0100007F:A1B4 0100007F:0277 06 00000000:00000000 03:00000F1E 00000000     0        0 0 3 0000000000000000
+   3: 0100007F:A1B6 0100007F:0277 08 00000000:00000000 00:00000000 00000000  1000        0 1004 1 0000000000000000 20 4 30 10 -1
+";
+
+    #[test]
+    fn test_socket_owners() {
+        let mut states = HashMap::new();
+        parse_tcp_table(TCP, &mut states);
+        /* time-wait and close-wait sockets are left out */
+        assert_eq!(states.len(), 2);
+        assert_eq!(states.get(&1001), Some(&NetState::Listening));
+        assert_eq!(states.get(&1002), Some(&NetState::Established));
+
+        assert_eq!(socket_inode(Path::new("socket:[1001]")), Some(1001));
+        assert_eq!(socket_inode(Path::new("pipe:[1001]")), None);
+        assert_eq!(socket_inode(Path::new("/dev/null")), None);
+
+        let owners = socket_owners(
+            vec![(1, 1002), (1, 1001), (2, 1002), (3, 1004), (3, 7)],
+            &states,
+        );
+        assert_eq!(owners.get(&1), Some(&NetState::Listening));
+        assert_eq!(owners.get(&2), Some(&NetState::Established));
+        assert_eq!(owners.get(&3), None);
+        assert_eq!(NetState::Listening.label(), "listen");
+        assert!(!NetState::Unreadable.is_open());
+    }
+
+    #[test]
+    fn test_socket_owner_of_inode() {
+        let dir = std::env::temp_dir().join(format!("bb-test-sockets-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("net")).unwrap();
+        std::fs::write(dir.join("net/tcp"), TCP).unwrap();
+        for pid in &["1", "2"] {
+            std::fs::create_dir_all(dir.join(pid).join("fd")).unwrap();
+        }
+        std::fs::create_dir_all(dir.join("3")).unwrap();
+        let link = |pid: &str, fd: &str, target: &str| {
+            std::os::unix::fs::symlink(target, dir.join(pid).join("fd").join(fd)).unwrap()
+        };
+        link("1", "0", "/dev/null");
+        link("1", "3", "socket:[1002]");
+        link("2", "4", "socket:[1004]");
+        let mut sockets = Sockets::new(ProcSource::fixture(&dir));
+        assert_eq!(sockets.state(1), NetState::Established);
+        assert_eq!(sockets.state(2), NetState::Idle);
+        /* no descriptors to read */
+        assert_eq!(sockets.state(3), NetState::Unreadable);
+        assert_eq!(sockets.state(4), NetState::Idle);
+
+        /* found once per sample */
+        link("2", "5", "socket:[1001]");
+        assert_eq!(sockets.state(2), NetState::Idle);
+        sockets.clear();
+        assert_eq!(sockets.state(2), NetState::Listening);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
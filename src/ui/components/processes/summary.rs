@@ -0,0 +1,149 @@
+/*
+ * bb
+ *
+ * Copyright 2019 Manos Pitsidianakis
+ *
+ * This file is part of bb.
+ *
+ * bb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * bb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with bb. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+/*! A one-line tally of the processes of a snapshot by state, like the `Tasks:` line of top, shown
+above the process list with `--summary`.
+*/
+
+use super::*;
+
+/// Number of processes in each state.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct TaskCounts {
+    pub total: usize,
+    pub running: usize,
+    pub sleeping: usize,
+    /// In uninterruptible sleep (D).
+    pub waiting: usize,
+    /// Stopped by a signal or by a tracer (T and t).
+    pub stopped: usize,
+    pub zombie: usize,
+}
+
+impl TaskCounts {
+    pub fn tally<I: IntoIterator<Item = State>>(states: I) -> TaskCounts {
+        let mut ret = TaskCounts::default();
+        for state in states {
+            ret.total += 1;
+            match state {
+                State::Running => ret.running += 1,
+                State::Sleeping => ret.sleeping += 1,
+                State::Waiting => ret.waiting += 1,
+                State::Stopped | State::Tracing => ret.stopped += 1,
+                State::Zombie => ret.zombie += 1,
+                /* dead processes are only seen for an instant while they're torn down */
+                State::Dead => {}
+            }
+        }
+        ret
+    }
+
+    /* The parts of the summary line and their colors. States without processes are left out. */
+    fn parts(&self) -> Vec<(String, Color)> {
+        let mut ret = vec![(format!("tasks: {}", self.total), Color::Default)];
+        for &(count, name, color) in &[
+            (self.running, "running", Color::Green),
+            (self.sleeping, "sleeping", Color::Default),
+            (self.waiting, "uninterruptible", Color::Default),
+            (self.stopped, "stopped", Color::Default),
+            (self.zombie, "zombie", Color::Red),
+        ] {
+            if count > 0 {
+                ret.push((format!(", {} {}", count, name), color));
+            }
+        }
+        ret
+    }
+}
+
+/// Draws the `TaskCounts` of the latest snapshot on a single row.
+#[derive(Debug, Default)]
+pub struct TaskSummary {
+    counts: TaskCounts,
+}
+
+impl TaskSummary {
+    /// Count the processes of a new snapshot.
+    pub fn update<I: IntoIterator<Item = State>>(&mut self, states: I) {
+        self.counts = TaskCounts::tally(states);
+    }
+
+    /// Write the summary on the first row of `area`.
+    pub fn draw(&self, grid: &mut CellBuffer, area: Area) {
+        let (mut upper_left, bottom_right) = area;
+        for (text, color) in self.counts.parts() {
+            let (x, _) = write_string_to_grid(
+                &text,
+                grid,
+                color,
+                Color::Default,
+                if color == Color::Default {
+                    Attr::Default
+                } else {
+                    Attr::Bold
+                },
+                (upper_left, (bottom_right.0, upper_left.1)),
+                false,
+            );
+            upper_left.0 = x;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tally() {
+        let states = "RSSSDZZTtSI".chars().map(State::from);
+        let counts = TaskCounts::tally(states);
+        assert_eq!(
+            counts,
+            TaskCounts {
+                total: 11,
+                running: 1,
+                sleeping: 5,
+                waiting: 1,
+                stopped: 2,
+                zombie: 2,
+            }
+        );
+        let text = counts
+            .parts()
+            .into_iter()
+            .map(|(text, _)| text)
+            .collect::<String>();
+        assert_eq!(
+            text,
+            "tasks: 11, 1 running, 5 sleeping, 1 uninterruptible, 2 stopped, 2 zombie"
+        );
+        assert_eq!(counts.parts()[1].1, Color::Green);
+        assert_eq!(counts.parts()[5].1, Color::Red);
+
+        /* only the states that have processes are shown */
+        let counts = TaskCounts::tally("SSZ".chars().map(State::from));
+        let parts = counts.parts();
+        assert_eq!(parts.len(), 3);
+        assert_eq!(parts[2], (", 1 zombie".to_string(), Color::Red));
+        assert_eq!(TaskCounts::tally(vec![]).parts().len(), 1);
+    }
+}
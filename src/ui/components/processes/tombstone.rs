@@ -0,0 +1,99 @@
+/*
+ * bb
+ *
+ * Copyright 2019 Manos Pitsidianakis
+ *
+ * This file is part of bb.
+ *
+ * bb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * bb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with bb. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+/*! Rows of processes that exited, kept in the list for a few samples with `--tombstones` so that
+short-lived processes can be read before they're gone.
+*/
+
+use super::{Pid, ProcessDisplay, State};
+use crate::ui::Color;
+use std::collections::HashSet;
+
+/// Foreground of the rows of tombstones, which are also dimmed.
+pub const TOMBSTONE_FG: Color = Color::Byte(243); // Grey46
+
+/// The processes that exited in the last `retention` samples.
+#[derive(Debug)]
+pub struct Tombstones {
+    retention: usize,
+    /* each with the number of samples since it exited */
+    graves: Vec<(ProcessDisplay, usize)>,
+}
+
+impl Tombstones {
+    pub fn new(retention: usize) -> Tombstones {
+        Tombstones {
+            retention,
+            graves: vec![],
+        }
+    }
+
+    /* Turn the processes of `previous`, the list before `current` was read, that aren't in
+     * `current` into tombstones. On a `new_sample` the older tombstones age, and are dropped after
+     * `retention` samples. Tombstones whose PID is in use again are dropped right away. Rows of
+     * groups and of earlier tombstones in `previous` are skipped. */
+    pub fn record(
+        &mut self,
+        previous: Vec<ProcessDisplay>,
+        current: &[ProcessDisplay],
+        new_sample: bool,
+    ) {
+        let alive = current.iter().map(|p| p.i).collect::<HashSet<Pid>>();
+        let buried = self
+            .graves
+            .iter()
+            .map(|(p, _)| p.i)
+            .collect::<HashSet<Pid>>();
+        let retention = self.retention;
+        if new_sample {
+            for (_, age) in self.graves.iter_mut() {
+                *age += 1;
+            }
+        }
+        self.graves
+            .retain(|(p, age)| *age < retention && !alive.contains(&p.i));
+        for p in previous {
+            if p.members == 0 && !alive.contains(&p.i) && !buried.contains(&p.i) {
+                self.graves.push((bury(p), 0));
+            }
+        }
+    }
+
+    /// Append a row for each tombstone to `processes`.
+    pub fn merge_into(&self, processes: &mut Vec<ProcessDisplay>) {
+        processes.extend(self.graves.iter().map(|(p, _)| p.clone()));
+    }
+
+    pub fn contains(&self, pid: Pid) -> bool {
+        self.graves.iter().any(|(p, _)| p.i == pid)
+    }
+}
+
+/* `p` as it's shown after it exited: dead, without a cpu usage */
+fn bury(p: ProcessDisplay) -> ProcessDisplay {
+    ProcessDisplay {
+        state: State::Dead,
+        cpu_percent: None,
+        cpu_percent_raw: None,
+        sparkline: None,
+        ..p
+    }
+}
@@ -0,0 +1,136 @@
+/*
+ * bb
+ *
+ * Copyright 2019 Manos Pitsidianakis
+ *
+ * This file is part of bb.
+ *
+ * bb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * bb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with bb. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+/*! The guides the tree view draws to the left of command lines, with `--tree-glyphs`, eg.
+
+```text
+├─>worker
+│ └─>child
+└─+worker
+```
+
+The `>` and `+` after the guides mark expanded and collapsed processes, and are the same in every
+set.
+*/
+
+use crate::ui::text_processing::wcwidth;
+
+/// Characters the guides of the tree view are drawn with.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct TreeGlyphs {
+    /// Continues the guide of an ancestor that has more children below.
+    pub vertical: char,
+    /// Leads to a process with more siblings below it.
+    pub branch: char,
+    /// Leads to the last of its siblings.
+    pub last: char,
+    /// Between the branch and the process.
+    pub horizontal: char,
+}
+
+/// Names accepted by `TreeGlyphs::parse`, besides four characters.
+pub const TREE_GLYPH_SETS: &[&str] = &["unicode", "ascii"];
+
+impl TreeGlyphs {
+    pub const UNICODE: TreeGlyphs = TreeGlyphs {
+        vertical: '│',
+        branch: '├',
+        last: '└',
+        horizontal: '─',
+    };
+    pub const ASCII: TreeGlyphs = TreeGlyphs {
+        vertical: '|',
+        branch: '|',
+        last: '`',
+        horizontal: '-',
+    };
+
+    /// One of `TREE_GLYPH_SETS`, or the vertical, branch, last branch and horizontal characters,
+    /// eg. `|++-`.
+    pub fn parse(s: &str) -> Option<TreeGlyphs> {
+        match s {
+            "unicode" => return Some(TreeGlyphs::UNICODE),
+            "ascii" => return Some(TreeGlyphs::ASCII),
+            _ => {}
+        }
+        let chars = s.chars().collect::<Vec<char>>();
+        match chars[..] {
+            [vertical, branch, last, horizontal] => Some(TreeGlyphs {
+                vertical,
+                branch,
+                last,
+                horizontal,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Guides are laid out assuming every glyph takes up one cell. Returns `ASCII` if some glyph
+    /// of `self` doesn't.
+    pub fn or_ascii(self) -> TreeGlyphs {
+        let narrow = |ch: char| wcwidth(u32::from(ch)) == Some(1);
+        if [self.vertical, self.branch, self.last, self.horizontal]
+            .iter()
+            .all(|&ch| narrow(ch))
+        {
+            self
+        } else {
+            TreeGlyphs::ASCII
+        }
+    }
+}
+
+impl Default for TreeGlyphs {
+    fn default() -> TreeGlyphs {
+        TreeGlyphs::UNICODE
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_tree_glyphs() {
+        assert_eq!(TreeGlyphs::parse("unicode"), Some(TreeGlyphs::UNICODE));
+        assert_eq!(TreeGlyphs::parse("ascii"), Some(TreeGlyphs::ASCII));
+        assert_eq!(
+            TreeGlyphs::parse("|++-"),
+            Some(TreeGlyphs {
+                vertical: '|',
+                branch: '+',
+                last: '+',
+                horizontal: '-',
+            })
+        );
+        assert_eq!(TreeGlyphs::parse("|+-"), None);
+        assert_eq!(TreeGlyphs::parse("|++--"), None);
+        for name in TREE_GLYPH_SETS {
+            let glyphs = TreeGlyphs::parse(name).unwrap();
+            assert_eq!(glyphs.or_ascii(), glyphs, "{}", name);
+        }
+        /* fullwidth characters take up two cells */
+        assert_eq!(
+            TreeGlyphs::parse("｜++-").unwrap().or_ascii(),
+            TreeGlyphs::ASCII
+        );
+    }
+}
@@ -0,0 +1,220 @@
+/*
+ * bb
+ *
+ * Copyright 2019 Manos Pitsidianakis
+ *
+ * This file is part of bb.
+ *
+ * bb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * bb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with bb. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+/*! Views: saved combinations of a sort order, optional columns and a filter, given with `--view`
+and cycled through with Alt-v.
+*/
+
+use super::{SecondarySort, Sort};
+
+/// Optional columns a view can show, by their names in `--align`.
+pub const VIEW_COLUMNS: &[&str] = &["threads", "time", "elapsed", "caps"];
+
+/// Which of the `VIEW_COLUMNS` are shown.
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct ViewColumns {
+    pub threads: bool,
+    pub cpu_time: bool,
+    pub elapsed: bool,
+    pub caps: bool,
+}
+
+/// The state of the list that a view sets, all of it at once. `Views::new` fills in the sort
+/// and columns a view leaves out from the initial view.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ViewPreset {
+    pub name: String,
+    pub(super) sort: Option<Sort>,
+    pub(super) columns: Option<ViewColumns>,
+    pub(super) filter: Option<String>,
+}
+
+impl ViewPreset {
+    /// Parses the value of `--view`: a name followed by comma separated `sort=KEY[:asc|:desc]`,
+    /// with the keys of `--then-sort`, `columns=COLUMN+...` of `VIEW_COLUMNS` and `filter=TERM`,
+    /// eg. `mem,sort=memory:desc,columns=threads+time`. Without a filter, the view shows every
+    /// process.
+    pub fn parse(spec: &str) -> Result<ViewPreset, String> {
+        let mut parts = spec.split(',');
+        let name = parts.next().unwrap_or("");
+        if name.is_empty() || name.contains('=') {
+            return Err(format!("view `{}` has no name", spec));
+        }
+        let mut ret = ViewPreset {
+            name: name.to_string(),
+            sort: None,
+            columns: None,
+            filter: None,
+        };
+        for part in parts {
+            match part.split_once('=') {
+                Some(("sort", key)) => ret.sort = Some(SecondarySort::parse(key)?.0),
+                Some(("columns", names)) => {
+                    let columns = ret.columns.insert(ViewColumns::default());
+                    for column in names.split('+').filter(|name| !name.is_empty()) {
+                        match column {
+                            "threads" => columns.threads = true,
+                            "time" => columns.cpu_time = true,
+                            "elapsed" => columns.elapsed = true,
+                            "caps" => columns.caps = true,
+                            _ => {
+                                return Err(format!(
+                                    "unknown column `{}`, expected one of: {}",
+                                    column,
+                                    VIEW_COLUMNS.join(", ")
+                                ))
+                            }
+                        }
+                    }
+                }
+                Some(("filter", term)) => {
+                    ret.filter = Some(term.to_string()).filter(|term| !term.is_empty())
+                }
+                _ => {
+                    return Err(format!(
+                        "invalid view setting `{}`, expected sort=, columns= or filter=",
+                        part
+                    ))
+                }
+            }
+        }
+        Ok(ret)
+    }
+}
+
+/// The views Alt-v cycles through, starting from the initial one.
+#[derive(Debug)]
+pub struct Views {
+    views: Vec<ViewPreset>,
+    current: usize,
+}
+
+impl Views {
+    /// `initial` is the view bb starts in, with its sort and columns.
+    pub fn new(initial: ViewPreset, presets: Vec<ViewPreset>) -> Views {
+        let views = presets
+            .into_iter()
+            .map(|preset| ViewPreset {
+                sort: preset.sort.or(initial.sort),
+                columns: preset.columns.or(initial.columns),
+                ..preset
+            })
+            .collect::<Vec<ViewPreset>>();
+        Views {
+            views: std::iter::once(initial).chain(views).collect(),
+            current: 0,
+        }
+    }
+
+    /// Whether there are views besides the initial one.
+    pub fn is_empty(&self) -> bool {
+        self.views.len() < 2
+    }
+
+    /// Set the columns of the current view, eg. to those picked while in it.
+    pub fn set_columns(&mut self, columns: ViewColumns) {
+        self.views[self.current].columns = Some(columns);
+    }
+
+    /// Advance to the next view, back to the initial one after the last, and return it.
+    pub fn cycle(&mut self) -> &ViewPreset {
+        self.current = (self.current + 1) % self.views.len();
+        &self.views[self.current]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_views() {
+        let view = ViewPreset::parse("mem,sort=memory:desc,columns=time+caps,filter=fire").unwrap();
+        assert_eq!(view.name, "mem");
+        assert!(matches!(view.sort, Some(Sort::VmRssDesc)));
+        assert_eq!(
+            view.columns,
+            Some(ViewColumns {
+                cpu_time: true,
+                caps: true,
+                ..Default::default()
+            })
+        );
+        assert_eq!(view.filter.as_deref(), Some("fire"));
+        let view = ViewPreset::parse("pids,sort=pid").unwrap();
+        assert!(matches!(view.sort, Some(Sort::PidAsc)));
+        assert_eq!((view.columns, view.filter), (None, None));
+        assert_eq!(
+            ViewPreset::parse("bare,columns=").unwrap().columns,
+            Some(ViewColumns::default())
+        );
+
+        assert!(ViewPreset::parse("").is_err());
+        assert!(ViewPreset::parse("sort=cpu").is_err());
+        assert!(ViewPreset::parse("x,sort=size").is_err());
+        assert!(ViewPreset::parse("x,columns=io").is_err());
+        assert!(ViewPreset::parse("x,colour=red").is_err());
+    }
+
+    #[test]
+    fn test_cycle_views() {
+        let initial = ViewPreset {
+            name: "default".to_string(),
+            sort: Some(Sort::CpuDesc),
+            columns: Some(ViewColumns {
+                threads: true,
+                ..Default::default()
+            }),
+            filter: None,
+        };
+        let mut views = Views::new(
+            initial.clone(),
+            vec![
+                ViewPreset::parse("mem,sort=memory:desc,filter=fire").unwrap(),
+                ViewPreset::parse("plain,columns=").unwrap(),
+            ],
+        );
+        assert!(!views.is_empty());
+        /* what the presets leave out is as in the initial view */
+        let mem = views.cycle().clone();
+        assert_eq!(mem.name, "mem");
+        assert!(matches!(mem.sort, Some(Sort::VmRssDesc)));
+        assert_eq!(mem.columns, initial.columns);
+        let plain = views.cycle().clone();
+        assert_eq!(plain.name, "plain");
+        assert!(matches!(plain.sort, Some(Sort::CpuDesc)));
+        assert_eq!(plain.columns, Some(ViewColumns::default()));
+        /* and back to the start */
+        assert_eq!(views.cycle(), &initial);
+        assert_eq!(views.cycle().name, "mem");
+        assert!(Views::new(initial.clone(), vec![]).is_empty());
+
+        /* columns picked in a view stay with it */
+        let picked = ViewColumns {
+            caps: true,
+            ..Default::default()
+        };
+        views.set_columns(picked);
+        assert_eq!(views.cycle().name, "plain");
+        assert_eq!(views.cycle(), &initial);
+        assert_eq!(views.cycle().columns, Some(picked));
+    }
+}
@@ -0,0 +1,73 @@
+/*
+ * bb
+ *
+ * Copyright 2019 Manos Pitsidianakis
+ *
+ * This file is part of bb.
+ *
+ * bb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * bb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with bb. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+/*! Drawing components without a terminal, to test what they look like.
+
+`render` draws a `Component` on a grid of a fixed size; `render_with` draws anything that draws
+on an area, like a single meter. What was drawn can then be compared as text with `grid_rows`, or
+with its colors and attributes as the escape sequences a terminal would get with `frame`.
+*/
+
+use super::*;
+
+/// Draw `component` as on a tick, on a blank grid of `cols` by `rows` cells, and return the grid.
+pub fn render(component: &mut dyn Component, size: (usize, usize)) -> CellBuffer {
+    render_with(size, |grid, area| {
+        component.draw(grid, area, &mut VecDeque::new(), true)
+    })
+}
+
+/// Call `draw` with a blank grid of `cols` by `rows` cells and the area of all of it, and return
+/// the grid.
+pub fn render_with(
+    (cols, rows): (usize, usize),
+    draw: impl FnOnce(&mut CellBuffer, Area),
+) -> CellBuffer {
+    let mut grid = CellBuffer::new(cols, rows, Cell::default());
+    if cols > 0 && rows > 0 {
+        draw(&mut grid, ((0, 0), (cols - 1, rows - 1)));
+    }
+    grid
+}
+
+/// The characters of each row of `grid`, one per cell.
+pub fn grid_rows(grid: &CellBuffer) -> Vec<String> {
+    let (cols, rows) = grid.size();
+    (0..rows)
+        .map(|y| (0..cols).map(|x| grid[(x, y)].ch()).collect())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let mut grid = render_with((3, 2), |grid, _| {
+            grid[(1, 1)].set_ch('x').set_attrs(Attr::Bold);
+        });
+        assert_eq!(grid_rows(&grid), ["   ", " x "]);
+        assert_eq!(frame(&grid), "\x1B[1;1H   \x1B[2;1H \x1B[1mx\x1B[0m ");
+        grid = render_with((0, 0), |_, _| unreachable!());
+        assert!(grid_rows(&grid).is_empty());
+    }
+}
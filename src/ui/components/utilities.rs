@@ -25,7 +25,9 @@ extern crate cassowary;
 use super::*;
 use std::fs::File;
 use std::io::prelude::*;
+use std::path::{self, Path, PathBuf};
 use std::str::FromStr;
+use std::time::{Duration, Instant};
 
 use cassowary::strength::{REQUIRED, STRONG, WEAK};
 use cassowary::WeightedRelation::*;
@@ -65,9 +67,143 @@ impl Bytes {
             format!("{:.2} PiB", bytes / PETABYTE)
         }
     }
+
+    /// With one decimal place and the initial of the unit, eg. `4.2G`, for narrow columns.
+    pub fn as_short_string(&self) -> String {
+        let bytes = self.0 as f64;
+        if bytes < KILOBYTE {
+            format!("{}B", self.0)
+        } else if bytes < MEGABYTE {
+            format!("{:.1}K", bytes / KILOBYTE)
+        } else if bytes < GIGABYTE {
+            format!("{:.1}M", bytes / MEGABYTE)
+        } else if bytes < PETABYTE {
+            format!("{:.1}G", bytes / GIGABYTE)
+        } else {
+            format!("{:.1}P", bytes / PETABYTE)
+        }
+    }
+
+    /// In `notation`, eg. `1.15 EB` or `1.15e18 bytes` for 2⁶⁰ bytes. `Notation::Binary` is
+    /// `as_convenient_string`.
+    pub fn in_notation(&self, notation: Notation) -> String {
+        let bytes = self.0 as f64;
+        if notation == Notation::Binary {
+            return self.as_convenient_string();
+        } else if bytes == 0.0 {
+            return "0".to_string();
+        } else if bytes < SCIENTIFIC_FROM {
+            return format!("{:.2} bytes", bytes);
+        }
+        if notation == Notation::Scientific {
+            return format!("{:.2e} bytes", bytes);
+        }
+        let group = std::cmp::min(bytes.log10() as i32 / 3, SI_PREFIXES.len() as i32);
+        format!(
+            "{:.2} {}B",
+            bytes / 1000f64.powi(group),
+            SI_PREFIXES[group as usize - 1]
+        )
+    }
+}
+
+/* Counts below this are written out in every notation */
+const SCIENTIFIC_FROM: f64 = 1000.0;
+
+/* The prefixes of powers of 1000 in engineering notation, from kilo */
+const SI_PREFIXES: &[&str] = &["k", "M", "G", "T", "P", "E"];
+
+/// Names accepted by `Notation::parse`.
+pub const NOTATIONS: &[&str] = &["binary", "engineering", "scientific"];
+
+/// How large byte counts are written, with `--notation`.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub enum Notation {
+    /// In powers of 1024, eg. `390.53 MiB`, up to PiB.
+    #[default]
+    Binary,
+    /// In powers of 1000 with SI prefixes, eg. `409.50 MB`, up to EB.
+    Engineering,
+    /// With a power of ten, eg. `4.09e8 bytes`.
+    Scientific,
 }
 
+impl Notation {
+    pub fn parse(name: &str) -> Option<Notation> {
+        match name {
+            "binary" => Some(Notation::Binary),
+            "engineering" => Some(Notation::Engineering),
+            "scientific" => Some(Notation::Scientific),
+            _ => None,
+        }
+    }
+}
+
+/* Samples further apart than this, eg. while updates are frozen, restart the average */
+const EWMA_MAX_GAP: Duration = Duration::from_secs(5);
+
+/// Exponentially weighted moving average of a metric, to smooth values that jitter between
+/// samples.
 #[derive(Debug, Copy, Clone)]
+pub struct Ewma {
+    alpha: f64,
+    value: Option<(f64, Instant)>,
+}
+
+impl Ewma {
+    /// `alpha` in (0, 1] is the weight of each new sample, so 1 disables smoothing.
+    pub fn new(alpha: f64) -> Ewma {
+        Ewma { alpha, value: None }
+    }
+
+    /// Add a sample taken at `now` and return the new average. Samples that aren't finite (eg. a
+    /// percentage of a zero interval) are ignored.
+    pub fn update(&mut self, sample: f64, now: Instant) -> f64 {
+        if !sample.is_finite() {
+            return self.value().unwrap_or(sample);
+        }
+        let value = match self.value {
+            Some((value, last)) if now.saturating_duration_since(last) <= EWMA_MAX_GAP => {
+                self.alpha * sample + (1.0 - self.alpha) * value
+            }
+            _ => sample,
+        };
+        self.value = Some((value, now));
+        value
+    }
+
+    /// The current average, if any sample was added.
+    pub fn value(&self) -> Option<f64> {
+        self.value.map(|(value, _)| value)
+    }
+}
+
+/// How much a cumulative counter grew per unit of `elapsed`, from its `previous` reading to
+/// `current`.
+///
+/// The first reading of a counter only establishes a baseline, so there is no rate without a
+/// `previous` reading, nor when no time has elapsed.
+pub fn rate(previous: Option<usize>, current: usize, elapsed: usize) -> Option<f64> {
+    let previous = previous?;
+    if elapsed == 0 {
+        return None;
+    }
+    Some(current.saturating_sub(previous) as f64 / elapsed as f64)
+}
+
+/* Longest interval rates are computed over. Samples are 1.6 seconds apart, so a longer one between
+ * two of them is a counter that jumped rather than time that passed */
+const MAX_RATE_INTERVAL: f64 = 24.0 * 60.0 * 60.0;
+
+/// Whether rates can be computed over `seconds` between two samples. The intervals are measured
+/// with the cpu time counters of `/proc/stat`, which don't follow the wall clock when it's stepped,
+/// but don't advance either when cpus go offline, and no interval would give nonsensical rates.
+/// Rates of an interval that isn't usable are skipped, and the previous ones are kept instead.
+pub fn usable_interval(seconds: f64) -> bool {
+    seconds.is_finite() && seconds > 0.0 && seconds <= MAX_RATE_INTERVAL
+}
+
+#[derive(Debug, Default, Copy, Clone)]
 pub struct Stat {
     pub user_time: usize,
     pub nice_time: usize,
@@ -82,6 +218,11 @@ pub struct Stat {
 }
 
 impl Stat {
+    /// Time spent running user and system code or waiting for I/O, which the cpu bars show.
+    pub fn busy_time(&self) -> usize {
+        self.user_time + self.system_time + self.iowait_time
+    }
+
     pub fn total_time(&self) -> usize {
         (self.user_time - self.guest)
             + self.system_time
@@ -95,31 +236,328 @@ impl Stat {
     }
 }
 
-pub fn get_stat(boot_time: &mut usize) -> Vec<Stat> {
-    let mut file = File::open("/proc/stat").unwrap();
-    let mut res = String::with_capacity(2048);
-    file.read_to_string(&mut res).unwrap();
+/// Clock ticks per second, the unit of the process times in `/proc/[pid]/stat`.
+pub fn clock_ticks() -> usize {
+    match unsafe { libc::sysconf(libc::_SC_CLK_TCK) } {
+        ticks if ticks > 0 => ticks as usize,
+        /* USER_HZ on every architecture Linux supports */
+        _ => 100,
+    }
+}
+
+/// Seconds a process has been running at `now`, given its `start_time` in clock ticks after boot
+/// (`starttime` in `/proc/[pid]/stat`), and `boot_time` (`btime` in `/proc/stat`) and `now` in
+/// seconds since the epoch.
+pub fn elapsed_since_start(
+    start_time: usize,
+    boot_time: usize,
+    clock_ticks: usize,
+    now: u64,
+) -> u64 {
+    let started = (boot_time + start_time / clock_ticks) as u64;
+    now.saturating_sub(started)
+}
+
+/// Format a duration in seconds as `HH:MM:SS`, prefixed with the number of days if there are
+/// any, eg. `3d 04:05:06`.
+pub fn format_elapsed(secs: u64) -> String {
+    let (days, secs) = (secs / 86400, secs % 86400);
+    let hms = format!("{:02}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60);
+    if days > 0 {
+        format!("{}d {}", days, hms)
+    } else {
+        hms
+    }
+}
+
+/// Format `ticks` of cpu time like the `TIME+` column of top, as `MM:SS.hh`. Minutes aren't
+/// wrapped into hours, eg. `1234:56.78`.
+pub fn format_cpu_time(ticks: usize, clock_ticks: usize) -> String {
+    let hundredths = ticks as u64 * 100 / clock_ticks.max(1) as u64;
+    let secs = hundredths / 100;
+    format!("{}:{:02}.{:02}", secs / 60, secs % 60, hundredths % 100)
+}
+
+/// How cpu percentages are shown with `--precise-below`: the ones below `precise_below` with
+/// `decimals` decimal places, eg. `0.3%`, and the others rounded to whole numbers, eg. `12%`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct PercentFormat {
+    /// In hundredths of a percent.
+    pub precise_below: usize,
+    /// 1 or 2.
+    pub decimals: usize,
+}
+
+impl PercentFormat {
+    /// Format `hundredths` of a percent.
+    pub fn format(&self, hundredths: usize) -> String {
+        if hundredths >= self.precise_below {
+            return format!("{}%", (hundredths + 50) / 100);
+        }
+        let decimals = self.decimals.clamp(1, 2) as u32;
+        let step = 10usize.pow(2 - decimals);
+        let value = (hundredths + step / 2) / step;
+        let scale = 10usize.pow(decimals);
+        format!(
+            "{}.{:0width$}%",
+            value / scale,
+            value % scale,
+            width = decimals as usize
+        )
+    }
+}
+
+/// Names accepted by `MemFormat::parse`.
+pub const MEM_FORMATS: &[&str] = &["bytes", "percent", "combined", "compact"];
+
+/* Columns `MemFormat::Compact` fits in, as wide as eg. `390.5M 2%` */
+const COMPACT_MEM_WIDTH: usize = 9;
+
+/// How the memory column shows the resident memory of processes, with `--mem-format`.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub enum MemFormat {
+    /// eg. `390.53 MiB`
+    #[default]
+    Bytes,
+    /// The share of the total memory, eg. `2.5%`.
+    Percent,
+    /// See `format_mem_combined`.
+    Combined,
+    /// See `format_mem_compact`, in `COMPACT_MEM_WIDTH` columns.
+    Compact,
+}
+
+impl MemFormat {
+    pub fn parse(name: &str) -> Option<MemFormat> {
+        match name {
+            "bytes" => Some(MemFormat::Bytes),
+            "percent" => Some(MemFormat::Percent),
+            "combined" => Some(MemFormat::Combined),
+            "compact" => Some(MemFormat::Compact),
+            _ => None,
+        }
+    }
+
+    /// Format `used` bytes of `total`. Without a `total`, every format but `Bytes` shows `used`
+    /// like `Bytes::as_short_string`.
+    pub fn format(self, used: usize, total: usize) -> String {
+        match self {
+            MemFormat::Bytes => Bytes(used).as_convenient_string(),
+            MemFormat::Percent if total > 0 => {
+                format!("{:.1}%", used as f64 * 100.0 / total as f64)
+            }
+            MemFormat::Percent | MemFormat::Combined => format_mem_combined(used, total),
+            MemFormat::Compact => format_mem_compact(used, total, COMPACT_MEM_WIDTH),
+        }
+    }
+}
+
+/* `used` in percent of `total`, rounded to a whole number */
+fn mem_percent(used: usize, total: usize) -> String {
+    format!("{}%", (used as f64 * 100.0 / total as f64).round())
+}
+
+/// `used` and `total` bytes and the share of one in the other, eg. `4.2G/15.6G (27%)`.
+pub fn format_mem_combined(used: usize, total: usize) -> String {
+    if total == 0 {
+        return Bytes(used).as_short_string();
+    }
+    format!(
+        "{}/{} ({})",
+        Bytes(used).as_short_string(),
+        Bytes(total).as_short_string(),
+        mem_percent(used, total)
+    )
+}
+
+/// `format_mem_combined` shortened until it fits in `width` columns: without the total, eg.
+/// `4.2G (27%)`, then without the parentheses, eg. `4.2G 27%`, and then only the percentage.
+pub fn format_mem_compact(used: usize, total: usize, width: usize) -> String {
+    let combined = format_mem_combined(used, total);
+    if total == 0 || combined.len() <= width {
+        return combined;
+    }
+    let (used, percent) = (Bytes(used).as_short_string(), mem_percent(used, total));
+    vec![
+        format!("{} ({})", used, percent),
+        format!("{} {}", used, percent),
+    ]
+    .into_iter()
+    .find(|s| s.len() <= width)
+    .unwrap_or(percent)
+}
+
+/// Local time as `YYYY-MM-DD HH:MM:SS`.
+pub fn timestamp() -> String {
+    local_time(b"%Y-%m-%d %H:%M:%S\0")
+}
+
+/// Local time of day as `HH:MM:SS`.
+pub fn clock() -> String {
+    local_time(b"%H:%M:%S\0")
+}
+
+/* The local time formatted by strftime(3) with the nul-terminated `format` */
+fn local_time(format: &[u8]) -> String {
+    let now = unsafe { libc::time(std::ptr::null_mut()) };
+    strftime(&broken_down(now as u64, libc::localtime_r), format)
+}
+
+/// `time`, in seconds since the epoch, in the local time zone formatted by strftime(3) with
+/// `format`, eg. `%H:%M:%S`. It's empty if the result takes more than `MAX_TIME_LEN` bytes.
+pub fn format_local_time(time: u64, format: &str) -> String {
+    let mut format = format.as_bytes().to_vec();
+    format.push(0);
+    strftime(&broken_down(time, libc::localtime_r), &format)
+}
+
+/// Longest result of `format_local_time`.
+pub const MAX_TIME_LEN: usize = 63;
+
+/* The fields of `time`, in seconds since the epoch, as split by `convert`: localtime_r(3) or
+ * gmtime_r(3) */
+fn broken_down(
+    time: u64,
+    convert: unsafe extern "C" fn(*const libc::time_t, *mut libc::tm) -> *mut libc::tm,
+) -> libc::tm {
+    let time = time as libc::time_t;
+    unsafe {
+        let mut tm: libc::tm = std::mem::zeroed();
+        convert(&time, &mut tm);
+        tm
+    }
+}
+
+/* strftime(3) of `tm` with the nul-terminated `format` */
+fn strftime(tm: &libc::tm, format: &[u8]) -> String {
+    let mut buf = [0u8; MAX_TIME_LEN + 1];
+    let len = unsafe {
+        libc::strftime(
+            buf.as_mut_ptr() as *mut libc::c_char,
+            buf.len(),
+            format.as_ptr() as *const libc::c_char,
+            tm,
+        )
+    };
+    String::from_utf8_lossy(&buf[..len]).into_owned()
+}
+
+/// Where procfs is mounted, which every file about the system and its processes is read from.
+/// It's `/proc` unless `--proc-root` points somewhere else, eg. at the procfs of another mount
+/// namespace.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProcSource {
+    root: PathBuf,
+}
+
+impl Default for ProcSource {
+    /// `/proc`, without the check of `new`: settings start out with it until they're given a
+    /// checked one.
+    fn default() -> Self {
+        ProcSource {
+            root: PathBuf::from("/proc"),
+        }
+    }
+}
+
+impl ProcSource {
+    /// The procfs at `root`, if its `stat` can be read, which everything else reads
+    /// unconditionally. The error keeps the kind of the failed read and describes the likely
+    /// cause.
+    pub fn new(root: impl Into<PathBuf>) -> std::io::Result<ProcSource> {
+        let root = root.into();
+        let stat = root.join("stat");
+        File::open(&stat)
+            .and_then(|mut file| file.read(&mut [0; 1]))
+            .map_err(|err| {
+                std::io::Error::new(
+                    err.kind(),
+                    format!(
+                        "cannot read {}: {}\n\
+                         bb reads process information from procfs, so it only runs on Linux. If \
+                         this is a container, make sure {} is mounted.",
+                        stat.display(),
+                        err,
+                        root.display()
+                    ),
+                )
+            })?;
+        Ok(ProcSource { root })
+    }
+
+    /// The procfs at `root` for tests of files other than `stat`, given an empty `stat` if it has
+    /// none so that it passes the check of `new`.
+    #[cfg(test)]
+    pub fn fixture(root: impl Into<PathBuf>) -> ProcSource {
+        let root = root.into();
+        std::fs::create_dir_all(&root).unwrap();
+        if !root.join("stat").exists() {
+            std::fs::write(root.join("stat"), "").unwrap();
+        }
+        ProcSource::new(root).unwrap()
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// The path of `relative`, eg. `stat` or `1/status`, under the root. Absolute paths and paths
+    /// with `..` in them could lead out of the root, so they are an `InvalidInput` error. Symbolic
+    /// links aren't resolved, as procfs is full of them, eg. `self`.
+    pub fn path(&self, relative: impl AsRef<Path>) -> std::io::Result<PathBuf> {
+        let relative = relative.as_ref();
+        if relative
+            .components()
+            .all(|c| matches!(c, path::Component::Normal(_) | path::Component::CurDir))
+        {
+            Ok(self.root.join(relative))
+        } else {
+            Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!(
+                    "{} is outside of {}",
+                    relative.display(),
+                    self.root.display()
+                ),
+            ))
+        }
+    }
+
+    pub fn read_to_string(&self, relative: impl AsRef<Path>) -> std::io::Result<String> {
+        std::fs::read_to_string(self.path(relative)?)
+    }
+}
+
+pub fn get_stat(source: &ProcSource, boot_time: &mut usize) -> Vec<Stat> {
+    read_stat(source, boot_time).unwrap()
+}
+
+/// Like `get_stat`, but it's an error instead of a panic if `stat` can't be read or parsed, so that
+/// the sampler thread can try again on its next tick.
+pub fn read_stat(source: &ProcSource, boot_time: &mut usize) -> std::io::Result<Vec<Stat>> {
+    let res = source.read_to_string("stat")?;
+    let field = |value| parse_field(value, "stat");
     let mut lines_iter = res.lines();
     let mut ret = Vec::with_capacity(8);
     let mut line;
     loop {
-        line = lines_iter.next().unwrap();
+        line = lines_iter.next().ok_or_else(|| malformed("stat"))?;
         if !line.starts_with("cpu") {
             break;
         }
 
         let mut mut_value_iter = line.split_whitespace().skip(1);
 
-        let user_time = usize::from_str(&mut_value_iter.next().unwrap()).unwrap();
-        let nice_time = usize::from_str(&mut_value_iter.next().unwrap()).unwrap();
-        let system_time = usize::from_str(&mut_value_iter.next().unwrap()).unwrap();
-        let idle_time = usize::from_str(&mut_value_iter.next().unwrap()).unwrap();
-        let iowait_time = usize::from_str(&mut_value_iter.next().unwrap()).unwrap();
-        let irq = usize::from_str(&mut_value_iter.next().unwrap()).unwrap();
-        let soft_irq = usize::from_str(&mut_value_iter.next().unwrap()).unwrap();
-        let steal = usize::from_str(&mut_value_iter.next().unwrap()).unwrap();
-        let guest = usize::from_str(&mut_value_iter.next().unwrap()).unwrap();
-        let guest_nice = usize::from_str(&mut_value_iter.next().unwrap()).unwrap();
+        let user_time = field(mut_value_iter.next())?;
+        let nice_time = field(mut_value_iter.next())?;
+        let system_time = field(mut_value_iter.next())?;
+        let idle_time = field(mut_value_iter.next())?;
+        let iowait_time = field(mut_value_iter.next())?;
+        let irq = field(mut_value_iter.next())?;
+        let soft_irq = field(mut_value_iter.next())?;
+        let steal = field(mut_value_iter.next())?;
+        let guest = field(mut_value_iter.next())?;
+        let guest_nice = field(mut_value_iter.next())?;
         ret.push(Stat {
             user_time,
             system_time,
@@ -134,11 +572,42 @@ pub fn get_stat(boot_time: &mut usize) -> Vec<Stat> {
         });
     }
     while !line.starts_with("btime") {
-        line = lines_iter.next().unwrap();
+        line = lines_iter.next().ok_or_else(|| malformed("stat"))?;
     }
-    *boot_time = usize::from_str(&line.split_whitespace().skip(1).next().unwrap()).unwrap();
+    *boot_time = field(line.split_whitespace().nth(1))?;
+
+    Ok(ret)
+}
+
+/// Available and total memory in KiB, from `meminfo`.
+pub fn get_mem_info(source: &ProcSource) -> std::io::Result<(usize, usize)> {
+    let res = source.read_to_string("meminfo")?;
+    let mut lines_iter = res.lines();
+    let mut next_value = || {
+        parse_field(
+            lines_iter
+                .next()
+                .and_then(|line| line.split_whitespace().nth(1)),
+            "meminfo",
+        )
+    };
+    let mem_total = next_value()?;
+    let mem_available = next_value()?;
+    Ok((mem_available, mem_total))
+}
+
+/* A number of `file`, which is malformed if it's missing or isn't one */
+fn parse_field(value: Option<&str>, file: &str) -> std::io::Result<usize> {
+    value
+        .and_then(|value| usize::from_str(value).ok())
+        .ok_or_else(|| malformed(file))
+}
 
-    ret
+fn malformed(file: &str) -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::InvalidData,
+        format!("malformed {}", file),
+    )
 }
 
 /// A horizontally split in half container.
@@ -146,6 +615,10 @@ pub fn get_stat(boot_time: &mut usize) -> Vec<Stat> {
 pub struct Window {
     top_bars: Box<dyn Component>,
     list: Box<dyn Component>,
+    /* rows the top bars are given when there's room */
+    top_bars_height: usize,
+    /* draw the titles of the components in a row above each, see `Component::title` */
+    titles: bool,
 }
 
 impl fmt::Display for Window {
@@ -155,9 +628,46 @@ impl fmt::Display for Window {
 }
 
 impl Window {
-    pub fn new(top_bars: Box<dyn Component>, list: Box<dyn Component>) -> Self {
-        Window { top_bars, list }
+    pub fn new(
+        top_bars: Box<dyn Component>,
+        list: Box<dyn Component>,
+        top_bars_height: usize,
+        titles: bool,
+    ) -> Self {
+        Window {
+            top_bars,
+            list,
+            top_bars_height,
+            titles,
+        }
+    }
+}
+
+/* Draw `component` in `area`, below its title if `titles` is set */
+fn draw_panel(
+    component: &mut dyn Component,
+    titles: bool,
+    grid: &mut CellBuffer,
+    area: Area,
+    dirty_areas: &mut VecDeque<Area>,
+    tick: bool,
+) {
+    if !titles {
+        component.draw(grid, area, dirty_areas, tick);
+        return;
     }
+    let upper_left = upper_left!(area);
+    let bottom_right = bottom_right!(area);
+    component.draw(
+        grid,
+        (pos_inc(upper_left, (0, 1)), bottom_right),
+        dirty_areas,
+        tick,
+    );
+    /* after drawing, so that the title is up to date with what the component shows */
+    let title_row = (upper_left, (get_x(bottom_right), get_y(upper_left)));
+    draw_title(grid, title_row, &component.title().unwrap_or_default());
+    dirty_areas.push_back(title_row);
 }
 
 struct Element {
@@ -178,6 +688,8 @@ impl Component for Window {
         let upper_left = upper_left!(area);
         let bottom_right = bottom_right!(area);
         let total_rows = get_y(bottom_right) - get_y(upper_left);
+        let title_rows = if self.titles { 1 } else { 0 };
+        let top_bars_height = (self.top_bars_height + title_rows) as f64;
         let window_height = Variable::new();
 
         let top_bars = Element {
@@ -201,10 +713,10 @@ impl Component for Window {
                 top_bars.top | LE(REQUIRED) | top_bars.bottom,
                 list.top | LE(REQUIRED) | list.bottom,
                 // preferred heights:
-                top_bars.bottom - top_bars.top | GE(REQUIRED) | 6.0,
-                top_bars.bottom - top_bars.top | EQ(WEAK) | 8.0,
-                top_bars.bottom - top_bars.top | LE(REQUIRED) | 8.0,
-                list.bottom - list.top | GE(REQUIRED) | 11.0,
+                (top_bars.bottom - top_bars.top) | GE(REQUIRED) | 6.0,
+                (top_bars.bottom - top_bars.top) | EQ(WEAK) | top_bars_height,
+                (top_bars.bottom - top_bars.top) | LE(REQUIRED) | top_bars_height,
+                (list.bottom - list.top) | GE(REQUIRED) | (11 + title_rows) as f64,
             ])
             .unwrap();
 
@@ -220,7 +732,9 @@ impl Component for Window {
                 .find(|(a, _)| *a == top_bars.bottom)
                 .map(|(_, b)| b)
                 .unwrap() as usize);
-        self.top_bars.draw(
+        draw_panel(
+            self.top_bars.as_mut(),
+            self.titles,
             grid,
             (
                 upper_left,
@@ -229,7 +743,9 @@ impl Component for Window {
             dirty_areas,
             tick,
         );
-        self.list.draw(
+        draw_panel(
+            self.list.as_mut(),
+            self.titles,
             grid,
             ((get_x(upper_left), get_y(upper_left) + mid), bottom_right),
             dirty_areas,
@@ -253,7 +769,356 @@ impl Component for Window {
 
     fn get_shortcuts(&self) -> ShortcutMaps {
         let mut top_bars_map = self.top_bars.get_shortcuts();
-        top_bars_map.extend(self.list.get_shortcuts().into_iter());
+        top_bars_map.extend(self.list.get_shortcuts());
         top_bars_map
     }
+
+    fn takes_text_input(&self) -> bool {
+        self.top_bars.takes_text_input() || self.list.takes_text_input()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_proc_source_check() {
+        let err = ProcSource::new("/nonexistent-bb-test/proc").unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+        let err = err.to_string();
+        assert!(
+            err.starts_with("cannot read /nonexistent-bb-test/proc/stat: "),
+            "{}",
+            err
+        );
+        assert!(err.contains("make sure /nonexistent-bb-test/proc is mounted"));
+    }
+
+    #[test]
+    fn test_proc_source() {
+        let dir = std::env::temp_dir().join(format!("bb-test-proc-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("sys/kernel")).unwrap();
+        std::fs::write(
+            dir.join("stat"),
+            "cpu  10 0 5 100 1 0 0 0 0 0\ncpu0 10 0 5 100 1 0 0 0 0 0\nintr 0\nbtime 1600000000\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("meminfo"),
+            "MemTotal:       16384 kB\nMemFree:         4096 kB\nMemAvailable:    8192 kB\n",
+        )
+        .unwrap();
+        std::fs::write(dir.join("sys/kernel/hostname"), "fixture\n").unwrap();
+
+        let source = ProcSource::new(&dir).unwrap();
+        let mut boot_time = 0;
+        let stats = get_stat(&source, &mut boot_time);
+        assert_eq!(
+            (stats.len(), stats[1].busy_time(), boot_time),
+            (2, 16, 1600000000)
+        );
+        assert_eq!(get_mem_info(&source).unwrap().1, 16384);
+        assert_eq!(
+            source.read_to_string("sys/kernel/hostname").unwrap(),
+            "fixture\n"
+        );
+        assert_eq!(source.path("./1/status").unwrap(), dir.join("1/status"));
+
+        /* nothing outside of the root can be reached */
+        for path in &["../stat", "sys/../../stat", "/etc/passwd", "/"] {
+            let err = source.read_to_string(path).unwrap_err();
+            assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput, "{}", path);
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(ProcSource::default().root(), Path::new("/proc"));
+    }
+
+    #[test]
+    fn test_rate() {
+        assert_eq!(rate(None, 100, 10), None);
+        assert_eq!(rate(Some(100), 100, 0), None);
+        assert_eq!(rate(Some(100), 150, 100), Some(0.5));
+        assert_eq!(rate(Some(100), 100, 100), Some(0.0));
+        /* counters that went backwards, eg. a reused PID, count as idle */
+        assert_eq!(rate(Some(100), 50, 100), Some(0.0));
+
+        /* the first sample of a series is the baseline */
+        let mut previous = None;
+        let rates = [0, 20, 50, 50]
+            .iter()
+            .map(|current| {
+                let ret = rate(previous, *current, 100);
+                previous = Some(*current);
+                ret
+            })
+            .collect::<Vec<Option<f64>>>();
+        assert_eq!(rates, vec![None, Some(0.2), Some(0.3), Some(0.0)]);
+    }
+
+    #[test]
+    fn test_elapsed_since_start() {
+        const BOOT_TIME: usize = 1_700_000_000;
+        /* started 12.34 seconds after boot, the fraction is dropped */
+        assert_eq!(
+            elapsed_since_start(1234, BOOT_TIME, 100, 1_700_000_112),
+            100
+        );
+        assert_eq!(elapsed_since_start(1234, BOOT_TIME, 100, 1_700_000_012), 0);
+        assert_eq!(
+            elapsed_since_start(12340, BOOT_TIME, 1000, 1_700_000_112),
+            100
+        );
+        assert_eq!(elapsed_since_start(0, BOOT_TIME, 250, 1_700_086_400), 86400);
+        /* a clock that went backwards doesn't underflow */
+        assert_eq!(elapsed_since_start(5000, BOOT_TIME, 100, 1_700_000_010), 0);
+
+        assert_eq!(format_elapsed(0), "00:00:00");
+        assert_eq!(format_elapsed(59), "00:00:59");
+        assert_eq!(format_elapsed(3600 + 2 * 60 + 3), "01:02:03");
+        assert_eq!(format_elapsed(86399), "23:59:59");
+        assert_eq!(format_elapsed(86400), "1d 00:00:00");
+        assert_eq!(
+            format_elapsed(3 * 86400 + 4 * 3600 + 5 * 60 + 6),
+            "3d 04:05:06"
+        );
+        assert_eq!(format_elapsed(400 * 86400), "400d 00:00:00");
+    }
+
+    #[test]
+    fn test_format_cpu_time() {
+        assert_eq!(format_cpu_time(0, 100), "0:00.00");
+        assert_eq!(format_cpu_time(7, 100), "0:00.07");
+        assert_eq!(format_cpu_time(5999, 100), "0:59.99");
+        assert_eq!(format_cpu_time(6000, 100), "1:00.00");
+        /* a tick is 4ms at 250 Hz */
+        assert_eq!(format_cpu_time(1, 250), "0:00.00");
+        assert_eq!(format_cpu_time(3, 250), "0:00.01");
+        assert_eq!(format_cpu_time(250 * 61 + 125, 250), "1:01.50");
+        /* hours of cpu time only add to the minutes */
+        assert_eq!(format_cpu_time(100 * 3600, 100), "60:00.00");
+        assert_eq!(
+            format_cpu_time(100 * (2 * 86400 + 5 * 3600 + 7 * 60 + 9) + 42, 100),
+            "3187:09.42"
+        );
+    }
+
+    #[test]
+    fn test_percent_format() {
+        let one_decimal = PercentFormat {
+            precise_below: 100,
+            decimals: 1,
+        };
+        assert_eq!(one_decimal.format(0), "0.0%");
+        assert_eq!(one_decimal.format(3), "0.0%");
+        assert_eq!(one_decimal.format(34), "0.3%");
+        assert_eq!(one_decimal.format(35), "0.4%");
+        assert_eq!(one_decimal.format(94), "0.9%");
+        /* from the threshold up, whole numbers */
+        assert_eq!(one_decimal.format(100), "1%");
+        assert_eq!(one_decimal.format(149), "1%");
+        assert_eq!(one_decimal.format(150), "2%");
+        assert_eq!(one_decimal.format(1234), "12%");
+        let two_decimals = PercentFormat {
+            precise_below: 500,
+            decimals: 2,
+        };
+        assert_eq!(two_decimals.format(3), "0.03%");
+        assert_eq!(two_decimals.format(120), "1.20%");
+        assert_eq!(two_decimals.format(499), "4.99%");
+        assert_eq!(two_decimals.format(500), "5%");
+        assert_eq!(two_decimals.format(10000), "100%");
+    }
+
+    #[test]
+    fn test_format_time() {
+        /* 2021-03-04 05:06:07 UTC, a Thursday */
+        let tm = broken_down(1614834367, libc::gmtime_r);
+        assert_eq!(strftime(&tm, b"%H:%M:%S\0"), "05:06:07");
+        assert_eq!(strftime(&tm, b"%Y-%m-%d %H:%M\0"), "2021-03-04 05:06");
+        assert_eq!(
+            strftime(&tm, b"sampled %a %T, 100%%\0"),
+            "sampled Thu 05:06:07, 100%"
+        );
+        assert_eq!(strftime(&tm, b"\0"), "");
+        /* too long for the buffer */
+        let long = format!("{}\0", "%Y".repeat(MAX_TIME_LEN / 4 + 1));
+        assert_eq!(strftime(&tm, long.as_bytes()), "");
+        let fits = format!("{}\0", "%Y".repeat(MAX_TIME_LEN / 4));
+        assert_eq!(strftime(&tm, fits.as_bytes()).len(), MAX_TIME_LEN / 4 * 4);
+        /* time zones are whole minutes apart */
+        assert!(format_local_time(1614834367, "%H:%M:%S").ends_with(":07"));
+    }
+
+    #[test]
+    fn test_usable_interval() {
+        assert!(usable_interval(1.6));
+        assert!(usable_interval(3600.0));
+        assert!(!usable_interval(0.0));
+        assert!(!usable_interval(-1.6));
+        assert!(!usable_interval(f64::NAN));
+        assert!(!usable_interval(f64::INFINITY));
+        assert!(!usable_interval(400.0 * 24.0 * 60.0 * 60.0));
+    }
+
+    #[test]
+    fn test_mem_format() {
+        const GIB: usize = 1024 * 1024 * 1024;
+        let (used, total) = (GIB * 42 / 10, GIB * 156 / 10);
+        assert_eq!(Bytes(used).as_short_string(), "4.2G");
+        assert_eq!(Bytes(512).as_short_string(), "512B");
+        assert_eq!(Bytes(1536).as_short_string(), "1.5K");
+        assert_eq!(format_mem_combined(used, total), "4.2G/15.6G (27%)");
+        assert_eq!(format_mem_combined(used, 0), "4.2G");
+
+        /* the compact variant drops parts until it fits */
+        assert_eq!(format_mem_compact(used, total, 16), "4.2G/15.6G (27%)");
+        assert_eq!(format_mem_compact(used, total, 15), "4.2G (27%)");
+        assert_eq!(format_mem_compact(used, total, 9), "4.2G 27%");
+        assert_eq!(format_mem_compact(used, total, 7), "27%");
+        assert_eq!(format_mem_compact(used, total, 1), "27%");
+        assert_eq!(format_mem_compact(total, total, 9), "100%");
+
+        assert_eq!(MemFormat::Bytes.format(used, total), "4.20 GiB");
+        assert_eq!(MemFormat::Percent.format(used, total), "26.9%");
+        assert_eq!(MemFormat::Percent.format(used, 0), "4.2G");
+        assert_eq!(MemFormat::Combined.format(used, total), "4.2G/15.6G (27%)");
+        let mib = 1024 * 1024;
+        assert_eq!(MemFormat::Compact.format(390 * mib, total), "390.0M 2%");
+        assert_eq!(MemFormat::Compact.format(used, total), "4.2G 27%");
+        for name in MEM_FORMATS {
+            assert!(MemFormat::parse(name).is_some(), "{}", name);
+        }
+        assert_eq!(MemFormat::parse("kib"), None);
+    }
+
+    #[test]
+    fn test_notation() {
+        let exabytes = Bytes(1 << 60);
+        assert_eq!(
+            exabytes.in_notation(Notation::Binary),
+            exabytes.as_convenient_string()
+        );
+        assert_eq!(exabytes.in_notation(Notation::Engineering), "1.15 EB");
+        assert_eq!(exabytes.in_notation(Notation::Scientific), "1.15e18 bytes");
+        /* past exa, engineering notation counts in EB */
+        let max = Bytes(usize::MAX);
+        assert_eq!(max.in_notation(Notation::Engineering), "18.45 EB");
+        assert_eq!(max.in_notation(Notation::Scientific), "1.84e19 bytes");
+        assert_eq!(
+            Bytes(409_500_000).in_notation(Notation::Engineering),
+            "409.50 MB"
+        );
+        assert_eq!(Bytes(1000).in_notation(Notation::Engineering), "1.00 kB");
+        assert_eq!(
+            Bytes(1000).in_notation(Notation::Scientific),
+            "1.00e3 bytes"
+        );
+        /* small counts are written out */
+        for notation in &[Notation::Engineering, Notation::Scientific] {
+            assert_eq!(Bytes(999).in_notation(*notation), "999.00 bytes");
+            assert_eq!(Bytes(0).in_notation(*notation), "0");
+        }
+        for name in NOTATIONS {
+            assert!(Notation::parse(name).is_some(), "{}", name);
+        }
+        assert_eq!(Notation::parse("si"), None);
+    }
+
+    /* Fills its area with `fill` */
+    #[derive(Debug)]
+    struct Panel {
+        title: &'static str,
+        fill: char,
+    }
+
+    impl fmt::Display for Panel {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "{}", self.title)
+        }
+    }
+
+    impl Component for Panel {
+        fn draw(&mut self, grid: &mut CellBuffer, area: Area, _: &mut VecDeque<Area>, _: bool) {
+            for y in get_y(upper_left!(area))..=get_y(bottom_right!(area)) {
+                for x in get_x(upper_left!(area))..=get_x(bottom_right!(area)) {
+                    grid[(x, y)].set_ch(self.fill);
+                }
+            }
+        }
+
+        fn process_event(&mut self, _: &mut UIEvent) {}
+
+        fn set_dirty(&mut self) {}
+
+        fn title(&self) -> Option<String> {
+            Some(self.title.to_string())
+        }
+    }
+
+    #[test]
+    fn test_titles() {
+        let row = |grid: &CellBuffer, y| {
+            (0..grid.size().0)
+                .map(|x| grid[(x, y)].ch())
+                .collect::<String>()
+        };
+        let mut grid = CellBuffer::new(16, 1, Cell::default());
+        draw_title(&mut grid, ((0, 0), (15, 0)), "CPU");
+        assert_eq!(row(&grid, 0), "─ CPU ──────────");
+        assert_eq!(grid[(2, 0)].attrs(), Attr::Bold);
+        /* too long for the 12 columns between the line segments */
+        draw_title(&mut grid, ((0, 0), (15, 0)), "Processes (1234)");
+        assert_eq!(row(&grid, 0), "─ Processes (… ─");
+        /* no room for a title at all */
+        let mut grid = CellBuffer::new(4, 1, Cell::default());
+        draw_title(&mut grid, ((0, 0), (3, 0)), "CPU");
+        assert_eq!(row(&grid, 0), "────");
+
+        let mut window = Window::new(
+            Box::new(Panel {
+                title: "System",
+                fill: 's',
+            }),
+            Box::new(Panel {
+                title: "Processes (2)",
+                fill: 'p',
+            }),
+            6,
+            true,
+        );
+        let mut grid = CellBuffer::new(20, 20, Cell::default());
+        let mut dirty_areas = VecDeque::new();
+        window.draw(&mut grid, ((0, 0), (19, 19)), &mut dirty_areas, true);
+        assert_eq!(row(&grid, 0), "─ System ───────────");
+        assert_eq!(row(&grid, 1), "s".repeat(20));
+        assert_eq!(row(&grid, 6), "s".repeat(20));
+        assert_eq!(row(&grid, 7), "─ Processes (2) ────");
+        assert_eq!(row(&grid, 8), "p".repeat(20));
+        assert_eq!(dirty_areas, [((0, 0), (19, 0)), ((0, 7), (19, 7))]);
+    }
+
+    #[test]
+    fn test_ewma() {
+        let start = Instant::now();
+        let tick = Duration::from_millis(1600);
+        let mut ewma = Ewma::new(0.5);
+        assert_eq!(ewma.value(), None);
+        let averages = [10.0, 30.0, 30.0, 0.0]
+            .iter()
+            .enumerate()
+            .map(|(i, sample)| ewma.update(*sample, start + tick * i as u32))
+            .collect::<Vec<f64>>();
+        assert_eq!(averages, vec![10.0, 20.0, 25.0, 12.5]);
+        assert_eq!(ewma.update(f64::NAN, start + tick * 4), 12.5);
+        assert_eq!(ewma.value(), Some(12.5));
+
+        /* A sample after a pause restarts the average */
+        assert_eq!(ewma.update(80.0, start + tick * 4 + EWMA_MAX_GAP * 2), 80.0);
+        assert_eq!(ewma.update(40.0, start + tick * 5 + EWMA_MAX_GAP * 2), 60.0);
+
+        let mut ewma = Ewma::new(1.0);
+        assert_eq!(ewma.update(10.0, start), 10.0);
+        assert_eq!(ewma.update(90.0, start + tick), 90.0);
+    }
 }
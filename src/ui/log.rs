@@ -0,0 +1,224 @@
+/*
+ * bb
+ *
+ * Copyright 2019 Manos Pitsidianakis
+ *
+ * This file is part of bb.
+ *
+ * bb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * bb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with bb. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+/*! Optional logging to a file, to diagnose problems in the field.
+
+Nothing is logged unless `BB_LOG_FILE` names a file, which records are appended to. `BB_LOG` is the
+most verbose level that gets written: `error`, `warn` (the default), `info` or `debug`. Each record
+is flushed as soon as it's written so that a crash doesn't lose the last ones, and panics are
+logged too.
+
+Log with the `log_error!`, `log_warn!`, `log_info!` and `log_debug!` macros, which take `format!`
+arguments.
+*/
+
+use crate::ui::components::timestamp;
+use std::fmt;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::sync::Mutex;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Error,
+    Warn,
+    Info,
+    Debug,
+}
+
+impl Level {
+    /// Parse a level name as accepted in `BB_LOG`.
+    pub fn from_name(name: &str) -> Option<Level> {
+        match name.to_ascii_lowercase().as_str() {
+            "error" => Some(Level::Error),
+            "warn" => Some(Level::Warn),
+            "info" => Some(Level::Info),
+            "debug" => Some(Level::Debug),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.pad(match self {
+            Level::Error => "ERROR",
+            Level::Warn => "WARN",
+            Level::Info => "INFO",
+            Level::Debug => "DEBUG",
+        })
+    }
+}
+
+/// Writes records of `level` and below to `sink`.
+pub struct Logger {
+    level: Level,
+    sink: Box<dyn Write + Send>,
+}
+
+impl fmt::Debug for Logger {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Logger")
+            .field("level", &self.level)
+            .finish()
+    }
+}
+
+impl Logger {
+    pub fn new(level: Level, sink: Box<dyn Write + Send>) -> Logger {
+        Logger { level, sink }
+    }
+
+    /// Write a record if `level` is enabled. Errors writing to the sink are ignored, there's
+    /// nowhere to report them.
+    pub fn log(&mut self, level: Level, args: fmt::Arguments) {
+        if level > self.level {
+            return;
+        }
+        let _ = writeln!(self.sink, "{} {:<5} {}", timestamp(), level, args);
+        let _ = self.sink.flush();
+    }
+}
+
+static LOGGER: Mutex<Option<Logger>> = Mutex::new(None);
+
+/// Replace the global logger, `None` turns logging off.
+pub fn set_logger(logger: Option<Logger>) {
+    *LOGGER.lock().unwrap_or_else(|err| err.into_inner()) = logger;
+}
+
+/// Log to the global logger, if any. Used by the `log_*!` macros.
+pub fn log(level: Level, args: fmt::Arguments) {
+    if let Some(ref mut logger) = *LOGGER.lock().unwrap_or_else(|err| err.into_inner()) {
+        logger.log(level, args);
+    }
+}
+
+/// Set up the global logger from `BB_LOG_FILE` and `BB_LOG`, and log panics.
+pub fn init() -> Result<(), String> {
+    let path = match std::env::var_os("BB_LOG_FILE") {
+        Some(path) => path,
+        None => return Ok(()),
+    };
+    let level = match std::env::var("BB_LOG") {
+        Ok(name) => Level::from_name(&name).ok_or_else(|| {
+            format!(
+                "invalid BB_LOG level `{}`, expected one of: error, warn, info, debug",
+                name
+            )
+        })?,
+        Err(_) => Level::Warn,
+    };
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .map_err(|err| {
+            format!(
+                "cannot open log file {}: {}",
+                std::path::Path::new(&path).display(),
+                err
+            )
+        })?;
+    set_logger(Some(Logger::new(level, Box::new(file))));
+
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        /* Don't wait for the lock, the panic might have happened while it was held */
+        if let Ok(mut logger) = LOGGER.try_lock() {
+            if let Some(ref mut logger) = *logger {
+                logger.log(Level::Error, format_args!("{}", info));
+            }
+        }
+        default_hook(info);
+    }));
+    Ok(())
+}
+
+#[macro_export]
+macro_rules! log_error {
+    ($($arg:tt)*) => {
+        $crate::ui::log::log($crate::ui::log::Level::Error, format_args!($($arg)*))
+    };
+}
+
+#[macro_export]
+macro_rules! log_warn {
+    ($($arg:tt)*) => {
+        $crate::ui::log::log($crate::ui::log::Level::Warn, format_args!($($arg)*))
+    };
+}
+
+#[macro_export]
+macro_rules! log_info {
+    ($($arg:tt)*) => {
+        $crate::ui::log::log($crate::ui::log::Level::Info, format_args!($($arg)*))
+    };
+}
+
+#[macro_export]
+macro_rules! log_debug {
+    ($($arg:tt)*) => {
+        $crate::ui::log::log($crate::ui::log::Level::Debug, format_args!($($arg)*))
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_log_to_file() {
+        let path = std::env::temp_dir().join(format!("bb-test-log-{}", std::process::id()));
+        let file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(&path)
+            .unwrap();
+        set_logger(Some(Logger::new(Level::Warn, Box::new(file))));
+        log_error!("cannot read {}: {}", "/proc/1/status", "permission denied");
+        log_warn!("warning");
+        log_debug!("not written");
+        /* flushed without dropping the logger */
+        let contents = std::fs::read_to_string(&path).unwrap();
+        set_logger(None);
+        log_error!("not written either");
+        std::fs::remove_file(&path).unwrap();
+
+        let lines = contents.lines().collect::<Vec<&str>>();
+        assert_eq!(lines.len(), 2, "{}", contents);
+        assert!(
+            lines[0].ends_with(" ERROR cannot read /proc/1/status: permission denied"),
+            "{}",
+            lines[0]
+        );
+        assert!(lines[1].ends_with(" WARN  warning"), "{}", lines[1]);
+    }
+
+    #[test]
+    fn test_level_from_name() {
+        assert_eq!(Level::from_name("debug"), Some(Level::Debug));
+        assert_eq!(Level::from_name("WARN"), Some(Level::Warn));
+        assert_eq!(Level::from_name("trace"), None);
+        assert!(Level::Error < Level::Debug);
+    }
+}
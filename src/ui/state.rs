@@ -32,12 +32,37 @@ use super::*;
 use crossbeam::channel::{Receiver, Sender};
 use std::collections::VecDeque;
 use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use termion::raw::IntoRawMode;
-use termion::screen::AlternateScreen;
 use termion::{clear, cursor, style};
 
-type StateStdout = termion::screen::AlternateScreen<termion::raw::RawTerminal<std::io::Stdout>>;
+/* Until `State::set_min_size` says otherwise: the layouts don't fit narrower terminals */
+const DEFAULT_MIN_SIZE: (usize, usize) = (80, 24);
+
+type StateStdout = termion::raw::RawTerminal<std::io::Stdout>;
+
+/// How bb runs when it's started without `--batch`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum StartMode {
+    /// Draw the UI on the terminal.
+    Interactive,
+    /// Print the process list like `--batch`, because there's no terminal to draw on.
+    Batch,
+}
+
+impl StartMode {
+    /// `Batch` if stdout isn't a terminal, eg. it's piped, or if its size can't be read or is zero,
+    /// since there would be no cells to draw the UI in. `size` is that of `termion::terminal_size`.
+    pub fn select(stdout_is_tty: bool, size: Option<(u16, u16)>) -> StartMode {
+        match size {
+            Some((cols, rows)) if stdout_is_tty && cols > 0 && rows > 0 => StartMode::Interactive,
+            _ => StartMode::Batch,
+        }
+    }
+}
 
 struct InputHandler {
     rx: Receiver<bool>,
@@ -53,8 +78,8 @@ impl InputHandler {
             .spawn(move || {
                 get_events(
                     stdin,
-                    |k| {
-                        tx.send(ThreadEvent::Input(k)).unwrap();
+                    |ev| {
+                        tx.send(ev).unwrap();
                     },
                     &rx,
                 )
@@ -72,26 +97,46 @@ pub struct State {
     cols: usize,
     rows: usize,
 
-    grid: CellBuffer,
+    screen: Screen,
     stdout: Option<StateStdout>,
+    /* the screen drawn in, which is switched to and away from with the raw mode */
+    screen_mode: ScreenMode,
     components: Vec<Box<dyn Component>>,
     pub dirty_areas: VecDeque<Area>,
     sender: Sender<ThreadEvent>,
     receiver: Receiver<ThreadEvent>,
     input: InputHandler,
+    /* whether mouse motion reporting was requested with `enable_mouse_motion` */
+    mouse_motion: bool,
+    /* whether focus reporting was requested with `enable_focus_tracking` */
+    focus_tracking: bool,
+    /* the components are drawn, and sample, but the frames aren't written, see `suppress_redraws` */
+    redraws_suppressed: bool,
+    /* columns and rows below which the components aren't drawn, see `draw_components` */
+    min_size: (usize, usize),
+    /* the named pipe frames are copied to, see `stream_frames_to` */
+    frame_stream: Option<FrameStream>,
 }
 
 impl Drop for State {
     fn drop(&mut self) {
         // When done, restore the defaults to avoid messing with the terminal.
+        if self.mouse_motion {
+            write!(self.stdout(), "{}", MouseMotionEnd).unwrap();
+        }
+        if self.focus_tracking {
+            write!(self.stdout(), "{}", FocusTrackingEnd).unwrap();
+        }
+        let leave = self.screen_mode.leave();
         write!(
             self.stdout(),
-            "{}{}{}{}{}",
+            "{}{}{}{}{}{}",
             clear::All,
             style::Reset,
             cursor::Goto(1, 1),
             cursor::Show,
             BracketModeEnd,
+            leave,
         )
         .unwrap();
         self.flush();
@@ -100,12 +145,12 @@ impl Drop for State {
 
 impl Default for State {
     fn default() -> Self {
-        Self::new()
+        Self::new(ScreenMode::default())
     }
 }
 
 impl State {
-    pub fn new() -> Self {
+    pub fn new(screen_mode: ScreenMode) -> Self {
         /* Create a channel to communicate with other threads. The main process is the sole receiver.
          * */
         let (sender, receiver) =
@@ -121,15 +166,14 @@ impl State {
         let cols = termsize.map(|(w, _)| w).unwrap_or(0) as usize;
         let rows = termsize.map(|(_, h)| h).unwrap_or(0) as usize;
 
-        let _stdout = std::io::stdout();
-        _stdout.lock();
-        let stdout = AlternateScreen::from(_stdout.into_raw_mode().unwrap());
+        let stdout = std::io::stdout().into_raw_mode().unwrap();
 
         let mut s = State {
             cols,
             rows,
-            grid: CellBuffer::new(cols, rows, Cell::with_char(' ')),
+            screen: Screen::new(cols, rows),
             stdout: Some(stdout),
+            screen_mode,
             components: Vec::with_capacity(1),
             sender,
             receiver,
@@ -138,11 +182,18 @@ impl State {
                 rx: input_receiver,
                 tx: input_sender,
             },
+            mouse_motion: false,
+            focus_tracking: false,
+            redraws_suppressed: false,
+            min_size: DEFAULT_MIN_SIZE,
+            frame_stream: None,
         };
 
+        let enter = s.screen_mode.enter();
         write!(
             s.stdout(),
-            "{}{}{}{}",
+            "{}{}{}{}{}",
+            enter,
             BracketModeStart,
             cursor::Hide,
             clear::All,
@@ -154,34 +205,92 @@ impl State {
         s
     }
 
+    /// Ask the terminal to report mouse clicks and motion as `UIEvent::Mouse` events.
+    pub fn enable_mouse_motion(&mut self) {
+        self.mouse_motion = true;
+        write!(self.stdout(), "{}", MouseMotionStart).unwrap();
+        self.flush();
+    }
+
+    /// Ask the terminal to report when it gains and loses focus as `ThreadEvent::Focus` events.
+    pub fn enable_focus_tracking(&mut self) {
+        self.focus_tracking = true;
+        write!(self.stdout(), "{}", FocusTrackingStart).unwrap();
+        self.flush();
+    }
+
+    /// Stop writing frames to the terminal, or start again and draw the whole screen. The
+    /// components are still drawn, so that they keep sampling.
+    pub fn suppress_redraws(&mut self, suppress: bool) {
+        let was_suppressed = std::mem::replace(&mut self.redraws_suppressed, suppress);
+        if was_suppressed && !suppress {
+            self.render();
+        }
+    }
+
+    /// Only draw the components on terminals of at least `cols` columns and `rows` rows, and a
+    /// message asking for a bigger one otherwise.
+    pub fn set_min_size(&mut self, cols: usize, rows: usize) {
+        self.min_size = (cols, rows);
+    }
+
+    /// Also write each frame, in full, to the named pipe at `path` for another program to read.
+    /// Frames are dropped while the reader is behind, it never holds up drawing.
+    pub fn stream_frames_to(&mut self, path: std::path::PathBuf) {
+        self.frame_stream = Some(FrameStream::new(path));
+    }
+
+    /// Set how characters without a width, such as control characters, are drawn.
+    pub fn set_non_printable(&mut self, non_printable: NonPrintable) {
+        self.screen.buffer_mut().set_non_printable(non_printable);
+    }
+
+    /// Draw right-to-left text in visual order.
+    pub fn set_bidi(&mut self, bidi: bool) {
+        self.screen.buffer_mut().set_bidi(bidi);
+    }
+
     /// Switch back to the terminal's main screen (The command line the user sees before opening
-    /// the application)
+    /// the application) and stop reading input, eg. to hand the terminal to a child process.
     pub fn switch_to_main_screen(&mut self) {
-        write!(
-            self.stdout(),
-            "{}{}",
-            termion::screen::ToMainScreen,
-            cursor::Show
-        )
-        .unwrap();
+        self.leave_alternate_screen();
+        self.input.kill();
+    }
+
+    /* Leave raw mode and the alternate screen, or clear the main screen if it's drawn in, but keep
+     * reading input */
+    fn leave_alternate_screen(&mut self) {
+        if self.mouse_motion {
+            write!(self.stdout(), "{}", MouseMotionEnd).unwrap();
+        }
+        if self.focus_tracking {
+            write!(self.stdout(), "{}", FocusTrackingEnd).unwrap();
+        }
+        let leave = self.screen_mode.leave();
+        write!(self.stdout(), "{}{}", leave, cursor::Show).unwrap();
         self.flush();
+        /* dropping the raw terminal restores the previous mode */
         self.stdout = None;
-        self.input.kill();
     }
     pub fn switch_to_alternate_screen(&mut self) {
-        let s = std::io::stdout();
-        s.lock();
-        self.stdout = Some(AlternateScreen::from(s.into_raw_mode().unwrap()));
+        self.stdout = Some(std::io::stdout().into_raw_mode().unwrap());
 
+        let enter = self.screen_mode.enter();
         write!(
             self.stdout(),
             "{}{}{}{}",
-            termion::screen::ToAlternateScreen,
+            enter,
             cursor::Hide,
             clear::All,
             cursor::Goto(1, 1)
         )
         .unwrap();
+        if self.mouse_motion {
+            write!(self.stdout(), "{}", MouseMotionStart).unwrap();
+        }
+        if self.focus_tracking {
+            write!(self.stdout(), "{}", FocusTrackingStart).unwrap();
+        }
         self.flush();
     }
 
@@ -204,7 +313,7 @@ impl State {
         }
         self.cols = termcols.unwrap_or(72) as usize;
         self.rows = termrows.unwrap_or(120) as usize;
-        self.grid.resize(self.cols, self.rows, Cell::with_char(' '));
+        self.screen.resize(self.cols, self.rows);
 
         self.rcv_event(UIEvent::Resize);
 
@@ -214,129 +323,85 @@ impl State {
 
     /// Force a redraw for all dirty components.
     pub fn redraw(&mut self, tick: bool) {
-        for i in 0..self.components.len() {
-            self.draw_component(i, tick);
-        }
-        let mut areas: Vec<Area> = self.dirty_areas.drain(0..).collect();
-        /* Sort by x_start, ie upper_left corner's x coordinate */
-        areas.sort_by(|a, b| (a.0).0.partial_cmp(&(b.0).0).unwrap());
-        /* draw each dirty area */
-        let rows = self.rows;
-        for y in 0..rows {
-            let mut segment = None;
-            for ((x_start, y_start), (x_end, y_end)) in &areas {
-                if y < *y_start || y > *y_end {
-                    continue;
-                }
-                if let Some((x_start, x_end)) = segment.take() {
-                    self.draw_horizontal_segment(x_start, x_end, y);
-                }
-                match segment {
-                    ref mut s @ None => {
-                        *s = Some((*x_start, *x_end));
-                    }
-                    ref mut s @ Some(_) if s.unwrap().1 < *x_start => {
-                        self.draw_horizontal_segment(s.unwrap().0, s.unwrap().1, y);
-                        *s = Some((*x_start, *x_end));
-                    }
-                    ref mut s @ Some(_) if s.unwrap().1 < *x_end => {
-                        self.draw_horizontal_segment(s.unwrap().0, s.unwrap().1, y);
-                        *s = Some((s.unwrap().1, *x_end));
-                    }
-                    Some((_, ref mut x)) => {
-                        *x = *x_end;
-                    }
-                }
-            }
-            if let Some((x_start, x_end)) = segment {
-                self.draw_horizontal_segment(x_start, x_end, y);
-            }
+        draw_components(
+            self.screen.buffer_mut(),
+            &mut self.components,
+            &mut self.dirty_areas,
+            self.min_size,
+            tick,
+        );
+        /* `Screen::flush` finds the cells that changed, the areas the components drew in aren't needed */
+        self.dirty_areas.clear();
+        if self.redraws_suppressed {
+            return;
         }
-        self.flush();
-    }
-
-    /// Draw only a specific `area` on the screen.
-    fn draw_horizontal_segment(&mut self, x_start: usize, x_end: usize, y: usize) {
-        write!(
-            self.stdout(),
-            "{}",
-            cursor::Goto(x_start as u16 + 1, (y + 1) as u16)
-        )
-        .unwrap();
-        for x in x_start..=x_end {
-            let c = self.grid[(x, y)];
-            if c.bg() != Color::Default {
-                write!(self.stdout(), "{}", termion::color::Bg(c.bg().as_termion())).unwrap();
-            }
-            if c.fg() != Color::Default {
-                write!(self.stdout(), "{}", termion::color::Fg(c.fg().as_termion())).unwrap();
-            }
-            if c.attrs() != Attr::Default {
-                write!(self.stdout(), "\x1B[{}m", c.attrs() as u8).unwrap();
-            }
-            if !c.empty() {
-                write!(self.stdout(), "{}", c.ch()).unwrap();
-            }
-
-            if c.bg() != Color::Default {
-                write!(
-                    self.stdout(),
-                    "{}",
-                    termion::color::Bg(termion::color::Reset)
-                )
-                .unwrap();
-            }
-            if c.fg() != Color::Default {
-                write!(
-                    self.stdout(),
-                    "{}",
-                    termion::color::Fg(termion::color::Reset)
-                )
-                .unwrap();
-            }
-            if c.attrs() != Attr::Default {
-                write!(self.stdout(), "\x1B[{}m", Attr::Default as u8).unwrap();
-            }
+        /* `Screen::flush` flushes stdout itself, right after writing the frame */
+        if let Some(stdout) = self.stdout.as_mut() {
+            self.screen.flush(stdout).unwrap();
+        }
+        if let Some(stream) = self.frame_stream.as_mut() {
+            stream.send(self.screen.full_frame().as_bytes());
         }
     }
 
     /// Draw the entire screen from scratch.
     pub fn render(&mut self) {
         self.update_size();
-        let cols = self.cols;
-        let rows = self.rows;
-        self.dirty_areas.push_back(((0, 0), (cols - 1, rows - 1)));
-
+        self.screen.invalidate();
         self.redraw(true);
     }
 
-    pub fn draw_component(&mut self, idx: usize, tick: bool) {
-        if self.cols < 80 || self.cols < 24 {
-            return;
-        }
-
-        let component = &mut self.components[idx];
-        let upper_left = (0, 0);
-        let bottom_right = (self.cols - 1, self.rows - 1);
-
-        if component.is_dirty() {
-            component.draw(
-                &mut self.grid,
-                (upper_left, bottom_right),
-                &mut self.dirty_areas,
-                tick,
-            );
-        }
-    }
     pub fn register_component(&mut self, component: Box<dyn Component>) {
         self.components.push(component);
     }
     /// The application's main loop sends `UIEvents` to state via this method.
+    /// Act on a `RefreshPolicy` decision.
+    pub fn refresh(&mut self, refresh: Refresh) {
+        match refresh {
+            Refresh::Resample => self.redraw(true),
+            Refresh::Redraw => self.redraw(false),
+            Refresh::Wait => {}
+        }
+    }
+
+    /// Whether any component is taking typed text, see `Component::takes_text_input`.
+    pub fn takes_text_input(&self) -> bool {
+        self.components.iter().any(|c| c.takes_text_input())
+    }
+
     pub fn rcv_event(&mut self, mut event: UIEvent) {
         /* inform each component */
         for i in 0..self.components.len() {
             self.components[i].process_event(&mut event);
         }
+        if let UIEvent::Exec(argv) = event {
+            let message = self.exec(&argv);
+            self.render();
+            self.rcv_event(UIEvent::StatusMessage(message));
+        }
+    }
+
+    /* Hand the terminal to `argv` until it exits, and return how it went for the status line. It's
+     * run directly instead of by a shell, so arguments are never interpreted. */
+    fn exec(&mut self, argv: &[String]) -> String {
+        let (program, args) = match argv.split_first() {
+            Some(split) => split,
+            None => return "empty command".to_string(),
+        };
+        self.switch_to_main_screen();
+        /* SIGINT and SIGQUIT from the terminal reach bb too: SIGQUIT is caught and ignored, and
+         * SIGINT, which requests a shutdown, is ignored until the command exits */
+        let status = status_ignoring_sigint(std::process::Command::new(program).args(args));
+        self.switch_to_alternate_screen();
+        self.restore_input();
+        match status {
+            Ok(status) if status.success() => format!("{} exited", program),
+            Ok(status) => match status.code() {
+                Some(code) => format!("{} exited with status {}", program, code),
+                None => format!("{} was killed by a signal", program),
+            },
+            Err(err) => format!("could not run {}: {}", program, err),
+        }
     }
 
     fn flush(&mut self) {
@@ -353,3 +418,652 @@ impl State {
         self.input.restore(self.sender.clone());
     }
 }
+
+/* Run `command` to completion with SIGINT ignored, so that Ctrl-C stopping it doesn't request a
+ * shutdown. A signal that's ignored when it's sent is discarded, instead of being left pending until
+ * the handler is back. The command itself gets the default action, which exec doesn't restore for
+ * ignored signals. */
+fn status_ignoring_sigint(
+    command: &mut std::process::Command,
+) -> std::io::Result<std::process::ExitStatus> {
+    use std::os::unix::process::CommandExt;
+    unsafe {
+        command.pre_exec(|| {
+            libc::signal(libc::SIGINT, libc::SIG_DFL);
+            Ok(())
+        });
+        let mut ignore: libc::sigaction = std::mem::zeroed();
+        ignore.sa_sigaction = libc::SIG_IGN;
+        let mut handler: libc::sigaction = std::mem::zeroed();
+        libc::sigaction(libc::SIGINT, &ignore, &mut handler);
+        let status = command.status();
+        libc::sigaction(libc::SIGINT, &handler, std::ptr::null_mut());
+        status
+    }
+}
+
+/// The steps of suspending bb as a shell job, in the order `suspend` takes them.
+pub trait JobControl {
+    /// Leave raw mode and the alternate screen.
+    fn teardown(&mut self);
+    /// Stop the process, until it's continued with SIGCONT.
+    fn stop(&mut self);
+    /// Undo `teardown` and redraw everything.
+    fn setup(&mut self);
+}
+
+/// Suspend on Ctrl-Z or SIGTSTP: give the terminal back to the shell, stop, and take it over
+/// again once continued with `fg`.
+pub fn suspend(term: &mut impl JobControl) {
+    term.teardown();
+    term.stop();
+    term.setup();
+}
+
+impl JobControl for State {
+    fn teardown(&mut self) {
+        /* The input thread is kept: it's stopped along with the rest of the process, and a new
+         * one would race the old one, which is blocked reading stdin, for the next key. */
+        self.leave_alternate_screen();
+    }
+
+    fn stop(&mut self) {
+        /* SIGTSTP is caught to get here, so stop with the signal that can't be */
+        let self_pid = nix::unistd::Pid::this();
+        nix::sys::signal::kill(self_pid, nix::sys::signal::Signal::SIGSTOP).unwrap();
+    }
+
+    fn setup(&mut self) {
+        self.switch_to_alternate_screen();
+        /* the terminal might have been resized in the meantime */
+        self.update_size();
+        self.render();
+        self.redraw(true);
+    }
+}
+
+/// What woke up the main loop.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Wake {
+    /// The sampling timer fired.
+    Tick,
+    /// A key press or mouse event was handled.
+    Input,
+    /// The deadline returned by `RefreshPolicy::deadline` passed.
+    Frame,
+}
+
+/// What the main loop should do after waking up.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Refresh {
+    /// Take new measurements and redraw.
+    Resample,
+    /// Redraw with the current measurements.
+    Redraw,
+    /// Nothing, wait for the next wake up.
+    Wait,
+}
+
+/// Decides when the main loop redraws and when it also resamples.
+///
+/// Ticks resample, unless input already did so during the last half interval. Input redraws right
+/// away, or waits for the next tick if `redraw_on_input` is off. Either way, input resamples when
+/// a whole interval has passed without a sample, so that key repeats can't hold back updates.
+///
+/// With a `frame`, input redraws at most once per frame: the redraws it asks for within a frame of
+/// the last one are coalesced into one at `deadline`. Resampling always redraws.
+#[derive(Debug)]
+pub struct RefreshPolicy {
+    interval: Duration,
+    redraw_on_input: bool,
+    frame: Option<Duration>,
+    last_sample: Instant,
+    last_redraw: Instant,
+    /* input asked for a redraw that was held back until the end of the frame */
+    pending: bool,
+}
+
+impl RefreshPolicy {
+    pub fn new(
+        interval: Duration,
+        redraw_on_input: bool,
+        frame: Option<Duration>,
+        now: Instant,
+    ) -> RefreshPolicy {
+        RefreshPolicy {
+            interval,
+            redraw_on_input,
+            frame,
+            last_sample: now,
+            last_redraw: now,
+            pending: false,
+        }
+    }
+
+    pub fn wake(&mut self, wake: Wake, now: Instant) -> Refresh {
+        let since_sample = now.saturating_duration_since(self.last_sample);
+        let in_frame = self
+            .frame
+            .map(|frame| now.saturating_duration_since(self.last_redraw) < frame)
+            .unwrap_or(false);
+        let ret = match wake {
+            Wake::Tick if since_sample < self.interval / 2 => Refresh::Redraw,
+            Wake::Tick => Refresh::Resample,
+            Wake::Input if since_sample >= self.interval => Refresh::Resample,
+            Wake::Input if self.redraw_on_input && in_frame => {
+                self.pending = true;
+                Refresh::Wait
+            }
+            Wake::Input if self.redraw_on_input => Refresh::Redraw,
+            Wake::Input => Refresh::Wait,
+            Wake::Frame if self.pending && !in_frame => Refresh::Redraw,
+            Wake::Frame => Refresh::Wait,
+        };
+        if ret == Refresh::Resample {
+            self.last_sample = now;
+        }
+        if ret != Refresh::Wait {
+            self.last_redraw = now;
+            self.pending = false;
+        }
+        ret
+    }
+
+    /// When to wake up with `Wake::Frame` for a held back redraw, if there is one.
+    pub fn deadline(&self) -> Option<Instant> {
+        match self.frame {
+            Some(frame) if self.pending => Some(self.last_redraw + frame),
+            _ => None,
+        }
+    }
+}
+
+/// Holds back redraws while the terminal doesn't have focus, with `--idle-unfocused`.
+///
+/// Focus-out suppresses redraws, see `State::suppress_redraws`: only one in `sample_every` ticks
+/// resamples and nothing else redraws. Focus-in lifts the suppression. Terminals report focus
+/// changes with focus tracking on, see `State::enable_focus_tracking`; the ones that don't are
+/// always focused. Without a `sample_every` focus changes are ignored.
+#[derive(Debug)]
+pub struct FocusPolicy {
+    sample_every: Option<usize>,
+    focused: bool,
+    /* ticks since the last one that resampled while unfocused */
+    skipped: usize,
+}
+
+impl FocusPolicy {
+    pub fn new(sample_every: Option<usize>) -> FocusPolicy {
+        FocusPolicy {
+            sample_every,
+            focused: true,
+            skipped: 0,
+        }
+    }
+
+    /// The terminal gained focus if `focused`, or lost it. Returns whether that changes
+    /// `suppressed`, as terminals repeat focus events eg. when their tabs are switched.
+    pub fn focus(&mut self, focused: bool) -> bool {
+        if self.sample_every.is_none() || self.focused == focused {
+            return false;
+        }
+        self.focused = focused;
+        self.skipped = 0;
+        true
+    }
+
+    /// Whether redraws are suppressed.
+    pub fn suppressed(&self) -> bool {
+        !self.focused
+    }
+
+    /// What to do of a `RefreshPolicy` decision.
+    pub fn refresh(&mut self, refresh: Refresh) -> Refresh {
+        if self.focused {
+            return refresh;
+        }
+        match refresh {
+            Refresh::Resample => {
+                self.skipped += 1;
+                if Some(self.skipped) >= self.sample_every {
+                    self.skipped = 0;
+                    Refresh::Resample
+                } else {
+                    Refresh::Wait
+                }
+            }
+            Refresh::Redraw | Refresh::Wait => Refresh::Wait,
+        }
+    }
+}
+
+/// Decides whether pressing the quit key quits.
+///
+/// Without a `timeout` every press quits. With one, the first press only arms the guard and a
+/// second press within `timeout` of it quits. Any other input disarms the guard.
+#[derive(Debug)]
+pub struct QuitGuard {
+    timeout: Option<Duration>,
+    armed: Option<Instant>,
+}
+
+impl QuitGuard {
+    pub fn new(timeout: Option<Duration>) -> QuitGuard {
+        QuitGuard {
+            timeout,
+            armed: None,
+        }
+    }
+
+    /// Whether a press of the quit key at `now` should quit.
+    pub fn press(&mut self, now: Instant) -> bool {
+        let timeout = match self.timeout {
+            None => return true,
+            Some(timeout) => timeout,
+        };
+        match self.armed.take() {
+            Some(armed) if now.saturating_duration_since(armed) <= timeout => true,
+            _ => {
+                self.armed = Some(now);
+                false
+            }
+        }
+    }
+
+    /// Forget an earlier press of the quit key.
+    pub fn reset(&mut self) {
+        self.armed = None;
+    }
+}
+
+/// Asks the main loop to quit, eg. on SIGINT.
+///
+/// The signal handler only sets the flag, and `run` checks it between iterations of the loop, never
+/// during one, so a frame that's being flushed is finished before the terminal is restored.
+#[derive(Debug, Clone, Default)]
+pub struct Shutdown(Arc<AtomicBool>);
+
+impl Shutdown {
+    pub fn new() -> Shutdown {
+        Shutdown::default()
+    }
+
+    /// Request a shutdown whenever `signal` arrives.
+    pub fn register(&self, signal: libc::c_int) -> std::io::Result<()> {
+        signal_hook::flag::register(signal, Arc::clone(&self.0)).map(|_| ())
+    }
+
+    pub fn request(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_requested(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+/// The main loop: call `iteration` on `state` until it returns `false` or a `shutdown` is
+/// requested, then drop `state`, which restores the terminal for a `State`.
+pub fn run<S>(mut state: S, shutdown: &Shutdown, mut iteration: impl FnMut(&mut S) -> bool) {
+    while !shutdown.is_requested() && iteration(&mut state) {}
+    drop(state);
+}
+
+/* Draw the dirty `components` over the whole of `grid`. A grid smaller than `min_size` gets a
+ * message asking for a bigger terminal instead, since the layouts of the components don't fit in it
+ * and would index cells outside of it. Returns whether the components were drawn. */
+fn draw_components(
+    grid: &mut CellBuffer,
+    components: &mut [Box<dyn Component>],
+    dirty_areas: &mut VecDeque<Area>,
+    (min_cols, min_rows): (usize, usize),
+    tick: bool,
+) -> bool {
+    let (cols, rows) = grid.size();
+    if cols == 0 || rows == 0 {
+        return false;
+    }
+    let area = ((0, 0), (cols - 1, rows - 1));
+    if cols < min_cols || rows < min_rows {
+        let msg = format!("terminal too small (need {}x{})", min_cols, min_rows);
+        clear_area(grid, area);
+        let x = cols.saturating_sub(msg.len()) / 2;
+        write_string_to_grid(
+            &msg,
+            grid,
+            Color::Default,
+            Color::Default,
+            Attr::Bold,
+            ((x, rows / 2), bottom_right!(area)),
+            false,
+        );
+        dirty_areas.push_back(area);
+        return false;
+    }
+    for component in components.iter_mut() {
+        if component.is_dirty() {
+            component.draw(grid, area, dirty_areas, tick);
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_refresh_policy() {
+        let start = Instant::now();
+        let interval = Duration::from_millis(1600);
+        let at = |ms| start + Duration::from_millis(ms);
+
+        let mut policy = RefreshPolicy::new(interval, true, None, start);
+        assert_eq!(policy.wake(Wake::Input, at(100)), Refresh::Redraw);
+        assert_eq!(policy.wake(Wake::Tick, at(1600)), Refresh::Resample);
+        assert_eq!(policy.wake(Wake::Input, at(1700)), Refresh::Redraw);
+        /* key repeats that keep the loop busy past a tick still resample */
+        for ms in (1800..3200).step_by(100) {
+            assert_eq!(policy.wake(Wake::Input, at(ms)), Refresh::Redraw, "{}", ms);
+        }
+        assert_eq!(policy.wake(Wake::Input, at(3200)), Refresh::Resample);
+        assert_eq!(policy.wake(Wake::Input, at(3250)), Refresh::Redraw);
+        /* the late tick doesn't resample again right after */
+        assert_eq!(policy.wake(Wake::Tick, at(3300)), Refresh::Redraw);
+        assert_eq!(policy.wake(Wake::Tick, at(4800)), Refresh::Resample);
+
+        let mut policy = RefreshPolicy::new(interval, false, None, start);
+        assert_eq!(policy.wake(Wake::Input, at(100)), Refresh::Wait);
+        assert_eq!(policy.wake(Wake::Tick, at(1600)), Refresh::Resample);
+        assert_eq!(policy.wake(Wake::Input, at(3300)), Refresh::Resample);
+        assert_eq!(policy.wake(Wake::Input, at(3400)), Refresh::Wait);
+        assert_eq!(policy.deadline(), None);
+    }
+
+    #[test]
+    fn test_refresh_policy_frame() {
+        let start = Instant::now();
+        let interval = Duration::from_millis(1600);
+        let frame = Duration::from_millis(33);
+        let at = |ms| start + Duration::from_millis(ms);
+
+        let mut policy = RefreshPolicy::new(interval, true, Some(frame), start);
+        assert_eq!(policy.wake(Wake::Input, at(100)), Refresh::Redraw);
+        assert_eq!(policy.deadline(), None);
+        /* a burst of key repeats within the frame redraws once, at its end */
+        for ms in 101..120 {
+            assert_eq!(policy.wake(Wake::Input, at(ms)), Refresh::Wait, "{}", ms);
+        }
+        assert_eq!(policy.deadline(), Some(at(133)));
+        assert_eq!(policy.wake(Wake::Frame, at(120)), Refresh::Wait);
+        assert_eq!(policy.wake(Wake::Frame, at(133)), Refresh::Redraw);
+        assert_eq!(policy.deadline(), None);
+        assert_eq!(policy.wake(Wake::Frame, at(200)), Refresh::Wait);
+        assert_eq!(policy.wake(Wake::Input, at(200)), Refresh::Redraw);
+
+        /* a tick redraws within the frame, and takes care of the held back redraw */
+        assert_eq!(policy.wake(Wake::Input, at(210)), Refresh::Wait);
+        assert_eq!(policy.wake(Wake::Tick, at(220)), Refresh::Redraw);
+        assert_eq!(policy.deadline(), None);
+        assert_eq!(policy.wake(Wake::Input, at(230)), Refresh::Wait);
+        assert_eq!(policy.wake(Wake::Tick, at(1600)), Refresh::Resample);
+        assert_eq!(policy.deadline(), None);
+
+        /* input that resamples isn't held back either */
+        assert_eq!(policy.wake(Wake::Input, at(3200)), Refresh::Resample);
+        assert_eq!(policy.wake(Wake::Input, at(3201)), Refresh::Wait);
+        assert_eq!(policy.wake(Wake::Input, at(4801)), Refresh::Resample);
+    }
+
+    #[test]
+    fn test_start_mode() {
+        assert_eq!(
+            StartMode::select(true, Some((80, 24))),
+            StartMode::Interactive
+        );
+        /* a pipe, even if the size of the controlling terminal can be read */
+        assert_eq!(StartMode::select(false, Some((80, 24))), StartMode::Batch);
+        assert_eq!(StartMode::select(false, None), StartMode::Batch);
+        /* a failed or empty size would make a screen without any cells */
+        assert_eq!(StartMode::select(true, None), StartMode::Batch);
+        assert_eq!(StartMode::select(true, Some((0, 0))), StartMode::Batch);
+        assert_eq!(StartMode::select(true, Some((80, 0))), StartMode::Batch);
+        assert_eq!(StartMode::select(true, Some((0, 24))), StartMode::Batch);
+    }
+
+    #[test]
+    fn test_min_size() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        #[derive(Debug)]
+        struct MockComponent {
+            draws: Arc<AtomicUsize>,
+        }
+        impl std::fmt::Display for MockComponent {
+            fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                write!(f, "mock")
+            }
+        }
+        impl Component for MockComponent {
+            fn draw(&mut self, grid: &mut CellBuffer, area: Area, _: &mut VecDeque<Area>, _: bool) {
+                self.draws.fetch_add(1, Ordering::SeqCst);
+                grid[bottom_right!(area)].set_ch('#');
+            }
+            fn process_event(&mut self, _: &mut UIEvent) {}
+            fn set_dirty(&mut self) {}
+        }
+        let draws = Arc::new(AtomicUsize::new(0));
+        let mut components: Vec<Box<dyn Component>> = vec![Box::new(MockComponent {
+            draws: draws.clone(),
+        })];
+        let mut dirty_areas = VecDeque::new();
+        let mut grid = CellBuffer::new(40, 5, Cell::with_char(' '));
+        assert!(!draw_components(
+            &mut grid,
+            &mut components,
+            &mut dirty_areas,
+            (80, 10),
+            true
+        ));
+        assert_eq!(draws.load(Ordering::SeqCst), 0);
+        let row = (0..40).map(|x| grid[(x, 2)].ch()).collect::<String>();
+        assert_eq!(row, "    terminal too small (need 80x10)     ");
+        assert_eq!(dirty_areas, [((0, 0), (39, 4))]);
+
+        /* too narrow for the whole message */
+        let mut grid = CellBuffer::new(10, 3, Cell::with_char(' '));
+        assert!(!draw_components(
+            &mut grid,
+            &mut components,
+            &mut dirty_areas,
+            (80, 10),
+            true
+        ));
+        assert_eq!(draws.load(Ordering::SeqCst), 0);
+
+        /* once the terminal is big enough the components are drawn again */
+        let mut grid = CellBuffer::new(80, 10, Cell::with_char(' '));
+        assert!(draw_components(
+            &mut grid,
+            &mut components,
+            &mut dirty_areas,
+            (80, 10),
+            true
+        ));
+        assert_eq!(draws.load(Ordering::SeqCst), 1);
+        assert_eq!(grid[(79, 9)].ch(), '#');
+    }
+
+    #[test]
+    fn test_suspend() {
+        #[derive(Default)]
+        struct MockTerminal {
+            calls: Vec<&'static str>,
+            raw: bool,
+        }
+        impl JobControl for MockTerminal {
+            fn teardown(&mut self) {
+                assert!(self.raw, "teardown of a terminal that isn't set up");
+                self.raw = false;
+                self.calls.push("teardown");
+            }
+            fn stop(&mut self) {
+                assert!(!self.raw, "stopped in raw mode");
+                self.calls.push("stop");
+            }
+            fn setup(&mut self) {
+                self.raw = true;
+                self.calls.push("setup");
+            }
+        }
+
+        let mut term = MockTerminal {
+            raw: true,
+            ..MockTerminal::default()
+        };
+        suspend(&mut term);
+        assert_eq!(term.calls, ["teardown", "stop", "setup"]);
+        assert!(term.raw);
+        /* and again after resuming */
+        suspend(&mut term);
+        assert_eq!(term.calls.len(), 6);
+        assert!(term.raw);
+    }
+
+    #[test]
+    fn test_shutdown() {
+        struct MockState {
+            log: std::rc::Rc<std::cell::RefCell<Vec<&'static str>>>,
+        }
+        impl Drop for MockState {
+            fn drop(&mut self) {
+                self.log.borrow_mut().push("teardown");
+            }
+        }
+        let log = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+        let shutdown = Shutdown::new();
+        let mut iterations = 0;
+        run(
+            MockState { log: log.clone() },
+            &shutdown,
+            |state: &mut MockState| {
+                iterations += 1;
+                if iterations == 3 {
+                    /* as if a signal arrived while drawing: the frame is still finished */
+                    shutdown.request();
+                }
+                state.log.borrow_mut().push("frame");
+                true
+            },
+        );
+        assert_eq!(*log.borrow(), ["frame", "frame", "frame", "teardown"]);
+
+        /* quitting from within the loop tears down too */
+        log.borrow_mut().clear();
+        run(MockState { log: log.clone() }, &Shutdown::new(), |_| false);
+        assert_eq!(*log.borrow(), ["teardown"]);
+
+        /* a shutdown requested before the loop starts skips it */
+        log.borrow_mut().clear();
+        run(MockState { log: log.clone() }, &shutdown, |_| {
+            panic!("iterated after a shutdown")
+        });
+        assert_eq!(*log.borrow(), ["teardown"]);
+
+        /* the signal handler only sets the flag */
+        let shutdown = Shutdown::new();
+        shutdown.register(libc::SIGUSR2).unwrap();
+        assert!(!shutdown.is_requested());
+        unsafe { libc::raise(libc::SIGUSR2) };
+        assert!(shutdown.is_requested());
+    }
+
+    #[test]
+    fn test_sigint_during_command() {
+        let shutdown = Shutdown::new();
+        shutdown.register(libc::SIGINT).unwrap();
+        /* as if Ctrl-C was pressed to stop a hook command, which the terminal sends to bb too */
+        let status = status_ignoring_sigint(
+            std::process::Command::new("sh").args(["-c", "kill -INT $PPID; kill -INT $$"]),
+        )
+        .unwrap();
+        assert_eq!(
+            std::os::unix::process::ExitStatusExt::signal(&status),
+            Some(libc::SIGINT)
+        );
+        std::thread::sleep(Duration::from_millis(50));
+        assert!(!shutdown.is_requested());
+
+        /* and one sent after it exited still is */
+        unsafe { libc::raise(libc::SIGINT) };
+        assert!(shutdown.is_requested());
+    }
+
+    #[test]
+    fn test_quit_guard() {
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+
+        /* off by default: every press quits */
+        let mut guard = QuitGuard::new(None);
+        assert!(guard.press(at(0)));
+        assert!(guard.press(at(5000)));
+
+        let mut guard = QuitGuard::new(Some(Duration::from_millis(2000)));
+        assert!(!guard.press(at(0)));
+        assert!(guard.press(at(1500)));
+        /* a press past the timeout arms the guard again */
+        assert!(!guard.press(at(10_000)));
+        assert!(!guard.press(at(12_001)));
+        assert!(guard.press(at(14_001)));
+        /* other input in between disarms it */
+        assert!(!guard.press(at(20_000)));
+        guard.reset();
+        assert!(!guard.press(at(20_100)));
+        assert!(guard.press(at(20_200)));
+    }
+
+    #[test]
+    fn test_focus_policy() {
+        use Refresh::*;
+        /* off, focus changes are ignored */
+        let mut focus = FocusPolicy::new(None);
+        assert!(!focus.focus(false));
+        assert_eq!(focus.refresh(Redraw), Redraw);
+
+        let mut focus = FocusPolicy::new(Some(3));
+        /* focused, decisions pass through */
+        assert!(!focus.suppressed());
+        assert_eq!(
+            (focus.refresh(Resample), focus.refresh(Redraw)),
+            (Resample, Redraw)
+        );
+        assert!(!focus.focus(true));
+
+        /* focus-out suppresses redraws and samples on every third tick */
+        assert!(focus.focus(false));
+        assert!(focus.suppressed());
+        assert_eq!(focus.refresh(Redraw), Wait);
+        let ticks = (0..6).map(|_| focus.refresh(Resample)).collect::<Vec<_>>();
+        assert_eq!(ticks, [Wait, Wait, Resample, Wait, Wait, Resample]);
+        /* a repeated focus-out changes nothing */
+        assert_eq!(focus.refresh(Resample), Wait);
+        assert!(!focus.focus(false));
+        assert_eq!(
+            (focus.refresh(Resample), focus.refresh(Resample)),
+            (Wait, Resample)
+        );
+
+        /* focus-in lifts the suppression, and the count starts over on the next focus-out */
+        assert_eq!(focus.refresh(Resample), Wait);
+        assert!(focus.focus(true));
+        assert!(!focus.suppressed());
+        assert_eq!(focus.refresh(Redraw), Redraw);
+        assert!(focus.focus(false));
+        assert_eq!(
+            (0..3).map(|_| focus.refresh(Resample)).collect::<Vec<_>>(),
+            [Wait, Wait, Resample]
+        );
+    }
+}
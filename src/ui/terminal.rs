@@ -24,6 +24,14 @@ mod position;
 mod cells;
 #[macro_use]
 mod keys;
+mod capabilities;
+mod screen;
+mod stream;
+mod terminfo;
+pub use self::capabilities::Capabilities;
 pub use self::cells::*;
 pub use self::keys::*;
 pub use self::position::*;
+pub use self::screen::*;
+pub use self::stream::{FrameSink, FrameStream};
+pub use self::terminfo::ScreenMode;
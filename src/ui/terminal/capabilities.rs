@@ -0,0 +1,211 @@
+/*
+ * bb
+ *
+ * Copyright 2019 Manos Pitsidianakis
+ *
+ * This file is part of bb.
+ *
+ * bb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * bb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with bb. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+/*! What bb detects about the terminal it draws on, printed by `--print-caps`.
+
+Everything here is read the way the renderer reads it: the screen mode with `ScreenMode::select`,
+the background with `Background::from_colorfgbg`, and the start mode from the size of the terminal.
+*/
+
+use super::terminfo::{has_mouse, max_colors, read_entry};
+use super::ScreenMode;
+use crate::ui::theme::Background;
+use std::fmt::Write;
+
+/* Colors of terminals that say they have 24-bit color in `COLORTERM` */
+const TRUECOLOR: u32 = 1 << 24;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Capabilities {
+    pub term: Option<String>,
+    pub colorterm: Option<String>,
+    pub stdout_is_tty: bool,
+    /// Columns and rows, if they can be read.
+    pub size: Option<(u16, u16)>,
+    /// `None` if neither `COLORTERM` nor the terminfo entry say.
+    pub colors: Option<u32>,
+    pub screen_mode: ScreenMode,
+    pub mouse: bool,
+    pub background: Option<Background>,
+}
+
+impl Capabilities {
+    /// The capabilities of the terminal of this process, with the `--no-altscreen` flag.
+    pub fn detect(no_altscreen: bool) -> Capabilities {
+        let entry = std::env::var("TERM")
+            .ok()
+            .and_then(|term| read_entry(&term));
+        Capabilities::new(
+            &|name| std::env::var(name).ok(),
+            entry.as_deref(),
+            termion::is_tty(&std::io::stdout()),
+            termion::terminal_size().ok(),
+            no_altscreen,
+        )
+    }
+
+    /// The capabilities of a terminal with the environment variables of `env` and the compiled
+    /// terminfo `entry` of its `TERM`.
+    pub fn new(
+        env: &dyn Fn(&str) -> Option<String>,
+        entry: Option<&[u8]>,
+        stdout_is_tty: bool,
+        size: Option<(u16, u16)>,
+        no_altscreen: bool,
+    ) -> Capabilities {
+        let colorterm = env("COLORTERM");
+        let colors = match colorterm.as_deref() {
+            Some("truecolor") | Some("24bit") => Some(TRUECOLOR),
+            _ => entry.and_then(max_colors),
+        };
+        Capabilities {
+            term: env("TERM"),
+            colorterm,
+            stdout_is_tty,
+            size,
+            colors,
+            screen_mode: ScreenMode::from_entry(no_altscreen, entry),
+            mouse: entry.is_some_and(has_mouse),
+            background: env("COLORFGBG")
+                .as_deref()
+                .and_then(Background::from_colorfgbg),
+        }
+    }
+
+    /// One `name: value` line per capability.
+    pub fn report(&self) -> String {
+        let unset = || "unset".to_string();
+        let unknown = || "unknown".to_string();
+        let mut ret = String::new();
+        let mut line = |name: &str, value: String| {
+            let _ = writeln!(ret, "{:<12}{}", format!("{}:", name), value);
+        };
+        line("TERM", self.term.clone().unwrap_or_else(unset));
+        line("COLORTERM", self.colorterm.clone().unwrap_or_else(unset));
+        line(
+            "tty",
+            if self.stdout_is_tty { "yes" } else { "no" }.to_string(),
+        );
+        line(
+            "size",
+            self.size
+                .map_or_else(unknown, |(cols, rows)| format!("{}x{}", cols, rows)),
+        );
+        line(
+            "colors",
+            self.colors
+                .map_or_else(unknown, |colors| colors.to_string()),
+        );
+        line(
+            "altscreen",
+            match self.screen_mode {
+                ScreenMode::Alternate => "yes",
+                ScreenMode::Main => "no",
+            }
+            .to_string(),
+        );
+        line("mouse", if self.mouse { "yes" } else { "no" }.to_string());
+        line(
+            "background",
+            match self.background {
+                Some(Background::Dark) => "dark".to_string(),
+                Some(Background::Light) => "light".to_string(),
+                None => unknown(),
+            },
+        );
+        ret
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::terminfo::tests::compiled_entry;
+    use super::*;
+
+    #[test]
+    fn test_report() {
+        let vars = [
+            ("TERM", "xterm-256color"),
+            ("COLORTERM", "truecolor"),
+            ("COLORFGBG", "0;15"),
+        ];
+        let env = |name: &str| {
+            vars.iter()
+                .find(|(var, _)| *var == name)
+                .map(|(_, value)| value.to_string())
+        };
+        /* smcup, kmous and colors at their indices in term(5) */
+        let mut numbers = [-1; 14];
+        numbers[13] = 256;
+        let entry = compiled_entry(
+            0o432,
+            &numbers,
+            356,
+            &[(28, "\x1b[?1049h"), (355, "\x1b[M")],
+        );
+        let caps = Capabilities::new(&env, Some(&entry), true, Some((80, 24)), false);
+        assert_eq!(
+            caps.report(),
+            "TERM:       xterm-256color
+COLORTERM:  truecolor
+tty:        yes
+size:       80x24
+colors:     16777216
+altscreen:  yes
+mouse:      yes
+background: light
+"
+        );
+
+        /* without COLORTERM the colors are the entry's */
+        let caps = Capabilities::new(
+            &|name| env(name).filter(|_| name == "TERM"),
+            Some(&entry),
+            true,
+            Some((80, 24)),
+            true,
+        );
+        let report = caps.report();
+        for line in &[
+            "COLORTERM:  unset\n",
+            "colors:     256\n",
+            "altscreen:  no\n",
+            "background: unknown\n",
+        ] {
+            assert!(report.contains(line), "{}", report);
+        }
+
+        /* piped, without TERM or a terminfo entry */
+        let caps = Capabilities::new(&|_| None, None, false, None, false);
+        assert_eq!(
+            caps.report(),
+            "TERM:       unset
+COLORTERM:  unset
+tty:        no
+size:       unknown
+colors:     unknown
+altscreen:  yes
+mouse:      no
+background: unknown
+"
+        );
+    }
+}
@@ -25,14 +25,15 @@
 */
 
 use super::position::*;
-use crate::ui::text_processing::wcwidth;
+use crate::ui::text_processing::{reorder_visual, wcwidth};
 
+use std::borrow::Cow;
 use std::convert::From;
 use std::fmt;
 use std::ops::{Deref, DerefMut, Index, IndexMut};
 use termion::color::AnsiValue;
 
-/// Types and implementations taken from rustty for convenience.
+/* Types and implementations taken from rustty for convenience. */
 
 pub trait CellAccessor: HasSize {
     fn cellvec(&self) -> &Vec<Cell>;
@@ -40,9 +41,7 @@ pub trait CellAccessor: HasSize {
 
     /// Clears `self`, using the given `Cell` as a blank.
     fn clear(&mut self, blank: Cell) {
-        for cell in self.cellvec_mut().iter_mut() {
-            *cell = blank;
-        }
+        self.cellvec_mut().fill(blank);
     }
 
     fn pos_to_index(&self, x: usize, y: usize) -> Option<usize> {
@@ -59,7 +58,7 @@ pub trait CellAccessor: HasSize {
     ///
     /// # Examples
     ///
-    /// ```norun
+    /// ```ignore
     /// use rustty::{Terminal, CellAccessor};
     ///
     /// let mut term = Terminal::new().unwrap();
@@ -78,7 +77,7 @@ pub trait CellAccessor: HasSize {
     ///
     /// # Examples
     ///
-    /// ```norun
+    /// ```ignore
     /// use rustty::{Terminal, CellAccessor};
     ///
     /// let mut term = Terminal::new().unwrap();
@@ -105,6 +104,60 @@ pub struct CellBuffer {
     cols: usize,
     rows: usize,
     buf: Vec<Cell>,
+    /* how `write_string_to_grid` draws characters without a width */
+    non_printable: NonPrintable,
+    /* whether `write_string_to_grid` puts right-to-left text in visual order */
+    bidi: bool,
+}
+
+/// How `write_string_to_grid` draws characters that `wcwidth` has no width for, such as control
+/// characters. Every policy leaves the following characters in the columns the terminal will draw
+/// them in.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum NonPrintable {
+    /// Draw a `·` in one column.
+    #[default]
+    Replace,
+    /// Draw the character's escape, eg. `\u{1b}`, one column per character of the escape.
+    Escape,
+    /// Leave the character out.
+    Skip,
+}
+
+impl NonPrintable {
+    /* `s` with the characters without a width drawn according to `self`. Tabs and carriage returns
+     * are left to `write_string_to_grid`. */
+    fn apply(self, s: &str) -> Cow<'_, str> {
+        self.apply_unless(s, |c| {
+            c == '\t' || c == '\r' || wcwidth(u32::from(c)).is_some()
+        })
+    }
+
+    /// `s` as one line of plain text, eg. a command line printed by `--batch`: the characters
+    /// without a width, line breaks, tabs and escapes among them, written according to `self`, so
+    /// that they can't move the cursor or restyle the terminal it's printed on.
+    pub fn apply_to_line(self, s: &str) -> Cow<'_, str> {
+        self.apply_unless(s, |c| wcwidth(u32::from(c)).is_some() && !c.is_control())
+    }
+
+    fn apply_unless(self, s: &str, printable: impl Fn(char) -> bool) -> Cow<'_, str> {
+        if s.chars().all(&printable) {
+            return Cow::Borrowed(s);
+        }
+        let mut ret = String::with_capacity(s.len());
+        for c in s.chars() {
+            if printable(c) {
+                ret.push(c);
+                continue;
+            }
+            match self {
+                NonPrintable::Replace => ret.push('·'),
+                NonPrintable::Escape => ret.extend(c.escape_default()),
+                NonPrintable::Skip => {}
+            }
+        }
+        Cow::Owned(ret)
+    }
 }
 
 impl fmt::Debug for CellBuffer {
@@ -127,27 +180,129 @@ impl CellBuffer {
             cols,
             rows,
             buf: vec![cell; cols * rows],
+            non_printable: NonPrintable::default(),
+            bidi: false,
+        }
+    }
+
+    /// Set how characters without a width are drawn, see `NonPrintable`.
+    pub fn set_non_printable(&mut self, non_printable: NonPrintable) {
+        self.non_printable = non_printable;
+    }
+
+    /// Reorder right-to-left text, such as Arabic and Hebrew, to the order it is read in before
+    /// drawing it, see `reorder_visual`.
+    pub fn set_bidi(&mut self, bidi: bool) {
+        self.bidi = bidi;
+    }
+
+    /// The characters `write_string_to_grid` draws for `s`, in the order it draws them.
+    pub fn displayed<'s>(&self, s: &'s str) -> Cow<'s, str> {
+        match self.non_printable.apply(s) {
+            Cow::Borrowed(s) if self.bidi => reorder_visual(s),
+            Cow::Owned(s) if self.bidi => Cow::Owned(reorder_visual(&s).into_owned()),
+            s => s,
         }
     }
 
     /// Resizes `CellBuffer` to the given number of rows and columns, using the given `Cell` as
     /// a blank.
     pub fn resize(&mut self, newcols: usize, newrows: usize, blank: Cell) {
-        let newlen = newcols * newrows;
-        if self.buf.len() == newlen {
+        if self.cols == newcols && self.rows == newrows {
             return;
         }
-        let mut newbuf: Vec<Cell> = Vec::with_capacity(newlen);
-        for y in 0..newrows {
-            for x in 0..newcols {
-                let cell = self.get(x, y).unwrap_or(&blank);
-                newbuf.push(*cell);
+        let mut newbuf: Vec<Cell> = Vec::with_capacity(newcols * newrows);
+        let copy_cols = std::cmp::min(self.cols, newcols);
+        if copy_cols > 0 {
+            for row in self.buf.chunks(self.cols).take(newrows) {
+                newbuf.extend_from_slice(&row[..copy_cols]);
+                newbuf.resize(newbuf.len() + newcols - copy_cols, blank);
             }
         }
+        newbuf.resize(newcols * newrows, blank);
         self.buf = newbuf;
         self.cols = newcols;
         self.rows = newrows;
     }
+
+    /// Returns the cells of row `y` mutably.
+    pub fn row_mut(&mut self, y: usize) -> &mut [Cell] {
+        &mut self.buf[y * self.cols..(y + 1) * self.cols]
+    }
+
+    /// Returns the areas of the cells showing `needle`, eg. to highlight them. Each row is searched
+    /// separately, matching is literal and case-sensitive and matches don't overlap. Empty cells,
+    /// such as the right half of a wide character, are skipped when reading a row.
+    pub fn find(&self, needle: &str) -> Vec<Area> {
+        self.find_with(needle, false)
+    }
+
+    /// Like `find`, but ignores case.
+    pub fn find_ignore_case(&self, needle: &str) -> Vec<Area> {
+        self.find_with(needle, true)
+    }
+
+    fn find_with(&self, needle: &str, ignore_case: bool) -> Vec<Area> {
+        let mut ret = vec![];
+        let needle: Vec<char> = needle.chars().collect();
+        if needle.is_empty() || self.cols == 0 {
+            return ret;
+        }
+        let eq =
+            |a: char, b: char| a == b || (ignore_case && a.to_lowercase().eq(b.to_lowercase()));
+        /* The visible characters of a row, with their x coordinate */
+        let mut line: Vec<(char, usize)> = Vec::with_capacity(self.cols);
+        for (y, row) in self.buf.chunks(self.cols).enumerate() {
+            line.clear();
+            line.extend(
+                row.iter()
+                    .enumerate()
+                    .filter(|(_, c)| !c.empty())
+                    .map(|(x, c)| (c.ch(), x)),
+            );
+            let mut i = 0;
+            while i + needle.len() <= line.len() {
+                let end = i + needle.len();
+                if line[i..end]
+                    .iter()
+                    .zip(&needle)
+                    .all(|(&(c, _), &n)| eq(c, n))
+                {
+                    /* Extend the match up to the next visible character, to cover the right half
+                     * of a wide last character */
+                    let x_end = line.get(end).map(|&(_, x)| x - 1).unwrap_or(self.cols - 1);
+                    ret.push(((line[i].1, y), (x_end, y)));
+                    i = end;
+                } else {
+                    i += 1;
+                }
+            }
+        }
+        ret
+    }
+
+    /// Copies the cells of `top` onto `self` with the upper left corner of `top` at `at`, except
+    /// for those `transparent` returns true for, which leave the cells below them as they are. This
+    /// allows overlays that aren't rectangular, such as a box with a drop shadow. Cells that fall
+    /// outside of `self` are clipped.
+    pub fn overlay(&mut self, top: &CellBuffer, at: Pos, transparent: impl Fn(&Cell) -> bool) {
+        let (x_start, y_start) = at;
+        if x_start >= self.cols || top.cols == 0 {
+            return;
+        }
+        let cols = std::cmp::min(top.cols, self.cols - x_start);
+        for (y, row) in top.buf.chunks(top.cols).enumerate() {
+            if y_start + y >= self.rows {
+                break;
+            }
+            let dest = &mut self.row_mut(y_start + y)[x_start..x_start + cols];
+            for (dest, cell) in dest.iter_mut().zip(&row[..cols]) {
+                if !transparent(cell) {
+                    *dest = *cell;
+                }
+            }
+        }
+    }
 }
 
 impl HasSize for CellBuffer {
@@ -223,14 +378,19 @@ impl<'a> From<&'a str> for CellBuffer {
 
 impl fmt::Display for CellBuffer {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        '_y: for y in 0..self.rows {
-            for x in 0..self.cols {
-                let c: &char = &self[(x, y)].ch();
-                write!(f, "{}", *c).unwrap();
-                if *c == '\n' {
-                    continue '_y;
+        if self.cols == 0 {
+            return Ok(());
+        }
+        let mut line = String::with_capacity(self.cols);
+        for row in self.buf.chunks(self.cols) {
+            line.clear();
+            for c in row {
+                line.push(c.ch);
+                if c.ch == '\n' {
+                    break;
                 }
             }
+            f.write_str(&line)?;
         }
         Ok(())
     }
@@ -254,7 +414,7 @@ impl Cell {
     ///
     /// # Examples
     ///
-    /// ```norun
+    /// ```ignore
     /// use rustty::{Cell, Color, Attr};
     ///
     /// let cell = Cell::new('x', Color::Default, Color::Green, Attr::Default);
@@ -277,7 +437,7 @@ impl Cell {
     ///
     /// # Examples
     ///
-    /// ```norun
+    /// ```ignore
     /// use rustty::{Cell, Color, Attr};
     ///
     /// let mut cell = Cell::with_char('x');
@@ -294,7 +454,7 @@ impl Cell {
     ///
     /// # Examples
     ///
-    /// ```norun
+    /// ```ignore
     /// use rustty::Cell;
     ///
     /// let mut cell = Cell::with_char('x');
@@ -308,7 +468,7 @@ impl Cell {
     ///
     /// # Examples
     ///
-    /// ```norun
+    /// ```ignore
     /// use rustty::Cell;
     ///
     /// let mut cell = Cell::with_char('x');
@@ -326,7 +486,7 @@ impl Cell {
     ///
     /// # Examples
     ///
-    /// ```norun
+    /// ```ignore
     /// use rustty::{Cell, Color, Attr};
     ///
     /// let mut cell = Cell::with_style(Color::Blue, Color::Default, Attr::Default);
@@ -340,7 +500,7 @@ impl Cell {
     ///
     /// # Examples
     ///
-    /// ```norun
+    /// ```ignore
     /// use rustty::{Cell, Color, Attr};
     ///
     /// let mut cell = Cell::default();
@@ -358,7 +518,7 @@ impl Cell {
     ///
     /// # Examples
     ///
-    /// ```norun
+    /// ```ignore
     /// use rustty::{Cell, Color, Attr};
     ///
     /// let mut cell = Cell::with_style(Color::Default, Color::Green, Attr::Default);
@@ -372,7 +532,7 @@ impl Cell {
     ///
     /// # Examples
     ///
-    /// ```norun
+    /// ```ignore
     /// use rustty::{Cell, Color, Attr};
     ///
     /// let mut cell = Cell::default();
@@ -405,7 +565,7 @@ impl Default for Cell {
     ///
     /// # Examples
     ///
-    /// ```norun
+    /// ```ignore
     /// use rustty::{Cell, Color};
     ///
     /// let mut cell = Cell::default();
@@ -430,7 +590,7 @@ impl Default for Cell {
 ///
 /// # Examples
 ///
-/// ```norun
+/// ```ignore
 /// use rustty::Color;
 ///
 /// // The default color.
@@ -488,11 +648,159 @@ impl Color {
             | b @ Color::Cyan
             | b @ Color::White
             | b @ Color::Default => AnsiValue(b.as_byte()),
-            Color::Byte(b) => AnsiValue(b as u8),
+            Color::Byte(b) => AnsiValue(b),
+        }
+    }
+
+    /// The color a fraction `t` in [0, 1] of the way from `self` to `other`, as the nearest color
+    /// of the 8-bit range.
+    ///
+    /// `Color::Default` can't be blended since what it looks like isn't known, so a blend with it
+    /// is whichever of the two colors is closer.
+    pub fn blend(self, other: Color, t: f64) -> Color {
+        let t = t.clamp(0.0, 1.0);
+        match (self.rgb(), other.rgb()) {
+            (Some(from), Some(to)) => {
+                let mix = |from: u8, to: u8| {
+                    (f64::from(from) + (f64::from(to) - f64::from(from)) * t).round() as u8
+                };
+                Color::nearest((mix(from.0, to.0), mix(from.1, to.1), mix(from.2, to.2)))
+            }
+            _ if t < 0.5 => self,
+            _ => other,
+        }
+    }
+
+    /// Black or white, whichever is easier to read on `self` as a background.
+    ///
+    /// What `Color::Default` looks like isn't known, so text on it keeps the default color too.
+    pub fn contrasting(self) -> Color {
+        match self.rgb() {
+            /* perceived brightness, from the weights of ITU-R BT.601 */
+            Some((r, g, b))
+                if 299 * u32::from(r) + 587 * u32::from(g) + 114 * u32::from(b) > 128 * 1000 =>
+            {
+                Color::Black
+            }
+            Some(_) => Color::White,
+            None => Color::Default,
+        }
+    }
+
+    /// The name of the color in theme files: `default`, `red` and the other basic colors,
+    /// `color0` to `color15` for the 16 first colors of the 8-bit range, which terminals let
+    /// users redefine, and `#rrggbb` for the rest, as xterm draws them. `Color::parse` reads it
+    /// back to the same color.
+    pub fn name(self) -> String {
+        match self {
+            Color::Black => "black".to_string(),
+            Color::Red => "red".to_string(),
+            Color::Green => "green".to_string(),
+            Color::Yellow => "yellow".to_string(),
+            Color::Blue => "blue".to_string(),
+            Color::Magenta => "magenta".to_string(),
+            Color::Cyan => "cyan".to_string(),
+            Color::White => "white".to_string(),
+            Color::Default => "default".to_string(),
+            Color::Byte(b @ 0..=15) => format!("color{}", b),
+            c => {
+                let (r, g, b) = c.rgb().unwrap();
+                format!("#{:02x}{:02x}{:02x}", r, g, b)
+            }
+        }
+    }
+
+    /// Parses a color `name`. `colorN` is any color of the 8-bit range, and `#rrggbb` the
+    /// nearest one of the 6x6x6 cube or the greyscale ramp.
+    pub fn parse(name: &str) -> Option<Color> {
+        Some(match name {
+            "black" => Color::Black,
+            "red" => Color::Red,
+            "green" => Color::Green,
+            "yellow" => Color::Yellow,
+            "blue" => Color::Blue,
+            "magenta" => Color::Magenta,
+            "cyan" => Color::Cyan,
+            "white" => Color::White,
+            "default" => Color::Default,
+            _ => {
+                if let Some(index) = name.strip_prefix("color") {
+                    return index.parse().ok().map(Color::Byte);
+                }
+                let hex = name
+                    .strip_prefix('#')
+                    .filter(|hex| hex.len() == 6 && hex.bytes().all(|b| b.is_ascii_hexdigit()))?;
+                let component = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).unwrap();
+                Color::nearest((component(0), component(2), component(4)))
+            }
+        })
+    }
+
+    /* The red, green and blue components of the color in xterm's palette */
+    fn rgb(self) -> Option<(u8, u8, u8)> {
+        const BASIC: [(u8, u8, u8); 16] = [
+            (0, 0, 0),
+            (205, 0, 0),
+            (0, 205, 0),
+            (205, 205, 0),
+            (0, 0, 238),
+            (205, 0, 205),
+            (0, 205, 205),
+            (229, 229, 229),
+            (127, 127, 127),
+            (255, 0, 0),
+            (0, 255, 0),
+            (255, 255, 0),
+            (92, 92, 255),
+            (255, 0, 255),
+            (0, 255, 255),
+            (255, 255, 255),
+        ];
+        match self {
+            Color::Default => None,
+            c => Some(match c.as_byte() {
+                b @ 0..=15 => BASIC[b as usize],
+                b @ 16..=231 => {
+                    let b = b - 16;
+                    (
+                        CUBE[(b / 36) as usize],
+                        CUBE[(b / 6 % 6) as usize],
+                        CUBE[(b % 6) as usize],
+                    )
+                }
+                b => {
+                    let grey = 8 + 10 * (b - 232);
+                    (grey, grey, grey)
+                }
+            }),
+        }
+    }
+
+    /* The color of the 6x6x6 cube or the greyscale ramp closest to `rgb` */
+    fn nearest((r, g, b): (u8, u8, u8)) -> Color {
+        let distance = |(r2, g2, b2): (u8, u8, u8)| {
+            let d = |a: u8, b: u8| (i32::from(a) - i32::from(b)).pow(2);
+            d(r, r2) + d(g, g2) + d(b, b2)
+        };
+        let level = |v: u8| {
+            (0..CUBE.len())
+                .min_by_key(|&i| (i32::from(CUBE[i]) - i32::from(v)).abs())
+                .unwrap() as u8
+        };
+        let cube = 16 + 36 * level(r) + 6 * level(g) + level(b);
+        let mean = (u16::from(r) + u16::from(g) + u16::from(b)) / 3;
+        let grey = 232 + (mean.saturating_sub(3) / 10).min(23) as u8;
+        if distance(Color::Byte(grey).rgb().unwrap()) < distance(Color::Byte(cube).rgb().unwrap()) {
+            Color::Byte(grey)
+        } else {
+            Color::Byte(cube)
         }
     }
 }
 
+/* Levels of each component in the 6x6x6 color cube of the 8-bit range */
+const CUBE: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
 /// The attributes of a `Cell`.
 ///
 /// `Attr` enumerates all combinations of attributes a given style may have.
@@ -501,7 +809,7 @@ impl Color {
 ///
 /// # Examples
 ///
-/// ```norun
+/// ```ignore
 /// use rustty::Attr;
 ///
 /// // Default attribute.
@@ -524,6 +832,24 @@ pub enum Attr {
     BoldReverse = 0b101,
     UnderlineReverse = 0b110,
     BoldReverseUnderline = 0b111,
+    Dim = 0b1000,
+}
+
+impl Attr {
+    /// Returns the SGR parameters that turn on `self`.
+    pub fn as_sgr(self) -> &'static str {
+        match self {
+            Attr::Default => "0",
+            Attr::Bold => "1",
+            Attr::Underline => "4",
+            Attr::BoldUnderline => "1;4",
+            Attr::Reverse => "7",
+            Attr::BoldReverse => "1;7",
+            Attr::UnderlineReverse => "4;7",
+            Attr::BoldReverseUnderline => "1;4;7",
+            Attr::Dim => "2",
+        }
+    }
 }
 
 /// Change foreground and background colors in an `Area`
@@ -576,6 +902,25 @@ macro_rules! inspect_bounds {
     };
 }
 
+/// The colors and attributes text is written with.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Style {
+    pub fg: Color,
+    pub bg: Color,
+    pub attrs: Attr,
+}
+
+impl Default for Style {
+    /// The terminal's default colors, without attributes.
+    fn default() -> Style {
+        Style {
+            fg: Color::Default,
+            bg: Color::Default,
+            attrs: Attr::Default,
+        }
+    }
+}
+
 /// Write an `&str` to a `CellBuffer` in a specified `Area` with the passed colors.
 pub fn write_string_to_grid(
     s: &str,
@@ -585,6 +930,28 @@ pub fn write_string_to_grid(
     attrs: Attr,
     area: Area,
     line_break: bool,
+) -> Pos {
+    write_styled(
+        s,
+        grid,
+        Style {
+            fg: fg_color,
+            bg: bg_color,
+            attrs,
+        },
+        area,
+        line_break,
+    )
+}
+
+/// Write an `&str` to a `CellBuffer` in a specified `Area` with `style`, like
+/// `write_string_to_grid`. Returns the position after the last character written.
+pub fn write_styled(
+    s: &str,
+    grid: &mut CellBuffer,
+    style: Style,
+    area: Area,
+    line_break: bool,
 ) -> Pos {
     let bounds = grid.size();
     let upper_left = upper_left!(area);
@@ -601,33 +968,51 @@ pub fn write_string_to_grid(
     {
         return (x, y);
     }
+    let s = grid.displayed(s);
+    /* `area` can reach past the grid, eg. when it was computed before a resize, so writes to cells
+     * outside of it are dropped instead of indexing out of bounds */
     for c in s.chars() {
         if c == '\r' {
             continue;
         }
-        grid[(x, y)].set_attrs(attrs);
-        grid[(x, y)].set_fg(fg_color);
-        grid[(x, y)].set_bg(bg_color);
+        /* A wide character in the last column of the area would cover the cell after it, eg. the
+         * separator before the next column, so a space takes its place */
+        let c = if wcwidth(u32::from(c)) == Some(2) && x >= get_x(bottom_right) {
+            ' '
+        } else {
+            c
+        };
+        if let Some(cell) = grid.get_mut(x, y) {
+            cell.set_attrs(style.attrs);
+            cell.set_fg(style.fg);
+            cell.set_bg(style.bg);
+            cell.set_ch(if c == '\t' { ' ' } else { c });
+            /* the cell may have been covered by a wide character before */
+            cell.empty = false;
+        }
         if c == '\t' {
-            grid[(x, y)].set_ch(' ');
             x += 1;
             inspect_bounds!(grid, area, x, y, line_break);
-            grid[(x, y)].set_ch(' ');
-        } else {
-            grid[(x, y)].set_ch(c);
+            if let Some(cell) = grid.get_mut(x, y) {
+                cell.set_ch(' ');
+            }
         }
 
         match wcwidth(u32::from(c)) {
             Some(0) | None => {
                 /* Skip drawing zero width characters */
-                grid[(x, y)].empty = true;
+                if let Some(cell) = grid.get_mut(x, y) {
+                    cell.empty = true;
+                }
             }
             Some(2) => {
                 /* Grapheme takes more than one column, so the next cell will be
                  * drawn over. Set it as empty to skip drawing it. */
                 x += 1;
                 inspect_bounds!(grid, area, x, y, line_break);
-                grid[(x, y)].empty = true;
+                if let Some(cell) = grid.get_mut(x, y) {
+                    cell.empty = true;
+                }
             }
             _ => {}
         }
@@ -645,13 +1030,400 @@ pub fn clear_area(grid: &mut CellBuffer, area: Area) {
     }
     let upper_left = upper_left!(area);
     let bottom_right = bottom_right!(area);
+    let blank = Cell::with_char(' ');
     for y in get_y(upper_left)..=get_y(bottom_right) {
-        for x in get_x(upper_left)..=get_x(bottom_right) {
-            grid[(x, y)].set_ch(' ');
-            grid[(x, y)].set_bg(Color::Default);
-            grid[(x, y)].set_fg(Color::Default);
-            grid[(x, y)].set_attrs(Attr::Default);
-            grid[(x, y)].empty = false;
+        grid.row_mut(y)[get_x(upper_left)..=get_x(bottom_right)].fill(blank);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resize() {
+        let mut grid = CellBuffer::new(3, 2, Cell::with_char(' '));
+        for (i, c) in "abcdef".chars().enumerate() {
+            grid[(i % 3, i / 3)].set_ch(c);
+        }
+        let contents = |grid: &CellBuffer| grid.iter().map(Cell::ch).collect::<String>();
+
+        grid.resize(4, 3, Cell::with_char('.'));
+        assert_eq!(grid.size(), (4, 3));
+        assert_eq!(contents(&grid), "abc.def.....");
+        grid.resize(2, 1, Cell::with_char('.'));
+        assert_eq!(contents(&grid), "ab");
+        /* Same length, different shape */
+        let mut grid = CellBuffer::new(3, 2, Cell::with_char('x'));
+        grid.resize(2, 3, Cell::with_char('.'));
+        assert_eq!(grid.size(), (2, 3));
+        assert_eq!(contents(&grid), "xxxx..");
+        grid.resize(0, 0, Cell::with_char('.'));
+        assert_eq!(contents(&grid), "");
+        grid.resize(2, 1, Cell::with_char('.'));
+        assert_eq!(contents(&grid), "..");
+    }
+
+    #[test]
+    fn test_wide_characters() {
+        let mut grid = CellBuffer::new(8, 1, Cell::with_char(' '));
+        let contents = |grid: &CellBuffer| {
+            grid.iter()
+                .filter(|c| !c.empty())
+                .map(Cell::ch)
+                .collect::<String>()
+        };
+        let write = |grid: &mut CellBuffer, s: &str, area: Area| {
+            write_string_to_grid(
+                s,
+                grid,
+                Color::Default,
+                Color::Default,
+                Attr::Default,
+                area,
+                false,
+            )
+        };
+        /* a wide command line that ends at the edge of its column keeps the separator after it */
+        write(&mut grid, "|", ((4, 0), (4, 0)));
+        assert_eq!(write(&mut grid, "ab漢", ((0, 0), (3, 0))), (0, 0));
+        assert_eq!(contents(&grid), "ab漢|   ");
+        assert_eq!(write(&mut grid, "abc漢字", ((0, 0), (3, 0))), (0, 0));
+        assert_eq!(contents(&grid), "abc |   ");
+        assert!(!grid[(4, 0)].empty());
+
+        /* cells that were covered by a wide character are drawn again once written over */
+        grid.clear(Cell::with_char(' '));
+        write(&mut grid, "漢字", ((0, 0), (7, 0)));
+        assert!(grid[(1, 0)].empty());
+        write(&mut grid, "a|b|", ((0, 0), (7, 0)));
+        assert_eq!(contents(&grid), "a|b|    ");
+    }
+
+    #[test]
+    fn test_non_printable() {
+        let mut grid = CellBuffer::new(16, 1, Cell::with_char(' '));
+        let contents = |grid: &CellBuffer| {
+            grid.iter()
+                .filter(|c| !c.empty())
+                .map(Cell::ch)
+                .collect::<String>()
+        };
+        for (policy, expected, end) in &[
+            (NonPrintable::Replace, "a·b·c           ", 5),
+            (NonPrintable::Escape, "a\\u{1b}b\\u{7}c  ", 14),
+            (NonPrintable::Skip, "abc             ", 3),
+        ] {
+            grid.clear(Cell::with_char(' '));
+            grid.set_non_printable(*policy);
+            let pos = write_string_to_grid(
+                "a\u{1b}b\u{7}c",
+                &mut grid,
+                Color::Default,
+                Color::Default,
+                Attr::Default,
+                ((0, 0), (15, 0)),
+                false,
+            );
+            /* every cell is drawn, so the terminal's cursor advances like `x` */
+            assert_eq!(contents(&grid), *expected, "{:?}", policy);
+            assert_eq!(pos, (*end, 0), "{:?}", policy);
+        }
+    }
+
+    #[test]
+    fn test_non_printable_line() {
+        let line = "sh -c 'a\u{1b}[2Jb'\n\tc\rd α";
+        assert_eq!(
+            NonPrintable::Replace.apply_to_line(line),
+            "sh -c 'a·[2Jb'··c·d α"
+        );
+        assert_eq!(
+            NonPrintable::Escape.apply_to_line(line),
+            "sh -c 'a\\u{1b}[2Jb'\\n\\tc\\rd α"
+        );
+        assert_eq!(NonPrintable::Skip.apply_to_line(line), "sh -c 'a[2Jb'cd α");
+        assert!(matches!(
+            NonPrintable::Replace.apply_to_line("/usr/bin/bb"),
+            Cow::Borrowed(_)
+        ));
+    }
+
+    #[test]
+    fn test_blend() {
+        let green = Color::Byte(46);
+        let grey = Color::Byte(252);
+        assert_eq!(green.blend(grey, 0.0), green);
+        assert_eq!(green.blend(grey, 1.0), grey);
+        assert_eq!(green.blend(grey, 0.5), Color::Byte(77));
+        assert_eq!(green.blend(grey, 2.0), grey);
+        /* basic colors blend in xterm's palette */
+        assert_eq!(Color::Black.blend(Color::White, 1.0), Color::Byte(254));
+        assert_eq!(Color::Black.blend(Color::Black, 0.3), Color::Byte(16));
+        assert_eq!(
+            Color::Byte(232).blend(Color::Byte(255), 0.5),
+            Color::Byte(244)
+        );
+        /* the default color can only be switched to */
+        assert_eq!(green.blend(Color::Default, 0.4), green);
+        assert_eq!(green.blend(Color::Default, 0.6), Color::Default);
+        assert_eq!(Color::Default.blend(green, 0.5), green);
+    }
+
+    #[test]
+    fn test_contrasting() {
+        /* light backgrounds get dark text and dark ones light text */
+        for &light in &[
+            Color::White,
+            Color::Yellow,
+            Color::Byte(252),
+            Color::Byte(229),
+        ] {
+            assert_eq!(light.contrasting(), Color::Black, "{:?}", light);
+        }
+        for &dark in &[Color::Black, Color::Blue, Color::Byte(235), Color::Byte(18)] {
+            assert_eq!(dark.contrasting(), Color::White, "{:?}", dark);
+        }
+        /* the greyscale ramp flips past its middle, grey 50% */
+        assert_eq!(Color::Byte(244).contrasting(), Color::White);
+        assert_eq!(Color::Byte(245).contrasting(), Color::Black);
+        assert_eq!(Color::Default.contrasting(), Color::Default);
+    }
+
+    #[test]
+    fn test_color_names() {
+        for c in [Color::Default, Color::Black, Color::White]
+            .iter()
+            .copied()
+            .chain((0..=255).map(Color::Byte))
+        {
+            assert_eq!(Color::parse(&c.name()), Some(c), "{}", c.name());
+        }
+        assert_eq!(Color::Byte(9).name(), "color9");
+        assert_eq!(Color::Byte(70).name(), "#5faf00");
+        assert_eq!(Color::Byte(233).name(), "#121212");
+        /* colors off the palette are its nearest one */
+        assert_eq!(Color::parse("#5fb001"), Some(Color::Byte(70)));
+        assert_eq!(Color::parse("#FFFFFF"), Some(Color::Byte(231)));
+        for name in &["", "grey", "color256", "#12345", "#12345g", "#+12345"] {
+            assert_eq!(Color::parse(name), None, "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_bidi() {
+        let mut grid = CellBuffer::new(12, 1, Cell::with_char(' '));
+        let contents = |grid: &CellBuffer| grid.iter().map(Cell::ch).collect::<String>();
+        for (bidi, expected) in &[(false, "vim שלום.txt"), (true, "vim םולש.txt")] {
+            grid.set_bidi(*bidi);
+            write_string_to_grid(
+                "vim שלום.txt",
+                &mut grid,
+                Color::Default,
+                Color::Default,
+                Attr::Default,
+                ((0, 0), (11, 0)),
+                false,
+            );
+            assert_eq!(contents(&grid), *expected);
+        }
+        /* right-to-left text is read from the right */
+        assert_eq!(grid[(4, 0)].ch(), 'ם');
+        assert_eq!(grid[(7, 0)].ch(), 'ש');
+    }
+
+    #[test]
+    fn test_write_to_corner() {
+        let contents = |grid: &CellBuffer| grid.iter().map(Cell::ch).collect::<String>();
+        let write = |grid: &mut CellBuffer, s: &str, area: Area, line_break: bool| {
+            write_string_to_grid(
+                s,
+                grid,
+                Color::Default,
+                Color::Default,
+                Attr::Default,
+                area,
+                line_break,
+            )
+        };
+
+        /* ending exactly on the bottom right corner */
+        let mut grid = CellBuffer::new(4, 2, Cell::with_char('.'));
+        assert_eq!(write(&mut grid, "abcdefgh", ((0, 0), (3, 1)), true), (0, 1));
+        assert_eq!(contents(&grid), "abcdefgh");
+        let mut grid = CellBuffer::new(4, 2, Cell::with_char('.'));
+        assert_eq!(write(&mut grid, "ab", ((2, 1), (3, 1)), false), (2, 1));
+        assert_eq!(contents(&grid), "......ab");
+
+        /* an area that reaches a column and a row past the grid, as after a resize */
+        let mut grid = CellBuffer::new(4, 2, Cell::with_char('.'));
+        write(&mut grid, "abcde", ((0, 1), (4, 2)), false);
+        assert_eq!(contents(&grid), "....abcd");
+        let mut grid = CellBuffer::new(4, 2, Cell::with_char('.'));
+        write(&mut grid, "abcdefghijk", ((0, 0), (4, 2)), true);
+        assert_eq!(contents(&grid), "abcdfghi");
+        /* the second column of a wide character and a tab's padding */
+        let mut grid = CellBuffer::new(4, 1, Cell::with_char('.'));
+        write(&mut grid, "abc日", ((0, 0), (5, 0)), false);
+        assert_eq!(contents(&grid), "abc日");
+        let mut grid = CellBuffer::new(4, 1, Cell::with_char('.'));
+        write(&mut grid, "abc\t", ((0, 0), (5, 0)), false);
+        assert_eq!(contents(&grid), "abc ");
+    }
+
+    #[test]
+    fn test_write_styled() {
+        let styles = [
+            Style::default(),
+            Style {
+                fg: Color::White,
+                bg: Color::Byte(26),
+                attrs: Attr::Bold,
+            },
+            Style {
+                bg: Color::Red,
+                attrs: Attr::Dim,
+                ..Default::default()
+            },
+        ];
+        for style in styles.iter() {
+            for (s, area, line_break) in &[
+                ("ps 中文\ttop", ((1, 0), (7, 2)), true),
+                ("abcdefghijk", ((0, 1), (4, 1)), false),
+                ("", ((0, 0), (7, 2)), false),
+            ] {
+                let mut styled = CellBuffer::new(8, 3, Cell::with_char('.'));
+                let mut colored = styled.clone();
+                assert_eq!(
+                    write_styled(s, &mut styled, *style, *area, *line_break),
+                    write_string_to_grid(
+                        s,
+                        &mut colored,
+                        style.fg,
+                        style.bg,
+                        style.attrs,
+                        *area,
+                        *line_break
+                    )
+                );
+                assert!(styled == colored, "{:?} {:?}", s, style);
+            }
+        }
+        let mut grid = CellBuffer::new(4, 1, Cell::with_char(' '));
+        write_styled("ab", &mut grid, styles[1], ((0, 0), (3, 0)), false);
+        assert_eq!(
+            (grid[(1, 0)].fg(), grid[(1, 0)].bg(), grid[(1, 0)].attrs()),
+            (Color::White, Color::Byte(26), Attr::Bold)
+        );
+        assert_eq!(grid[(2, 0)].bg(), Color::Default);
+    }
+
+    #[test]
+    fn test_find() {
+        let mut grid = CellBuffer::new(16, 2, Cell::with_char(' '));
+        write_string_to_grid(
+            "ps 中文 top",
+            &mut grid,
+            Color::Default,
+            Color::Default,
+            Attr::Default,
+            ((0, 0), (15, 0)),
+            false,
+        );
+        write_string_to_grid(
+            "Top top",
+            &mut grid,
+            Color::Default,
+            Color::Default,
+            Attr::Default,
+            ((2, 1), (15, 1)),
+            false,
+        );
+        /* "中" and "文" take up x = 3..=4 and 5..=6 */
+        assert_eq!(grid.find("中文 t"), vec![((3, 0), (8, 0))]);
+        assert_eq!(grid.find("文"), vec![((5, 0), (6, 0))]);
+        assert_eq!(grid.find("top"), vec![((8, 0), (10, 0)), ((6, 1), (8, 1))]);
+        assert_eq!(
+            grid.find_ignore_case("TOP"),
+            vec![((8, 0), (10, 0)), ((2, 1), (4, 1)), ((6, 1), (8, 1))]
+        );
+        assert_eq!(grid.find("TOP"), vec![]);
+        /* Matches don't span rows */
+        assert_eq!(grid.find("top       Top"), vec![]);
+        assert_eq!(grid.find(""), vec![]);
+    }
+
+    #[test]
+    fn test_overlay() {
+        let contents = |grid: &CellBuffer| {
+            grid.buf
+                .chunks(grid.cols)
+                .map(|row| row.iter().map(Cell::ch).collect::<String>())
+                .collect::<Vec<String>>()
+        };
+        let mut grid = CellBuffer::new(5, 4, Cell::with_char('.'));
+        /* a box with a shadow on its right and bottom, `~` marking the transparent corners */
+        let mut top = CellBuffer::new(3, 3, Cell::with_char('#'));
+        for &pos in &[(2, 0), (0, 2)] {
+            top[pos].set_ch('~');
+        }
+        for &pos in &[(2, 1), (1, 2), (2, 2)] {
+            top[pos].set_ch(' ').set_bg(Color::Byte(236));
+        }
+        grid[(3, 1)].set_fg(Color::Red);
+        grid.overlay(&top, (1, 1), |cell| cell.ch() == '~');
+        assert_eq!(contents(&grid), vec![".....", ".##..", ".## .", "..  ."]);
+        /* transparent cells keep the background's attributes too, opaque ones replace them */
+        assert_eq!(grid[(3, 1)].fg(), Color::Red);
+        assert_eq!(grid[(3, 2)].bg(), Color::Byte(236));
+        assert_eq!(grid[(0, 3)].bg(), Color::Default);
+
+        /* clipped at the edges */
+        let mut grid = CellBuffer::new(5, 4, Cell::with_char('.'));
+        grid.overlay(&top, (3, 2), |cell| cell.ch() == '~');
+        assert_eq!(contents(&grid), vec![".....", ".....", "...##", "...##"]);
+        grid.overlay(&top, (5, 0), |_| false);
+        grid.overlay(&top, (0, 4), |_| false);
+        assert_eq!(contents(&grid), vec![".....", ".....", "...##", "...##"]);
+        grid.overlay(&CellBuffer::default(), (0, 0), |_| false);
+        assert_eq!(contents(&grid), vec![".....", ".....", "...##", "...##"]);
+    }
+
+    /* Run with `cargo test --release -- --ignored --nocapture bench_` */
+    #[test]
+    #[ignore]
+    fn bench_wide_buffer() {
+        use std::time::Instant;
+        let blank = Cell::with_char(' ');
+        let mut grid = CellBuffer::new(400, 100, blank);
+        let now = Instant::now();
+        for i in 0..1000 {
+            grid.clear(if i % 2 == 0 {
+                Cell::with_char('a')
+            } else {
+                blank
+            });
+        }
+        eprintln!("clear:      {:?}/iter", now.elapsed() / 1000);
+        let now = Instant::now();
+        for i in 0..1000 {
+            clear_area(&mut grid, ((0, 0), (399 - i % 2, 99)));
+        }
+        eprintln!("clear_area: {:?}/iter", now.elapsed() / 1000);
+        let now = Instant::now();
+        for i in 0..1000 {
+            if i % 2 == 0 {
+                grid.resize(401, 101, blank);
+            } else {
+                grid.resize(400, 100, blank);
+            }
+        }
+        eprintln!("resize:     {:?}/iter", now.elapsed() / 1000);
+        let now = Instant::now();
+        let mut len = 0;
+        for _ in 0..100 {
+            len += grid.to_string().len();
         }
+        eprintln!("display:    {:?}/iter ({})", now.elapsed() / 100, len);
     }
 }
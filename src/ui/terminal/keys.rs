@@ -20,11 +20,14 @@
  */
 
 use crate::crossbeam::*;
+use crate::ui::types::ThreadEvent;
 
 use std::fmt;
 use std::io;
 use termion::event::Event as TermionEvent;
 use termion::event::Key as TermionKey;
+use termion::event::MouseButton as TermionMouseButton;
+use termion::event::MouseEvent as TermionMouseEvent;
 use termion::input::TermRead;
 
 #[derive(Debug, PartialEq, Eq, Clone)]
@@ -72,7 +75,7 @@ pub enum Key {
 
 impl fmt::Display for Key {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        use crate::Key::*;
+        use crate::ui::Key::*;
         match self {
             F(n) => write!(f, "F{}", n),
             Char('\t') => write!(f, "Tab"),
@@ -136,6 +139,45 @@ impl PartialEq<Key> for &Key {
     }
 }
 
+/// Mouse event. Coordinates are 0-based, like `Pos`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum MouseEvent {
+    /// Left button press.
+    Press(usize, usize),
+    /// Button release.
+    Release(usize, usize),
+    /// Pointer motion, with or without a button held.
+    Move(usize, usize),
+}
+
+impl MouseEvent {
+    /// Convert a termion event, ignoring buttons other than the left one.
+    fn from_termion(ev: TermionMouseEvent) -> Option<MouseEvent> {
+        match ev {
+            TermionMouseEvent::Press(TermionMouseButton::Left, x, y) => {
+                Some(MouseEvent::Press(x as usize - 1, y as usize - 1))
+            }
+            TermionMouseEvent::Press(_, _, _) => None,
+            TermionMouseEvent::Release(x, y) => {
+                Some(MouseEvent::Release(x as usize - 1, y as usize - 1))
+            }
+            TermionMouseEvent::Hold(x, y) => Some(MouseEvent::Move(x as usize - 1, y as usize - 1)),
+        }
+    }
+
+    /// Parse the SGR motion reports termion doesn't recognize, ie. `ESC [ < Cb ; Cx ; Cy M` with
+    /// `Cb` in `33..=35` (motion with the middle, right or no button held).
+    fn from_sgr_motion(bytes: &[u8]) -> Option<MouseEvent> {
+        let s = std::str::from_utf8(bytes).ok()?;
+        let s = s.strip_prefix("\x1B[<")?.strip_suffix('M')?;
+        let mut nums = s.split(';').map(|n| n.parse::<usize>().ok());
+        match (nums.next()??, nums.next()??, nums.next()??, nums.next()) {
+            (33..=35, x, y, None) if x > 0 && y > 0 => Some(MouseEvent::Move(x - 1, y - 1)),
+            _ => None,
+        }
+    }
+}
+
 #[derive(PartialEq)]
 enum InputMode {
     Normal,
@@ -152,7 +194,7 @@ enum InputMode {
  */
 pub fn get_events(
     stdin: io::Stdin,
-    mut closure: impl FnMut(Key),
+    mut closure: impl FnMut(ThreadEvent),
     rx: &crossbeam::channel::Receiver<bool>,
 ) {
     let mut input_mode = InputMode::Normal;
@@ -166,7 +208,7 @@ pub fn get_events(
         };
         match c {
             Ok(TermionEvent::Key(k)) if input_mode == InputMode::Normal => {
-                closure(Key::from(k));
+                closure(ThreadEvent::Input(Key::from(k)));
             }
             Ok(TermionEvent::Key(TermionKey::Char(k))) if input_mode == InputMode::Paste => {
                 paste_buf.push(k);
@@ -178,9 +220,25 @@ pub fn get_events(
                 input_mode = InputMode::Normal;
                 let ret = Key::from(&paste_buf);
                 paste_buf.clear();
-                closure(ret);
+                closure(ThreadEvent::Input(ret));
+            }
+            Ok(TermionEvent::Unsupported(ref k)) if k.as_slice() == FOCUS_IN => {
+                closure(ThreadEvent::Focus(true));
+            }
+            Ok(TermionEvent::Unsupported(ref k)) if k.as_slice() == FOCUS_OUT => {
+                closure(ThreadEvent::Focus(false));
+            }
+            Ok(TermionEvent::Mouse(ev)) if input_mode == InputMode::Normal => {
+                if let Some(ev) = MouseEvent::from_termion(ev) {
+                    closure(ThreadEvent::Mouse(ev));
+                }
             }
-            _ => {} // Mouse events or errors.
+            Ok(TermionEvent::Unsupported(ref k)) if input_mode == InputMode::Normal => {
+                if let Some(ev) = MouseEvent::from_sgr_motion(k) {
+                    closure(ThreadEvent::Mouse(ev));
+                }
+            }
+            _ => {} // Other mouse events or errors.
         }
     }
 }
@@ -233,5 +291,27 @@ derive_csi_sequence!(
     (BracketModeEnd, "?2003l")
 );
 
+derive_csi_sequence!(
+    #[doc = "Start reporting mouse motion even when no button is held, with [SGR encoded coordinates](http://www.xfree86.org/current/ctlseqs.html#Mouse%20Tracking)"]
+    (MouseMotionStart, "?1003h\x1B[?1006h")
+);
+
+derive_csi_sequence!(
+    #[doc = "Stop reporting mouse events, see `MouseMotionStart`"]
+    (MouseMotionEnd, "?1006l\x1B[?1003l")
+);
+
+derive_csi_sequence!(
+    #[doc = "Start reporting when the terminal gains and loses focus, see [FocusIn/FocusOut](http://www.xfree86.org/current/ctlseqs.html#Mouse%20Tracking)"]
+    (FocusTrackingStart, "?1004h")
+);
+
+derive_csi_sequence!(
+    #[doc = "Stop reporting focus changes, see `FocusTrackingStart`"]
+    (FocusTrackingEnd, "?1004l")
+);
+
 pub const BRACKET_PASTE_START: &[u8] = b"\x1B[200~";
 pub const BRACKET_PASTE_END: &[u8] = b"\x1B[201~";
+pub const FOCUS_IN: &[u8] = b"\x1B[I";
+pub const FOCUS_OUT: &[u8] = b"\x1B[O";
@@ -58,8 +58,8 @@ pub fn pos_dec(p: Pos, dec: (usize, usize)) -> Pos {
 ///
 /// Example:
 /// ```
-/// # #[macro_use] extern crate ui; fn main() {
-/// use ui::*;
+/// # #[macro_use] extern crate bb; fn main() {
+/// use bb::ui::*;
 ///
 /// let new_area = ((0, 0), (1, 1));
 /// # }
@@ -70,8 +70,8 @@ pub type Area = (Pos, Pos);
 ///
 /// Example:
 /// ```
-/// # #[macro_use] extern crate ui; fn main() {
-/// use ui::*;
+/// # #[macro_use] extern crate bb; fn main() {
+/// use bb::ui::*;
 ///
 /// let new_area = ((0, 0), (1, 1));
 /// assert_eq!(height!(new_area), 1);
@@ -88,8 +88,8 @@ macro_rules! height {
 ///
 /// Example:
 /// ```
-/// # #[macro_use] extern crate ui; fn main() {
-/// use ui::*;
+/// # #[macro_use] extern crate bb; fn main() {
+/// use bb::ui::*;
 ///
 /// let new_area = ((0, 0), (1, 1));
 /// assert_eq!(width!(new_area), 1);
@@ -106,8 +106,8 @@ macro_rules! width {
 ///
 /// Example:
 /// ```
-/// # #[macro_use] extern crate ui; fn main() {
-/// use ui::*;
+/// # #[macro_use] extern crate bb; fn main() {
+/// use bb::ui::*;
 ///
 /// let new_area = ((0, 0), (1, 1));
 /// assert_eq!(upper_left!(new_area), (0, 0));
@@ -124,8 +124,8 @@ macro_rules! upper_left {
 ///
 /// Example:
 /// ```
-/// # #[macro_use] extern crate ui; fn main() {
-/// use ui::*;
+/// # #[macro_use] extern crate bb; fn main() {
+/// use bb::ui::*;
 ///
 /// let new_area = ((0, 0), (1, 1));
 /// assert_eq!(bottom_right!(new_area), (1, 1));
@@ -142,8 +142,8 @@ macro_rules! bottom_right {
 ///
 /// Example:
 /// ```
-/// # #[macro_use] extern crate ui; fn main() {
-/// use ui::*;
+/// # #[macro_use] extern crate bb; fn main() {
+/// use bb::ui::*;
 ///
 /// let valid_area = ((0, 0), (1, 1));
 /// assert!(is_valid_area!(valid_area));
@@ -0,0 +1,271 @@
+/*
+ * bb
+ *
+ * Copyright 2019 Manos Pitsidianakis
+ *
+ * This file is part of bb.
+ *
+ * bb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * bb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with bb. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+/*!
+ A `Screen` holds what the terminal shows and what is drawn next, and writes only the cells that
+ differ between the two.
+*/
+
+use super::cells::*;
+use super::position::*;
+use std::io::Write;
+use termion::cursor;
+
+/// A front buffer of what the terminal shows and a back buffer that components draw into.
+/// `flush` writes the cells of the back buffer that differ from the front buffer to the terminal,
+/// and then copies them over to the front buffer.
+pub struct Screen {
+    front: CellBuffer,
+    back: CellBuffer,
+    /* whether the terminal shows something other than the front buffer, eg. after a resize, so
+     * that every cell has to be written */
+    invalid: bool,
+}
+
+impl Screen {
+    pub fn new(cols: usize, rows: usize) -> Screen {
+        Screen {
+            front: CellBuffer::new(cols, rows, Cell::with_char(' ')),
+            back: CellBuffer::new(cols, rows, Cell::with_char(' ')),
+            invalid: true,
+        }
+    }
+
+    pub fn size(&self) -> (usize, usize) {
+        self.back.size()
+    }
+
+    /// The back buffer, to draw the next frame into. It keeps the cells of the previous frame.
+    pub fn buffer_mut(&mut self) -> &mut CellBuffer {
+        &mut self.back
+    }
+
+    /// Resize both buffers, keeping the cells that still fit, and write every cell on the next
+    /// `flush`.
+    pub fn resize(&mut self, cols: usize, rows: usize) {
+        self.front.resize(cols, rows, Cell::with_char(' '));
+        self.back.resize(cols, rows, Cell::with_char(' '));
+        self.invalidate();
+    }
+
+    /// Write every cell on the next `flush`, eg. when the terminal was cleared.
+    pub fn invalidate(&mut self) {
+        self.invalid = true;
+    }
+
+    /// Write each run of cells of a row in which the back buffer differs from the front buffer to
+    /// `out`, and make the front buffer a copy of the back buffer.
+    ///
+    /// The whole frame is written with a single `write_all` and then `out` is flushed, so that the
+    /// terminal never gets to show part of a frame.
+    pub fn flush(&mut self, out: &mut impl Write) -> std::io::Result<()> {
+        let cols = self.back.size().0;
+        let mut s = String::new();
+        if cols > 0 {
+            for (y, (back, front)) in self
+                .back
+                .chunks(cols)
+                .zip(self.front.chunks(cols))
+                .enumerate()
+            {
+                let changed = |x: usize| self.invalid || back[x] != front[x];
+                let mut x = 0;
+                while x < cols {
+                    if !changed(x) {
+                        x += 1;
+                        continue;
+                    }
+                    let start = x;
+                    while x < cols && changed(x) {
+                        x += 1;
+                    }
+                    draw_horizontal_segment(&mut s, back, start, x - 1, y);
+                }
+            }
+        }
+        if !s.is_empty() {
+            out.write_all(s.as_bytes())?;
+        }
+        out.flush()?;
+        self.front.copy_from_slice(&self.back);
+        self.invalid = false;
+        Ok(())
+    }
+
+    /// Every cell of the last frame `flush` wrote, to draw it on a terminal that shows anything
+    /// else.
+    pub fn full_frame(&self) -> String {
+        frame(&self.front)
+    }
+}
+
+/// Every cell of `grid` as the escape sequences and characters that draw it on a terminal, the
+/// way `Screen` writes them.
+pub fn frame(grid: &CellBuffer) -> String {
+    let cols = grid.size().0;
+    let mut s = String::new();
+    if cols > 0 {
+        for (y, row) in grid.chunks(cols).enumerate() {
+            draw_horizontal_segment(&mut s, row, 0, cols - 1, y);
+        }
+    }
+    s
+}
+
+/// Render the cells `x_start..=x_end` of `row`, which is row `y` of the grid, into `out`.
+fn draw_horizontal_segment(out: &mut String, row: &[Cell], x_start: usize, x_end: usize, y: usize) {
+    use std::fmt::Write;
+    write!(out, "{}", cursor::Goto(x_start as u16 + 1, (y + 1) as u16)).unwrap();
+    for c in &row[x_start..=x_end] {
+        if c.bg() != Color::Default {
+            write!(out, "{}", termion::color::Bg(c.bg().as_termion())).unwrap();
+        }
+        if c.fg() != Color::Default {
+            write!(out, "{}", termion::color::Fg(c.fg().as_termion())).unwrap();
+        }
+        if c.attrs() != Attr::Default {
+            write!(out, "\x1B[{}m", c.attrs().as_sgr()).unwrap();
+        }
+        if !c.empty() {
+            out.push(c.ch());
+        }
+
+        if c.bg() != Color::Default {
+            write!(out, "{}", termion::color::Bg(termion::color::Reset)).unwrap();
+        }
+        if c.fg() != Color::Default {
+            write!(out, "{}", termion::color::Fg(termion::color::Reset)).unwrap();
+        }
+        if c.attrs() != Attr::Default {
+            write!(out, "\x1B[{}m", Attr::Default.as_sgr()).unwrap();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flush() {
+        let flush = |screen: &mut Screen| {
+            let mut out = vec![];
+            screen.flush(&mut out).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+        let mut screen = Screen::new(4, 2);
+        /* everything is written the first time */
+        assert_eq!(flush(&mut screen), "\x1B[1;1H    \x1B[2;1H    ");
+        assert_eq!(flush(&mut screen), "");
+
+        screen.buffer_mut()[(1, 1)].set_ch('a');
+        screen.buffer_mut()[(2, 1)].set_ch('b');
+        assert_eq!(flush(&mut screen), "\x1B[2;2Hab");
+        assert_eq!(flush(&mut screen), "");
+        /* drawing the same cells again writes nothing */
+        screen.buffer_mut()[(1, 1)].set_ch('a');
+        assert_eq!(flush(&mut screen), "");
+
+        screen.buffer_mut()[(0, 0)].set_ch('x');
+        screen.buffer_mut()[(3, 0)].set_fg(Color::Red);
+        assert_eq!(
+            flush(&mut screen),
+            "\x1B[1;1Hx\x1B[1;4H\x1B[38;5;1m \x1B[39m"
+        );
+
+        screen.resize(3, 2);
+        assert_eq!(screen.size(), (3, 2));
+        assert_eq!(flush(&mut screen), "\x1B[1;1Hx  \x1B[2;1H ab");
+        screen.invalidate();
+        assert_eq!(flush(&mut screen), "\x1B[1;1Hx  \x1B[2;1H ab");
+        assert_eq!(flush(&mut screen), "");
+        /* a full frame has every cell, whatever changed */
+        assert_eq!(screen.full_frame(), "\x1B[1;1Hx  \x1B[2;1H ab");
+    }
+
+    #[test]
+    fn test_flush_styles() {
+        let mut screen = Screen::new(3, 1);
+        screen.buffer_mut()[(0, 0)]
+            .set_ch('a')
+            .set_fg(Color::White)
+            .set_bg(Color::Byte(235));
+        screen.buffer_mut()[(1, 0)]
+            .set_ch('b')
+            .set_attrs(Attr::BoldUnderline);
+        screen.buffer_mut()[(2, 0)].set_ch('c');
+        let mut out: Vec<u8> = vec![];
+        screen.flush(&mut out).unwrap();
+        /* every styled cell resets what it set, so that the unstyled one after them is plain */
+        assert_eq!(
+            out,
+            b"\x1B[1;1H\x1B[48;5;235m\x1B[38;5;7ma\x1B[49m\x1B[39m\x1B[1;4mb\x1B[0mc"
+        );
+    }
+
+    /* Counts the calls that reach the terminal */
+    #[derive(Default)]
+    struct CountingWriter {
+        bytes: Vec<u8>,
+        writes: usize,
+        flushes: usize,
+    }
+
+    impl Write for CountingWriter {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.bytes.extend_from_slice(buf);
+            self.writes += 1;
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            self.flushes += 1;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_flush_writes_once() {
+        let mut screen = Screen::new(80, 24);
+        let mut out = CountingWriter::default();
+        screen.flush(&mut out).unwrap();
+        assert_eq!((out.writes, out.flushes), (1, 1));
+        /* many separate runs of changed cells, in different colors */
+        for y in 0..24 {
+            for x in (0..80).step_by(3) {
+                screen.buffer_mut()[(x, y)]
+                    .set_ch('x')
+                    .set_fg(Color::Byte(x as u8));
+            }
+        }
+        let mut out = CountingWriter::default();
+        screen.flush(&mut out).unwrap();
+        assert_eq!((out.writes, out.flushes), (1, 1));
+        assert_eq!(
+            String::from_utf8(out.bytes).unwrap().matches('x').count(),
+            24 * 27
+        );
+        /* a frame without changes writes nothing, but still flushes */
+        let mut out = CountingWriter::default();
+        screen.flush(&mut out).unwrap();
+        assert_eq!((out.writes, out.flushes), (0, 1));
+    }
+}
@@ -0,0 +1,155 @@
+/*
+ * bb
+ *
+ * Copyright 2019 Manos Pitsidianakis
+ *
+ * This file is part of bb.
+ *
+ * bb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * bb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with bb. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+/*!
+ Copies of the frames bb draws, written to a named pipe with `--stream-to` so that another program
+ can show or record them, eg. `cat` in a terminal of the same size.
+
+ Each frame is written in full, as the escape sequences that draw every cell. The pipe is written
+ without blocking: while the reader hasn't taken the rest of a frame, newer frames are dropped.
+*/
+
+use std::fs::{File, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::PathBuf;
+
+/// Writes whole frames to `W` without ever waiting for it. A frame that's only partly written is
+/// finished before the next one is taken, so that the reader never gets half of two frames.
+#[derive(Debug)]
+pub struct FrameSink<W: Write> {
+    out: W,
+    /* the unwritten rest of the last frame taken */
+    pending: Vec<u8>,
+}
+
+impl<W: Write> FrameSink<W> {
+    /// `out` has to be non-blocking, eg. a pipe opened with `O_NONBLOCK`.
+    pub fn new(out: W) -> FrameSink<W> {
+        FrameSink {
+            out,
+            pending: vec![],
+        }
+    }
+
+    /// Write as much of `frame` as `out` takes right away. Returns `Ok(false)` if the frame was
+    /// dropped, because the reader is still behind on an earlier one.
+    pub fn send(&mut self, frame: &[u8]) -> std::io::Result<bool> {
+        self.write_pending()?;
+        if !self.pending.is_empty() {
+            return Ok(false);
+        }
+        self.pending.extend_from_slice(frame);
+        self.write_pending()?;
+        Ok(true)
+    }
+
+    fn write_pending(&mut self) -> std::io::Result<()> {
+        while !self.pending.is_empty() {
+            match self.out.write(&self.pending) {
+                Ok(0) => return Err(ErrorKind::WriteZero.into()),
+                Ok(n) => {
+                    self.pending.drain(..n);
+                }
+                Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                Err(err) if err.kind() == ErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The named pipe of `--stream-to`. It can only be opened for writing while a reader has it open,
+/// so it's opened again on each frame until one does, and after a reader goes away.
+#[derive(Debug)]
+pub struct FrameStream {
+    path: PathBuf,
+    sink: Option<FrameSink<File>>,
+}
+
+impl FrameStream {
+    pub fn new(path: PathBuf) -> FrameStream {
+        FrameStream { path, sink: None }
+    }
+
+    /// Send `frame` to the reader of the pipe if there's one and it has kept up.
+    pub fn send(&mut self, frame: &[u8]) {
+        if self.sink.is_none() {
+            self.sink = OpenOptions::new()
+                .write(true)
+                .custom_flags(libc::O_NONBLOCK)
+                .open(&self.path)
+                .ok()
+                .map(FrameSink::new);
+        }
+        if let Some(sink) = self.sink.as_mut() {
+            /* `EPIPE`, the reader closed the pipe: wait for the next one */
+            if sink.send(frame).is_err() {
+                self.sink = None;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use std::os::unix::io::{AsRawFd, FromRawFd};
+
+    /* The two ends of a pipe whose writing end doesn't block */
+    fn pipe() -> (File, File) {
+        let mut fds = [0; 2];
+        assert_eq!(unsafe { libc::pipe(fds.as_mut_ptr()) }, 0);
+        unsafe {
+            let flags = libc::fcntl(fds[1], libc::F_GETFL);
+            libc::fcntl(fds[1], libc::F_SETFL, flags | libc::O_NONBLOCK);
+            (File::from_raw_fd(fds[0]), File::from_raw_fd(fds[1]))
+        }
+    }
+
+    #[test]
+    fn test_frame_sink() {
+        let (mut reader, writer) = pipe();
+        let mut sink = FrameSink::new(writer);
+        assert!(sink.send(b"first").unwrap());
+        assert!(sink.send(b"second").unwrap());
+        let mut buf = [0; 64];
+        let n = reader.read(&mut buf).unwrap();
+        assert_eq!(&buf[..n], b"firstsecond");
+
+        /* fill the pipe: the frame that doesn't fit is kept until the reader catches up and the
+         * ones after it are dropped */
+        let capacity = unsafe { libc::fcntl(sink.out.as_raw_fd(), libc::F_GETPIPE_SZ) } as usize;
+        let frame = vec![b'x'; capacity - 2];
+        assert!(sink.send(&frame).unwrap());
+        assert!(sink.send(b"abcd").unwrap());
+        assert!(!sink.send(b"dropped").unwrap());
+        let mut buf = vec![0; capacity - 2];
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, frame);
+        assert!(sink.send(b"next").unwrap());
+        let mut buf = [0; 64];
+        let n = reader.read(&mut buf).unwrap();
+        assert_eq!(&buf[..n], b"abcdnext");
+    }
+}
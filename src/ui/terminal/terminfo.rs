@@ -0,0 +1,314 @@
+/*
+ * bb
+ *
+ * Copyright 2019 Manos Pitsidianakis
+ *
+ * This file is part of bb.
+ *
+ * bb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * bb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with bb. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+/*!
+ Whether the terminal has an alternate screen, from the `smcup` capability of its terminfo entry.
+
+ Without one, eg. on serial consoles and `TERM=vt100`, the escapes that switch screens are
+ ignored and bb draws over the scrollback, so it draws in the main screen on purpose instead and
+ clears it when it's done.
+
+ The number of colors and whether the terminal reports mouse events are read from the entry too,
+ for `--print-caps`.
+*/
+
+use std::convert::TryFrom;
+use std::path::PathBuf;
+use termion::{clear, cursor, screen};
+
+/* Magic numbers of compiled terminfo entries, with 16-bit and 32-bit numbers */
+const MAGIC: i16 = 0o432;
+const MAGIC_32BIT: i16 = 0o1036;
+/* Index of `enter_ca_mode` (`smcup`) among the string capabilities */
+const SMCUP: usize = 28;
+/* Index of `key_mouse` (`kmous`) among the string capabilities */
+const KMOUS: usize = 355;
+/* Index of `max_colors` (`colors`) among the numeric capabilities */
+const COLORS: usize = 13;
+
+/// Which screen of the terminal bb draws in.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum ScreenMode {
+    /// The alternate screen, which leaves the scrollback as it was on exit.
+    #[default]
+    Alternate,
+    /// The main screen, cleared on exit so that the prompt starts at the top.
+    Main,
+}
+
+impl ScreenMode {
+    /// `Main` if `no_altscreen` or if the terminfo entry of `$TERM` lacks `smcup`. Terminals
+    /// without an entry are assumed to have an alternate screen, like most do.
+    pub fn select(no_altscreen: bool) -> ScreenMode {
+        let entry = std::env::var("TERM")
+            .ok()
+            .and_then(|term| read_entry(&term));
+        ScreenMode::from_entry(no_altscreen, entry.as_deref())
+    }
+
+    /* `select` with the terminfo `entry` of `$TERM`, if there's one */
+    pub(crate) fn from_entry(no_altscreen: bool, entry: Option<&[u8]>) -> ScreenMode {
+        if no_altscreen {
+            return ScreenMode::Main;
+        }
+        entry
+            .and_then(ScreenMode::from_terminfo)
+            .unwrap_or_default()
+    }
+
+    /// The screen to use for the compiled terminfo `entry`, or `None` if it isn't one.
+    pub fn from_terminfo(entry: &[u8]) -> Option<ScreenMode> {
+        Some(if Entry::parse(entry)?.has_string(SMCUP)? {
+            ScreenMode::Alternate
+        } else {
+            ScreenMode::Main
+        })
+    }
+
+    /* Written when taking over the terminal, before clearing it */
+    pub(crate) fn enter(self) -> String {
+        match self {
+            ScreenMode::Alternate => screen::ToAlternateScreen.to_string(),
+            ScreenMode::Main => String::new(),
+        }
+    }
+
+    /* Written when giving the terminal back, on exit or on suspend */
+    pub(crate) fn leave(self) -> String {
+        match self {
+            ScreenMode::Alternate => screen::ToMainScreen.to_string(),
+            /* leave a blank screen instead of the last frame under the prompt */
+            ScreenMode::Main => format!("{}{}", clear::All, cursor::Goto(1, 1)),
+        }
+    }
+}
+
+/// The number of colors of the compiled terminfo `entry`, if it has a `colors` capability.
+pub fn max_colors(entry: &[u8]) -> Option<u32> {
+    Entry::parse(entry)?.number(COLORS)
+}
+
+/// Whether the compiled terminfo `entry` has a `kmous` capability, the prefix of mouse events.
+pub fn has_mouse(entry: &[u8]) -> bool {
+    Entry::parse(entry)
+        .and_then(|entry| entry.has_string(KMOUS))
+        .unwrap_or(false)
+}
+
+/* Where the capabilities are in a compiled terminfo entry, see term(5) */
+struct Entry<'a> {
+    data: &'a [u8],
+    number_size: usize,
+    numbers_offset: usize,
+    numbers: usize,
+    strings_offset: usize,
+    strings: usize,
+}
+
+impl<'a> Entry<'a> {
+    fn parse(data: &'a [u8]) -> Option<Entry<'a>> {
+        let header = |i: usize| -> Option<i16> {
+            Some(i16::from_le_bytes([
+                *data.get(2 * i)?,
+                *data.get(2 * i + 1)?,
+            ]))
+        };
+        let number_size = match header(0)? {
+            MAGIC => 2,
+            MAGIC_32BIT => 4,
+            _ => return None,
+        };
+        let count = |i: usize| header(i).and_then(|n| usize::try_from(n).ok());
+        let (names, bools, numbers, strings) = (count(1)?, count(2)?, count(3)?, count(4)?);
+        /* the numbers start on an even offset */
+        let numbers_offset = (12 + names + bools).next_multiple_of(2);
+        Some(Entry {
+            data,
+            number_size,
+            numbers_offset,
+            numbers,
+            strings_offset: numbers_offset + numbers * number_size,
+            strings,
+        })
+    }
+
+    fn bytes(&self, offset: usize, len: usize) -> Option<&'a [u8]> {
+        self.data.get(offset..offset + len)
+    }
+
+    /* The numeric capability `i`, `None` if it's absent or the entry ends before it */
+    fn number(&self, i: usize) -> Option<u32> {
+        if i >= self.numbers {
+            return None;
+        }
+        let bytes = self.bytes(self.numbers_offset + i * self.number_size, self.number_size)?;
+        let value = match *bytes {
+            [a, b] => i32::from(i16::from_le_bytes([a, b])),
+            [a, b, c, d] => i32::from_le_bytes([a, b, c, d]),
+            _ => return None,
+        };
+        /* negative values are absent or cancelled capabilities */
+        u32::try_from(value).ok()
+    }
+
+    /* Whether the string capability `i` is there, `None` if the entry ends before its offset */
+    fn has_string(&self, i: usize) -> Option<bool> {
+        if i >= self.strings {
+            return Some(false);
+        }
+        let bytes = self.bytes(self.strings_offset + 2 * i, 2)?;
+        /* -1 is an absent capability and -2 a cancelled one */
+        Some(i16::from_le_bytes([bytes[0], bytes[1]]) >= 0)
+    }
+}
+
+/* The compiled terminfo entry of `term`, from the directories ncurses looks in */
+pub(crate) fn read_entry(term: &str) -> Option<Vec<u8>> {
+    let first = term.chars().next()?;
+    if term.contains('/') {
+        return None;
+    }
+    let mut dirs = vec![];
+    dirs.extend(std::env::var_os("TERMINFO").map(PathBuf::from));
+    dirs.extend(std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".terminfo")));
+    if let Some(var) = std::env::var_os("TERMINFO_DIRS") {
+        dirs.extend(std::env::split_paths(&var).filter(|dir| !dir.as_os_str().is_empty()));
+    }
+    dirs.extend(
+        ["/etc/terminfo", "/lib/terminfo", "/usr/share/terminfo"]
+            .iter()
+            .map(PathBuf::from),
+    );
+    /* entries are in a directory named after their first letter, or its hex code on macOS */
+    dirs.iter()
+        .flat_map(|dir| {
+            vec![
+                dir.join(first.to_string()).join(term),
+                dir.join(format!("{:x}", first as u32)).join(term),
+            ]
+        })
+        .find_map(|path| std::fs::read(path).ok())
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /* A compiled entry with a `smcup` of `smcup`, or without it if `None` */
+    fn entry(magic: i16, smcup: Option<&str>) -> Vec<u8> {
+        let strings = smcup.map(|smcup| (SMCUP, smcup));
+        compiled_entry(magic, &[-1, -1], SMCUP + 1, strings.as_slice())
+    }
+
+    /* A compiled entry with `numbers` and `count` string capabilities, of which only `strings`
+     * are present */
+    pub(crate) fn compiled_entry(
+        magic: i16,
+        numbers: &[i32],
+        count: usize,
+        strings: &[(usize, &str)],
+    ) -> Vec<u8> {
+        let names = b"test|a test terminal\0";
+        let bools = [1u8, 0, 1];
+        let table = strings.iter().map(|(_, s)| s.len() + 1).sum::<usize>();
+        let mut ret = vec![];
+        for n in &[
+            magic,
+            names.len() as i16,
+            bools.len() as i16,
+            numbers.len() as i16,
+            count as i16,
+            table as i16,
+        ] {
+            ret.extend_from_slice(&n.to_le_bytes());
+        }
+        ret.extend_from_slice(names);
+        ret.extend_from_slice(&bools);
+        if ret.len() % 2 == 1 {
+            ret.push(0);
+        }
+        for &n in numbers {
+            if magic == MAGIC {
+                ret.extend_from_slice(&(n as i16).to_le_bytes());
+            } else {
+                ret.extend_from_slice(&n.to_le_bytes());
+            }
+        }
+        let mut offset = 0;
+        for i in 0..count {
+            match strings.iter().find(|(j, _)| *j == i) {
+                Some((_, s)) => {
+                    ret.extend_from_slice(&(offset as i16).to_le_bytes());
+                    offset += s.len() + 1;
+                }
+                None => ret.extend_from_slice(&(-1i16).to_le_bytes()),
+            }
+        }
+        for (_, s) in strings {
+            ret.extend_from_slice(s.as_bytes());
+            ret.push(0);
+        }
+        ret
+    }
+
+    #[test]
+    fn test_screen_mode() {
+        for &magic in &[MAGIC, MAGIC_32BIT] {
+            assert_eq!(
+                ScreenMode::from_terminfo(&entry(magic, Some("\x1b[?1049h"))),
+                Some(ScreenMode::Alternate)
+            );
+            assert_eq!(
+                ScreenMode::from_terminfo(&entry(magic, None)),
+                Some(ScreenMode::Main)
+            );
+        }
+        /* an entry with fewer strings than `smcup`'s index has none */
+        let mut short = entry(MAGIC, Some("\x1b[?1049h"));
+        short[8..10].copy_from_slice(&10i16.to_le_bytes());
+        assert_eq!(ScreenMode::from_terminfo(&short), Some(ScreenMode::Main));
+        assert_eq!(ScreenMode::from_terminfo(b"not terminfo"), None);
+        assert_eq!(ScreenMode::from_terminfo(&entry(MAGIC, None)[..20]), None);
+
+        /* the flag always picks the main screen, which is cleared instead of switched away from */
+        assert_eq!(ScreenMode::select(true), ScreenMode::Main);
+        assert_eq!(ScreenMode::Main.enter(), "");
+        assert_eq!(ScreenMode::Main.leave(), "\x1b[2J\x1b[1;1H");
+        assert_eq!(ScreenMode::Alternate.enter(), "\x1b[?1049h");
+        assert_eq!(ScreenMode::Alternate.leave(), "\x1b[?1049l");
+    }
+
+    #[test]
+    fn test_colors_and_mouse() {
+        for &magic in &[MAGIC, MAGIC_32BIT] {
+            let mut numbers = [-1; COLORS + 2];
+            numbers[COLORS] = 256;
+            let entry = compiled_entry(magic, &numbers, KMOUS + 1, &[(KMOUS, "\x1b[M")]);
+            assert_eq!(max_colors(&entry), Some(256));
+            assert!(has_mouse(&entry));
+        }
+        /* vt100 has neither */
+        let entry = compiled_entry(MAGIC, &[8, 3, 66], SMCUP + 1, &[]);
+        assert_eq!(max_colors(&entry), None);
+        assert!(!has_mouse(&entry));
+        assert_eq!(max_colors(b"not terminfo"), None);
+    }
+}
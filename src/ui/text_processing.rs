@@ -19,11 +19,19 @@
  * along with bb. If not, see <http://www.gnu.org/licenses/>.
  */
 
+pub mod bidi;
+pub mod case_folding;
+pub mod fuzzy;
 pub mod grapheme_clusters;
 pub mod line_break;
+pub mod normalization;
 mod tables;
+pub mod truncation;
 mod types;
 pub mod wcwidth;
-pub use grapheme_clusters::*;
-pub use line_break::*;
+pub use bidi::reorder_visual;
+pub use case_folding::{contains_folded, fold_case};
+pub use fuzzy::{fuzzy_match, FuzzyMatch};
+pub use normalization::normalize_nfc;
+pub use truncation::{truncate, Truncation};
 pub use wcwidth::*;
@@ -0,0 +1,290 @@
+/*
+ * bb
+ *
+ * Copyright 2019 Manos Pitsidianakis
+ *
+ * This file is part of bb.
+ *
+ * bb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * bb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with bb. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+/*! Reordering of bidirectional text for display, see [UAX #9](https://www.unicode.org/reports/tr9/).
+
+Strings are kept in logical order, so Arabic and Hebrew read backwards when their characters are
+put in cells from left to right. `reorder_visual` resolves the levels of a left-to-right paragraph
+with the weak, neutral and implicit rules of the algorithm and reverses the runs at odd levels.
+Explicit embeddings, overrides and isolates aren't supported, their formatting characters are
+ignored, and neither is bracket pairing (rule N0): brackets are treated like any other neutral.
+*/
+
+extern crate unicode_segmentation;
+use self::unicode_segmentation::UnicodeSegmentation;
+use crate::ui::text_processing::tables::BIDI_CLASS;
+use crate::ui::text_processing::types::BidiClass::{self, *};
+use std::borrow::Cow;
+
+/* Characters with the Bidi_Mirrored property that are drawn as their pair at odd levels (rule
+ * L4), for the brackets likely to be found in command lines */
+const MIRRORED: &[(char, char)] = &[
+    ('(', ')'),
+    (')', '('),
+    ('<', '>'),
+    ('>', '<'),
+    ('[', ']'),
+    (']', '['),
+    ('{', '}'),
+    ('}', '{'),
+    ('«', '»'),
+    ('»', '«'),
+    ('‹', '›'),
+    ('›', '‹'),
+];
+
+pub fn bidi_class(c: char) -> BidiClass {
+    let c = c as u32;
+    match BIDI_CLASS.binary_search_by(|&(lo, hi, _)| {
+        if c < lo {
+            std::cmp::Ordering::Greater
+        } else if c > hi {
+            std::cmp::Ordering::Less
+        } else {
+            std::cmp::Ordering::Equal
+        }
+    }) {
+        Ok(i) => BIDI_CLASS[i].2,
+        Err(_) => L,
+    }
+}
+
+/* Whether `c` is a strong right-to-left character. There are none before the Hebrew block. */
+fn is_rtl(c: char) -> bool {
+    c >= '\u{590}' && matches!(bidi_class(c), R | AL)
+}
+
+/// Return `s` in visual order, with the runs of right-to-left text reversed. Strings without
+/// right-to-left characters are borrowed.
+pub fn reorder_visual(s: &str) -> Cow<'_, str> {
+    if !s.chars().any(is_rtl) {
+        return Cow::Borrowed(s);
+    }
+    let levels = resolve_levels(&s.chars().map(bidi_class).collect::<Vec<BidiClass>>());
+
+    /* Combining marks have to stay after their base character (rule L3), so whole grapheme clusters
+     * are reordered, at the level of their first character */
+    let mut clusters = Vec::new();
+    let mut i = 0;
+    for g in s.graphemes(true) {
+        clusters.push((g, levels[i]));
+        i += g.chars().count();
+    }
+
+    /* Rule L2: reverse every run at the highest level or above, then at the next lower one and so on
+     * down to the lowest odd level */
+    let max = clusters.iter().map(|c| c.1).max().unwrap_or(0);
+    for level in (1..=max).rev() {
+        let mut start = 0;
+        while start < clusters.len() {
+            if clusters[start].1 < level {
+                start += 1;
+                continue;
+            }
+            let mut end = start;
+            while end < clusters.len() && clusters[end].1 >= level {
+                end += 1;
+            }
+            clusters[start..end].reverse();
+            start = end;
+        }
+    }
+
+    let mut ret = String::with_capacity(s.len());
+    for (g, level) in clusters {
+        for c in g.chars() {
+            ret.push(match MIRRORED.iter().find(|m| m.0 == c) {
+                Some(&(_, mirror)) if level % 2 == 1 => mirror,
+                _ => c,
+            });
+        }
+    }
+    Cow::Owned(ret)
+}
+
+/* The embedding level of each character of a left-to-right paragraph with these `classes` */
+fn resolve_levels(classes: &[BidiClass]) -> Vec<u8> {
+    /* Rule X9: formatting characters are left out, and take the level of the character before them
+     * at the end */
+    let kept = (0..classes.len())
+        .filter(|&i| !is_removed(classes[i]))
+        .collect::<Vec<usize>>();
+    let mut t = kept.iter().map(|&i| classes[i]).collect::<Vec<BidiClass>>();
+    let n = t.len();
+
+    /* W1: non-spacing marks take the class of the character before them */
+    let mut prev = L;
+    for c in t.iter_mut() {
+        if *c == NSM {
+            *c = prev;
+        }
+        prev = *c;
+    }
+    /* W2, W3: European numbers after Arabic letters are Arabic numbers, and Arabic letters are R */
+    let mut strong = L;
+    for c in t.iter_mut() {
+        match *c {
+            L | R | AL => strong = *c,
+            EN if strong == AL => *c = AN,
+            _ => {}
+        }
+        if *c == AL {
+            *c = R;
+        }
+    }
+    /* W4: a single separator between two numbers of the same type joins them */
+    for i in 1..n.saturating_sub(1) {
+        match (t[i - 1], t[i], t[i + 1]) {
+            (EN, ES, EN) | (EN, CS, EN) => t[i] = EN,
+            (AN, CS, AN) => t[i] = AN,
+            _ => {}
+        }
+    }
+    /* W5: terminators next to European numbers are part of them */
+    let mut i = 0;
+    while i < n {
+        if t[i] != ET {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        while i < n && t[i] == ET {
+            i += 1;
+        }
+        if (start > 0 && t[start - 1] == EN) || (i < n && t[i] == EN) {
+            for c in &mut t[start..i] {
+                *c = EN;
+            }
+        }
+    }
+    /* W6, W7: the remaining separators and terminators are neutral, and European numbers in left to
+     * right text are L */
+    let mut strong = L;
+    for c in t.iter_mut() {
+        match *c {
+            ES | ET | CS => *c = ON,
+            L | R => strong = *c,
+            EN if strong == L => *c = L,
+            _ => {}
+        }
+    }
+    /* N1, N2: neutrals between text of the same direction take it, numbers counting as R, and the
+     * others the direction of the paragraph */
+    let direction = |c: BidiClass| if c == L { L } else { R };
+    let mut i = 0;
+    while i < n {
+        if !is_neutral(t[i]) {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        while i < n && is_neutral(t[i]) {
+            i += 1;
+        }
+        let before = if start > 0 {
+            direction(t[start - 1])
+        } else {
+            L
+        };
+        let after = if i < n { direction(t[i]) } else { L };
+        let resolved = if before == after { before } else { L };
+        for c in &mut t[start..i] {
+            *c = resolved;
+        }
+    }
+
+    /* I1 */
+    let mut levels = vec![0; classes.len()];
+    for (&i, &c) in kept.iter().zip(t.iter()) {
+        levels[i] = match c {
+            R => 1,
+            AN | EN => 2,
+            _ => 0,
+        };
+    }
+    for i in 1..classes.len() {
+        if is_removed(classes[i]) {
+            levels[i] = levels[i - 1];
+        }
+    }
+    /* L1: separators, and the whitespace before them or at the end, are at the paragraph level */
+    let mut trailing = true;
+    for i in (0..classes.len()).rev() {
+        match classes[i] {
+            S | B => {
+                levels[i] = 0;
+                trailing = true;
+            }
+            WS => {
+                if trailing {
+                    levels[i] = 0;
+                }
+            }
+            c if is_removed(c) => {
+                if trailing {
+                    levels[i] = 0;
+                }
+            }
+            _ => trailing = false,
+        }
+    }
+    levels
+}
+
+fn is_removed(c: BidiClass) -> bool {
+    matches!(c, BN | LRE | LRO | RLE | RLO | PDF | LRI | RLI | FSI | PDI)
+}
+
+fn is_neutral(c: BidiClass) -> bool {
+    matches!(c, B | S | WS | ON)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reorder_visual() {
+        let s = "cp /tmp/a.txt";
+        assert!(matches!(reorder_visual(s), Cow::Borrowed(_)));
+        /* the Hebrew run is reversed, the text around it isn't */
+        assert_eq!(reorder_visual("vim שלום.txt"), "vim םולש.txt");
+        assert_eq!(reorder_visual("שלום עולם"), "םלוע םולש");
+        /* numbers keep their order inside right-to-left text */
+        assert_eq!(reorder_visual("א 123 ב"), "ב 123 א");
+        /* Arabic, with Arabic-Indic digits */
+        assert_eq!(
+            reorder_visual("mpv \u{645}\u{644}\u{641} \u{661}\u{662}.mkv"),
+            "mpv \u{661}\u{662} \u{641}\u{644}\u{645}.mkv"
+        );
+        /* brackets in right-to-left text are mirrored */
+        assert_eq!(reorder_visual("א (ב) ג"), "ג (ב) א");
+        /* points stay after the letters they're on */
+        assert_eq!(
+            reorder_visual("\u{5e9}\u{5b8}\u{5dc}"),
+            "\u{5dc}\u{5e9}\u{5b8}"
+        );
+        /* trailing whitespace and tabs stay at the paragraph level */
+        assert_eq!(reorder_visual("אב\tגד  "), "בא\tדג  ");
+        /* formatting characters are ignored */
+        assert_eq!(reorder_visual("א\u{200f}ב x"), "ב\u{200f}א x");
+    }
+}
@@ -0,0 +1,97 @@
+/*
+ * bb
+ *
+ * Copyright 2019 Manos Pitsidianakis
+ *
+ * This file is part of bb.
+ *
+ * bb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * bb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with bb. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+/*! Case-insensitive matching for non-ASCII text.
+
+Strings are lowercased one grapheme cluster at a time. Lowercasing the whole string would make a
+capital sigma fold to `ς` at the end of a word and to `σ` elsewhere, so a query would fold
+differently from the same letters inside a longer command line.
+*/
+
+use super::grapheme_clusters::Graphemes;
+use std::borrow::Cow;
+
+/// Lowercase `s` for case-insensitive comparisons. Strings that have no uppercase letters are
+/// borrowed, as long as they are ASCII.
+pub fn fold_case(s: &str) -> Cow<'_, str> {
+    if s.is_ascii() {
+        if s.bytes().any(|b| b.is_ascii_uppercase()) {
+            Cow::Owned(s.to_ascii_lowercase())
+        } else {
+            Cow::Borrowed(s)
+        }
+    } else {
+        Cow::Owned(
+            s.split_graphemes()
+                .into_iter()
+                .map(str::to_lowercase)
+                .collect(),
+        )
+    }
+}
+
+/// Whether `haystack` contains `needle` ignoring case. `needle` must already be folded with
+/// `fold_case`, so that it's folded once instead of for every string it's matched against.
+pub fn contains_folded(haystack: &str, needle: &str) -> bool {
+    if needle.is_empty() {
+        return true;
+    }
+    if haystack.is_ascii() && needle.is_ascii() {
+        /* Avoid allocating for the common case */
+        haystack
+            .as_bytes()
+            .windows(needle.len())
+            .any(|w| w.eq_ignore_ascii_case(needle.as_bytes()))
+    } else {
+        fold_case(haystack).contains(needle)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_contains_folded() {
+        let matches = |haystack: &str, query: &str| contains_folded(haystack, &fold_case(query));
+        assert!(matches("/usr/bin/nginx: worker", "NGINX"));
+        assert!(matches("/usr/bin/NGINX", "nginx"));
+        assert!(!matches("/usr/bin/nginx", "apache"));
+        assert!(matches("bash", ""));
+
+        /* accented and non-Latin uppercase queries */
+        assert!(matches("/opt/äpfel/birnen", "Ä"));
+        assert!(matches("/opt/äpfel/birnen", "ÄPFEL"));
+        assert!(matches("python3 école.py", "ÉCOLE"));
+        assert!(matches("./привет --debug", "ПРИВЕТ"));
+        assert!(matches("ΣΊΣΥΦΟΣ", "σίσυφοσ"));
+        assert!(matches("σίσυφοσ", "ΣΊΣΥΦΟΣ"));
+        assert!(!matches("/opt/apfel", "Ä"));
+        /* an ASCII query in a non-ASCII command line and vice versa */
+        assert!(matches("café --PORT 80", "port"));
+        assert!(!matches("cafe", "CAFÉ"));
+
+        assert!(matches!(fold_case("/usr/bin/bash"), Cow::Borrowed(_)));
+        assert_eq!(fold_case("/usr/BIN/Bash"), "/usr/bin/bash");
+        /* a lone capital sigma folds the same wherever it is */
+        assert_eq!(fold_case("ΟΔΟΣ"), "οδοσ");
+    }
+}
@@ -0,0 +1,186 @@
+/*
+ * bb
+ *
+ * Copyright 2019 Manos Pitsidianakis
+ *
+ * This file is part of bb.
+ *
+ * bb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * bb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with bb. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+/*! Fuzzy matching of filter terms, where a term matches any string that contains its characters in
+order, eg. `chrm` matches `chrome`.
+
+Of all the ways a term can match, the one with the best score is chosen: a matched character scores
+more if it follows the previous one or starts a word, and every character skipped between two
+matched ones costs a point. So `chr` in `ech chrome` matches the three characters of `chrome`
+instead of the first `c` and `h`.
+*/
+
+/// The characters of a string that a fuzzy term matched.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FuzzyMatch {
+    /// Higher is better.
+    pub score: i64,
+    /// Indices of the matched characters, not bytes, in increasing order.
+    pub positions: Vec<usize>,
+}
+
+const MATCH: i64 = 16;
+/* matching the character after the previous match */
+const CONSECUTIVE: i64 = 8;
+/* matching the first character of the string or of a word */
+const BOUNDARY: i64 = 8;
+/* for every character between two matched ones */
+const GAP: i64 = 1;
+const NONE: i64 = i64::MIN / 4;
+
+fn is_separator(c: char) -> bool {
+    c.is_whitespace() || matches!(c, '/' | '-' | '_' | '.' | ':' | '=' | ',')
+}
+
+/// Match `needle` against `haystack` as a subsequence, or return `None` if its characters don't
+/// all appear in order. Unless `case_sensitive`, `needle` must already be folded with `fold_case`
+/// and the characters of `haystack` are lowercased to compare them.
+pub fn fuzzy_match(haystack: &str, needle: &str, case_sensitive: bool) -> Option<FuzzyMatch> {
+    let fold = |c: char| {
+        if case_sensitive {
+            c
+        } else if c.is_ascii() {
+            c.to_ascii_lowercase()
+        } else {
+            c.to_lowercase().next().unwrap_or(c)
+        }
+    };
+    let hay = haystack.chars().map(fold).collect::<Vec<char>>();
+    let needle = needle.chars().collect::<Vec<char>>();
+    let (n, m) = (hay.len(), needle.len());
+    if m == 0 {
+        return Some(FuzzyMatch {
+            score: 0,
+            positions: vec![],
+        });
+    }
+    /* Rule out most strings before filling in the tables */
+    let mut rest = hay.iter();
+    if !needle.iter().all(|c| rest.any(|h| h == c)) {
+        return None;
+    }
+
+    let bonus = |j: usize| {
+        if j == 0 || is_separator(hay[j - 1]) {
+            BOUNDARY
+        } else {
+            0
+        }
+    };
+    /* `matched[i * n + j]` is the best score of the first `i + 1` characters of `needle` with the
+     * last one matched at `hay[j]`, and `best[i * n + j]` the best score of them matched anywhere
+     * up to `hay[j]`, minus the gap after the last one */
+    let mut matched = vec![NONE; m * n];
+    let mut best = vec![NONE; m * n];
+    for (i, &c) in needle.iter().enumerate() {
+        for (j, &h) in hay.iter().enumerate() {
+            let k = i * n + j;
+            if h == c {
+                if i == 0 {
+                    matched[k] = MATCH + bonus(j);
+                } else if j > 0 {
+                    let prev = std::cmp::max(matched[k - n - 1] + CONSECUTIVE, best[k - n - 1]);
+                    if prev > NONE / 2 {
+                        matched[k] = prev + MATCH + bonus(j);
+                    }
+                }
+            }
+            best[k] = if j > 0 {
+                std::cmp::max(matched[k], best[k - 1] - GAP)
+            } else {
+                matched[k]
+            };
+        }
+    }
+
+    let last = &matched[(m - 1) * n..];
+    let (mut j, &score) = last
+        .iter()
+        .enumerate()
+        .rev()
+        .max_by_key(|&(_, score)| *score)?;
+    if score <= NONE / 2 {
+        return None;
+    }
+    /* Walk back through the tables to find which characters gave the best score */
+    let mut positions = vec![0; m];
+    positions[m - 1] = j;
+    for i in (1..m).rev() {
+        let prev = matched[i * n + j] - MATCH - bonus(j);
+        let row = &matched[(i - 1) * n..i * n];
+        j = if row[j - 1] + CONSECUTIVE == prev {
+            j - 1
+        } else {
+            (0..j)
+                .rev()
+                .find(|&k| row[k] - GAP * (j - 1 - k) as i64 == prev)
+                .unwrap()
+        };
+        positions[i - 1] = j;
+    }
+    Some(FuzzyMatch { score, positions })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fuzzy_match() {
+        let positions = |haystack: &str, needle: &str| {
+            fuzzy_match(haystack, needle, false).map(|m| m.positions)
+        };
+        let score =
+            |haystack: &str, needle: &str| fuzzy_match(haystack, needle, false).unwrap().score;
+
+        assert_eq!(positions("chrome", "chrm"), Some(vec![0, 1, 2, 4]));
+        assert_eq!(
+            positions("/usr/bin/chrome", "chrome"),
+            Some(vec![9, 10, 11, 12, 13, 14])
+        );
+        assert_eq!(positions("chrome", "chz"), None);
+        assert_eq!(positions("chrome", "mc"), None);
+        assert_eq!(positions("ch", "chr"), None);
+        assert_eq!(positions("bash", ""), Some(vec![]));
+        /* the word that matches as a whole is preferred over the first characters that match */
+        assert_eq!(positions("ech chrome", "chr"), Some(vec![4, 5, 6]));
+        assert_eq!(
+            positions("cat /tmp/a.log", "alog"),
+            Some(vec![9, 11, 12, 13])
+        );
+        /* case is ignored unless matching is case-sensitive */
+        assert_eq!(positions("/opt/Chrome", "chr"), Some(vec![5, 6, 7]));
+        assert_eq!(fuzzy_match("/opt/Chrome", "chr", true), None);
+        assert_eq!(positions("/opt/ÄPFEL", "äpf"), Some(vec![5, 6, 7]));
+
+        /* consecutive characters score more than scattered ones, and gaps cost */
+        assert_eq!(
+            score("chrome", "chr"),
+            3 * MATCH + BOUNDARY + 2 * CONSECUTIVE
+        );
+        assert_eq!(score("chrome", "cr"), 2 * MATCH + BOUNDARY - GAP);
+        assert!(score("crond", "cr") > score("chrome", "cr"));
+        assert!(score("xchrome", "chr") > score("cxhxr", "chr"));
+        /* and so do matches at the start of words */
+        assert!(score("/usr/bin/rsync", "rs") > score("/usr/bin/cursor", "rs"));
+        assert!(score("a-b-c", "abc") > score("axbxc", "abc"));
+    }
+}
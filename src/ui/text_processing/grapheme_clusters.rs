@@ -34,11 +34,11 @@ extern crate unicode_segmentation;
 use self::unicode_segmentation::UnicodeSegmentation;
 
 pub trait Graphemes: UnicodeSegmentation + CodePointsIter {
-    fn split_graphemes<'a>(&'a self) -> Vec<&'a str> {
+    fn split_graphemes(&self) -> Vec<&str> {
         UnicodeSegmentation::graphemes(self, true).collect::<Vec<&str>>()
     }
 
-    fn graphemes_indices<'a>(&'a self) -> Vec<(usize, &'a str)> {
+    fn graphemes_indices(&self) -> Vec<(usize, &str)> {
         UnicodeSegmentation::grapheme_indices(self, true).collect::<Vec<(usize, &str)>>()
     }
 
@@ -53,7 +53,7 @@ pub trait Graphemes: UnicodeSegmentation + CodePointsIter {
     fn grapheme_width(&self) -> usize {
         let mut count = 0;
         for c in self.code_points() {
-            count += if let Some(w) = wcwidth(c) { w } else { 0 };
+            count += wcwidth(c).unwrap_or_default();
         }
 
         count
@@ -77,7 +77,7 @@ impl<'s> Iterator for WordBreakIter<'s> {
         if self.input.is_empty() {
             return None;
         }
-        self.input = self.input.trim_start_matches(|c| c == ' ');
+        self.input = self.input.trim_start_matches(' ');
         if self.input.starts_with('\n') {
             let ret = &self.input[0..0];
             self.input = &self.input[1..];
@@ -110,8 +110,7 @@ macro_rules! next_grapheme_class {
 /// Returns positions where breaks can happen
 /// Examples:
 /// ```
-/// use text_processing::{self, LineBreakCandidate::{self, *}};
-/// use text_processing::line_break::LineBreakCandidateIter;
+/// use bb::ui::line_break::{LineBreakCandidate::{self, *}, LineBreakCandidateIter};
 ///
 /// assert!(LineBreakCandidateIter::new("").collect::<Vec<(usize, LineBreakCandidate)>>().is_empty());
 /// assert_eq!(&[(7, BreakAllowed), (12, MandatoryBreak)],
@@ -135,7 +134,7 @@ impl<'a> Iterator for LineBreakCandidateIter<'a> {
         let (idx, mut grapheme) = self.iter.next().unwrap();
         let LineBreakCandidateIter {
             ref mut iter,
-            ref text,
+            text,
             ref mut reg_ind_streak,
             ref mut pos,
         } = self;
@@ -286,7 +285,7 @@ impl<'a> Iterator for LineBreakCandidateIter<'a> {
                 /* LB14 Do not break after ‘[’, even after spaces.
                  * OP SP* ×
                  */
-                while let Some((idx, grapheme)) = self.iter.next() {
+                for (idx, grapheme) in self.iter.by_ref() {
                     *pos = idx + grapheme.len();
                     if !(get_class!(grapheme) == SP) {
                         break;
@@ -384,7 +383,7 @@ impl<'a> Iterator for LineBreakCandidateIter<'a> {
                 let (idx, next_grapheme) = next_char.unwrap();
                 *pos = idx + next_grapheme.len();
                 self.iter.next();
-                return self.next();
+                self.next()
             }
             /* LB21b Don’t break between ,Solidus and Hebrew letters.  SY × HL */
             SY if next_grapheme_class!((next_char is HL)) => {
@@ -395,7 +394,7 @@ impl<'a> Iterator for LineBreakCandidateIter<'a> {
                 if let Some((idx, next_grapheme)) = self.iter.next() {
                     *pos = idx + next_grapheme.len();
                 }
-                return self.next();
+                self.next()
             }
             /*  LB22 Do not break between two ellipses, or between letters, numbers or excla-
              *  mations and ellipsis.
@@ -405,40 +404,40 @@ impl<'a> Iterator for LineBreakCandidateIter<'a> {
                 let (idx, next_grapheme) = next_char.unwrap();
                 *pos = idx + next_grapheme.len();
                 self.iter.next();
-                return self.next();
+                self.next()
             }
             /*  EX × IN */
             EX if next_grapheme_class!((next_char is IN)) => {
                 let (idx, next_grapheme) = next_char.unwrap();
                 *pos = idx + next_grapheme.len();
                 self.iter.next();
-                return self.next();
+                self.next()
             }
             EX => {
                 // LB13
                 *pos += grapheme.len();
-                return self.next();
+                self.next()
             }
             /*  (ID | EB | EM) × IN */
             ID | EB | EM if next_grapheme_class!((next_char is IN)) => {
                 let (idx, next_grapheme) = next_char.unwrap();
                 *pos = idx + next_grapheme.len();
                 self.iter.next();
-                return self.next();
+                self.next()
             }
             /*  IN × IN */
             IN if next_grapheme_class!((next_char is IN)) => {
                 let (idx, next_grapheme) = next_char.unwrap();
                 *pos = idx + next_grapheme.len();
                 self.iter.next();
-                return self.next();
+                self.next()
             }
             /*  NU × IN */
             NU if next_grapheme_class!((next_char is IN)) => {
                 let (idx, next_grapheme) = next_char.unwrap();
                 *pos = idx + next_grapheme.len();
                 self.iter.next();
-                return self.next();
+                self.next()
             }
             /* LB23 Do not break between digits and letters.
              * (AL | HL) × NU */
@@ -446,14 +445,14 @@ impl<'a> Iterator for LineBreakCandidateIter<'a> {
                 let (idx, next_grapheme) = next_char.unwrap();
                 *pos = idx + next_grapheme.len();
                 self.iter.next();
-                return self.next();
+                self.next()
             }
             /* NU × (AL | HL) */
             NU if next_grapheme_class!((next_char is AL, HL)) => {
                 let (idx, next_grapheme) = next_char.unwrap();
                 *pos = idx + next_grapheme.len();
                 self.iter.next();
-                return self.next();
+                self.next()
             }
             /* LB23a Do not break between numeric prefixes and ideographs, or between ideographs
              * and numeric postfixes.
@@ -462,14 +461,14 @@ impl<'a> Iterator for LineBreakCandidateIter<'a> {
                 let (idx, next_grapheme) = next_char.unwrap();
                 *pos = idx + next_grapheme.len();
                 self.iter.next();
-                return self.next();
+                self.next()
             }
             /* (ID | EB | EM) × PO */
             ID | EB | EM if next_grapheme_class!((next_char is PO)) => {
                 let (idx, next_grapheme) = next_char.unwrap();
                 *pos = idx + next_grapheme.len();
                 self.iter.next();
-                return self.next();
+                self.next()
             }
             /* B24 Do not break between numeric prefix/postfix and letters, or between
             letters and prefix/postfix.
@@ -478,14 +477,14 @@ impl<'a> Iterator for LineBreakCandidateIter<'a> {
                 let (idx, next_grapheme) = next_char.unwrap();
                 *pos = idx + next_grapheme.len();
                 self.iter.next();
-                return self.next();
+                self.next()
             }
             /*(AL | HL) × (PR | PO) */
             AL | HL if next_grapheme_class!((next_char is PR, PO)) => {
                 let (idx, next_grapheme) = next_char.unwrap();
                 *pos = idx + next_grapheme.len();
                 self.iter.next();
-                return self.next();
+                self.next()
             }
             /* LB25 Do not break between the following pairs of classes relevant to numbers:
              * CL × PO */
@@ -493,98 +492,98 @@ impl<'a> Iterator for LineBreakCandidateIter<'a> {
                 let (idx, next_grapheme) = next_char.unwrap();
                 *pos = idx + next_grapheme.len();
                 self.iter.next();
-                return self.next();
+                self.next()
             }
             /* CP × PO */
             CP if next_grapheme_class!((next_char is PO)) => {
                 let (idx, next_grapheme) = next_char.unwrap();
                 *pos = idx + next_grapheme.len();
                 self.iter.next();
-                return self.next();
+                self.next()
             }
             /* CL × PR */
             CL if next_grapheme_class!((next_char is PR)) => {
                 let (idx, next_grapheme) = next_char.unwrap();
                 *pos = idx + next_grapheme.len();
                 self.iter.next();
-                return self.next();
+                self.next()
             }
             /* CP × PR */
             CP if next_grapheme_class!((next_char is PR)) => {
                 let (idx, next_grapheme) = next_char.unwrap();
                 *pos = idx + next_grapheme.len();
                 self.iter.next();
-                return self.next();
+                self.next()
             }
             /* NU × PO */
             NU if next_grapheme_class!((next_char is PO)) => {
                 let (idx, next_grapheme) = next_char.unwrap();
                 *pos = idx + next_grapheme.len();
                 self.iter.next();
-                return self.next();
+                self.next()
             }
             /* NU × PR */
             NU if next_grapheme_class!((next_char is PR)) => {
                 let (idx, next_grapheme) = next_char.unwrap();
                 *pos = idx + next_grapheme.len();
                 self.iter.next();
-                return self.next();
+                self.next()
             }
             /* PO × OP */
             PO if next_grapheme_class!((next_char is OP)) => {
                 let (idx, next_grapheme) = next_char.unwrap();
                 *pos = idx + next_grapheme.len();
                 self.iter.next();
-                return self.next();
+                self.next()
             }
             /* PO × NU */
             PO if next_grapheme_class!((next_char is NU)) => {
                 let (idx, next_grapheme) = next_char.unwrap();
                 *pos = idx + next_grapheme.len();
                 self.iter.next();
-                return self.next();
+                self.next()
             }
             /* PR × OP */
             PR if next_grapheme_class!((next_char is OP)) => {
                 let (idx, next_grapheme) = next_char.unwrap();
                 *pos = idx + next_grapheme.len();
                 self.iter.next();
-                return self.next();
+                self.next()
             }
             /* PR × NU */
             PR if next_grapheme_class!((next_char is NU)) => {
                 let (idx, next_grapheme) = next_char.unwrap();
                 *pos = idx + next_grapheme.len();
                 self.iter.next();
-                return self.next();
+                self.next()
             }
             /* HY × NU */
             HY if next_grapheme_class!((next_char is NU)) => {
                 let (idx, next_grapheme) = next_char.unwrap();
                 *pos = idx + next_grapheme.len();
                 self.iter.next();
-                return self.next();
+                self.next()
             }
             /* IS × NU */
             IS if next_grapheme_class!((next_char is NU)) => {
                 let (idx, next_grapheme) = next_char.unwrap();
                 *pos = idx + next_grapheme.len();
                 self.iter.next();
-                return self.next();
+                self.next()
             }
             /* NU × NU */
             NU if next_grapheme_class!((next_char is NU)) => {
                 let (idx, next_grapheme) = next_char.unwrap();
                 *pos = idx + next_grapheme.len();
                 self.iter.next();
-                return self.next();
+                self.next()
             }
             /* SY × NU */
             SY if next_grapheme_class!((next_char is NU)) => {
                 let (idx, next_grapheme) = next_char.unwrap();
                 *pos = idx + next_grapheme.len();
                 self.iter.next();
-                return self.next();
+                self.next()
             }
             /* LB26 Do not break a Korean syllable.
              * JL × (JL | JV | H2 | H3) */
@@ -592,21 +591,21 @@ impl<'a> Iterator for LineBreakCandidateIter<'a> {
                 let (idx, next_grapheme) = next_char.unwrap();
                 *pos = idx + next_grapheme.len();
                 self.iter.next();
-                return self.next();
+                self.next()
             }
             /* (JV | H2) × (JV | JT) */
             JV | H2 if next_grapheme_class!((next_char is JV, JT)) => {
                 let (idx, next_grapheme) = next_char.unwrap();
                 *pos = idx + next_grapheme.len();
                 self.iter.next();
-                return self.next();
+                self.next()
             }
             /* (JT | H3) × JT */
             JT | H3 if next_grapheme_class!((next_char is JT)) => {
                 let (idx, next_grapheme) = next_char.unwrap();
                 *pos = idx + next_grapheme.len();
                 self.iter.next();
-                return self.next();
+                self.next()
             }
             /* LB27 Treat a Korean Syllable Block the same as ID.
              * (JL | JV | JT | H2 | H3) × IN */
@@ -614,21 +613,21 @@ impl<'a> Iterator for LineBreakCandidateIter<'a> {
                 let (idx, next_grapheme) = next_char.unwrap();
                 *pos = idx + next_grapheme.len();
                 self.iter.next();
-                return self.next();
+                self.next()
             }
             /* (JL | JV | JT | H2 | H3) × PO */
             JL | JV | JT | H2 | H3 if next_grapheme_class!((next_char is PO)) => {
                 let (idx, next_grapheme) = next_char.unwrap();
                 *pos = idx + next_grapheme.len();
                 self.iter.next();
-                return self.next();
+                self.next()
             }
             /* PR × (JL | JV | JT | H2 | H3) */
             PR if next_grapheme_class!((next_char is JL, JV, JT, H2, H3)) => {
                 let (idx, next_grapheme) = next_char.unwrap();
                 *pos = idx + next_grapheme.len();
                 self.iter.next();
-                return self.next();
+                self.next()
             }
             /* LB28 Do not break between alphabetics (“at”).
             (AL | HL) × (AL | HL) */
@@ -636,7 +635,7 @@ impl<'a> Iterator for LineBreakCandidateIter<'a> {
                 let (idx, next_grapheme) = next_char.unwrap();
                 *pos = idx + next_grapheme.len();
                 self.iter.next();
-                return self.next();
+                self.next()
             }
             /* LB29 Do not break between numeric punctuation and alphabetics (“e.g.”).
             IS × (AL | HL) */
@@ -644,7 +643,7 @@ impl<'a> Iterator for LineBreakCandidateIter<'a> {
                 let (idx, next_grapheme) = next_char.unwrap();
                 *pos = idx + next_grapheme.len();
                 self.iter.next();
-                return self.next();
+                self.next()
             }
             /* LB30 Do not break between letters, numbers, or ordinary symbols and opening
             or closing parentheses.
@@ -653,14 +652,14 @@ impl<'a> Iterator for LineBreakCandidateIter<'a> {
                 let (idx, next_grapheme) = next_char.unwrap();
                 *pos = idx + next_grapheme.len();
                 self.iter.next();
-                return self.next();
+                self.next()
             }
             /* CP × (AL | HL | NU) */
             CP if next_grapheme_class!((next_char is AL, HL , NU)) => {
                 let (idx, next_grapheme) = next_char.unwrap();
                 *pos = idx + next_grapheme.len();
                 self.iter.next();
-                return self.next();
+                self.next()
             }
             /*LB30b Do not break between an emoji base and an emoji modifier.
              * EB × EM */
@@ -668,7 +667,7 @@ impl<'a> Iterator for LineBreakCandidateIter<'a> {
                 let (idx, next_grapheme) = next_char.unwrap();
                 *pos = idx + next_grapheme.len();
                 self.iter.next();
-                return self.next();
+                self.next()
             }
             RI => {
                 /* LB30a Break between two regional indicator symbols if and only if there are an
@@ -681,11 +680,11 @@ impl<'a> Iterator for LineBreakCandidateIter<'a> {
                     return Some((*pos - grapheme.len(), BreakAllowed));
                 }
                 self.iter.next();
-                return self.next();
+                self.next()
             }
             _ => {
                 *pos += grapheme.len();
-                return Some((*pos - grapheme.len(), BreakAllowed));
+                Some((*pos - grapheme.len(), BreakAllowed))
             }
         }
     }
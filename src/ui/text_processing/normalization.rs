@@ -0,0 +1,182 @@
+/*
+ * bb
+ *
+ * Copyright 2019 Manos Pitsidianakis
+ *
+ * This file is part of bb.
+ *
+ * bb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * bb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with bb. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+/*! Unicode Normalization Form C, see [UAX #15](https://www.unicode.org/reports/tr15/).
+
+Strings read from `/proc` are displayed as they are, so a decomposed (NFD) name takes more cells and
+doesn't match a filter typed in composed form. Normalizing is opt-in since it changes the bytes of
+the string.
+*/
+
+use crate::ui::text_processing::tables::{
+    CANONICAL_COMBINING_CLASS, CANONICAL_COMPOSITION, CANONICAL_DECOMPOSITION,
+};
+use std::borrow::Cow;
+use std::cmp::Ordering;
+
+/* Hangul syllables are (de)composed algorithmically, see section 3.12 of the Unicode Standard */
+const S_BASE: u32 = 0xAC00;
+const L_BASE: u32 = 0x1100;
+const V_BASE: u32 = 0x1161;
+const T_BASE: u32 = 0x11A7;
+const L_COUNT: u32 = 19;
+const V_COUNT: u32 = 21;
+const T_COUNT: u32 = 28;
+const N_COUNT: u32 = V_COUNT * T_COUNT;
+const S_COUNT: u32 = L_COUNT * N_COUNT;
+
+/// Return `s` in Normalization Form C. Strings that are already normalized are borrowed.
+pub fn normalize_nfc(s: &str) -> Cow<'_, str> {
+    /* Characters below U+0300 are all in NFC and none of them compose with each other */
+    if s.chars().all(|c| (c as u32) < 0x300) {
+        return Cow::Borrowed(s);
+    }
+
+    let mut buf = Vec::with_capacity(s.len());
+    for c in s.chars() {
+        decompose(c as u32, &mut buf);
+    }
+
+    /* Canonical ordering: sort each run of non-starters by combining class, keeping the order of
+     * equal classes */
+    let mut i = 0;
+    while i < buf.len() {
+        if combining_class(buf[i]) == 0 {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        while i < buf.len() && combining_class(buf[i]) != 0 {
+            i += 1;
+        }
+        buf[start..i].sort_by_key(|c| combining_class(*c));
+    }
+
+    /* Canonical composition */
+    let mut composed: Vec<u32> = Vec::with_capacity(buf.len());
+    let mut starter: Option<usize> = None;
+    let mut last_class = 0;
+    for c in buf {
+        let class = combining_class(c);
+        if let Some(starter) = starter {
+            /* `c` is blocked from the starter by a character in between with class 0 or >= its own */
+            let blocked = composed.len() != starter + 1 && (last_class == 0 || last_class >= class);
+            if !blocked {
+                if let Some(composite) = compose(composed[starter], c) {
+                    composed[starter] = composite;
+                    continue;
+                }
+            }
+        }
+        if class == 0 {
+            starter = Some(composed.len());
+        }
+        last_class = class;
+        composed.push(c);
+    }
+
+    let ret: String = composed
+        .into_iter()
+        .filter_map(std::char::from_u32)
+        .collect();
+    if ret == s {
+        Cow::Borrowed(s)
+    } else {
+        Cow::Owned(ret)
+    }
+}
+
+fn combining_class(c: u32) -> u8 {
+    CANONICAL_COMBINING_CLASS
+        .binary_search_by(|&(start, end, _)| {
+            if end < c {
+                Ordering::Less
+            } else if start > c {
+                Ordering::Greater
+            } else {
+                Ordering::Equal
+            }
+        })
+        .map(|i| CANONICAL_COMBINING_CLASS[i].2)
+        .unwrap_or(0)
+}
+
+/* Push the full canonical decomposition of `c` to `out` */
+fn decompose(c: u32, out: &mut Vec<u32>) {
+    if (S_BASE..S_BASE + S_COUNT).contains(&c) {
+        let s = c - S_BASE;
+        out.push(L_BASE + s / N_COUNT);
+        out.push(V_BASE + (s % N_COUNT) / T_COUNT);
+        if !s.is_multiple_of(T_COUNT) {
+            out.push(T_BASE + s % T_COUNT);
+        }
+    } else if let Ok(i) = CANONICAL_DECOMPOSITION.binary_search_by_key(&c, |&(c, _)| c) {
+        out.extend_from_slice(CANONICAL_DECOMPOSITION[i].1);
+    } else {
+        out.push(c);
+    }
+}
+
+/* The primary composite of `a` followed by `b`, if any */
+fn compose(a: u32, b: u32) -> Option<u32> {
+    if (L_BASE..L_BASE + L_COUNT).contains(&a) && (V_BASE..V_BASE + V_COUNT).contains(&b) {
+        return Some(S_BASE + ((a - L_BASE) * V_COUNT + (b - V_BASE)) * T_COUNT);
+    }
+    if (S_BASE..S_BASE + S_COUNT).contains(&a)
+        && (a - S_BASE).is_multiple_of(T_COUNT)
+        && (T_BASE + 1..T_BASE + T_COUNT).contains(&b)
+    {
+        return Some(a + b - T_BASE);
+    }
+    CANONICAL_COMPOSITION
+        .binary_search_by_key(&(a, b), |&(a, b, _)| (a, b))
+        .ok()
+        .map(|i| CANONICAL_COMPOSITION[i].2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ui::text_processing::grapheme_clusters::Graphemes;
+
+    #[test]
+    fn test_normalize_nfc() {
+        /* "café" and "한국", decomposed */
+        let nfd = "cafe\u{301} \u{1112}\u{1161}\u{11AB}\u{1100}\u{116E}\u{11A8}";
+        let nfc = "caf\u{E9} \u{D55C}\u{AD6D}";
+        assert_eq!(normalize_nfc(nfd), nfc);
+        assert_eq!(normalize_nfc(nfd).chars().count(), nfc.chars().count());
+        assert_eq!(
+            normalize_nfc(nfd).grapheme_width(),
+            normalize_nfc(nfc).grapheme_width()
+        );
+        assert!(matches!(normalize_nfc(nfc), Cow::Borrowed(_)));
+        assert!(matches!(normalize_nfc("/usr/bin/bash"), Cow::Borrowed(_)));
+
+        /* Marks are reordered by combining class: dot below (220) before dot above (230) */
+        assert_eq!(normalize_nfc("q\u{307}\u{323}"), "q\u{323}\u{307}");
+        assert_eq!(normalize_nfc("s\u{307}\u{323}"), "\u{1E69}");
+        /* A mark of the same class blocks the second one from composing */
+        assert_eq!(normalize_nfc("a\u{301}\u{301}"), "\u{E1}\u{301}");
+        /* Composition exclusions stay decomposed */
+        assert_eq!(normalize_nfc("\u{915}\u{93C}"), "\u{915}\u{93C}");
+    }
+}
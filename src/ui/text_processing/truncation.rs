@@ -0,0 +1,118 @@
+/*
+ * bb
+ *
+ * Copyright 2019 Manos Pitsidianakis
+ *
+ * This file is part of bb.
+ *
+ * bb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * bb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with bb. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+/*! Shortening strings to a display width, with `…` marking what was left out. */
+
+use super::grapheme_clusters::Graphemes;
+use std::borrow::Cow;
+
+/// Which part of a string that doesn't fit is left out.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub enum Truncation {
+    /// Keep the start, eg. `/usr/lib/x86_64-linux-gnu/…`.
+    #[default]
+    End,
+    /// Keep the start and the end, eg. `/usr/lib/x8…/thing`, which for command lines keeps the
+    /// executable's directory and its last arguments.
+    Middle,
+}
+
+/// Shorten `s` to at most `width` columns. Strings that fit are borrowed. Wide characters are
+/// never split, so the result can be a column short of `width` on each side of the ellipsis.
+pub fn truncate(s: &str, width: usize, truncation: Truncation) -> Cow<'_, str> {
+    if s.grapheme_width() <= width {
+        return Cow::Borrowed(s);
+    }
+    if width == 0 {
+        return Cow::Borrowed("");
+    }
+    /* one column goes to the ellipsis */
+    let budget = width - 1;
+    let graphemes = s.split_graphemes();
+    /* Index of the first grapheme left out when taking `graphemes` in order until they fill
+     * `width` columns */
+    let fit = |graphemes: &mut dyn Iterator<Item = &&str>, width: usize| {
+        let mut used = 0;
+        graphemes
+            .take_while(|g| {
+                used += g.grapheme_width();
+                used <= width
+            })
+            .count()
+    };
+    let (head, tail) = match truncation {
+        Truncation::End => (fit(&mut graphemes.iter(), budget), 0),
+        /* the end gets the odd column, since it has the basename or the last argument */
+        Truncation::Middle => (
+            fit(&mut graphemes.iter(), budget / 2),
+            fit(&mut graphemes.iter().rev(), budget - budget / 2),
+        ),
+    };
+    let mut ret = graphemes[..head].concat();
+    ret.push('…');
+    ret.push_str(&graphemes[graphemes.len() - tail..].concat());
+    Cow::Owned(ret)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_truncate() {
+        let path = "/usr/lib/x86_64-linux-gnu/libexec/kf5/thing --verbose";
+        assert_eq!(truncate(path, 20, Truncation::End), "/usr/lib/x86_64-lin…");
+        assert_eq!(
+            truncate(path, 20, Truncation::Middle),
+            "/usr/lib/… --verbose"
+        );
+        assert_eq!(
+            truncate("/usr/lib/x86_64-linux-gnu/thing", 16, Truncation::Middle),
+            "/usr/li…nu/thing"
+        );
+        for &truncation in &[Truncation::End, Truncation::Middle] {
+            for width in 0..=path.len() {
+                let truncated = truncate(path, width, truncation);
+                assert_eq!(truncated.grapheme_width(), width, "{:?}", truncated);
+            }
+            assert!(matches!(
+                truncate(path, path.len(), truncation),
+                Cow::Borrowed(_)
+            ));
+            assert_eq!(truncate(path, 1, truncation), "…");
+            assert_eq!(truncate(path, 0, truncation), "");
+        }
+
+        /* wide characters take two columns and aren't split */
+        let wide = "/opt/应用程序/启动器";
+        assert_eq!(wide.grapheme_width(), 20);
+        assert_eq!(truncate(wide, 10, Truncation::End), "/opt/应用…");
+        assert_eq!(truncate(wide, 11, Truncation::End), "/opt/应用…");
+        assert_eq!(truncate(wide, 10, Truncation::Middle), "/opt…动器");
+        assert_eq!(truncate(wide, 9, Truncation::Middle), "/opt…动器");
+        assert_eq!(truncate(wide, 16, Truncation::Middle), "/opt/应…/启动器");
+        for width in 0..=20 {
+            let truncated = truncate(wide, width, Truncation::Middle);
+            assert!(truncated.grapheme_width() <= width, "{:?}", truncated);
+            assert!(truncated.grapheme_width() + 2 >= width, "{:?}", truncated);
+        }
+    }
+}
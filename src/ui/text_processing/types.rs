@@ -19,6 +19,8 @@
  * along with bb. If not, see <http://www.gnu.org/licenses/>.
  */
 
+/* Class names are the ones used in UAX #14 */
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub enum LineBreakClass {
     BK,
@@ -121,3 +123,32 @@ impl From<&str> for LineBreakClass {
         }
     }
 }
+
+/* Class names are the ones used in UAX #9 */
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum BidiClass {
+    L,
+    R,
+    AL,
+    EN,
+    ES,
+    ET,
+    AN,
+    CS,
+    NSM,
+    BN,
+    B,
+    S,
+    WS,
+    ON,
+    LRE,
+    LRO,
+    RLE,
+    RLO,
+    PDF,
+    LRI,
+    RLI,
+    FSI,
+    PDI,
+}
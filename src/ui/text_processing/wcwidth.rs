@@ -30,6 +30,8 @@
 // TODO: Spacing widths
 // Update to Unicode 12
 
+use std::sync::atomic::{AtomicBool, Ordering};
+
 #[macro_export]
 macro_rules! big_if_true {
     ($a:expr) => {
@@ -44,6 +46,65 @@ macro_rules! big_if_true {
 type WChar = u32;
 type Interval = (WChar, WChar);
 
+/// How many columns characters of East Asian Width "Ambiguous" take up, eg. `α`, `…` and the box
+/// drawing characters. Terminals draw them one column wide, except that CJK users often set them
+/// up to draw them two columns wide like the ideographs around them.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub enum AmbiguousWidth {
+    #[default]
+    Narrow,
+    Wide,
+}
+
+impl AmbiguousWidth {
+    /// `narrow` or `wide`.
+    pub fn parse(s: &str) -> Option<AmbiguousWidth> {
+        match s {
+            "narrow" => Some(AmbiguousWidth::Narrow),
+            "wide" => Some(AmbiguousWidth::Wide),
+            _ => None,
+        }
+    }
+
+    /// `Wide` for Chinese, Japanese and Korean locales, eg. `ja_JP.UTF-8`, `Narrow` otherwise.
+    pub fn from_locale(locale: &str) -> AmbiguousWidth {
+        let language = locale.split(&['_', '.', '@'][..]).next();
+        match language {
+            Some("zh") | Some("ja") | Some("ko") => AmbiguousWidth::Wide,
+            _ => AmbiguousWidth::Narrow,
+        }
+    }
+
+    /// `from_locale` of the locale of character types, the first of `LC_ALL`, `LC_CTYPE` and
+    /// `LANG` that is set.
+    pub fn from_env() -> AmbiguousWidth {
+        ["LC_ALL", "LC_CTYPE", "LANG"]
+            .iter()
+            .filter_map(|var| std::env::var(var).ok())
+            .find(|locale| !locale.is_empty())
+            .map_or(AmbiguousWidth::Narrow, |locale| {
+                AmbiguousWidth::from_locale(&locale)
+            })
+    }
+}
+
+/* Whether `wcwidth` counts ambiguous characters as wide. It's global since widths are measured all
+ * over, not only when drawing. */
+static AMBIGUOUS_WIDE: AtomicBool = AtomicBool::new(false);
+
+/// Set the width `wcwidth` gives ambiguous characters from now on.
+pub fn set_ambiguous_width(width: AmbiguousWidth) {
+    AMBIGUOUS_WIDE.store(width == AmbiguousWidth::Wide, Ordering::Relaxed);
+}
+
+pub fn ambiguous_width() -> AmbiguousWidth {
+    if AMBIGUOUS_WIDE.load(Ordering::Relaxed) {
+        AmbiguousWidth::Wide
+    } else {
+        AmbiguousWidth::Narrow
+    }
+}
+
 pub struct CodePointsIterator<'a> {
     rest: &'a [u8],
 }
@@ -73,13 +134,13 @@ impl<'a> Iterator for CodePointsIterator<'a> {
         }
         if self.rest[0] & 0b1110_0000 == 0b1100_0000 {
             let ret: WChar = (WChar::from(self.rest[0]) & 0b0001_1111).rotate_left(6)
-                + (WChar::from(self.rest[1]) & 0b0111_1111);
+                + (WChar::from(self.rest[1]) & 0b0011_1111);
             self.rest = &self.rest[2..];
             return Some(ret);
         }
 
         if self.rest[0] & 0b1111_0000 == 0b1110_0000 {
-            let ret: WChar = (WChar::from(self.rest[0]) & 0b0000_0111).rotate_left(12)
+            let ret: WChar = (WChar::from(self.rest[0]) & 0b0000_1111).rotate_left(12)
                 + (WChar::from(self.rest[1]) & 0b0011_1111).rotate_left(6)
                 + (WChar::from(self.rest[2]) & 0b0011_1111);
             self.rest = &self.rest[3..];
@@ -95,18 +156,18 @@ impl<'a> Iterator for CodePointsIterator<'a> {
     }
 }
 pub trait CodePointsIter {
-    fn code_points(&self) -> CodePointsIterator;
+    fn code_points(&self) -> CodePointsIterator<'_>;
 }
 
 impl CodePointsIter for str {
-    fn code_points(&self) -> CodePointsIterator {
+    fn code_points(&self) -> CodePointsIterator<'_> {
         CodePointsIterator {
             rest: self.as_bytes(),
         }
     }
 }
 impl CodePointsIter for &str {
-    fn code_points(&self) -> CodePointsIterator {
+    fn code_points(&self) -> CodePointsIterator<'_> {
         CodePointsIterator {
             rest: self.as_bytes(),
         }
@@ -168,6 +229,12 @@ fn bisearch(ucs: WChar, table: &'static [Interval]) -> bool {
  */
 
 pub fn wcwidth(ucs: WChar) -> Option<usize> {
+    wcwidth_with(ucs, ambiguous_width())
+}
+
+/// `wcwidth`, with ambiguous characters `ambiguous` wide instead of as set with
+/// `set_ambiguous_width`.
+pub fn wcwidth_with(ucs: WChar, ambiguous: AmbiguousWidth) -> Option<usize> {
     /* sorted list of non-overlapping intervals of non-spacing characters */
     let combining: &'static [Interval] = &[
         (0x0300, 0x034E),
@@ -276,7 +343,7 @@ pub fn wcwidth(ucs: WChar) -> Option<usize> {
     if ucs == 0 {
         return Some(0);
     }
-    if ucs < 32 || (ucs >= 0x7f && ucs < 0xa0) {
+    if ucs < 32 || (0x7f..0xa0).contains(&ucs) {
         return None;
     }
 
@@ -288,6 +355,9 @@ pub fn wcwidth(ucs: WChar) -> Option<usize> {
     if bisearch(ucs, combining) {
         return Some(1);
     }
+    if ambiguous == AmbiguousWidth::Wide && bisearch(ucs, AMBIGUOUS_RANGES) {
+        return Some(2);
+    }
 
     /* if we arrive here, ucs is not a combining or C0/C1 control character */
 
@@ -295,14 +365,14 @@ pub fn wcwidth(ucs: WChar) -> Option<usize> {
         1 + big_if_true!(
             ucs >= 0x1100
                 && (ucs <= 0x115f ||                    /* Hangul Jamo init. consonants */
-      (ucs >= 0x2e80 && ucs <= 0xa4cf && (ucs & !0x0011) != 0x300a &&
+      ((0x2e80..=0xa4cf).contains(&ucs) && (ucs & !0x0011) != 0x300a &&
        ucs != 0x303f) ||                  /* CJK ... Yi */
-      (ucs >= 0xac00 && ucs <= 0xd7a3) || /* Hangul Syllables */
-      (ucs >= 0xf900 && ucs <= 0xfaff) || /* CJK Compatibility Ideographs */
-      (ucs >= 0xfe30 && ucs <= 0xfe6f) || /* CJK Compatibility Forms */
-      (ucs >= 0xff00 && ucs <= 0xff5f) || /* Fullwidth Forms */
-      (ucs >= 0xffe0 && ucs <= 0xffe6) ||
-      (ucs >= 0x20000 && ucs <= 0x2ffff))
+      (0xac00..=0xd7a3).contains(&ucs) || /* Hangul Syllables */
+      (0xf900..=0xfaff).contains(&ucs) || /* CJK Compatibility Ideographs */
+      (0xfe30..=0xfe6f).contains(&ucs) || /* CJK Compatibility Forms */
+      (0xff00..=0xff5f).contains(&ucs) || /* Fullwidth Forms */
+      (0xffe0..=0xffe6).contains(&ucs) ||
+      (0x20000..=0x2ffff).contains(&ucs))
         ),
     )
 }
@@ -324,7 +394,7 @@ pub fn wcswidth(mut pwcs: WChar, mut n: usize) -> Option<usize> {
     Some(width)
 }
 
-const EMOJI_RANGES: &'static [Interval] = &[
+const EMOJI_RANGES: &[Interval] = &[
     (0x231A, 0x231B), //    ; Basic_Emoji              ; watch                                                          #  1.1  [2] (⌚..⌛)
     (0x23E9, 0x23EC), //    ; Basic_Emoji              ; fast-forward button                                            #  6.0  [4] (⏩..⏬)
     (0x23F0, 0x23F0), //          ; Basic_Emoji              ; alarm clock                                                    #  6.0  [1] (⏰)
@@ -472,6 +542,185 @@ const EMOJI_RANGES: &'static [Interval] = &[
     (0x1FA80, 0x1FA82), //  ; Basic_Emoji              ; yo-yo                                                          # 12.0  [3] (🪀..🪂)
     (0x1FA90, 0x1FA95), //  ; Basic_Emoji              ; ringed planet                                                  # 12.0  [6] (🪐..🪕)
 ];
+
+/* Characters of East Asian Width "Ambiguous" in Unicode 14.0, drawn two columns wide by terminals
+ * set up for CJK text. Combining, format and private use characters are left out, since their
+ * width doesn't depend on the locale. */
+const AMBIGUOUS_RANGES: &[Interval] = &[
+    (0x00A1, 0x00A1),
+    (0x00A4, 0x00A4),
+    (0x00A7, 0x00A8),
+    (0x00AA, 0x00AA),
+    (0x00AE, 0x00AE),
+    (0x00B0, 0x00B4),
+    (0x00B6, 0x00BA),
+    (0x00BC, 0x00BF),
+    (0x00C6, 0x00C6),
+    (0x00D0, 0x00D0),
+    (0x00D7, 0x00D8),
+    (0x00DE, 0x00E1),
+    (0x00E6, 0x00E6),
+    (0x00E8, 0x00EA),
+    (0x00EC, 0x00ED),
+    (0x00F0, 0x00F0),
+    (0x00F2, 0x00F3),
+    (0x00F7, 0x00FA),
+    (0x00FC, 0x00FC),
+    (0x00FE, 0x00FE),
+    (0x0101, 0x0101),
+    (0x0111, 0x0111),
+    (0x0113, 0x0113),
+    (0x011B, 0x011B),
+    (0x0126, 0x0127),
+    (0x012B, 0x012B),
+    (0x0131, 0x0133),
+    (0x0138, 0x0138),
+    (0x013F, 0x0142),
+    (0x0144, 0x0144),
+    (0x0148, 0x014B),
+    (0x014D, 0x014D),
+    (0x0152, 0x0153),
+    (0x0166, 0x0167),
+    (0x016B, 0x016B),
+    (0x01CE, 0x01CE),
+    (0x01D0, 0x01D0),
+    (0x01D2, 0x01D2),
+    (0x01D4, 0x01D4),
+    (0x01D6, 0x01D6),
+    (0x01D8, 0x01D8),
+    (0x01DA, 0x01DA),
+    (0x01DC, 0x01DC),
+    (0x0251, 0x0251),
+    (0x0261, 0x0261),
+    (0x02C4, 0x02C4),
+    (0x02C7, 0x02C7),
+    (0x02C9, 0x02CB),
+    (0x02CD, 0x02CD),
+    (0x02D0, 0x02D0),
+    (0x02D8, 0x02DB),
+    (0x02DD, 0x02DD),
+    (0x02DF, 0x02DF),
+    (0x0391, 0x03A1),
+    (0x03A3, 0x03A9),
+    (0x03B1, 0x03C1),
+    (0x03C3, 0x03C9),
+    (0x0401, 0x0401),
+    (0x0410, 0x044F),
+    (0x0451, 0x0451),
+    (0x2010, 0x2010),
+    (0x2013, 0x2016),
+    (0x2018, 0x2019),
+    (0x201C, 0x201D),
+    (0x2020, 0x2022),
+    (0x2024, 0x2027),
+    (0x2030, 0x2030),
+    (0x2032, 0x2033),
+    (0x2035, 0x2035),
+    (0x203B, 0x203B),
+    (0x203E, 0x203E),
+    (0x2074, 0x2074),
+    (0x207F, 0x207F),
+    (0x2081, 0x2084),
+    (0x20AC, 0x20AC),
+    (0x2103, 0x2103),
+    (0x2105, 0x2105),
+    (0x2109, 0x2109),
+    (0x2113, 0x2113),
+    (0x2116, 0x2116),
+    (0x2121, 0x2122),
+    (0x2126, 0x2126),
+    (0x212B, 0x212B),
+    (0x2153, 0x2154),
+    (0x215B, 0x215E),
+    (0x2160, 0x216B),
+    (0x2170, 0x2179),
+    (0x2189, 0x2189),
+    (0x2190, 0x2199),
+    (0x21B8, 0x21B9),
+    (0x21D2, 0x21D2),
+    (0x21D4, 0x21D4),
+    (0x21E7, 0x21E7),
+    (0x2200, 0x2200),
+    (0x2202, 0x2203),
+    (0x2207, 0x2208),
+    (0x220B, 0x220B),
+    (0x220F, 0x220F),
+    (0x2211, 0x2211),
+    (0x2215, 0x2215),
+    (0x221A, 0x221A),
+    (0x221D, 0x2220),
+    (0x2223, 0x2223),
+    (0x2225, 0x2225),
+    (0x2227, 0x222C),
+    (0x222E, 0x222E),
+    (0x2234, 0x2237),
+    (0x223C, 0x223D),
+    (0x2248, 0x2248),
+    (0x224C, 0x224C),
+    (0x2252, 0x2252),
+    (0x2260, 0x2261),
+    (0x2264, 0x2267),
+    (0x226A, 0x226B),
+    (0x226E, 0x226F),
+    (0x2282, 0x2283),
+    (0x2286, 0x2287),
+    (0x2295, 0x2295),
+    (0x2299, 0x2299),
+    (0x22A5, 0x22A5),
+    (0x22BF, 0x22BF),
+    (0x2312, 0x2312),
+    (0x2460, 0x24E9),
+    (0x24EB, 0x254B),
+    (0x2550, 0x2573),
+    (0x2580, 0x258F),
+    (0x2592, 0x2595),
+    (0x25A0, 0x25A1),
+    (0x25A3, 0x25A9),
+    (0x25B2, 0x25B3),
+    (0x25B6, 0x25B7),
+    (0x25BC, 0x25BD),
+    (0x25C0, 0x25C1),
+    (0x25C6, 0x25C8),
+    (0x25CB, 0x25CB),
+    (0x25CE, 0x25D1),
+    (0x25E2, 0x25E5),
+    (0x25EF, 0x25EF),
+    (0x2605, 0x2606),
+    (0x2609, 0x2609),
+    (0x260E, 0x260F),
+    (0x261C, 0x261C),
+    (0x261E, 0x261E),
+    (0x2640, 0x2640),
+    (0x2642, 0x2642),
+    (0x2660, 0x2661),
+    (0x2663, 0x2665),
+    (0x2667, 0x266A),
+    (0x266C, 0x266D),
+    (0x266F, 0x266F),
+    (0x269E, 0x269F),
+    (0x26BF, 0x26BF),
+    (0x26C6, 0x26CD),
+    (0x26CF, 0x26D3),
+    (0x26D5, 0x26E1),
+    (0x26E3, 0x26E3),
+    (0x26E8, 0x26E9),
+    (0x26EB, 0x26F1),
+    (0x26F4, 0x26F4),
+    (0x26F6, 0x26F9),
+    (0x26FB, 0x26FC),
+    (0x26FE, 0x26FF),
+    (0x273D, 0x273D),
+    (0x2776, 0x277F),
+    (0x2B56, 0x2B59),
+    (0x3248, 0x324F),
+    (0xFFFD, 0xFFFD),
+    (0x1F100, 0x1F10A),
+    (0x1F110, 0x1F12D),
+    (0x1F130, 0x1F169),
+    (0x1F170, 0x1F18D),
+    (0x1F18F, 0x1F190),
+    (0x1F19B, 0x1F1AC),
+];
 /*
 00A9 FE0F     ; Basic_Emoji              ; copyright                                                      #  3.2  [1] (©️)
 00AE FE0F     ; Basic_Emoji              ; registered                                                     #  3.2  [1] (®️)
@@ -680,3 +929,55 @@ const EMOJI_RANGES: &'static [Interval] = &[
 1F6F0 FE0F    ; Basic_Emoji              ; satellite                                                      #  7.0  [1] (🛰️)
 1F6F3 FE0F    ; Basic_Emoji              ; passenger ship                                                 #  7.0  [1] (🛳️)
 */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ambiguous_width() {
+        for &c in &['α', '…', '─', '°'] {
+            assert_eq!(
+                wcwidth_with(c as WChar, AmbiguousWidth::Narrow),
+                Some(1),
+                "{}",
+                c
+            );
+            assert_eq!(
+                wcwidth_with(c as WChar, AmbiguousWidth::Wide),
+                Some(2),
+                "{}",
+                c
+            );
+        }
+        /* unambiguous characters keep their width */
+        for &(c, width) in &[('a', 1), ('中', 2), ('😀', 2)] {
+            for &ambiguous in &[AmbiguousWidth::Narrow, AmbiguousWidth::Wide] {
+                assert_eq!(wcwidth_with(c as WChar, ambiguous), Some(width), "{}", c);
+            }
+        }
+        /* nor do combining characters, some of which are ambiguous */
+        assert_eq!(
+            wcwidth_with(0x0301, AmbiguousWidth::Wide),
+            wcwidth_with(0x0301, AmbiguousWidth::Narrow)
+        );
+
+        assert_eq!(AmbiguousWidth::parse("wide"), Some(AmbiguousWidth::Wide));
+        assert_eq!(
+            AmbiguousWidth::parse("narrow"),
+            Some(AmbiguousWidth::Narrow)
+        );
+        assert_eq!(AmbiguousWidth::parse("auto"), None);
+        for &(locale, width) in &[
+            ("ja_JP.UTF-8", AmbiguousWidth::Wide),
+            ("zh_CN", AmbiguousWidth::Wide),
+            ("ko_KR.utf8", AmbiguousWidth::Wide),
+            ("zh", AmbiguousWidth::Wide),
+            ("en_US.UTF-8", AmbiguousWidth::Narrow),
+            ("C", AmbiguousWidth::Narrow),
+            ("", AmbiguousWidth::Narrow),
+        ] {
+            assert_eq!(AmbiguousWidth::from_locale(locale), width, "{}", locale);
+        }
+    }
+}
@@ -0,0 +1,646 @@
+/*
+ * bb
+ *
+ * Copyright 2019 Manos Pitsidianakis
+ *
+ * This file is part of bb.
+ *
+ * bb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * bb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with bb. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+/*! Colors used by the components, grouped in a `Theme`.
+
+Built-in themes can be selected by name with `Theme::preset`. The `auto` preset picks the `light`
+one on terminals that say they have a light background in `COLORFGBG`, see `Background`.
+
+Themes can also be written to and read from files, with `Theme::to_toml` and `Theme::from_toml`.
+They're the small part of TOML that's needed: one `key = value` per line, where values are
+strings or arrays of strings and numbers, with `#` comments:
+
+```text
+meter_medium = "#5faf00"
+selected_fg = "auto"
+threshold_colors = ["green", "yellow", "red"]
+cpu_thresholds = [50.0, 80.0]
+```
+
+Colors are written by their `Color::name`. Keys that a file leaves out keep their default value.
+*/
+
+use super::components::Category;
+use super::Color;
+
+/// Names accepted by `Theme::preset`.
+pub const PRESETS: &[&str] = &["default", "auto", "light", "deuteranopia", "protanopia"];
+
+/// Whether the terminal's background is dark or light.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Background {
+    Dark,
+    Light,
+}
+
+impl Background {
+    /// Parses the value of `COLORFGBG`, which some terminals (rxvt, Konsole, iTerm2...) set to the
+    /// palette indices of their default colors: `fg;bg`, or `fg;default;bg` with rxvt's xpm
+    /// support. White and the bright colors but dark grey are light backgrounds. Returns `None` if
+    /// the background isn't a palette index, eg. `default`.
+    pub fn from_colorfgbg(value: &str) -> Option<Background> {
+        match value.rsplit(';').next()?.trim().parse::<u8>().ok()? {
+            0..=6 | 8 => Some(Background::Dark),
+            7 | 9..=15 => Some(Background::Light),
+            _ => None,
+        }
+    }
+}
+
+/// Foreground of the values of a numeric column by how high they are: the first color below the
+/// first limit, the second below the second limit and the third from there up.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Thresholds {
+    limits: [f64; 2],
+    colors: [Color; 3],
+}
+
+impl Thresholds {
+    /// Fails unless the `limits` are numbers in increasing order.
+    pub fn new(limits: [f64; 2], colors: [Color; 3]) -> Result<Thresholds, String> {
+        if !limits.iter().all(|limit| limit.is_finite()) {
+            return Err(format!(
+                "invalid thresholds `{},{}`, expected numbers",
+                limits[0], limits[1]
+            ));
+        }
+        if limits[0] >= limits[1] {
+            return Err(format!(
+                "invalid thresholds `{},{}`, expected increasing values",
+                limits[0], limits[1]
+            ));
+        }
+        Ok(Thresholds { limits, colors })
+    }
+
+    /// Parses `LOW,HIGH`, eg. `50,80`, into the `limits` of `Thresholds::new`.
+    pub fn parse_limits(spec: &str) -> Result<[f64; 2], String> {
+        let invalid = || format!("invalid thresholds `{}`, expected LOW,HIGH", spec);
+        let mut limits = spec.split(',').map(|limit| limit.trim().parse::<f64>());
+        match (limits.next(), limits.next(), limits.next()) {
+            (Some(Ok(low)), Some(Ok(high)), None) => {
+                /* checked here too, so that bad thresholds are reported as they're read */
+                Thresholds::new([low, high], [Color::Default; 3]).map(|_| [low, high])
+            }
+            _ => Err(invalid()),
+        }
+    }
+
+    pub fn limits(&self) -> [f64; 2] {
+        self.limits
+    }
+
+    pub fn color(&self, value: f64) -> Color {
+        if value < self.limits[0] {
+            self.colors[0]
+        } else if value < self.limits[1] {
+            self.colors[1]
+        } else {
+            self.colors[2]
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Theme {
+    /// Background of meter values below 15%.
+    pub meter_low: Color,
+    /// Background of meter values from 15% up to 50%.
+    pub meter_medium: Color,
+    /// Background of meter values of 50% and over.
+    pub meter_high: Color,
+    /// Background of every other process row, with `--zebra`.
+    pub row_stripe: Color,
+    /// Foreground of the rows of processes in uninterruptible sleep, with `--highlight-d-state`.
+    pub uninterruptible: Color,
+    /// Foreground of the rows of processes holding TCP sockets, with `--highlight-sockets`.
+    pub sockets: Color,
+    /// Foreground of the command lines of kernel threads, with `--color-categories`.
+    pub kernel_thread: Color,
+    /// Foreground of the command lines of daemons, with `--color-categories`.
+    pub daemon: Color,
+    /// Foreground of the command lines of processes with a terminal, with `--color-categories`.
+    pub interactive: Color,
+    /// Background of the row under the cursor.
+    pub selected_bg: Color,
+    /// Foreground of the row under the cursor, or `None` for "auto": black or white, whichever
+    /// contrasts with `selected_bg`.
+    pub selected_fg: Option<Color>,
+    /// Foregrounds of low, medium and high values of the columns colored by `Thresholds`.
+    pub threshold_colors: [Color; 3],
+    /// Colors of the cpu usage column by percentage, with `--cpu-thresholds`.
+    pub cpu_thresholds: Option<Thresholds>,
+    /// Colors of the memory column by percentage of the total memory, with `--mem-thresholds`.
+    pub mem_thresholds: Option<Thresholds>,
+    /// Colors of the load averages, with `--load-thresholds`.
+    pub load_thresholds: Option<Thresholds>,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme {
+            meter_low: Color::Default,
+            meter_medium: Color::Byte(70), // Chartreuse4
+            meter_high: Color::Red,
+            row_stripe: Color::Byte(233), // Grey7
+            uninterruptible: Color::Red,
+            sockets: Color::Byte(73),        // CadetBlue
+            kernel_thread: Color::Byte(242), // Grey42
+            daemon: Color::Byte(110),        // LightSkyBlue3
+            interactive: Color::Byte(179),   // LightGoldenrod3
+            selected_bg: Color::Byte(235),   // Grey15
+            selected_fg: None,
+            threshold_colors: [Color::Green, Color::Yellow, Color::Red],
+            cpu_thresholds: None,
+            mem_thresholds: None,
+            load_thresholds: None,
+        }
+    }
+}
+
+impl Theme {
+    /// Returns the built-in theme called `name`, if any.
+    ///
+    /// The colorblind-friendly presets replace green and red with blue and orange, and use their
+    /// `meter_high` color for `uninterruptible` too:
+    ///
+    /// | preset         | `meter_medium`               | `meter_high`             |
+    /// |----------------|------------------------------|--------------------------|
+    /// | `default`      | `Byte(70)` (Chartreuse4)     | `Red`                    |
+    /// | `deuteranopia` | `Byte(33)` (DodgerBlue1)     | `Byte(208)` (DarkOrange) |
+    /// | `protanopia`   | `Byte(39)` (DeepSkyBlue1)    | `Byte(214)` (Orange1)    |
+    pub fn preset(name: &str) -> Option<Theme> {
+        match name {
+            "default" => Some(Theme::default()),
+            "auto" => Some(Theme::auto(std::env::var("COLORFGBG").ok().as_deref())),
+            "light" => Some(Theme {
+                meter_medium: Color::Byte(28),   // Green4
+                row_stripe: Color::Byte(255),    // Grey93
+                kernel_thread: Color::Byte(245), // Grey54
+                daemon: Color::Byte(25),         // DeepSkyBlue4
+                interactive: Color::Byte(130),   // DarkOrange3
+                selected_bg: Color::Byte(252),   // Grey82
+                threshold_colors: [Color::Byte(28), Color::Byte(136), Color::Red], // Green4, DarkGoldenrod
+                ..Theme::default()
+            }),
+            "deuteranopia" => Some(Theme {
+                meter_medium: Color::Byte(33),
+                meter_high: Color::Byte(208),
+                uninterruptible: Color::Byte(208),
+                threshold_colors: [Color::Byte(33), Color::Yellow, Color::Byte(208)],
+                ..Theme::default()
+            }),
+            "protanopia" => Some(Theme {
+                meter_medium: Color::Byte(39),
+                meter_high: Color::Byte(214),
+                uninterruptible: Color::Byte(214),
+                threshold_colors: [Color::Byte(39), Color::Yellow, Color::Byte(214)],
+                ..Theme::default()
+            }),
+            _ => None,
+        }
+    }
+
+    /// The `light` theme if `colorfgbg`, the value of `COLORFGBG`, is of a light background, and
+    /// the default one otherwise.
+    pub fn auto(colorfgbg: Option<&str>) -> Theme {
+        match colorfgbg.and_then(Background::from_colorfgbg) {
+            Some(Background::Light) => Theme::preset("light").unwrap(),
+            Some(Background::Dark) | None => Theme::default(),
+        }
+    }
+
+    /// Foreground of the command lines of processes of `category`.
+    pub fn category(&self, category: Category) -> Color {
+        match category {
+            Category::Kernel => self.kernel_thread,
+            Category::Daemon => self.daemon,
+            Category::Interactive => self.interactive,
+            Category::Other => Color::Default,
+        }
+    }
+
+    /// Foreground and background of the row under the cursor.
+    pub fn selection(&self) -> (Color, Color) {
+        (
+            self.selected_fg
+                .unwrap_or_else(|| self.selected_bg.contrasting()),
+            self.selected_bg,
+        )
+    }
+
+    /// `Thresholds` at `limits` with the `threshold_colors` of the theme.
+    pub fn thresholds(&self, limits: [f64; 2]) -> Result<Thresholds, String> {
+        Thresholds::new(limits, self.threshold_colors)
+    }
+
+    /// The theme as a theme file that `Theme::from_toml` reads back. The colors of thresholds are
+    /// the `threshold_colors`.
+    pub fn to_toml(&self) -> String {
+        let mut theme = *self;
+        let mut ret = String::new();
+        for key in COLOR_KEYS {
+            let color = theme.color_mut(key).unwrap();
+            ret.push_str(&format!("{} = \"{}\"\n", key, color.name()));
+        }
+        ret.push_str(&format!(
+            "selected_fg = \"{}\"\n",
+            self.selected_fg
+                .map_or_else(|| "auto".to_string(), Color::name)
+        ));
+        ret.push_str(&format!(
+            "threshold_colors = [{}]\n",
+            self.threshold_colors
+                .iter()
+                .map(|color| format!("\"{}\"", color.name()))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+        for (key, thresholds) in &[
+            ("cpu_thresholds", self.cpu_thresholds),
+            ("mem_thresholds", self.mem_thresholds),
+            ("load_thresholds", self.load_thresholds),
+        ] {
+            if let Some(thresholds) = thresholds {
+                let [low, high] = thresholds.limits();
+                ret.push_str(&format!("{} = [{:?}, {:?}]\n", key, low, high));
+            }
+        }
+        ret
+    }
+
+    /// Parses a theme file, see the module documentation. Errors tell the line they're on.
+    pub fn from_toml(toml: &str) -> Result<Theme, String> {
+        let mut ret = Theme::default();
+        let mut thresholds: [Option<[f64; 2]>; 3] = [None; 3];
+        for (n, line) in toml.lines().enumerate() {
+            let error = |err: String| format!("line {}: {}", n + 1, err);
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, value) = line
+                .split_once('=')
+                .map(|(key, value)| (key.trim(), value.trim()))
+                .ok_or_else(|| error("expected `key = value`".to_string()))?;
+            let color = |name: &str| {
+                Color::parse(name).ok_or_else(|| error(format!("invalid color `{}`", name)))
+            };
+            match key {
+                "selected_fg" => {
+                    let name = toml_string(value).ok_or_else(|| error(expected_string(key)))?;
+                    ret.selected_fg = if name == "auto" {
+                        None
+                    } else {
+                        Some(color(name)?)
+                    };
+                }
+                "threshold_colors" => {
+                    let colors = toml_array(value)
+                        .and_then(|colors| {
+                            colors
+                                .into_iter()
+                                .map(toml_string)
+                                .collect::<Option<Vec<_>>>()
+                        })
+                        .ok_or_else(|| error(format!("`{}` expects an array of strings", key)))?;
+                    match colors[..] {
+                        [low, medium, high] => {
+                            ret.threshold_colors = [color(low)?, color(medium)?, color(high)?]
+                        }
+                        _ => return Err(error(format!("`{}` expects 3 colors", key))),
+                    }
+                }
+                "cpu_thresholds" | "mem_thresholds" | "load_thresholds" => {
+                    let limits = toml_array(value)
+                        .and_then(|limits| {
+                            limits
+                                .into_iter()
+                                .map(|limit| limit.parse::<f64>().ok())
+                                .collect::<Option<Vec<_>>>()
+                        })
+                        .filter(|limits| limits.len() == 2)
+                        .ok_or_else(|| error(format!("`{}` expects 2 numbers", key)))?;
+                    let limits = [limits[0], limits[1]];
+                    Thresholds::new(limits, [Color::Default; 3]).map_err(error)?;
+                    let i = ["cpu_thresholds", "mem_thresholds", "load_thresholds"]
+                        .iter()
+                        .position(|name| *name == key)
+                        .unwrap();
+                    thresholds[i] = Some(limits);
+                }
+                _ => {
+                    if ret.color_mut(key).is_none() {
+                        return Err(error(format!("unknown key `{}`", key)));
+                    }
+                    let name = toml_string(value).ok_or_else(|| error(expected_string(key)))?;
+                    *ret.color_mut(key).unwrap() = color(name)?;
+                }
+            }
+        }
+        /* like with --cpu-thresholds and the others, whichever line sets the colors */
+        let [cpu, mem, load] = thresholds;
+        let theme = ret;
+        let colored = |limits: Option<[f64; 2]>| limits.map(|limits| theme.thresholds(limits));
+        ret.cpu_thresholds = colored(cpu).transpose()?;
+        ret.mem_thresholds = colored(mem).transpose()?;
+        ret.load_thresholds = colored(load).transpose()?;
+        Ok(ret)
+    }
+
+    fn color_mut(&mut self, key: &str) -> Option<&mut Color> {
+        Some(match key {
+            "meter_low" => &mut self.meter_low,
+            "meter_medium" => &mut self.meter_medium,
+            "meter_high" => &mut self.meter_high,
+            "row_stripe" => &mut self.row_stripe,
+            "uninterruptible" => &mut self.uninterruptible,
+            "sockets" => &mut self.sockets,
+            "kernel_thread" => &mut self.kernel_thread,
+            "daemon" => &mut self.daemon,
+            "interactive" => &mut self.interactive,
+            "selected_bg" => &mut self.selected_bg,
+            _ => return None,
+        })
+    }
+
+    /// Background color of a meter showing `percent`, a value in [0, 1].
+    pub fn meter(&self, percent: f64) -> Color {
+        if percent < 0.15 {
+            self.meter_low
+        } else if percent < 0.50 {
+            self.meter_medium
+        } else {
+            self.meter_high
+        }
+    }
+}
+
+/* The keys of the theme files for the color fields of `Theme`, in the order they're written */
+const COLOR_KEYS: [&str; 10] = [
+    "meter_low",
+    "meter_medium",
+    "meter_high",
+    "row_stripe",
+    "uninterruptible",
+    "sockets",
+    "kernel_thread",
+    "daemon",
+    "interactive",
+    "selected_bg",
+];
+
+fn expected_string(key: &str) -> String {
+    format!("`{}` expects a string, eg. `{} = \"red\"`", key, key)
+}
+
+/* The rest of a line after a value, which can only be a comment */
+fn toml_rest(rest: &str) -> Option<()> {
+    let rest = rest.trim();
+    if rest.is_empty() || rest.starts_with('#') {
+        Some(())
+    } else {
+        None
+    }
+}
+
+/* A string value, without escapes since theme files don't need them */
+fn toml_string(value: &str) -> Option<&str> {
+    let (string, rest) = value.strip_prefix('"')?.split_once('"')?;
+    toml_rest(rest).map(|()| string)
+}
+
+/* The elements of an array value, strings still quoted and numbers alike */
+fn toml_array(value: &str) -> Option<Vec<&str>> {
+    let (elements, rest) = value.strip_prefix('[')?.split_once(']')?;
+    toml_rest(rest)?;
+    Some(
+        elements
+            .split(',')
+            .map(str::trim)
+            .filter(|element| !element.is_empty())
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_presets() {
+        for name in PRESETS {
+            assert!(Theme::preset(name).is_some(), "{}", name);
+        }
+        assert_eq!(Theme::preset("nonexistent"), None);
+
+        let theme = Theme::preset("default").unwrap();
+        assert_eq!(
+            (theme.meter(0.0), theme.meter(0.2), theme.meter(0.9)),
+            (Color::Default, Color::Byte(70), Color::Red)
+        );
+        let theme = Theme::preset("deuteranopia").unwrap();
+        assert_eq!(
+            (theme.meter(0.1), theme.meter(0.15), theme.meter(0.5)),
+            (Color::Default, Color::Byte(33), Color::Byte(208))
+        );
+        let theme = Theme::preset("protanopia").unwrap();
+        assert_eq!(
+            (theme.meter(0.14), theme.meter(0.49), theme.meter(1.0)),
+            (Color::Default, Color::Byte(39), Color::Byte(214))
+        );
+    }
+
+    #[test]
+    fn test_colorfgbg() {
+        use Background::*;
+        for (value, background) in &[
+            ("15;0", Some(Dark)),
+            ("0;15", Some(Light)),
+            ("0;7", Some(Light)),
+            ("7;8", Some(Dark)),
+            ("12;default;0", Some(Dark)),
+            ("0;default;15", Some(Light)),
+            ("11;4", Some(Dark)),
+            ("default;default", None),
+            ("0;16", None),
+            ("", None),
+        ] {
+            assert_eq!(Background::from_colorfgbg(value), *background, "{}", value);
+        }
+
+        assert_eq!(Theme::auto(Some("0;15")), Theme::preset("light").unwrap());
+        assert_eq!(Theme::auto(Some("15;0")), Theme::default());
+        assert_eq!(Theme::auto(Some("default;default")), Theme::default());
+        assert_eq!(Theme::auto(None), Theme::default());
+        /* the text under the cursor is dark on the light theme's highlight */
+        assert_eq!(Theme::preset("light").unwrap().selection().0, Color::Black);
+    }
+
+    #[test]
+    fn test_selection_colors() {
+        let theme = Theme::default();
+        assert_eq!(theme.selection(), (Color::White, Color::Byte(235)));
+        /* "auto" picks dark text for a light highlight and light text for a dark one */
+        let light = Theme {
+            selected_bg: Color::Byte(252),
+            ..theme
+        };
+        assert_eq!(light.selection(), (Color::Black, Color::Byte(252)));
+        let dark = Theme {
+            selected_bg: Color::Blue,
+            ..theme
+        };
+        assert_eq!(dark.selection(), (Color::White, Color::Blue));
+        /* unless the theme sets the foreground */
+        let fixed = Theme {
+            selected_fg: Some(Color::Yellow),
+            ..light
+        };
+        assert_eq!(fixed.selection(), (Color::Yellow, Color::Byte(252)));
+    }
+
+    #[test]
+    fn test_thresholds() {
+        let theme = Theme::default();
+        let thresholds = theme.thresholds([50.0, 80.0]).unwrap();
+        for (value, color) in &[
+            (0.0, Color::Green),
+            (49.9, Color::Green),
+            (50.0, Color::Yellow),
+            (79.99, Color::Yellow),
+            (80.0, Color::Red),
+            (250.0, Color::Red),
+        ] {
+            assert_eq!(thresholds.color(*value), *color, "{}", value);
+        }
+        /* the bands follow the theme */
+        let thresholds = Theme::preset("deuteranopia")
+            .unwrap()
+            .thresholds([1.0, 4.0])
+            .unwrap();
+        assert_eq!(
+            (
+                thresholds.color(0.5),
+                thresholds.color(2.0),
+                thresholds.color(4.0)
+            ),
+            (Color::Byte(33), Color::Yellow, Color::Byte(208))
+        );
+
+        assert_eq!(Thresholds::parse_limits("50,80"), Ok([50.0, 80.0]));
+        assert_eq!(Thresholds::parse_limits(" 0.5, 2 "), Ok([0.5, 2.0]));
+        assert_eq!(
+            Thresholds::parse_limits("80,50"),
+            Err("invalid thresholds `80,50`, expected increasing values".to_string())
+        );
+        assert!(Thresholds::parse_limits("50,50").is_err());
+        assert!(Thresholds::parse_limits("50").is_err());
+        assert!(Thresholds::parse_limits("50,80,90").is_err());
+        assert!(Thresholds::parse_limits("low,high").is_err());
+        assert!(Thresholds::parse_limits("50,inf").is_err());
+        assert!(theme.thresholds([f64::NAN, 1.0]).is_err());
+    }
+
+    #[test]
+    fn test_toml() {
+        for name in PRESETS {
+            let theme = Theme::preset(name).unwrap();
+            assert_eq!(Theme::from_toml(&theme.to_toml()), Ok(theme), "{}", name);
+        }
+
+        let mut theme = Theme::preset("deuteranopia").unwrap();
+        theme.meter_low = Color::Byte(3);
+        theme.row_stripe = Color::Cyan;
+        theme.selected_fg = Some(Color::Byte(250));
+        theme.threshold_colors = [Color::Byte(12), Color::Default, Color::Magenta];
+        theme.cpu_thresholds = Some(theme.thresholds([12.5, 80.0]).unwrap());
+        theme.load_thresholds = Some(theme.thresholds([0.7, 1.0]).unwrap());
+        let toml = theme.to_toml();
+        assert!(toml.starts_with("meter_low = \"color3\"\nmeter_medium = \"#0087ff\"\n"));
+        assert!(toml.contains("\nselected_fg = \"#bcbcbc\"\n"));
+        assert!(toml.ends_with(
+            "threshold_colors = [\"color12\", \"default\", \"magenta\"]
+cpu_thresholds = [12.5, 80.0]
+load_thresholds = [0.7, 1.0]
+"
+        ));
+        assert_eq!(Theme::from_toml(&toml), Ok(theme));
+
+        /* keys left out are those of the default theme, in any order */
+        let theme = Theme::from_toml(
+            "# shared theme
+  mem_thresholds = [ 50, 80, ]  # percent
+threshold_colors = [\"blue\", \"cyan\", \"#ff0000\"]
+
+meter_high = \"#ff8700\"
+",
+        )
+        .unwrap();
+        assert_eq!(theme.meter_high, Color::Byte(208));
+        assert_eq!(theme.meter_medium, Theme::default().meter_medium);
+        assert_eq!(
+            theme.mem_thresholds,
+            Some(
+                Thresholds::new([50.0, 80.0], [Color::Blue, Color::Cyan, Color::Byte(196)])
+                    .unwrap()
+            )
+        );
+
+        for (toml, err) in &[
+            ("meter_low", "line 1: expected `key = value`"),
+            ("\nmeter_lo = \"red\"", "line 2: unknown key `meter_lo`"),
+            (
+                "daemon = red",
+                "line 1: `daemon` expects a string, eg. `daemon = \"red\"`",
+            ),
+            ("daemon = \"reddish\"", "line 1: invalid color `reddish`"),
+            (
+                "daemon = \"red\" blue",
+                "line 1: `daemon` expects a string, eg. `daemon = \"red\"`",
+            ),
+            ("selected_fg = \"#12345\"", "line 1: invalid color `#12345`"),
+            (
+                "threshold_colors = [\"red\"]",
+                "line 1: `threshold_colors` expects 3 colors",
+            ),
+            (
+                "cpu_thresholds = [80, 50]",
+                "line 1: invalid thresholds `80,50`, expected increasing values",
+            ),
+            (
+                "load_thresholds = [1]",
+                "line 1: `load_thresholds` expects 2 numbers",
+            ),
+        ] {
+            assert_eq!(Theme::from_toml(toml), Err(err.to_string()), "{}", toml);
+        }
+    }
+
+    #[test]
+    fn test_category_colors() {
+        let theme = Theme::default();
+        assert_eq!(theme.category(Category::Kernel), Color::Byte(242));
+        assert_eq!(theme.category(Category::Daemon), Color::Byte(110));
+        assert_eq!(theme.category(Category::Interactive), Color::Byte(179));
+        assert_eq!(theme.category(Category::Other), Color::Default);
+    }
+}
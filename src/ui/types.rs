@@ -19,16 +19,25 @@
  * along with bb. If not, see <http://www.gnu.org/licenses/>.
  */
 
-use crate::ui::Key;
+use crate::ui::{Key, MouseEvent};
 
 /// `ThreadEvent` encapsulates all of the possible values we need to transfer between our threads
 /// to the main process.
 #[derive(Debug)]
 pub enum ThreadEvent {
     Input(Key),
+    Mouse(MouseEvent),
+    /// The terminal gained (`true`) or lost focus, with focus tracking on.
+    Focus(bool),
 }
 #[derive(Debug)]
 pub enum UIEvent {
     Input(Key),
+    Mouse(MouseEvent),
     Resize,
+    /// A message for the status line from outside of the components, eg. a prompt.
+    StatusMessage(String),
+    /// Put in place of the key press that asked for it by the component that handled it: run the
+    /// program and arguments in the terminal, and take it back once the program exits.
+    Exec(Vec<String>),
 }